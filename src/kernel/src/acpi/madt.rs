@@ -0,0 +1,251 @@
+use acpi::{
+    InterruptModel,
+    platform::{
+        ProcessorState,
+        interrupt::{LocalInterruptLine, NmiProcessor},
+    },
+};
+use alloc::vec::Vec;
+
+pub use acpi::platform::interrupt::{Polarity, TriggerMode};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Acpi(#[from] super::Error),
+
+    #[error("failed to parse the MADT: {0:?}")]
+    Parse(acpi::AcpiError),
+
+    #[error("platform does not use the APIC interrupt model")]
+    NoApic,
+}
+
+/// A processor described by the MADT.
+#[derive(Debug, Clone, Copy)]
+pub struct Processor {
+    pub processor_uid: u32,
+    pub local_apic_id: u32,
+    pub is_bsp: bool,
+    /// Whether the processor can be started (i.e. it is not disabled by firmware).
+    pub is_usable: bool,
+}
+
+/// An I/O APIC described by the MADT.
+#[derive(Debug, Clone, Copy)]
+pub struct IoApic {
+    pub id: u8,
+    pub address: u32,
+    /// First global system interrupt handled by this I/O APIC.
+    pub gsi_base: u32,
+}
+
+/// Remaps an ISA IRQ to a different global system interrupt.
+#[derive(Debug, Clone, Copy)]
+pub struct SourceOverride {
+    pub isa_source: u8,
+    pub gsi: u32,
+    pub polarity: Polarity,
+    pub trigger_mode: TriggerMode,
+}
+
+/// A global system interrupt which should be configured as an NMI.
+#[derive(Debug, Clone, Copy)]
+pub struct NmiSource {
+    pub gsi: u32,
+    pub polarity: Polarity,
+    pub trigger_mode: TriggerMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalLine {
+    Lint0,
+    Lint1,
+}
+
+/// A local APIC line which should be configured as an NMI.
+#[derive(Debug, Clone, Copy)]
+pub struct LocalNmi {
+    /// Processor UID the configuration applies to, or `None` for all processors.
+    pub processor_uid: Option<u32>,
+    pub line: LocalLine,
+}
+
+crate::singleton! {
+    /// Processor and interrupt controller topology, as described by the MADT.
+    pub Topology {
+        local_apic_address: u64,
+        has_legacy_pics: bool,
+        processors: Vec<Processor>,
+        io_apics: Vec<IoApic>,
+        source_overrides: Vec<SourceOverride>,
+        nmi_sources: Vec<NmiSource>,
+        local_nmis: Vec<LocalNmi>,
+    }
+
+    fn init() {
+        match Self::parse() {
+            Ok(topology) => {
+                debug!(
+                    "MADT topology: {{ processors: {}, I/O APICs: {}, source overrides: {}, NMI sources: {}, local NMIs: {} }}",
+                    topology.processors.len(),
+                    topology.io_apics.len(),
+                    topology.source_overrides.len(),
+                    topology.nmi_sources.len(),
+                    topology.local_nmis.len()
+                );
+
+                topology
+            }
+
+            Err(error) => {
+                warn!("Failed to parse MADT, topology will be empty: {error}");
+
+                Self {
+                    local_apic_address: 0,
+                    has_legacy_pics: false,
+                    processors: Vec::new(),
+                    io_apics: Vec::new(),
+                    source_overrides: Vec::new(),
+                    nmi_sources: Vec::new(),
+                    local_nmis: Vec::new(),
+                }
+            }
+        }
+    }
+}
+
+impl Topology {
+    fn parse() -> Result<Self, Error> {
+        let platform_info = super::Tables::root()?
+            .platform_info()
+            .map_err(Error::Parse)?;
+
+        let InterruptModel::Apic(apic) = platform_info.interrupt_model else {
+            return Err(Error::NoApic);
+        };
+
+        let processors = platform_info
+            .processor_info
+            .map(|processor_info| {
+                core::iter::once(processor_info.boot_processor)
+                    .chain(processor_info.application_processors.iter().copied())
+                    .map(|processor| Processor {
+                        processor_uid: processor.processor_uid,
+                        local_apic_id: processor.local_apic_id,
+                        is_bsp: !processor.is_ap,
+                        is_usable: processor.state != ProcessorState::Disabled,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            local_apic_address: apic.local_apic_address,
+            has_legacy_pics: apic.also_has_legacy_pics,
+            processors,
+            io_apics: apic
+                .io_apics
+                .iter()
+                .map(|io_apic| IoApic {
+                    id: io_apic.id,
+                    address: io_apic.address,
+                    gsi_base: io_apic.global_system_interrupt_base,
+                })
+                .collect(),
+            source_overrides: apic
+                .interrupt_source_overrides
+                .iter()
+                .map(|source_override| SourceOverride {
+                    isa_source: source_override.isa_source,
+                    gsi: source_override.global_system_interrupt,
+                    polarity: source_override.polarity,
+                    trigger_mode: source_override.trigger_mode,
+                })
+                .collect(),
+            nmi_sources: apic
+                .nmi_sources
+                .iter()
+                .map(|nmi_source| NmiSource {
+                    gsi: nmi_source.global_system_interrupt,
+                    polarity: nmi_source.polarity,
+                    trigger_mode: nmi_source.trigger_mode,
+                })
+                .collect(),
+            local_nmis: apic
+                .local_apic_nmi_lines
+                .iter()
+                .map(|nmi_line| LocalNmi {
+                    processor_uid: match nmi_line.processor {
+                        NmiProcessor::All => None,
+                        NmiProcessor::ProcessorUid(processor_uid) => Some(processor_uid),
+                    },
+                    line: match nmi_line.line {
+                        LocalInterruptLine::Lint0 => LocalLine::Lint0,
+                        LocalInterruptLine::Lint1 => LocalLine::Lint1,
+                    },
+                })
+                .collect(),
+        })
+    }
+
+    /// Physical address of the local APIC registers (unused in x2APIC mode).
+    pub fn local_apic_address() -> u64 {
+        Self::get_static().local_apic_address
+    }
+
+    /// Whether the platform also has dual legacy 8259 PICs, which must be masked.
+    pub fn has_legacy_pics() -> bool {
+        Self::get_static().has_legacy_pics
+    }
+
+    pub fn processors() -> &'static [Processor] {
+        &Self::get_static().processors
+    }
+
+    pub fn io_apics() -> &'static [IoApic] {
+        &Self::get_static().io_apics
+    }
+
+    pub fn source_overrides() -> &'static [SourceOverride] {
+        &Self::get_static().source_overrides
+    }
+
+    pub fn nmi_sources() -> &'static [NmiSource] {
+        &Self::get_static().nmi_sources
+    }
+
+    pub fn local_nmis() -> &'static [LocalNmi] {
+        &Self::get_static().local_nmis
+    }
+
+    /// Resolves an ISA IRQ to its global system interrupt, applying any source override.
+    pub fn isa_irq_to_gsi(isa_irq: u8) -> (u32, Polarity, TriggerMode) {
+        Self::source_overrides()
+            .iter()
+            .find(|source_override| source_override.isa_source == isa_irq)
+            .map_or(
+                (
+                    u32::from(isa_irq),
+                    Polarity::SameAsBus,
+                    TriggerMode::SameAsBus,
+                ),
+                |source_override| {
+                    (
+                        source_override.gsi,
+                        source_override.polarity,
+                        source_override.trigger_mode,
+                    )
+                },
+            )
+    }
+
+    /// Finds the I/O APIC responsible for `gsi`.
+    pub fn io_apic_for_gsi(gsi: u32) -> Option<&'static IoApic> {
+        // I/O APIC input ranges are contiguous, so the closest base at or below the GSI owns it.
+        Self::io_apics()
+            .iter()
+            .filter(|io_apic| io_apic.gsi_base <= gsi)
+            .max_by_key(|io_apic| io_apic.gsi_base)
+    }
+}
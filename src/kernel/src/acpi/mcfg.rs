@@ -0,0 +1,44 @@
+use alloc::vec::Vec;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Acpi(#[from] super::Error),
+}
+
+/// A PCIe enhanced configuration access mechanism (ECAM) region, as described by the MCFG.
+#[derive(Debug, Clone, Copy)]
+pub struct EcamRegion {
+    /// Physical address of the configuration space for `bus_start`.
+    pub base_address: u64,
+    pub segment_group: u16,
+    pub bus_start: u8,
+    pub bus_end: u8,
+}
+
+impl EcamRegion {
+    /// Whether this region provides the configuration space for `bus` in `segment_group`.
+    pub fn contains(&self, segment_group: u16, bus: u8) -> bool {
+        self.segment_group == segment_group && (self.bus_start..=self.bus_end).contains(&bus)
+    }
+}
+
+/// Reads every ECAM region described by the MCFG.
+pub fn ecam_regions() -> Result<Vec<EcamRegion>, Error> {
+    let mcfg = super::Tables::find_table::<acpi::mcfg::Mcfg>()?;
+
+    let regions = mcfg
+        .entries()
+        .iter()
+        .map(|entry| EcamRegion {
+            base_address: entry.base_address,
+            segment_group: entry.pci_segment_group,
+            bus_start: entry.bus_number_start,
+            bus_end: entry.bus_number_end,
+        })
+        .collect::<Vec<_>>();
+
+    trace!("Found ECAM regions: {regions:#X?}");
+
+    Ok(regions)
+}
@@ -1,7 +1,15 @@
 use crate::mem::HigherHalfDirectMap;
-use acpi::{AcpiError, AcpiTables};
+use acpi::{AcpiError, AcpiTables, sdt::Signature};
 use core::ptr::NonNull;
 
+pub mod madt;
+pub mod mcfg;
+pub mod slit;
+pub mod srat;
+
+mod tables;
+pub use tables::*;
+
 #[derive(Clone, Copy)]
 pub struct Handler;
 
@@ -39,6 +47,15 @@ pub enum Error {
 
     #[error("failed to validate ACPI root table")]
     ValidationFailed(AcpiError),
+
+    #[error("ACPI tables are unavailable")]
+    Unavailable,
+
+    #[error("platform does not provide a {0} table")]
+    TableNotFound(Signature),
+
+    #[error("{0} table failed checksum validation")]
+    InvalidChecksum(Signature),
 }
 
 impl From<AcpiError> for Error {
@@ -47,23 +64,25 @@ impl From<AcpiError> for Error {
     }
 }
 
-pub fn get_root_table(
+fn rsdp_physical_address(rsdp_response: &limine::response::RsdpResponse) -> usize {
+    let rsdp_address = rsdp_response.address();
+
+    // Limine protocol specification states that base revisions < 3 provides
+    // the RSDP address as a virtual address rather than physical.
+    if rsdp_response.revision() < 3 {
+        HigherHalfDirectMap::negative_offset(rsdp_address).get()
+    } else {
+        rsdp_address
+    }
+}
+
+fn get_root_table(
     rsdp_request: &limine::request::RsdpRequest,
 ) -> Result<AcpiTables<Handler>, Error> {
     let rsdp_response = rsdp_request.get_response().ok_or(Error::NoRsdpAddress)?;
+    debug!("Found RSDP: {:#X?}", rsdp_response.address());
 
-    let rsdp_address = rsdp_response.address();
-    debug!("Found RSDP: {rsdp_address:#X?}");
-
-    let physical_address = {
-        // Limine protocol specification states that base revisions < 3 provides
-        // the RSDP address as a virtual address rather than physical.
-        if rsdp_response.revision() < 3 {
-            HigherHalfDirectMap::negative_offset(rsdp_address).get()
-        } else {
-            rsdp_address
-        }
-    };
+    let physical_address = rsdp_physical_address(rsdp_response);
 
     // Safety: Bootloader guarantees provided RSDP address to be valid.
     let root_table = unsafe { AcpiTables::from_rsdp(Handler, physical_address) }?;
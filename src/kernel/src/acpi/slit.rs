@@ -0,0 +1,90 @@
+use acpi::{
+    AcpiTable,
+    sdt::{SdtHeader, Signature},
+};
+use alloc::vec::Vec;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Acpi(#[from] super::Error),
+
+    #[error("SLIT is smaller than its locality count requires")]
+    Truncated,
+}
+
+/// System Locality Information Table.
+#[repr(C, packed)]
+pub struct Slit {
+    header: SdtHeader,
+    locality_count: u64,
+}
+
+// Safety: `Slit` is `repr(C, packed)`, and begins with the SDT header.
+unsafe impl AcpiTable for Slit {
+    const SIGNATURE: Signature = Signature::SLIT;
+
+    fn header(&self) -> &SdtHeader {
+        &self.header
+    }
+}
+
+/// Relative distances between each pair of proximity domains, as described by the SLIT.
+#[derive(Debug, Clone)]
+pub struct Distances {
+    locality_count: usize,
+    matrix: Vec<u8>,
+}
+
+impl Distances {
+    /// Distance of a domain to itself.
+    pub const LOCAL: u8 = 10;
+    /// Distance of a domain which is unreachable.
+    pub const UNREACHABLE: u8 = 0xFF;
+
+    #[inline]
+    pub const fn locality_count(&self) -> usize {
+        self.locality_count
+    }
+
+    /// Relative distance from domain `from` to domain `to`, or `None` if either domain is out of range.
+    pub fn distance(&self, from: u32, to: u32) -> Option<u8> {
+        let from = usize::try_from(from)
+            .ok()
+            .filter(|from| *from < self.locality_count)?;
+        let to = usize::try_from(to)
+            .ok()
+            .filter(|to| *to < self.locality_count)?;
+
+        self.matrix.get((from * self.locality_count) + to).copied()
+    }
+}
+
+/// Reads the locality distance matrix from the SLIT.
+pub fn distances() -> Result<Distances, Error> {
+    let slit = super::Tables::find_table::<Slit>()?;
+
+    let table_len = usize::try_from(slit.header().length).unwrap();
+    let locality_count = usize::try_from(slit.locality_count).map_err(|_| Error::Truncated)?;
+    let matrix_len = locality_count
+        .checked_mul(locality_count)
+        .ok_or(Error::Truncated)?;
+    if size_of::<Slit>() + matrix_len > table_len {
+        return Err(Error::Truncated);
+    }
+
+    // Safety: The mapping covers the entire table, and the matrix is checked to lie within it.
+    let matrix = unsafe {
+        core::slice::from_raw_parts(
+            core::ptr::from_ref::<Slit>(&*slit)
+                .cast::<u8>()
+                .add(size_of::<Slit>()),
+            matrix_len,
+        )
+    };
+
+    Ok(Distances {
+        locality_count,
+        matrix: Vec::from(matrix),
+    })
+}
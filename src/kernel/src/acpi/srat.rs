@@ -0,0 +1,125 @@
+use acpi::{
+    AcpiTable,
+    sdt::{SdtHeader, Signature},
+};
+use alloc::vec::Vec;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Acpi(#[from] super::Error),
+}
+
+/// System Resource Affinity Table.
+#[repr(C, packed)]
+pub struct Srat {
+    header: SdtHeader,
+    _reserved: [u8; 12],
+}
+
+// Safety: `Srat` is `repr(C, packed)`, and begins with the SDT header.
+unsafe impl AcpiTable for Srat {
+    const SIGNATURE: Signature = Signature::SRAT;
+
+    fn header(&self) -> &SdtHeader {
+        &self.header
+    }
+}
+
+const PROCESSOR_AFFINITY: u8 = 0;
+const MEMORY_AFFINITY: u8 = 1;
+const X2APIC_AFFINITY: u8 = 2;
+
+/// Bit set in an affinity structure's flags if the structure should be used.
+const ENABLED: u32 = 1 << 0;
+
+/// Associates a processor with a proximity domain.
+#[derive(Debug, Clone, Copy)]
+pub struct ProcessorAffinity {
+    pub apic_id: u32,
+    pub proximity_domain: u32,
+}
+
+/// Associates a physical memory range with a proximity domain.
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryAffinity {
+    pub base_address: u64,
+    pub length: u64,
+    pub proximity_domain: u32,
+    pub hot_pluggable: bool,
+    pub non_volatile: bool,
+}
+
+/// Affinities described by the SRAT.
+#[derive(Debug, Default)]
+pub struct Affinities {
+    pub processors: Vec<ProcessorAffinity>,
+    pub memory: Vec<MemoryAffinity>,
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..(offset + 4)].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..(offset + 8)].try_into().unwrap())
+}
+
+/// Reads every enabled affinity structure from the SRAT.
+pub fn affinities() -> Result<Affinities, Error> {
+    let srat = super::Tables::find_table::<Srat>()?;
+
+    let table_len = usize::try_from(srat.header().length).unwrap();
+    // Safety: The mapping covers the entire table, as described by its header.
+    let table = unsafe {
+        core::slice::from_raw_parts(core::ptr::from_ref::<Srat>(&*srat).cast::<u8>(), table_len)
+    };
+
+    let mut affinities = Affinities::default();
+    let mut entries = &table[size_of::<Srat>().min(table_len)..];
+    while let [entry_type, entry_len, ..] = *entries {
+        let entry_len = usize::from(entry_len);
+        let Some(entry) = entries.get(..entry_len).filter(|_| entry_len >= 2) else {
+            warn!("SRAT contains a malformed entry; ignoring the remainder.");
+            break;
+        };
+
+        match (entry_type, entry_len) {
+            (PROCESSOR_AFFINITY, 16) if (read_u32(entry, 4) & ENABLED) > 0 => {
+                let domain_high = u32::from_le_bytes([0, entry[9], entry[10], entry[11]]);
+
+                affinities.processors.push(ProcessorAffinity {
+                    apic_id: u32::from(entry[3]),
+                    proximity_domain: domain_high | u32::from(entry[2]),
+                });
+            }
+
+            (MEMORY_AFFINITY, 40) if (read_u32(entry, 28) & ENABLED) > 0 => {
+                let flags = read_u32(entry, 28);
+
+                affinities.memory.push(MemoryAffinity {
+                    base_address: read_u64(entry, 8),
+                    length: read_u64(entry, 16),
+                    proximity_domain: read_u32(entry, 2),
+                    hot_pluggable: (flags & (1 << 1)) > 0,
+                    non_volatile: (flags & (1 << 2)) > 0,
+                });
+            }
+
+            (X2APIC_AFFINITY, 24) if (read_u32(entry, 12) & ENABLED) > 0 => {
+                affinities.processors.push(ProcessorAffinity {
+                    apic_id: read_u32(entry, 8),
+                    proximity_domain: read_u32(entry, 4),
+                });
+            }
+
+            _ => {}
+        }
+
+        entries = &entries[entry_len..];
+    }
+
+    trace!("Found SRAT affinities: {affinities:#X?}");
+
+    Ok(affinities)
+}
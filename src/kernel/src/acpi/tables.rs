@@ -0,0 +1,183 @@
+use super::{Error, Handler};
+use crate::mem::HigherHalfDirectMap;
+use acpi::{AcpiTable, AcpiTables, PhysicalMapping};
+use alloc::vec::Vec;
+
+/// Size of the common system description table header.
+const SDT_HEADER_LEN: usize = 36;
+
+/// A system description table discovered through the root table.
+#[derive(Debug, Clone, Copy)]
+pub struct TableEntry {
+    pub signature: [u8; 4],
+    pub physical_address: usize,
+    pub length: u32,
+    /// Whether every byte of the table sums to zero.
+    pub checksum_valid: bool,
+}
+
+impl TableEntry {
+    /// The table's signature as a string, or `"????"` if it isn't ASCII.
+    pub fn signature_str(&self) -> &str {
+        core::str::from_utf8(&self.signature).unwrap_or("????")
+    }
+}
+
+crate::singleton! {
+    /// Registry of every ACPI table reachable from the RSDP.
+    pub Tables {
+        root: Option<AcpiTables<Handler>>,
+        /// Every table listed by the XSDT (or RSDT), in listing order.
+        entries: Vec<TableEntry>,
+    }
+
+    fn init(rsdp_request: &limine::request::RsdpRequest) {
+        let root = super::get_root_table(rsdp_request)
+            .inspect_err(|error| warn!("ACPI tables will be unavailable: {error}"))
+            .ok();
+
+        let entries = root
+            .as_ref()
+            .map(|_| {
+                rsdp_request
+                    .get_response()
+                    .map(|rsdp_response| discover(super::rsdp_physical_address(rsdp_response)))
+                    .unwrap_or_default()
+            })
+            .unwrap_or_default();
+
+        for entry in &entries {
+            if entry.checksum_valid {
+                info!(
+                    "ACPI table {} @ {:#X} (length:{})",
+                    entry.signature_str(),
+                    entry.physical_address,
+                    entry.length
+                );
+            } else {
+                warn!(
+                    "ACPI table {} @ {:#X} (length:{}) failed checksum validation; it will be ignored.",
+                    entry.signature_str(),
+                    entry.physical_address,
+                    entry.length
+                );
+            }
+        }
+
+        Self { root, entries }
+    }
+}
+
+impl Tables {
+    /// Every table listed by the root table, including those which failed validation.
+    pub fn entries() -> &'static [TableEntry] {
+        &Self::get_static().entries
+    }
+
+    /// The parsed root table.
+    pub fn root() -> Result<&'static AcpiTables<Handler>, Error> {
+        Self::get_static().root.as_ref().ok_or(Error::Unavailable)
+    }
+
+    /// Finds and maps the table with signature `T::SIGNATURE`, provided its checksum is valid.
+    pub fn find_table<T: AcpiTable>() -> Result<PhysicalMapping<Handler, T>, Error> {
+        let signature = T::SIGNATURE;
+        let entry = Self::entries()
+            .iter()
+            .find(|entry| entry.signature == *signature.as_str().as_bytes())
+            .ok_or(Error::TableNotFound(signature))?;
+
+        if !entry.checksum_valid {
+            return Err(Error::InvalidChecksum(signature));
+        }
+
+        Ok(Self::root()?.find_table::<T>()?)
+    }
+}
+
+fn read_bytes(physical_address: usize, len: usize) -> &'static [u8] {
+    // Safety: The higher-half direct map covers all physical memory, and ACPI tables are never reclaimed.
+    unsafe {
+        core::slice::from_raw_parts(
+            core::ptr::with_exposed_provenance(HigherHalfDirectMap::offset(physical_address)),
+            len,
+        )
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..(offset + 4)].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..(offset + 8)].try_into().unwrap())
+}
+
+fn read_entry(physical_address: usize) -> TableEntry {
+    let header = read_bytes(physical_address, SDT_HEADER_LEN);
+    let length = read_u32(header, 4);
+    let table = read_bytes(physical_address, usize::try_from(length).unwrap());
+
+    TableEntry {
+        signature: header[..4].try_into().unwrap(),
+        physical_address,
+        length,
+        checksum_valid: usize::try_from(length).unwrap() >= SDT_HEADER_LEN
+            && table.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) == 0,
+    }
+}
+
+/// Walks the XSDT (or the RSDT, on ACPI 1.0 platforms) and records every table it lists.
+fn discover(rsdp_address: usize) -> Vec<TableEntry> {
+    let rsdp = read_bytes(rsdp_address, 36);
+    let revision = rsdp[15];
+    let (root_address, pointer_len) = if revision >= 2 {
+        (usize::try_from(read_u64(rsdp, 24)).unwrap(), 8)
+    } else {
+        (usize::try_from(read_u32(rsdp, 16)).unwrap(), 4)
+    };
+
+    let root = read_entry(root_address);
+    if !root.checksum_valid {
+        warn!("ACPI root table failed checksum validation.");
+        return Vec::new();
+    }
+
+    let root_table = read_bytes(root_address, usize::try_from(root.length).unwrap());
+    let mut entries = Vec::from([root]);
+    entries.extend(
+        root_table[SDT_HEADER_LEN..]
+            .chunks_exact(pointer_len)
+            .map(|pointer| {
+                if pointer_len == 8 {
+                    usize::try_from(read_u64(pointer, 0)).unwrap()
+                } else {
+                    usize::try_from(read_u32(pointer, 0)).unwrap()
+                }
+            })
+            .filter(|address| *address != 0)
+            .map(read_entry),
+    );
+
+    // The DSDT isn't listed by the root table, so it's found through the FADT.
+    if let Some(fadt) = entries
+        .iter()
+        .find(|entry| entry.checksum_valid && &entry.signature == b"FACP")
+    {
+        let fadt_table = read_bytes(fadt.physical_address, usize::try_from(fadt.length).unwrap());
+        let x_dsdt = fadt_table
+            .get(140..148)
+            .map(|_| read_u64(fadt_table, 140))
+            .filter(|address| *address != 0);
+        let dsdt = x_dsdt
+            .map(|address| usize::try_from(address).unwrap())
+            .or_else(|| Some(usize::try_from(read_u32(fadt_table, 40)).unwrap()))
+            .filter(|address| *address != 0);
+
+        if let Some(dsdt_address) = dsdt {
+            entries.push(read_entry(dsdt_address));
+        }
+    }
+
+    entries
+}
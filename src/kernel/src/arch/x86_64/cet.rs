@@ -0,0 +1,272 @@
+//! Control-flow enforcement technology (CET) supervisor shadow stacks.
+//!
+//! With shadow stacks enabled, every `call` also pushes its return address to a shadow stack, which ordinary
+//! writes can't modify, and every `ret` faults with a `#CP` if the two copies differ; so a stack overflow can't
+//! redirect a return.
+//!
+//! Each hardware thread has a shadow stack for each of its interrupt stacks, and one for privilege level 0. The
+//! latter is loaded on every interrupt from userspace, and also holds the kernel's own context (i.e. the idle loop);
+//! it's switched onto with its restore token once the hardware thread is on its kernel stack.
+//!
+//! # Remarks
+//!
+//! The scheduler and the debugger redirect interrupted contexts by rewriting their interrupt stack frames, which
+//! the copy of the return address pushed to the shadow stack would then disagree with. Instead, the interrupt stubs
+//! rewrite that copy before returning (see `stubs.s`), which requires the kernel to be permitted to write to
+//! shadow stacks with `wrss`.
+
+use crate::{
+    arch::x86_64::{
+        cpuid::extended_feature_info,
+        registers::model_specific::{IA32_INTERRUPT_SSP_TABLE_ADDR, IA32_PL0_SSP},
+        structures::tss::InterruptStackTableIndex,
+    },
+    mem::{
+        HigherHalfDirectMap,
+        paging::{TableDepth, TableEntryFlags},
+        pmm::PhysicalMemoryManager,
+    },
+};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use libsys::{Address, page_size};
+
+crate::parameters! {
+    /// Whether supervisor shadow stacks should be left disabled, even if supported.
+    "noshstk": bool = false,
+}
+
+/// Size of each shadow stack, i.e. room for 2048 return addresses.
+const SHADOW_STACK_SIZE: usize = 0x4000;
+
+/// Start of the region shadow stacks are mapped into, just below the kernel image; it shares the kernel image's
+/// top-level table entry, so mappings made after tasks have copied the kernel page table are still visible to them.
+const REGION_START: usize = 0xFFFF_FFFF_0000_0000;
+/// End of the region shadow stacks are mapped into, at the start of the kernel image.
+const REGION_END: usize = 0xFFFF_FFFF_8000_0000;
+
+/// Shadow stack pages are present and dirty, but not writable.
+const SHADOW_STACK_FLAGS: TableEntryFlags = TableEntryFlags::PRESENT
+    .union(TableEntryFlags::DIRTY)
+    .union(TableEntryFlags::NO_EXECUTE);
+
+/// Space above the privilege level 0 token, so the interrupt stubs can write a shadow stack frame over the token to
+/// return to the kernel from an interrupt which came from userspace, and the word after that frame is never a token.
+const PRIVILEGE_TOKEN_OFFSET: usize = 4 * size_of::<u64>();
+
+/// Mode bit of a restore token, set for tokens created in 64-bit mode.
+const RESTORE_TOKEN_64BIT: usize = 1 << 0;
+
+/// `IA32_S_CET`, which controls CET for privilege levels 0 through 2.
+const IA32_S_CET: u32 = 0x6A2;
+/// Enables shadow stacks for privilege level 0.
+const S_CET_SH_STK_EN: u32 = 1 << 0;
+/// Permits `wrss` at privilege level 0.
+const S_CET_WR_SHSTK_EN: u32 = 1 << 1;
+
+/// Next address to map a shadow stack (and its guard page) at.
+static NEXT_ADDRESS: AtomicUsize = AtomicUsize::new(REGION_START);
+
+/// Whether `CR4.CET` should be set on the current hardware thread.
+pub fn is_supported() -> bool {
+    extended_feature_info().is_some_and(raw_cpuid::ExtendedFeatures::has_cet_ss)
+        && !crate::params::get::<bool>("noshstk")
+}
+
+/// Maps a zeroed shadow stack above an unmapped guard page, writing `token(address)` at `address`, which is
+/// `token_offset` bytes below its top; returns `address`.
+fn allocate(token_offset: usize, token: impl FnOnce(usize) -> usize) -> usize {
+    let bottom =
+        NEXT_ADDRESS.fetch_add(page_size() + SHADOW_STACK_SIZE, Ordering::Relaxed) + page_size();
+    let top = bottom + SHADOW_STACK_SIZE;
+    assert!(top <= REGION_END, "shadow stack region is exhausted");
+
+    let token_address = top - token_offset;
+    let token = token(token_address);
+
+    crate::mem::with_kernel_mapper(|kmapper| {
+        for page_address in (bottom..top).step_by(page_size()) {
+            let frame = PhysicalMemoryManager::next_frame()
+                .expect("failed to allocate a frame for a shadow stack");
+            let frame_ptr = HigherHalfDirectMap::frame_to_page(frame).as_ptr();
+
+            // Safety: The frame was just allocated, and is accessible through the HHDM. Shadow stack pages can't be
+            //         written through their own mapping, so they're initialized through the HHDM instead.
+            unsafe {
+                frame_ptr.write_bytes(0, page_size());
+
+                if let Some(offset) = token_address
+                    .checked_sub(page_address)
+                    .filter(|offset| *offset < page_size())
+                {
+                    frame_ptr.add(offset).cast::<usize>().write(token);
+                }
+            }
+
+            kmapper
+                .map(
+                    Address::new(page_address).unwrap(),
+                    TableDepth::min(),
+                    frame,
+                    false,
+                    SHADOW_STACK_FLAGS,
+                )
+                .expect("failed to map a shadow stack");
+        }
+    });
+
+    token_address
+}
+
+/// Allocates the current hardware thread's shadow stacks, and points the processor at their tokens, if shadow
+/// stacks are supported.
+///
+/// # Safety
+///
+/// This must be called once per hardware thread, before [`enable`].
+pub unsafe fn prepare() {
+    if !is_supported() {
+        return;
+    }
+
+    // The interrupt stubs restore a supervisor token here before returning to userspace; until then, it's a restore
+    // token for the kernel's own context.
+    let privilege_token = allocate(PRIVILEGE_TOKEN_OFFSET, |address| {
+        (address + size_of::<u64>()) | RESTORE_TOKEN_64BIT
+    });
+    crate::percpu!(privilege_shadow_stack).store(privilege_token, Ordering::Relaxed);
+    IA32_PL0_SSP::write(u64::try_from(privilege_token).unwrap());
+
+    // Indexed by IST number, which is one greater than the interrupt stack table index; the first entry is unused.
+    let interrupt_tokens = crate::mem::alloc::KERNEL_ALLOCATOR
+        .allocate_t_static::<[u64; 8]>()
+        .expect("failed to allocate the interrupt shadow stack table");
+
+    for index in [
+        InterruptStackTableIndex::Debug,
+        InterruptStackTableIndex::NonMaskableInterrupt,
+        InterruptStackTableIndex::DoubleFault,
+        InterruptStackTableIndex::MachineCheck,
+    ] {
+        // A supervisor token holds its own address, with the busy bit clear.
+        let token = allocate(size_of::<u64>(), |address| address);
+
+        interrupt_tokens[usize::from(u16::from(index)) + 1] = u64::try_from(token).unwrap();
+    }
+
+    IA32_INTERRUPT_SSP_TABLE_ADDR::write(
+        u64::try_from(core::ptr::from_ref(interrupt_tokens).addr()).unwrap(),
+    );
+}
+
+/// Enables shadow stacks on the current hardware thread (if [`prepare`] allocated them), switching onto the
+/// privilege level 0 shadow stack.
+///
+/// # Safety
+///
+/// The caller must never return, as the return addresses pushed before this call were never pushed to the shadow
+/// stack; so this is inlined, and must be called before anything else by a function which doesn't return.
+#[inline(always)]
+pub unsafe fn enable() {
+    let restore_token = crate::percpu!(privilege_shadow_stack).load(Ordering::Relaxed);
+    if restore_token == 0 {
+        return;
+    }
+
+    // Safety: `prepare` wrote a restore token here, and caller is required to never return. The MSR is written here,
+    //         rather than through `model_specific`, so no `ret` executes between the two instructions.
+    unsafe {
+        core::arch::asm!(
+            "wrmsr",
+            "rstorssp qword ptr [{restore_token}]",
+            restore_token = in(reg) restore_token,
+            in("ecx") IA32_S_CET,
+            in("eax") S_CET_SH_STK_EN | S_CET_WR_SHSTK_EN,
+            in("edx") 0u32,
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
+/// Reads the current shadow stack pointer, or `0` if shadow stacks are disabled.
+#[inline(always)]
+pub fn read_ssp() -> usize {
+    let mut ssp: usize = 0;
+
+    // Safety: `rdssp` is a no-op if shadow stacks are disabled, and otherwise has no side effects.
+    unsafe {
+        core::arch::asm!(
+            "rdsspq {}",
+            inout(reg) ssp,
+            options(nostack, nomem, preserves_flags)
+        );
+    }
+
+    ssp
+}
+
+/// Return addresses which disagreed, causing a near `ret` to raise a `#CP`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReturnMismatch {
+    /// Return address on the (ordinary) stack.
+    pub stack: usize,
+    /// Return address on the shadow stack.
+    pub shadow_stack: usize,
+}
+
+/// Reads the return addresses a near `ret` in the kernel disagreed on.
+///
+/// `stack_pointer` is the interrupted stack pointer, and `handler_ssp` is the shadow stack pointer on entry to the
+/// `#CP` handler, which is just below the shadow stack frame of the `#CP` (i.e. only the handler's return address
+/// has been pushed since).
+///
+/// # Safety
+///
+/// Both pointers must be the `#CP`'s, which must have been raised by a near `ret` in the kernel.
+pub unsafe fn read_return_mismatch(
+    stack_pointer: usize,
+    handler_ssp: usize,
+) -> Option<ReturnMismatch> {
+    if handler_ssp == 0 {
+        return None;
+    }
+
+    // The interrupt's shadow stack frame holds the interrupted shadow stack pointer, then its return address and code
+    // segment.
+    let frame = handler_ssp + size_of::<u64>();
+
+    // Safety: Caller is required to provide the `#CP`'s pointers; shadow stack pages are readable.
+    unsafe {
+        let interrupted_ssp = core::ptr::with_exposed_provenance::<usize>(frame).read();
+        if interrupted_ssp == 0 || !interrupted_ssp.is_multiple_of(size_of::<u64>()) {
+            return None;
+        }
+
+        Some(ReturnMismatch {
+            stack: core::ptr::with_exposed_provenance::<usize>(stack_pointer).read(),
+            shadow_stack: core::ptr::with_exposed_provenance::<usize>(interrupted_ssp).read(),
+        })
+    }
+}
+
+crate::kernel_test! {
+    /// The kernel runs on a mapped shadow stack, if shadow stacks are enabled.
+    fn shadow_stack_active() -> crate::tests::Outcome {
+        let ssp = read_ssp();
+
+        if ssp == 0 {
+            return Ok(());
+        }
+
+        if !(REGION_START..REGION_END).contains(&ssp) {
+            return Err("shadow stack pointer is outside of the shadow stack region");
+        }
+
+        if crate::mem::with_kernel_mapper(|kmapper| kmapper.get_page_attributes(Address::new_truncate(ssp)))
+            .is_none_or(|attributes| attributes.contains(TableEntryFlags::WRITABLE))
+        {
+            return Err("shadow stack pointer is not within a shadow stack page");
+        }
+
+        Ok(())
+    }
+}
@@ -1,10 +1,13 @@
 use raw_cpuid::{
     ApmInfo, CpuId, CpuIdReaderNative, ExtendedFeatures, ExtendedProcessorFeatureIdentifiers,
-    ExtendedTopologyIter, FeatureInfo, HypervisorInfo, ProcessorFrequencyInfo, VendorInfo,
+    ExtendedStateInfo, ExtendedTopologyIter, FeatureInfo, HypervisorInfo,
+    PerformanceMonitoringInfo, ProcessorFrequencyInfo, TscInfo, VendorInfo,
 };
 use spin::Lazy;
 
-static CPUID: Lazy<CpuId<CpuIdReaderNative>> = Lazy::new(CpuId::new);
+crate::ro_after_init! {
+    static CPUID: Lazy<CpuId<CpuIdReaderNative>> = Lazy::new(CpuId::new);
+}
 
 pub fn vendor_info() -> &'static str {
     static VENDOR_INFO: Lazy<Option<VendorInfo>> = Lazy::new(|| CPUID.get_vendor_info());
@@ -15,25 +18,38 @@ pub fn vendor_info() -> &'static str {
 }
 
 pub fn feature_info() -> Option<&'static FeatureInfo> {
-    static FEATURE_INFO: Lazy<Option<FeatureInfo>> = Lazy::new(|| CPUID.get_feature_info());
+    crate::ro_after_init! {
+        static FEATURE_INFO: Lazy<Option<FeatureInfo>> = Lazy::new(|| CPUID.get_feature_info());
+    }
 
     FEATURE_INFO.as_ref()
 }
 
 pub fn extended_feature_info() -> Option<&'static ExtendedFeatures> {
-    static EXT_FEATURE_INFO: Lazy<Option<ExtendedFeatures>> =
-        Lazy::new(|| CPUID.get_extended_feature_info());
+    crate::ro_after_init! {
+        static EXT_FEATURE_INFO: Lazy<Option<ExtendedFeatures>> =
+            Lazy::new(|| CPUID.get_extended_feature_info());
+    }
 
     EXT_FEATURE_INFO.as_ref()
 }
 
 pub fn extended_feature_identifiers() -> Option<&'static ExtendedProcessorFeatureIdentifiers> {
-    static EXT_FEATURE_IDENTIFIERS: Lazy<Option<ExtendedProcessorFeatureIdentifiers>> =
-        Lazy::new(|| CPUID.get_extended_processor_and_feature_identifiers());
+    crate::ro_after_init! {
+        static EXT_FEATURE_IDENTIFIERS: Lazy<Option<ExtendedProcessorFeatureIdentifiers>> =
+            Lazy::new(|| CPUID.get_extended_processor_and_feature_identifiers());
+    }
 
     EXT_FEATURE_IDENTIFIERS.as_ref()
 }
 
+pub fn extended_state_info() -> Option<&'static ExtendedStateInfo<CpuIdReaderNative>> {
+    static EXTENDED_STATE_INFO: Lazy<Option<ExtendedStateInfo<CpuIdReaderNative>>> =
+        Lazy::new(|| CPUID.get_extended_state_info());
+
+    EXTENDED_STATE_INFO.as_ref()
+}
+
 pub fn processor_frequency_info() -> Option<&'static ProcessorFrequencyInfo> {
     static PROCESSOR_FREQUENCY_INFO: Lazy<Option<ProcessorFrequencyInfo>> =
         Lazy::new(|| CPUID.get_processor_frequency_info());
@@ -41,6 +57,12 @@ pub fn processor_frequency_info() -> Option<&'static ProcessorFrequencyInfo> {
     PROCESSOR_FREQUENCY_INFO.as_ref()
 }
 
+pub fn tsc_info() -> Option<&'static TscInfo> {
+    static TSC_INFO: Lazy<Option<TscInfo>> = Lazy::new(|| CPUID.get_tsc_info());
+
+    TSC_INFO.as_ref()
+}
+
 pub fn advanced_power_management_info() -> Option<&'static ApmInfo> {
     static ADVANCED_PWM_INFO: Lazy<Option<ApmInfo>> =
         Lazy::new(|| CPUID.get_advanced_power_mgmt_info());
@@ -69,12 +91,20 @@ pub fn hypervisor_info() -> Option<&'static HypervisorInfo<CpuIdReaderNative>> {
     HYPERVISOR_INFO.as_ref()
 }
 
+pub fn performance_monitoring_info() -> Option<&'static PerformanceMonitoringInfo> {
+    static PERFORMANCE_MONITORING_INFO: Lazy<Option<PerformanceMonitoringInfo>> =
+        Lazy::new(|| CPUID.get_performance_monitoring_info());
+
+    PERFORMANCE_MONITORING_INFO.as_ref()
+}
+
 pub fn print_info() {
     info!("CPU Vendor: {}", vendor_info());
     debug!("{:#?}", feature_info());
     debug!("{:#?}", extended_feature_info());
     debug!("{:#?}", extended_feature_identifiers());
     debug!("{:#?}", processor_frequency_info());
+    debug!("{:#?}", tsc_info());
     debug!("{:#?}", advanced_power_management_info());
     debug!("{:#?}", hypervisor_info());
 }
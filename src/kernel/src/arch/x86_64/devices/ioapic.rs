@@ -0,0 +1,196 @@
+use crate::{
+    acpi::madt::{Polarity, Topology, TriggerMode},
+    sync::SpinMutex,
+};
+use alloc::vec::Vec;
+use bit_field::BitField;
+use core::ptr::NonNull;
+use libsys::{Address, Frame};
+use spin::Once;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to map I/O APIC registers: {0:?}")]
+    Map(#[from] crate::mem::paging::Error),
+
+    #[error("I/O APIC has an invalid base address: {0:#X}")]
+    InvalidBaseAddress(u32),
+
+    #[error("no I/O APIC handles global system interrupt {0}")]
+    NoIoApic(u32),
+
+    #[error("APIC ID cannot be targeted by an I/O APIC: {0}")]
+    InvalidDestination(u32),
+}
+
+/// Size of the I/O APIC register window.
+const REGISTER_BLOCK_SIZE: usize = 0x20;
+
+const IOREGSEL: usize = 0x00;
+const IOWIN: usize = 0x10;
+
+const VERSION_REGISTER: u32 = 0x01;
+const REDIRECTION_BASE: u32 = 0x10;
+
+struct IoApic {
+    gsi_base: u32,
+    redirection_count: u32,
+    registers: SpinMutex<NonNull<u8>>,
+}
+
+// Safety: Register block is mapped in the kernel address space, and accessed only behind a lock.
+unsafe impl Send for IoApic {}
+// Safety: Register block is mapped in the kernel address space, and accessed only behind a lock.
+unsafe impl Sync for IoApic {}
+
+impl IoApic {
+    fn read(registers: NonNull<u8>, register: u32) -> u32 {
+        // Safety: Both registers lie within the mapped register block.
+        unsafe {
+            registers
+                .add(IOREGSEL)
+                .cast::<u32>()
+                .write_volatile(register);
+            registers.add(IOWIN).cast::<u32>().read_volatile()
+        }
+    }
+
+    fn write(registers: NonNull<u8>, register: u32, value: u32) {
+        // Safety: Both registers lie within the mapped register block.
+        unsafe {
+            registers
+                .add(IOREGSEL)
+                .cast::<u32>()
+                .write_volatile(register);
+            registers.add(IOWIN).cast::<u32>().write_volatile(value);
+        }
+    }
+
+    fn handles(&self, gsi: u32) -> bool {
+        (self.gsi_base..(self.gsi_base + self.redirection_count)).contains(&gsi)
+    }
+
+    fn redirection_register(&self, gsi: u32) -> u32 {
+        REDIRECTION_BASE + ((gsi - self.gsi_base) * 2)
+    }
+
+    // The registers are locked without interrupts, as interrupt handlers may mask their own lines.
+
+    fn write_redirection(&self, gsi: u32, entry: u64) {
+        let register = self.redirection_register(gsi);
+
+        crate::interrupts::uninterruptable(|| {
+            let registers = self.registers.lock();
+
+            // Mask the entry before changing it, so a half-written entry is never delivered.
+            Self::write(*registers, register, 1 << 16);
+            Self::write(
+                *registers,
+                register + 1,
+                u32::try_from(entry.get_bits(32..64)).unwrap(),
+            );
+            Self::write(
+                *registers,
+                register,
+                u32::try_from(entry.get_bits(0..32)).unwrap(),
+            );
+        });
+    }
+
+    fn set_masked(&self, gsi: u32, masked: bool) {
+        let register = self.redirection_register(gsi);
+
+        crate::interrupts::uninterruptable(|| {
+            let registers = self.registers.lock();
+
+            let mut entry = Self::read(*registers, register);
+            entry.set_bit(16, masked);
+            Self::write(*registers, register, entry);
+        });
+    }
+}
+
+static IO_APICS: Once<Vec<IoApic>> = Once::new();
+
+fn io_apics() -> &'static [IoApic] {
+    IO_APICS.call_once(|| {
+        Topology::io_apics()
+            .iter()
+            .filter_map(|io_apic| {
+                let map = || -> Result<IoApic, Error> {
+                    let base_frame =
+                        Address::<Frame>::new(usize::try_from(io_apic.address).unwrap())
+                            .ok_or(Error::InvalidBaseAddress(io_apic.address))?;
+                    let registers = crate::mem::map_mmio(base_frame, REGISTER_BLOCK_SIZE)?;
+                    let redirection_count =
+                        IoApic::read(registers, VERSION_REGISTER).get_bits(16..24) + 1;
+
+                    Ok(IoApic {
+                        gsi_base: io_apic.gsi_base,
+                        redirection_count,
+                        registers: SpinMutex::new(registers),
+                    })
+                };
+
+                map()
+                    .inspect(|mapped| {
+                        debug!(
+                            "I/O APIC #{}: {{ GSIs: {}..{} }}",
+                            io_apic.id,
+                            mapped.gsi_base,
+                            mapped.gsi_base + mapped.redirection_count
+                        );
+                    })
+                    .inspect_err(|error| warn!("I/O APIC #{} is unavailable: {error}", io_apic.id))
+                    .ok()
+            })
+            .collect()
+    })
+}
+
+fn io_apic_for(gsi: u32) -> Result<&'static IoApic, Error> {
+    io_apics()
+        .iter()
+        .find(|io_apic| io_apic.handles(gsi))
+        .ok_or(Error::NoIoApic(gsi))
+}
+
+/// Routes `gsi` to `vector` on the local APIC with ID `apic_id`, and unmasks it.
+pub fn route(
+    gsi: u32,
+    vector: u8,
+    polarity: Polarity,
+    trigger_mode: TriggerMode,
+    apic_id: u32,
+) -> Result<(), Error> {
+    let destination = u8::try_from(apic_id).map_err(|_| Error::InvalidDestination(apic_id))?;
+
+    let mut entry = 0u64;
+    entry.set_bits(0..8, u64::from(vector));
+    // Delivery mode (fixed) and destination mode (physical) are both zero.
+    entry.set_bit(13, matches!(polarity, Polarity::ActiveLow));
+    entry.set_bit(15, matches!(trigger_mode, TriggerMode::Level));
+    entry.set_bits(56..64, u64::from(destination));
+
+    io_apic_for(gsi)?.write_redirection(gsi, entry);
+
+    trace!(
+        "Routed GSI {gsi} -> vector {vector:#X} (APIC #{apic_id}, {polarity:?}, {trigger_mode:?})"
+    );
+
+    Ok(())
+}
+
+/// Masks `gsi`, so it is no longer delivered.
+pub fn mask(gsi: u32) -> Result<(), Error> {
+    io_apic_for(gsi)?.write_redirection(gsi, 1 << 16);
+
+    Ok(())
+}
+
+/// Masks or unmasks `gsi`, keeping its route.
+pub fn set_masked(gsi: u32, masked: bool) -> Result<(), Error> {
+    io_apic_for(gsi)?.set_masked(gsi, masked);
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use crate::arch::x86_64::devices::x2apic::InterruptDeliveryMode;
+use crate::arch::x86_64::devices::local_apic::InterruptDeliveryMode;
 use bit_field::BitField;
 use core::num::NonZeroU8;
 
@@ -212,6 +212,43 @@ impl InterruptCommand {
         )
     }
 
+    /// Constructs a fixed interrupt on `vector`, sent to the hardware thread with the given APIC ID.
+    pub fn new_fixed(vector: NonZeroU8, apic_id: u32) -> Self {
+        Self::new(
+            Some(vector),
+            InterruptDestination::Processor { id: apic_id },
+            InterruptDeliveryMode::Fixed,
+            InterruptDestinationMode::Physical,
+            InterruptTriggerMode::Edge,
+            InterruptAssertMode::Assert,
+        )
+    }
+
+    /// Constructs a non-maskable interrupt, sent to the hardware thread with the given APIC ID.
+    pub fn new_nmi(apic_id: u32) -> Self {
+        Self::new(
+            None,
+            InterruptDestination::Processor { id: apic_id },
+            InterruptDeliveryMode::NonMaskable,
+            InterruptDestinationMode::Physical,
+            InterruptTriggerMode::Edge,
+            InterruptAssertMode::Assert,
+        )
+    }
+
+    /// Constructs a non-maskable interrupt, sent to every hardware thread except the
+    /// one issuing the command.
+    pub fn new_nmi_broadcast() -> Self {
+        Self::new(
+            None,
+            InterruptDestination::AllExclusingSelf,
+            InterruptDeliveryMode::NonMaskable,
+            InterruptDestinationMode::Physical,
+            InterruptTriggerMode::Edge,
+            InterruptAssertMode::Assert,
+        )
+    }
+
     pub(crate) fn high(self) -> u32 {
         self.high
     }
@@ -1,7 +1,7 @@
 use crate::{
     arch::x86_64::{
         cpuid::feature_info,
-        devices::x2apic::{InterruptDeliveryMode, Register, x2Apic},
+        devices::local_apic::{InterruptDeliveryMode, LocalApic, Register},
     },
     interrupts::Vector,
 };
@@ -170,7 +170,7 @@ impl LocalVector<Timer> {
     }
 }
 
-impl x2Apic {
+impl LocalApic {
     pub fn lvt_lint0() -> LocalVector<LINT0> {
         LocalVector(PhantomData)
     }
@@ -190,18 +190,18 @@ impl x2Apic {
     pub fn lvt_performance_counter() -> Option<LocalVector<PerformanceCounter>> {
         // If max LVT is 4, then 5 registers are supported, which includes
         // the performance counter register.
-        (x2Apic::max_lvt_entry() >= 4).then_some(LocalVector(PhantomData))
+        (LocalApic::max_lvt_entry() >= 4).then_some(LocalVector(PhantomData))
     }
 
     pub fn lvt_thermal_monitor() -> Option<LocalVector<ThermalSensor>> {
         // If max LVT is 5, then 6 registers are supported, which includes
         // thermal monitor register.
-        (x2Apic::max_lvt_entry() >= 5).then_some(LocalVector(PhantomData))
+        (LocalApic::max_lvt_entry() >= 5).then_some(LocalVector(PhantomData))
     }
 
     pub fn lvt_cmci() -> Option<LocalVector<CMCI>> {
         // If max LVT is 6, then 7 registers are supported, which includes
         // the CMCI register.
-        (x2Apic::max_lvt_entry() >= 6).then_some(LocalVector(PhantomData))
+        (LocalApic::max_lvt_entry() >= 6).then_some(LocalVector(PhantomData))
     }
 }
@@ -1,9 +1,19 @@
+//! Local APIC, accessed through its model-specific registers in x2APIC mode, or otherwise through its memory-mapped
+//! registers (xAPIC mode).
+
 pub mod interrupt_command;
 pub mod local_vector;
-
-use crate::interrupts::Vector;
+mod x2apic;
+mod xapic;
+
+use crate::{
+    arch::x86_64::{cpuid::feature_info, registers::model_specific::IA32_APIC_BASE},
+    interrupts::Vector,
+};
+use alloc::boxed::Box;
 use bit_field::BitField;
 use core::fmt;
+use spin::Once;
 
 pub const US_PER_SEC: u64 = 1000000;
 pub const US_WAIT: u64 = 10000;
@@ -35,42 +45,32 @@ pub enum Register {
     TIMER_DIVIDE_CONFIGURATION  = 0x83E,
 }
 
-/// Reads from the model-specific register at the provided `address`.
+/// Means of reading and writing the local APIC's registers, selected by [`LocalApic::init`].
+///
+/// Registers are read and written with their x2APIC layout, which xAPIC access converts to and from.
+trait RegisterAccess: Sync {
+    fn read(&self, register: Register) -> u64;
+    fn write(&self, register: Register, value: u64);
+}
+
+crate::ro_after_init! {
+    static ACCESS: Once<&'static dyn RegisterAccess> = Once::new();
+}
+
 #[inline(always)]
 fn read_register(register: Register) -> u64 {
-    let value_low: u64;
-    let value_high: u64;
-
-    // Safety: Reading from a model-specific register cannot create undefined behaviour.
-    unsafe {
-        core::arch::asm!(
-            "rdmsr",
-            in("ecx") u32::from(register),
-            out("edx") value_high,
-            out("eax") value_low,
-            options(nostack, nomem, preserves_flags)
-        );
-    }
-
-    (value_high << 32) | value_low
+    ACCESS
+        .get()
+        .expect("local APIC has not been initialized")
+        .read(register)
 }
 
-/// Writes `value` to the model-specific register at the provided `address`.
 #[inline(always)]
 fn write_register(register: Register, value: u64) {
-    let value_low = value & 0xFFFF_FFFF;
-    let value_high = value >> 32;
-
-    // Safety: Writing to x2 APIC model-specific registers cannot create undefined behaviour.
-    unsafe {
-        core::arch::asm!(
-            "wrmsr",
-            in("ecx") u32::from(register),
-            in("edx") value_high,
-            in("eax") value_low,
-            options(nostack, nomem, preserves_flags)
-        );
-    }
+    ACCESS
+        .get()
+        .expect("local APIC has not been initialized")
+        .write(register, value);
 }
 
 bitflags! {
@@ -179,12 +179,71 @@ pub enum TimerDivideConfiguration {
     DivideBy128 = 0b1010,
 }
 
+impl TimerDivideConfiguration {
+    /// All divide configurations, in ascending order of divisor.
+    pub const ALL: [Self; 8] = [
+        Self::DivideBy1,
+        Self::DivideBy2,
+        Self::DivideBy4,
+        Self::DivideBy8,
+        Self::DivideBy16,
+        Self::DivideBy32,
+        Self::DivideBy64,
+        Self::DivideBy128,
+    ];
+
+    /// Value the timer's base frequency is divided by.
+    pub const fn divisor(self) -> u32 {
+        match self {
+            Self::DivideBy1 => 1,
+            Self::DivideBy2 => 2,
+            Self::DivideBy4 => 4,
+            Self::DivideBy8 => 8,
+            Self::DivideBy16 => 16,
+            Self::DivideBy32 => 32,
+            Self::DivideBy64 => 64,
+            Self::DivideBy128 => 128,
+        }
+    }
+}
+
 #[allow(non_camel_case_types)]
-pub struct x2Apic;
+pub struct LocalApic;
+
+impl LocalApic {
+    /// Selects how the local APIC's registers are accessed: as model-specific registers, if the bootloader left it in
+    /// x2APIC mode, or otherwise through its register page, which is mapped here.
+    ///
+    /// # Remarks
+    ///
+    /// Every hardware thread's local APIC is expected to be in the same mode, at the same base address, as the
+    /// bootstrap hardware thread's.
+    pub fn init() {
+        ACCESS.call_once(|| {
+            if IA32_APIC_BASE::get_is_x2apic_mode() {
+                debug!("Local APIC is in x2APIC mode.");
+
+                return &x2apic::X2Apic;
+            }
+
+            let base_frame = IA32_APIC_BASE::get_base_frame();
+            debug!("Local APIC is in xAPIC mode, with its registers at {base_frame:X?}.");
+
+            let registers = crate::mem::map_mmio(base_frame, xapic::REGISTER_PAGE_SIZE)
+                .expect("failed to map the local APIC's registers");
+
+            // Safety: `registers` is the local APIC's register page, which `map_mmio` mapped uncacheable.
+            Box::leak(Box::new(unsafe { xapic::XApic::new(registers) }))
+        });
+    }
+
+    /// Whether the local APIC's registers are accessed as model-specific registers.
+    pub fn is_x2apic_mode() -> bool {
+        IA32_APIC_BASE::get_is_x2apic_mode()
+    }
 
-impl x2Apic {
     pub fn reset() {
-        debug!("Local APIC:\n{x2Apic:#X?}");
+        debug!("Local APIC:\n{LocalApic:#X?}");
 
         trace!("Disabling local APIC for reset sequence...");
         Self::set_enabled(false);
@@ -209,10 +268,11 @@ impl x2Apic {
         Self::lvt_timer().set_vector(Vector::Timer).set_masked(true);
 
         if let Some(lvt_performance_counter) = Self::lvt_performance_counter() {
-            trace!("Configuring the performance counter interrupt...");
+            // The profiler unmasks this once it has checked the performance monitoring unit is usable.
+            trace!("Configuring the performance counter interrupt (will be masked)...");
             lvt_performance_counter
                 .set_vector(Vector::PerformanceCounter)
-                .set_masked(false);
+                .set_masked(true);
         } else {
             trace!("Performance counter local vector not supported.");
         }
@@ -237,8 +297,17 @@ impl x2Apic {
     }
 
     /// The initial ID of the local APIC device.
+    ///
+    /// # Remarks
+    ///
+    /// This is needed before [`Self::init`] (when hardware threads claim their per-CPU blocks), so in xAPIC mode it's
+    /// the initial APIC ID reported by CPUID, rather than a read of the (possibly unmapped) register page.
     pub fn get_id() -> u32 {
-        u32::try_from(read_register(Register::ID)).unwrap()
+        if Self::is_x2apic_mode() {
+            u32::try_from(x2apic::X2Apic.read(Register::ID)).unwrap()
+        } else {
+            feature_info().map_or(0, |info| u32::from(info.initial_local_apic_id()))
+        }
     }
 
     /// Version of the APIC device.
@@ -321,8 +390,10 @@ impl x2Apic {
     }
 
     /// Whether the local APIC is enabled (`1`/`true`) or disabled (`0`/`false`).
+    ///
+    /// Before [`Self::init`], the local APIC is reported as disabled, as it can't yet be used.
     pub fn get_enabled() -> bool {
-        read_register(Register::SPURIOUS_VECTOR).get_bit(8)
+        ACCESS.get().is_some() && read_register(Register::SPURIOUS_VECTOR).get_bit(8)
     }
 
     /// Enables (`1`/`true`) or disables (`0`/`false`) the local APIC.
@@ -388,7 +459,7 @@ impl x2Apic {
         let low = u64::from(interrupt_command.low());
 
         assert!(
-            low.get_bits(8..11) != 0b001,
+            !Self::is_x2apic_mode() || low.get_bits(8..11) != 0b001,
             "x2 APIC does not support low priority delivery mode"
         );
 
@@ -400,7 +471,7 @@ impl x2Apic {
     }
 }
 
-impl fmt::Debug for x2Apic {
+impl fmt::Debug for LocalApic {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Version")
             .field("ID", &Self::get_id())
@@ -0,0 +1,43 @@
+//! x2APIC mode, in which the local APIC's registers are model-specific registers.
+
+use super::{Register, RegisterAccess};
+
+pub struct X2Apic;
+
+impl RegisterAccess for X2Apic {
+    #[inline(always)]
+    fn read(&self, register: Register) -> u64 {
+        let value_low: u64;
+        let value_high: u64;
+
+        // Safety: Reading from a model-specific register cannot create undefined behaviour.
+        unsafe {
+            core::arch::asm!(
+                "rdmsr",
+                in("ecx") u32::from(register),
+                out("edx") value_high,
+                out("eax") value_low,
+                options(nostack, nomem, preserves_flags)
+            );
+        }
+
+        (value_high << 32) | value_low
+    }
+
+    #[inline(always)]
+    fn write(&self, register: Register, value: u64) {
+        let value_low = value & 0xFFFF_FFFF;
+        let value_high = value >> 32;
+
+        // Safety: Writing to x2 APIC model-specific registers cannot create undefined behaviour.
+        unsafe {
+            core::arch::asm!(
+                "wrmsr",
+                in("ecx") u32::from(register),
+                in("edx") value_high,
+                in("eax") value_low,
+                options(nostack, nomem, preserves_flags)
+            );
+        }
+    }
+}
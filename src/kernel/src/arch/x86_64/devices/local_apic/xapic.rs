@@ -0,0 +1,100 @@
+//! xAPIC mode, in which the local APIC's registers are a page of memory-mapped IO.
+//!
+//! Each register is 32 bits, 16-byte aligned, at the offset its x2APIC MSR has from `0x800` (shifted left by 4). The
+//! few registers whose layouts differ from x2APIC mode are converted here, so callers only see the x2APIC layout:
+//!   * the ID is held in bits 24..32;
+//!   * the interrupt command register is split in two, with the destination in bits 24..32 of the upper half.
+
+use super::{Register, RegisterAccess};
+use bit_field::BitField;
+use core::ptr::NonNull;
+
+/// Size of the memory-mapped register page.
+pub const REGISTER_PAGE_SIZE: usize = 0x1000;
+
+/// Offset of the upper half of the interrupt command register.
+const INTERRUPT_COMMAND_HIGH: usize = 0x310;
+
+/// Interrupt command register bit which is set while an interrupt is pending delivery.
+const DELIVERY_STATUS_BIT: usize = 12;
+
+pub struct XApic {
+    registers: NonNull<u8>,
+}
+
+// Safety: The register page is only ever accessed with volatile reads and writes, which each hardware thread makes
+//         to its own local APIC.
+unsafe impl Send for XApic {}
+// Safety: See above.
+unsafe impl Sync for XApic {}
+
+impl XApic {
+    /// # Safety
+    ///
+    /// `registers` must point to the local APIC's register page, mapped as uncacheable memory.
+    pub unsafe fn new(registers: NonNull<u8>) -> Self {
+        Self { registers }
+    }
+
+    fn offset(register: Register) -> usize {
+        usize::try_from(u32::from(register) - 0x800).unwrap() << 4
+    }
+
+    fn read_offset(&self, offset: usize) -> u32 {
+        // Safety: `offset` is a register within the register page, which is valid for the kernel's lifetime.
+        unsafe { self.registers.add(offset).cast::<u32>().read_volatile() }
+    }
+
+    fn write_offset(&self, offset: usize, value: u32) {
+        // Safety: `offset` is a register within the register page, which is valid for the kernel's lifetime.
+        unsafe {
+            self.registers
+                .add(offset)
+                .cast::<u32>()
+                .write_volatile(value)
+        }
+    }
+}
+
+impl RegisterAccess for XApic {
+    fn read(&self, register: Register) -> u64 {
+        let value = self.read_offset(Self::offset(register));
+
+        match register {
+            Register::ID => u64::from(value.get_bits(24..32)),
+
+            Register::INTERRUPT_COMMAND => {
+                let destination = self.read_offset(INTERRUPT_COMMAND_HIGH).get_bits(24..32);
+
+                (u64::from(destination) << 32) | u64::from(value)
+            }
+
+            _ => u64::from(value),
+        }
+    }
+
+    fn write(&self, register: Register, value: u64) {
+        let low = u32::try_from(value.get_bits(..32)).unwrap();
+
+        match register {
+            Register::INTERRUPT_COMMAND => {
+                // xAPIC IDs are 8 bits, so the x2APIC broadcast destination (`0xFFFFFFFF`) truncates to the xAPIC one.
+                let destination = u32::try_from(value.get_bits(32..40)).unwrap();
+
+                // Writing the lower half sends the interrupt, so the destination has to be written first.
+                self.write_offset(INTERRUPT_COMMAND_HIGH, destination << 24);
+                self.write_offset(Self::offset(register), low);
+
+                // Unlike in x2APIC mode, an interrupt command mustn't be written until the last has been delivered.
+                while self
+                    .read_offset(Self::offset(register))
+                    .get_bit(DELIVERY_STATUS_BIT)
+                {
+                    core::hint::spin_loop();
+                }
+            }
+
+            register => self.write_offset(Self::offset(register), low),
+        }
+    }
+}
@@ -1 +1,3 @@
-pub mod x2apic;
+pub mod ioapic;
+pub mod local_apic;
+pub mod pic8259;
@@ -0,0 +1,92 @@
+//! Legacy 8259 programmable interrupt controllers (PICs).
+//!
+//! Device interrupts are routed through the I/O APICs, so the PICs are only remapped and masked. Even with every
+//! line masked, a PIC raises a spurious IRQ 7 (or 15) if a line deasserts before the interrupt is acknowledged,
+//! which still reaches the bootstrap hardware thread if firmware left its `LINT0` in ExtINT mode. So the PICs are
+//! remapped to [`LEGACY_PIC_VECTORS`], rather than their power-on vectors (which overlap the exception vectors) or
+//! the conventional `0x20..=0x2F` (which overlap the kernel's fixed vectors).
+//!
+//! Platforms without PICs ignore writes to their ports, so they're configured whether or not the MADT reports them.
+
+use crate::interrupts::LEGACY_PIC_VECTORS;
+use ioports::WriteOnlyPort;
+
+const MASTER_COMMAND_PORT: u16 = 0x20;
+const MASTER_DATA_PORT: u16 = 0x21;
+const SLAVE_COMMAND_PORT: u16 = 0xA0;
+const SLAVE_DATA_PORT: u16 = 0xA1;
+/// Unused port (POST codes), written between commands to give older PICs time to process them.
+const DELAY_PORT: u16 = 0x80;
+
+/// ICW1: begin initialization, expecting an ICW4.
+const ICW1_INIT: u8 = 0x11;
+/// ICW3 (master): the slave is cascaded on IRQ 2.
+const ICW3_MASTER_CASCADE: u8 = 1 << 2;
+/// ICW3 (slave): the slave's cascade identity.
+const ICW3_SLAVE_IDENTITY: u8 = 2;
+/// ICW4: 8086 mode, with normal end-of-interrupt.
+const ICW4_8086: u8 = 0x01;
+
+/// Mask with every line of a PIC masked.
+const MASK_ALL: u8 = 0xFF;
+
+/// Remaps both PICs to [`LEGACY_PIC_VECTORS`], and masks every line.
+pub fn init() {
+    let master_vector_base = *LEGACY_PIC_VECTORS.start();
+    let slave_vector_base = master_vector_base + 8;
+
+    // Safety: The PICs' ports are fixed on PC-compatible platforms, and ignored on others; nothing else accesses them.
+    unsafe {
+        let mut master_command = WriteOnlyPort::<u8>::new(MASTER_COMMAND_PORT);
+        let mut master_data = WriteOnlyPort::<u8>::new(MASTER_DATA_PORT);
+        let mut slave_command = WriteOnlyPort::<u8>::new(SLAVE_COMMAND_PORT);
+        let mut slave_data = WriteOnlyPort::<u8>::new(SLAVE_DATA_PORT);
+        let mut delay = WriteOnlyPort::<u8>::new(DELAY_PORT);
+
+        // Lines are masked first, so nothing is raised mid-initialization.
+        master_data.write(MASK_ALL);
+        slave_data.write(MASK_ALL);
+
+        for (command, data, vector_base, cascade) in [
+            (
+                &mut master_command,
+                &mut master_data,
+                master_vector_base,
+                ICW3_MASTER_CASCADE,
+            ),
+            (
+                &mut slave_command,
+                &mut slave_data,
+                slave_vector_base,
+                ICW3_SLAVE_IDENTITY,
+            ),
+        ] {
+            command.write(ICW1_INIT);
+            delay.write(0);
+            data.write(vector_base);
+            delay.write(0);
+            data.write(cascade);
+            delay.write(0);
+            data.write(ICW4_8086);
+            delay.write(0);
+
+            // Initialization clears the mask, so it's masked again.
+            data.write(MASK_ALL);
+        }
+    }
+
+    debug!("Legacy PICs remapped to {LEGACY_PIC_VECTORS:#X?}, and masked.");
+}
+
+/// Handles an interrupt raised by one of the PICs on `vector`, which can only be spurious, as every line is masked.
+///
+/// # Remarks
+///
+/// Spurious interrupts aren't acknowledged by the PIC, and ExtINT interrupts aren't acknowledged by the local APIC,
+/// so there's nothing to acknowledge.
+pub fn handle_interrupt(vector: u8) {
+    trace!(
+        "Spurious legacy PIC interrupt (IRQ {}).",
+        vector - LEGACY_PIC_VECTORS.start()
+    );
+}
@@ -5,14 +5,60 @@ use libsys::{Address, Page};
 pub enum Error {
     #[error("instruction is not supported on the current platform")]
     InstructionSupport,
+
+    #[error("instruction produced no value after {0} attempts")]
+    Exhausted(usize),
 }
 
+/// Attempts made at `RDRAND` & `RDSEED` before giving up; Intel recommends 10 for `RDRAND`, which only fails if the
+/// generator is starved.
+const RANDOM_RETRIES: usize = 10;
+
+/// Reads a random number from the processor's DRBG.
 pub fn __rdrand() -> Result<u64, Error> {
-    todo!()
+    if !crate::arch::x86_64::cpuid::feature_info().is_some_and(raw_cpuid::FeatureInfo::has_rdrand) {
+        return Err(Error::InstructionSupport);
+    }
+
+    (0..RANDOM_RETRIES)
+        .find_map(|_| {
+            let value: u64;
+            let is_valid: u8;
+            // Safety: CPUID advertises the instruction, which only writes its operands & flags.
+            unsafe {
+                asm!("rdrand {}", "setc {}", out(reg) value, out(reg_byte) is_valid, options(nostack, nomem));
+            }
+
+            (is_valid != 0).then_some(value)
+        })
+        .ok_or(Error::Exhausted(RANDOM_RETRIES))
 }
 
+/// Reads a random seed from the processor's entropy source, which (unlike `RDRAND`) is fully conditioned entropy.
 pub fn __rdseed() -> Result<u64, Error> {
-    todo!()
+    if !crate::arch::x86_64::cpuid::extended_feature_info()
+        .is_some_and(raw_cpuid::ExtendedFeatures::has_rdseed)
+    {
+        return Err(Error::InstructionSupport);
+    }
+
+    (0..RANDOM_RETRIES)
+        .find_map(|_| {
+            let value: u64;
+            let is_valid: u8;
+            // Safety: CPUID advertises the instruction, which only writes its operands & flags.
+            unsafe {
+                asm!("rdseed {}", "setc {}", out(reg) value, out(reg_byte) is_valid, options(nostack, nomem));
+            }
+
+            // The entropy source refills slowly, so back off between attempts.
+            if is_valid == 0 {
+                core::hint::spin_loop();
+            }
+
+            (is_valid != 0).then_some(value)
+        })
+        .ok_or(Error::Exhausted(RANDOM_RETRIES))
 }
 
 /// Enables interrupts for the current hardware thread.
@@ -33,6 +79,32 @@ pub fn __cli() {
     }
 }
 
+/// Sets `RFLAGS.AC`, permitting supervisor-mode access to user pages with SMAP enabled.
+///
+/// # Remarks
+///
+/// This raises `#UD` if SMAP isn't supported.
+#[inline(always)]
+pub fn __stac() {
+    // Safety: Setting `RFLAGS.AC` only relaxes access checks, which the caller restores with `__clac`.
+    unsafe {
+        asm!("stac", options(nostack, nomem));
+    }
+}
+
+/// Clears `RFLAGS.AC`, preventing supervisor-mode access to user pages with SMAP enabled.
+///
+/// # Remarks
+///
+/// This raises `#UD` if SMAP isn't supported.
+#[inline(always)]
+pub fn __clac() {
+    // Safety: Clearing `RFLAGS.AC` only tightens access checks.
+    unsafe {
+        asm!("clac", options(nostack, nomem));
+    }
+}
+
 /// Waits for the next interrupt on the current hardware thread.
 pub fn __hlt() {
     // Safety: Caller must guarantee this does not cause a deadlock.
@@ -57,3 +129,11 @@ pub fn __mfence() {
         core::arch::asm!("mfence", options(nostack, nomem, preserves_flags));
     }
 }
+
+#[inline(always)]
+pub fn __wbinvd() {
+    // Safety: `wbinvd` writes back and invalidates the caches, which has no effect on program state.
+    unsafe {
+        core::arch::asm!("wbinvd", options(nostack, preserves_flags));
+    }
+}
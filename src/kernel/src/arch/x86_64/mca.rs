@@ -0,0 +1,352 @@
+//! Machine check architecture (MCA) error reporting.
+//!
+//! Hardware errors are logged to error reporting banks, each of which covers some unit of the processor (a cache,
+//! the memory controller, the interconnect, ...), and those which can't be corrected raise a `#MC`. On a `#MC`, the
+//! error logged to every bank is decoded into a [`MachineCheckReport`], and execution only continues if the
+//! processor reports it can (see [`MachineCheckReport::is_recoverable`]).
+
+use crate::arch::x86_64::{
+    cpuid::{feature_info, vendor_info},
+    registers::model_specific::{IA32_MCG_CAP, IA32_MCG_CTL, IA32_MCG_STATUS, IA32_MCi},
+};
+use bit_field::BitField;
+
+bitflags! {
+    /// Machine check state of the hardware thread (`IA32_MCG_STATUS`).
+    #[repr(transparent)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct GlobalStatus: u64 {
+        /// Execution can be restarted at the interrupted instruction pointer.
+        const RIPV = 1 << 0;
+        /// The interrupted instruction pointer is that of the instruction which caused the error.
+        const EIPV = 1 << 1;
+        /// A machine check is in progress; another before this is cleared shuts the processor down.
+        const MCIP = 1 << 2;
+        /// The machine check was delivered to this hardware thread only.
+        const LMCE_S = 1 << 3;
+    }
+}
+
+bitflags! {
+    /// Flags of the error logged to a bank (`IA32_MCi_STATUS`).
+    #[repr(transparent)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct BankStatus: u64 {
+        /// Recovery action is required before execution continues (only meaningful with `S`).
+        const AR = 1 << 55;
+        /// The error was signalled with a `#MC` for software to recover from.
+        const S = 1 << 56;
+        /// The processor's state may have been corrupted by the error; execution can't reliably continue.
+        const PCC = 1 << 57;
+        /// `IA32_MCi_ADDR` holds the address of the error.
+        const ADDRV = 1 << 58;
+        /// `IA32_MCi_MISC` holds additional information about the error.
+        const MISCV = 1 << 59;
+        /// Reporting of the error was enabled in `IA32_MCi_CTL`.
+        const EN = 1 << 60;
+        /// The error wasn't corrected.
+        const UC = 1 << 61;
+        /// Another error was logged while this one was still valid.
+        const OVER = 1 << 62;
+        /// The bank holds a logged error.
+        const VAL = 1 << 63;
+    }
+}
+
+/// Level of the cache (or TLB) an error occurred in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    L0,
+    L1,
+    L2,
+    Generic,
+}
+
+impl Level {
+    fn decode(code: u16) -> Self {
+        match code.get_bits(0..2) {
+            0 => Self::L0,
+            1 => Self::L1,
+            2 => Self::L2,
+            _ => Self::Generic,
+        }
+    }
+}
+
+/// Architectural error type, decoded from the MCA error code (bits 15:0 of `IA32_MCi_STATUS`).
+///
+/// This is defined by the following manual sections:
+///   * Intel Volume 3B: 16.9
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorType {
+    NoError,
+    Unclassified,
+    MicrocodeRomParity,
+    External,
+    FunctionalRedundancyCheck,
+    InternalParity,
+    SmmHandlerCodeAccess,
+    InternalTimer,
+    Io,
+    InternalUnclassified,
+    GenericCacheHierarchy(Level),
+    Tlb(Level),
+    MemoryController,
+    CacheHierarchy(Level),
+    BusInterconnect(Level),
+    Unknown(u16),
+}
+
+impl ErrorType {
+    pub fn decode(code: u16) -> Self {
+        match code {
+            0x0000 => return Self::NoError,
+            0x0001 => return Self::Unclassified,
+            0x0002 => return Self::MicrocodeRomParity,
+            0x0003 => return Self::External,
+            0x0004 => return Self::FunctionalRedundancyCheck,
+            0x0005 => return Self::InternalParity,
+            0x0006 => return Self::SmmHandlerCodeAccess,
+            0x0400 => return Self::InternalTimer,
+            0x0E0B => return Self::Io,
+            code if (code & 0xFC00) == 0x0400 => return Self::InternalUnclassified,
+            _ => {}
+        }
+
+        // Compound error codes may set bit 12, which only reports whether corrected errors are being filtered.
+        let compound = code & !(1 << 12);
+        if (compound & 0xEFFC) == 0x000C {
+            Self::GenericCacheHierarchy(Level::decode(compound))
+        } else if (compound & 0xEFF0) == 0x0010 {
+            Self::Tlb(Level::decode(compound))
+        } else if (compound & 0xEF80) == 0x0080 {
+            Self::MemoryController
+        } else if (compound & 0xEF00) == 0x0100 {
+            Self::CacheHierarchy(Level::decode(compound))
+        } else if (compound & 0xE800) == 0x0800 {
+            Self::BusInterconnect(Level::decode(compound))
+        } else {
+            Self::Unknown(code)
+        }
+    }
+}
+
+/// Error logged to a single bank.
+#[derive(Debug, Clone, Copy)]
+pub struct BankError {
+    pub bank: u8,
+    pub status: u64,
+    /// Address of the error, if the bank logged one.
+    pub address: Option<u64>,
+    /// Additional model-specific information, if the bank logged any.
+    pub misc: Option<u64>,
+}
+
+impl BankError {
+    /// Reads the error logged to `bank`, if there is one.
+    fn read(bank: u8) -> Option<Self> {
+        let status = IA32_MCi::read_status(bank);
+        let flags = BankStatus::from_bits_truncate(status);
+        if !flags.contains(BankStatus::VAL) {
+            return None;
+        }
+
+        Some(Self {
+            bank,
+            status,
+            address: flags
+                .contains(BankStatus::ADDRV)
+                .then(|| IA32_MCi::read_addr(bank)),
+            misc: flags
+                .contains(BankStatus::MISCV)
+                .then(|| IA32_MCi::read_misc(bank)),
+        })
+    }
+
+    pub fn flags(&self) -> BankStatus {
+        BankStatus::from_bits_truncate(self.status)
+    }
+
+    pub fn error_type(&self) -> ErrorType {
+        ErrorType::decode(self.status.get_bits(0..16).try_into().unwrap())
+    }
+
+    /// Whether execution can't continue past the error: the processor context is corrupt, the error overflowed
+    /// another uncorrected one, or it was signalled as uncorrected without being marked as needing no action (i.e.
+    /// anything but a corrected error, an uncorrected error which needed no action, or an action-optional one).
+    pub fn is_fatal(&self) -> bool {
+        let flags = self.flags();
+
+        if flags.contains(BankStatus::PCC) {
+            return true;
+        }
+
+        if !flags.contains(BankStatus::UC) {
+            return false;
+        }
+
+        let is_action_optional = flags.contains(BankStatus::S) && !flags.contains(BankStatus::AR);
+
+        flags.contains(BankStatus::OVER) || (flags.contains(BankStatus::EN) && !is_action_optional)
+    }
+}
+
+impl core::fmt::Display for BankError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let flags = self.flags();
+        let correction = if flags.contains(BankStatus::UC) {
+            "uncorrected"
+        } else {
+            "corrected"
+        };
+
+        write!(
+            f,
+            "bank {}: {:?} ({correction}",
+            self.bank,
+            self.error_type()
+        )?;
+
+        if flags.contains(BankStatus::PCC) {
+            f.write_str(", processor context corrupt")?;
+        }
+
+        if flags.contains(BankStatus::OVER) {
+            f.write_str(", overflowed")?;
+        }
+
+        f.write_str(")")?;
+
+        if let Some(address) = self.address {
+            write!(f, " address: {address:#X}")?;
+        }
+
+        if let Some(misc) = self.misc {
+            write!(f, " misc: {misc:#X}")?;
+        }
+
+        write!(f, " status: {:#018X}", self.status)
+    }
+}
+
+/// Machine check state read on `#MC`.
+#[derive(Debug, Clone, Copy)]
+pub struct MachineCheckReport {
+    pub status: GlobalStatus,
+    bank_count: u8,
+}
+
+impl MachineCheckReport {
+    pub fn read() -> Self {
+        Self {
+            status: GlobalStatus::from_bits_truncate(IA32_MCG_STATUS::read()),
+            bank_count: if is_supported() {
+                IA32_MCG_CAP::get_bank_count()
+            } else {
+                0
+            },
+        }
+    }
+
+    /// Errors logged to the banks.
+    ///
+    /// # Remarks
+    ///
+    /// The banks are read each time this is iterated, so errors logged after [`Self::read`] are included too.
+    pub fn errors(&self) -> impl Iterator<Item = BankError> {
+        (0..self.bank_count).filter_map(BankError::read)
+    }
+
+    /// Whether execution can continue at the interrupted instruction pointer.
+    pub fn is_recoverable(&self) -> bool {
+        self.status.contains(GlobalStatus::RIPV) && !self.errors().any(|error| error.is_fatal())
+    }
+
+    /// Clears the logged errors, and the machine check in progress, so another machine check can be taken.
+    pub fn clear(&self) {
+        for error in self.errors() {
+            IA32_MCi::write_status(error.bank, 0);
+        }
+
+        IA32_MCG_STATUS::write(0);
+    }
+}
+
+impl core::fmt::Display for MachineCheckReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "  machine check status: {:?}", self.status)?;
+
+        for error in self.errors() {
+            write!(f, "\n  {error}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the current hardware thread has error reporting banks.
+pub fn is_supported() -> bool {
+    feature_info().is_some_and(|info| info.has_mce() && info.has_mca())
+}
+
+/// Whether bank 0's control register is left to firmware, as on Intel's P6 family processors.
+fn is_bank_0_firmware_controlled() -> bool {
+    vendor_info() == "GenuineIntel"
+        && feature_info().is_some_and(|info| info.family_id() == 0x6 && info.model_id() < 0x1A)
+}
+
+/// Enables error reporting for every bank on the current hardware thread, reporting (and clearing) any errors they
+/// logged before boot.
+///
+/// # Safety
+///
+/// This must be called before `CR4.MCE` is set.
+pub unsafe fn configure() {
+    if !is_supported() {
+        return;
+    }
+
+    let bank_count = IA32_MCG_CAP::get_bank_count();
+    trace!("Configuring {bank_count} machine check banks...");
+
+    if IA32_MCG_CAP::get_has_global_control() {
+        IA32_MCG_CTL::write(u64::MAX);
+    }
+
+    for bank in 0..bank_count {
+        if bank > 0 || !is_bank_0_firmware_controlled() {
+            IA32_MCi::write_ctl(bank, u64::MAX);
+        }
+
+        if let Some(error) = BankError::read(bank) {
+            warn!("Machine check logged before boot: {error}");
+        }
+
+        IA32_MCi::write_status(bank, 0);
+    }
+}
+
+crate::kernel_test! {
+    /// MCA error codes decode to their architectural error types.
+    fn decodes_error_codes() -> crate::tests::Outcome {
+        let cases = [
+            (0x0000, ErrorType::NoError),
+            (0x0400, ErrorType::InternalTimer),
+            (0x0401, ErrorType::InternalUnclassified),
+            (0x0E0B, ErrorType::Io),
+            (0x000E, ErrorType::GenericCacheHierarchy(Level::L2)),
+            (0x0011, ErrorType::Tlb(Level::L1)),
+            (0x009F, ErrorType::MemoryController),
+            (0x0136, ErrorType::CacheHierarchy(Level::L2)),
+            // The corrected error filtering bit is ignored.
+            (0x1136, ErrorType::CacheHierarchy(Level::L2)),
+            (0x0E0F, ErrorType::BusInterconnect(Level::Generic)),
+        ];
+
+        if cases.iter().any(|(code, error_type)| ErrorType::decode(*code) != *error_type) {
+            return Err("error code decoded to the wrong error type");
+        }
+
+        Ok(())
+    }
+}
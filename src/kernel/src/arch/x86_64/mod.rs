@@ -2,15 +2,21 @@ use raw_cpuid::{ExtendedFeatures, ExtendedProcessorFeatureIdentifiers, FeatureIn
 
 use crate::arch::x86_64::{
     cpuid::{extended_feature_identifiers, extended_feature_info, feature_info},
-    devices::x2apic::x2Apic,
+    devices::local_apic::LocalApic,
     structures::{gdt::GlobalDescriptorTable, idt::InterruptDescriptorTable},
 };
 
+pub mod cet;
 pub mod cpuid;
 pub mod devices;
 pub mod instructions;
+pub mod mca;
+pub mod mtrr;
+pub mod pat;
+pub mod pmu;
 pub mod registers;
 pub mod structures;
+pub mod xsave;
 
 /// # Safety
 ///
@@ -20,7 +26,7 @@ pub mod structures;
 pub unsafe fn configure_hwthread() {
     use registers::{
         control::{CR0, CR0Flags, CR4, CR4Flags},
-        model_specific::IA32_EFER,
+        model_specific::{IA32_APIC_BASE, IA32_EFER},
     };
 
     trace!("Configuring `CR0`...");
@@ -36,6 +42,12 @@ pub unsafe fn configure_hwthread() {
 
     let mut cr4_flags = CR4Flags::PAE | CR4Flags::PGE | CR4Flags::OSXMMEXCPT;
 
+    // The paging mode can't change while paging is enabled (writing `CR4.LA57` would raise a `#GP`), so whichever the
+    // bootloader chose is kept.
+    if CR4::read().contains(CR4Flags::LA57) {
+        cr4_flags.insert(CR4Flags::LA57);
+    }
+
     if feature_info().is_some_and(FeatureInfo::has_de) {
         cr4_flags.insert(CR4Flags::DE);
     }
@@ -60,6 +72,10 @@ pub unsafe fn configure_hwthread() {
         cr4_flags.insert(CR4Flags::FSGSBASE);
     }
 
+    if xsave::is_supported() {
+        cr4_flags.insert(CR4Flags::OSXSAVE);
+    }
+
     if extended_feature_info().is_some_and(ExtendedFeatures::has_smep) {
         cr4_flags.insert(CR4Flags::SMEP);
     }
@@ -68,11 +84,36 @@ pub unsafe fn configure_hwthread() {
         cr4_flags.insert(CR4Flags::SMAP);
     }
 
+    // Shadow stacks aren't enabled until the hardware thread is on its kernel stack (see `cet::enable`).
+    if cet::is_supported() {
+        cr4_flags.insert(CR4Flags::CET);
+    }
+
+    // Safety: `CR4.MCE` hasn't been set yet.
+    unsafe {
+        mca::configure();
+    }
+
+    // Safety: The hardware thread is being configured, so nothing relies on the PAT's layout yet.
+    unsafe {
+        pat::configure();
+    }
+
+    // Firmware programs every hardware thread's MTRRs identically, so they're only reported once.
+    if IA32_APIC_BASE::get_is_bsp() {
+        mtrr::report();
+    }
+
     // Safety:  Initialize the CR4 register with all CPU & kernel supported features.
     unsafe {
         CR4::write(cr4_flags);
     }
 
+    // Safety: `CR4.OSXSAVE` has been set if XSAVE is supported, and no task has run yet.
+    unsafe {
+        xsave::configure();
+    }
+
     trace!("Configuring `IA32_EFER.NXE`...");
 
     // Enable use of the `NO_EXECUTE` page attribute, if supported.
@@ -106,12 +147,9 @@ pub unsafe fn configure_hwthread() {
 ///
 /// # Remarks
 ///
-/// Currently, this effectively just reads the 32-bit ID provided by the x2APIC
-/// controller. In the future, obviously there is interest in supporting identification
-/// of more diverse hardware layouts than just assuming a flat CPU model that disregards
-/// even hyper-threading (which is all but ubiquitous in 2025, at time of writing). So,
-/// it can be expected that in the future, this function will change significantly.
+/// This reads the 32-bit ID provided by the x2APIC controller, which is flat; see
+/// [`crate::cpu::topology`] for which package and core the hardware thread belongs to.
 #[allow(clippy::map_unwrap_or)]
 pub fn get_hwthread_id() -> u32 {
-    x2Apic::get_id()
+    LocalApic::get_id()
 }
@@ -0,0 +1,257 @@
+//! Memory type range registers (MTRRs), with which firmware types ranges of physical memory.
+//!
+//! A page's memory type combines the type the PAT selects for it with the MTRR type of its physical memory: the
+//! write-combining and uncacheable PAT types override the MTRRs, but write-back defers to them. So memory the
+//! kernel maps write-back (i.e. all ordinary memory) is only as fast as the MTRRs allow, and firmware which leaves
+//! some of it uncacheable makes everything using it mysteriously slow; see [`check`].
+
+use crate::arch::x86_64::{
+    cpuid::feature_info,
+    pat::MemoryType,
+    registers::model_specific::{IA32_MTRR_DEF_TYPE, IA32_MTRR_FIX, IA32_MTRR_PHYSi, IA32_MTRRCAP},
+};
+use bit_field::BitField;
+use core::ops::Range;
+
+/// End of the physical memory typed by the fixed ranges.
+const FIXED_END: u64 = 0x10_0000;
+/// Number of fixed range registers.
+const FIXED_REGISTERS: usize = 11;
+/// Number of ranges each fixed range register types.
+const RANGES_PER_FIXED_REGISTER: u64 = 8;
+
+/// Start of the physical memory typed by the fixed range register at `register`, and the size of each of its
+/// ranges.
+fn fixed_register_layout(register: usize) -> (u64, u64) {
+    match register {
+        0 => (0x0_0000, 0x1_0000),
+        1 => (0x8_0000, 0x4000),
+        2 => (0xA_0000, 0x4000),
+        register => (
+            0xC_0000 + ((u64::try_from(register).unwrap() - 3) * 0x8000),
+            0x1000,
+        ),
+    }
+}
+
+/// Finds the fixed range register, and the range within it, which types `address` (below 1MiB).
+fn fixed_location(address: u64) -> Option<(usize, u64)> {
+    (0..FIXED_REGISTERS).find_map(|register| {
+        let (start, size) = fixed_register_layout(register);
+        let offset = address.checked_sub(start)?;
+
+        (offset < (size * RANGES_PER_FIXED_REGISTER)).then_some((register, offset / size))
+    })
+}
+
+/// Variable range, typing the physical addresses which match its base under its mask.
+#[derive(Debug, Clone, Copy)]
+pub struct VariableRange {
+    pub base: u64,
+    pub mask: u64,
+    pub memory_type: Option<MemoryType>,
+}
+
+impl VariableRange {
+    /// Reads the variable range at `index`, if it's enabled.
+    fn read(index: u8) -> Option<Self> {
+        let mask = IA32_MTRR_PHYSi::read_mask(index);
+        if !mask.get_bit(11) {
+            return None;
+        }
+
+        let base = IA32_MTRR_PHYSi::read_base(index);
+
+        Some(Self {
+            base: base & !0xFFF,
+            mask: mask & !0xFFF,
+            memory_type: u8::try_from(base.get_bits(0..8))
+                .ok()
+                .and_then(|memory_type| MemoryType::try_from(memory_type).ok()),
+        })
+    }
+
+    /// Size of the range, assuming its mask is contiguous (as firmware's always are).
+    pub fn size(&self) -> u64 {
+        1 << self.mask.trailing_zeros()
+    }
+
+    pub fn contains(&self, address: u64) -> bool {
+        (address & self.mask) == (self.base & self.mask)
+    }
+}
+
+/// Whether the current hardware thread has MTRRs.
+pub fn is_supported() -> bool {
+    feature_info().is_some_and(raw_cpuid::FeatureInfo::has_mtrr)
+}
+
+fn default_type() -> Option<MemoryType> {
+    MemoryType::try_from(IA32_MTRR_DEF_TYPE::get_default_type()).ok()
+}
+
+fn has_fixed_ranges() -> bool {
+    IA32_MTRRCAP::get_has_fixed() && IA32_MTRR_DEF_TYPE::get_fixed_enabled()
+}
+
+fn fixed_type(register: usize, range: u64) -> Option<MemoryType> {
+    let memory_type = IA32_MTRR_FIX::read(register).to_le_bytes()[usize::try_from(range).unwrap()];
+
+    MemoryType::try_from(memory_type).ok()
+}
+
+/// Iterates the enabled variable ranges.
+pub fn variable_ranges() -> impl Iterator<Item = VariableRange> {
+    (0..IA32_MTRRCAP::get_variable_count()).filter_map(VariableRange::read)
+}
+
+/// MTRR type of the physical memory at `address`, or `None` if there are no MTRRs (or it's typed with a reserved
+/// type).
+///
+/// # Remarks
+///
+/// Where variable ranges overlap, uncacheable wins, then write-through over write-back; any other overlap is
+/// undefined, so the lowest range's type is reported.
+pub fn memory_type(address: u64) -> Option<MemoryType> {
+    if !is_supported() {
+        return None;
+    }
+
+    if !IA32_MTRR_DEF_TYPE::get_enabled() {
+        return Some(MemoryType::Uncacheable);
+    }
+
+    if address < FIXED_END && has_fixed_ranges() {
+        let (register, range) = fixed_location(address)?;
+
+        return fixed_type(register, range);
+    }
+
+    variable_ranges()
+        .filter(|range| range.contains(address))
+        .map(|range| range.memory_type)
+        .reduce(|combined, memory_type| match (combined?, memory_type?) {
+            (MemoryType::Uncacheable, _) | (_, MemoryType::Uncacheable) => {
+                Some(MemoryType::Uncacheable)
+            }
+            (MemoryType::WriteThrough, MemoryType::WriteBack)
+            | (MemoryType::WriteBack, MemoryType::WriteThrough) => Some(MemoryType::WriteThrough),
+            (combined, _) => Some(combined),
+        })
+        .unwrap_or_else(default_type)
+}
+
+/// Checks the physical memory in `range` has the MTRR type `expected` throughout, returning the first address
+/// (and its type) which doesn't.
+pub fn check(range: Range<u64>, expected: MemoryType) -> Option<(u64, MemoryType)> {
+    if !is_supported() || range.is_empty() {
+        return None;
+    }
+
+    // The type can only change at the start of a fixed or variable range, or just after the end of one.
+    let fixed_boundaries = (0..FIXED_REGISTERS)
+        .flat_map(|register| {
+            let (start, size) = fixed_register_layout(register);
+
+            (0..RANGES_PER_FIXED_REGISTER).map(move |range| start + (range * size))
+        })
+        .chain(core::iter::once(FIXED_END));
+    let variable_boundaries = variable_ranges()
+        .flat_map(|variable| [variable.base, variable.base.saturating_add(variable.size())]);
+
+    core::iter::once(range.start)
+        .chain(fixed_boundaries)
+        .chain(variable_boundaries)
+        .filter(|address| range.contains(address))
+        .filter_map(|address| Some((address, memory_type(address)?)))
+        .filter(|(_, memory_type)| *memory_type != expected)
+        .min_by_key(|(address, _)| *address)
+}
+
+/// Logs the MTRRs of the current hardware thread.
+pub fn report() {
+    if !is_supported() {
+        debug!("MTRRs are not supported.");
+        return;
+    }
+
+    if !IA32_MTRR_DEF_TYPE::get_enabled() {
+        warn!("MTRRs are disabled, so all memory is uncacheable.");
+        return;
+    }
+
+    debug!(
+        "MTRRs: default type {:?}, {} variable ranges, fixed ranges {}",
+        default_type(),
+        IA32_MTRRCAP::get_variable_count(),
+        if has_fixed_ranges() {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+
+    if has_fixed_ranges() {
+        // Consecutive fixed ranges of the same type are reported together.
+        let mut run: Option<(u64, u64, Option<MemoryType>)> = None;
+        let log_run = |(start, end, memory_type): (u64, u64, Option<MemoryType>)| {
+            debug!("  fixed {start:#07X}..{end:#07X} {memory_type:?}");
+        };
+
+        for register in 0..FIXED_REGISTERS {
+            let (register_start, size) = fixed_register_layout(register);
+
+            for range in 0..RANGES_PER_FIXED_REGISTER {
+                let start = register_start + (range * size);
+                let memory_type = fixed_type(register, range);
+
+                run = match run {
+                    Some((run_start, _, run_type)) if run_type == memory_type => {
+                        Some((run_start, start + size, run_type))
+                    }
+                    run => {
+                        if let Some(run) = run {
+                            log_run(run);
+                        }
+
+                        Some((start, start + size, memory_type))
+                    }
+                };
+            }
+        }
+
+        if let Some(run) = run {
+            log_run(run);
+        }
+    }
+
+    for (index, range) in variable_ranges().enumerate() {
+        debug!(
+            "  variable #{index} {:#X}..{:#X} {:?}",
+            range.base,
+            range.base.saturating_add(range.size()),
+            range.memory_type
+        );
+    }
+}
+
+crate::kernel_test! {
+    /// Addresses below 1MiB are located within the fixed range register which types them.
+    fn locates_fixed_ranges() -> crate::tests::Outcome {
+        let cases = [
+            (0x0_0000, Some((0, 0))),
+            (0x7_FFFF, Some((0, 7))),
+            (0x8_0000, Some((1, 0))),
+            (0xA_4000, Some((2, 1))),
+            (0xC_8000, Some((4, 0))),
+            (0xF_F000, Some((10, 7))),
+            (0x10_0000, None),
+        ];
+
+        if cases.iter().any(|(address, location)| fixed_location(*address) != *location) {
+            return Err("address located in the wrong fixed range");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+//! Page attribute table (PAT), which holds the memory types page table entries select between.
+//!
+//! A page's memory type is selected by its `PWT`, `PCD` and `PAT` bits, as an index into the table. The `PAT` bit is
+//! bit 7 for the smallest pages, but bit 12 for huge pages, so the kernel only uses the first four entries (which
+//! `PWT` and `PCD` select between alone); they're laid out so memory-mapped IO and framebuffers can be mapped with
+//! the same bits at every page size:
+//!
+//! | Index | `PCD` | `PWT` | Memory type     | [`TableEntryFlags`]  |
+//! |-------|-------|-------|-----------------|----------------------|
+//! | 0     | 0     | 0     | Write-back      |                      |
+//! | 1     | 0     | 1     | Write-combining | `WRITE_COMBINING`    |
+//! | 2     | 1     | 0     | Uncached-minus  |                      |
+//! | 3     | 1     | 1     | Uncacheable     | `UNCACHEABLE`        |
+//!
+//! [`TableEntryFlags`]: crate::mem::paging::TableEntryFlags
+
+use crate::arch::x86_64::{
+    cpuid::feature_info,
+    instructions::__wbinvd,
+    registers::{control::CR3, model_specific::IA32_PAT},
+};
+
+/// Memory type of a PAT entry.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+pub enum MemoryType {
+    Uncacheable = 0x00,
+    WriteCombining = 0x01,
+    WriteThrough = 0x04,
+    WriteProtected = 0x05,
+    WriteBack = 0x06,
+    /// Uncacheable, unless overridden by an MTRR as write-combining.
+    UncachedMinus = 0x07,
+}
+
+/// Layout the PAT is programmed with; the upper four entries (selected with the `PAT` bit) are unused by the kernel,
+/// and hold the remaining memory types.
+const LAYOUT: [MemoryType; 8] = [
+    MemoryType::WriteBack,
+    MemoryType::WriteCombining,
+    MemoryType::UncachedMinus,
+    MemoryType::Uncacheable,
+    MemoryType::WriteBack,
+    MemoryType::WriteThrough,
+    MemoryType::WriteProtected,
+    MemoryType::Uncacheable,
+];
+
+fn encode(layout: [MemoryType; 8]) -> u64 {
+    u64::from_le_bytes(layout.map(u8::from))
+}
+
+/// Whether the current hardware thread has a PAT.
+pub fn is_supported() -> bool {
+    feature_info().is_some_and(raw_cpuid::FeatureInfo::has_pat)
+}
+
+/// Memory type of the PAT entry at `index`, as the current hardware thread has it programmed.
+pub fn memory_type(index: usize) -> Option<MemoryType> {
+    let entry = IA32_PAT::read().to_le_bytes().get(index).copied()?;
+
+    MemoryType::try_from(entry & 0b111).ok()
+}
+
+/// Programs the PAT of the current hardware thread with the kernel's layout.
+///
+/// # Remarks
+///
+/// Without a PAT, the bits which select write-combining instead select write-through; which is slower, but correct.
+///
+/// # Safety
+///
+/// This must be called once per hardware thread, while it's configured (before anything has mapped memory which
+/// relies on the layout).
+pub unsafe fn configure() {
+    if !is_supported() {
+        return;
+    }
+
+    trace!("Configuring `IA32_PAT`...");
+
+    // Memory which the bootloader mapped with its own layout may be cached, or have TLB entries, with the memory type
+    // it had before; so both are flushed around the write (Intel Volume 3A: "Programming the PAT").
+    __wbinvd();
+    IA32_PAT::write(encode(LAYOUT));
+    __wbinvd();
+    CR3::refresh();
+}
+
+crate::kernel_test! {
+    /// The PAT holds the kernel's layout, so `WRITE_COMBINING` and `UNCACHEABLE` select their memory types.
+    fn programmed_layout() -> crate::tests::Outcome {
+        if !is_supported() {
+            return Ok(());
+        }
+
+        if memory_type(1) != Some(MemoryType::WriteCombining) {
+            return Err("PAT entry 1 is not write-combining");
+        }
+
+        if memory_type(3) != Some(MemoryType::Uncacheable) {
+            return Err("PAT entry 3 is not uncacheable");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+//! Architectural performance monitoring, as used by the sampling profiler.
+//!
+//! Unhalted core cycles are counted with fixed-function counter 1 where it's available, which leaves the
+//! general-purpose counters free; otherwise general-purpose counter 0 is programmed with the equivalent architectural
+//! event. Precise event based sampling (PEBS) is kept disabled for counter 0, as PEBS records overflows to the debug
+//! store rather than raising an interrupt.
+
+use crate::arch::x86_64::{
+    cpuid::{feature_info, performance_monitoring_info},
+    devices::local_apic::LocalApic,
+    registers::model_specific::{
+        IA32_FIXED_CTR_CTRL, IA32_FIXED_CTR1, IA32_MISC_ENABLE, IA32_PEBS_ENABLE,
+        IA32_PERF_GLOBAL_CTRL, IA32_PERF_GLOBAL_OVF_CTRL, IA32_PERF_GLOBAL_STATUS,
+        IA32_PERFEVTSEL0, IA32_PMC0,
+    },
+};
+use bit_field::BitField;
+use spin::Once;
+
+/// Largest period the counter can be reloaded for, as writes to general-purpose counters are sign-extended from 32
+/// bits.
+pub const MAX_PERIOD: u64 = 0x7FFF_FFFF;
+
+/// `UnHalted Core Cycles` architectural event.
+const CORE_CYCLES_EVENT: u8 = 0x3C;
+const CORE_CYCLES_UMASK: u8 = 0x00;
+
+/// `IA32_PERF_GLOBAL_STATUS` bit set when general-purpose counter 0 overflows.
+const PMC0_OVERFLOW_BIT: usize = 0;
+/// `IA32_PERF_GLOBAL_STATUS` bit set when fixed-function counter 1 overflows.
+const FIXED_CTR1_OVERFLOW_BIT: usize = 33;
+
+#[derive(Debug, Error)]
+pub enum Unsupported {
+    #[error("architectural performance monitoring is not reported by CPUID")]
+    NotReported,
+
+    #[error("architectural performance monitoring version {0} has no global counter control")]
+    Version(u8),
+
+    #[error("no general-purpose performance counters are available")]
+    NoCounters,
+
+    #[error("the unhalted core cycles event is unavailable")]
+    NoCycleEvent,
+
+    #[error("the local APIC has no performance counter vector")]
+    NoLocalVector,
+}
+
+/// Counter which unhalted core cycles are sampled with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    /// Fixed-function counter 1, which is `width` bits wide.
+    Fixed1 { width: u8 },
+    /// General-purpose counter 0.
+    Pmc0,
+}
+
+impl Counter {
+    /// Value to load the counter with, for it to overflow after `period` more cycles.
+    fn reload_value(self, period: u64) -> u64 {
+        match self {
+            Self::Fixed1 { width } => period.wrapping_neg() & ((1 << width) - 1),
+            Self::Pmc0 => period.wrapping_neg(),
+        }
+    }
+
+    fn overflow_bit(self) -> usize {
+        match self {
+            Self::Fixed1 { .. } => FIXED_CTR1_OVERFLOW_BIT,
+            Self::Pmc0 => PMC0_OVERFLOW_BIT,
+        }
+    }
+}
+
+/// Counter which [`start`] started, so the overflow interrupt can reload it.
+static COUNTER: Once<Counter> = Once::new();
+
+/// Checks the current hardware thread can sample unhalted core cycles, returning the counter to sample with.
+pub fn check_support() -> Result<Counter, Unsupported> {
+    // Hypervisors which don't virtualize the PMU report this leaf as absent, or zeroed.
+    let info = performance_monitoring_info().ok_or(Unsupported::NotReported)?;
+
+    match info.version_id() {
+        0 => return Err(Unsupported::NotReported),
+        version @ 1 => return Err(Unsupported::Version(version)),
+        _ => {}
+    }
+
+    if LocalApic::lvt_performance_counter().is_none() {
+        return Err(Unsupported::NoLocalVector);
+    }
+
+    // The fixed-function counters are reported by version 2 and later, and are always wide enough for `MAX_PERIOD`
+    // (unless CPUID is misreporting them).
+    let width = info.fixed_function_counters_bit_width();
+    if info.fixed_function_counters() > 1 && width >= 32 {
+        return Ok(Counter::Fixed1 { width });
+    }
+
+    if info.number_of_counters() == 0 {
+        return Err(Unsupported::NoCounters);
+    }
+
+    if info.is_core_cyc_ev_unavailable() {
+        return Err(Unsupported::NoCycleEvent);
+    }
+
+    Ok(Counter::Pmc0)
+}
+
+/// Disables PEBS for counter 0, if it's available, so every overflow raises an interrupt.
+fn disable_pebs() {
+    if feature_info().is_some_and(raw_cpuid::FeatureInfo::has_ds)
+        && !IA32_MISC_ENABLE::get_pebs_unavailable()
+    {
+        // Safety: PEBS is available.
+        unsafe {
+            if IA32_PEBS_ENABLE::get_pmc0() {
+                debug!("Disabling PEBS for performance counter 0.");
+                IA32_PEBS_ENABLE::set_pmc0(false);
+            }
+        }
+    }
+}
+
+/// Sets whether `counter` is counting, without changing its value.
+fn set_enabled(counter: Counter, enable: bool) {
+    match counter {
+        Counter::Fixed1 { .. } => {
+            IA32_PERF_GLOBAL_CTRL::set_fixed_ctr1(enable);
+            IA32_FIXED_CTR_CTRL::set_ctr1(enable);
+        }
+
+        Counter::Pmc0 => {
+            IA32_PERF_GLOBAL_CTRL::set_pmc0(enable);
+            IA32_PERFEVTSEL0::set_event(CORE_CYCLES_EVENT, CORE_CYCLES_UMASK, enable);
+        }
+    }
+}
+
+fn reload(counter: Counter, period: u64) {
+    let value = counter.reload_value(period);
+
+    match counter {
+        Counter::Fixed1 { .. } => IA32_FIXED_CTR1::set(value),
+        Counter::Pmc0 => IA32_PMC0::set(value),
+    }
+}
+
+/// Starts `counter` overflowing every `period` unhalted core cycles, raising the performance counter interrupt.
+///
+/// # Remarks
+///
+/// `counter` must have been returned by [`check_support`] (which is the same for every hardware thread), and
+/// `period` must be within `1..=MAX_PERIOD`.
+pub fn start(counter: Counter, period: u64) {
+    debug_assert!((1..=MAX_PERIOD).contains(&period));
+
+    let counter = *COUNTER.call_once(|| counter);
+
+    set_enabled(counter, false);
+    if counter == Counter::Pmc0 {
+        disable_pebs();
+    }
+
+    reload(counter, period);
+    IA32_PERF_GLOBAL_OVF_CTRL::clear(IA32_PERF_GLOBAL_STATUS::read());
+
+    if let Some(lvt_performance_counter) = LocalApic::lvt_performance_counter() {
+        lvt_performance_counter.set_masked(false);
+    }
+
+    set_enabled(counter, true);
+}
+
+/// Stops the sampling counter (if one was started), and masks the performance counter interrupt.
+pub fn stop() {
+    if let Some(lvt_performance_counter) = LocalApic::lvt_performance_counter() {
+        lvt_performance_counter.set_masked(true);
+    }
+
+    if let Some(counter) = COUNTER.get() {
+        set_enabled(*counter, false);
+    }
+}
+
+/// Acknowledges a performance counter interrupt, reloading the sampling counter for another `period` cycles. Returns
+/// whether the sampling counter overflowed (as opposed to another counter, or the debug store buffer).
+///
+/// # Remarks
+///
+/// The local APIC masks the performance counter vector when delivering the interrupt, so this unmasks it again.
+pub fn rearm(period: u64) -> bool {
+    let status = IA32_PERF_GLOBAL_STATUS::read();
+    let overflowed = COUNTER
+        .get()
+        .copied()
+        .filter(|counter| status.get_bit(counter.overflow_bit()))
+        .inspect(|counter| reload(*counter, period))
+        .is_some();
+
+    IA32_PERF_GLOBAL_OVF_CTRL::clear(status);
+
+    if let Some(lvt_performance_counter) = LocalApic::lvt_performance_counter() {
+        lvt_performance_counter.set_masked(false);
+    }
+
+    overflowed
+}
@@ -9,3 +9,6 @@ pub use cr3::*;
 
 mod cr4;
 pub use cr4::*;
+
+mod xcr0;
+pub use xcr0::*;
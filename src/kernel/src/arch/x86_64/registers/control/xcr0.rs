@@ -0,0 +1,58 @@
+bitflags! {
+    /// State components enabled for `xsave`/`xrstor` (and use by software).
+    #[repr(transparent)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct XCR0Flags: u64 {
+        const X87       = 1 << 0;
+        const SSE       = 1 << 1;
+        const AVX       = 1 << 2;
+        const BNDREG    = 1 << 3;
+        const BNDCSR    = 1 << 4;
+        const OPMASK    = 1 << 5;
+        const ZMM_HI256 = 1 << 6;
+        const HI16_ZMM  = 1 << 7;
+        const PKRU      = 1 << 9;
+    }
+}
+
+/// Extended control register 0, which is only accessible once `CR4.OSXSAVE` is set.
+pub struct XCR0;
+
+impl XCR0 {
+    pub fn read() -> XCR0Flags {
+        let value_low: u64;
+        let value_high: u64;
+
+        // Safety: Reading XCR0 has no side effects.
+        unsafe {
+            core::arch::asm!(
+                "xgetbv",
+                in("ecx") 0,
+                out("eax") value_low,
+                out("edx") value_high,
+                options(nostack, nomem, preserves_flags)
+            );
+        }
+
+        XCR0Flags::from_bits_truncate((value_high << 32) | value_low)
+    }
+
+    /// # Safety
+    ///
+    /// - `flags` must contain only components supported by the current CPU, and must include [`XCR0Flags::X87`].
+    /// - `flags` must not be updated while any task's saved state depends on the components enabled.
+    pub unsafe fn write(flags: XCR0Flags) {
+        let value = flags.bits();
+
+        // Safety: Caller is required to maintain safety invariants.
+        unsafe {
+            core::arch::asm!(
+                "xsetbv",
+                in("ecx") 0,
+                in("eax") value & 0xFFFF_FFFF,
+                in("edx") value >> 32,
+                options(nostack, nomem, preserves_flags)
+            );
+        }
+    }
+}
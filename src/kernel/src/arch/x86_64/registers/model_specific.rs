@@ -5,14 +5,9 @@
 //! It is *possible* that the current CPU doesn't support the MSR feature.
 //! In this case, well... all of this fails. And we're going to ignore that.
 
-use core::{num::NonZero, ptr::NonNull};
-
-use crate::{
-    arch::x86_64::{registers::RFlags, structures::gdt::SegmentSelector},
-    cpu::local_state::LocalState,
-};
+use crate::arch::x86_64::{registers::RFlags, structures::gdt::SegmentSelector};
 use bit_field::BitField;
-use libsys::{Address, Virtual};
+use libsys::{Address, Frame};
 
 /// # Safety
 ///
@@ -20,6 +15,12 @@ use libsys::{Address, Virtual};
 /// - Caller mu
 #[inline(always)]
 fn rdmsr<T: ModelSpecificRegister>() -> u64 {
+    rdmsr_at(T::REGISTER_ADDRESS)
+}
+
+/// Reads the MSR at `address`, for registers which are indexed rather than fixed (see [`rdmsr`]).
+#[inline(always)]
+fn rdmsr_at(address: u32) -> u64 {
     let value_low: u64;
     let value_high: u64;
 
@@ -27,7 +28,7 @@ fn rdmsr<T: ModelSpecificRegister>() -> u64 {
     unsafe {
         core::arch::asm!(
             "rdmsr",
-            in("ecx") T::REGISTER_ADDRESS,
+            in("ecx") address,
             out("eax") value_low,
             out("edx") value_high,
             options(nostack, nomem, preserves_flags)
@@ -43,6 +44,12 @@ fn rdmsr<T: ModelSpecificRegister>() -> u64 {
 /// * Caller must ensure writing the value to the MSR address will not result in undefined behaviour.
 #[inline(always)]
 fn wrmsr<T: ModelSpecificRegister>(value: u64) {
+    wrmsr_at(T::REGISTER_ADDRESS, value);
+}
+
+/// Writes the MSR at `address`, for registers which are indexed rather than fixed (see [`wrmsr`]).
+#[inline(always)]
+fn wrmsr_at(address: u32, value: u64) {
     let value_low = value & 0xFFFF_FFFF;
     let value_high = value >> 32;
 
@@ -50,7 +57,7 @@ fn wrmsr<T: ModelSpecificRegister>(value: u64) {
     unsafe {
         core::arch::asm!(
             "wrmsr",
-            in("ecx") T::REGISTER_ADDRESS,
+            in("ecx") address,
             in("eax") value_low,
             in("edx") value_high,
             options(nostack, nomem, preserves_flags)
@@ -62,7 +69,66 @@ trait ModelSpecificRegister {
     const REGISTER_ADDRESS: u32;
 }
 
-/// Contains the address to the [`LocalState`][crate::cpu::state::LocalState].
+/// Base of the FS segment, which userspace uses as its thread pointer (see [`crate::task::tls`]).
+pub struct IA32_FS_BASE;
+
+impl ModelSpecificRegister for IA32_FS_BASE {
+    const REGISTER_ADDRESS: u32 = 0xC0000100;
+}
+
+impl IA32_FS_BASE {
+    /// Whether `rdfsbase`/`wrfsbase` are enabled, which are cheaper than accessing the MSR.
+    fn has_fsgsbase() -> bool {
+        crate::arch::x86_64::cpuid::extended_feature_info()
+            .is_some_and(raw_cpuid::ExtendedFeatures::has_fsgsbase)
+    }
+
+    pub fn write(value: u64) {
+        if Self::has_fsgsbase() {
+            // Safety: `CR4.FSGSBASE` is set whenever it's supported, and the FS base isn't used by the kernel.
+            unsafe {
+                core::arch::asm!("wrfsbase {}", in(reg) value, options(nostack, nomem, preserves_flags));
+            }
+        } else {
+            wrmsr::<Self>(value);
+        }
+    }
+
+    pub fn read() -> u64 {
+        if Self::has_fsgsbase() {
+            let value: u64;
+
+            // Safety: `CR4.FSGSBASE` is set whenever it's supported.
+            unsafe {
+                core::arch::asm!("rdfsbase {}", out(reg) value, options(nostack, nomem, preserves_flags));
+            }
+
+            value
+        } else {
+            rdmsr::<Self>()
+        }
+    }
+}
+
+/// Base of the GS segment; holds the address of the current hardware thread's
+/// [`PerCpu`][crate::cpu::percpu::PerCpu] block while in the kernel.
+pub struct IA32_GS_BASE;
+
+impl ModelSpecificRegister for IA32_GS_BASE {
+    const REGISTER_ADDRESS: u32 = 0xC0000101;
+}
+
+impl IA32_GS_BASE {
+    pub fn write(value: u64) {
+        wrmsr::<Self>(value);
+    }
+
+    pub fn read() -> u64 {
+        rdmsr::<Self>()
+    }
+}
+
+/// Exchanged with [`IA32_GS_BASE`] by `swapgs`; holds userspace's GS base while in the kernel.
 pub struct IA32_KERNEL_GS_BASE;
 
 impl ModelSpecificRegister for IA32_KERNEL_GS_BASE {
@@ -70,15 +136,56 @@ impl ModelSpecificRegister for IA32_KERNEL_GS_BASE {
 }
 
 impl IA32_KERNEL_GS_BASE {
-    pub fn write(ptr: NonNull<LocalState>) {
-        wrmsr::<Self>(NonZero::<u64>::try_from(ptr.addr()).unwrap().get());
+    pub fn write(value: u64) {
+        wrmsr::<Self>(value);
+    }
+
+    pub fn read() -> u64 {
+        rdmsr::<Self>()
+    }
+}
+
+/// Shadow stack pointer loaded on an interrupt from userspace (without an IST), which must address a supervisor
+/// shadow stack token.
+pub struct IA32_PL0_SSP;
+
+impl ModelSpecificRegister for IA32_PL0_SSP {
+    const REGISTER_ADDRESS: u32 = 0x6A4;
+}
+
+impl IA32_PL0_SSP {
+    pub fn write(value: u64) {
+        wrmsr::<Self>(value);
+    }
+}
+
+/// Address of the table of shadow stack pointers loaded on an interrupt with an IST, indexed by IST number.
+pub struct IA32_INTERRUPT_SSP_TABLE_ADDR;
+
+impl ModelSpecificRegister for IA32_INTERRUPT_SSP_TABLE_ADDR {
+    const REGISTER_ADDRESS: u32 = 0x6A8;
+}
+
+impl IA32_INTERRUPT_SSP_TABLE_ADDR {
+    pub fn write(value: u64) {
+        wrmsr::<Self>(value);
     }
+}
 
-    pub fn read() -> Option<NonNull<LocalState>> {
-        usize::try_from(rdmsr::<Self>())
-            .ok()
-            .and_then(NonZero::new)
-            .map(NonNull::with_exposed_provenance)
+/// Page attribute table, holding the memory types which page table entries select between.
+pub struct IA32_PAT;
+
+impl ModelSpecificRegister for IA32_PAT {
+    const REGISTER_ADDRESS: u32 = 0x277;
+}
+
+impl IA32_PAT {
+    pub fn read() -> u64 {
+        rdmsr::<Self>()
+    }
+
+    pub fn write(value: u64) {
+        wrmsr::<Self>(value);
     }
 }
 
@@ -109,10 +216,9 @@ impl IA32_APIC_BASE {
         wrmsr::<Self>(*rdmsr::<Self>().set_bit(11, enable));
     }
 
-    /// Gets the base address of the local APIC.
-    pub fn get_base_address() -> Address<Virtual> {
-        let base_address = usize::try_from(rdmsr::<Self>())
-            .expect("could not convert `IA32_APIC_BASE` to `usize`");
+    /// Gets the frame holding the local APIC's memory-mapped registers (used in xAPIC mode).
+    pub fn get_base_frame() -> Address<Frame> {
+        let base_address = usize::try_from(rdmsr::<Self>().get_bits(12..52) << 12).unwrap();
 
         Address::new(base_address).expect("`IA32_APIC_BASE` returned an invalid address")
     }
@@ -228,3 +334,325 @@ impl IA32_TSC_DEADLINE {
         wrmsr::<Self>(value);
     }
 }
+
+/// General-purpose performance counter 0.
+pub struct IA32_PMC0;
+
+impl ModelSpecificRegister for IA32_PMC0 {
+    const REGISTER_ADDRESS: u32 = 0xC1;
+}
+
+impl IA32_PMC0 {
+    /// Sets the counter's value.
+    ///
+    /// # Remarks
+    ///
+    /// Only the low 32 bits are written, and are sign-extended to the width of the counter.
+    pub fn set(value: u64) {
+        wrmsr::<Self>(value);
+    }
+}
+
+/// Selects the event counted by [`IA32_PMC0`], and when it's counted.
+pub struct IA32_PERFEVTSEL0;
+
+impl ModelSpecificRegister for IA32_PERFEVTSEL0 {
+    const REGISTER_ADDRESS: u32 = 0x186;
+}
+
+impl IA32_PERFEVTSEL0 {
+    /// Counts `event` with `umask` at any privilege level, raising a performance monitoring interrupt on overflow.
+    pub fn set_event(event: u8, umask: u8, enable: bool) {
+        let mut value = 0u64;
+        value.set_bits(0..8, u64::from(event));
+        value.set_bits(8..16, u64::from(umask));
+        value.set_bit(16, true); // USR
+        value.set_bit(17, true); // OS
+        value.set_bit(20, true); // INT
+        value.set_bit(22, enable); // EN
+
+        wrmsr::<Self>(value);
+    }
+}
+
+/// Fixed-function performance counter 1, which counts unhalted core cycles.
+pub struct IA32_FIXED_CTR1;
+
+impl ModelSpecificRegister for IA32_FIXED_CTR1 {
+    const REGISTER_ADDRESS: u32 = 0x30A;
+}
+
+impl IA32_FIXED_CTR1 {
+    /// Sets the counter's value.
+    ///
+    /// # Remarks
+    ///
+    /// Unlike [`IA32_PMC0`], the full width of the counter is written, so `value` must fit within it.
+    pub fn set(value: u64) {
+        wrmsr::<Self>(value);
+    }
+}
+
+/// Controls when each fixed-function performance counter counts.
+pub struct IA32_FIXED_CTR_CTRL;
+
+impl ModelSpecificRegister for IA32_FIXED_CTR_CTRL {
+    const REGISTER_ADDRESS: u32 = 0x38D;
+}
+
+impl IA32_FIXED_CTR_CTRL {
+    /// Sets whether [`IA32_FIXED_CTR1`] counts at any privilege level, raising a performance monitoring interrupt on
+    /// overflow, leaving the other counters as they are.
+    pub fn set_ctr1(enable: bool) {
+        let mut value = rdmsr::<Self>();
+        value.set_bit(4, enable); // OS
+        value.set_bit(5, enable); // USR
+        value.set_bit(7, enable); // PMI
+
+        wrmsr::<Self>(value);
+    }
+}
+
+pub struct IA32_MISC_ENABLE;
+
+impl ModelSpecificRegister for IA32_MISC_ENABLE {
+    const REGISTER_ADDRESS: u32 = 0x1A0;
+}
+
+impl IA32_MISC_ENABLE {
+    /// Whether precise event based sampling is unavailable (and [`IA32_PEBS_ENABLE`] can't be accessed).
+    pub fn get_pebs_unavailable() -> bool {
+        rdmsr::<Self>().get_bit(12)
+    }
+}
+
+pub struct IA32_PEBS_ENABLE;
+
+impl ModelSpecificRegister for IA32_PEBS_ENABLE {
+    const REGISTER_ADDRESS: u32 = 0x3F1;
+}
+
+impl IA32_PEBS_ENABLE {
+    /// Gets whether precise event based sampling is enabled for [`IA32_PMC0`].
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure PEBS is available (see [`IA32_MISC_ENABLE::get_pebs_unavailable`]).
+    pub unsafe fn get_pmc0() -> bool {
+        rdmsr::<Self>().get_bit(0)
+    }
+
+    /// Sets whether precise event based sampling is enabled for [`IA32_PMC0`].
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure PEBS is available (see [`IA32_MISC_ENABLE::get_pebs_unavailable`]).
+    pub unsafe fn set_pmc0(enable: bool) {
+        wrmsr::<Self>(*rdmsr::<Self>().set_bit(0, enable));
+    }
+}
+
+/// Overflow status of the performance counters (architectural performance monitoring version 2 and later).
+pub struct IA32_PERF_GLOBAL_STATUS;
+
+impl ModelSpecificRegister for IA32_PERF_GLOBAL_STATUS {
+    const REGISTER_ADDRESS: u32 = 0x38E;
+}
+
+impl IA32_PERF_GLOBAL_STATUS {
+    pub fn read() -> u64 {
+        rdmsr::<Self>()
+    }
+}
+
+/// Global enable of the performance counters (architectural performance monitoring version 2 and later).
+pub struct IA32_PERF_GLOBAL_CTRL;
+
+impl ModelSpecificRegister for IA32_PERF_GLOBAL_CTRL {
+    const REGISTER_ADDRESS: u32 = 0x38F;
+}
+
+impl IA32_PERF_GLOBAL_CTRL {
+    /// Sets whether [`IA32_PMC0`] is enabled, leaving the other counters as they are.
+    pub fn set_pmc0(enable: bool) {
+        wrmsr::<Self>(*rdmsr::<Self>().set_bit(0, enable));
+    }
+
+    /// Sets whether [`IA32_FIXED_CTR1`] is enabled, leaving the other counters as they are.
+    pub fn set_fixed_ctr1(enable: bool) {
+        wrmsr::<Self>(*rdmsr::<Self>().set_bit(33, enable));
+    }
+}
+
+/// Clears bits of [`IA32_PERF_GLOBAL_STATUS`] (architectural performance monitoring version 2 and later).
+pub struct IA32_PERF_GLOBAL_OVF_CTRL;
+
+impl ModelSpecificRegister for IA32_PERF_GLOBAL_OVF_CTRL {
+    const REGISTER_ADDRESS: u32 = 0x390;
+}
+
+impl IA32_PERF_GLOBAL_OVF_CTRL {
+    /// Clears each status bit set in `status`.
+    pub fn clear(status: u64) {
+        wrmsr::<Self>(status);
+    }
+}
+
+/// Machine check capabilities: the number of error reporting banks, and which global registers are present.
+pub struct IA32_MCG_CAP;
+
+impl ModelSpecificRegister for IA32_MCG_CAP {
+    const REGISTER_ADDRESS: u32 = 0x179;
+}
+
+impl IA32_MCG_CAP {
+    /// Number of error reporting banks.
+    pub fn get_bank_count() -> u8 {
+        rdmsr::<Self>().get_bits(0..8).try_into().unwrap()
+    }
+
+    /// Whether [`IA32_MCG_CTL`] is present.
+    pub fn get_has_global_control() -> bool {
+        rdmsr::<Self>().get_bit(8)
+    }
+}
+
+/// Machine check state of the hardware thread, read on `#MC`.
+pub struct IA32_MCG_STATUS;
+
+impl ModelSpecificRegister for IA32_MCG_STATUS {
+    const REGISTER_ADDRESS: u32 = 0x17A;
+}
+
+impl IA32_MCG_STATUS {
+    pub fn read() -> u64 {
+        rdmsr::<Self>()
+    }
+
+    pub fn write(value: u64) {
+        wrmsr::<Self>(value);
+    }
+}
+
+/// Global enable of machine check reporting, if [`IA32_MCG_CAP::get_has_global_control`].
+pub struct IA32_MCG_CTL;
+
+impl ModelSpecificRegister for IA32_MCG_CTL {
+    const REGISTER_ADDRESS: u32 = 0x17B;
+}
+
+impl IA32_MCG_CTL {
+    pub fn write(value: u64) {
+        wrmsr::<Self>(value);
+    }
+}
+
+/// Error reporting bank registers, of which there are [`IA32_MCG_CAP::get_bank_count`]; each bank's `CTL`, `STATUS`,
+/// `ADDR` and `MISC` registers are consecutive.
+pub struct IA32_MCi;
+
+impl IA32_MCi {
+    const BASE_ADDRESS: u32 = 0x400;
+
+    fn address(bank: u8, register: u32) -> u32 {
+        Self::BASE_ADDRESS + (u32::from(bank) * 4) + register
+    }
+
+    pub fn write_ctl(bank: u8, value: u64) {
+        wrmsr_at(Self::address(bank, 0), value);
+    }
+
+    pub fn read_status(bank: u8) -> u64 {
+        rdmsr_at(Self::address(bank, 1))
+    }
+
+    pub fn write_status(bank: u8, value: u64) {
+        wrmsr_at(Self::address(bank, 1), value);
+    }
+
+    pub fn read_addr(bank: u8) -> u64 {
+        rdmsr_at(Self::address(bank, 2))
+    }
+
+    pub fn read_misc(bank: u8) -> u64 {
+        rdmsr_at(Self::address(bank, 3))
+    }
+}
+
+/// Memory type range register capabilities.
+pub struct IA32_MTRRCAP;
+
+impl ModelSpecificRegister for IA32_MTRRCAP {
+    const REGISTER_ADDRESS: u32 = 0xFE;
+}
+
+impl IA32_MTRRCAP {
+    /// Number of variable ranges.
+    pub fn get_variable_count() -> u8 {
+        rdmsr::<Self>().get_bits(0..8).try_into().unwrap()
+    }
+
+    /// Whether the fixed ranges are supported.
+    pub fn get_has_fixed() -> bool {
+        rdmsr::<Self>().get_bit(8)
+    }
+}
+
+/// Default memory type, and whether the memory type range registers are enabled.
+pub struct IA32_MTRR_DEF_TYPE;
+
+impl ModelSpecificRegister for IA32_MTRR_DEF_TYPE {
+    const REGISTER_ADDRESS: u32 = 0x2FF;
+}
+
+impl IA32_MTRR_DEF_TYPE {
+    /// Memory type of physical memory not covered by any range.
+    pub fn get_default_type() -> u8 {
+        rdmsr::<Self>().get_bits(0..8).try_into().unwrap()
+    }
+
+    /// Whether the fixed ranges are enabled (if the memory type range registers are).
+    pub fn get_fixed_enabled() -> bool {
+        rdmsr::<Self>().get_bit(10)
+    }
+
+    /// Whether the memory type range registers are enabled; if not, all physical memory is uncacheable.
+    pub fn get_enabled() -> bool {
+        rdmsr::<Self>().get_bit(11)
+    }
+}
+
+/// Variable memory type range registers, of which there are [`IA32_MTRRCAP::get_variable_count`]; each range's
+/// `PHYSBASE` and `PHYSMASK` registers are consecutive.
+pub struct IA32_MTRR_PHYSi;
+
+impl IA32_MTRR_PHYSi {
+    const BASE_ADDRESS: u32 = 0x200;
+
+    fn address(range: u8, register: u32) -> u32 {
+        Self::BASE_ADDRESS + (u32::from(range) * 2) + register
+    }
+
+    pub fn read_base(range: u8) -> u64 {
+        rdmsr_at(Self::address(range, 0))
+    }
+
+    pub fn read_mask(range: u8) -> u64 {
+        rdmsr_at(Self::address(range, 1))
+    }
+}
+
+/// Fixed memory type range registers, which each hold the memory types of 8 consecutive ranges below 1MiB: one of
+/// 64KiB ranges, two of 16KiB ranges, then eight of 4KiB ranges.
+pub struct IA32_MTRR_FIX;
+
+impl IA32_MTRR_FIX {
+    const ADDRESSES: [u32; 11] = [
+        0x250, 0x258, 0x259, 0x268, 0x269, 0x26A, 0x26B, 0x26C, 0x26D, 0x26E, 0x26F,
+    ];
+
+    /// Reads the `index`th fixed range register, in ascending order of address.
+    pub fn read(index: usize) -> u64 {
+        rdmsr_at(Self::ADDRESSES[index])
+    }
+}
@@ -1,14 +1,20 @@
-use crate::arch::x86_64::structures::{DescriptorTablePointer, tss::TaskStateSegment};
+use crate::arch::x86_64::{
+    registers::model_specific::IA32_GS_BASE,
+    structures::{DescriptorTablePointer, tss::TaskStateSegment},
+};
 use bit_field::BitField;
 use core::ops::Range;
 use spin::Once;
 
-pub static KCODE_SELECTOR: Once<SegmentSelector> = Once::new();
-pub static KDATA_SELECTOR: Once<SegmentSelector> = Once::new();
-pub static UDATA_SELECTOR: Once<SegmentSelector> = Once::new();
-pub static UCODE_SELECTOR: Once<SegmentSelector> = Once::new();
+crate::ro_after_init! {
+    pub static KCODE_SELECTOR: Once<SegmentSelector> = Once::new();
+    pub static KDATA_SELECTOR: Once<SegmentSelector> = Once::new();
+    pub static UDATA_SELECTOR: Once<SegmentSelector> = Once::new();
+    pub static UCODE_SELECTOR: Once<SegmentSelector> = Once::new();
+}
 
 crate::singleton! {
+    #[ro_after_init]
     #[derive(Debug, Clone)]
     #[repr(C, align(8))]
     pub GlobalDescriptorTable {
@@ -92,10 +98,12 @@ impl GlobalDescriptorTable {
         }
 
         trace!("Clearing extant segment registers...");
+        // Loading GS clears its base (see below), which already holds the per-CPU block.
+        let gs_base = IA32_GS_BASE::read();
+
         // Safety: While setting the ES & DS segment registers to null is perfectly safe, setting
         //         the FS & GS segment registers (on Intel only, not AMD) clears the respective
-        //         FS/GS base. Thus, it is imperative that this function not be run after the GS
-        //         base has been loaded with the CPU thread-local state structure pointer.
+        //         FS/GS base. The GS base is restored below, before anything can read through it.
         unsafe {
             // Because this is x86, everything is complicated. It's important we load the extra
             // data segment registers (FS/GS) with the null descriptors, because if they don't
@@ -125,14 +133,16 @@ impl GlobalDescriptorTable {
             );
         }
 
+        IA32_GS_BASE::write(gs_base);
+
         trace!("Finished loading static global descriptor table.");
     }
 
     /// # Safety
     ///
     /// - An invalid [`GlobalDescriptorTable`] could potentially make memory unreadable or unwriteable.
-    /// - This should be executed prior to any point when the FS/GS _BASE MSRs will
-    ///   be in use, as they are cleared when this function is run.
+    /// - Reloading the FS/GS segment registers afterwards clears their bases, so any base in use
+    ///   must be restored (as [`Self::load_static`] does for GS).
     unsafe fn load(&self) {
         use core::arch::asm;
 
@@ -237,6 +247,50 @@ impl core::fmt::Debug for SegmentSelector {
     }
 }
 
+/// Segment selectors loaded on the current hardware thread.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadedSelectors {
+    pub cs: SegmentSelector,
+    pub ds: SegmentSelector,
+    pub es: SegmentSelector,
+    pub fs: SegmentSelector,
+    pub gs: SegmentSelector,
+    pub ss: SegmentSelector,
+    /// Task register, selecting the active TSS.
+    pub tr: SegmentSelector,
+}
+
+impl LoadedSelectors {
+    pub fn read() -> Self {
+        macro_rules! read_selector {
+            ($instruction:literal) => {{
+                let value: u16;
+
+                // Safety: Reading a segment selector has no side effects.
+                unsafe {
+                    core::arch::asm!(
+                        $instruction,
+                        out(reg) value,
+                        options(nomem, nostack, preserves_flags)
+                    );
+                }
+
+                SegmentSelector(value)
+            }};
+        }
+
+        Self {
+            cs: read_selector!("mov {:x}, cs"),
+            ds: read_selector!("mov {:x}, ds"),
+            es: read_selector!("mov {:x}, es"),
+            fs: read_selector!("mov {:x}, fs"),
+            gs: read_selector!("mov {:x}, gs"),
+            ss: read_selector!("mov {:x}, ss"),
+            tr: read_selector!("str {:x}"),
+        }
+    }
+}
+
 /// Represents a protection ring level.
 #[repr(u16)]
 #[derive(Debug, TryFromPrimitive, IntoPrimitive, Copy, Clone, PartialEq, Eq, Hash)]
@@ -378,7 +432,7 @@ pub struct SystemSegmentDescriptor(u128);
 
 impl SystemSegmentDescriptor {
     const BASE_BIT_RANGE_1: Range<usize> = 16..32;
-    const BASE_BIT_RANGE_2: Range<usize> = 56..64;
+    const BASE_BIT_RANGE_2: Range<usize> = 32..40;
     const BASE_BIT_RANGE_3: Range<usize> = 56..64;
     const BASE_BIT_RANGE_4: Range<usize> = 64..96;
 
@@ -108,11 +108,19 @@ impl Entry {
         entry
     }
 
-    fn handler_addr(&self) -> u64 {
+    pub(super) fn handler_addr(&self) -> u64 {
         (u64::from(self.pointer_high) << 32)
             | (u64::from(self.pointer_middle) << 16)
             | u64::from(self.pointer_low)
     }
+
+    pub(super) fn is_present(&self) -> bool {
+        self.options.bits.get_bit(15)
+    }
+
+    pub(super) fn privilege_level(&self) -> PrivilegeLevel {
+        PrivilegeLevel::try_from(self.options.bits.get_bits(13..15)).unwrap()
+    }
 }
 
 /// Represents the 4 non-offset bytes of an IDT entry.
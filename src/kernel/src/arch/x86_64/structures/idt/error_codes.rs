@@ -92,10 +92,22 @@ impl SelectorErrorCode {
 
 impl core::fmt::Debug for SelectorErrorCode {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        if self.is_null() {
+            return f.write_str("Selector Error (null)");
+        }
+
         let mut s = f.debug_struct("Selector Error");
         s.field("external", &self.is_external());
         s.field("descriptor table", &self.table_kind());
-        s.field("index", &self.table_index());
+
+        match self.table_kind() {
+            // IDT references are to an interrupt vector, rather than a segment selector.
+            DescriptorTableKind::IDT => s.field("vector", &self.table_index()),
+            DescriptorTableKind::GDT | DescriptorTableKind::LDT => s
+                .field("index", &self.table_index())
+                .field("selector", &(self.0 & 0xFFFC)),
+        };
+
         s.finish()
     }
 }
@@ -112,3 +124,60 @@ pub enum DescriptorTableKind {
     /// Logical Descriptor Table.
     LDT,
 }
+
+/// The kind of control-flow violation a #CP was raised for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlProtectionKind {
+    /// A near `ret`'s return address differed from the shadow stack's.
+    NearRet,
+    /// A far `ret` or `iret`'s return address or code segment differed from the shadow stack's.
+    FarRetOrIret,
+    /// An indirect branch target wasn't an `endbr` instruction.
+    EndBranch,
+    /// `rstorssp` found an invalid restore token.
+    RstorSsp,
+    /// `setssbsy` found an invalid supervisor shadow stack token.
+    SetSsBsy,
+    /// A reserved kind.
+    Unknown(u16),
+}
+
+/// Describes a control protection (#CP) error code.
+///
+/// This structure is defined by the following manual sections:
+///   * Intel Volume 1: 17.2.1
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ControlProtectionErrorCode(u64);
+
+impl ControlProtectionErrorCode {
+    pub const fn new(code: u64) -> Self {
+        Self(code)
+    }
+
+    /// The kind of violation which raised the exception.
+    pub fn kind(self) -> ControlProtectionKind {
+        match self.0.get_bits(0..15) {
+            1 => ControlProtectionKind::NearRet,
+            2 => ControlProtectionKind::FarRetOrIret,
+            3 => ControlProtectionKind::EndBranch,
+            4 => ControlProtectionKind::RstorSsp,
+            5 => ControlProtectionKind::SetSsBsy,
+            kind => ControlProtectionKind::Unknown(kind.try_into().unwrap()),
+        }
+    }
+
+    /// Indicates whether the exception occurred within an SGX enclave.
+    pub fn is_enclave(self) -> bool {
+        self.0.get_bit(15)
+    }
+}
+
+impl core::fmt::Debug for ControlProtectionErrorCode {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Control Protection Error")
+            .field("kind", &self.kind())
+            .field("enclave", &self.is_enclave())
+            .finish()
+    }
+}
@@ -12,9 +12,13 @@ pub use isf::*;
 mod error_codes;
 pub use error_codes::*;
 
-use crate::arch::x86_64::structures::{DescriptorTablePointer, tss::InterruptStackTableIndex};
+use crate::{
+    arch::x86_64::structures::{DescriptorTablePointer, tss::InterruptStackTableIndex},
+    interrupts::{Vector, VectorKind},
+};
 
 crate::singleton! {
+    #[ro_after_init]
     /// An Interrupt Descriptor Table with 256 entries.
     ///
     /// The first 32 entries are used for CPU exceptions. These entries can be either accessed through
@@ -471,244 +475,31 @@ crate::singleton! {
                 },
                 simd_floating_point: Entry::new(__xm_stub.as_usize()),
                 virtualization: Entry::new(__ve_stub.as_usize()),
-                cp_protection_exception: Entry::missing(),
+                cp_protection_exception: Entry::new(__cp_stub.as_usize()),
                 _2: [Entry::missing(); _],
                 hv_injection_exception: Entry::missing(),
                 vmm_communication_exception: Entry::missing(),
                 security_exception: Entry::missing(),
                 _3: [Entry::missing(); _],
-                interrupts: [
-                    // Safety: Privilege level is set for coming FROM userspace (ring 3) for syscalls.
-                    unsafe {
-                        Entry::new_with_privilege(
-                            __irq_128_stub.as_usize(),
-                            super::gdt::PrivilegeLevel::Ring3,
-                        )
-                    },
-                    Entry::new(__irq_32_stub.as_usize()),
-                    Entry::new(__irq_33_stub.as_usize()),
-                    Entry::new(__irq_34_stub.as_usize()),
-                    Entry::new(__irq_35_stub.as_usize()),
-                    Entry::new(__irq_36_stub.as_usize()),
-                    Entry::new(__irq_37_stub.as_usize()),
-                    Entry::new(__irq_39_stub.as_usize()),
-                    Entry::new(__irq_38_stub.as_usize()),
-                    Entry::new(__irq_40_stub.as_usize()),
-                    Entry::new(__irq_41_stub.as_usize()),
-                    Entry::new(__irq_42_stub.as_usize()),
-                    Entry::new(__irq_43_stub.as_usize()),
-                    Entry::new(__irq_44_stub.as_usize()),
-                    Entry::new(__irq_45_stub.as_usize()),
-                    Entry::new(__irq_46_stub.as_usize()),
-                    Entry::new(__irq_47_stub.as_usize()),
-                    Entry::new(__irq_48_stub.as_usize()),
-                    Entry::new(__irq_49_stub.as_usize()),
-                    Entry::new(__irq_50_stub.as_usize()),
-                    Entry::new(__irq_51_stub.as_usize()),
-                    Entry::new(__irq_52_stub.as_usize()),
-                    Entry::new(__irq_53_stub.as_usize()),
-                    Entry::new(__irq_54_stub.as_usize()),
-                    Entry::new(__irq_55_stub.as_usize()),
-                    Entry::new(__irq_56_stub.as_usize()),
-                    Entry::new(__irq_57_stub.as_usize()),
-                    Entry::new(__irq_58_stub.as_usize()),
-                    Entry::new(__irq_59_stub.as_usize()),
-                    Entry::new(__irq_60_stub.as_usize()),
-                    Entry::new(__irq_61_stub.as_usize()),
-                    Entry::new(__irq_62_stub.as_usize()),
-                    Entry::new(__irq_63_stub.as_usize()),
-                    Entry::new(__irq_64_stub.as_usize()),
-                    Entry::new(__irq_65_stub.as_usize()),
-                    Entry::new(__irq_66_stub.as_usize()),
-                    Entry::new(__irq_67_stub.as_usize()),
-                    Entry::new(__irq_68_stub.as_usize()),
-                    Entry::new(__irq_69_stub.as_usize()),
-                    Entry::new(__irq_70_stub.as_usize()),
-                    Entry::new(__irq_71_stub.as_usize()),
-                    Entry::new(__irq_72_stub.as_usize()),
-                    Entry::new(__irq_73_stub.as_usize()),
-                    Entry::new(__irq_74_stub.as_usize()),
-                    Entry::new(__irq_75_stub.as_usize()),
-                    Entry::new(__irq_76_stub.as_usize()),
-                    Entry::new(__irq_77_stub.as_usize()),
-                    Entry::new(__irq_78_stub.as_usize()),
-                    Entry::new(__irq_79_stub.as_usize()),
-                    Entry::new(__irq_80_stub.as_usize()),
-                    Entry::new(__irq_81_stub.as_usize()),
-                    Entry::new(__irq_82_stub.as_usize()),
-                    Entry::new(__irq_83_stub.as_usize()),
-                    Entry::new(__irq_84_stub.as_usize()),
-                    Entry::new(__irq_85_stub.as_usize()),
-                    Entry::new(__irq_86_stub.as_usize()),
-                    Entry::new(__irq_87_stub.as_usize()),
-                    Entry::new(__irq_88_stub.as_usize()),
-                    Entry::new(__irq_89_stub.as_usize()),
-                    Entry::new(__irq_90_stub.as_usize()),
-                    Entry::new(__irq_91_stub.as_usize()),
-                    Entry::new(__irq_92_stub.as_usize()),
-                    Entry::new(__irq_93_stub.as_usize()),
-                    Entry::new(__irq_94_stub.as_usize()),
-                    Entry::new(__irq_95_stub.as_usize()),
-                    Entry::new(__irq_96_stub.as_usize()),
-                    Entry::new(__irq_97_stub.as_usize()),
-                    Entry::new(__irq_98_stub.as_usize()),
-                    Entry::new(__irq_99_stub.as_usize()),
-                    Entry::new(__irq_100_stub.as_usize()),
-                    Entry::new(__irq_101_stub.as_usize()),
-                    Entry::new(__irq_102_stub.as_usize()),
-                    Entry::new(__irq_103_stub.as_usize()),
-                    Entry::new(__irq_104_stub.as_usize()),
-                    Entry::new(__irq_105_stub.as_usize()),
-                    Entry::new(__irq_106_stub.as_usize()),
-                    Entry::new(__irq_107_stub.as_usize()),
-                    Entry::new(__irq_108_stub.as_usize()),
-                    Entry::new(__irq_109_stub.as_usize()),
-                    Entry::new(__irq_110_stub.as_usize()),
-                    Entry::new(__irq_111_stub.as_usize()),
-                    Entry::new(__irq_112_stub.as_usize()),
-                    Entry::new(__irq_113_stub.as_usize()),
-                    Entry::new(__irq_114_stub.as_usize()),
-                    Entry::new(__irq_115_stub.as_usize()),
-                    Entry::new(__irq_116_stub.as_usize()),
-                    Entry::new(__irq_117_stub.as_usize()),
-                    Entry::new(__irq_118_stub.as_usize()),
-                    Entry::new(__irq_119_stub.as_usize()),
-                    Entry::new(__irq_120_stub.as_usize()),
-                    Entry::new(__irq_121_stub.as_usize()),
-                    Entry::new(__irq_122_stub.as_usize()),
-                    Entry::new(__irq_123_stub.as_usize()),
-                    Entry::new(__irq_124_stub.as_usize()),
-                    Entry::new(__irq_125_stub.as_usize()),
-                    Entry::new(__irq_126_stub.as_usize()),
-                    Entry::new(__irq_127_stub.as_usize()),
-                    Entry::new(__irq_129_stub.as_usize()),
-                    Entry::new(__irq_130_stub.as_usize()),
-                    Entry::new(__irq_131_stub.as_usize()),
-                    Entry::new(__irq_132_stub.as_usize()),
-                    Entry::new(__irq_133_stub.as_usize()),
-                    Entry::new(__irq_134_stub.as_usize()),
-                    Entry::new(__irq_135_stub.as_usize()),
-                    Entry::new(__irq_136_stub.as_usize()),
-                    Entry::new(__irq_137_stub.as_usize()),
-                    Entry::new(__irq_138_stub.as_usize()),
-                    Entry::new(__irq_139_stub.as_usize()),
-                    Entry::new(__irq_140_stub.as_usize()),
-                    Entry::new(__irq_141_stub.as_usize()),
-                    Entry::new(__irq_142_stub.as_usize()),
-                    Entry::new(__irq_143_stub.as_usize()),
-                    Entry::new(__irq_144_stub.as_usize()),
-                    Entry::new(__irq_145_stub.as_usize()),
-                    Entry::new(__irq_146_stub.as_usize()),
-                    Entry::new(__irq_147_stub.as_usize()),
-                    Entry::new(__irq_148_stub.as_usize()),
-                    Entry::new(__irq_149_stub.as_usize()),
-                    Entry::new(__irq_150_stub.as_usize()),
-                    Entry::new(__irq_151_stub.as_usize()),
-                    Entry::new(__irq_152_stub.as_usize()),
-                    Entry::new(__irq_153_stub.as_usize()),
-                    Entry::new(__irq_154_stub.as_usize()),
-                    Entry::new(__irq_155_stub.as_usize()),
-                    Entry::new(__irq_156_stub.as_usize()),
-                    Entry::new(__irq_157_stub.as_usize()),
-                    Entry::new(__irq_158_stub.as_usize()),
-                    Entry::new(__irq_159_stub.as_usize()),
-                    Entry::new(__irq_160_stub.as_usize()),
-                    Entry::new(__irq_161_stub.as_usize()),
-                    Entry::new(__irq_162_stub.as_usize()),
-                    Entry::new(__irq_163_stub.as_usize()),
-                    Entry::new(__irq_164_stub.as_usize()),
-                    Entry::new(__irq_165_stub.as_usize()),
-                    Entry::new(__irq_166_stub.as_usize()),
-                    Entry::new(__irq_167_stub.as_usize()),
-                    Entry::new(__irq_168_stub.as_usize()),
-                    Entry::new(__irq_169_stub.as_usize()),
-                    Entry::new(__irq_170_stub.as_usize()),
-                    Entry::new(__irq_171_stub.as_usize()),
-                    Entry::new(__irq_172_stub.as_usize()),
-                    Entry::new(__irq_173_stub.as_usize()),
-                    Entry::new(__irq_174_stub.as_usize()),
-                    Entry::new(__irq_175_stub.as_usize()),
-                    Entry::new(__irq_176_stub.as_usize()),
-                    Entry::new(__irq_177_stub.as_usize()),
-                    Entry::new(__irq_178_stub.as_usize()),
-                    Entry::new(__irq_179_stub.as_usize()),
-                    Entry::new(__irq_180_stub.as_usize()),
-                    Entry::new(__irq_181_stub.as_usize()),
-                    Entry::new(__irq_182_stub.as_usize()),
-                    Entry::new(__irq_183_stub.as_usize()),
-                    Entry::new(__irq_184_stub.as_usize()),
-                    Entry::new(__irq_185_stub.as_usize()),
-                    Entry::new(__irq_186_stub.as_usize()),
-                    Entry::new(__irq_187_stub.as_usize()),
-                    Entry::new(__irq_188_stub.as_usize()),
-                    Entry::new(__irq_189_stub.as_usize()),
-                    Entry::new(__irq_190_stub.as_usize()),
-                    Entry::new(__irq_191_stub.as_usize()),
-                    Entry::new(__irq_192_stub.as_usize()),
-                    Entry::new(__irq_193_stub.as_usize()),
-                    Entry::new(__irq_194_stub.as_usize()),
-                    Entry::new(__irq_195_stub.as_usize()),
-                    Entry::new(__irq_196_stub.as_usize()),
-                    Entry::new(__irq_197_stub.as_usize()),
-                    Entry::new(__irq_198_stub.as_usize()),
-                    Entry::new(__irq_199_stub.as_usize()),
-                    Entry::new(__irq_200_stub.as_usize()),
-                    Entry::new(__irq_201_stub.as_usize()),
-                    Entry::new(__irq_202_stub.as_usize()),
-                    Entry::new(__irq_203_stub.as_usize()),
-                    Entry::new(__irq_204_stub.as_usize()),
-                    Entry::new(__irq_205_stub.as_usize()),
-                    Entry::new(__irq_206_stub.as_usize()),
-                    Entry::new(__irq_207_stub.as_usize()),
-                    Entry::new(__irq_208_stub.as_usize()),
-                    Entry::new(__irq_209_stub.as_usize()),
-                    Entry::new(__irq_210_stub.as_usize()),
-                    Entry::new(__irq_211_stub.as_usize()),
-                    Entry::new(__irq_212_stub.as_usize()),
-                    Entry::new(__irq_213_stub.as_usize()),
-                    Entry::new(__irq_214_stub.as_usize()),
-                    Entry::new(__irq_215_stub.as_usize()),
-                    Entry::new(__irq_216_stub.as_usize()),
-                    Entry::new(__irq_217_stub.as_usize()),
-                    Entry::new(__irq_218_stub.as_usize()),
-                    Entry::new(__irq_219_stub.as_usize()),
-                    Entry::new(__irq_220_stub.as_usize()),
-                    Entry::new(__irq_221_stub.as_usize()),
-                    Entry::new(__irq_222_stub.as_usize()),
-                    Entry::new(__irq_223_stub.as_usize()),
-                    Entry::new(__irq_224_stub.as_usize()),
-                    Entry::new(__irq_225_stub.as_usize()),
-                    Entry::new(__irq_226_stub.as_usize()),
-                    Entry::new(__irq_227_stub.as_usize()),
-                    Entry::new(__irq_228_stub.as_usize()),
-                    Entry::new(__irq_229_stub.as_usize()),
-                    Entry::new(__irq_230_stub.as_usize()),
-                    Entry::new(__irq_231_stub.as_usize()),
-                    Entry::new(__irq_232_stub.as_usize()),
-                    Entry::new(__irq_233_stub.as_usize()),
-                    Entry::new(__irq_234_stub.as_usize()),
-                    Entry::new(__irq_235_stub.as_usize()),
-                    Entry::new(__irq_236_stub.as_usize()),
-                    Entry::new(__irq_237_stub.as_usize()),
-                    Entry::new(__irq_238_stub.as_usize()),
-                    Entry::new(__irq_239_stub.as_usize()),
-                    Entry::new(__irq_240_stub.as_usize()),
-                    Entry::new(__irq_241_stub.as_usize()),
-                    Entry::new(__irq_242_stub.as_usize()),
-                    Entry::new(__irq_243_stub.as_usize()),
-                    Entry::new(__irq_244_stub.as_usize()),
-                    Entry::new(__irq_245_stub.as_usize()),
-                    Entry::new(__irq_246_stub.as_usize()),
-                    Entry::new(__irq_247_stub.as_usize()),
-                    Entry::new(__irq_248_stub.as_usize()),
-                    Entry::new(__irq_249_stub.as_usize()),
-                    Entry::new(__irq_250_stub.as_usize()),
-                    Entry::new(__irq_251_stub.as_usize()),
-                    Entry::new(__irq_252_stub.as_usize()),
-                    Entry::new(__irq_253_stub.as_usize()),
-                    Entry::new(__irq_254_stub.as_usize()),
-                    Entry::new(__irq_255_stub.as_usize()),
-                ],
+                interrupts: core::array::from_fn(|index| {
+                    let vector = u8::try_from(index + 32).unwrap();
+                    let stub_address = __irq_stub_table[index];
+
+                    match crate::interrupts::vector_kind(vector) {
+                        VectorKind::Unassigned => Entry::missing(),
+
+                        // Safety: Privilege level is set for coming FROM userspace (ring 3) for syscalls.
+                        VectorKind::Fixed(Vector::Syscall) => unsafe {
+                            Entry::new_with_privilege(stub_address, super::gdt::PrivilegeLevel::Ring3)
+                        },
+
+                        VectorKind::Fixed(_) | VectorKind::Dynamic | VectorKind::LegacyPic => {
+                            Entry::new(stub_address)
+                        }
+
+                        VectorKind::Exception => unreachable!("exceptions are not interrupt vectors"),
+                    }
+                }),
             }
         }
     }
@@ -758,3 +549,46 @@ impl InterruptDescriptorTable {
         }
     }
 }
+
+crate::kernel_test! {
+    /// Interrupt vectors index their own entries, which are present (and point to their stubs) only if assigned.
+    fn indexing() -> crate::tests::Outcome {
+        let idt = InterruptDescriptorTable::get_static();
+
+        for vector in 32..=u8::MAX {
+            let entry = &idt[vector];
+            let index = usize::from(vector - 32);
+
+            if !core::ptr::eq(entry, &idt.interrupts[index]) {
+                return Err("vector indexes the wrong entry");
+            }
+
+            match crate::interrupts::vector_kind(vector) {
+                VectorKind::Unassigned if entry.is_present() => {
+                    return Err("unassigned vector has a present entry");
+                }
+
+                VectorKind::Unassigned => {}
+
+                _ => {
+                    // Safety: The stub table is only read.
+                    let stub_address = unsafe { __irq_stub_table[index] };
+
+                    if !entry.is_present() {
+                        return Err("assigned vector has no present entry");
+                    }
+
+                    if entry.handler_addr() != u64::try_from(stub_address).unwrap() {
+                        return Err("vector entry does not point to its stub");
+                    }
+                }
+            }
+        }
+
+        if idt[u8::from(Vector::Syscall)].privilege_level() != super::gdt::PrivilegeLevel::Ring3 {
+            return Err("system call vector is not reachable from userspace");
+        }
+
+        Ok(())
+    }
+}
@@ -1,163 +1,253 @@
 use crate::{
     LinkerSymbol,
     arch::x86_64::{
-        devices::x2apic::x2Apic,
-        structures::idt::{InterruptStackFrame, PageFaultErrorCode, SelectorErrorCode},
+        devices::{local_apic::LocalApic, pic8259},
+        mca::MachineCheckReport,
+        structures::{
+            gdt::PrivilegeLevel,
+            idt::{
+                ControlProtectionErrorCode, ControlProtectionKind, InterruptStackFrame,
+                PageFaultErrorCode, SelectorErrorCode,
+            },
+        },
     },
     cpu::local_state::LocalState,
     interrupts::{
-        Vector,
-        exceptions::{ArchException, handle},
+        Vector, VectorKind,
+        exceptions::{ArchException, Recovery, handle},
     },
     task::Registers,
 };
 
+/// Carries out the recovery action decided by the exception handler.
+fn recover(recovery: Recovery, stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) {
+    match recovery {
+        Recovery::Resume => {}
+        Recovery::Fault(address) => {
+            if crate::task::signals::deliver_fault(address, stack_frame, gprs) {
+                return;
+            }
+
+            LocalState::with_scheduler(|scheduler| {
+                scheduler.kill_task(crate::task::lifecycle::KILLED_CODE, stack_frame, gprs);
+            });
+        }
+    }
+}
+
 #[unsafe(no_mangle)]
-extern "sysv64" fn __de_handler(stack_frame: &InterruptStackFrame, gprs: &Registers) {
-    handle(&ArchException::DivideError(stack_frame, gprs));
+extern "sysv64" fn __de_handler(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) {
+    let recovery = handle(&ArchException::DivideError(stack_frame, gprs));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
-extern "sysv64" fn __db_handler(stack_frame: &InterruptStackFrame, gprs: &Registers) {
-    handle(&ArchException::Debug(stack_frame, gprs));
+extern "sysv64" fn __db_handler(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) {
+    if crate::debugger::gdb::on_debug(stack_frame, gprs) {
+        return;
+    }
+
+    let recovery = handle(&ArchException::Debug(stack_frame, gprs));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
-extern "sysv64" fn __nm_handler(stack_frame: &InterruptStackFrame, gprs: &Registers) {
-    handle(&ArchException::NonMaskable(stack_frame, gprs));
+extern "sysv64" fn __nm_handler(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) {
+    let recovery = handle(&ArchException::NonMaskable(stack_frame, gprs));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
-extern "sysv64" fn __bp_handler(stack_frame: &InterruptStackFrame, gprs: &Registers) {
-    handle(&ArchException::Breakpoint(stack_frame, gprs));
+extern "sysv64" fn __bp_handler(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) {
+    if crate::debugger::gdb::on_breakpoint(stack_frame, gprs) {
+        return;
+    }
+
+    let recovery = handle(&ArchException::Breakpoint(stack_frame, gprs));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
-extern "sysv64" fn __of_handler(stack_frame: &InterruptStackFrame, gprs: &Registers) {
-    handle(&ArchException::Overflow(stack_frame, gprs));
+extern "sysv64" fn __of_handler(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) {
+    let recovery = handle(&ArchException::Overflow(stack_frame, gprs));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
-extern "sysv64" fn __br_handler(stack_frame: &InterruptStackFrame, gprs: &Registers) {
-    handle(&ArchException::BoundRangeExceeded(stack_frame, gprs));
+extern "sysv64" fn __br_handler(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) {
+    let recovery = handle(&ArchException::BoundRangeExceeded(stack_frame, gprs));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
-extern "sysv64" fn __ud_handler(stack_frame: &InterruptStackFrame, gprs: &Registers) {
-    handle(&ArchException::InvalidOpcode(stack_frame, gprs));
+extern "sysv64" fn __ud_handler(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) {
+    let recovery = handle(&ArchException::InvalidOpcode(stack_frame, gprs));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
-extern "sysv64" fn __na_handler(stack_frame: &InterruptStackFrame, gprs: &Registers) {
-    handle(&ArchException::DeviceNotAvailable(stack_frame, gprs));
+extern "sysv64" fn __na_handler(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) {
+    let recovery = handle(&ArchException::DeviceNotAvailable(stack_frame, gprs));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
 extern "sysv64" fn __df_handler(stack_frame: &InterruptStackFrame, _: u64, gprs: &Registers) {
-    handle(&ArchException::DoubleFault(stack_frame, gprs));
+    let _ = handle(&ArchException::DoubleFault(stack_frame, gprs));
     unreachable!("#DF cannot be recovered from");
 }
 
 #[unsafe(no_mangle)]
 extern "sysv64" fn __ts_handler(
-    stack_frame: &InterruptStackFrame,
+    stack_frame: &mut InterruptStackFrame,
     error_code: u64,
-    gprs: &Registers,
+    gprs: &mut Registers,
 ) {
-    handle(&ArchException::InvalidTSS(
+    let recovery = handle(&ArchException::InvalidTSS(
         stack_frame,
         SelectorErrorCode::new(error_code).unwrap(),
         gprs,
     ));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
 extern "sysv64" fn __np_handler(
-    stack_frame: &InterruptStackFrame,
+    stack_frame: &mut InterruptStackFrame,
     error_code: u64,
-    gprs: &Registers,
+    gprs: &mut Registers,
 ) {
-    handle(&ArchException::SegmentNotPresent(
+    let recovery = handle(&ArchException::SegmentNotPresent(
         stack_frame,
         SelectorErrorCode::new(error_code).unwrap(),
         gprs,
     ));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
 extern "sysv64" fn __ss_handler(
-    stack_frame: &InterruptStackFrame,
+    stack_frame: &mut InterruptStackFrame,
     error_code: u64,
-    gprs: &Registers,
+    gprs: &mut Registers,
 ) {
-    handle(&ArchException::StackSegmentFault(
+    let recovery = handle(&ArchException::StackSegmentFault(
         stack_frame,
         SelectorErrorCode::new(error_code).unwrap(),
         gprs,
     ));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
 extern "sysv64" fn __gp_handler(
-    stack_frame: &InterruptStackFrame,
+    stack_frame: &mut InterruptStackFrame,
     error_code: u64,
-    gprs: &Registers,
+    gprs: &mut Registers,
 ) {
-    handle(&ArchException::GeneralProtectionFault(
+    let recovery = handle(&ArchException::GeneralProtectionFault(
         stack_frame,
         SelectorErrorCode::new(error_code).unwrap(),
         gprs,
     ));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
 extern "sysv64" fn __pf_handler(
-    stack_frame: &InterruptStackFrame,
+    stack_frame: &mut InterruptStackFrame,
     err: PageFaultErrorCode,
-    gprs: &Registers,
+    gprs: &mut Registers,
 ) {
-    handle(&ArchException::PageFault(
+    let recovery = handle(&ArchException::PageFault(
         stack_frame,
         gprs,
         err,
         crate::arch::x86_64::registers::control::CR2::read(),
     ));
+    recover(recovery, stack_frame, gprs);
 }
 
 // --- reserved 15
 
 #[unsafe(no_mangle)]
-extern "sysv64" fn __mf_handler(stack_frame: &InterruptStackFrame, gprs: &Registers) {
-    handle(&ArchException::x87FloatingPoint(stack_frame, gprs));
+extern "sysv64" fn __mf_handler(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) {
+    let recovery = handle(&ArchException::x87FloatingPoint(stack_frame, gprs));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
 extern "sysv64" fn __ac_handler(
-    stack_frame: &InterruptStackFrame,
+    stack_frame: &mut InterruptStackFrame,
     error_code: u64,
-    gprs: &Registers,
+    gprs: &mut Registers,
 ) {
-    handle(&ArchException::AlignmentCheck(
+    let recovery = handle(&ArchException::AlignmentCheck(
         stack_frame,
         error_code,
         gprs,
     ));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
-extern "sysv64" fn __mc_handler(stack_frame: &InterruptStackFrame, gprs: &Registers) {
-    handle(&ArchException::MachineCheck(stack_frame, gprs));
+extern "sysv64" fn __mc_handler(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) {
+    let report = MachineCheckReport::read();
+
+    if report.is_recoverable() {
+        warn!("Continuing after machine check:\n{report}");
+        report.clear();
+
+        return;
+    }
+
+    let _ = handle(&ArchException::MachineCheck(stack_frame, gprs, &report));
     unreachable!("#MC cannot be recovered");
 }
 
 #[unsafe(no_mangle)]
-extern "sysv64" fn __xm_handler(stack_frame: &InterruptStackFrame, gprs: &Registers) {
-    handle(&ArchException::SimdFlaotingPoint(stack_frame, gprs));
+extern "sysv64" fn __xm_handler(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) {
+    let recovery = handle(&ArchException::SimdFlaotingPoint(stack_frame, gprs));
+    recover(recovery, stack_frame, gprs);
 }
 
 #[unsafe(no_mangle)]
-extern "sysv64" fn __ve_handler(stack_frame: &InterruptStackFrame, gprs: &Registers) {
-    handle(&ArchException::Virtualization(stack_frame, gprs));
+extern "sysv64" fn __ve_handler(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) {
+    let recovery = handle(&ArchException::Virtualization(stack_frame, gprs));
+    recover(recovery, stack_frame, gprs);
+}
+
+#[unsafe(no_mangle)]
+extern "sysv64" fn __cp_handler(
+    stack_frame: &mut InterruptStackFrame,
+    error_code: u64,
+    gprs: &mut Registers,
+) {
+    // Read before anything else is pushed to the shadow stack, so it's just below the exception's shadow stack frame.
+    let handler_ssp = crate::arch::x86_64::cet::read_ssp();
+
+    let error_code = ControlProtectionErrorCode::new(error_code);
+    let mismatch = if error_code.kind() == ControlProtectionKind::NearRet
+        && stack_frame.get_code_segment().privilege_level() == PrivilegeLevel::Ring0
+    {
+        // Safety: The shadow stack pointer was read on entry, and a near `ret` in the kernel raised the exception.
+        unsafe {
+            crate::arch::x86_64::cet::read_return_mismatch(
+                stack_frame.get_stack_pointer().get(),
+                handler_ssp,
+            )
+        }
+    } else {
+        None
+    };
+
+    let recovery = handle(&ArchException::ControlProtection(
+        stack_frame,
+        error_code,
+        mismatch,
+        gprs,
+    ));
+    recover(recovery, stack_frame, gprs);
 }
 
 // --- reserved 22-30
@@ -169,39 +259,75 @@ extern "sysv64" fn __irq_handler(
     irq_number: u8,
     isf: &mut InterruptStackFrame,
     regs: &mut Registers,
+    timestamp: u64,
 ) {
+    // System calls are made from task context, so they don't count as interrupt context.
+    let is_interrupt = Vector::from(irq_number) != Vector::Syscall;
+    if is_interrupt {
+        LocalState::enter_interrupt();
+    }
+
+    let timestamp = crate::interrupts::irq::IrqTimestamp::from_ticks(timestamp);
+    crate::interrupts::irq::record(irq_number, timestamp);
+
+    let is_legacy_pic = crate::interrupts::vector_kind(irq_number) == VectorKind::LegacyPic;
+
     match Vector::from(irq_number) {
         Vector::Timer => {
-            LocalState::with_scheduler(|scheduler| {
-                scheduler.interrupt_task(isf, regs);
-            });
+            crate::time::vdso::publish();
+            crate::time::timers::process_expired();
+
+            if crate::task::workqueue::is_draining() {
+                // The tick interrupted deferred work, rather than a task.
+                crate::task::workqueue::defer_preemption();
+            } else {
+                LocalState::with_scheduler(|scheduler| {
+                    scheduler.interrupt_task(isf, regs);
+                });
+            }
+        }
+
+        Vector::Watchdog => {
+            crate::watchdog::pet();
+        }
+
+        Vector::TlbShootdown => {
+            crate::mem::tlb::service();
+        }
+
+        Vector::PerformanceCounter => {
+            crate::profiler::handle_overflow(isf.get_instruction_pointer());
         }
 
         Vector::Syscall => {
-            let vector = regs.rax;
-            let arg0 = regs.rdi;
-            let arg1 = regs.rsi;
-            let arg2 = regs.rdx;
-            let arg3 = regs.rcx;
-            let arg4 = regs.r8;
-            let arg5 = regs.r9;
-            let result = crate::interrupts::syscall::process(
-                vector, arg0, arg1, arg2, arg3, arg4, arg5, isf, regs,
-            );
-            let (rdi, rsi) =
-                <libsys::syscall::Result as libsys::syscall::ResultConverter>::into_registers(
-                    result,
-                );
-            regs.rdi = rdi;
-            regs.rsi = rsi;
+            crate::interrupts::syscall::process(isf, regs);
         }
-        vector => unimplemented!("unsupported interrupt vector: {vector:?}"),
+        _ if is_legacy_pic => pic8259::handle_interrupt(irq_number),
+
+        _ if crate::interrupts::irq::dispatch(irq_number, timestamp) => {}
+
+        vector => unimplemented!("unsupported interrupt vector: {vector:?} ({irq_number:#X})"),
     }
 
-    // Safety: This is the end of an interrupt context.
-    unsafe {
-        #[cfg(target_arch = "x86_64")]
-        x2Apic::end_of_interrupt();
+    // Legacy PIC interrupts are delivered as ExtINT, which the local APIC doesn't track as in service.
+    if !is_legacy_pic {
+        // Safety: This is the end of an interrupt context.
+        unsafe {
+            #[cfg(target_arch = "x86_64")]
+            LocalApic::end_of_interrupt();
+        }
+    }
+
+    if is_interrupt {
+        // Deferred work runs once the interrupt has been acknowledged, so other interrupts can be taken meanwhile.
+        crate::task::workqueue::run_pending(isf, regs);
+
+        LocalState::exit_interrupt();
+    }
+
+    // Pending events are delivered as the task returns to user mode, so they never interrupt the kernel.
+    if isf.get_code_segment().privilege_level() == PrivilegeLevel::Ring3 {
+        crate::task::signals::deliver_pending(isf, regs);
     }
 }
 
@@ -225,6 +351,7 @@ unsafe extern "C" {
     pub unsafe static __mc_stub: LinkerSymbol;
     pub unsafe static __xm_stub: LinkerSymbol;
     pub unsafe static __ve_stub: LinkerSymbol;
+    pub unsafe static __cp_stub: LinkerSymbol;
     pub unsafe static __irq_32_stub: LinkerSymbol;
     pub unsafe static __irq_33_stub: LinkerSymbol;
     pub unsafe static __irq_34_stub: LinkerSymbol;
@@ -449,12 +576,19 @@ unsafe extern "C" {
     pub unsafe static __irq_253_stub: LinkerSymbol;
     pub unsafe static __irq_254_stub: LinkerSymbol;
     pub unsafe static __irq_255_stub: LinkerSymbol;
+
+    /// Addresses of the IRQ stubs, indexed by `vector - 32`.
+    pub unsafe static __irq_stub_table: [usize; 224];
 }
 
 core::arch::global_asm! {
 "
 .global __de_stub
 __de_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -471,12 +605,12 @@ __de_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -484,6 +618,25 @@ __de_stub:
   lea rsi, [rsp + (2 * 8)]
   call __de_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -499,10 +652,18 @@ __de_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __db_stub
 __db_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -519,12 +680,12 @@ __db_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -532,6 +693,25 @@ __db_stub:
   lea rsi, [rsp + (2 * 8)]
   call __db_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -547,6 +727,10 @@ __db_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __nm_stub
@@ -567,12 +751,21 @@ __nm_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov ecx, 0xC0000101
+  rdmsr
+  shl rdx, 32
+  or rax, rdx
+  mov rbx, rax
+  mov rax, [rsp + (20 * 8)]
+  mov rdx, rax
+  shr rdx, 32
+  wrmsr
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -580,6 +773,30 @@ __nm_stub:
   lea rsi, [rsp + (2 * 8)]
   call __nm_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
+  mov ecx, 0xC0000101
+  mov rax, rbx
+  mov rdx, rbx
+  shr rdx, 32
+  wrmsr
   pop rax
   pop rbx
   pop rcx
@@ -599,6 +816,10 @@ __nm_stub:
 
 .global __bp_stub
 __bp_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -615,12 +836,12 @@ __bp_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -628,6 +849,25 @@ __bp_stub:
   lea rsi, [rsp + (2 * 8)]
   call __bp_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -643,10 +883,18 @@ __bp_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __of_stub
 __of_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -663,12 +911,12 @@ __of_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -676,6 +924,25 @@ __of_stub:
   lea rsi, [rsp + (2 * 8)]
   call __of_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -691,10 +958,18 @@ __of_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __br_stub
 __br_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -711,12 +986,12 @@ __br_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -724,6 +999,25 @@ __br_stub:
   lea rsi, [rsp + (2 * 8)]
   call __br_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -739,10 +1033,18 @@ __br_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __ud_stub
 __ud_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -759,12 +1061,12 @@ __ud_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -772,6 +1074,25 @@ __ud_stub:
   lea rsi, [rsp + (2 * 8)]
   call __ud_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -787,10 +1108,18 @@ __ud_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __na_stub
 __na_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -807,12 +1136,12 @@ __na_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -820,6 +1149,25 @@ __na_stub:
   lea rsi, [rsp + (2 * 8)]
   call __na_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -835,10 +1183,18 @@ __na_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __mf_stub
 __mf_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -855,12 +1211,12 @@ __mf_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -868,6 +1224,25 @@ __mf_stub:
   lea rsi, [rsp + (2 * 8)]
   call __mf_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -883,10 +1258,18 @@ __mf_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __xm_stub
 __xm_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -903,12 +1286,12 @@ __xm_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -916,6 +1299,25 @@ __xm_stub:
   lea rsi, [rsp + (2 * 8)]
   call __xm_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -931,10 +1333,18 @@ __xm_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __ve_stub
 __ve_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -951,12 +1361,12 @@ __ve_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -964,6 +1374,25 @@ __ve_stub:
   lea rsi, [rsp + (2 * 8)]
   call __ve_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -979,10 +1408,18 @@ __ve_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __ts_stub
 __ts_stub:
+  test qword ptr [rsp + 16], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -999,12 +1436,12 @@ __ts_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((16 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((16 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((16 + 1) * 8)]
+  mov rax, [rsp + (16 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -1014,6 +1451,25 @@ __ts_stub:
   sub rsp, 0x8
   call __ts_handler
   add rsp, 0x18
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((16 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (16 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((16 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1030,10 +1486,18 @@ __ts_stub:
   pop r14
   pop r15
   add rsp, 0x8
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __np_stub
 __np_stub:
+  test qword ptr [rsp + 16], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1050,12 +1514,12 @@ __np_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((16 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((16 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((16 + 1) * 8)]
+  mov rax, [rsp + (16 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -1065,6 +1529,25 @@ __np_stub:
   sub rsp, 0x8
   call __np_handler
   add rsp, 0x18
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((16 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (16 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((16 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1081,10 +1564,18 @@ __np_stub:
   pop r14
   pop r15
   add rsp, 0x8
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __ss_stub
 __ss_stub:
+  test qword ptr [rsp + 16], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1101,12 +1592,12 @@ __ss_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((16 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((16 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((16 + 1) * 8)]
+  mov rax, [rsp + (16 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -1116,6 +1607,25 @@ __ss_stub:
   sub rsp, 0x8
   call __ss_handler
   add rsp, 0x18
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((16 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (16 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((16 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1132,10 +1642,18 @@ __ss_stub:
   pop r14
   pop r15
   add rsp, 0x8
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __gp_stub
 __gp_stub:
+  test qword ptr [rsp + 16], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1152,12 +1670,12 @@ __gp_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((16 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((16 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((16 + 1) * 8)]
+  mov rax, [rsp + (16 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -1167,6 +1685,25 @@ __gp_stub:
   sub rsp, 0x8
   call __gp_handler
   add rsp, 0x18
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((16 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (16 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((16 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1183,10 +1720,18 @@ __gp_stub:
   pop r14
   pop r15
   add rsp, 0x8
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __pf_stub
 __pf_stub:
+  test qword ptr [rsp + 16], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1203,12 +1748,12 @@ __pf_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((16 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((16 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((16 + 1) * 8)]
+  mov rax, [rsp + (16 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -1218,6 +1763,25 @@ __pf_stub:
   sub rsp, 0x8
   call __pf_handler
   add rsp, 0x18
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((16 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (16 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((16 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1234,10 +1798,18 @@ __pf_stub:
   pop r14
   pop r15
   add rsp, 0x8
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __ac_stub
 __ac_stub:
+  test qword ptr [rsp + 16], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1254,12 +1826,12 @@ __ac_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((16 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((16 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((16 + 1) * 8)]
+  mov rax, [rsp + (16 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -1269,6 +1841,103 @@ __ac_stub:
   sub rsp, 0x8
   call __ac_handler
   add rsp, 0x18
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((16 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (16 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((16 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
+  pop rax
+  pop rbx
+  pop rcx
+  pop rdx
+  pop rdi
+  pop rsi
+  pop rbp
+  pop r8
+  pop r9
+  pop r10
+  pop r11
+  pop r12
+  pop r13
+  pop r14
+  pop r15
+  add rsp, 0x8
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
+  iretq
+
+.global __cp_stub
+__cp_stub:
+  test qword ptr [rsp + 16], 0x3
+  jz 3f
+  swapgs
+  3:
+  cld
+  push r15
+  push r14
+  push r13
+  push r12
+  push r11
+  push r10
+  push r9
+  push r8
+  push rbp
+  push rsi
+  push rdi
+  push rdx
+  push rcx
+  push rbx
+  push rax
+  mov rax, [rsp + ((16 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
+  xor rbp, rbp
+  2:
+  mov rax, [rsp + (16 * 8)]
+  push rax
+  push rbp
+  mov rbp, rsp
+  lea rdi, [rsp + (18 * 8)]
+  mov rsi, [rsp + (17 * 8)]
+  lea rdx, [rsp + (2 * 8)]
+  sub rsp, 0x8
+  call __cp_handler
+  add rsp, 0x18
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((16 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (16 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((16 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1285,6 +1954,10 @@ __ac_stub:
   pop r14
   pop r15
   add rsp, 0x8
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __mc_stub
@@ -1305,12 +1978,21 @@ __mc_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov ecx, 0xC0000101
+  rdmsr
+  shl rdx, 32
+  or rax, rdx
+  mov rbx, rax
+  mov rax, [rsp + (20 * 8)]
+  mov rdx, rax
+  shr rdx, 32
+  wrmsr
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -1318,6 +2000,30 @@ __mc_stub:
   lea rsi, [rsp + (2 * 8)]
   call __mc_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
+  mov ecx, 0xC0000101
+  mov rax, rbx
+  mov rdx, rbx
+  shr rdx, 32
+  wrmsr
   pop rax
   pop rbx
   pop rcx
@@ -1333,9 +2039,7 @@ __mc_stub:
   pop r13
   pop r14
   pop r15
-  2:
-  pause
-  jmp 2b
+  iretq
 
 .global __df_stub
 __df_stub:
@@ -1355,12 +2059,21 @@ __df_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((16 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov ecx, 0xC0000101
+  rdmsr
+  shl rdx, 32
+  or rax, rdx
+  mov rbx, rax
+  mov rax, [rsp + (21 * 8)]
+  mov rdx, rax
+  shr rdx, 32
+  wrmsr
+  mov rax, [rsp + ((16 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((16 + 1) * 8)]
+  mov rax, [rsp + (16 * 8)]
   push rax
   push rbp
   mov rbp, rsp
@@ -1392,6 +2105,10 @@ __df_stub:
 
 .global __irq_32_stub
 __irq_32_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1408,20 +2125,43 @@ __irq_32_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 32
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1437,10 +2177,18 @@ __irq_32_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_33_stub
 __irq_33_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1457,20 +2205,43 @@ __irq_33_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 33
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1486,10 +2257,18 @@ __irq_33_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_34_stub
 __irq_34_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1506,20 +2285,43 @@ __irq_34_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 34
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1535,10 +2337,18 @@ __irq_34_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_35_stub
 __irq_35_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1555,20 +2365,43 @@ __irq_35_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 35
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1584,10 +2417,18 @@ __irq_35_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_36_stub
 __irq_36_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1604,20 +2445,43 @@ __irq_36_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 36
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1633,10 +2497,18 @@ __irq_36_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_37_stub
 __irq_37_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1653,20 +2525,43 @@ __irq_37_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 37
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1682,10 +2577,18 @@ __irq_37_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_38_stub
 __irq_38_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1702,20 +2605,43 @@ __irq_38_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 38
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1731,10 +2657,18 @@ __irq_38_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_39_stub
 __irq_39_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1751,20 +2685,43 @@ __irq_39_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 39
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1780,10 +2737,18 @@ __irq_39_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_40_stub
 __irq_40_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1800,20 +2765,43 @@ __irq_40_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 40
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1829,10 +2817,18 @@ __irq_40_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_41_stub
 __irq_41_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1849,20 +2845,43 @@ __irq_41_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 41
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1878,10 +2897,18 @@ __irq_41_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_42_stub
 __irq_42_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1898,20 +2925,43 @@ __irq_42_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 42
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1927,10 +2977,18 @@ __irq_42_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_43_stub
 __irq_43_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1947,20 +3005,43 @@ __irq_43_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 43
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -1976,10 +3057,18 @@ __irq_43_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_44_stub
 __irq_44_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -1996,20 +3085,43 @@ __irq_44_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 44
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2025,10 +3137,18 @@ __irq_44_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_45_stub
 __irq_45_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2045,20 +3165,43 @@ __irq_45_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 45
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2074,10 +3217,18 @@ __irq_45_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_46_stub
 __irq_46_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2094,20 +3245,43 @@ __irq_46_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 46
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2123,10 +3297,18 @@ __irq_46_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_47_stub
 __irq_47_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2143,20 +3325,43 @@ __irq_47_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 47
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2172,10 +3377,18 @@ __irq_47_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_48_stub
 __irq_48_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2192,20 +3405,43 @@ __irq_48_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 48
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2221,10 +3457,18 @@ __irq_48_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_49_stub
 __irq_49_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2241,20 +3485,43 @@ __irq_49_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 49
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2270,10 +3537,18 @@ __irq_49_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_50_stub
 __irq_50_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2290,20 +3565,43 @@ __irq_50_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 50
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2319,10 +3617,18 @@ __irq_50_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_51_stub
 __irq_51_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2339,20 +3645,43 @@ __irq_51_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 51
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2368,10 +3697,18 @@ __irq_51_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_52_stub
 __irq_52_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2388,20 +3725,43 @@ __irq_52_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 52
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2417,10 +3777,18 @@ __irq_52_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_53_stub
 __irq_53_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2437,20 +3805,43 @@ __irq_53_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 53
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2466,10 +3857,18 @@ __irq_53_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_54_stub
 __irq_54_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2486,20 +3885,43 @@ __irq_54_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 54
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2515,10 +3937,18 @@ __irq_54_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_55_stub
 __irq_55_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2535,20 +3965,43 @@ __irq_55_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 55
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2564,10 +4017,18 @@ __irq_55_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_56_stub
 __irq_56_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2584,20 +4045,43 @@ __irq_56_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 56
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2613,10 +4097,18 @@ __irq_56_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_57_stub
 __irq_57_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2633,20 +4125,43 @@ __irq_57_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 57
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2662,10 +4177,18 @@ __irq_57_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_58_stub
 __irq_58_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2682,20 +4205,43 @@ __irq_58_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 58
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2711,10 +4257,18 @@ __irq_58_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_59_stub
 __irq_59_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2731,20 +4285,43 @@ __irq_59_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 59
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2760,10 +4337,18 @@ __irq_59_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_60_stub
 __irq_60_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2780,20 +4365,43 @@ __irq_60_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 60
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2809,10 +4417,18 @@ __irq_60_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_61_stub
 __irq_61_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2829,20 +4445,43 @@ __irq_61_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 61
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2858,10 +4497,18 @@ __irq_61_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_62_stub
 __irq_62_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2878,20 +4525,43 @@ __irq_62_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 62
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2907,10 +4577,18 @@ __irq_62_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_63_stub
 __irq_63_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2927,20 +4605,43 @@ __irq_63_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 63
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -2956,10 +4657,18 @@ __irq_63_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_64_stub
 __irq_64_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -2976,20 +4685,43 @@ __irq_64_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 64
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3005,10 +4737,18 @@ __irq_64_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_65_stub
 __irq_65_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3025,20 +4765,43 @@ __irq_65_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 65
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3054,10 +4817,18 @@ __irq_65_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_66_stub
 __irq_66_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3074,20 +4845,43 @@ __irq_66_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 66
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3103,10 +4897,18 @@ __irq_66_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_67_stub
 __irq_67_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3123,20 +4925,43 @@ __irq_67_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 67
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3152,10 +4977,18 @@ __irq_67_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_68_stub
 __irq_68_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3172,20 +5005,43 @@ __irq_68_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 68
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3201,10 +5057,18 @@ __irq_68_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_69_stub
 __irq_69_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3221,20 +5085,43 @@ __irq_69_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 69
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3250,10 +5137,18 @@ __irq_69_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_70_stub
 __irq_70_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3270,20 +5165,43 @@ __irq_70_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 70
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3299,10 +5217,18 @@ __irq_70_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_71_stub
 __irq_71_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3319,20 +5245,43 @@ __irq_71_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 71
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3348,10 +5297,18 @@ __irq_71_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_72_stub
 __irq_72_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3368,20 +5325,43 @@ __irq_72_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 72
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3397,10 +5377,18 @@ __irq_72_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_73_stub
 __irq_73_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3417,20 +5405,43 @@ __irq_73_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 73
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3446,10 +5457,18 @@ __irq_73_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_74_stub
 __irq_74_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3466,20 +5485,43 @@ __irq_74_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 74
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3495,10 +5537,18 @@ __irq_74_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_75_stub
 __irq_75_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3515,20 +5565,43 @@ __irq_75_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 75
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3544,10 +5617,18 @@ __irq_75_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_76_stub
 __irq_76_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3564,20 +5645,43 @@ __irq_76_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 76
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3593,10 +5697,18 @@ __irq_76_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_77_stub
 __irq_77_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3613,20 +5725,43 @@ __irq_77_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 77
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3642,10 +5777,18 @@ __irq_77_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_78_stub
 __irq_78_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3662,20 +5805,43 @@ __irq_78_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 78
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3691,10 +5857,18 @@ __irq_78_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_79_stub
 __irq_79_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3711,20 +5885,43 @@ __irq_79_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 79
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3740,10 +5937,18 @@ __irq_79_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_80_stub
 __irq_80_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3760,20 +5965,43 @@ __irq_80_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 80
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3789,10 +6017,18 @@ __irq_80_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_81_stub
 __irq_81_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3809,20 +6045,43 @@ __irq_81_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 81
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3838,10 +6097,18 @@ __irq_81_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_82_stub
 __irq_82_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3858,20 +6125,43 @@ __irq_82_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 82
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3887,10 +6177,18 @@ __irq_82_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_83_stub
 __irq_83_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3907,20 +6205,43 @@ __irq_83_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 83
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3936,10 +6257,18 @@ __irq_83_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_84_stub
 __irq_84_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -3956,20 +6285,43 @@ __irq_84_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 84
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -3985,10 +6337,18 @@ __irq_84_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_85_stub
 __irq_85_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4005,20 +6365,43 @@ __irq_85_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 85
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4034,10 +6417,18 @@ __irq_85_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_86_stub
 __irq_86_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4054,20 +6445,43 @@ __irq_86_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 86
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4083,10 +6497,18 @@ __irq_86_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_87_stub
 __irq_87_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4103,20 +6525,43 @@ __irq_87_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 87
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4132,10 +6577,18 @@ __irq_87_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_88_stub
 __irq_88_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4152,20 +6605,43 @@ __irq_88_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 88
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4181,10 +6657,18 @@ __irq_88_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_89_stub
 __irq_89_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4201,20 +6685,43 @@ __irq_89_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 89
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4230,10 +6737,18 @@ __irq_89_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_90_stub
 __irq_90_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4250,20 +6765,43 @@ __irq_90_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 90
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4279,10 +6817,18 @@ __irq_90_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_91_stub
 __irq_91_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4299,20 +6845,43 @@ __irq_91_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 91
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4328,10 +6897,18 @@ __irq_91_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_92_stub
 __irq_92_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4348,20 +6925,43 @@ __irq_92_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 92
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4377,10 +6977,18 @@ __irq_92_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_93_stub
 __irq_93_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4397,20 +7005,43 @@ __irq_93_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 93
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4426,10 +7057,18 @@ __irq_93_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_94_stub
 __irq_94_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4446,20 +7085,43 @@ __irq_94_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 94
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4475,10 +7137,18 @@ __irq_94_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_95_stub
 __irq_95_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4495,20 +7165,43 @@ __irq_95_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 95
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4524,10 +7217,18 @@ __irq_95_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_96_stub
 __irq_96_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4544,20 +7245,43 @@ __irq_96_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 96
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4573,10 +7297,18 @@ __irq_96_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_97_stub
 __irq_97_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4593,20 +7325,43 @@ __irq_97_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 97
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4622,10 +7377,18 @@ __irq_97_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_98_stub
 __irq_98_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4642,20 +7405,43 @@ __irq_98_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 98
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4671,10 +7457,18 @@ __irq_98_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_99_stub
 __irq_99_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4691,20 +7485,43 @@ __irq_99_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 99
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4720,10 +7537,18 @@ __irq_99_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_100_stub
 __irq_100_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4740,20 +7565,43 @@ __irq_100_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 100
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4769,10 +7617,18 @@ __irq_100_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_101_stub
 __irq_101_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4789,20 +7645,43 @@ __irq_101_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 101
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4818,10 +7697,18 @@ __irq_101_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_102_stub
 __irq_102_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4838,20 +7725,43 @@ __irq_102_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 102
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4867,10 +7777,18 @@ __irq_102_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_103_stub
 __irq_103_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4887,20 +7805,43 @@ __irq_103_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 103
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4916,10 +7857,18 @@ __irq_103_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_104_stub
 __irq_104_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4936,20 +7885,43 @@ __irq_104_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 104
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -4965,10 +7937,18 @@ __irq_104_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_105_stub
 __irq_105_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -4985,20 +7965,43 @@ __irq_105_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 105
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5014,10 +8017,18 @@ __irq_105_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_106_stub
 __irq_106_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5034,20 +8045,43 @@ __irq_106_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 106
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5063,10 +8097,18 @@ __irq_106_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_107_stub
 __irq_107_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5083,20 +8125,43 @@ __irq_107_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 107
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5112,10 +8177,18 @@ __irq_107_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_108_stub
 __irq_108_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5132,20 +8205,43 @@ __irq_108_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 108
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5161,10 +8257,18 @@ __irq_108_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_109_stub
 __irq_109_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5181,20 +8285,43 @@ __irq_109_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 109
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5210,10 +8337,18 @@ __irq_109_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_110_stub
 __irq_110_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5230,20 +8365,43 @@ __irq_110_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 110
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5259,10 +8417,18 @@ __irq_110_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_111_stub
 __irq_111_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5279,20 +8445,43 @@ __irq_111_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 111
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5308,10 +8497,18 @@ __irq_111_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_112_stub
 __irq_112_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5328,20 +8525,43 @@ __irq_112_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 112
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5357,10 +8577,18 @@ __irq_112_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_113_stub
 __irq_113_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5377,20 +8605,43 @@ __irq_113_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 113
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5406,10 +8657,18 @@ __irq_113_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_114_stub
 __irq_114_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5426,20 +8685,43 @@ __irq_114_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 114
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5455,10 +8737,18 @@ __irq_114_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_115_stub
 __irq_115_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5475,20 +8765,43 @@ __irq_115_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 115
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5504,10 +8817,18 @@ __irq_115_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_116_stub
 __irq_116_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5524,20 +8845,43 @@ __irq_116_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 116
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5553,10 +8897,18 @@ __irq_116_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_117_stub
 __irq_117_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5573,20 +8925,43 @@ __irq_117_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 117
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5602,10 +8977,18 @@ __irq_117_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_118_stub
 __irq_118_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5622,20 +9005,43 @@ __irq_118_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 118
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5651,10 +9057,18 @@ __irq_118_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_119_stub
 __irq_119_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5671,20 +9085,43 @@ __irq_119_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 119
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5700,10 +9137,18 @@ __irq_119_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_120_stub
 __irq_120_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5720,20 +9165,43 @@ __irq_120_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 120
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5749,10 +9217,18 @@ __irq_120_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_121_stub
 __irq_121_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5769,20 +9245,43 @@ __irq_121_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 121
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5798,10 +9297,18 @@ __irq_121_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_122_stub
 __irq_122_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5818,20 +9325,43 @@ __irq_122_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 122
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5847,10 +9377,18 @@ __irq_122_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_123_stub
 __irq_123_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5867,20 +9405,43 @@ __irq_123_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 123
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5896,10 +9457,18 @@ __irq_123_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_124_stub
 __irq_124_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5916,20 +9485,43 @@ __irq_124_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 124
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5945,10 +9537,18 @@ __irq_124_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_125_stub
 __irq_125_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -5965,20 +9565,43 @@ __irq_125_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 125
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -5994,10 +9617,18 @@ __irq_125_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_126_stub
 __irq_126_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6014,20 +9645,43 @@ __irq_126_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 126
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6043,10 +9697,18 @@ __irq_126_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_127_stub
 __irq_127_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6063,20 +9725,43 @@ __irq_127_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 127
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6092,10 +9777,18 @@ __irq_127_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_128_stub
 __irq_128_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6112,20 +9805,43 @@ __irq_128_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 128
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6141,10 +9857,18 @@ __irq_128_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_129_stub
 __irq_129_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6161,20 +9885,43 @@ __irq_129_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 129
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6190,10 +9937,18 @@ __irq_129_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_130_stub
 __irq_130_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6210,20 +9965,43 @@ __irq_130_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 130
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6239,10 +10017,18 @@ __irq_130_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_131_stub
 __irq_131_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6259,20 +10045,43 @@ __irq_131_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 131
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6288,10 +10097,18 @@ __irq_131_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_132_stub
 __irq_132_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6308,20 +10125,43 @@ __irq_132_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 132
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6337,10 +10177,18 @@ __irq_132_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_133_stub
 __irq_133_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6357,20 +10205,43 @@ __irq_133_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 133
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6386,10 +10257,18 @@ __irq_133_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_134_stub
 __irq_134_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6406,20 +10285,43 @@ __irq_134_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 134
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6435,10 +10337,18 @@ __irq_134_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_135_stub
 __irq_135_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6455,20 +10365,43 @@ __irq_135_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 135
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6484,10 +10417,18 @@ __irq_135_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_136_stub
 __irq_136_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6504,20 +10445,43 @@ __irq_136_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 136
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6533,10 +10497,18 @@ __irq_136_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_137_stub
 __irq_137_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6553,20 +10525,43 @@ __irq_137_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 137
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6582,10 +10577,18 @@ __irq_137_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_138_stub
 __irq_138_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6602,20 +10605,43 @@ __irq_138_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 138
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6631,10 +10657,18 @@ __irq_138_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_139_stub
 __irq_139_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6651,20 +10685,43 @@ __irq_139_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 139
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6680,10 +10737,18 @@ __irq_139_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_140_stub
 __irq_140_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6700,20 +10765,43 @@ __irq_140_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 140
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6729,10 +10817,18 @@ __irq_140_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_141_stub
 __irq_141_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6749,20 +10845,43 @@ __irq_141_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 141
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6778,10 +10897,18 @@ __irq_141_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_142_stub
 __irq_142_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6798,20 +10925,43 @@ __irq_142_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 142
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6827,10 +10977,18 @@ __irq_142_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_143_stub
 __irq_143_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6847,20 +11005,43 @@ __irq_143_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 143
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6876,10 +11057,18 @@ __irq_143_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_144_stub
 __irq_144_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6896,20 +11085,43 @@ __irq_144_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 144
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6925,10 +11137,18 @@ __irq_144_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_145_stub
 __irq_145_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6945,20 +11165,43 @@ __irq_145_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 145
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -6974,10 +11217,18 @@ __irq_145_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_146_stub
 __irq_146_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -6994,20 +11245,43 @@ __irq_146_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 146
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7023,10 +11297,18 @@ __irq_146_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_147_stub
 __irq_147_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7043,20 +11325,43 @@ __irq_147_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 147
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7072,10 +11377,18 @@ __irq_147_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_148_stub
 __irq_148_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7092,20 +11405,43 @@ __irq_148_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 148
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7121,10 +11457,18 @@ __irq_148_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_149_stub
 __irq_149_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7141,20 +11485,43 @@ __irq_149_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 149
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7170,10 +11537,18 @@ __irq_149_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_150_stub
 __irq_150_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7190,20 +11565,43 @@ __irq_150_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 150
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7219,10 +11617,18 @@ __irq_150_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_151_stub
 __irq_151_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7239,20 +11645,43 @@ __irq_151_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 151
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7268,10 +11697,18 @@ __irq_151_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_152_stub
 __irq_152_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7288,20 +11725,43 @@ __irq_152_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 152
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7317,10 +11777,18 @@ __irq_152_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_153_stub
 __irq_153_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7337,20 +11805,43 @@ __irq_153_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 153
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7366,10 +11857,18 @@ __irq_153_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_154_stub
 __irq_154_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7386,20 +11885,43 @@ __irq_154_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 154
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7415,10 +11937,18 @@ __irq_154_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_155_stub
 __irq_155_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7435,20 +11965,43 @@ __irq_155_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 155
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7464,10 +12017,18 @@ __irq_155_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_156_stub
 __irq_156_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7484,20 +12045,43 @@ __irq_156_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 156
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7513,10 +12097,18 @@ __irq_156_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_157_stub
 __irq_157_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7533,20 +12125,43 @@ __irq_157_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 157
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7562,10 +12177,18 @@ __irq_157_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_158_stub
 __irq_158_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7582,20 +12205,43 @@ __irq_158_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 158
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7611,10 +12257,18 @@ __irq_158_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_159_stub
 __irq_159_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7631,20 +12285,43 @@ __irq_159_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 159
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7660,10 +12337,18 @@ __irq_159_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_160_stub
 __irq_160_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7680,20 +12365,43 @@ __irq_160_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 160
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7709,10 +12417,18 @@ __irq_160_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_161_stub
 __irq_161_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7729,20 +12445,43 @@ __irq_161_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 161
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7758,10 +12497,18 @@ __irq_161_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_162_stub
 __irq_162_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7778,20 +12525,43 @@ __irq_162_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 162
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7807,10 +12577,18 @@ __irq_162_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_163_stub
 __irq_163_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7827,20 +12605,43 @@ __irq_163_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 163
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7856,10 +12657,18 @@ __irq_163_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_164_stub
 __irq_164_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7876,20 +12685,43 @@ __irq_164_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 164
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7905,10 +12737,18 @@ __irq_164_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_165_stub
 __irq_165_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7925,20 +12765,43 @@ __irq_165_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 165
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -7954,10 +12817,18 @@ __irq_165_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_166_stub
 __irq_166_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -7974,20 +12845,43 @@ __irq_166_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 166
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8003,10 +12897,18 @@ __irq_166_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_167_stub
 __irq_167_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8023,20 +12925,43 @@ __irq_167_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 167
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8052,10 +12977,18 @@ __irq_167_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_168_stub
 __irq_168_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8072,20 +13005,43 @@ __irq_168_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 168
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8101,10 +13057,18 @@ __irq_168_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_169_stub
 __irq_169_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8121,20 +13085,43 @@ __irq_169_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 169
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8150,10 +13137,18 @@ __irq_169_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_170_stub
 __irq_170_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8170,20 +13165,43 @@ __irq_170_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 170
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8199,10 +13217,18 @@ __irq_170_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_171_stub
 __irq_171_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8219,20 +13245,43 @@ __irq_171_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 171
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8248,10 +13297,18 @@ __irq_171_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_172_stub
 __irq_172_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8268,20 +13325,43 @@ __irq_172_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 172
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8297,10 +13377,18 @@ __irq_172_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_173_stub
 __irq_173_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8317,20 +13405,43 @@ __irq_173_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 173
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8346,10 +13457,18 @@ __irq_173_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_174_stub
 __irq_174_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8366,20 +13485,43 @@ __irq_174_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 174
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8395,10 +13537,18 @@ __irq_174_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_175_stub
 __irq_175_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8415,20 +13565,43 @@ __irq_175_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 175
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8444,10 +13617,18 @@ __irq_175_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_176_stub
 __irq_176_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8464,20 +13645,43 @@ __irq_176_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 176
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8493,10 +13697,18 @@ __irq_176_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_177_stub
 __irq_177_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8513,20 +13725,43 @@ __irq_177_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 177
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8542,10 +13777,18 @@ __irq_177_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_178_stub
 __irq_178_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8562,20 +13805,43 @@ __irq_178_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 178
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8591,10 +13857,18 @@ __irq_178_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_179_stub
 __irq_179_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8611,20 +13885,43 @@ __irq_179_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 179
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8640,10 +13937,18 @@ __irq_179_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_180_stub
 __irq_180_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8660,20 +13965,43 @@ __irq_180_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 180
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8689,10 +14017,18 @@ __irq_180_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_181_stub
 __irq_181_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8709,20 +14045,43 @@ __irq_181_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 181
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8738,10 +14097,18 @@ __irq_181_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_182_stub
 __irq_182_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8758,20 +14125,43 @@ __irq_182_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 182
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8787,10 +14177,18 @@ __irq_182_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_183_stub
 __irq_183_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8807,20 +14205,43 @@ __irq_183_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 183
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8836,10 +14257,18 @@ __irq_183_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_184_stub
 __irq_184_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8856,20 +14285,43 @@ __irq_184_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 184
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8885,10 +14337,18 @@ __irq_184_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_185_stub
 __irq_185_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8905,20 +14365,43 @@ __irq_185_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 185
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8934,10 +14417,18 @@ __irq_185_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_186_stub
 __irq_186_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -8954,20 +14445,43 @@ __irq_186_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 186
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -8983,10 +14497,18 @@ __irq_186_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_187_stub
 __irq_187_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9003,20 +14525,43 @@ __irq_187_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 187
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9032,10 +14577,18 @@ __irq_187_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_188_stub
 __irq_188_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9052,20 +14605,43 @@ __irq_188_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 188
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9081,10 +14657,18 @@ __irq_188_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_189_stub
 __irq_189_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9101,20 +14685,43 @@ __irq_189_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 189
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9130,10 +14737,18 @@ __irq_189_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_190_stub
 __irq_190_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9150,20 +14765,43 @@ __irq_190_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 190
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9179,10 +14817,18 @@ __irq_190_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_191_stub
 __irq_191_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9199,20 +14845,43 @@ __irq_191_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 191
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9228,10 +14897,18 @@ __irq_191_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_192_stub
 __irq_192_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9248,20 +14925,43 @@ __irq_192_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 192
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9277,10 +14977,18 @@ __irq_192_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_193_stub
 __irq_193_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9297,20 +15005,43 @@ __irq_193_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 193
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9326,10 +15057,18 @@ __irq_193_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_194_stub
 __irq_194_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9346,20 +15085,43 @@ __irq_194_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 194
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9375,10 +15137,18 @@ __irq_194_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_195_stub
 __irq_195_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9395,20 +15165,43 @@ __irq_195_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 195
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9424,10 +15217,18 @@ __irq_195_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_196_stub
 __irq_196_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9444,20 +15245,43 @@ __irq_196_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 196
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9473,10 +15297,18 @@ __irq_196_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_197_stub
 __irq_197_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9493,20 +15325,43 @@ __irq_197_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 197
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9522,10 +15377,18 @@ __irq_197_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_198_stub
 __irq_198_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9542,20 +15405,43 @@ __irq_198_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 198
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9571,10 +15457,18 @@ __irq_198_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_199_stub
 __irq_199_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9591,20 +15485,43 @@ __irq_199_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 199
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9620,10 +15537,18 @@ __irq_199_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_200_stub
 __irq_200_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9640,20 +15565,43 @@ __irq_200_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 200
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9669,10 +15617,18 @@ __irq_200_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_201_stub
 __irq_201_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9689,20 +15645,43 @@ __irq_201_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 201
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9718,10 +15697,18 @@ __irq_201_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_202_stub
 __irq_202_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9738,20 +15725,43 @@ __irq_202_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 202
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9767,10 +15777,18 @@ __irq_202_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_203_stub
 __irq_203_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9787,20 +15805,43 @@ __irq_203_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 203
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9816,10 +15857,18 @@ __irq_203_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_204_stub
 __irq_204_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9836,20 +15885,43 @@ __irq_204_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 204
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9865,10 +15937,18 @@ __irq_204_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_205_stub
 __irq_205_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9885,20 +15965,43 @@ __irq_205_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 205
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9914,10 +16017,18 @@ __irq_205_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_206_stub
 __irq_206_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9934,20 +16045,43 @@ __irq_206_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 206
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -9963,10 +16097,18 @@ __irq_206_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_207_stub
 __irq_207_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -9983,20 +16125,43 @@ __irq_207_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 207
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10012,10 +16177,18 @@ __irq_207_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_208_stub
 __irq_208_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10032,20 +16205,43 @@ __irq_208_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 208
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10061,10 +16257,18 @@ __irq_208_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_209_stub
 __irq_209_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10081,20 +16285,43 @@ __irq_209_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 209
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10110,10 +16337,18 @@ __irq_209_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_210_stub
 __irq_210_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10130,20 +16365,43 @@ __irq_210_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 210
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10159,10 +16417,18 @@ __irq_210_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_211_stub
 __irq_211_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10179,20 +16445,43 @@ __irq_211_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 211
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10208,10 +16497,18 @@ __irq_211_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_212_stub
 __irq_212_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10228,20 +16525,43 @@ __irq_212_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 212
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10257,10 +16577,18 @@ __irq_212_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_213_stub
 __irq_213_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10277,20 +16605,43 @@ __irq_213_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 213
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10306,10 +16657,18 @@ __irq_213_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_214_stub
 __irq_214_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10326,20 +16685,43 @@ __irq_214_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 214
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10355,10 +16737,18 @@ __irq_214_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_215_stub
 __irq_215_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10375,20 +16765,43 @@ __irq_215_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 215
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10404,10 +16817,18 @@ __irq_215_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_216_stub
 __irq_216_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10424,20 +16845,43 @@ __irq_216_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 216
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10453,10 +16897,18 @@ __irq_216_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_217_stub
 __irq_217_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10473,20 +16925,43 @@ __irq_217_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 217
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10502,10 +16977,18 @@ __irq_217_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_218_stub
 __irq_218_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10522,20 +17005,43 @@ __irq_218_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 218
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10551,10 +17057,18 @@ __irq_218_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_219_stub
 __irq_219_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10571,20 +17085,43 @@ __irq_219_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 219
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10600,10 +17137,18 @@ __irq_219_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_220_stub
 __irq_220_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10620,20 +17165,43 @@ __irq_220_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 220
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10649,10 +17217,18 @@ __irq_220_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_221_stub
 __irq_221_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10669,20 +17245,43 @@ __irq_221_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 221
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10698,10 +17297,18 @@ __irq_221_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_222_stub
 __irq_222_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10718,20 +17325,43 @@ __irq_222_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 222
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10747,10 +17377,18 @@ __irq_222_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_223_stub
 __irq_223_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10767,20 +17405,43 @@ __irq_223_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 223
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10796,10 +17457,18 @@ __irq_223_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_224_stub
 __irq_224_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10816,20 +17485,43 @@ __irq_224_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 224
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10845,10 +17537,18 @@ __irq_224_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_225_stub
 __irq_225_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10865,20 +17565,43 @@ __irq_225_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 225
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10894,10 +17617,18 @@ __irq_225_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_226_stub
 __irq_226_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10914,20 +17645,43 @@ __irq_226_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 226
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10943,10 +17697,18 @@ __irq_226_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_227_stub
 __irq_227_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -10963,20 +17725,43 @@ __irq_227_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 227
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -10992,10 +17777,18 @@ __irq_227_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_228_stub
 __irq_228_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11012,20 +17805,43 @@ __irq_228_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 228
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11041,10 +17857,18 @@ __irq_228_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_229_stub
 __irq_229_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11061,20 +17885,43 @@ __irq_229_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 229
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11090,10 +17937,18 @@ __irq_229_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_230_stub
 __irq_230_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11110,20 +17965,43 @@ __irq_230_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 230
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11139,10 +18017,18 @@ __irq_230_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_231_stub
 __irq_231_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11159,20 +18045,43 @@ __irq_231_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 231
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11188,10 +18097,18 @@ __irq_231_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_232_stub
 __irq_232_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11208,20 +18125,43 @@ __irq_232_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 232
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11237,10 +18177,18 @@ __irq_232_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_233_stub
 __irq_233_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11257,20 +18205,43 @@ __irq_233_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 233
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11286,10 +18257,18 @@ __irq_233_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_234_stub
 __irq_234_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11306,20 +18285,43 @@ __irq_234_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 234
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11335,10 +18337,18 @@ __irq_234_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_235_stub
 __irq_235_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11355,20 +18365,43 @@ __irq_235_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 235
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11384,10 +18417,18 @@ __irq_235_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_236_stub
 __irq_236_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11404,20 +18445,43 @@ __irq_236_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 236
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11433,10 +18497,18 @@ __irq_236_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_237_stub
 __irq_237_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11453,20 +18525,43 @@ __irq_237_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 237
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11482,10 +18577,18 @@ __irq_237_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_238_stub
 __irq_238_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11502,20 +18605,43 @@ __irq_238_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 238
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11531,10 +18657,18 @@ __irq_238_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_239_stub
 __irq_239_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11551,20 +18685,43 @@ __irq_239_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 239
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11580,10 +18737,18 @@ __irq_239_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_240_stub
 __irq_240_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11600,20 +18765,43 @@ __irq_240_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 240
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11629,10 +18817,18 @@ __irq_240_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_241_stub
 __irq_241_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11649,20 +18845,43 @@ __irq_241_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 241
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11678,10 +18897,18 @@ __irq_241_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_242_stub
 __irq_242_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11698,20 +18925,43 @@ __irq_242_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 242
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11727,10 +18977,18 @@ __irq_242_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_243_stub
 __irq_243_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11747,20 +19005,43 @@ __irq_243_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 243
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11776,10 +19057,18 @@ __irq_243_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_244_stub
 __irq_244_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11796,20 +19085,43 @@ __irq_244_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 244
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11825,10 +19137,18 @@ __irq_244_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_245_stub
 __irq_245_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11845,20 +19165,43 @@ __irq_245_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 245
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11874,10 +19217,18 @@ __irq_245_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_246_stub
 __irq_246_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11894,20 +19245,43 @@ __irq_246_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 246
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11923,10 +19297,18 @@ __irq_246_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_247_stub
 __irq_247_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11943,20 +19325,43 @@ __irq_247_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 247
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -11972,10 +19377,18 @@ __irq_247_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_248_stub
 __irq_248_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -11992,20 +19405,43 @@ __irq_248_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 248
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -12021,10 +19457,18 @@ __irq_248_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_249_stub
 __irq_249_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -12041,20 +19485,43 @@ __irq_249_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 249
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -12070,10 +19537,18 @@ __irq_249_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_250_stub
 __irq_250_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -12090,20 +19565,43 @@ __irq_250_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 250
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -12119,10 +19617,18 @@ __irq_250_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_251_stub
 __irq_251_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -12139,20 +19645,43 @@ __irq_251_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 251
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -12168,10 +19697,18 @@ __irq_251_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_252_stub
 __irq_252_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -12188,20 +19725,43 @@ __irq_252_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 252
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -12217,10 +19777,18 @@ __irq_252_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_253_stub
 __irq_253_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -12237,20 +19805,43 @@ __irq_253_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 253
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -12266,10 +19857,18 @@ __irq_253_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_254_stub
 __irq_254_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -12286,20 +19885,43 @@ __irq_254_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 254
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -12315,10 +19937,18 @@ __irq_254_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
 
 .global __irq_255_stub
 __irq_255_stub:
+  test qword ptr [rsp + 8], 0x3
+  jz 3f
+  swapgs
+  3:
   cld
   push r15
   push r14
@@ -12335,20 +19965,43 @@ __irq_255_stub:
   push rcx
   push rbx
   push rax
-  mov rax, [rsp + ((15 + 1) * 0)]
-  cmp rax, 0x8
-  je 2f
+  mov rax, [rsp + ((15 + 1) * 8)]
+  test rax, 0x3
+  jz 2f
   xor rbp, rbp
   2:
-  mov rax, [rsp + ((15 + 1) * 8)]
+  mov rax, [rsp + (15 * 8)]
   push rax
   push rbp
   mov rbp, rsp
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx
   mov rdi, 255
   lea rsi, [rsp + (17 * 8)]
   lea rdx, [rsp + (2 * 8)]
   call __irq_handler
   add rsp, 0x10
+  xor eax, eax
+  rdsspq rax
+  test rax, rax
+  jz 5f
+  mov rcx, qword ptr gs:[{PRIVILEGE_SHADOW_STACK}]
+  test qword ptr [rsp + ((15 * 8) + 8)], 0x3
+  jnz 6f
+  cmp rax, rcx
+  jne 7f
+  wrssq qword ptr [rax], rcx
+  7:
+  mov rcx, [rsp + (15 * 8)]
+  wrssq qword ptr [rax + 8], rcx
+  mov rcx, [rsp + ((15 * 8) + 8)]
+  wrssq qword ptr [rax + 16], rcx
+  jmp 5f
+  6:
+  wrssq qword ptr [rcx], rcx
+  5:
   pop rax
   pop rbx
   pop rcx
@@ -12364,6 +20017,271 @@ __irq_255_stub:
   pop r13
   pop r14
   pop r15
+  test qword ptr [rsp + 8], 0x3
+  jz 4f
+  swapgs
+  4:
   iretq
-"
+
+.pushsection .rodata
+.balign 8
+.global __irq_stub_table
+__irq_stub_table:
+  .quad __irq_32_stub
+  .quad __irq_33_stub
+  .quad __irq_34_stub
+  .quad __irq_35_stub
+  .quad __irq_36_stub
+  .quad __irq_37_stub
+  .quad __irq_38_stub
+  .quad __irq_39_stub
+  .quad __irq_40_stub
+  .quad __irq_41_stub
+  .quad __irq_42_stub
+  .quad __irq_43_stub
+  .quad __irq_44_stub
+  .quad __irq_45_stub
+  .quad __irq_46_stub
+  .quad __irq_47_stub
+  .quad __irq_48_stub
+  .quad __irq_49_stub
+  .quad __irq_50_stub
+  .quad __irq_51_stub
+  .quad __irq_52_stub
+  .quad __irq_53_stub
+  .quad __irq_54_stub
+  .quad __irq_55_stub
+  .quad __irq_56_stub
+  .quad __irq_57_stub
+  .quad __irq_58_stub
+  .quad __irq_59_stub
+  .quad __irq_60_stub
+  .quad __irq_61_stub
+  .quad __irq_62_stub
+  .quad __irq_63_stub
+  .quad __irq_64_stub
+  .quad __irq_65_stub
+  .quad __irq_66_stub
+  .quad __irq_67_stub
+  .quad __irq_68_stub
+  .quad __irq_69_stub
+  .quad __irq_70_stub
+  .quad __irq_71_stub
+  .quad __irq_72_stub
+  .quad __irq_73_stub
+  .quad __irq_74_stub
+  .quad __irq_75_stub
+  .quad __irq_76_stub
+  .quad __irq_77_stub
+  .quad __irq_78_stub
+  .quad __irq_79_stub
+  .quad __irq_80_stub
+  .quad __irq_81_stub
+  .quad __irq_82_stub
+  .quad __irq_83_stub
+  .quad __irq_84_stub
+  .quad __irq_85_stub
+  .quad __irq_86_stub
+  .quad __irq_87_stub
+  .quad __irq_88_stub
+  .quad __irq_89_stub
+  .quad __irq_90_stub
+  .quad __irq_91_stub
+  .quad __irq_92_stub
+  .quad __irq_93_stub
+  .quad __irq_94_stub
+  .quad __irq_95_stub
+  .quad __irq_96_stub
+  .quad __irq_97_stub
+  .quad __irq_98_stub
+  .quad __irq_99_stub
+  .quad __irq_100_stub
+  .quad __irq_101_stub
+  .quad __irq_102_stub
+  .quad __irq_103_stub
+  .quad __irq_104_stub
+  .quad __irq_105_stub
+  .quad __irq_106_stub
+  .quad __irq_107_stub
+  .quad __irq_108_stub
+  .quad __irq_109_stub
+  .quad __irq_110_stub
+  .quad __irq_111_stub
+  .quad __irq_112_stub
+  .quad __irq_113_stub
+  .quad __irq_114_stub
+  .quad __irq_115_stub
+  .quad __irq_116_stub
+  .quad __irq_117_stub
+  .quad __irq_118_stub
+  .quad __irq_119_stub
+  .quad __irq_120_stub
+  .quad __irq_121_stub
+  .quad __irq_122_stub
+  .quad __irq_123_stub
+  .quad __irq_124_stub
+  .quad __irq_125_stub
+  .quad __irq_126_stub
+  .quad __irq_127_stub
+  .quad __irq_128_stub
+  .quad __irq_129_stub
+  .quad __irq_130_stub
+  .quad __irq_131_stub
+  .quad __irq_132_stub
+  .quad __irq_133_stub
+  .quad __irq_134_stub
+  .quad __irq_135_stub
+  .quad __irq_136_stub
+  .quad __irq_137_stub
+  .quad __irq_138_stub
+  .quad __irq_139_stub
+  .quad __irq_140_stub
+  .quad __irq_141_stub
+  .quad __irq_142_stub
+  .quad __irq_143_stub
+  .quad __irq_144_stub
+  .quad __irq_145_stub
+  .quad __irq_146_stub
+  .quad __irq_147_stub
+  .quad __irq_148_stub
+  .quad __irq_149_stub
+  .quad __irq_150_stub
+  .quad __irq_151_stub
+  .quad __irq_152_stub
+  .quad __irq_153_stub
+  .quad __irq_154_stub
+  .quad __irq_155_stub
+  .quad __irq_156_stub
+  .quad __irq_157_stub
+  .quad __irq_158_stub
+  .quad __irq_159_stub
+  .quad __irq_160_stub
+  .quad __irq_161_stub
+  .quad __irq_162_stub
+  .quad __irq_163_stub
+  .quad __irq_164_stub
+  .quad __irq_165_stub
+  .quad __irq_166_stub
+  .quad __irq_167_stub
+  .quad __irq_168_stub
+  .quad __irq_169_stub
+  .quad __irq_170_stub
+  .quad __irq_171_stub
+  .quad __irq_172_stub
+  .quad __irq_173_stub
+  .quad __irq_174_stub
+  .quad __irq_175_stub
+  .quad __irq_176_stub
+  .quad __irq_177_stub
+  .quad __irq_178_stub
+  .quad __irq_179_stub
+  .quad __irq_180_stub
+  .quad __irq_181_stub
+  .quad __irq_182_stub
+  .quad __irq_183_stub
+  .quad __irq_184_stub
+  .quad __irq_185_stub
+  .quad __irq_186_stub
+  .quad __irq_187_stub
+  .quad __irq_188_stub
+  .quad __irq_189_stub
+  .quad __irq_190_stub
+  .quad __irq_191_stub
+  .quad __irq_192_stub
+  .quad __irq_193_stub
+  .quad __irq_194_stub
+  .quad __irq_195_stub
+  .quad __irq_196_stub
+  .quad __irq_197_stub
+  .quad __irq_198_stub
+  .quad __irq_199_stub
+  .quad __irq_200_stub
+  .quad __irq_201_stub
+  .quad __irq_202_stub
+  .quad __irq_203_stub
+  .quad __irq_204_stub
+  .quad __irq_205_stub
+  .quad __irq_206_stub
+  .quad __irq_207_stub
+  .quad __irq_208_stub
+  .quad __irq_209_stub
+  .quad __irq_210_stub
+  .quad __irq_211_stub
+  .quad __irq_212_stub
+  .quad __irq_213_stub
+  .quad __irq_214_stub
+  .quad __irq_215_stub
+  .quad __irq_216_stub
+  .quad __irq_217_stub
+  .quad __irq_218_stub
+  .quad __irq_219_stub
+  .quad __irq_220_stub
+  .quad __irq_221_stub
+  .quad __irq_222_stub
+  .quad __irq_223_stub
+  .quad __irq_224_stub
+  .quad __irq_225_stub
+  .quad __irq_226_stub
+  .quad __irq_227_stub
+  .quad __irq_228_stub
+  .quad __irq_229_stub
+  .quad __irq_230_stub
+  .quad __irq_231_stub
+  .quad __irq_232_stub
+  .quad __irq_233_stub
+  .quad __irq_234_stub
+  .quad __irq_235_stub
+  .quad __irq_236_stub
+  .quad __irq_237_stub
+  .quad __irq_238_stub
+  .quad __irq_239_stub
+  .quad __irq_240_stub
+  .quad __irq_241_stub
+  .quad __irq_242_stub
+  .quad __irq_243_stub
+  .quad __irq_244_stub
+  .quad __irq_245_stub
+  .quad __irq_246_stub
+  .quad __irq_247_stub
+  .quad __irq_248_stub
+  .quad __irq_249_stub
+  .quad __irq_250_stub
+  .quad __irq_251_stub
+  .quad __irq_252_stub
+  .quad __irq_253_stub
+  .quad __irq_254_stub
+  .quad __irq_255_stub
+.popsection
+",
+    PRIVILEGE_SHADOW_STACK = const core::mem::offset_of!(crate::cpu::percpu::PerCpu, privilege_shadow_stack),
+}
+
+crate::kernel_test! {
+    /// An interrupt taken in the kernel doesn't `swapgs`, so the kernel's GS base stays loaded, and userspace's is
+    /// left in place.
+    fn kernel_entry_keeps_gs() -> crate::tests::Outcome {
+        use crate::arch::x86_64::registers::model_specific::{IA32_GS_BASE, IA32_KERNEL_GS_BASE};
+
+        const USER_GS_BASE: u64 = 0x7AB0_0000;
+
+        let kernel_gs_base = IA32_GS_BASE::read();
+        let user_gs_base = IA32_KERNEL_GS_BASE::read();
+        IA32_KERNEL_GS_BASE::write(USER_GS_BASE);
+
+        // Safety: The watchdog vector's handler only records that this hardware thread is responsive.
+        unsafe {
+            #[allow(clippy::as_conversions)]
+            core::arch::asm!("int {vector}", vector = const Vector::Watchdog as u8);
+        }
+
+        let swapped = IA32_GS_BASE::read() != kernel_gs_base
+            || IA32_KERNEL_GS_BASE::read() != USER_GS_BASE;
+        IA32_KERNEL_GS_BASE::write(user_gs_base);
+
+        if swapped {
+            return Err("GS base was swapped for an interrupt from the kernel");
+        }
+
+        Ok(())
+    }
 }
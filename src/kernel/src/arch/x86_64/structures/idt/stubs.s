@@ -35,18 +35,18 @@
 %endmacro
 
 %macro _save_trace_frame 1
-  mov rax, [rsp + ((%1 + 1) * 0)] ; copy the code segment to `rax`
+  mov rax, [rsp + ((%1 + 1) * 8)] ; copy the code segment to `rax`
 
   ; We don't want to try and trace a fault in the kernel back to
   ; userspace, so we check if we're coming from the kernel.
-  cmp rax, 0x8 ; are we coming from kernel code?
-  je .skip_xor ; if so, don't zero the frame pointer
+  test rax, 0x3 ; are we coming from kernel code?
+  jz .skip_xor ; if so, don't zero the frame pointer
   xor rbp, rbp ; if not, zero the frame pointer
 
   .skip_xor:
 
   ; Copy instruction pointer to `rax`.
-  mov rax, [rsp + ((%1 + 1) * 8)]
+  mov rax, [rsp + (%1 * 8)]
   
   ; Push the stack frame.
   push rax ; instruction pointer
@@ -54,13 +54,117 @@
   mov rbp, rsp
 %endmacro
 
+; While in the kernel, the GS base holds the hardware thread's per-CPU block, and
+; `IA32_KERNEL_GS_BASE` holds userspace's GS base; `swapgs` exchanges the two.
+;
+; Swaps them if the code segment selector at `rsp + %1` has a requested privilege
+; level of 3, i.e. the interrupt came from (or is returning to) userspace.
+%macro _swapgs_if_user 1
+  test qword [rsp + %1], 0x3 ; are we coming from userspace?
+  jz %%skip_swapgs           ; if not, the kernel's GS base is loaded
+  swapgs
+
+  %%skip_swapgs:
+%endmacro
+
+; Exceptions which can interrupt the kernel anywhere (i.e. between a `swapgs` and
+; the `iretq` after it) can't tell from the code segment which GS base is loaded,
+; nor from the GS base itself, as userspace may load any value (even a higher-half
+; one) with `wrgsbase`. So the GS base is saved, and the kernel's is loaded from the
+; slot at the top of the interrupt stack, just above the interrupt stack frame, which
+; holds the hardware thread's per-CPU block (see `TaskStateSegment::load_local`).
+;
+; `%1` is the offset of the slot. Leaves the saved GS base in `rbx`, which is
+; preserved across the handler call; so this must come after `_save_registers`.
+%macro _paranoid_gs_entry 1
+  mov ecx, 0xC0000101 ; `IA32_GS_BASE`
+  rdmsr
+  shl rdx, 32
+  or rax, rdx
+  mov rbx, rax        ; saved GS base
+  mov rax, [rsp + %1] ; the hardware thread's per-CPU block
+  mov rdx, rax
+  shr rdx, 32
+  wrmsr
+%endmacro
+
+%macro _paranoid_gs_exit 0
+  mov ecx, 0xC0000101 ; `IA32_GS_BASE`
+  mov rax, rbx        ; GS base saved on entry
+  mov rdx, rbx
+  shr rdx, 32
+  wrmsr
+%endmacro
+
+; Offset of `PerCpu::privilege_shadow_stack` (`stubs.rs` uses `offset_of!` instead).
+%define PERCPU_PRIVILEGE_SHADOW_STACK 40
+
+; With supervisor shadow stacks enabled, the interrupt's shadow stack frame (its old
+; SSP, return address and code segment) must agree with the interrupt stack frame,
+; which the handler may have rewritten; so it's rewritten to match with `wrss`. When
+; returning to userspace, the privilege level 0 token is restored instead, as the
+; interrupt may have switched away from the task it came from.
+;
+; `%1` is the offset of the interrupt stack frame, after the trace frame is popped.
+%macro _sync_shadow_stack 1
+  xor eax, eax
+  rdsspq rax   ; `rdssp` is a no-op with shadow stacks disabled
+  test rax, rax
+  jz %%done
+
+  mov rcx, [gs:PERCPU_PRIVILEGE_SHADOW_STACK]
+  test qword [rsp + %1 + 8], 0x3 ; are we returning to userspace?
+  jnz %%restore_token
+
+  cmp rax, rcx ; did the interrupt come from userspace?
+  jne %%write_frame
+  wrssq [rax], rcx ; old SSP is the privilege level 0 token
+
+  %%write_frame:
+  mov rcx, [rsp + %1]     ; return address
+  wrssq [rax + 8], rcx
+  mov rcx, [rsp + %1 + 8] ; code segment
+  wrssq [rax + 16], rcx
+  jmp %%done
+
+  %%restore_token:
+  wrssq [rcx], rcx ; supervisor token holds its own address
+
+  %%done:
+%endmacro
+
 %macro _exception_handler 1
 extern __%1_handler
 global __%1_stub
+__%1_stub:
+  _swapgs_if_user 8
+  cld
+
+  _save_registers
+  _save_trace_frame 15
+
+  lea rdi, [rsp + (17 * 8)] ; interrupt stack frame (1st param)
+  lea rsi, [rsp + (2 * 8)]  ; saved registers (2nd param)
+
+  call __%1_handler
+
+  add rsp, 0x10 ; pop trace frame
+
+  _sync_shadow_stack (15 * 8)
+  _restore_registers
+
+  _swapgs_if_user 8
+  iretq
+%endmacro
+
+%macro _exception_handler_paranoid 1
+extern __%1_handler
+global __%1_stub
 __%1_stub:
   cld
 
   _save_registers
+  _paranoid_gs_entry (20 * 8)
   _save_trace_frame 15
 
   lea rdi, [rsp + (17 * 8)] ; interrupt stack frame (1st param)
@@ -70,6 +174,8 @@ __%1_stub:
 
   add rsp, 0x10 ; pop trace frame
 
+  _sync_shadow_stack (15 * 8)
+  _paranoid_gs_exit
   _restore_registers
 
   iretq
@@ -79,6 +185,7 @@ __%1_stub:
 extern __%1_handler
 global __%1_stub
 __%1_stub:
+  _swapgs_if_user 16
   cld
 
   _save_registers
@@ -94,10 +201,12 @@ __%1_stub:
 
   add rsp, 0x18 ; pop trace frame & stack alignment
 
+  _sync_shadow_stack (16 * 8)
   _restore_registers
 
   add rsp, 0x8  ; pop interrupt error code
 
+  _swapgs_if_user 8
   iretq
 %endmacro
 
@@ -107,27 +216,6 @@ __%1_stub:
   jmp .halt_and_catch_fire
 %endmacro
 
-%macro _exception_handler_noreturn 1
-extern __%1_handler
-global __%1_stub
-__%1_stub:
-  cld
-
-  _save_registers
-  _save_trace_frame 15
-
-  lea rdi, [rsp + (17 * 8)] ; interrupt stack frame (1st param)
-  lea rsi, [rsp + (2 * 8)]  ; saved registers (2nd param)
-
-  call __%1_handler
-
-  add rsp, 0x10 ; pop trace frame
-
-  _restore_registers
-
-  _halt_and_catch_fire
-%endmacro
-
 %macro _exception_handler_with_error_noreturn 1
 extern __%1_handler
 global __%1_stub
@@ -135,6 +223,7 @@ __%1_stub:
   cld
 
   _save_registers
+  _paranoid_gs_entry (21 * 8)
   _save_trace_frame 16
 
   lea rdi, [rsp + (18 * 8)] ; interrupt stack frame (1st param)
@@ -157,11 +246,17 @@ __%1_stub:
 %macro _irq_stub 1
 global __irq_%1_stub
 __irq_%1_stub:
+  _swapgs_if_user 8
   cld
 
   _save_registers
   _save_trace_frame 15
 
+  rdtsc
+  shl rdx, 32
+  or rdx, rax
+  mov rcx, rdx              ; timestamp counter at entry (4th param)
+
   mov rdi, %1               ; IRQ vector (1st param)
   lea rsi, [rsp + (17 * 8)] ; interrupt stack frame (2nd param)
   lea rdx, [rsp + (2 * 8)]  ; saved registers (3rd param)
@@ -170,8 +265,10 @@ __irq_%1_stub:
 
   add rsp, 0x10 ; pop trace frame
 
+  _sync_shadow_stack (15 * 8)
   _restore_registers
 
+  _swapgs_if_user 8
   iretq
 %endmacro
 
@@ -179,7 +276,7 @@ __irq_%1_stub:
 
 _exception_handler de
 _exception_handler db
-_exception_handler nm
+_exception_handler_paranoid nm
 _exception_handler bp
 _exception_handler of
 _exception_handler br
@@ -195,8 +292,9 @@ _exception_handler_with_error ss
 _exception_handler_with_error gp
 _exception_handler_with_error pf
 _exception_handler_with_error ac
+_exception_handler_with_error cp
 
-_exception_handler_noreturn mc
+_exception_handler_paranoid mc
 
 _exception_handler_with_error_noreturn df
 
@@ -207,3 +305,13 @@ extern __irq_handler
   _irq_stub irq_number
   %assign irq_number irq_number+1
 %endrep
+
+section .rodata
+align 8
+global __irq_stub_table
+__irq_stub_table:
+%assign irq_number 32
+%rep 224
+  dq __irq_%[irq_number]_stub
+  %assign irq_number irq_number+1
+%endrep
@@ -1,9 +1,8 @@
-use crate::interrupts;
+use crate::{interrupts, sync::SpinMutex};
 // use acpi::platform::interrupt::{Polarity, TriggerMode};
 // use alloc::vec::Vec;
 use bit_field::BitField;
 use libkernel::mem::VolatileCell;
-use spin::Mutex;
 
 #[repr(transparent)]
 pub struct RedirectionEntry(u64);
@@ -101,7 +100,7 @@ impl RedirectionEntry {
     }
 }
 
-type IoApicRegisters<'a> = Mutex<(
+type IoApicRegisters<'a> = SpinMutex<(
     &'a VolatileCell<u32, libkernel::WriteOnly>,
     &'a VolatileCell<u32, libkernel::ReadWrite>,
 )>;
@@ -113,9 +112,9 @@ pub struct IoApic<'a> {
     ioregs: IoApicRegisters<'a>,
 }
 
-// Safety: Non-read-only mutations are behind a [`spin::Mutex`].
+// Safety: Non-read-only mutations are behind a [`SpinMutex`].
 unsafe impl Send for IoApic<'_> {}
-// Safety: Non-read-only mutations are behind a [`spin::Mutex`].
+// Safety: Non-read-only mutations are behind a [`SpinMutex`].
 unsafe impl Sync for IoApic<'_> {}
 
 impl IoApic<'_> {
@@ -218,7 +217,7 @@ impl IoApic<'_> {
 //                      let irq_base = ioapic_info.global_system_interrupt_base;
 //                      let handled_irqs = irq_base..=(irq_base + irq_count);
 
-//                      IoApic { id, version, handled_irqs, ioregs: Mutex::new((ioregsel, ioregwin)) }
+//                      IoApic { id, version, handled_irqs, ioregs: SpinMutex::new((ioregsel, ioregwin)) }
 //                  })
 //                  .collect()
 //          } else {
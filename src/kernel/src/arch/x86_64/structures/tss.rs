@@ -4,10 +4,48 @@ use crate::{
     arch::x86_64::structures::gdt::{GlobalDescriptorTable, SystemSegmentDescriptor},
     mem::alloc::KERNEL_ALLOCATOR,
 };
-use core::ptr::NonNull;
+use core::{ptr::NonNull, sync::atomic::Ordering};
 
 type StackTableStack = crate::mem::stack::Stack<0x16000>;
 
+/// Length of the slot at the top of each stack table stack, above where the processor pushes the interrupt stack
+/// frame, which holds the address of the hardware thread's [`PerCpu`] block; it keeps the stack pointer 16-byte
+/// aligned.
+///
+/// The stubs of interrupts which may be taken anywhere (i.e. NMIs) load the GS base from it, as the GS base they're
+/// entered with may be userspace's.
+///
+/// [`PerCpu`]: crate::cpu::percpu::PerCpu
+const PERCPU_SLOT_LEN: usize = 0x10;
+
+/// Length of the I/O permission bit map, which has a bit for each of the 65536 I/O ports.
+pub const IO_BITMAP_LEN: usize = 0x2000;
+
+/// Ports userspace is permitted to access with I/O instructions, which are those whose bits are clear.
+#[derive(Clone)]
+pub struct IoBitmap([u8; IO_BITMAP_LEN]);
+
+impl IoBitmap {
+    /// Constructs an [`IoBitmap`] permitting no ports.
+    pub const fn new() -> Self {
+        Self([u8::MAX; IO_BITMAP_LEN])
+    }
+
+    /// Permits the `count` ports from `first`.
+    pub fn allow(&mut self, first: u16, count: u16) {
+        for port in u32::from(first)..(u32::from(first) + u32::from(count)) {
+            let port = usize::try_from(port).unwrap();
+            self.0[port / 8] &= !(1 << (port % 8));
+        }
+    }
+
+    pub fn allows(&self, port: u16) -> bool {
+        let port = usize::from(port);
+
+        self.0[port / 8] & (1 << (port % 8)) == 0
+    }
+}
+
 // Pre-defined indexes into the interrupt stack table (IST).
 #[repr(u16)]
 #[derive(Debug, IntoPrimitive, Clone, Copy, PartialEq, Eq)]
@@ -25,18 +63,28 @@ pub struct TaskStateSegment {
 
     /// The stack pointers used when a privilege level change occurs from a lower privilege level
     /// to a higher one (e.g. ring 3 to ring 0).
-    privilege_stack_table: [Option<NonNull<StackTableStack>>; 3],
+    privilege_stack_table: [Option<NonNull<u8>>; 3],
 
     _2: [u8; 8],
 
     /// The stack pointers used when an entry in the Interrupt Descriptor Table has an IST value
     /// other than 0.
-    interrupt_stack_table: [Option<NonNull<StackTableStack>>; 7],
+    interrupt_stack_table: [Option<NonNull<u8>>; 7],
 
     _3: [u8; 10],
 
     /// The 16-bit offset to the I/O permission bit map from the 64-bit TSS base.
     iomap_base: u16,
+
+    /// Ports the running task may access, which is checked by I/O instructions issued from user mode.
+    io_bitmap: [u8; IO_BITMAP_LEN],
+    /// The processor reads two bytes of the bit map for each access, so it's followed by a byte with every bit set.
+    io_bitmap_end: u8,
+
+    /// Whether any port is permitted by the I/O permission bit map; this isn't read by the processor.
+    is_io_bitmap_loaded: bool,
+    /// Stacks the tables point into, so their canaries can be checked; this isn't read by the processor.
+    stacks: [Option<NonNull<StackTableStack>>; 5],
 }
 
 impl TaskStateSegment {
@@ -48,28 +96,65 @@ impl TaskStateSegment {
     /// runtime error if more than one are loaded per hardware threads.
     pub fn load_local() {
         fn allocate_stack_table_stack() -> NonNull<StackTableStack> {
-            KERNEL_ALLOCATOR
+            let mut stack = KERNEL_ALLOCATOR
                 .allocate_t::<StackTableStack>()
-                .expect("failed to allocate a new stack for task state segment")
+                .expect("failed to allocate a new stack for task state segment");
+
+            // Safety: The stack was just allocated, so there are no other references to it.
+            unsafe { stack.as_mut() }.arm_canary();
+
+            stack
+        }
+
+        /// Stack pointer for `stack`, below the slot at its top, which is filled with the address of the current
+        /// hardware thread's per-CPU block.
+        fn stack_pointer(stack: NonNull<StackTableStack>) -> NonNull<u8> {
+            let percpu = core::ptr::from_ref(crate::cpu::percpu::PerCpu::current());
+
+            // Safety: The stack was just allocated, so there are no other references to it.
+            let top = unsafe { stack.as_ref() }.top().cast::<u8>();
+
+            // Safety: The slot is within the stack, which is much longer than it.
+            let slot = unsafe { top.byte_sub(PERCPU_SLOT_LEN) };
+
+            // Safety: The slot is within the stack, and 16-byte aligned, as the stack's top is.
+            unsafe {
+                slot.cast::<*const crate::cpu::percpu::PerCpu>()
+                    .write(percpu)
+            };
+
+            slot
         }
 
         let tss = crate::mem::alloc::KERNEL_ALLOCATOR
             .allocate_t_static::<Self>()
             .expect("failed to allocate task state segment");
 
+        // No ports are permitted until a task which was granted some is switched in.
+        tss.iomap_base = u16::try_from(core::mem::offset_of!(Self, io_bitmap)).unwrap();
+        tss.io_bitmap = IoBitmap::new().0;
+        tss.io_bitmap_end = u8::MAX;
+
         // Set the stack for transitions to ring 0.
-        tss.privilege_stack_table[0] = Some(allocate_stack_table_stack());
+        let privilege_stack = allocate_stack_table_stack();
+        tss.privilege_stack_table[0] = Some(stack_pointer(privilege_stack));
+        tss.stacks[0] = Some(privilege_stack);
 
         // Set the stacks for faults that cannot be disabled or are caused by runtime errors.
-        tss.interrupt_stack_table[usize::from(u16::from(InterruptStackTableIndex::Debug))] =
-            Some(allocate_stack_table_stack());
-        tss.interrupt_stack_table
-            [usize::from(u16::from(InterruptStackTableIndex::NonMaskableInterrupt))] =
-            Some(allocate_stack_table_stack());
-        tss.interrupt_stack_table[usize::from(u16::from(InterruptStackTableIndex::DoubleFault))] =
-            Some(allocate_stack_table_stack());
-        tss.interrupt_stack_table[usize::from(u16::from(InterruptStackTableIndex::MachineCheck))] =
-            Some(allocate_stack_table_stack());
+        for (stack_index, table_index) in [
+            InterruptStackTableIndex::Debug,
+            InterruptStackTableIndex::NonMaskableInterrupt,
+            InterruptStackTableIndex::DoubleFault,
+            InterruptStackTableIndex::MachineCheck,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let stack = allocate_stack_table_stack();
+            tss.interrupt_stack_table[usize::from(u16::from(table_index))] =
+                Some(stack_pointer(stack));
+            tss.stacks[stack_index + 1] = Some(stack);
+        }
 
         GlobalDescriptorTable::with_temporary(|temp_gdt| {
             let tss_segment_descriptor = SystemSegmentDescriptor::from_tss(tss);
@@ -86,5 +171,73 @@ impl TaskStateSegment {
                 );
             }
         });
+
+        crate::percpu!(tss).store(core::ptr::from_mut(tss), Ordering::Relaxed);
+    }
+
+    /// Loads `io_bitmap` into the current hardware thread's [`TaskStateSegment`], or permits no ports if it's `None`.
+    ///
+    /// # Remarks
+    ///
+    /// This must be called without interrupts, as the running task is switched in, or changes its own bit map.
+    pub fn load_io_bitmap(io_bitmap: Option<&IoBitmap>) {
+        // Safety: Each hardware thread's segment is only accessed by that hardware thread, without interrupts, so
+        //         there are no other references to it; it's `None` before the segment is loaded.
+        let Some(tss) = (unsafe { crate::percpu!(tss).load(Ordering::Relaxed).as_mut() }) else {
+            return;
+        };
+
+        match io_bitmap {
+            Some(io_bitmap) => {
+                tss.io_bitmap = io_bitmap.0;
+                tss.is_io_bitmap_loaded = true;
+            }
+
+            // Most tasks aren't granted ports, so the bit map is only rewritten if the last task was.
+            None if tss.is_io_bitmap_loaded => {
+                tss.io_bitmap = IoBitmap::new().0;
+                tss.is_io_bitmap_loaded = false;
+            }
+
+            None => {}
+        }
+    }
+
+    /// Whether the canaries at the bases of the current hardware thread's privilege & interrupt stacks are intact
+    /// (which they are, trivially, before its segment is loaded).
+    pub fn local_stacks_intact() -> bool {
+        // Safety: Each hardware thread's segment is only written by that hardware thread, without interrupts; it's
+        //         `None` before the segment is loaded.
+        let Some(tss) = (unsafe { crate::percpu!(tss).load(Ordering::Relaxed).as_ref() }) else {
+            return true;
+        };
+
+        // The segment is packed, so its stacks are copied out rather than borrowed.
+        let stacks = tss.stacks;
+
+        stacks
+            .into_iter()
+            .flatten()
+            // Safety: The segment's stacks are allocated (and armed) as it's loaded, and never freed.
+            .all(|stack| unsafe { stack.as_ref() }.canary_intact())
+    }
+}
+
+crate::kernel_test! {
+    /// Only the ports an I/O permission bit map allows have their bits cleared.
+    fn io_bitmap_allows_ranges() -> crate::tests::Outcome {
+        let mut io_bitmap = IoBitmap::new();
+        io_bitmap.allow(0x3F8, 8);
+        io_bitmap.allow(u16::MAX, 1);
+
+        if !(0x3F8..0x400).all(|port| io_bitmap.allows(port)) || !io_bitmap.allows(u16::MAX) {
+            return Err("allowed port isn't permitted");
+        }
+
+        if io_bitmap.allows(0x3F7) || io_bitmap.allows(0x400) {
+            return Err("port outside the allowed range is permitted");
+        }
+
+        Ok(())
     }
 }
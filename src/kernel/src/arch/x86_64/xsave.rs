@@ -0,0 +1,228 @@
+//! Saving and restoring tasks' extended (x87, SSE, AVX) register state.
+//!
+//! With XSAVE supported, every state component the kernel knows how to enable is enabled in `XCR0`, and state is
+//! saved with `xsaveopt` (or `xsave`) and restored with `xrstor`. Otherwise only the x87 and SSE state is, with
+//! `fxsave` and `fxrstor`.
+//!
+//! The kernel itself is built without SIMD, so a task's extended state stays in the registers from when it's
+//! interrupted until it's switched out.
+
+use crate::arch::x86_64::{
+    cpuid::{extended_state_info, feature_info},
+    registers::control::{XCR0, XCR0Flags},
+};
+use core::{alloc::Layout, ptr::NonNull};
+use spin::Once;
+
+/// Alignment required of a save area by `xsave` (`fxsave` only requires 16 bytes).
+const AREA_ALIGN: usize = 64;
+/// Size of the legacy region of a save area, which is all `fxsave` uses.
+const LEGACY_AREA_SIZE: usize = 512;
+
+/// Offset of the x87 control word within the legacy region.
+const FCW_OFFSET: usize = 0;
+/// Offset of `MXCSR` within the legacy region.
+const MXCSR_OFFSET: usize = 24;
+/// x87 control word after `fninit`, with every exception masked.
+const DEFAULT_FCW: u16 = 0x037F;
+/// `MXCSR` at reset, with every exception masked.
+const DEFAULT_MXCSR: u32 = 0x1F80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mechanism {
+    Fxsave,
+    Xsave,
+    Xsaveopt,
+}
+
+struct Config {
+    mechanism: Mechanism,
+    components: XCR0Flags,
+    area_size: usize,
+}
+
+crate::ro_after_init! {
+    static CONFIG: Once<Config> = Once::new();
+}
+
+/// Whether `CR4.OSXSAVE` should be set on the current hardware thread.
+pub fn is_supported() -> bool {
+    feature_info().is_some_and(raw_cpuid::FeatureInfo::has_xsave) && extended_state_info().is_some()
+}
+
+fn detect() -> Config {
+    let Some(info) = extended_state_info().filter(|_| is_supported()) else {
+        return Config {
+            mechanism: Mechanism::Fxsave,
+            components: XCR0Flags::X87 | XCR0Flags::SSE,
+            area_size: LEGACY_AREA_SIZE,
+        };
+    };
+
+    let mut components = XCR0Flags::X87 | XCR0Flags::SSE;
+
+    if info.xcr0_supports_avx_256() {
+        components.insert(XCR0Flags::AVX);
+
+        // The AVX-512 components can only be enabled together, and with AVX.
+        if info.xcr0_supports_avx512_opmask()
+            && info.xcr0_supports_avx512_zmm_hi256()
+            && info.xcr0_supports_avx512_zmm_hi16()
+        {
+            components.insert(XCR0Flags::OPMASK | XCR0Flags::ZMM_HI256 | XCR0Flags::HI16_ZMM);
+        }
+    }
+
+    Config {
+        mechanism: if info.has_xsaveopt() {
+            Mechanism::Xsaveopt
+        } else {
+            Mechanism::Xsave
+        },
+        components,
+        // Large enough for every supported component, so it doesn't depend on what's enabled at the time of reading.
+        area_size: usize::try_from(info.xsave_area_size_supported_features()).unwrap(),
+    }
+}
+
+/// Enables the extended state components to be saved for tasks on the current hardware thread.
+///
+/// # Safety
+///
+/// This must be called after `CR4.OSXSAVE` is set (if [`is_supported`]), and before any task runs.
+pub unsafe fn configure() {
+    let config = CONFIG.call_once(detect);
+
+    if config.mechanism != Mechanism::Fxsave {
+        trace!("Configuring `XCR0`: {:?}", config.components);
+
+        // Safety: Only components reported by CPUID are enabled, and no task has run yet.
+        unsafe {
+            XCR0::write(config.components);
+        }
+    }
+}
+
+fn config() -> &'static Config {
+    CONFIG
+        .get()
+        .expect("extended state saving has not been configured")
+}
+
+/// Layout of a save area.
+pub fn area_layout() -> Layout {
+    Layout::from_size_align(config().area_size, AREA_ALIGN).unwrap()
+}
+
+/// Fills a zeroed save area with the initial state (i.e. every floating point exception masked).
+pub fn init_area(area: &mut [u8]) {
+    area[FCW_OFFSET..(FCW_OFFSET + 2)].copy_from_slice(&DEFAULT_FCW.to_le_bytes());
+    area[MXCSR_OFFSET..(MXCSR_OFFSET + 4)].copy_from_slice(&DEFAULT_MXCSR.to_le_bytes());
+}
+
+/// Saves the current hardware thread's extended state to `area`.
+///
+/// # Safety
+///
+/// `area` must be valid for writes of [`area_layout`].
+pub unsafe fn save(area: NonNull<u8>) {
+    let config = config();
+    let components = config.components.bits();
+
+    // Safety: Caller is required to provide a valid save area, and the components are enabled.
+    unsafe {
+        match config.mechanism {
+            Mechanism::Fxsave => {
+                core::arch::asm!("fxsave64 [{}]", in(reg) area.as_ptr(), options(nostack, preserves_flags));
+            }
+
+            Mechanism::Xsave => {
+                core::arch::asm!(
+                    "xsave64 [{}]",
+                    in(reg) area.as_ptr(),
+                    in("eax") components & 0xFFFF_FFFF,
+                    in("edx") components >> 32,
+                    options(nostack, preserves_flags)
+                );
+            }
+
+            Mechanism::Xsaveopt => {
+                core::arch::asm!(
+                    "xsaveopt64 [{}]",
+                    in(reg) area.as_ptr(),
+                    in("eax") components & 0xFFFF_FFFF,
+                    in("edx") components >> 32,
+                    options(nostack, preserves_flags)
+                );
+            }
+        }
+    }
+}
+
+/// Loads the current hardware thread's extended state from `area`.
+///
+/// # Safety
+///
+/// `area` must be valid for reads of [`area_layout`], and hold state saved by [`save`] or initialized by
+/// [`init_area`].
+pub unsafe fn restore(area: NonNull<u8>) {
+    let config = config();
+    let components = config.components.bits();
+
+    // Safety: Caller is required to provide a valid save area, and the components are enabled.
+    unsafe {
+        match config.mechanism {
+            Mechanism::Fxsave => {
+                core::arch::asm!("fxrstor64 [{}]", in(reg) area.as_ptr(), options(nostack, preserves_flags, readonly));
+            }
+
+            Mechanism::Xsave | Mechanism::Xsaveopt => {
+                core::arch::asm!(
+                    "xrstor64 [{}]",
+                    in(reg) area.as_ptr(),
+                    in("eax") components & 0xFFFF_FFFF,
+                    in("edx") components >> 32,
+                    options(nostack, preserves_flags, readonly)
+                );
+            }
+        }
+    }
+}
+
+crate::kernel_test! {
+    /// Restoring a saved area brings back the state it was saved with.
+    fn round_trip() -> crate::tests::Outcome {
+        const SAVED: u64 = 0x0123_4567_89AB_CDEF;
+
+        fn set_xmm0(value: u64) {
+            // Safety: The kernel doesn't otherwise use SIMD registers, and no task's state is live while tests run.
+            unsafe {
+                core::arch::asm!("movq xmm0, {}", in(reg) value, options(nostack, nomem, preserves_flags));
+            }
+        }
+
+        fn get_xmm0() -> u64 {
+            let value: u64;
+
+            // Safety: Reading a register has no side effects.
+            unsafe {
+                core::arch::asm!("movq {}, xmm0", out(reg) value, options(nostack, nomem, preserves_flags));
+            }
+
+            value
+        }
+
+        let mut state = crate::task::ExtendedState::new();
+
+        set_xmm0(SAVED);
+        state.save();
+        set_xmm0(!SAVED);
+        state.restore();
+
+        if get_xmm0() != SAVED {
+            return Err("restored state differs from the saved state");
+        }
+
+        Ok(())
+    }
+}
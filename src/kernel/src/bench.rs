@@ -0,0 +1,85 @@
+//! Boot-time micro-benchmarks, run on every hardware thread when requested with `--bench`.
+//!
+//! Currently this measures false sharing: every hardware thread increments its own counter, first with the
+//! counters packed next to each other (so they share cache lines), then with each padded by [`CachePadded`]. The
+//! difference grows with the number of hardware threads, and is pronounced from around 8.
+
+use crate::util::CachePadded;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use spin::{Barrier, Once};
+
+/// Increments each hardware thread makes per layout.
+const ITERATIONS: u64 = 1_000_000;
+
+/// Most hardware threads which get their own counter; any beyond this share the last.
+const MAX_PARTICIPANTS: usize = 64;
+
+crate::parameters! {
+    /// Whether to run the boot-time benchmarks on every hardware thread.
+    "bench": bool = false,
+}
+
+static PARTICIPANTS: Once<Barrier> = Once::new();
+static NEXT_SLOT: AtomicUsize = AtomicUsize::new(0);
+
+static PACKED: [AtomicU64; MAX_PARTICIPANTS] = [const { AtomicU64::new(0) }; MAX_PARTICIPANTS];
+static PADDED: [CachePadded<AtomicU64>; MAX_PARTICIPANTS] =
+    [const { CachePadded::new(AtomicU64::new(0)) }; MAX_PARTICIPANTS];
+
+/// Slowest time (in nanoseconds) any hardware thread took, for each layout.
+static PACKED_NS: AtomicU64 = AtomicU64::new(0);
+static PADDED_NS: AtomicU64 = AtomicU64::new(0);
+
+/// Prepares the benchmarks to be run by `hwthread_count` hardware threads.
+///
+/// # Remarks
+///
+/// This must be called by the bootstrap processor before any hardware thread calls [`run`].
+pub fn prepare(hwthread_count: usize) {
+    PARTICIPANTS.call_once(|| Barrier::new(hwthread_count));
+}
+
+/// Runs the benchmarks on the current hardware thread, if they were prepared.
+///
+/// # Remarks
+///
+/// Every participating hardware thread must call this, as each benchmark begins once all have arrived.
+pub fn run(is_bsp: bool) {
+    let Some(participants) = PARTICIPANTS.get() else {
+        return;
+    };
+
+    let slot = NEXT_SLOT
+        .fetch_add(1, Ordering::Relaxed)
+        .min(MAX_PARTICIPANTS - 1);
+
+    participants.wait();
+    measure(&PACKED[slot], &PACKED_NS);
+
+    participants.wait();
+    measure(&PADDED[slot], &PADDED_NS);
+
+    participants.wait();
+
+    if is_bsp {
+        let packed_ns = PACKED_NS.load(Ordering::Relaxed);
+        let padded_ns = PADDED_NS.load(Ordering::Relaxed);
+
+        info!(
+            "False sharing benchmark ({} hardware threads, {ITERATIONS} increments each): packed {}us, padded {}us",
+            NEXT_SLOT.load(Ordering::Relaxed),
+            packed_ns / 1000,
+            padded_ns / 1000,
+        );
+    }
+}
+
+fn measure(counter: &AtomicU64, slowest_ns: &AtomicU64) {
+    let start_ns = crate::time::now_ns();
+    for _ in 0..ITERATIONS {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+    let elapsed_ns = crate::time::now_ns() - start_ns;
+
+    slowest_ns.fetch_max(elapsed_ns, Ordering::Relaxed);
+}
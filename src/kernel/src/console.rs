@@ -0,0 +1,208 @@
+//! Interactive serial console input.
+//!
+//! Received bytes are buffered from the serial receive interrupt when it can be routed, and polled from the UART
+//! otherwise. Receiving [`crate::debugger::ESCAPE_SEQUENCE`] enters the kernel debug shell, unless GDB is attached.
+//!
+//! Lines read for the debug shell also take characters typed at the keyboard (see [`crate::devices::input`]).
+
+use crate::{debugger::gdb, interrupts::InterruptCell, logging::serial, sync::SpinMutex};
+use alloc::string::String;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use heapless::Deque;
+
+const RX_BUFFER_SIZE: usize = 256;
+
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7F;
+
+static RX_BUFFER: InterruptCell<SpinMutex<Deque<u8, RX_BUFFER_SIZE>>> =
+    InterruptCell::new(SpinMutex::new(Deque::new()));
+
+/// Whether received bytes are delivered by the serial receive interrupt.
+static IRQ_DRIVEN: AtomicBool = AtomicBool::new(false);
+
+/// Number of bytes of the debugger escape sequence received consecutively.
+static ESCAPE_MATCHED: AtomicUsize = AtomicUsize::new(0);
+
+/// Tracks `byte` against the debugger escape sequence, returning whether it completed the sequence.
+fn match_escape(byte: u8) -> bool {
+    let escape_sequence = crate::debugger::ESCAPE_SEQUENCE;
+    let matched = ESCAPE_MATCHED.load(Ordering::Relaxed);
+
+    if escape_sequence.get(matched) == Some(&byte) {
+        if matched + 1 == escape_sequence.len() {
+            ESCAPE_MATCHED.store(0, Ordering::Relaxed);
+
+            return true;
+        }
+
+        ESCAPE_MATCHED.store(matched + 1, Ordering::Relaxed);
+    } else {
+        ESCAPE_MATCHED.store(
+            usize::from(escape_sequence.first() == Some(&byte)),
+            Ordering::Relaxed,
+        );
+    }
+
+    false
+}
+
+/// Moves received bytes into the receive buffer, then enters the debug shell if it was requested.
+fn buffer_received() {
+    let Some(serial) = serial::get() else {
+        return;
+    };
+
+    let mut escaped = false;
+    let mut interrupted = false;
+    RX_BUFFER.with(|rx_buffer| {
+        let mut rx_buffer = rx_buffer.lock();
+
+        serial.receive(|byte| {
+            if gdb::is_attached() {
+                // GDB interrupts a running target with a lone byte, sent outside of any packet.
+                if byte == gdb::INTERRUPT && !gdb::is_stopped() {
+                    interrupted = true;
+                    return;
+                }
+            } else {
+                escaped |= !crate::debugger::is_active() && match_escape(byte);
+            }
+
+            // Drop input when the reader has fallen behind, rather than blocking in interrupt context.
+            rx_buffer.push_back(byte).ok();
+        });
+    });
+
+    if interrupted {
+        gdb::interrupt();
+    }
+
+    if escaped {
+        crate::debugger::enter();
+    }
+}
+
+fn on_receive(_: u8, _: crate::interrupts::irq::IrqTimestamp) {
+    buffer_received();
+}
+
+/// Routes the serial receive interrupt, so input is buffered as it arrives.
+///
+/// If the interrupt can't be routed, the console falls back to polling the UART.
+pub fn init() {
+    let Some(serial) = serial::get() else {
+        warn!("Serial console is unavailable: no serial device.");
+        return;
+    };
+
+    match crate::interrupts::irq::register_isa(serial::COM1_IRQ, on_receive) {
+        Ok(vector) => {
+            serial.set_receive_interrupt(true);
+            IRQ_DRIVEN.store(true, Ordering::Release);
+
+            debug!("Serial console receiving on vector {vector:#X}.");
+        }
+
+        Err(error) => {
+            warn!("Serial console will poll for input: {error}");
+        }
+    }
+}
+
+/// Takes the next received byte, if any.
+pub fn read_byte() -> Option<u8> {
+    // Poll when the receive interrupt can't be delivered, i.e. from within the debug shell.
+    if !IRQ_DRIVEN.load(Ordering::Acquire) || !crate::interrupts::is_enabled() {
+        buffer_received();
+    }
+
+    RX_BUFFER.with(|rx_buffer| rx_buffer.lock().pop_front())
+}
+
+/// Takes the next byte received over the serial line, or typed at the keyboard, if any.
+fn read_input() -> Option<u8> {
+    if !crate::devices::i8042::is_irq_driven() || !crate::interrupts::is_enabled() {
+        crate::devices::i8042::poll();
+    }
+
+    read_byte().or_else(crate::devices::input::pop_ascii)
+}
+
+/// Blocks until a byte is received.
+pub fn wait_byte() -> u8 {
+    wait_for(read_byte)
+}
+
+/// Blocks until `read` returns a byte.
+fn wait_for(read: fn() -> Option<u8>) -> u8 {
+    loop {
+        if let Some(byte) = read() {
+            return byte;
+        }
+
+        if IRQ_DRIVEN.load(Ordering::Acquire) && crate::interrupts::is_enabled() {
+            crate::interrupts::wait_next();
+        } else {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+/// Writes `s` directly to the console.
+pub fn write_str(s: &str) {
+    if let Some(serial) = serial::get() {
+        serial.write_bytes(s.as_bytes());
+    }
+}
+
+/// Formatted output directly to the console, translating line feeds for serial terminals.
+pub struct Writer;
+
+impl core::fmt::Write for Writer {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for (index, line) in s.split('\n').enumerate() {
+            if index > 0 {
+                write_str("\r\n");
+            }
+
+            write_str(line);
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a line of input (from the serial line or the keyboard) into `line`, echoing it back, until a carriage
+/// return or line feed is received.
+///
+/// Returns the number of bytes read, excluding the line terminator.
+pub fn read_line(line: &mut String) -> usize {
+    let start_len = line.len();
+
+    loop {
+        match wait_for(read_input) {
+            b'\r' | b'\n' => {
+                write_str("\r\n");
+
+                return line.len() - start_len;
+            }
+
+            BACKSPACE | DELETE => {
+                if line.len() > start_len {
+                    line.pop();
+                    write_str("\x08 \x08");
+                }
+            }
+
+            byte if byte.is_ascii_graphic() || byte == b' ' => {
+                line.push(char::from(byte));
+                write_str(core::str::from_utf8(&[byte]).unwrap());
+            }
+
+            _ => {
+                // Ignore control & non-ASCII input.
+            }
+        }
+    }
+}
@@ -1,11 +1,18 @@
 use crate::{
     interrupts::{InterruptCell, exceptions::Exception},
+    logging::staging::Staging,
     mem::alloc::KERNEL_ALLOCATOR,
-    task::Scheduler,
-    time::LocalTimer,
+    rand::prng::LocalRng,
+    sync::SpinMutex,
+    task::{Scheduler, workqueue::WorkQueue},
+    time::{LocalTimer, timers::TimerWheel},
+};
+use core::{
+    cell::UnsafeCell,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
 };
-use core::{cell::UnsafeCell, ptr::NonNull, sync::atomic::AtomicBool, time::Duration};
-use spin::Mutex;
 
 pub const STACK_SIZE: usize = 0x10000;
 pub const SYSCALL_STACK_SIZE: usize = 0x40000;
@@ -17,18 +24,19 @@ pub enum ExceptionCatcher {
 }
 
 fn try_get_local_static_ptr() -> Option<NonNull<LocalState>> {
-    #[cfg(target_arch = "x86_64")]
-    {
-        crate::arch::x86_64::registers::model_specific::IA32_KERNEL_GS_BASE::read()
-    }
+    NonNull::new(crate::percpu!(local_state).load(Ordering::Acquire))
 }
 
 /// Local (to the current hardware thread) state structure.
 pub struct LocalState {
     timer: LocalTimer,
-    scheduler: InterruptCell<Mutex<Scheduler>>,
+    scheduler: InterruptCell<SpinMutex<Scheduler>>,
+    rng: InterruptCell<SpinMutex<LocalRng>>,
+    timers: InterruptCell<SpinMutex<TimerWheel>>,
     catch_exception: AtomicBool,
     exception: UnsafeCell<Option<Exception>>,
+    log_staging: InterruptCell<SpinMutex<Staging>>,
+    work_queue: WorkQueue,
 }
 
 impl LocalState {
@@ -45,6 +53,15 @@ impl LocalState {
         trace!("Configuring local scheduler...");
         let scheduler = Scheduler::new().expect("failed to allocate idle stack");
 
+        trace!("Seeding local random state...");
+        let rng = LocalRng::new();
+
+        trace!("Configuring local timer wheel...");
+        let timers = TimerWheel::new();
+
+        trace!("Allocating local work queue...");
+        let work_queue = WorkQueue::new().expect("failed to allocate worker stack");
+
         let local_state_ptr = KERNEL_ALLOCATOR
             .allocate_t::<LocalState>()
             .expect("failed to allocate local state");
@@ -53,27 +70,38 @@ impl LocalState {
         unsafe {
             local_state_ptr.write(LocalState {
                 timer,
-                scheduler: InterruptCell::new(Mutex::new(scheduler)),
+                scheduler: InterruptCell::new(SpinMutex::new(scheduler)),
+                rng: InterruptCell::new(SpinMutex::new(rng)),
+                timers: InterruptCell::new(SpinMutex::new(timers)),
                 catch_exception: AtomicBool::new(false),
                 exception: UnsafeCell::new(None),
+                log_staging: InterruptCell::new(SpinMutex::new(Staging::new())),
+                work_queue,
             });
         }
 
         // Set the local state pointer for this hardware thread.
-        #[cfg(target_arch = "x86_64")]
-        crate::arch::x86_64::registers::model_specific::IA32_KERNEL_GS_BASE::write(local_state_ptr);
+        crate::percpu!(local_state).store(local_state_ptr.as_ptr(), Ordering::Release);
 
         debug!("Local state has been initialized.");
     }
 
+    /// Whether the local state has been initialized for the current hardware thread.
+    pub fn is_initialized() -> bool {
+        try_get_local_static_ptr().is_some()
+    }
+
+    /// Gets the local hardware thread state structure, if it has been initialized.
+    fn try_get_static() -> Option<&'static Self> {
+        try_get_local_static_ptr().map(|local_state_ptr| {
+            // Safety: If the state pointer is non-null, the kernel guarantees it will be valid for reading as `LocalState`.
+            unsafe { local_state_ptr.as_ref() }
+        })
+    }
+
     /// Gets the local hardware thread state structure.
     fn get_static() -> &'static Self {
-        try_get_local_static_ptr()
-            .map(|local_state_ptr| {
-                // Safety: If the state pointer is non-null, the kernel guarantees it will be valid for reading as `LocalState`.
-                unsafe { local_state_ptr.as_ref() }
-            })
-            .expect("local state has not been initialized")
+        Self::try_get_static().expect("local state has not been initialized")
     }
 
     pub fn with_scheduler<T>(func: impl FnOnce(&mut Scheduler) -> T) -> T {
@@ -84,6 +112,32 @@ impl LocalState {
         })
     }
 
+    /// Runs `func` with the scheduler, or returns `None` if the local state hasn't been initialized, or the scheduler
+    /// is locked (i.e. by whatever was interrupted).
+    pub fn try_with_scheduler<T>(func: impl FnOnce(&mut Scheduler) -> T) -> Option<T> {
+        Self::try_get_static()?.scheduler.with(|scheduler| {
+            scheduler
+                .try_lock()
+                .map(|mut scheduler| func(&mut scheduler))
+        })
+    }
+
+    pub fn with_rng<T>(func: impl FnOnce(&mut LocalRng) -> T) -> T {
+        Self::get_static().rng.with(|rng| {
+            let mut rng = rng.lock();
+
+            func(&mut rng)
+        })
+    }
+
+    pub fn with_timers<T>(func: impl FnOnce(&mut TimerWheel) -> T) -> T {
+        Self::get_static().timers.with(|timers| {
+            let mut timers = timers.lock();
+
+            func(&mut timers)
+        })
+    }
+
     /// ## Safety
     ///
     /// - Function should only be called once the last preemption wait has resolved.
@@ -93,6 +147,45 @@ impl LocalState {
             .set_wait(duration)
             .expect("preemption wait duration was too long");
     }
+
+    pub fn irq_timestamp() -> crate::interrupts::irq::IrqTimestamp {
+        crate::interrupts::irq::IrqTimestamp::from_ticks(
+            crate::percpu!(irq_timestamp).load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn set_irq_timestamp(timestamp: crate::interrupts::irq::IrqTimestamp) {
+        crate::percpu!(irq_timestamp).store(timestamp.ticks(), Ordering::Relaxed);
+    }
+
+    /// Marks the start of an interrupt handler on the current hardware thread.
+    pub fn enter_interrupt() {
+        crate::percpu!(interrupt_depth).fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Marks the end of an interrupt handler on the current hardware thread.
+    pub fn exit_interrupt() {
+        crate::percpu!(interrupt_depth).fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Whether the current hardware thread is handling an interrupt.
+    pub fn in_interrupt() -> bool {
+        crate::percpu!(interrupt_depth).load(Ordering::Relaxed) > 0
+    }
+
+    /// Passes the current hardware thread's work queue to `func`, if the local state has been initialized.
+    pub fn with_work_queue<T>(func: impl FnOnce(&'static WorkQueue) -> T) -> Option<T> {
+        Self::try_get_static().map(|local_state| func(&local_state.work_queue))
+    }
+
+    /// Passes the current hardware thread's log staging buffer to `func`, if the local state has been initialized.
+    pub fn with_log_staging<T>(func: impl FnOnce(&mut Staging) -> T) -> Option<T> {
+        Self::try_get_static().map(|local_state| {
+            local_state
+                .log_staging
+                .with(|staging| func(&mut staging.lock()))
+        })
+    }
 }
 
 // /// TODO inline this function
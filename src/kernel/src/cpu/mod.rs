@@ -1,18 +1,19 @@
-use crate::{arch::x86_64::devices::x2apic::x2Apic, cpu::local_state::LocalState};
-use core::{
-    ops::Range,
-    sync::atomic::{AtomicBool, Ordering},
-};
-use libsys::{Address, Frame, Physical};
-use spin::{Barrier, Once, RwLock};
+use crate::{arch::x86_64::devices::local_apic::LocalApic, cpu::local_state::LocalState};
+use libsys::{Address, Frame};
+use spin::{Barrier, Once};
 
 pub mod local_state;
+pub mod percpu;
+pub mod topology;
 
+crate::parameters! {
+    /// Whether to leave every hardware thread but the bootstrap processor idle.
+    "nomp": bool = false,
+}
+
+/// Gets the ID of the current hardware thread, as cached in its [`percpu::PerCpu`] block.
 pub fn get_id() -> u32 {
-    #[cfg(target_arch = "x86_64")]
-    {
-        crate::arch::x86_64::get_hwthread_id()
-    }
+    crate::percpu!(hwthread_id).load(core::sync::atomic::Ordering::Relaxed)
 }
 
 /// # Safety
@@ -23,6 +24,8 @@ pub fn get_id() -> u32 {
 pub unsafe fn configure() {
     // Safety: Caller is required to meet invariants.
     unsafe {
+        percpu::init();
+
         #[cfg(target_arch = "x86_64")]
         crate::arch::x86_64::configure_hwthread();
     }
@@ -33,7 +36,7 @@ pub unsafe fn configure() {
 ///
 /// # Returns
 ///
-/// - If request was satisfied, `Some` of the count of non-bootstrap hardware threads in the system.
+/// - If request was satisfied, `Some` of the count of hardware threads which will synchronize.
 /// - If request was not satisfied, `None`.
 pub fn begin_multiprocessing(mp_request: &limine::request::MpRequest) -> Option<usize> {
     let Some(response) = mp_request.get_response() else {
@@ -43,6 +46,8 @@ pub fn begin_multiprocessing(mp_request: &limine::request::MpRequest) -> Option<
 
     debug!("Detecting and starting additional cores.");
 
+    cross_check_topology(response);
+
     for cpu in response.cpus().iter().filter(|cpu| {
         // Make sure we skip the boot thread (we're using it right now!).
         cpu.lapic_id != response.bsp_lapic_id()
@@ -72,168 +77,197 @@ pub fn begin_multiprocessing(mp_request: &limine::request::MpRequest) -> Option<
             crate::cpu::halt_and_catch_fire()
         }
 
-        if crate::params::use_multiprocessing() {
+        if !crate::params::get::<bool>("nomp") {
             cpu.goto_address.write(_mp_entry);
         } else {
             cpu.goto_address.write(_idle_forever);
         }
     }
 
-    Some(response.cpus().len())
+    // Idle hardware threads never synchronize, so only count them when they'll be participating.
+    if !crate::params::get::<bool>("nomp") {
+        Some(response.cpus().len())
+    } else {
+        Some(1)
+    }
 }
 
-/// Frees bootloader reclaimable memory, then begins local post-memory-system-initialization
-/// operations on each harware thread.
-///
-/// # Safety
-///
-/// - Function can only be run once at the end of the kernel init phase.
-/// - `pre_call_sp` must be the current hardware thread's stack pointer immediately prior to
-///   this method being called.
-#[allow(clippy::too_many_lines)]
-pub unsafe fn synchronize(
-    bsp_requests: Option<(
-        &limine::request::MpRequest,
-        &limine::request::MemoryMapRequest,
-    )>,
-) -> ! {
-    /// Checks if `range` contains the `stack_address`, and print out a message to
-    /// indicate the check was true.
-    fn check_range_contains_stack(range: &Range<usize>, stack_address: Address<Physical>) -> bool {
-        let range_contains_stack = range.contains(&stack_address.get());
+/// Compares the bootloader's view of the hardware threads with the MADT, warning on any disagreement.
+fn cross_check_topology(response: &limine::response::MpResponse) {
+    use crate::acpi::madt::Topology;
 
-        trace!(
-            "Checking: {:#X}..{:#X} contains {:#X} ({range_contains_stack})",
-            range.start,
-            range.end,
-            stack_address.get()
+    if !Topology::is_initialized() || Topology::processors().is_empty() {
+        warn!("MADT topology is unavailable; hardware threads will not be cross-checked.");
+        return;
+    }
+
+    let usable_processors = || {
+        Topology::processors()
+            .iter()
+            .filter(|processor| processor.is_usable)
+    };
+
+    for cpu in response.cpus() {
+        if !usable_processors().any(|processor| processor.local_apic_id == cpu.lapic_id) {
+            warn!(
+                "Hardware thread LAPIC#{} is not described as usable by the MADT.",
+                cpu.lapic_id
+            );
+        }
+    }
+
+    if let Some(bsp) = Topology::processors()
+        .iter()
+        .find(|processor| processor.is_bsp)
+        && bsp.local_apic_id != response.bsp_lapic_id()
+    {
+        warn!(
+            "Bootstrap hardware thread mismatch: bootloader reports LAPIC#{}, MADT reports LAPIC#{}",
+            response.bsp_lapic_id(),
+            bsp.local_apic_id
         );
+    }
 
-        range_contains_stack
+    let usable_count = usable_processors().count();
+    if usable_count != response.cpus().len() {
+        warn!(
+            "Hardware thread count mismatch: bootloader reports {}, MADT reports {usable_count}",
+            response.cpus().len()
+        );
     }
+}
 
-    // TODO use a `spin::RwLock` for this.
-    static ENTRY_TO_CHECK: RwLock<Option<Range<usize>>> = RwLock::new(None);
-    static IS_ENTRY_USED: AtomicBool = AtomicBool::new(false);
-    static ENTRY_READY_SYNC: Once<Barrier> = Once::new();
-    static ENTRY_PROCESSED_SYNC: Once<Barrier> = Once::new();
+/// Kernel-owned stack each hardware thread runs on once bootloader memory is reclaimed.
+type HwthreadStack = crate::mem::stack::Stack<{ crate::KERNEL_STACK_SIZE }>;
 
-    let stack_address = crate::mem::HigherHalfDirectMap::virtual_to_physical(Address::from_ptr(
-        get_stack_ptr().cast_mut(),
-    ));
+/// Hardware threads which must be off their bootloader-provided stacks before bootloader memory is reclaimed.
+static STACKS_SWITCHED: Once<Barrier> = Once::new();
 
+/// Moves the current hardware thread onto a freshly allocated kernel stack, then frees bootloader reclaimable
+/// memory and begins local post-memory-system-initialization operations on each hardware thread.
+///
+/// # Safety
+///
+/// - Function can only be run once per hardware thread, at the end of the kernel init phase.
+/// - Nothing on the current stack may be referenced after this call, as the stack is abandoned (and, being
+///   bootloader memory, reclaimed).
+pub unsafe fn synchronize(
+    bsp_requests: Option<(
+        &'static limine::request::MpRequest,
+        &'static limine::request::MemoryMapRequest,
+    )>,
+) -> ! {
     trace!("Beginning multiprocessing synchronization / bootloader memory reclaim procedure.");
 
     // If this this the bootstrap processor context, the requests will have been passed.
-    if let Some((mp_request, memory_map_request)) = bsp_requests {
+    let memory_map_request = bsp_requests.map(|(mp_request, memory_map_request)| {
         // Begin multiprocessing and store the processor count to use in synchronization later.
-        if let Some(hwthread_count) = crate::cpu::begin_multiprocessing(mp_request) {
+        let hwthread_count = crate::cpu::begin_multiprocessing(mp_request);
+        if let Some(hwthread_count) = hwthread_count {
             trace!("We will synchronize {hwthread_count} hardware threads.");
 
-            ENTRY_READY_SYNC.call_once(|| Barrier::new(hwthread_count));
-            ENTRY_PROCESSED_SYNC.call_once(|| Barrier::new(hwthread_count));
+            STACKS_SWITCHED.call_once(|| Barrier::new(hwthread_count));
         }
 
-        debug!("Reclaiming bootloader memory...");
+        if crate::params::get::<bool>("bench") {
+            crate::bench::prepare(hwthread_count.unwrap_or(1));
+        }
 
         memory_map_request
-            .get_response()
-            .expect("bootloader did not provide a response to the memory map request")
-            .entries()
-            .iter()
-            // We're only freeing bootloader reclaimable memory...
-            .filter(|entry| {
-                entry.entry_type == limine::memory_map::EntryType::BOOTLOADER_RECLAIMABLE
-            })
-            .map(|entry| {
-                let entry_start = usize::try_from(entry.base).unwrap();
-                let entry_end = usize::try_from(entry.base + entry.length).unwrap();
-
-                trace!("Attempting to free memory: {entry_start:#X}:{entry_end:#X}");
-
-                entry_start..entry_end
-            })
-            .filter(|entry_range| {
-                // Check if the entry contains the BSP stack, and if so, filter it
-                // (check returned false, so invert and return true to avoid filtering).
-                !check_range_contains_stack(entry_range, stack_address)
-            })
-            .filter(|entry_range| {
-                // If the synchronizer hasn't been initialized, then multiprocessing was
-                // disabled, and no extra entry checks need to occur.
-                let (Some(entry_ready), Some(entry_processed)) =
-                    (ENTRY_READY_SYNC.get(), ENTRY_PROCESSED_SYNC.get())
-                else {
-                    return true;
-                };
-
-                // Set the new entry to be checked.
-                let mut entry_to_check = ENTRY_TO_CHECK.write();
-                *entry_to_check = Some(entry_range.clone());
-                drop(entry_to_check);
-
-                // Reset the consensus so the other hardware threads can set it again.
-                IS_ENTRY_USED.store(false, Ordering::Release);
-
-                trace!("Waiting for all hardware threads to be ready for next entry...");
-                entry_ready.wait();
-
-                trace!("Waiting for all hardware threads to check entry...");
-                entry_processed.wait();
-
-                IS_ENTRY_USED.load(Ordering::Acquire)
-            })
-            // We'll flatten each entry to a physical memory range...
-            .flatten()
-            // Iterate page-size chunks...
-            .step_by(libsys::page_size())
-            // Map entry to physical page address...
-            .map(|address| Address::<Frame>::new(address).unwrap())
-            // Free the requisite physical frames...
-            .for_each(|frame| crate::mem::pmm::PhysicalMemoryManager::free_frame(frame).unwrap());
-
-        if let Some(entry_ready) = ENTRY_READY_SYNC.get() {
-            // Clear the check entry to `None`, so other hardware threads know there's no more work.
-            let mut entry_to_check = ENTRY_TO_CHECK.write();
-            *entry_to_check = None;
-            drop(entry_to_check);
-
-            // Signal to other hardware threads to read the next extry.
-            entry_ready.wait();
-        }
+    });
 
-        debug!("Bootloader memory reclaimed.");
-    } else {
-        // Wait for bootstrap processor to populate the synchronizer...
-        let entry_ready = ENTRY_READY_SYNC.wait();
-        let entry_processed = ENTRY_PROCESSED_SYNC.wait();
+    // Safety: Function is run only once per hardware thread, and shadow stacks are enabled once on the kernel stack.
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        crate::arch::x86_64::cet::prepare();
+    }
 
-        trace!("Entering memory map entry stack check loop.");
+    let stack = crate::mem::alloc::KERNEL_ALLOCATOR
+        .allocate_t::<HwthreadStack>()
+        .expect("failed to allocate a kernel stack for hardware thread");
 
-        loop {
-            trace!("Waiting for next entry to be ready...");
-            entry_ready.wait();
+    // Safety: The stack was just allocated, and is never freed, so its top is valid for the life of the
+    //         hardware thread.
+    let stack_top = unsafe { stack.as_ref() }.top();
 
-            trace!("Waiting to acquire entry...");
-            let entry_to_check = ENTRY_TO_CHECK.read();
+    trace!("Switching onto kernel stack at {:#X}.", stack_top.addr());
 
-            let Some(entry_range) = entry_to_check.as_ref() else {
-                // If the entry is `None`, then we're done checking entries.
-                break;
-            };
+    // Safety: Caller is required to not reference the current stack after this call.
+    unsafe {
+        switch_stack(
+            stack_top,
+            synchronize_on_kernel_stack,
+            memory_map_request.map_or(0, |request| {
+                core::ptr::from_ref(request).expose_provenance()
+            }),
+        )
+    }
+}
 
-            if check_range_contains_stack(entry_range, stack_address) {
-                IS_ENTRY_USED.store(true, Ordering::Release);
-            }
+/// Switches the current hardware thread onto the stack ending at `stack_top`, then calls `entry(arg)`.
+///
+/// # Safety
+///
+/// - `stack_top` must be the 16-byte aligned top of a stack which is valid for the life of the hardware thread.
+/// - Nothing on the current stack may be referenced after this call.
+unsafe fn switch_stack(
+    stack_top: core::ptr::NonNull<core::mem::MaybeUninit<u8>>,
+    entry: extern "C" fn(usize) -> !,
+    arg: usize,
+) -> ! {
+    #[cfg(target_arch = "x86_64")]
+    // Safety: Caller is required to provide a valid stack. Zeroing the frame pointer terminates
+    //         backtraces at `entry`, rather than walking into the abandoned stack.
+    unsafe {
+        core::arch::asm!(
+            "mov rsp, {stack_top}",
+            "xor ebp, ebp",
+            "call {entry}",
+            "ud2",
+            stack_top = in(reg) stack_top.as_ptr(),
+            entry = in(reg) entry,
+            in("rdi") arg,
+            options(noreturn)
+        )
+    }
+}
 
-            // Return the entry for other hardware threads to check.
-            drop(entry_to_check);
+/// Continuation of [`synchronize`], running on the hardware thread's kernel stack.
+///
+/// `memory_map_request` is the address of the memory map request on the bootstrap processor, and `0` otherwise.
+extern "C" fn synchronize_on_kernel_stack(memory_map_request: usize) -> ! {
+    // Safety: This function never returns, and nothing has been called on the kernel stack yet.
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        crate::arch::x86_64::cet::enable();
+    }
+
+    let is_bsp = memory_map_request != 0;
 
-            trace!("Waiting for entry to finish being checked...");
-            entry_processed.wait();
+    // The bootstrap processor only populates the synchronizer if multiprocessing began, but other hardware threads
+    // may get here before it's populated.
+    let stacks_switched = if is_bsp {
+        STACKS_SWITCHED.get()
+    } else {
+        Some(STACKS_SWITCHED.wait())
+    };
+
+    if let Some(stacks_switched) = stacks_switched {
+        trace!("Waiting for all hardware threads to leave their bootloader stacks...");
+        stacks_switched.wait();
+    }
+
+    if is_bsp {
+        // Safety: The bootstrap processor passes the address of a `'static` memory map request.
+        let memory_map_request = unsafe {
+            core::ptr::with_exposed_provenance::<limine::request::MemoryMapRequest>(
+                memory_map_request,
+            )
+            .as_ref()
         }
+        .unwrap();
 
-        trace!("Entry checks complete.");
+        reclaim_bootloader_memory(memory_map_request);
     }
 
     debug!("Preparing hardware thread for task scheduling...");
@@ -243,15 +277,27 @@ pub unsafe fn synchronize(
 
     trace!("Initializing the local interrupt controller.");
     #[cfg(target_arch = "x86_64")]
-    x2Apic::reset();
+    LocalApic::reset();
 
     trace!("Enabling the local interrupt controller.");
     #[cfg(target_arch = "x86_64")]
-    x2Apic::set_enabled(true);
+    LocalApic::set_enabled(true);
 
     debug!("Local interrupt controller has been initialized and enabled.");
 
     LocalState::init();
+    topology::register();
+    crate::sync::rcu::online();
+    crate::mem::tlb::online();
+    crate::logging::start_flusher();
+
+    crate::bench::run(is_bsp);
+    crate::profiler::start(is_bsp);
+    crate::watchdog::start(is_bsp);
+
+    if is_bsp {
+        crate::test_rpc::start();
+    }
 
     core::arch::breakpoint();
 
@@ -267,12 +313,65 @@ pub unsafe fn synchronize(
     crate::interrupts::wait_indefinite()
 }
 
-/// Gets the current hardware thread's stack pointer.
-#[inline(always)]
-pub fn get_stack_ptr() -> *const u8 {
+/// Frees all bootloader reclaimable memory.
+///
+/// # Remarks
+///
+/// Every hardware thread must be off its bootloader-provided stack before this is called.
+fn reclaim_bootloader_memory(memory_map_request: &limine::request::MemoryMapRequest) {
+    debug!("Reclaiming bootloader memory...");
+
+    // The memory map itself lives in bootloader reclaimable memory, so collect the entries before any frames are
+    // freed (and potentially reallocated by another hardware thread).
+    let reclaimable_ranges = memory_map_request
+        .get_response()
+        .expect("bootloader did not provide a response to the memory map request")
+        .entries()
+        .iter()
+        .filter(|entry| entry.entry_type == limine::memory_map::EntryType::BOOTLOADER_RECLAIMABLE)
+        .map(|entry| {
+            let entry_start = usize::try_from(entry.base).unwrap();
+            let entry_end = usize::try_from(entry.base + entry.length).unwrap();
+
+            trace!("Freeing memory: {entry_start:#X}:{entry_end:#X}");
+
+            entry_start..entry_end
+        })
+        .collect::<alloc::vec::Vec<_>>();
+
+    reclaimable_ranges
+        .into_iter()
+        // We'll flatten each entry to a physical memory range...
+        .flatten()
+        // Iterate page-size chunks...
+        .step_by(libsys::page_size())
+        // Map entry to physical page address...
+        .map(|address| Address::<Frame>::new(address).unwrap())
+        // Free the requisite physical frames...
+        .for_each(|frame| crate::mem::pmm::PhysicalMemoryManager::free_frame(frame).unwrap());
+
+    debug!("Bootloader memory reclaimed.");
+}
+
+/// Signals every other hardware thread in the system to stop executing.
+///
+/// # Remarks
+///
+/// This is delivered as a non-maskable interrupt, so it will be received even by
+/// hardware threads with interrupts disabled. Receivers are expected to check
+/// [`crate::panic::is_panicking`] and halt.
+pub fn halt_other_hwthreads() {
     #[cfg(target_arch = "x86_64")]
     {
-        crate::arch::x86_64::registers::RSP::read()
+        use crate::arch::x86_64::devices::local_apic::{
+            LocalApic, interrupt_command::InterruptCommand,
+        };
+
+        // If the local APIC isn't enabled yet, the other hardware threads can't have been
+        // started, so there's nothing to stop.
+        if LocalApic::get_enabled() {
+            LocalApic::send_interrupt_command(InterruptCommand::new_nmi_broadcast());
+        }
     }
 }
 
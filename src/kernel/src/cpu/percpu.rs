@@ -0,0 +1,179 @@
+//! Per-hardware-thread data, reached through the GS segment base.
+//!
+//! Each hardware thread claims a [`PerCpu`] block as the very first thing it does, and loads the block's address into
+//! `IA32_GS_BASE`. The block's first field is its own address, so it's found with a single `gs`-relative load, rather
+//! than an MSR read or a lookup by hardware thread ID. `IA32_KERNEL_GS_BASE` is left for userspace's GS base, which
+//! `swapgs` exchanges it with.
+
+use crate::{
+    arch::x86_64::{
+        registers::model_specific::{IA32_GS_BASE, IA32_KERNEL_GS_BASE},
+        structures::tss::TaskStateSegment,
+    },
+    cpu::{local_state::LocalState, topology::Location},
+    sync::SpinMutex,
+    task::{LocalRunQueue, Running},
+    util::CachePadded,
+};
+use core::{
+    ptr::null_mut,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+};
+use spin::Once;
+
+/// Blocks available to be claimed; any hardware thread brought up after they run out can't run.
+const MAX_HWTHREADS: usize = 256;
+
+/// Expands to a reference to `$field` of the current hardware thread's [`PerCpu`] block.
+///
+/// ```ignore
+/// crate::percpu!(interrupt_depth).fetch_add(1, Ordering::Relaxed);
+/// ```
+#[macro_export]
+macro_rules! percpu {
+    ($field:ident) => {
+        &$crate::cpu::percpu::PerCpu::current().$field
+    };
+}
+
+/// Data owned by a single hardware thread.
+#[repr(C)]
+pub struct PerCpu {
+    /// Address of this block, read through the GS base; this must remain the first field.
+    this: AtomicPtr<PerCpu>,
+    /// ID of the hardware thread this block belongs to.
+    pub hwthread_id: AtomicU32,
+    /// The hardware thread's [`LocalState`], once it's been initialized.
+    pub local_state: AtomicPtr<LocalState>,
+    /// Number of interrupts currently being handled (nested interrupts count more than once).
+    pub interrupt_depth: AtomicU32,
+    /// Timestamp counter value captured on entry to the most recent interrupt.
+    pub irq_timestamp: AtomicU64,
+    /// Address of the privilege level 0 shadow stack's token, or `0` if shadow stacks are disabled; read by the
+    /// interrupt stubs.
+    pub privilege_shadow_stack: AtomicUsize,
+    /// Where the hardware thread sits in the system, once it's registered (see [`crate::cpu::topology`]).
+    pub location: Once<Location>,
+    /// Whether the hardware thread is running a task, rather than idling.
+    pub is_busy: AtomicBool,
+    /// Number of times each vector (interrupt or exception) has been taken; see [`crate::interrupts::irq::counts`].
+    pub vector_counts: [AtomicU64; 256],
+    /// Task the hardware thread is running, if it isn't idle; see [`crate::task::publish_running`].
+    pub running_task: SpinMutex<Option<Running>>,
+    /// Tasks queued to run on the hardware thread; other hardware threads queue tasks here, and steal them to
+    /// balance load, so it's padded to keep its lock word on its own cache line.
+    pub run_queue: CachePadded<LocalRunQueue>,
+    /// The hardware thread's task state segment, once it's loaded.
+    pub tss: AtomicPtr<TaskStateSegment>,
+    /// Address of the root page table the hardware thread last loaded; see [`crate::mem::tlb`].
+    pub active_root: AtomicUsize,
+    /// Whether the hardware thread is sent TLB shootdowns, which it is once it's ready to take interrupts.
+    pub accepts_shootdowns: AtomicBool,
+    /// Whether the hardware thread has yet to invalidate the range of the TLB shootdown in flight.
+    pub tlb_shootdown_pending: AtomicBool,
+    /// Bumped to have the hardware thread's random generator reseed before its next output; see
+    /// [`crate::rand::prng::mark_local_stale`].
+    pub rng_generation: AtomicU64,
+    /// The most recent RCU grace period the hardware thread has seen while quiescent; see [`crate::sync::rcu`].
+    pub rcu_seen: AtomicU64,
+    /// Whether RCU grace periods wait on the hardware thread.
+    pub rcu_online: AtomicBool,
+}
+
+impl PerCpu {
+    const fn new() -> Self {
+        Self {
+            this: AtomicPtr::new(null_mut()),
+            hwthread_id: AtomicU32::new(0),
+            local_state: AtomicPtr::new(null_mut()),
+            interrupt_depth: AtomicU32::new(0),
+            irq_timestamp: AtomicU64::new(0),
+            privilege_shadow_stack: AtomicUsize::new(0),
+            location: Once::new(),
+            is_busy: AtomicBool::new(false),
+            vector_counts: [const { AtomicU64::new(0) }; 256],
+            running_task: SpinMutex::new(None),
+            run_queue: CachePadded::new(LocalRunQueue::new()),
+            tss: AtomicPtr::new(null_mut()),
+            active_root: AtomicUsize::new(0),
+            accepts_shootdowns: AtomicBool::new(false),
+            tlb_shootdown_pending: AtomicBool::new(false),
+            rng_generation: AtomicU64::new(0),
+            rcu_seen: AtomicU64::new(0),
+            rcu_online: AtomicBool::new(false),
+        }
+    }
+
+    /// Gets the current hardware thread's block.
+    ///
+    /// # Remarks
+    ///
+    /// This faults if called before [`init`] on the current hardware thread.
+    #[inline(always)]
+    pub fn current() -> &'static Self {
+        let this: *const Self;
+
+        // Safety: `init` loads the GS base with the address of a block owned by this hardware thread, whose first
+        //         field holds that same address.
+        unsafe {
+            core::arch::asm!(
+                "mov {}, gs:[0]",
+                out(reg) this,
+                options(nostack, readonly, preserves_flags)
+            );
+        }
+
+        // Safety: Blocks are statics, and so are valid for `'static`.
+        unsafe { &*this }
+    }
+}
+
+static BLOCKS: [CachePadded<PerCpu>; MAX_HWTHREADS] =
+    [const { CachePadded::new(PerCpu::new()) }; MAX_HWTHREADS];
+static NEXT_BLOCK: AtomicUsize = AtomicUsize::new(0);
+
+/// Iterates the blocks claimed by hardware threads so far.
+pub fn blocks() -> impl Iterator<Item = &'static PerCpu> {
+    BLOCKS
+        .iter()
+        .take(NEXT_BLOCK.load(Ordering::Acquire))
+        .map(|block| -> &'static PerCpu { block })
+        // A block may be claimed, but not yet initialized by its hardware thread.
+        .filter(|block| !block.this.load(Ordering::Acquire).is_null())
+}
+
+/// Claims a block for the current hardware thread, and loads it into the GS base.
+///
+/// # Safety
+///
+/// This must be called exactly once per hardware thread, before anything else runs on it (including logging).
+pub unsafe fn init() {
+    let index = NEXT_BLOCK.fetch_add(1, Ordering::Relaxed);
+    let Some(block) = BLOCKS.get(index) else {
+        // Nothing can be logged without a block, so there's nothing to do but stop.
+        crate::cpu::halt_and_catch_fire()
+    };
+
+    let block: &PerCpu = block;
+    block
+        .hwthread_id
+        .store(crate::arch::x86_64::get_hwthread_id(), Ordering::Relaxed);
+    block
+        .this
+        .store(core::ptr::from_ref(block).cast_mut(), Ordering::Release);
+
+    IA32_GS_BASE::write(u64::try_from(core::ptr::from_ref(block).addr()).unwrap());
+    // Userspace starts with a null GS base.
+    IA32_KERNEL_GS_BASE::write(0);
+}
+
+crate::kernel_test! {
+    /// The current hardware thread's block is found through the GS base.
+    fn current() -> crate::tests::Outcome {
+        if PerCpu::current().hwthread_id.load(Ordering::Relaxed) != crate::arch::x86_64::get_hwthread_id() {
+            return Err("block does not belong to the current hardware thread");
+        }
+
+        Ok(())
+    }
+}
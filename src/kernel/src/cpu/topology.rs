@@ -0,0 +1,157 @@
+//! Physical layout of the hardware threads: which package, and which core within it, each belongs to.
+//!
+//! A hardware thread's location is only reported by CPUID on that hardware thread, so each registers its own once
+//! it's ready to schedule tasks. The scheduler uses the locations to spread tasks across physical cores before
+//! doubling up on SMT siblings, which otherwise compete for the same execution units.
+
+use crate::cpu::percpu::PerCpu;
+use core::sync::atomic::Ordering;
+
+/// Where a hardware thread sits within the system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub package: u32,
+    /// Core within the package; hardware threads which share it are SMT siblings.
+    pub core: u32,
+    /// Hardware thread within the core.
+    pub thread: u32,
+}
+
+impl Location {
+    /// Whether `other` is on the same physical core.
+    pub fn shares_core(&self, other: &Self) -> bool {
+        self.package == other.package && self.core == other.core
+    }
+}
+
+/// Reads the current hardware thread's location from the extended topology leaf (`0x1F`, or `0xB`).
+///
+/// Without either leaf, every hardware thread is assumed to be its own core, in a single package.
+#[cfg(target_arch = "x86_64")]
+fn detect(hwthread_id: u32) -> Location {
+    use crate::arch::x86_64::cpuid::{get_extended_topology_info, get_extended_topology_info_v2};
+    use raw_cpuid::TopologyType;
+
+    let flat = Location {
+        package: 0,
+        core: hwthread_id,
+        thread: 0,
+    };
+
+    let Some(levels) = get_extended_topology_info_v2()
+        .or_else(get_extended_topology_info)
+        .cloned()
+    else {
+        return flat;
+    };
+
+    // Each level reports how far to shift the x2APIC ID right to reach the next level's ID; the last level's shift
+    // reaches the package ID. Any levels between SMT and the package (modules, tiles, dies) are treated as part of
+    // the core's ID.
+    let mut smt_shift = 0;
+    let mut package_shift = 0;
+    for level in levels {
+        if level.level_type() == TopologyType::SMT {
+            smt_shift = level.shift_right_for_next_apic_id();
+        }
+
+        package_shift = level.shift_right_for_next_apic_id();
+    }
+
+    if package_shift == 0 || package_shift >= u32::BITS {
+        return flat;
+    }
+
+    Location {
+        package: hwthread_id >> package_shift,
+        core: (hwthread_id & ((1 << package_shift) - 1)) >> smt_shift,
+        thread: hwthread_id & ((1 << smt_shift) - 1),
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn detect(hwthread_id: u32) -> Location {
+    Location {
+        package: 0,
+        core: hwthread_id,
+        thread: 0,
+    }
+}
+
+/// Records the current hardware thread's location.
+///
+/// # Remarks
+///
+/// This must be called once the hardware thread is ready to schedule tasks, as only registered hardware threads are
+/// considered by [`should_defer`].
+pub fn register() {
+    let block = PerCpu::current();
+    let hwthread_id = block.hwthread_id.load(Ordering::Relaxed);
+    let location = block.location.call_once(|| detect(hwthread_id));
+
+    debug!("Hardware thread #{hwthread_id} is at {location:?}.");
+}
+
+/// Gets the location of the hardware thread with `hwthread_id`, if it has registered.
+pub fn location(hwthread_id: u32) -> Option<Location> {
+    crate::cpu::percpu::blocks()
+        .find(|block| block.hwthread_id.load(Ordering::Relaxed) == hwthread_id)
+        .and_then(|block| block.location.get().copied())
+}
+
+/// Calls `func` with the ID and location of every registered hardware thread.
+pub fn for_each(mut func: impl FnMut(u32, Location)) {
+    for block in crate::cpu::percpu::blocks() {
+        if let Some(location) = block.location.get() {
+            func(block.hwthread_id.load(Ordering::Relaxed), *location);
+        }
+    }
+}
+
+/// Records whether the current hardware thread is running a task (rather than idling).
+pub fn set_busy(is_busy: bool) {
+    crate::percpu!(is_busy).store(is_busy, Ordering::Relaxed);
+}
+
+/// Whether the current hardware thread should leave `queued` tasks for other hardware threads to pick up, rather than
+/// run one itself: it does if an SMT sibling is busy, while there's a wholly idle core for each of them.
+///
+/// # Remarks
+///
+/// Idle hardware threads pick up queued tasks on their next scheduler tick, so a deferred task waits at most a
+/// time slice longer than it otherwise would.
+pub fn should_defer(queued: usize) -> bool {
+    let current = PerCpu::current();
+    let Some(location) = current.location.get() else {
+        return false;
+    };
+
+    // Isolated hardware threads won't pick up the queued tasks, so they're never counted as idle.
+    let registered = || {
+        crate::cpu::percpu::blocks()
+            .filter(|block| !core::ptr::eq(*block, current))
+            .filter(|block| !crate::task::is_isolated(block.hwthread_id.load(Ordering::Relaxed)))
+            .filter_map(|block| {
+                Some((block.location.get()?, block.is_busy.load(Ordering::Relaxed)))
+            })
+    };
+
+    let is_sibling_busy =
+        registered().any(|(other, is_busy)| is_busy && other.shares_core(location));
+    if !is_sibling_busy {
+        return false;
+    }
+
+    // A core is idle if none of its registered hardware threads are busy; it's counted once, by its lowest thread.
+    let idle_cores = registered()
+        .filter(|(other, is_busy)| {
+            !is_busy
+                && !other.shares_core(location)
+                && !registered().any(|(sibling, is_sibling_busy)| {
+                    sibling.shares_core(other) && (is_sibling_busy || sibling.thread < other.thread)
+                })
+        })
+        .count();
+
+    idle_cores >= queued
+}
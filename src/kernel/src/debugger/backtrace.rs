@@ -0,0 +1,131 @@
+use crate::{
+    arch::x86_64::structures::{gdt::PrivilegeLevel, idt::InterruptStackFrame},
+    interrupts::exceptions::triage::{AddressRegion, classify},
+    task::Registers,
+};
+use core::{
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    time::Duration,
+};
+use heapless::Vec;
+use libsys::{Address, Virtual};
+use spin::Mutex;
+
+/// Maximum number of frames captured for a single backtrace.
+pub const MAX_FRAMES: usize = 32;
+
+/// Time to wait for a remote hardware thread to capture its backtrace.
+const CAPTURE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Sentinel value for [`REQUESTED_HWTHREAD`] when no backtrace is requested.
+const NO_REQUEST: u32 = u32::MAX;
+
+/// ID of the hardware thread asked to capture its backtrace, or [`NO_REQUEST`].
+static REQUESTED_HWTHREAD: AtomicU32 = AtomicU32::new(NO_REQUEST);
+static CAPTURED: AtomicBool = AtomicBool::new(false);
+/// A plain spin lock (see [`crate::sync`]), as it's taken in NMI context.
+static FRAMES: Mutex<Vec<Address<Virtual>, MAX_FRAMES>> = Mutex::new(Vec::new());
+
+/// Whether the current hardware thread has been asked (via NMI) to capture its backtrace.
+pub fn is_backtrace_requested() -> bool {
+    REQUESTED_HWTHREAD.load(Ordering::Acquire) == crate::cpu::get_id()
+}
+
+/// Walks the frame-pointer chain starting at `frame_ptr`, pushing return addresses into `frames`.
+///
+/// # Remarks
+///
+/// The chain is walked defensively, as it may belong to a context which was interrupted at any point: walking
+/// stops at the first frame pointer which is misaligned, or not in the kernel's address space.
+fn walk(mut frame_ptr: usize, frames: &mut Vec<Address<Virtual>, MAX_FRAMES>) {
+    while !frames.is_full()
+        && frame_ptr.is_multiple_of(align_of::<usize>())
+        && matches!(
+            classify(frame_ptr),
+            AddressRegion::HigherHalfDirectMap
+                | AddressRegion::KernelData
+                | AddressRegion::KernelSpace
+        )
+    {
+        // Safety: Frame pointer is aligned and within the kernel's address space; a frame is a pair of words
+        //         containing the previous frame pointer, then the return address.
+        let [prev_frame_ptr, return_address] =
+            unsafe { core::ptr::with_exposed_provenance::<[usize; 2]>(frame_ptr).read_volatile() };
+
+        let Some(return_address) = Address::new(return_address) else {
+            break;
+        };
+
+        frames.push(return_address).ok();
+
+        if prev_frame_ptr <= frame_ptr {
+            // Frames only grow upwards; anything else is a corrupt (or terminating) chain.
+            break;
+        }
+
+        frame_ptr = prev_frame_ptr;
+    }
+}
+
+/// Captures the backtrace of the context interrupted by the backtrace-request NMI.
+pub fn capture_requested(stack_frame: &InterruptStackFrame, gprs: &Registers) {
+    {
+        let mut frames = FRAMES.lock();
+        frames.clear();
+        frames.push(stack_frame.get_instruction_pointer()).ok();
+
+        if stack_frame.get_code_segment().privilege_level() == PrivilegeLevel::Ring0 {
+            walk(gprs.rbp, &mut frames);
+        }
+    }
+
+    REQUESTED_HWTHREAD.store(NO_REQUEST, Ordering::Release);
+    CAPTURED.store(true, Ordering::Release);
+}
+
+/// Captures the backtrace of the current hardware thread.
+pub fn capture_local() -> Vec<Address<Virtual>, MAX_FRAMES> {
+    let frame_ptr: usize;
+
+    // Safety: We're just reading a register.
+    unsafe {
+        core::arch::asm!(
+            "mov {}, rbp",
+            out(reg) frame_ptr,
+            options(nostack, nomem, preserves_flags)
+        );
+    }
+
+    let mut frames = Vec::new();
+    walk(frame_ptr, &mut frames);
+
+    frames
+}
+
+/// Asks the hardware thread with ID `hwthread_id` to capture its backtrace, and waits for the result.
+pub fn capture_remote(hwthread_id: u32) -> Option<Vec<Address<Virtual>, MAX_FRAMES>> {
+    use crate::arch::x86_64::devices::local_apic::{
+        LocalApic, interrupt_command::InterruptCommand,
+    };
+
+    CAPTURED.store(false, Ordering::Release);
+    REQUESTED_HWTHREAD
+        .compare_exchange(NO_REQUEST, hwthread_id, Ordering::AcqRel, Ordering::Acquire)
+        .ok()?;
+
+    LocalApic::send_interrupt_command(InterruptCommand::new_nmi(hwthread_id));
+
+    let deadline_ns = crate::time::now_ns() + u64::try_from(CAPTURE_TIMEOUT.as_nanos()).unwrap();
+    while !CAPTURED.load(Ordering::Acquire) {
+        if crate::time::now_ns() >= deadline_ns {
+            // Withdraw the request, so a late NMI isn't mistaken for one.
+            REQUESTED_HWTHREAD.store(NO_REQUEST, Ordering::Release);
+
+            return None;
+        }
+
+        core::hint::spin_loop();
+    }
+
+    Some(FRAMES.lock().clone())
+}
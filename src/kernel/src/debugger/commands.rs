@@ -0,0 +1,366 @@
+use super::backtrace;
+use crate::{
+    console,
+    interrupts::{irq, vector_kind},
+    mem::{mapper::Mapper, pmm::PhysicalMemoryManager},
+};
+use alloc::string::{String, ToString};
+use core::fmt::Write;
+use libsys::{Address, Virtual};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("unknown command `{0}`; try `help`")]
+    UnknownCommand(String),
+
+    #[error("missing argument: {0}")]
+    MissingArgument(&'static str),
+
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("hwthread #{0} did not respond to the backtrace request")]
+    NoResponse(u32),
+
+    #[error("failed to write output")]
+    Format(#[from] core::fmt::Error),
+}
+
+const HELP: &str = "\
+help            list commands
+mem             physical memory usage
+ps              every task's state, hardware thread, priority, and CPU time (alias: tasks)
+irqstats        per-vector interrupt and exception counts, by hardware thread
+topology        package, core and thread of each hardware thread
+params          command line parameters, and their values
+dmesg [count]   retained log records (default: all of them)
+pt <addr>       walk the active page tables for a virtual address
+bt [hwthread]   backtrace of a hardware thread (default: this one)
+profile [count] [hwthread]
+                most sampled functions (default: 20), on one hardware thread (default: all of them)
+gdb             leave the shell, and wait for GDB to attach
+exit            leave the shell
+";
+
+/// Runs `command` with `args`, writing its output to `out`.
+pub fn run<'a>(
+    out: &mut console::Writer,
+    command: &str,
+    mut args: impl Iterator<Item = &'a str>,
+) -> Result<(), Error> {
+    match command {
+        "help" | "?" => out.write_str(HELP)?,
+        "mem" => mem(out)?,
+        "ps" | "tasks" => ps(out)?,
+        "irqstats" => irqstats(out)?,
+        "topology" => topology(out)?,
+        "params" => params(out)?,
+        "dmesg" => dmesg(out, args.next().map(parse).transpose()?)?,
+        "pt" => pt(
+            out,
+            parse(args.next().ok_or(Error::MissingArgument("addr"))?)?,
+        )?,
+        "bt" => bt(
+            out,
+            args.next()
+                .map(parse)
+                .transpose()?
+                .map(u32::try_from)
+                .transpose()
+                .map_err(|error| Error::InvalidArgument(error.to_string()))?,
+        )?,
+        "profile" => profile(
+            out,
+            args.next().map(parse).transpose()?.unwrap_or(20),
+            args.next()
+                .map(parse)
+                .transpose()?
+                .map(u32::try_from)
+                .transpose()
+                .map_err(|error| Error::InvalidArgument(error.to_string()))?,
+        )?,
+
+        command => return Err(Error::UnknownCommand(command.to_string())),
+    }
+
+    Ok(())
+}
+
+/// Parses a hexadecimal (`0x`-prefixed) or decimal number.
+fn parse(arg: &str) -> Result<usize, Error> {
+    let parsed = match arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(&hex.replace('_', ""), 16),
+        None => arg.parse(),
+    };
+
+    parsed.map_err(|_| Error::InvalidArgument(arg.to_string()))
+}
+
+fn mem(out: &mut console::Writer) -> Result<(), Error> {
+    let page_size = libsys::page_size();
+    let total_frames = PhysicalMemoryManager::total_frames();
+    let free_frames = PhysicalMemoryManager::free_frames();
+    let used_frames = total_frames - free_frames;
+
+    writeln!(
+        out,
+        "frames: {total_frames} total, {used_frames} used, {free_frames} free ({} KiB free of {} KiB)",
+        (free_frames * page_size) / 1024,
+        (total_frames * page_size) / 1024
+    )?;
+
+    let heap = crate::mem::alloc::stats();
+    writeln!(
+        out,
+        "heap: {} KiB allocated ({} KiB 2MiB-aligned, {} KiB split)",
+        heap.allocated_bytes / 1024,
+        heap.huge_bytes / 1024,
+        heap.split_bytes / 1024
+    )?;
+
+    if crate::mem::numa::NumaTopology::is_initialized() {
+        writeln!(
+            out,
+            "NUMA nodes: {}",
+            crate::mem::numa::NumaTopology::nodes().len()
+        )?;
+    }
+
+    writeln!(
+        out,
+        "DMA audit: {}",
+        if crate::mem::dma::is_enabled() {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    )?;
+
+    Ok(())
+}
+
+fn params(out: &mut console::Writer) -> Result<(), Error> {
+    for parameter in crate::params::registry() {
+        writeln!(
+            out,
+            "--{:<16} {:<8} {}",
+            parameter.name,
+            crate::params::value(parameter).to_string(),
+            parameter.doc.trim()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Lists every task, with the hardware thread it's running on (or last ran on), and the CPU time it has used.
+fn ps(out: &mut console::Writer) -> Result<(), Error> {
+    let Some(infos) = crate::task::try_snapshot() else {
+        writeln!(out, "(task queues are locked)")?;
+        return Ok(());
+    };
+
+    writeln!(
+        out,
+        "id                                   name             state    cpu   priority  cpu time (user / kernel)"
+    )?;
+
+    for info in infos {
+        // Unnamed tasks are userspace tasks, as every kernel thread is named.
+        let name = match info.name() {
+            "" => "-",
+            name => name,
+        };
+        let hwthread = match info.hwthread_id {
+            u32::MAX => String::from("-"),
+            hwthread_id => alloc::format!("#{hwthread_id}"),
+        };
+
+        writeln!(
+            out,
+            "{} {name:<16} {:<8} {hwthread:<5} {:<9} {:?} ({:?} / {:?})",
+            info.id(),
+            info.state()
+                .map_or(String::from("?"), |state| alloc::format!("{state:?}")),
+            info.priority()
+                .map_or(String::from("?"), |priority| alloc::format!("{priority:?}")),
+            core::time::Duration::from_nanos(info.cpu_time_ns),
+            core::time::Duration::from_nanos(info.user_time_ns),
+            core::time::Duration::from_nanos(info.kernel_time_ns),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn irqstats(out: &mut console::Writer) -> Result<(), Error> {
+    writeln!(
+        out,
+        "vector  kind               count       hardware threads"
+    )?;
+
+    for vector in u8::MIN..=u8::MAX {
+        let count = irq::count(vector);
+
+        if count > 0 {
+            write!(
+                out,
+                "{vector:#04X}    {:<18} {count:<11}",
+                alloc::format!("{:?}", vector_kind(vector))
+            )?;
+
+            for (hwthread_id, count) in irq::counts(vector).filter(|(_, count)| *count > 0) {
+                write!(out, " #{hwthread_id}:{count}")?;
+            }
+
+            writeln!(out)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn dmesg(out: &mut console::Writer, count: Option<usize>) -> Result<(), Error> {
+    use crate::logging::ring;
+
+    let from = count.map_or(ring::tail(), |count| {
+        ring::head().saturating_sub(u64::try_from(count).unwrap_or(u64::MAX))
+    });
+
+    for record in ring::records_from(from) {
+        writeln!(out, "{record}")?;
+    }
+
+    Ok(())
+}
+
+fn pt(out: &mut console::Writer, address: usize) -> Result<(), Error> {
+    let address = Address::<Virtual>::new(address)
+        .ok_or_else(|| Error::InvalidArgument(alloc::format!("{address:#X} is not canonical")))?;
+
+    // Safety: The page tables are only read.
+    let mapper = unsafe { Mapper::active() };
+    mapper.dump_range(address..=address, out)?;
+
+    match mapper.translate(address) {
+        Some((frame, _, depth)) => writeln!(
+            out,
+            "{:#X} -> {:#X} ({} KiB page)",
+            address.get(),
+            frame.get().get() + (address.get() & (libsys::page_size() - 1)),
+            depth.align() / 1024
+        )?,
+
+        None => writeln!(out, "{:#X} is not mapped", address.get())?,
+    }
+
+    Ok(())
+}
+
+fn bt(out: &mut console::Writer, hwthread_id: Option<u32>) -> Result<(), Error> {
+    let local_id = crate::cpu::get_id();
+    let hwthread_id = hwthread_id.unwrap_or(local_id);
+
+    let frames = if hwthread_id == local_id {
+        backtrace::capture_local()
+    } else {
+        backtrace::capture_remote(hwthread_id).ok_or(Error::NoResponse(hwthread_id))?
+    };
+
+    writeln!(out, "hwthread #{hwthread_id}:")?;
+    for (depth, address) in frames.into_iter().enumerate() {
+        #[cfg(feature = "panic_traces")]
+        crate::panic::tracing::write_frame(&mut *out, depth, address)?;
+
+        #[cfg(not(feature = "panic_traces"))]
+        writeln!(out, "#{depth: <4}{:#X}", address.get())?;
+    }
+
+    Ok(())
+}
+
+fn topology(out: &mut console::Writer) -> Result<(), Error> {
+    let mut hwthreads = alloc::vec::Vec::new();
+    crate::cpu::topology::for_each(|hwthread_id, location| hwthreads.push((location, hwthread_id)));
+    hwthreads.sort_unstable();
+
+    for (location, hwthread_id) in hwthreads {
+        writeln!(
+            out,
+            "hwthread #{hwthread_id:<4} package {} core {} thread {}",
+            location.package, location.core, location.thread
+        )?;
+    }
+
+    Ok(())
+}
+
+fn profile(out: &mut console::Writer, count: usize, hwthread_id: Option<u32>) -> Result<(), Error> {
+    let Some(period) = crate::profiler::period() else {
+        writeln!(
+            out,
+            "profiler is not running (enable it with `--profile=<cycles>`)"
+        )?;
+        return Ok(());
+    };
+
+    // Samples are grouped by function when symbols are available, otherwise by address.
+    let mut counts = alloc::collections::BTreeMap::<_, usize>::new();
+    let mut hwthread_counts = alloc::collections::BTreeMap::<u32, usize>::new();
+    let mut retained = 0usize;
+    crate::profiler::for_each_sample(|sample| {
+        *hwthread_counts.entry(sample.hwthread_id).or_default() += 1;
+        if hwthread_id.is_some_and(|hwthread_id| hwthread_id != sample.hwthread_id) {
+            return;
+        }
+
+        let address = sample.instruction_pointer;
+
+        #[cfg(feature = "panic_traces")]
+        let key = crate::panic::tracing::symbols::Symbols::get_name(address).map_or_else(
+            || alloc::format!("{:#X}", address.get()),
+            ToString::to_string,
+        );
+
+        #[cfg(not(feature = "panic_traces"))]
+        let key = alloc::format!("{:#X}", address.get());
+
+        *counts.entry(key).or_default() += 1;
+        retained += 1;
+    });
+
+    let mut counts = counts.into_iter().collect::<alloc::vec::Vec<_>>();
+    counts.sort_unstable_by(|(_, a), (_, b)| b.cmp(a));
+
+    writeln!(
+        out,
+        "{} samples recorded every {period} cycles, {} retained",
+        crate::profiler::recorded(),
+        hwthread_counts.values().sum::<usize>()
+    )?;
+
+    out.write_str("by hwthread:")?;
+    for (hwthread_id, samples) in &hwthread_counts {
+        write!(out, " #{hwthread_id}: {samples}")?;
+    }
+    out.write_char('\n')?;
+
+    if let Some(hwthread_id) = hwthread_id {
+        writeln!(out, "on hwthread #{hwthread_id} ({retained} samples):")?;
+    } else {
+        writeln!(out, "on every hwthread:")?;
+    }
+
+    for (key, samples) in counts.into_iter().take(count) {
+        let permille = (samples * 1000) / retained;
+
+        writeln!(
+            out,
+            "{samples:>6} {:>3}.{}% {key}",
+            permille / 10,
+            permille % 10
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,705 @@
+//! GDB remote serial protocol stub, spoken over the serial console.
+//!
+//! Once attached (via `--gdb`, or the debug shell's `gdb` command), `target remote` can read & write registers and
+//! memory, insert software breakpoints, and single-step the stopped hardware thread. Only the hardware thread which
+//! trapped into the stub is stopped; any others continue to run.
+
+use crate::{
+    arch::x86_64::{
+        registers::{
+            RFlags,
+            control::{CR0, CR0Flags},
+        },
+        structures::idt::InterruptStackFrame,
+    },
+    console,
+    logging::serial,
+    mem::{mapper::Mapper, paging::TableEntryFlags},
+    sync::SpinMutex,
+    task::Registers,
+};
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use heapless::Vec;
+use libsys::{Address, Virtual};
+
+/// Byte GDB sends (outside of any packet) to interrupt a running target.
+pub const INTERRUPT: u8 = 0x03;
+
+/// Maximum size of a packet's data, in bytes.
+crate::parameters! {
+    /// Whether to wait for GDB to attach over the serial console at the end of boot.
+    "gdb": bool = false,
+}
+
+const PACKET_SIZE: usize = 0x400;
+
+const MAX_BREAKPOINTS: usize = 32;
+
+/// Opcode of `int3`.
+const INT3: u8 = 0xCC;
+
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+
+type Buffer = Vec<u8, PACKET_SIZE>;
+
+#[derive(Debug, Error)]
+enum Error {
+    #[error("malformed packet")]
+    Malformed,
+
+    #[error("address {0:#X} is not mapped")]
+    Unmapped(usize),
+
+    #[error("reply exceeds the packet size")]
+    TooLong,
+
+    #[error("no free breakpoint slots")]
+    TooManyBreakpoints,
+
+    #[error("no breakpoint at {0:#X}")]
+    NoBreakpoint(usize),
+}
+
+impl Error {
+    /// Error reply for the error; the codes are `errno` values, as GDB expects.
+    const fn reply(&self) -> &'static [u8] {
+        match self {
+            Self::Malformed => b"E16",
+            Self::Unmapped(_) => b"E0E",
+            Self::TooLong => b"E07",
+            Self::TooManyBreakpoints => b"E1C",
+            Self::NoBreakpoint(_) => b"E02",
+        }
+    }
+}
+
+/// Software breakpoint inserted by the stub.
+struct Breakpoint {
+    address: usize,
+    original: u8,
+}
+
+struct Session {
+    breakpoints: Vec<Breakpoint, MAX_BREAKPOINTS>,
+
+    /// Whether GDB has resumed the target, and so is waiting for a stop reply.
+    resumed: bool,
+}
+
+/// What the stopped hardware thread does after a packet has been handled.
+enum Action {
+    Reply,
+    Resume { step: bool },
+    Detach { reply: bool },
+}
+
+static ATTACHED: AtomicBool = AtomicBool::new(false);
+
+/// Whether a hardware thread is stopped in the stub.
+static STOPPED: AtomicBool = AtomicBool::new(false);
+
+/// Whether GDB has asked for the running target to be interrupted.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+static SESSION: SpinMutex<Session> = SpinMutex::new(Session {
+    breakpoints: Vec::new(),
+    resumed: false,
+});
+
+pub fn is_attached() -> bool {
+    ATTACHED.load(Ordering::Acquire)
+}
+
+pub fn is_stopped() -> bool {
+    STOPPED.load(Ordering::Acquire)
+}
+
+/// Stops the current hardware thread in the stub, and waits for GDB to attach over the serial console.
+///
+/// # Remarks
+///
+/// Log output is kept off the serial line until GDB detaches.
+pub fn attach() {
+    if !is_attached() {
+        info!("Waiting for GDB to attach over the serial console...");
+
+        serial::set_muted(true);
+        ATTACHED.store(true, Ordering::Release);
+    }
+
+    core::arch::breakpoint();
+}
+
+/// Stops the current hardware thread in the stub, reporting to GDB that it was interrupted.
+pub(crate) fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Release);
+
+    core::arch::breakpoint();
+}
+
+/// Handles a `#BP` exception, returning whether it was taken by the stub.
+pub fn on_breakpoint(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) -> bool {
+    if !is_attached() {
+        return false;
+    }
+
+    let mut session = SESSION.lock();
+
+    // `int3` traps after itself, so rewind onto breakpoints inserted by the stub. A breakpoint removed while this
+    // hardware thread waited for the session is recognized by its original byte having been restored.
+    let address = stack_frame.get_instruction_pointer().get() - 1;
+    if session
+        .breakpoints
+        .iter()
+        .any(|breakpoint| breakpoint.address == address)
+        || access(address, None).is_ok_and(|byte| byte != INT3)
+    {
+        // Safety: `address` is the start of the instruction the breakpoint replaced.
+        unsafe {
+            stack_frame.set_instruction_pointer(Address::new(address).unwrap());
+        }
+    }
+
+    let signal = if INTERRUPTED.swap(false, Ordering::AcqRel) {
+        SIGINT
+    } else {
+        SIGTRAP
+    };
+
+    run(&mut session, signal, stack_frame, gprs);
+
+    true
+}
+
+/// Handles a `#DB` exception, returning whether it was taken by the stub.
+pub fn on_debug(stack_frame: &mut InterruptStackFrame, gprs: &mut Registers) -> bool {
+    // Only single-steps belong to the stub; it doesn't use the debug registers.
+    if !is_attached() || !stack_frame.get_cpu_flags().contains(RFlags::TRAP_FLAG) {
+        return false;
+    }
+
+    run(&mut SESSION.lock(), SIGTRAP, stack_frame, gprs);
+
+    true
+}
+
+/// Serves GDB's requests until it resumes the target, or detaches.
+fn run(
+    session: &mut Session,
+    signal: u8,
+    stack_frame: &mut InterruptStackFrame,
+    gprs: &mut Registers,
+) {
+    STOPPED.store(true, Ordering::Release);
+
+    // On a fresh attach, GDB asks for the stop reason itself.
+    if session.resumed {
+        session.resumed = false;
+
+        let mut reply = Buffer::new();
+        stop_reply(&mut reply, signal).ok();
+        send_packet(&reply);
+    }
+
+    let mut packet = Buffer::new();
+    let mut reply = Buffer::new();
+    loop {
+        receive_packet(&mut packet);
+
+        reply.clear();
+        let action = execute(session, &packet, signal, stack_frame, gprs, &mut reply)
+            .unwrap_or_else(|error| {
+                reply.clear();
+                reply.extend_from_slice(error.reply()).ok();
+
+                Action::Reply
+            });
+
+        match action {
+            Action::Reply => send_packet(&reply),
+
+            Action::Resume { step } => {
+                let mut cpu_flags = stack_frame.get_cpu_flags();
+                cpu_flags.set(RFlags::TRAP_FLAG, step);
+
+                // Safety: Only the trap flag has changed, which the stub handles.
+                unsafe {
+                    stack_frame.set_cpu_flags(cpu_flags);
+                }
+
+                session.resumed = true;
+                break;
+            }
+
+            Action::Detach { reply } => {
+                if reply {
+                    send_packet(b"OK");
+                }
+
+                detach(session, stack_frame);
+                break;
+            }
+        }
+    }
+
+    STOPPED.store(false, Ordering::Release);
+}
+
+fn detach(session: &mut Session, stack_frame: &mut InterruptStackFrame) {
+    while let Some(breakpoint) = session.breakpoints.pop() {
+        access(breakpoint.address, Some(breakpoint.original)).ok();
+    }
+
+    let mut cpu_flags = stack_frame.get_cpu_flags();
+    cpu_flags.remove(RFlags::TRAP_FLAG);
+
+    // Safety: Only the trap flag has changed, which the stub handles.
+    unsafe {
+        stack_frame.set_cpu_flags(cpu_flags);
+    }
+
+    session.resumed = false;
+    ATTACHED.store(false, Ordering::Release);
+    serial::set_muted(false);
+
+    info!("GDB detached.");
+}
+
+/// Handles a single packet, writing any reply to `reply`.
+fn execute(
+    session: &mut Session,
+    packet: &[u8],
+    signal: u8,
+    stack_frame: &mut InterruptStackFrame,
+    gprs: &mut Registers,
+    reply: &mut Buffer,
+) -> Result<Action, Error> {
+    let Some((&command, args)) = packet.split_first() else {
+        return Ok(Action::Reply);
+    };
+
+    match command {
+        b'?' => stop_reply(reply, signal)?,
+
+        b'g' => read_registers(stack_frame, gprs, reply)?,
+
+        b'G' => {
+            write_registers(stack_frame, gprs, args)?;
+            push(reply, b"OK")?;
+        }
+
+        b'm' => {
+            let (address, length) = parse_address_length(args)?;
+
+            if length > (PACKET_SIZE / 2) {
+                return Err(Error::TooLong);
+            }
+
+            for offset in 0..length {
+                let address = address.checked_add(offset).ok_or(Error::Malformed)?;
+                push_hex_bytes(reply, &[access(address, None)?])?;
+            }
+        }
+
+        b'M' => {
+            let (header, data) = split_once(args, b':').ok_or(Error::Malformed)?;
+            let (address, length) = parse_address_length(header)?;
+
+            if data.len() != (length * 2) {
+                return Err(Error::Malformed);
+            }
+
+            for (offset, byte) in data.chunks_exact(2).enumerate() {
+                let address = address.checked_add(offset).ok_or(Error::Malformed)?;
+                access(address, Some(parse_hex_byte(byte)?))?;
+            }
+
+            push(reply, b"OK")?;
+        }
+
+        b'Z' | b'z' => {
+            let mut fields = args.split(|byte| *byte == b',');
+
+            // Only software breakpoints are supported; an empty reply tells GDB as much.
+            if fields.next() != Some(b"0".as_slice()) {
+                return Ok(Action::Reply);
+            }
+
+            let address = parse_hex(fields.next().ok_or(Error::Malformed)?)?;
+            if command == b'Z' {
+                insert_breakpoint(session, address)?;
+            } else {
+                remove_breakpoint(session, address)?;
+            }
+
+            push(reply, b"OK")?;
+        }
+
+        b'c' | b's' => {
+            if !args.is_empty() {
+                let address = Address::new(parse_hex(args)?).ok_or(Error::Malformed)?;
+
+                // Safety: GDB has asked to resume at `address`.
+                unsafe {
+                    stack_frame.set_instruction_pointer(address);
+                }
+            }
+
+            return Ok(Action::Resume {
+                step: command == b's',
+            });
+        }
+
+        b'D' => return Ok(Action::Detach { reply: true }),
+        b'k' => return Ok(Action::Detach { reply: false }),
+
+        b'H' => push(reply, b"OK")?,
+
+        b'q' if args.starts_with(b"Supported") => {
+            push(reply, b"PacketSize=")?;
+            push_hex_number(reply, PACKET_SIZE)?;
+        }
+
+        b'q' if args == b"Attached" => push(reply, b"1")?,
+
+        _ => {
+            // Unsupported; reply with an empty packet.
+        }
+    }
+
+    Ok(Action::Reply)
+}
+
+fn stop_reply(reply: &mut Buffer, signal: u8) -> Result<(), Error> {
+    push(reply, b"S")?;
+    push_hex_bytes(reply, &[signal])
+}
+
+/// Writes the registers in the order of GDB's x86-64 `g` packet: the general purpose registers, `rip`, then the
+/// flags and segment registers.
+fn read_registers(
+    stack_frame: &InterruptStackFrame,
+    gprs: &Registers,
+    reply: &mut Buffer,
+) -> Result<(), Error> {
+    let registers = [
+        gprs.rax,
+        gprs.rbx,
+        gprs.rcx,
+        gprs.rdx,
+        gprs.rsi,
+        gprs.rdi,
+        gprs.rbp,
+        stack_frame.get_stack_pointer().get(),
+        gprs.r8,
+        gprs.r9,
+        gprs.r10,
+        gprs.r11,
+        gprs.r12,
+        gprs.r13,
+        gprs.r14,
+        gprs.r15,
+        stack_frame.get_instruction_pointer().get(),
+    ];
+
+    for register in registers {
+        push_hex_bytes(reply, &register.to_le_bytes())?;
+    }
+
+    let [ds, es, fs, gs] = data_segments();
+    let registers = [
+        u32::try_from(stack_frame.get_cpu_flags().bits()).unwrap(),
+        u32::from(stack_frame.get_code_segment().as_u16()),
+        u32::from(stack_frame.get_stack_segment().as_u16()),
+        u32::from(ds),
+        u32::from(es),
+        u32::from(fs),
+        u32::from(gs),
+    ];
+
+    for register in registers {
+        push_hex_bytes(reply, &register.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Updates the registers from a `G` packet.
+///
+/// # Remarks
+///
+/// Segment registers are never written, as changing them can't be safely resumed from.
+fn write_registers(
+    stack_frame: &mut InterruptStackFrame,
+    gprs: &mut Registers,
+    hex: &[u8],
+) -> Result<(), Error> {
+    let mut values = hex.chunks_exact(size_of::<usize>() * 2).map(|hex| {
+        let mut bytes = [0u8; size_of::<usize>()];
+        for (byte, hex) in bytes.iter_mut().zip(hex.chunks_exact(2)) {
+            *byte = parse_hex_byte(hex)?;
+        }
+
+        Ok::<_, Error>(usize::from_le_bytes(bytes))
+    });
+    let mut next = || values.next().unwrap_or(Err(Error::Malformed));
+
+    gprs.rax = next()?;
+    gprs.rbx = next()?;
+    gprs.rcx = next()?;
+    gprs.rdx = next()?;
+    gprs.rsi = next()?;
+    gprs.rdi = next()?;
+    gprs.rbp = next()?;
+    let stack_pointer = Address::<Virtual>::new(next()?).ok_or(Error::Malformed)?;
+    gprs.r8 = next()?;
+    gprs.r9 = next()?;
+    gprs.r10 = next()?;
+    gprs.r11 = next()?;
+    gprs.r12 = next()?;
+    gprs.r13 = next()?;
+    gprs.r14 = next()?;
+    gprs.r15 = next()?;
+    let instruction_pointer = Address::<Virtual>::new(next()?).ok_or(Error::Malformed)?;
+
+    // Safety: GDB has asked for the target to resume with these values.
+    unsafe {
+        stack_frame.set_stack_pointer(stack_pointer);
+        stack_frame.set_instruction_pointer(instruction_pointer);
+    }
+
+    // The flags follow the 64-bit registers as a 32-bit value.
+    let flags_offset = 17 * size_of::<usize>() * 2;
+    if let Some(flags_hex) = hex.get(flags_offset..(flags_offset + (size_of::<u32>() * 2))) {
+        let mut bytes = [0u8; size_of::<u32>()];
+        for (byte, hex) in bytes.iter_mut().zip(flags_hex.chunks_exact(2)) {
+            *byte = parse_hex_byte(hex)?;
+        }
+
+        // Safety: GDB has asked for the target to resume with these flags.
+        unsafe {
+            stack_frame.set_cpu_flags(RFlags::from_bits_truncate(u64::from(u32::from_le_bytes(
+                bytes,
+            ))));
+        }
+    }
+
+    Ok(())
+}
+
+fn data_segments() -> [u16; 4] {
+    let (ds, es, fs, gs): (u16, u16, u16, u16);
+
+    // Safety: We're just reading registers.
+    unsafe {
+        core::arch::asm!(
+            "mov {0:x}, ds",
+            "mov {1:x}, es",
+            "mov {2:x}, fs",
+            "mov {3:x}, gs",
+            out(reg) ds,
+            out(reg) es,
+            out(reg) fs,
+            out(reg) gs,
+            options(nostack, nomem, preserves_flags)
+        );
+    }
+
+    [ds, es, fs, gs]
+}
+
+fn insert_breakpoint(session: &mut Session, address: usize) -> Result<(), Error> {
+    if session
+        .breakpoints
+        .iter()
+        .any(|breakpoint| breakpoint.address == address)
+    {
+        return Ok(());
+    }
+
+    let original = access(address, None)?;
+    session
+        .breakpoints
+        .push(Breakpoint { address, original })
+        .map_err(|_| Error::TooManyBreakpoints)?;
+
+    access(address, Some(INT3)).inspect_err(|_| {
+        session.breakpoints.pop();
+    })?;
+
+    Ok(())
+}
+
+fn remove_breakpoint(session: &mut Session, address: usize) -> Result<(), Error> {
+    let index = session
+        .breakpoints
+        .iter()
+        .position(|breakpoint| breakpoint.address == address)
+        .ok_or(Error::NoBreakpoint(address))?;
+
+    let breakpoint = session.breakpoints.swap_remove(index);
+    access(breakpoint.address, Some(breakpoint.original))?;
+
+    Ok(())
+}
+
+/// Reads the byte at `address`, or writes `value` to it, returning the byte.
+///
+/// # Remarks
+///
+/// The address is checked against the active page tables first, so GDB can't fault the stub. Supervisor
+/// protections are lifted for the access, so user pages can be read and read-only pages (e.g. kernel text) can be
+/// written.
+fn access(address: usize, value: Option<u8>) -> Result<u8, Error> {
+    let virtual_address = Address::<Virtual>::new(address).ok_or(Error::Unmapped(address))?;
+    // Safety: The page tables are only read.
+    let (_, attributes, _) = unsafe { Mapper::active() }
+        .translate(virtual_address)
+        .ok_or(Error::Unmapped(address))?;
+
+    let lift_write_protect = value.is_some() && !attributes.contains(TableEntryFlags::WRITABLE);
+
+    // Safety: The stub runs with interrupts disabled, so lifted protections are never observed elsewhere.
+    unsafe {
+        if lift_write_protect {
+            CR0::disable(CR0Flags::WP);
+        }
+    }
+
+    let user_access = attributes
+        .contains(TableEntryFlags::USER)
+        .then(crate::mem::user::UserAccess::begin);
+
+    let ptr = core::ptr::with_exposed_provenance_mut::<u8>(address);
+    // Safety: The address is mapped, and accessible with the protections lifted above.
+    let byte = unsafe {
+        match value {
+            Some(value) => {
+                ptr.write_volatile(value);
+                value
+            }
+            None => ptr.read_volatile(),
+        }
+    };
+
+    drop(user_access);
+
+    // Safety: Restores the protections lifted above.
+    unsafe {
+        if lift_write_protect {
+            CR0::enable(CR0Flags::WP);
+        }
+    }
+
+    Ok(byte)
+}
+
+/// Receives the next well-formed packet into `packet`, acknowledging it.
+fn receive_packet(packet: &mut Buffer) {
+    loop {
+        // Acknowledgements and interrupts outside of a packet are ignored.
+        while console::wait_byte() != b'$' {}
+
+        packet.clear();
+        let mut checksum = 0u8;
+        let mut overflowed = false;
+        loop {
+            let byte = console::wait_byte();
+            if byte == b'#' {
+                break;
+            }
+
+            checksum = checksum.wrapping_add(byte);
+            overflowed |= packet.push(byte).is_err();
+        }
+
+        let expected_checksum = parse_hex_byte(&[console::wait_byte(), console::wait_byte()]);
+
+        if !overflowed && expected_checksum.is_ok_and(|expected| expected == checksum) {
+            write_bytes(b"+");
+
+            return;
+        }
+
+        // Ask GDB to retransmit.
+        write_bytes(b"-");
+    }
+}
+
+fn send_packet(data: &[u8]) {
+    let checksum = data
+        .iter()
+        .fold(0u8, |checksum, byte| checksum.wrapping_add(*byte));
+
+    write_bytes(b"$");
+    write_bytes(data);
+    write_bytes(&[b'#', hex_digit(checksum >> 4), hex_digit(checksum)]);
+}
+
+fn write_bytes(bytes: &[u8]) {
+    if let Some(serial) = serial::get() {
+        serial.write_bytes(bytes);
+    }
+}
+
+fn push(reply: &mut Buffer, bytes: &[u8]) -> Result<(), Error> {
+    reply.extend_from_slice(bytes).map_err(|_| Error::TooLong)
+}
+
+fn push_hex_bytes(reply: &mut Buffer, bytes: &[u8]) -> Result<(), Error> {
+    bytes
+        .iter()
+        .try_for_each(|byte| push(reply, &[hex_digit(byte >> 4), hex_digit(*byte)]))
+}
+
+fn push_hex_number(reply: &mut Buffer, value: usize) -> Result<(), Error> {
+    let mut digits = heapless::String::<{ size_of::<usize>() * 2 }>::new();
+    write!(digits, "{value:x}").map_err(|_| Error::TooLong)?;
+
+    push(reply, digits.as_bytes())
+}
+
+const fn hex_digit(nibble: u8) -> u8 {
+    match nibble & 0xF {
+        nibble @ 0..10 => b'0' + nibble,
+        nibble => b'a' + (nibble - 10),
+    }
+}
+
+fn parse_hex_byte(hex: &[u8]) -> Result<u8, Error> {
+    let [high, low] = hex else {
+        return Err(Error::Malformed);
+    };
+
+    let parse_digit = |digit: u8| {
+        char::from(digit)
+            .to_digit(16)
+            .and_then(|digit| u8::try_from(digit).ok())
+            .ok_or(Error::Malformed)
+    };
+
+    Ok((parse_digit(*high)? << 4) | parse_digit(*low)?)
+}
+
+fn parse_hex(hex: &[u8]) -> Result<usize, Error> {
+    let hex = core::str::from_utf8(hex).map_err(|_| Error::Malformed)?;
+
+    usize::from_str_radix(hex, 16).map_err(|_| Error::Malformed)
+}
+
+/// Parses the `addr,length` arguments of memory packets.
+fn parse_address_length(args: &[u8]) -> Result<(usize, usize), Error> {
+    let (address, length) = split_once(args, b',').ok_or(Error::Malformed)?;
+
+    Ok((parse_hex(address)?, parse_hex(length)?))
+}
+
+fn split_once(bytes: &[u8], delimiter: u8) -> Option<(&[u8], &[u8])> {
+    let index = bytes.iter().position(|byte| *byte == delimiter)?;
+
+    Some((&bytes[..index], &bytes[(index + 1)..]))
+}
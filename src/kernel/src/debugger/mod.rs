@@ -0,0 +1,94 @@
+//! Kernel debug shell, reachable over the serial console.
+//!
+//! The shell is entered either at the end of boot (via `--debug-shell`), or at any time by sending
+//! [`ESCAPE_SEQUENCE`] over the serial line. While it is active, the hardware thread running it does nothing else.
+
+mod backtrace;
+mod commands;
+pub mod gdb;
+
+pub use backtrace::{capture_remote, capture_requested, is_backtrace_requested};
+
+use crate::console;
+use alloc::string::String;
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Serial input which enters the debug shell: `Ctrl-]` twice.
+pub const ESCAPE_SEQUENCE: &[u8] = &[0x1D, 0x1D];
+
+crate::parameters! {
+    /// Whether to enter the kernel debug shell at the end of boot.
+    "debug-shell": bool = false,
+}
+
+const PROMPT: &str = "kdb> ";
+
+/// Whether a hardware thread is currently running the shell.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Acquire)
+}
+
+/// Runs the debug shell on the current hardware thread until the user exits it.
+///
+/// # Remarks
+///
+/// If the shell is already active (on any hardware thread), this returns immediately.
+pub fn enter() {
+    if ACTIVE
+        .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+        .is_err()
+    {
+        return;
+    }
+
+    let attach_gdb = crate::interrupts::uninterruptable(|| {
+        let mut out = console::Writer;
+        writeln!(
+            out,
+            "\n-- kernel debug shell (hwthread #{}); `help` lists commands --",
+            crate::cpu::get_id()
+        )
+        .ok();
+
+        let mut line = String::new();
+        let attach_gdb = loop {
+            console::write_str(PROMPT);
+
+            line.clear();
+            console::read_line(&mut line);
+
+            let mut args = line.split_whitespace();
+            let Some(command) = args.next() else {
+                continue;
+            };
+
+            if matches!(command, "exit" | "continue" | "c") {
+                break false;
+            }
+
+            if command == "gdb" {
+                break true;
+            }
+
+            if let Err(error) = commands::run(&mut out, command, args) {
+                writeln!(out, "error: {error}").ok();
+            }
+        };
+
+        writeln!(out, "-- leaving kernel debug shell --").ok();
+
+        attach_gdb
+    });
+
+    ACTIVE.store(false, Ordering::Release);
+
+    // The shell and GDB share the serial line, so GDB is only attached once the shell has been left.
+    if attach_gdb {
+        gdb::attach();
+    }
+}
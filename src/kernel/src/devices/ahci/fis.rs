@@ -0,0 +1,160 @@
+//! Frame information structures, and the command list & table entries which hand them to the HBA.
+
+use core::ops::Range;
+
+/// Length of a register host-to-device FIS, in bytes.
+pub const H2D_LEN: usize = 20;
+
+const FIS_TYPE_REGISTER_H2D: u8 = 0x27;
+/// Register host-to-device FIS: the FIS carries a command (rather than a device control update).
+const H2D_COMMAND: u8 = 1 << 7;
+/// Device register: addresses are LBAs.
+const DEVICE_LBA: u8 = 1 << 6;
+
+/// ATA commands.
+pub const ATA_IDENTIFY_DEVICE: u8 = 0xEC;
+pub const ATA_READ_DMA_EXT: u8 = 0x25;
+pub const ATA_WRITE_DMA_EXT: u8 = 0x35;
+
+/// Register host-to-device FIS issuing `command` for `count` sectors from `lba`.
+pub fn register_h2d(command: u8, lba: u64, count: u16) -> [u8; H2D_LEN] {
+    let lba = lba.to_le_bytes();
+    let count = count.to_le_bytes();
+
+    let mut fis = [0; H2D_LEN];
+    fis[0] = FIS_TYPE_REGISTER_H2D;
+    fis[1] = H2D_COMMAND;
+    fis[2] = command;
+    fis[4..7].copy_from_slice(&lba[..3]);
+    fis[7] = DEVICE_LBA;
+    fis[8..11].copy_from_slice(&lba[3..6]);
+    fis[12..14].copy_from_slice(&count);
+
+    fis
+}
+
+/// A command list entry.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CommandHeader {
+    /// Command FIS length (in dwords), direction, and the length of the PRD table.
+    pub flags: u32,
+    /// Bytes transferred, as written by the HBA.
+    pub transferred: u32,
+    pub table: u64,
+    pub reserved: [u32; 4],
+}
+
+/// Command header: data is written to the device.
+const HEADER_WRITE: u32 = 1 << 6;
+
+impl CommandHeader {
+    /// Header of a command whose table is at `table`, which carries a register FIS and `prd_count` PRD entries.
+    pub fn new(table: u64, prd_count: u16, write: bool) -> Self {
+        let fis_dwords = u32::try_from(H2D_LEN / 4).unwrap();
+        let direction = if write { HEADER_WRITE } else { 0 };
+
+        Self {
+            flags: fis_dwords | direction | (u32::from(prd_count) << 16),
+            transferred: 0,
+            table,
+            reserved: [0; 4],
+        }
+    }
+}
+
+/// An entry of a command table's physical region descriptor table.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PrdEntry {
+    pub address: u64,
+    pub reserved: u32,
+    /// Byte count (less one).
+    pub count: u32,
+}
+
+impl PrdEntry {
+    /// Entry describing the `len` bytes at `address`.
+    pub fn new(address: u64, len: usize) -> Self {
+        Self {
+            address,
+            reserved: 0,
+            count: u32::try_from(len - 1).unwrap(),
+        }
+    }
+}
+
+/// Offset of the PRD table within a command table, after the command FIS & ATAPI command areas.
+pub const PRD_TABLE_OFFSET: usize = 0x80;
+
+/// Words of the IDENTIFY DEVICE data.
+const IDENTIFY_MODEL: Range<usize> = 27..47;
+const IDENTIFY_COMMAND_SETS: usize = 83;
+const IDENTIFY_LBA28_SECTORS: Range<usize> = 60..62;
+const IDENTIFY_LBA48_SECTORS: Range<usize> = 100..104;
+/// Supported command sets: the 48-bit address feature set.
+const COMMAND_SETS_LBA48: u16 = 1 << 10;
+
+/// Device details from its IDENTIFY DEVICE data.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub model: alloc::string::String,
+    pub sector_count: u64,
+    pub lba48: bool,
+}
+
+impl Identity {
+    /// Parses the 512 bytes of IDENTIFY DEVICE data.
+    pub fn parse(data: &[u8]) -> Self {
+        let word = |index: usize| u16::from_le_bytes([data[index * 2], data[(index * 2) + 1]]);
+        let words = |range: Range<usize>| {
+            range
+                .rev()
+                .fold(0u64, |value, index| (value << 16) | u64::from(word(index)))
+        };
+
+        // Strings are stored with the bytes of each word swapped.
+        let model = IDENTIFY_MODEL
+            .flat_map(|index| word(index).to_be_bytes())
+            .map(char::from)
+            .collect::<alloc::string::String>();
+
+        let lba48 = (word(IDENTIFY_COMMAND_SETS) & COMMAND_SETS_LBA48) != 0;
+
+        Self {
+            model: alloc::string::String::from(model.trim()),
+            sector_count: if lba48 {
+                words(IDENTIFY_LBA48_SECTORS)
+            } else {
+                words(IDENTIFY_LBA28_SECTORS)
+            },
+            lba48,
+        }
+    }
+}
+
+crate::kernel_test! {
+    /// Commands are encoded into a register FIS, and identities decoded from their byte-swapped words.
+    fn encodes_ahci_structures() -> crate::tests::Outcome {
+        let fis = register_h2d(ATA_READ_DMA_EXT, 0x0605_0403_0201, 0x0708);
+        if fis[..4] != [FIS_TYPE_REGISTER_H2D, H2D_COMMAND, ATA_READ_DMA_EXT, 0]
+            || fis[4..11] != [1, 2, 3, DEVICE_LBA, 4, 5, 6]
+            || fis[12..14] != [8, 7]
+        {
+            return Err("register FIS was encoded incorrectly");
+        }
+
+        let mut data = [0; 512];
+        data[54..58].copy_from_slice(b"QMEU");
+        data[166..168].copy_from_slice(&COMMAND_SETS_LBA48.to_le_bytes());
+        data[200..204].copy_from_slice(&0x0123_4567u32.to_le_bytes());
+        data[120..124].copy_from_slice(&0xFFu32.to_le_bytes());
+
+        let identity = Identity::parse(&data);
+        if identity.model != "QEMU" || !identity.lba48 || identity.sector_count != 0x0123_4567 {
+            return Err("identity was decoded incorrectly");
+        }
+
+        Ok(())
+    }
+}
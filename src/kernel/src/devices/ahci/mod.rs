@@ -0,0 +1,252 @@
+//! AHCI (SATA) host bus adapter driver, exposing each attached ATA disk as a [`BlockDevice`].
+//!
+//! The HBA is switched into AHCI mode, and every implemented port with an ATA device attached is taken over: its
+//! command list & received FIS area are allocated, it's started, and the device is identified. Commands are issued
+//! one at a time through the port's first command slot (native command queuing isn't used), and their completion is
+//! polled, as interrupts would need MSI (which the kernel doesn't support), or legacy pin routing.
+//!
+//! Data is transferred through a contiguous bounce buffer per port, so each command names a single PRD entry, and
+//! transfers larger than the buffer are split into several commands.
+
+mod fis;
+mod port;
+
+use crate::devices::{
+    block::{self, BlockDevice},
+    pci::{
+        config::{Bar, Command as PciCommand},
+        driver::{Driver, Match},
+        tree::Device,
+    },
+    poll_for,
+};
+use alloc::{format, sync::Arc, vec::Vec};
+use core::{
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use libsys::{Address, Frame};
+use port::{Disk, Port};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to access the PCI configuration space: {0}")]
+    Config(#[from] crate::devices::pci::config::Error),
+
+    #[error("HBA registers lie in an unusable BAR")]
+    UnusableBar,
+
+    #[error("failed to map the HBA's registers")]
+    Paging(#[from] crate::mem::paging::Error),
+
+    #[error("failed to allocate port memory: {0}")]
+    Dma(#[from] crate::mem::dma::Error),
+
+    #[error("port memory lies above 4GiB, which the HBA can't address")]
+    Unaddressable,
+
+    #[error("HBA timed out {0}")]
+    Timeout(&'static str),
+
+    #[error("device doesn't support 48-bit addressing")]
+    Unsupported,
+
+    #[error("command {command:#04X} failed with task file {task_file:#X}")]
+    Command { command: u8, task_file: u32 },
+}
+
+/// Class, subclass, and programming interface of AHCI HBAs.
+const CLASS_MASS_STORAGE: u8 = 0x01;
+const SUBCLASS_SATA: u8 = 0x06;
+const PROG_IF_AHCI: u8 = 0x01;
+
+/// The HBA's registers (ABAR) are in BAR5.
+const ABAR_INDEX: u8 = 5;
+
+/// Generic host control registers, at the start of ABAR.
+#[repr(usize)]
+#[derive(Debug, IntoPrimitive, Clone, Copy)]
+#[allow(non_camel_case_types)]
+#[rustfmt::skip]
+enum Register {
+    CAP     = 0x00,
+    GHC     = 0x04,
+    PI      = 0x0C,
+    VS      = 0x10,
+}
+
+/// Offset & length of each port's register block.
+const PORT_BASE: usize = 0x100;
+const PORT_LEN: usize = 0x80;
+const MAX_PORTS: u32 = 32;
+
+/// Capabilities: the HBA can address memory above 4GiB.
+const CAP_64BIT: u32 = 1 << 31;
+
+/// Global HBA control: AHCI (rather than legacy IDE) mode is enabled.
+const GHC_AHCI_ENABLE: u32 = 1 << 31;
+/// Global HBA control: interrupts are enabled.
+const GHC_INTERRUPTS: u32 = 1 << 1;
+
+static DRIVER: Driver = Driver {
+    name: "ahci",
+    matches: &[Match::Class {
+        class: CLASS_MASS_STORAGE,
+        subclass: SUBCLASS_SATA,
+        prog_if: Some(PROG_IF_AHCI),
+    }],
+    probe,
+};
+
+/// Registers the AHCI driver.
+pub fn register() {
+    if let Err(error) = crate::devices::pci::driver::register(&DRIVER) {
+        warn!("Failed to register the AHCI driver: {error}");
+    }
+}
+
+/// Index the next disk is named by.
+static NEXT_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+fn probe(device: &'static Device) -> bool {
+    match bring_up(device) {
+        Ok(disks) => {
+            for disk in disks {
+                block::register(Arc::new(disk));
+            }
+
+            true
+        }
+
+        Err(error) => {
+            warn!(
+                "Failed to initialize AHCI HBA {}: {error}",
+                device.address()
+            );
+            false
+        }
+    }
+}
+
+/// The HBA's memory-mapped registers.
+struct Registers {
+    base: NonNull<u8>,
+}
+
+impl Registers {
+    fn read(&self, register: Register) -> u32 {
+        // Safety: Register offset is within the mapped registers, and naturally aligned.
+        unsafe {
+            self.base
+                .byte_add(usize::from(register))
+                .cast::<u32>()
+                .read_volatile()
+        }
+    }
+
+    fn write(&self, register: Register, value: u32) {
+        // Safety: Register offset is within the mapped registers, and naturally aligned.
+        unsafe {
+            self.base
+                .byte_add(usize::from(register))
+                .cast::<u32>()
+                .write_volatile(value);
+        }
+    }
+
+    /// Register block of the port `index`.
+    fn port(&self, index: u32) -> NonNull<u8> {
+        // Safety: The mapping covers the register blocks of every port.
+        unsafe {
+            self.base
+                .byte_add(PORT_BASE + (usize::try_from(index).unwrap() * PORT_LEN))
+        }
+    }
+}
+
+/// Maps the HBA's registers from ABAR.
+fn map_registers(device: &Device) -> Result<NonNull<u8>, Error> {
+    let (address, size) = device
+        .bars()
+        .iter()
+        .find(|decoded_bar| decoded_bar.index == ABAR_INDEX)
+        .and_then(|decoded_bar| match decoded_bar.bar {
+            Bar::Memory32 { address, .. } => {
+                Some((usize::try_from(address).ok()?, decoded_bar.size))
+            }
+            Bar::Memory64 { address, .. } => {
+                Some((usize::try_from(address).ok()?, decoded_bar.size))
+            }
+            Bar::Io { .. } => None,
+        })
+        .ok_or(Error::UnusableBar)?;
+
+    let frame = Address::<Frame>::new(address).ok_or(Error::UnusableBar)?;
+    let size = usize::try_from(size).map_err(|_| Error::UnusableBar)?;
+
+    Ok(crate::mem::map_mmio(frame, size)?)
+}
+
+/// Enables AHCI mode on the HBA of `device`, and takes over each of its ports with an ATA disk attached.
+///
+/// Ports which fail to come up are skipped, rather than failing the whole HBA.
+fn bring_up(device: &Device) -> Result<Vec<Disk>, Error> {
+    let mut config = device.config()?;
+    // Completions are polled, so the legacy pin is disabled.
+    config.set_command(
+        config.command()
+            | PciCommand::MEMORY_SPACE
+            | PciCommand::BUS_MASTER
+            | PciCommand::INTERRUPT_DISABLE,
+    );
+
+    let registers = Registers {
+        base: map_registers(device)?,
+    };
+    let control = registers.read(Register::GHC);
+    registers.write(Register::GHC, (control | GHC_AHCI_ENABLE) & !GHC_INTERRUPTS);
+
+    let addresses_64bit = (registers.read(Register::CAP) & CAP_64BIT) != 0;
+    let implemented = registers.read(Register::PI);
+    let version = registers.read(Register::VS);
+
+    let mut disks = Vec::new();
+    for index in (0..MAX_PORTS).filter(|index| (implemented & (1 << index)) != 0) {
+        let port_registers = registers.port(index);
+        if !port::has_ata_device(port_registers) {
+            continue;
+        }
+
+        let name = format!("sata{}", NEXT_INDEX.load(Ordering::Relaxed));
+        match Port::new(port_registers, addresses_64bit).and_then(|port| Disk::new(name, port)) {
+            Ok(disk) if !disk.identity().lba48 => {
+                warn!("AHCI port {index}: {}", Error::Unsupported);
+            }
+
+            Ok(disk) => {
+                NEXT_INDEX.fetch_add(1, Ordering::Relaxed);
+                info!(
+                    "AHCI port {index} of {} is {}: {} ({} sectors).",
+                    device.address(),
+                    disk.name(),
+                    disk.identity().model,
+                    disk.sector_count()
+                );
+
+                disks.push(disk);
+            }
+
+            Err(error) => warn!("Failed to initialize AHCI port {index}: {error}"),
+        }
+    }
+
+    info!(
+        "AHCI {}.{} HBA {} has {} disks.",
+        version >> 16,
+        (version >> 8) & 0xFF,
+        device.address(),
+        disks.len()
+    );
+
+    Ok(disks)
+}
@@ -0,0 +1,469 @@
+use super::{Error, fis};
+use crate::{
+    devices::block::{self, BlockDevice, SECTOR_SIZE},
+    interrupts::InterruptCell,
+    mem::HigherHalfDirectMap,
+    sync::SpinMutex,
+    time::Stopwatch,
+};
+use alloc::string::String;
+use core::{
+    num::NonZero,
+    ptr::NonNull,
+    sync::atomic::{Ordering, fence},
+    time::Duration,
+};
+use libsys::{Address, Frame, page_size};
+
+/// Port registers, relative to the port's register block.
+#[repr(usize)]
+#[derive(Debug, IntoPrimitive, Clone, Copy)]
+#[allow(non_camel_case_types)]
+#[rustfmt::skip]
+enum Register {
+    CLB     = 0x00,
+    CLBU    = 0x04,
+    FB      = 0x08,
+    FBU     = 0x0C,
+    IS      = 0x10,
+    IE      = 0x14,
+    CMD     = 0x18,
+    TFD     = 0x20,
+    SIG     = 0x24,
+    SSTS    = 0x28,
+    SERR    = 0x30,
+    CI      = 0x38,
+}
+
+/// Command & status: start processing the command list.
+const CMD_START: u32 = 1 << 0;
+/// Command & status: receive FISes.
+const CMD_FIS_RECEIVE: u32 = 1 << 4;
+/// Command & status: FIS receive is running.
+const CMD_FIS_RUNNING: u32 = 1 << 14;
+/// Command & status: the command list is running.
+const CMD_LIST_RUNNING: u32 = 1 << 15;
+
+/// Task file data: the device is busy, or has data to transfer.
+const TFD_BUSY: u32 = 1 << 7;
+const TFD_DRQ: u32 = 1 << 3;
+/// Task file data: the last command failed.
+const TFD_ERROR: u32 = 1 << 0;
+
+/// Interrupt status: a task file error was reported.
+const IS_TASK_FILE_ERROR: u32 = 1 << 30;
+
+/// SATA status: a device is present, and communication is established.
+const SSTS_DET_PRESENT: u32 = 0x3;
+/// SATA status: the interface is active.
+const SSTS_IPM_ACTIVE: u32 = 0x1;
+
+/// Signature of an ATA (rather than ATAPI or port multiplier) device.
+const SIGNATURE_ATA: u32 = 0x0000_0101;
+
+/// Offsets of the received FIS area & command table in a port's frame, after its command list.
+const RECEIVED_FIS_OFFSET: usize = 0x400;
+const COMMAND_TABLE_OFFSET: usize = 0x500;
+
+/// Frames of contiguous bounce buffer each port transfers through.
+const BOUNCE_FRAMES: usize = 16;
+
+/// Longest the port may take to stop or start, and a command to complete.
+const PORT_TIMEOUT: Duration = Duration::from_millis(500);
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Name port memory is tagged with in the DMA audit.
+const DMA_OWNER: &str = "ahci";
+
+/// Whether the port whose registers are at `registers` has an ATA device attached, with communication established.
+pub fn has_ata_device(registers: NonNull<u8>) -> bool {
+    let read = |register: Register| {
+        // Safety: Register offset is within the port's register block, and naturally aligned.
+        unsafe {
+            registers
+                .byte_add(usize::from(register))
+                .cast::<u32>()
+                .read_volatile()
+        }
+    };
+
+    let status = read(Register::SSTS);
+    (status & 0xF) == SSTS_DET_PRESENT
+        && ((status >> 8) & 0xF) == SSTS_IPM_ACTIVE
+        && read(Register::SIG) == SIGNATURE_ATA
+}
+
+/// A port's registers, memory, and the single command slot the driver issues commands through.
+pub struct Port {
+    registers: NonNull<u8>,
+    /// Command list, received FIS area, and command table.
+    frame: Address<Frame>,
+    memory: NonNull<u8>,
+    bounce_frame: Address<Frame>,
+    bounce: NonNull<u8>,
+    /// A command is in flight in the command slot.
+    busy: bool,
+}
+
+// Safety: Port registers & memory are only accessed through the owning disk's lock.
+unsafe impl Send for Port {}
+
+impl Port {
+    /// Takes over the port whose registers are at `registers`, allocating its memory and starting its command list.
+    ///
+    /// `addresses_64bit` is whether the HBA can address memory above 4GiB.
+    pub fn new(registers: NonNull<u8>, addresses_64bit: bool) -> Result<Self, Error> {
+        let frame = crate::mem::dma::allocate(NonZero::<usize>::MIN, DMA_OWNER)?;
+        let bounce_frame =
+            match crate::mem::dma::allocate(NonZero::new(BOUNCE_FRAMES).unwrap(), DMA_OWNER) {
+                Ok(bounce_frame) => bounce_frame,
+                Err(error) => {
+                    free(frame, 1);
+                    return Err(error.into());
+                }
+            };
+
+        let port = Self {
+            registers,
+            frame,
+            memory: NonNull::new(HigherHalfDirectMap::frame_to_page(frame).as_ptr()).unwrap(),
+            bounce_frame,
+            bounce: NonNull::new(HigherHalfDirectMap::frame_to_page(bounce_frame).as_ptr())
+                .unwrap(),
+            busy: false,
+        };
+
+        let highest = port.bounce_physical() + u64::try_from(Self::bounce_len()).unwrap();
+        if !addresses_64bit
+            && (port.physical(0) > u64::from(u32::MAX) || highest > u64::from(u32::MAX))
+        {
+            return Err(Error::Unaddressable);
+        }
+
+        // Safety: The frame was just allocated, and is accessible through the HHDM.
+        unsafe { port.memory.write_bytes(0, page_size()) };
+
+        port.stop()?;
+        // Addresses are written as halves, as HBAs needn't support 64-bit register accesses.
+        let (command_list, received_fis) = (port.physical(0), port.physical(RECEIVED_FIS_OFFSET));
+        port.write(
+            Register::CLB,
+            u32::try_from(command_list & 0xFFFF_FFFF).unwrap(),
+        );
+        port.write(Register::CLBU, u32::try_from(command_list >> 32).unwrap());
+        port.write(
+            Register::FB,
+            u32::try_from(received_fis & 0xFFFF_FFFF).unwrap(),
+        );
+        port.write(Register::FBU, u32::try_from(received_fis >> 32).unwrap());
+        port.start()?;
+
+        Ok(port)
+    }
+
+    /// Length of the bounce buffer, which bounds the data of a single command.
+    pub fn bounce_len() -> usize {
+        BOUNCE_FRAMES * page_size()
+    }
+
+    fn read<T: Copy>(&self, register: Register) -> T {
+        // Safety: Register offset is within the port's register block, and naturally aligned.
+        unsafe {
+            self.registers
+                .byte_add(usize::from(register))
+                .cast::<T>()
+                .read_volatile()
+        }
+    }
+
+    fn write<T: Copy>(&self, register: Register, value: T) {
+        // Safety: Register offset is within the port's register block, and naturally aligned.
+        unsafe {
+            self.registers
+                .byte_add(usize::from(register))
+                .cast::<T>()
+                .write_volatile(value);
+        }
+    }
+
+    fn physical(&self, offset: usize) -> u64 {
+        u64::try_from(self.frame.get().get() + offset).unwrap()
+    }
+
+    fn bounce_physical(&self) -> u64 {
+        u64::try_from(self.bounce_frame.get().get()).unwrap()
+    }
+
+    /// The bounce buffer, which the HBA doesn't access unless a command is in flight.
+    fn bounce(&mut self) -> &mut [u8] {
+        // Safety: Bounce buffer is contiguous, and only accessed through the port.
+        unsafe { core::slice::from_raw_parts_mut(self.bounce.as_ptr(), Self::bounce_len()) }
+    }
+
+    /// Stops the port processing its command list & receiving FISes.
+    fn stop(&self) -> Result<(), Error> {
+        let command: u32 = self.read(Register::CMD);
+        self.write(Register::CMD, command & !CMD_START);
+        crate::devices::poll_for(PORT_TIMEOUT, || {
+            ((self.read::<u32>(Register::CMD) & CMD_LIST_RUNNING) == 0).then_some(())
+        })
+        .ok_or(Error::Timeout("stopping a port's command list"))?;
+
+        let command: u32 = self.read(Register::CMD);
+        self.write(Register::CMD, command & !CMD_FIS_RECEIVE);
+        crate::devices::poll_for(PORT_TIMEOUT, || {
+            ((self.read::<u32>(Register::CMD) & CMD_FIS_RUNNING) == 0).then_some(())
+        })
+        .ok_or(Error::Timeout("stopping a port's FIS receive"))
+    }
+
+    /// Clears the port's errors, and starts it receiving FISes & processing its command list once the device is idle.
+    fn start(&self) -> Result<(), Error> {
+        self.write(Register::SERR, u32::MAX);
+        self.write(Register::IS, u32::MAX);
+        // Completions are polled.
+        self.write(Register::IE, 0u32);
+
+        let command: u32 = self.read(Register::CMD);
+        self.write(Register::CMD, command | CMD_FIS_RECEIVE);
+
+        crate::devices::poll_for(PORT_TIMEOUT, || {
+            ((self.read::<u32>(Register::TFD) & (TFD_BUSY | TFD_DRQ)) == 0).then_some(())
+        })
+        .ok_or(Error::Timeout("waiting for a port's device"))?;
+
+        let command: u32 = self.read(Register::CMD);
+        self.write(Register::CMD, command | CMD_START);
+
+        Ok(())
+    }
+
+    /// Issues `command` for `count` sectors from `lba`, transferring `len` bytes of the bounce buffer.
+    fn issue(&mut self, command: u8, lba: u64, count: u16, len: usize, write: bool) {
+        debug_assert!(!self.busy && len <= Self::bounce_len());
+
+        let table = COMMAND_TABLE_OFFSET;
+        let header = fis::CommandHeader::new(self.physical(table), 1, write);
+        let prd = fis::PrdEntry::new(self.bounce_physical(), len);
+        let register_fis = fis::register_h2d(command, lba, count);
+
+        // Safety: The command list's first header, and the command table, lie within the port's frame, and the HBA
+        //         doesn't access them while the slot isn't issued.
+        unsafe {
+            self.memory
+                .cast::<fis::CommandHeader>()
+                .write_volatile(header);
+            self.memory
+                .byte_add(table)
+                .cast::<[u8; fis::H2D_LEN]>()
+                .write_volatile(register_fis);
+            self.memory
+                .byte_add(table + fis::PRD_TABLE_OFFSET)
+                .cast::<fis::PrdEntry>()
+                .write_volatile(prd);
+        }
+
+        // The command must be visible before it's issued.
+        fence(Ordering::SeqCst);
+        self.busy = true;
+        self.write(Register::CI, 1u32);
+    }
+
+    /// Result of the command in flight, once it has completed, with the task file data if it failed.
+    fn poll_completion(&mut self) -> Option<Result<(), u32>> {
+        let interrupt_status: u32 = self.read(Register::IS);
+        let task_file: u32 = self.read(Register::TFD);
+        if (interrupt_status & IS_TASK_FILE_ERROR) != 0 || (task_file & TFD_ERROR) != 0 {
+            self.busy = false;
+
+            // The port halts on a task file error, so it's restarted to clear it.
+            if let Err(error) = self.stop().and_then(|()| self.start()) {
+                warn!("Failed to restart an AHCI port after an error: {error}");
+            }
+
+            return Some(Err(task_file));
+        }
+
+        if (self.read::<u32>(Register::CI) & 1) != 0 {
+            return None;
+        }
+
+        // Data must be read after the completion that published it.
+        fence(Ordering::Acquire);
+        self.busy = false;
+
+        Some(Ok(()))
+    }
+}
+
+impl Drop for Port {
+    fn drop(&mut self) {
+        if let Err(error) = self.stop() {
+            warn!("Failed to stop an AHCI port: {error}");
+        }
+
+        free(self.frame, 1);
+        free(self.bounce_frame, BOUNCE_FRAMES);
+    }
+}
+
+fn free(frame: Address<Frame>, count: usize) {
+    if let Err(error) = crate::mem::dma::deallocate(frame, NonZero::new(count).unwrap(), DMA_OWNER)
+    {
+        warn!("Failed to free AHCI port memory: {error}");
+    }
+}
+
+/// Data transferred by a single command.
+enum Transfer<'a> {
+    Identify(&'a mut [u8]),
+    Read(&'a mut [u8]),
+    Write(&'a [u8]),
+}
+
+/// An ATA device attached to a port, as a [`BlockDevice`].
+pub struct Disk {
+    name: String,
+    port: InterruptCell<SpinMutex<Port>>,
+    identity: fis::Identity,
+}
+
+impl Disk {
+    /// Identifies the device attached to `port`.
+    pub fn new(name: String, port: Port) -> Result<Self, Error> {
+        let mut disk = Self {
+            name,
+            port: InterruptCell::new(SpinMutex::new(port)),
+            identity: fis::Identity::default(),
+        };
+
+        let mut data = [0; SECTOR_SIZE];
+        disk.request(0, Transfer::Identify(&mut data))
+            .map_err(|task_file| Error::Command {
+                command: fis::ATA_IDENTIFY_DEVICE,
+                task_file,
+            })?;
+        disk.identity = fis::Identity::parse(&data);
+
+        Ok(disk)
+    }
+
+    #[inline]
+    pub const fn identity(&self) -> &fis::Identity {
+        &self.identity
+    }
+
+    /// Repeatedly calls `func` with the port until it returns `Some`.
+    fn wait_until<T>(&self, mut func: impl FnMut(&mut Port) -> Option<T>) -> T {
+        loop {
+            if let Some(value) = self.port.with(|port| func(&mut port.lock())) {
+                return value;
+            }
+
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Performs `transfer` (of at most the bounce buffer's length, and a whole number of sectors) from `sector`, as a
+    /// single command, waiting for it to complete.
+    fn request(&self, sector: u64, mut transfer: Transfer<'_>) -> Result<(), u32> {
+        let (command, len, write) = match &transfer {
+            Transfer::Identify(buf) => (fis::ATA_IDENTIFY_DEVICE, buf.len(), false),
+            Transfer::Read(buf) => (fis::ATA_READ_DMA_EXT, buf.len(), false),
+            Transfer::Write(buf) => (fis::ATA_WRITE_DMA_EXT, buf.len(), true),
+        };
+        let count = u16::try_from(len / SECTOR_SIZE).unwrap();
+
+        self.wait_until(|port| {
+            if port.busy {
+                return None;
+            }
+
+            if let Transfer::Write(data) = &transfer {
+                port.bounce()[..data.len()].copy_from_slice(data);
+            }
+            port.issue(command, sector, count, len, write);
+
+            Some(())
+        });
+
+        let deadline = Stopwatch::counter() + (COMMAND_TIMEOUT.as_secs() * Stopwatch::frequency());
+        self.wait_until(|port| {
+            let result = port.poll_completion();
+
+            match result {
+                Some(Ok(())) => {
+                    if let Transfer::Identify(buf) | Transfer::Read(buf) = &mut transfer {
+                        let len = buf.len();
+                        buf.copy_from_slice(&port.bounce()[..len]);
+                    }
+
+                    Some(Ok(()))
+                }
+
+                Some(Err(task_file)) => Some(Err(task_file)),
+
+                None if Stopwatch::counter() >= deadline => {
+                    // The command is abandoned by restarting the port, which clears its command issue register.
+                    port.busy = false;
+                    if let Err(error) = port.stop().and_then(|()| port.start()) {
+                        warn!("Failed to restart an AHCI port after a timeout: {error}");
+                    }
+
+                    Some(Err(port.read(Register::TFD)))
+                }
+
+                None => None,
+            }
+        })
+    }
+
+    fn transfer_failed(&self, sector: u64, task_file: u32) -> block::Error {
+        warn!(
+            "{} failed a command at sector {sector}: task file {task_file:#X}",
+            self.name
+        );
+
+        block::Error::Io
+    }
+}
+
+impl BlockDevice for Disk {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn sector_count(&self) -> u64 {
+        self.identity.sector_count
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn read(&self, sector: u64, buf: &mut [u8]) -> Result<(), block::Error> {
+        block::check_range(self.sector_count(), sector, buf.len())?;
+
+        let step = Port::bounce_len();
+        (sector..)
+            .step_by(step / SECTOR_SIZE)
+            .zip(buf.chunks_mut(step))
+            .try_for_each(|(sector, chunk)| {
+                self.request(sector, Transfer::Read(chunk))
+                    .map_err(|task_file| self.transfer_failed(sector, task_file))
+            })
+    }
+
+    fn write(&self, sector: u64, buf: &[u8]) -> Result<(), block::Error> {
+        block::check_range(self.sector_count(), sector, buf.len())?;
+
+        let step = Port::bounce_len();
+        (sector..)
+            .step_by(step / SECTOR_SIZE)
+            .zip(buf.chunks(step))
+            .try_for_each(|(sector, chunk)| {
+                self.request(sector, Transfer::Write(chunk))
+                    .map_err(|task_file| self.transfer_failed(sector, task_file))
+            })
+    }
+}
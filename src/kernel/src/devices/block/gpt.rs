@@ -0,0 +1,348 @@
+//! GUID partition tables, whose partitions are registered as block devices of their own.
+//!
+//! Each disk's primary table is read from its second sector, and its header & entry array are checked against their
+//! CRC32s. Every used entry becomes a [`Partition`], named after its disk & its index in the array (from 1), e.g.
+//! `sata0p1`, which transfers the sectors it spans through its disk. Backup tables aren't consulted, and disks with
+//! only an MBR aren't partitioned.
+
+use super::{BlockDevice, Request, SECTOR_SIZE};
+use crate::sync::SpinMutex;
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
+use uuid::Uuid;
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("failed to read the table: {0}")]
+    Block(#[from] super::Error),
+
+    #[error("disk has no GUID partition table")]
+    NoTable,
+
+    #[error("{0} checksum mismatch")]
+    Checksum(&'static str),
+
+    #[error("table is malformed: {0}")]
+    Malformed(&'static str),
+}
+
+/// Type of the EFI system partition, which holds the bootloader (and the kernel it loaded).
+pub const EFI_SYSTEM_PARTITION: Uuid = Uuid::from_u128(0xC12A7328_F81F_11D2_BA4B_00A0C93EC93B);
+
+const SIGNATURE: &[u8; 8] = b"EFI PART";
+/// Sector holding the primary table's header.
+const HEADER_SECTOR: u64 = 1;
+const MIN_HEADER_SIZE: usize = 92;
+const MIN_ENTRY_SIZE: usize = 128;
+/// Largest entry array read; the specification requires room for only 128 entries, which is what every tool writes.
+const MAX_ENTRIES_LEN: usize = 0x40000;
+/// Length of an entry's name, in UTF-16 code units.
+const NAME_LEN: usize = 36;
+
+/// CRC32 (as used by Ethernet, zlib, and UEFI) of `bytes`.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(u32::MAX, |crc, byte| {
+        (0..8).fold(crc ^ u32::from(*byte), |crc, _| {
+            (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(*bytes[offset..].first_chunk().unwrap())
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*bytes[offset..].first_chunk().unwrap())
+}
+
+/// GUIDs are stored with their first three fields little-endian.
+fn guid_at(bytes: &[u8], offset: usize) -> Uuid {
+    Uuid::from_bytes_le(*bytes[offset..].first_chunk().unwrap())
+}
+
+/// Fields of a table's header which locate its entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Header {
+    disk_guid: Uuid,
+    entries_sector: u64,
+    entry_count: usize,
+    entry_size: usize,
+    entries_crc: u32,
+}
+
+impl Header {
+    fn parse(sector: &[u8]) -> Result<Self, Error> {
+        if !sector.starts_with(SIGNATURE) {
+            return Err(Error::NoTable);
+        }
+
+        let size = usize::try_from(u32_at(sector, 12)).unwrap();
+        if !(MIN_HEADER_SIZE..=sector.len()).contains(&size) {
+            return Err(Error::Malformed("header size is out of range"));
+        }
+
+        // The header's checksum covers the header with the checksum itself zeroed.
+        let mut header = sector[..size].to_vec();
+        header[16..20].fill(0);
+        if crc32(&header) != u32_at(sector, 16) {
+            return Err(Error::Checksum("header"));
+        }
+
+        let entry_count = usize::try_from(u32_at(sector, 80)).unwrap();
+        let entry_size = usize::try_from(u32_at(sector, 84)).unwrap();
+        if entry_size < MIN_ENTRY_SIZE || !entry_size.is_power_of_two() {
+            return Err(Error::Malformed("entry size is invalid"));
+        }
+        if entry_count.saturating_mul(entry_size) > MAX_ENTRIES_LEN {
+            return Err(Error::Malformed("entry array is too large"));
+        }
+
+        Ok(Self {
+            disk_guid: guid_at(sector, 56),
+            entries_sector: u64_at(sector, 72),
+            entry_count,
+            entry_size,
+            entries_crc: u32_at(sector, 88),
+        })
+    }
+}
+
+/// A used entry of a partition table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub type_guid: Uuid,
+    pub guid: Uuid,
+    pub first_sector: u64,
+    /// Last sector of the partition, inclusive.
+    pub last_sector: u64,
+    pub attributes: u64,
+    pub name: String,
+}
+
+impl Entry {
+    /// Entry held in `bytes`, or `None` if it's unused.
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let type_guid = guid_at(bytes, 0);
+        if type_guid.is_nil() {
+            return None;
+        }
+
+        let name = (0..NAME_LEN)
+            .map(|index| u16::from_le_bytes(*bytes[56 + (index * 2)..].first_chunk().unwrap()))
+            .take_while(|unit| *unit != 0);
+
+        Some(Self {
+            type_guid,
+            guid: guid_at(bytes, 16),
+            first_sector: u64_at(bytes, 32),
+            last_sector: u64_at(bytes, 40),
+            attributes: u64_at(bytes, 48),
+            name: char::decode_utf16(name)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect(),
+        })
+    }
+
+    /// Number of sectors the partition spans.
+    pub fn sector_count(&self) -> u64 {
+        (self.last_sector - self.first_sector) + 1
+    }
+}
+
+/// Reads the primary partition table of `disk`, returning its used entries with their indices (from 1).
+pub fn read_table(disk: &dyn BlockDevice) -> Result<Vec<(usize, Entry)>, Error> {
+    let mut sector = vec![0u8; SECTOR_SIZE];
+    disk.read(HEADER_SECTOR, &mut sector)?;
+    let header = Header::parse(&sector)?;
+
+    let entries_len = header.entry_count * header.entry_size;
+    let mut entries = vec![0u8; entries_len.next_multiple_of(SECTOR_SIZE)];
+    disk.read(header.entries_sector, &mut entries)?;
+    if crc32(&entries[..entries_len]) != header.entries_crc {
+        return Err(Error::Checksum("entry array"));
+    }
+
+    trace!(
+        "Disk {} has partition table {}.",
+        disk.name(),
+        header.disk_guid
+    );
+
+    Ok(entries[..entries_len]
+        .chunks_exact(header.entry_size)
+        .enumerate()
+        .filter_map(|(index, bytes)| Some((index + 1, Entry::parse(bytes)?)))
+        .filter(|(index, entry)| {
+            let in_range =
+                entry.first_sector <= entry.last_sector && entry.last_sector < disk.sector_count();
+            if !in_range {
+                warn!(
+                    "Partition {index} of {} lies outside the disk.",
+                    disk.name()
+                );
+            }
+
+            in_range
+        })
+        .collect())
+}
+
+/// A partition of a disk, which transfers the sectors it spans.
+pub struct Partition {
+    name: String,
+    disk: Arc<dyn BlockDevice>,
+    entry: Entry,
+}
+
+impl Partition {
+    pub fn disk(&self) -> &Arc<dyn BlockDevice> {
+        &self.disk
+    }
+
+    pub fn entry(&self) -> &Entry {
+        &self.entry
+    }
+
+    pub fn is_efi_system(&self) -> bool {
+        self.entry.type_guid == EFI_SYSTEM_PARTITION
+    }
+}
+
+impl BlockDevice for Partition {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn sector_count(&self) -> u64 {
+        self.entry.sector_count()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.disk.is_read_only()
+    }
+
+    fn read(&self, sector: u64, buf: &mut [u8]) -> Result<(), super::Error> {
+        super::check_range(self.sector_count(), sector, buf.len())?;
+        self.disk.read(self.entry.first_sector + sector, buf)
+    }
+
+    fn write(&self, sector: u64, buf: &[u8]) -> Result<(), super::Error> {
+        super::check_range(self.sector_count(), sector, buf.len())?;
+        self.disk.write(self.entry.first_sector + sector, buf)
+    }
+
+    fn submit(&self, mut request: Request) -> Result<(), Request> {
+        if let Err(error) =
+            super::check_range(self.sector_count(), request.sector, request.buf.len())
+        {
+            request.complete(Err(error));
+            return Ok(());
+        }
+
+        request.sector += self.entry.first_sector;
+        self.disk.submit(request).map_err(|mut request| {
+            request.sector -= self.entry.first_sector;
+            request
+        })
+    }
+
+    fn max_request_sectors(&self) -> u64 {
+        self.disk.max_request_sectors()
+    }
+
+    fn poll(&self) {
+        self.disk.poll();
+    }
+}
+
+static PARTITIONS: SpinMutex<Vec<Arc<Partition>>> = SpinMutex::new(Vec::new());
+
+/// Reads the partition table of every registered disk, and registers its partitions.
+pub fn scan() {
+    for disk in super::devices() {
+        let entries = match read_table(disk.as_ref()) {
+            Ok(entries) => entries,
+            Err(Error::NoTable) => continue,
+            Err(error) => {
+                warn!(
+                    "Failed to read the partition table of {}: {error}",
+                    disk.name()
+                );
+                continue;
+            }
+        };
+
+        for (index, entry) in entries {
+            let partition = Arc::new(Partition {
+                name: format!("{}p{index}", disk.name()),
+                disk: disk.clone(),
+                entry,
+            });
+
+            debug!(
+                "Partition {} is {:?} ({}).",
+                partition.name, partition.entry.name, partition.entry.type_guid
+            );
+
+            if super::register(partition.clone()) {
+                crate::interrupts::uninterruptable(|| PARTITIONS.lock().push(partition));
+            }
+        }
+    }
+}
+
+/// Every partition registered by [`scan`].
+pub fn partitions() -> Vec<Arc<Partition>> {
+    crate::interrupts::uninterruptable(|| PARTITIONS.lock().clone())
+}
+
+crate::kernel_test! {
+    /// Table headers & entries are decoded, and checked against their checksums.
+    fn parses_partition_tables() -> crate::tests::Outcome {
+        if crc32(b"123456789") != 0xCBF4_3926 {
+            return Err("CRC32 was computed incorrectly");
+        }
+
+        let mut entry = [0u8; MIN_ENTRY_SIZE];
+        entry[..16].copy_from_slice(&EFI_SYSTEM_PARTITION.to_bytes_le());
+        entry[32..40].copy_from_slice(&2048u64.to_le_bytes());
+        entry[40..48].copy_from_slice(&4095u64.to_le_bytes());
+        for (index, c) in "EFI".encode_utf16().enumerate() {
+            entry[56 + (index * 2)..][..2].copy_from_slice(&c.to_le_bytes());
+        }
+
+        let mut sector = [0u8; SECTOR_SIZE];
+        sector[..8].copy_from_slice(SIGNATURE);
+        sector[12..16].copy_from_slice(&92u32.to_le_bytes());
+        sector[72..80].copy_from_slice(&2u64.to_le_bytes());
+        sector[80..84].copy_from_slice(&1u32.to_le_bytes());
+        sector[84..88].copy_from_slice(&128u32.to_le_bytes());
+        sector[88..92].copy_from_slice(&crc32(&entry).to_le_bytes());
+        let checksum = crc32(&sector[..92]);
+        sector[16..20].copy_from_slice(&checksum.to_le_bytes());
+
+        let header = Header::parse(&sector).map_err(|_| "valid header was rejected")?;
+        if header.entries_sector != 2 || header.entry_count != 1 || header.entry_size != 128 {
+            return Err("header was decoded incorrectly");
+        }
+
+        sector[80] = 2;
+        if Header::parse(&sector) != Err(Error::Checksum("header")) {
+            return Err("corrupt header was accepted");
+        }
+
+        match Entry::parse(&entry) {
+            Some(entry)
+                if entry.type_guid == EFI_SYSTEM_PARTITION
+                    && entry.sector_count() == 2048
+                    && entry.name == "EFI" => {}
+            _ => return Err("entry was decoded incorrectly"),
+        }
+
+        if Entry::parse(&[0u8; MIN_ENTRY_SIZE]).is_some() {
+            return Err("unused entry was decoded");
+        }
+
+        Ok(())
+    }
+}
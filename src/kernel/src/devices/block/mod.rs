@@ -0,0 +1,226 @@
+//! Block devices: storage addressed in fixed-size sectors, which filesystems are layered over.
+//!
+//! Drivers [`register`] each device they bring up under a unique name, after which it can be found by that name (see
+//! [`find`]), or listed among every other (see [`devices`]). Partitions of disks with a GUID partition table are
+//! registered as devices of their own (see [`gpt`]).
+//!
+//! Every registered device is fronted by a [`RequestQueue`], through which filesystems transfer: it merges contiguous
+//! requests, and [`submit`](BlockDevice::submit)s them to the device without waiting, keeping several in flight.
+//! Devices complete requests as they finish, typically from their interrupt handlers; those which can't transfer
+//! asynchronously complete each request synchronously as it's submitted.
+
+pub mod gpt;
+pub mod queue;
+
+pub use queue::RequestQueue;
+
+use crate::sync::SpinMutex;
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+
+/// Size of a sector, which every transfer is a whole number of.
+pub const SECTOR_SIZE: usize = 512;
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("buffer length {0} is not a whole number of sectors")]
+    Misaligned(usize),
+
+    #[error("{count} sectors from sector {sector} lie beyond the end of the device")]
+    OutOfRange { sector: u64, count: u64 },
+
+    #[error("device is read-only")]
+    ReadOnly,
+
+    #[error("device failed the request")]
+    Io,
+}
+
+/// A device which transfers whole sectors.
+pub trait BlockDevice: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Number of sectors the device holds.
+    fn sector_count(&self) -> u64;
+
+    fn is_read_only(&self) -> bool;
+
+    /// Reads the sectors from `sector` into `buf`, which must be a whole number of sectors long.
+    fn read(&self, sector: u64, buf: &mut [u8]) -> Result<(), Error>;
+
+    /// Writes `buf`, which must be a whole number of sectors long, to the sectors from `sector`.
+    fn write(&self, sector: u64, buf: &[u8]) -> Result<(), Error>;
+
+    /// Starts `request` without waiting for it, returning it if the device can't take another request yet. Once the
+    /// request is transferred, it's [completed](Request::complete), perhaps from the device's interrupt handler.
+    ///
+    /// By default, the request is transferred synchronously, and completed before this returns.
+    fn submit(&self, request: Request) -> Result<(), Request> {
+        transfer(self, request);
+
+        Ok(())
+    }
+
+    /// Most sectors a request [submitted](Self::submit) to the device may span.
+    fn max_request_sectors(&self) -> u64 {
+        u64::MAX
+    }
+
+    /// Completes any submitted requests the device has finished, for waiters which can't rely on its interrupt.
+    fn poll(&self) {}
+}
+
+/// Direction of a [`Request`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Read,
+    Write,
+}
+
+type Completion = Box<dyn FnOnce(Result<(), Error>, Vec<u8>) + Send>;
+
+/// A transfer submitted to a device, which owns its buffer until it's completed.
+pub struct Request {
+    pub direction: Direction,
+    pub sector: u64,
+    /// Data to write, or the buffer to read into, which is a whole number of sectors long.
+    pub buf: Vec<u8>,
+    on_complete: Completion,
+}
+
+impl Request {
+    /// Request to transfer `buf` from `sector`, which calls `on_complete` with its result and buffer once completed.
+    pub fn new(
+        direction: Direction,
+        sector: u64,
+        buf: Vec<u8>,
+        on_complete: impl FnOnce(Result<(), Error>, Vec<u8>) + Send + 'static,
+    ) -> Self {
+        Self {
+            direction,
+            sector,
+            buf,
+            on_complete: Box::new(on_complete),
+        }
+    }
+
+    /// Number of sectors the request spans, rounded up.
+    pub fn sector_count(&self) -> u64 {
+        u64::try_from(self.buf.len().div_ceil(SECTOR_SIZE)).unwrap()
+    }
+
+    /// Sector following the last the request spans.
+    pub fn end(&self) -> u64 {
+        self.sector + self.sector_count()
+    }
+
+    /// Completes the request with `result`, handing its buffer back to its submitter.
+    ///
+    /// # Remarks
+    ///
+    /// Requests may be completed in interrupt context, so their completions mustn't block.
+    pub fn complete(self, result: Result<(), Error>) {
+        (self.on_complete)(result, self.buf);
+    }
+}
+
+/// Transfers `request` through `device` synchronously, and completes it.
+pub fn transfer<D: BlockDevice + ?Sized>(device: &D, mut request: Request) {
+    let result = match request.direction {
+        Direction::Read => device.read(request.sector, &mut request.buf),
+        Direction::Write => device.write(request.sector, &request.buf),
+    };
+
+    request.complete(result);
+}
+
+/// Number of sectors in a transfer of `len` bytes from `sector`, if it's a whole number of sectors that lie within a
+/// device of `sector_count` sectors.
+pub fn check_range(sector_count: u64, sector: u64, len: usize) -> Result<u64, Error> {
+    if len % SECTOR_SIZE != 0 {
+        return Err(Error::Misaligned(len));
+    }
+
+    let count = u64::try_from(len / SECTOR_SIZE).unwrap();
+    match sector.checked_add(count) {
+        Some(end) if end <= sector_count => Ok(count),
+        _ => Err(Error::OutOfRange { sector, count }),
+    }
+}
+
+static QUEUES: SpinMutex<Vec<Arc<RequestQueue>>> = SpinMutex::new(Vec::new());
+
+/// Registers `device`, behind a request queue of its own, returning `false` if a device of the same name is already
+/// registered.
+pub fn register(device: Arc<dyn BlockDevice>) -> bool {
+    crate::interrupts::uninterruptable(|| {
+        let mut queues = QUEUES.lock();
+        if queues
+            .iter()
+            .any(|queue| queue.device().name() == device.name())
+        {
+            return false;
+        }
+
+        info!(
+            "Registered block device {} ({} sectors{}).",
+            device.name(),
+            device.sector_count(),
+            if device.is_read_only() {
+                ", read-only"
+            } else {
+                ""
+            }
+        );
+        queues.push(Arc::new(RequestQueue::new(device)));
+
+        true
+    })
+}
+
+/// Request queue of every registered block device, in order of registration.
+pub fn queues() -> Vec<Arc<RequestQueue>> {
+    crate::interrupts::uninterruptable(|| QUEUES.lock().clone())
+}
+
+/// Request queue of the registered block device named `name`.
+pub fn queue(name: &str) -> Option<Arc<RequestQueue>> {
+    crate::interrupts::uninterruptable(|| {
+        QUEUES
+            .lock()
+            .iter()
+            .find(|queue| queue.device().name() == name)
+            .cloned()
+    })
+}
+
+/// Every registered block device, in order of registration.
+pub fn devices() -> Vec<Arc<dyn BlockDevice>> {
+    queues()
+        .into_iter()
+        .map(|queue| queue.device().clone())
+        .collect()
+}
+
+/// Registered block device named `name`.
+pub fn find(name: &str) -> Option<Arc<dyn BlockDevice>> {
+    queue(name).map(|queue| queue.device().clone())
+}
+
+crate::kernel_test! {
+    /// Transfers must be whole sectors, and lie entirely within the device.
+    fn checks_block_ranges() -> crate::tests::Outcome {
+        if check_range(16, 8, SECTOR_SIZE * 8) != Ok(8) {
+            return Err("transfer ending at the last sector was refused");
+        }
+
+        if check_range(16, 0, SECTOR_SIZE + 1) != Err(Error::Misaligned(SECTOR_SIZE + 1)) {
+            return Err("partial sector was accepted");
+        }
+
+        if check_range(16, 15, SECTOR_SIZE * 2).is_ok() || check_range(16, u64::MAX, SECTOR_SIZE).is_ok() {
+            return Err("transfer beyond the end of the device was accepted");
+        }
+
+        Ok(())
+    }
+}
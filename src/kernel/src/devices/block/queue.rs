@@ -0,0 +1,384 @@
+//! Request queues, which stand between filesystems and the block devices they're layered over.
+//!
+//! Requests are queued as they're submitted, and dispatched to the device in order of submission, with up to
+//! [`QUEUE_DEPTH`] in flight at once. As a request is dispatched, any queued requests in the same direction which
+//! continue it (or which it continues) are merged into it, up to [`MAX_MERGE_SECTORS`] (or the device's own limit), so
+//! they're transferred as one. Each completion dispatches whatever has queued behind it, and a request the device
+//! turns away (as it's busy) is retried first.
+//!
+//! Submitters are called back as their requests complete, which may be from the device's interrupt handler; callers
+//! which can block use [`RequestQueue::read`] & [`RequestQueue::write`] instead, which wait for completion.
+
+use super::{BlockDevice, Direction, Error, Request, SECTOR_SIZE};
+use crate::{interrupts::InterruptCell, sync::SpinMutex, time::SystemClock};
+use alloc::{collections::VecDeque, sync::Arc, vec, vec::Vec};
+
+/// Requests in flight to a device at once.
+const QUEUE_DEPTH: usize = 8;
+/// Most sectors requests are merged up to.
+const MAX_MERGE_SECTORS: u64 = 256;
+
+/// Statistics of the requests submitted to a queue.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Statistics {
+    /// Requests completed successfully in each direction, and the sectors they spanned.
+    pub reads: u64,
+    pub writes: u64,
+    pub sectors_read: u64,
+    pub sectors_written: u64,
+    /// Requests merged into another as they were dispatched.
+    pub merges: u64,
+    /// Requests the device failed.
+    pub errors: u64,
+    /// Time requests spent between submission & completion, in total.
+    pub latency_ns: u64,
+    /// Requests queued but not yet dispatched.
+    pub queued: usize,
+    /// Requests (after merging) dispatched but not yet completed.
+    pub in_flight: usize,
+}
+
+/// A submitted request, and when it was submitted.
+struct Queued {
+    request: Request,
+    submitted_ns: u64,
+}
+
+#[derive(Default)]
+struct State {
+    pending: VecDeque<Queued>,
+    /// Merged request the device turned away, which is dispatched before any other.
+    rejected: Option<Request>,
+    in_flight: usize,
+    /// Whether a request is being dispatched, so completions arriving meanwhile leave dispatching to it.
+    dispatching: bool,
+    statistics: Statistics,
+}
+
+impl State {
+    /// Takes the oldest pending request, with every pending request merged with it, in order of sector.
+    fn take_next(&mut self, max_sectors: u64) -> Option<Vec<Queued>> {
+        let first = self.pending.pop_front()?;
+        let direction = first.request.direction;
+        let (mut start, mut end) = (first.request.sector, first.request.end());
+
+        // Partial sectors are left for the device to refuse.
+        let mut parts = VecDeque::from([first]);
+        if parts[0].request.buf.len() % SECTOR_SIZE != 0 {
+            return Some(parts.into());
+        }
+
+        while let Some(index) = self.pending.iter().position(|queued| {
+            let request = &queued.request;
+
+            request.direction == direction
+                && !request.buf.is_empty()
+                && (request.buf.len() % SECTOR_SIZE) == 0
+                && (request.sector == end || request.end() == start)
+                && ((end - start) + request.sector_count()) <= max_sectors
+        }) {
+            let queued = self.pending.remove(index).unwrap();
+            if queued.request.sector == end {
+                end = queued.request.end();
+                parts.push_back(queued);
+            } else {
+                start = queued.request.sector;
+                parts.push_front(queued);
+            }
+        }
+
+        Some(parts.into())
+    }
+}
+
+/// A block device, and the requests queued for it.
+pub struct RequestQueue {
+    device: Arc<dyn BlockDevice>,
+    state: InterruptCell<SpinMutex<State>>,
+}
+
+impl RequestQueue {
+    pub fn new(device: Arc<dyn BlockDevice>) -> Self {
+        Self {
+            device,
+            state: InterruptCell::new(SpinMutex::new(State::default())),
+        }
+    }
+
+    pub fn device(&self) -> &Arc<dyn BlockDevice> {
+        &self.device
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        self.state.with(|state| {
+            let state = state.lock();
+
+            Statistics {
+                queued: state.pending.len(),
+                in_flight: state.in_flight,
+                ..state.statistics
+            }
+        })
+    }
+
+    /// Queues `request`, which is completed once the device has transferred it.
+    pub fn submit(self: &Arc<Self>, request: Request) {
+        let queued = Queued {
+            request,
+            submitted_ns: SystemClock::now_ns(),
+        };
+
+        self.state
+            .with(|state| state.lock().pending.push_back(queued));
+        self.dispatch();
+    }
+
+    /// Completes any requests the device has finished, and dispatches whatever is queued.
+    pub fn poll(self: &Arc<Self>) {
+        self.device.poll();
+        self.dispatch();
+    }
+
+    /// Reads the sectors from `sector` into `buf`, waiting for them.
+    pub fn read(self: &Arc<Self>, sector: u64, buf: &mut [u8]) -> Result<(), Error> {
+        let data = self.wait(Direction::Read, sector, vec![0; buf.len()])?;
+        buf.copy_from_slice(&data);
+
+        Ok(())
+    }
+
+    /// Writes `buf` to the sectors from `sector`, waiting for them.
+    pub fn write(self: &Arc<Self>, sector: u64, buf: &[u8]) -> Result<(), Error> {
+        self.wait(Direction::Write, sector, buf.to_vec()).map(drop)
+    }
+
+    /// Submits a request, and waits for it to complete, returning its buffer.
+    fn wait(
+        self: &Arc<Self>,
+        direction: Direction,
+        sector: u64,
+        buf: Vec<u8>,
+    ) -> Result<Vec<u8>, Error> {
+        let done = Arc::new(SpinMutex::new(None));
+        let completed = done.clone();
+        self.submit(Request::new(direction, sector, buf, move |result, buf| {
+            *completed.lock() = Some((result, buf));
+        }));
+
+        loop {
+            // The lock is taken uninterruptably, as the request may be completed by an interrupt on this thread.
+            if let Some((result, buf)) = crate::interrupts::uninterruptable(|| done.lock().take()) {
+                return result.map(|()| buf);
+            }
+
+            self.poll();
+
+            if crate::interrupts::is_enabled() {
+                // A completion wakes the hardware thread it's routed to; any other is woken by its next timer tick.
+                crate::interrupts::wait_next();
+            } else {
+                core::hint::spin_loop();
+            }
+        }
+    }
+
+    /// Dispatches queued requests to the device, until it's been given as many as it may have in flight.
+    fn dispatch(self: &Arc<Self>) {
+        let max_sectors = self.device.max_request_sectors().min(MAX_MERGE_SECTORS);
+
+        loop {
+            let request = self.state.with(|state| {
+                let mut state = state.lock();
+                if state.dispatching || state.in_flight >= QUEUE_DEPTH {
+                    return None;
+                }
+
+                let request = match state.rejected.take() {
+                    Some(request) => request,
+                    None => {
+                        let parts = state.take_next(max_sectors)?;
+                        state.statistics.merges += u64::try_from(parts.len() - 1).unwrap();
+
+                        self.merge(parts)
+                    }
+                };
+
+                state.in_flight += 1;
+                state.dispatching = true;
+
+                Some(request)
+            });
+
+            let Some(request) = request else {
+                return;
+            };
+
+            // Devices without asynchronous transfers complete the request within this call, dispatching nothing.
+            let rejected = self.device.submit(request).err();
+
+            let is_busy = self.state.with(|state| {
+                let mut state = state.lock();
+                state.dispatching = false;
+
+                // The request is retried as the next request completes, or as the queue is polled.
+                rejected.is_some_and(|request| {
+                    state.in_flight -= 1;
+                    state.rejected = Some(request);
+
+                    true
+                })
+            });
+
+            if is_busy {
+                return;
+            }
+        }
+    }
+
+    /// Merges `parts`, which are contiguous & in order of sector, into a single request, which completes each of them.
+    fn merge(self: &Arc<Self>, mut parts: Vec<Queued>) -> Request {
+        let direction = parts[0].request.direction;
+        let sector = parts[0].request.sector;
+
+        let buf = if let [part] = parts.as_mut_slice() {
+            core::mem::take(&mut part.request.buf)
+        } else {
+            let len = parts.iter().map(|part| part.request.buf.len()).sum();
+            match direction {
+                Direction::Read => vec![0; len],
+                Direction::Write => parts
+                    .iter()
+                    .flat_map(|part| part.request.buf.iter().copied())
+                    .collect(),
+            }
+        };
+
+        let queue = self.clone();
+        Request::new(direction, sector, buf, move |result, buf| {
+            queue.complete(parts, result, buf);
+        })
+    }
+
+    /// Completes each of the `parts` of a merged request with `result`, having split `buf` between them.
+    fn complete(self: &Arc<Self>, mut parts: Vec<Queued>, result: Result<(), Error>, buf: Vec<u8>) {
+        if let [part] = parts.as_mut_slice() {
+            part.request.buf = buf;
+        } else if parts[0].request.direction == Direction::Read && result.is_ok() {
+            let mut remaining = buf.as_slice();
+            for part in &mut parts {
+                let (data, rest) = remaining.split_at(part.request.buf.len());
+                part.request.buf.copy_from_slice(data);
+                remaining = rest;
+            }
+        }
+
+        let now_ns = SystemClock::now_ns();
+        self.state.with(|state| {
+            let mut state = state.lock();
+            state.in_flight -= 1;
+
+            let statistics = &mut state.statistics;
+            for part in &parts {
+                statistics.latency_ns += now_ns.saturating_sub(part.submitted_ns);
+
+                match (result, part.request.direction) {
+                    (Err(_), _) => statistics.errors += 1,
+                    (Ok(()), Direction::Read) => {
+                        statistics.reads += 1;
+                        statistics.sectors_read += part.request.sector_count();
+                    }
+                    (Ok(()), Direction::Write) => {
+                        statistics.writes += 1;
+                        statistics.sectors_written += part.request.sector_count();
+                    }
+                }
+            }
+        });
+
+        self.dispatch();
+
+        for part in parts {
+            part.request.complete(result);
+        }
+    }
+}
+
+crate::kernel_test! {
+    /// Contiguous requests in the same direction are merged in order of sector, and transfer through the queue.
+    fn merges_block_requests() -> crate::tests::Outcome {
+        struct RamDisk(SpinMutex<Vec<u8>>);
+
+        impl BlockDevice for RamDisk {
+            fn name(&self) -> &str {
+                "ram"
+            }
+
+            fn sector_count(&self) -> u64 {
+                u64::try_from(self.0.lock().len() / SECTOR_SIZE).unwrap()
+            }
+
+            fn is_read_only(&self) -> bool {
+                false
+            }
+
+            fn read(&self, sector: u64, buf: &mut [u8]) -> Result<(), Error> {
+                super::check_range(self.sector_count(), sector, buf.len())?;
+                let start = usize::try_from(sector).unwrap() * SECTOR_SIZE;
+                buf.copy_from_slice(&self.0.lock()[start..(start + buf.len())]);
+
+                Ok(())
+            }
+
+            fn write(&self, sector: u64, buf: &[u8]) -> Result<(), Error> {
+                super::check_range(self.sector_count(), sector, buf.len())?;
+                let start = usize::try_from(sector).unwrap() * SECTOR_SIZE;
+                self.0.lock()[start..(start + buf.len())].copy_from_slice(buf);
+
+                Ok(())
+            }
+        }
+
+        let queued = |direction, sector, sectors| Queued {
+            request: Request::new(direction, sector, vec![0; sectors * SECTOR_SIZE], |_, _| {}),
+            submitted_ns: 0,
+        };
+
+        let mut state = State::default();
+        state.pending.extend([
+            queued(Direction::Write, 8, 8),
+            queued(Direction::Read, 16, 8),
+            queued(Direction::Write, 0, 8),
+            queued(Direction::Write, 16, 4),
+        ]);
+
+        let sectors = |parts: Vec<Queued>| parts.iter().map(|part| part.request.sector).collect::<Vec<_>>();
+        if state.take_next(MAX_MERGE_SECTORS).map(sectors) != Some(vec![0, 8, 16]) {
+            return Err("contiguous writes weren't merged in order of sector");
+        }
+        if state.take_next(MAX_MERGE_SECTORS).map(sectors) != Some(vec![16]) || !state.pending.is_empty() {
+            return Err("read was merged with writes");
+        }
+
+        state.pending.extend([queued(Direction::Read, 0, 8), queued(Direction::Read, 8, 8)]);
+        if state.take_next(8).map(sectors) != Some(vec![0]) {
+            return Err("requests were merged beyond the limit");
+        }
+
+        let queue = Arc::new(RequestQueue::new(Arc::new(RamDisk(SpinMutex::new(vec![0; 64 * SECTOR_SIZE])))));
+        let data = (0..(2 * SECTOR_SIZE)).map(|index| u8::try_from(index % 251).unwrap()).collect::<Vec<_>>();
+        queue.write(3, &data).map_err(|_| "write failed")?;
+
+        let mut buf = vec![0; data.len()];
+        queue.read(3, &mut buf).map_err(|_| "read failed")?;
+        if buf != data {
+            return Err("data read back differs from that written");
+        }
+
+        let statistics = queue.statistics();
+        if (statistics.reads, statistics.writes, statistics.sectors_read, statistics.in_flight) != (1, 1, 2, 0) {
+            return Err("statistics were counted incorrectly");
+        }
+
+        Ok(())
+    }
+}
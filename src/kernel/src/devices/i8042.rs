@@ -0,0 +1,375 @@
+//! Intel 8042 (PS/2) controller and keyboard driver.
+//!
+//! The controller is self-tested, and its first port enabled with scancode translation on, so the keyboard's
+//! scancodes arrive in set 1 whatever set it uses natively. The keyboard's IRQ (ISA IRQ 1) is routed through the I/O
+//! APIC; its handler decodes each scancode into a [`KeyEvent`], tracking modifiers, and queues it in
+//! [`crate::devices::input`]. The second (mouse) port is left disabled.
+//!
+//! While interrupts can't be delivered (i.e. from within the debug shell), the controller is drained by [`poll`].
+
+use crate::{
+    devices::input::{self, KeyEvent, Modifiers},
+    interrupts::{InterruptCell, irq::IrqTimestamp},
+    sync::SpinMutex,
+};
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use ioports::{ReadOnlyPort, WriteOnlyPort};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("controller timed out {0}")]
+    Timeout(&'static str),
+
+    #[error("controller self-test failed with {0:#04X}")]
+    SelfTest(u8),
+
+    #[error("keyboard port test failed with {0:#04X}")]
+    PortTest(u8),
+
+    #[error("keyboard didn't acknowledge enabling scanning ({0:#04X})")]
+    Scanning(u8),
+
+    #[error("failed to route the keyboard IRQ: {0}")]
+    Irq(#[from] crate::interrupts::irq::Error),
+}
+
+const DATA_PORT: u16 = 0x60;
+/// Status register when read, command register when written.
+const STATUS_PORT: u16 = 0x64;
+const COMMAND_PORT: u16 = 0x64;
+
+/// ISA IRQ raised by the first port.
+const KEYBOARD_IRQ: u8 = 1;
+
+/// Status: the output buffer holds a byte for the host.
+const STATUS_OUTPUT_FULL: u8 = 1 << 0;
+/// Status: the input buffer holds a byte not yet taken by the controller.
+const STATUS_INPUT_FULL: u8 = 1 << 1;
+/// Status: the output buffer's byte came from the second port.
+const STATUS_AUX_DATA: u8 = 1 << 5;
+
+/// Controller commands.
+const COMMAND_READ_CONFIG: u8 = 0x20;
+const COMMAND_WRITE_CONFIG: u8 = 0x60;
+const COMMAND_DISABLE_AUX: u8 = 0xA7;
+const COMMAND_SELF_TEST: u8 = 0xAA;
+const COMMAND_TEST_PORT: u8 = 0xAB;
+const COMMAND_DISABLE_PORT: u8 = 0xAD;
+const COMMAND_ENABLE_PORT: u8 = 0xAE;
+
+const SELF_TEST_PASSED: u8 = 0x55;
+const PORT_TEST_PASSED: u8 = 0x00;
+
+/// Configuration: the first & second ports' interrupts, and scancode translation.
+const CONFIG_PORT_IRQ: u8 = 1 << 0;
+const CONFIG_AUX_IRQ: u8 = 1 << 1;
+const CONFIG_TRANSLATION: u8 = 1 << 6;
+
+/// Keyboard commands, and its acknowledgement.
+const KEYBOARD_ENABLE_SCANNING: u8 = 0xF4;
+const KEYBOARD_ACK: u8 = 0xFA;
+
+/// Longest the controller may take to accept or produce a byte.
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Most bytes discarded when flushing the output buffer, which is far deeper than any controller's.
+const FLUSH_LIMIT: usize = 32;
+
+/// Scancode prefix of extended keys.
+const PREFIX_EXTENDED: u8 = 0xE0;
+/// Scancode bit marking a key's release.
+const RELEASE: u8 = 0x80;
+
+/// Scancodes (set 1) of the modifier keys.
+const SCANCODE_LEFT_SHIFT: u16 = 0x2A;
+const SCANCODE_RIGHT_SHIFT: u16 = 0x36;
+const SCANCODE_CONTROL: u8 = 0x1D;
+const SCANCODE_ALT: u8 = 0x38;
+const SCANCODE_CAPS_LOCK: u16 = 0x3A;
+
+/// Characters of the (unextended) set 1 scancodes, on a US layout, without and with shift.
+const UNSHIFTED: &[u8; 0x3A] =
+    b"\0\x1B1234567890-=\x08\tqwertyuiop[]\n\0asdfghjkl;'`\0\\zxcvbnm,./\0*\0 ";
+const SHIFTED: &[u8; 0x3A] =
+    b"\0\x1B!@#$%^&*()_+\x08\tQWERTYUIOP{}\n\0ASDFGHJKL:\"~\0|ZXCVBNM<>?\0*\0 ";
+
+/// Decodes set 1 scancodes into key events, tracking the held modifiers.
+#[derive(Debug, Default)]
+struct Decoder {
+    extended: bool,
+    modifiers: Modifiers,
+}
+
+impl Decoder {
+    /// Feeds `byte` to the decoder, returning the event it completes, if any.
+    fn decode(&mut self, byte: u8) -> Option<KeyEvent> {
+        if byte == PREFIX_EXTENDED {
+            self.extended = true;
+            return None;
+        }
+
+        let pressed = (byte & RELEASE) == 0;
+        let code = byte & !RELEASE;
+        let scancode = if core::mem::take(&mut self.extended) {
+            (u16::from(PREFIX_EXTENDED) << 8) | u16::from(code)
+        } else {
+            u16::from(code)
+        };
+
+        // Control & alt have right-hand (extended) counterparts, which are treated alike.
+        match scancode {
+            SCANCODE_LEFT_SHIFT | SCANCODE_RIGHT_SHIFT => {
+                self.modifiers.set(Modifiers::SHIFT, pressed)
+            }
+            _ if code == SCANCODE_CONTROL => self.modifiers.set(Modifiers::CONTROL, pressed),
+            _ if code == SCANCODE_ALT => self.modifiers.set(Modifiers::ALT, pressed),
+            SCANCODE_CAPS_LOCK if pressed => self.modifiers.toggle(Modifiers::CAPS_LOCK),
+            _ => {}
+        }
+
+        Some(KeyEvent {
+            scancode,
+            pressed,
+            modifiers: self.modifiers,
+            ascii: u8::try_from(scancode)
+                .ok()
+                .and_then(|code| self.ascii(code)),
+        })
+    }
+
+    /// Character the unextended scancode `code` produces under the held modifiers.
+    fn ascii(&self, code: u8) -> Option<u8> {
+        let table = if self.modifiers.contains(Modifiers::SHIFT) {
+            SHIFTED
+        } else {
+            UNSHIFTED
+        };
+        let mut ascii = *table.get(usize::from(code)).filter(|ascii| **ascii != 0)?;
+
+        if ascii.is_ascii_alphabetic() {
+            if self.modifiers.contains(Modifiers::CAPS_LOCK) {
+                ascii ^= 0x20;
+            }
+
+            // Control chords produce the C0 control characters, e.g. `Ctrl-C` is ETX.
+            if self.modifiers.contains(Modifiers::CONTROL) {
+                ascii &= 0x1F;
+            }
+        } else if self.modifiers.contains(Modifiers::CONTROL)
+            && matches!(ascii, b'[' | b'\\' | b']')
+        {
+            ascii &= 0x1F;
+        }
+
+        Some(ascii)
+    }
+}
+
+struct Controller {
+    data_read: ReadOnlyPort<u8>,
+    data_write: WriteOnlyPort<u8>,
+    status: ReadOnlyPort<u8>,
+    command: WriteOnlyPort<u8>,
+    decoder: Decoder,
+}
+
+// Safety: Ports are only accessed behind a lock.
+unsafe impl Send for Controller {}
+
+impl Controller {
+    /// Waits until the status bit `mask` is `set`.
+    fn wait(&mut self, mask: u8, set: bool, context: &'static str) -> Result<(), Error> {
+        crate::devices::poll_for(TIMEOUT, || {
+            (((self.status.read() & mask) != 0) == set).then_some(())
+        })
+        .ok_or(Error::Timeout(context))
+    }
+
+    fn send_command(&mut self, command: u8) -> Result<(), Error> {
+        self.wait(STATUS_INPUT_FULL, false, "accepting a command")?;
+        self.command.write(command);
+
+        Ok(())
+    }
+
+    fn send_data(&mut self, data: u8) -> Result<(), Error> {
+        self.wait(STATUS_INPUT_FULL, false, "accepting data")?;
+        self.data_write.write(data);
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<u8, Error> {
+        self.wait(STATUS_OUTPUT_FULL, true, "producing a response")?;
+
+        Ok(self.data_read.read())
+    }
+
+    /// Discards any bytes waiting in the output buffer.
+    fn flush(&mut self) {
+        // Bounded, as a missing controller reports its output buffer as always full.
+        for _ in 0..FLUSH_LIMIT {
+            if (self.status.read() & STATUS_OUTPUT_FULL) == 0 {
+                break;
+            }
+
+            self.data_read.read();
+        }
+    }
+
+    fn read_config(&mut self) -> Result<u8, Error> {
+        self.send_command(COMMAND_READ_CONFIG)?;
+        self.receive()
+    }
+
+    fn write_config(&mut self, config: u8) -> Result<(), Error> {
+        self.send_command(COMMAND_WRITE_CONFIG)?;
+        self.send_data(config)
+    }
+
+    /// Self-tests the controller, and enables its first port, with its interrupt, and the keyboard's scanning.
+    fn configure(&mut self) -> Result<(), Error> {
+        self.send_command(COMMAND_DISABLE_PORT)?;
+        self.send_command(COMMAND_DISABLE_AUX)?;
+        self.flush();
+
+        let config = self.read_config()? & !(CONFIG_PORT_IRQ | CONFIG_AUX_IRQ);
+        self.write_config(config | CONFIG_TRANSLATION)?;
+
+        self.send_command(COMMAND_SELF_TEST)?;
+        match self.receive()? {
+            SELF_TEST_PASSED => {}
+            response => return Err(Error::SelfTest(response)),
+        }
+        // The self-test may reset the controller's configuration.
+        self.write_config(config | CONFIG_TRANSLATION)?;
+
+        self.send_command(COMMAND_TEST_PORT)?;
+        match self.receive()? {
+            PORT_TEST_PASSED => {}
+            response => return Err(Error::PortTest(response)),
+        }
+
+        self.send_command(COMMAND_ENABLE_PORT)?;
+        self.send_data(KEYBOARD_ENABLE_SCANNING)?;
+        match self.receive()? {
+            KEYBOARD_ACK => {}
+            response => return Err(Error::Scanning(response)),
+        }
+
+        self.write_config(config | CONFIG_TRANSLATION | CONFIG_PORT_IRQ)
+    }
+
+    /// Decodes & queues every byte waiting in the output buffer.
+    fn drain(&mut self) {
+        loop {
+            let status = self.status.read();
+            if (status & STATUS_OUTPUT_FULL) == 0 {
+                break;
+            }
+
+            let byte = self.data_read.read();
+            if (status & STATUS_AUX_DATA) != 0 {
+                continue;
+            }
+
+            if let Some(event) = self.decoder.decode(byte) {
+                input::push(event);
+            }
+        }
+    }
+}
+
+static CONTROLLER: InterruptCell<SpinMutex<Option<Controller>>> =
+    InterruptCell::new(SpinMutex::new(None));
+
+/// Whether keyboard input is delivered by its interrupt.
+static IRQ_DRIVEN: AtomicBool = AtomicBool::new(false);
+
+fn on_interrupt(_: u8, _: IrqTimestamp) {
+    poll();
+}
+
+/// Decodes & queues any scancodes the keyboard has sent.
+pub fn poll() {
+    CONTROLLER.with(|controller| {
+        if let Some(controller) = controller.lock().as_mut() {
+            controller.drain();
+        }
+    });
+}
+
+/// Whether keyboard input is delivered without polling.
+pub fn is_irq_driven() -> bool {
+    IRQ_DRIVEN.load(Ordering::Acquire)
+}
+
+/// Brings up the controller & keyboard, if the platform has them, and routes the keyboard's IRQ.
+pub fn init() {
+    // Safety: The controller's ports are fixed on PC-compatible platforms; nothing else accesses them.
+    let mut controller = unsafe {
+        Controller {
+            data_read: ReadOnlyPort::new(DATA_PORT),
+            data_write: WriteOnlyPort::new(DATA_PORT),
+            status: ReadOnlyPort::new(STATUS_PORT),
+            command: WriteOnlyPort::new(COMMAND_PORT),
+            decoder: Decoder::default(),
+        }
+    };
+
+    // Platforms without a controller read back all-ones from its ports, so its self-test fails (or times out).
+    if let Err(error) = crate::interrupts::uninterruptable(|| controller.configure()) {
+        info!("PS/2 keyboard is unavailable: {error}");
+        return;
+    }
+
+    CONTROLLER.with(|slot| *slot.lock() = Some(controller));
+
+    match crate::interrupts::irq::register_isa(KEYBOARD_IRQ, on_interrupt).map_err(Error::from) {
+        Ok(vector) => {
+            IRQ_DRIVEN.store(true, Ordering::Release);
+            info!("PS/2 keyboard receiving on vector {vector:#X}.");
+        }
+
+        Err(error) => warn!("PS/2 keyboard will be polled: {error}"),
+    }
+}
+
+crate::kernel_test! {
+    /// Scancodes decode to characters under the held modifiers, and extended keys keep their prefix.
+    fn decodes_scancodes() -> crate::tests::Outcome {
+        let mut decoder = Decoder::default();
+        let mut type_bytes = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .filter_map(|byte| decoder.decode(*byte))
+                .filter(|event| event.pressed)
+                .filter_map(|event| event.ascii)
+                .collect::<alloc::vec::Vec<_>>()
+        };
+
+        // `a`, shift + `a` & `1`, then control + `]`.
+        if type_bytes(&[0x1E, 0x9E, 0x2A, 0x1E, 0x02, 0xAA, 0x1D, 0x1B, 0x9D]) != b"aA!\x1D" {
+            return Err("scancodes decoded to the wrong characters");
+        }
+
+        // Caps lock only affects letters.
+        if type_bytes(&[0x3A, 0xBA, 0x10, 0x02]) != b"Q1" {
+            return Err("caps lock was applied incorrectly");
+        }
+
+        if Decoder::default().decode(PREFIX_EXTENDED).is_some() {
+            return Err("extended prefix produced an event");
+        }
+
+        let mut decoder = Decoder::default();
+        decoder.decode(PREFIX_EXTENDED);
+        match decoder.decode(0xC8) {
+            Some(KeyEvent { scancode: 0xE048, pressed: false, ascii: None, .. }) => Ok(()),
+            _ => Err("extended key decoded incorrectly"),
+        }
+    }
+}
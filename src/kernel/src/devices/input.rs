@@ -0,0 +1,61 @@
+//! Input events, queued by input drivers (from their interrupt handlers) for the debug shell and, later, userspace.
+//!
+//! Events are held in a fixed-size ring; when its reader falls behind, new events are dropped (and counted), rather
+//! than blocking the driver in interrupt context.
+
+use crate::{interrupts::InterruptCell, sync::SpinMutex};
+use core::sync::atomic::{AtomicU64, Ordering};
+use heapless::Deque;
+
+const QUEUE_SIZE: usize = 128;
+
+bitflags! {
+    /// Modifier keys held (or locked) when a key event occurred.
+    #[repr(transparent)]
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    pub struct Modifiers: u8 {
+        const SHIFT     = 1 << 0;
+        const CONTROL   = 1 << 1;
+        const ALT       = 1 << 2;
+        const CAPS_LOCK = 1 << 3;
+    }
+}
+
+/// A key being pressed or released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// Scancode (set 1) of the key, with any `0xE0` prefix as its high byte, and without the release bit.
+    pub scancode: u16,
+    pub pressed: bool,
+    pub modifiers: Modifiers,
+    /// Character the key produces under its modifiers, if it produces one.
+    pub ascii: Option<u8>,
+}
+
+static QUEUE: InterruptCell<SpinMutex<Deque<KeyEvent, QUEUE_SIZE>>> =
+    InterruptCell::new(SpinMutex::new(Deque::new()));
+
+/// Events dropped as the queue was full.
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// Queues `event`, dropping it if the queue is full.
+pub fn push(event: KeyEvent) {
+    if QUEUE.with(|queue| queue.lock().push_back(event)).is_err() {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Takes the oldest queued event, if any.
+pub fn pop() -> Option<KeyEvent> {
+    QUEUE.with(|queue| queue.lock().pop_front())
+}
+
+/// Takes queued events until one produces a character, returning it.
+pub fn pop_ascii() -> Option<u8> {
+    core::iter::from_fn(pop).find_map(|event| event.pressed.then_some(event.ascii).flatten())
+}
+
+/// Events dropped since boot, as the queue's reader fell behind.
+pub fn dropped() -> u64 {
+    DROPPED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,33 @@
+pub mod ahci;
+pub mod block;
+pub mod i8042;
+pub mod input;
+pub mod nvme;
+pub mod pci;
+pub mod usb;
+pub mod virtio;
+
+use core::time::Duration;
+
+/// Polls `func` until it returns `Some`, for at most `timeout`.
+///
+/// # Remarks
+///
+/// This spins on the current hardware thread, so it's only suited to the short waits of bringing up a device. The
+/// timeout is measured by the system clock, so this mustn't be called before it's initialized.
+pub fn poll_for<T>(timeout: Duration, mut func: impl FnMut() -> Option<T>) -> Option<T> {
+    let deadline_ns =
+        crate::time::now_ns().saturating_add(u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX));
+
+    loop {
+        if let Some(value) = func() {
+            return Some(value);
+        }
+
+        if crate::time::now_ns() >= deadline_ns {
+            return None;
+        }
+
+        core::hint::spin_loop();
+    }
+}
@@ -0,0 +1,771 @@
+//! NVMe controller driver, exposing each active namespace as a [`BlockDevice`].
+//!
+//! Controllers are reset and brought up with an admin queue pair, through which they're identified, and a single I/O
+//! queue pair is created. Admin commands are only issued while probing, so the admin queue is polled. The I/O
+//! completion queue is routed an MSI-X vector (through [`crate::interrupts::irq`]) whose handler reaps completions, and
+//! requesters halt their hardware thread until a completion wakes it; without MSI-X, the I/O queue is polled too.
+//!
+//! Data is transferred through a page-sized bounce buffer per command slot, so each command names a single PRP entry,
+//! and transfers larger than a page are split into several commands.
+
+mod queue;
+
+use crate::{
+    devices::{
+        block::{self, BlockDevice, SECTOR_SIZE},
+        pci::{
+            PciAddress,
+            config::{Bar, Command as PciCommand},
+            driver::{Driver, Match},
+            msix::MsiX,
+            tree::Device,
+        },
+        poll_for,
+    },
+    interrupts::{InterruptCell, irq::IrqTimestamp},
+    mem::HigherHalfDirectMap,
+    sync::SpinMutex,
+};
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+use core::{
+    num::NonZero,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use libsys::{Address, Frame, page_size};
+use queue::{Command, Completion, QueuePair};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to access the PCI configuration space: {0}")]
+    Config(#[from] crate::devices::pci::config::Error),
+
+    #[error("controller registers lie in an unusable BAR")]
+    UnusableBar,
+
+    #[error("failed to map the controller's registers")]
+    Paging(#[from] crate::mem::paging::Error),
+
+    #[error("failed to allocate queue memory: {0}")]
+    Dma(#[from] crate::mem::dma::Error),
+
+    #[error("controller doesn't support the NVM command set with {0}-byte pages")]
+    Unsupported(usize),
+
+    #[error("controller timed out {0}")]
+    Timeout(&'static str),
+
+    #[error("controller reported a fatal status")]
+    Fatal,
+
+    #[error("admin command {opcode:#04X} failed with status {status:#X}")]
+    Command { opcode: u8, status: u16 },
+}
+
+/// Class, subclass, and programming interface of NVMe controllers.
+const CLASS_MASS_STORAGE: u8 = 0x01;
+const SUBCLASS_NVM: u8 = 0x08;
+const PROG_IF_NVME: u8 = 0x02;
+
+/// Controller registers, at the start of BAR0.
+#[repr(usize)]
+#[derive(Debug, IntoPrimitive, Clone, Copy)]
+#[allow(non_camel_case_types)]
+#[rustfmt::skip]
+enum Register {
+    CAP     = 0x00,
+    CC      = 0x14,
+    CSTS    = 0x1C,
+    AQA     = 0x24,
+    ASQ     = 0x28,
+    ACQ     = 0x30,
+}
+
+/// Offset of the first doorbell register.
+const DOORBELL_BASE: usize = 0x1000;
+
+/// Controller configuration: enable.
+const CC_ENABLE: u32 = 1 << 0;
+/// Controller configuration: I/O submission & completion queue entry sizes (64 and 16 bytes), as powers of two.
+const CC_IO_ENTRY_SIZES: u32 = (6 << 16) | (4 << 20);
+
+/// Controller status: ready.
+const CSTS_READY: u32 = 1 << 0;
+/// Controller status: fatal status.
+const CSTS_FATAL: u32 = 1 << 1;
+
+/// Capabilities: the NVM command set is supported.
+const CAP_CSS_NVM: u64 = 1 << 37;
+
+/// Admin command opcodes.
+const ADMIN_CREATE_IO_SQ: u8 = 0x01;
+const ADMIN_CREATE_IO_CQ: u8 = 0x05;
+const ADMIN_IDENTIFY: u8 = 0x06;
+const ADMIN_SET_FEATURES: u8 = 0x09;
+
+/// Identify command structures.
+const IDENTIFY_NAMESPACE: u32 = 0x00;
+const IDENTIFY_CONTROLLER: u32 = 0x01;
+const IDENTIFY_ACTIVE_NAMESPACES: u32 = 0x02;
+
+const FEATURE_NUMBER_OF_QUEUES: u32 = 0x07;
+
+/// NVM command opcodes.
+const IO_WRITE: u8 = 0x01;
+const IO_READ: u8 = 0x02;
+
+/// Queue creation: the queue is physically contiguous.
+const QUEUE_PHYSICALLY_CONTIGUOUS: u32 = 1 << 0;
+/// Completion queue creation: interrupts are enabled.
+const QUEUE_INTERRUPTS_ENABLED: u32 = 1 << 1;
+
+const ADMIN_QUEUE_SIZE: u16 = 16;
+const IO_QUEUE_SIZE: u16 = 32;
+const IO_QUEUE_ID: u16 = 1;
+/// MSI-X table entry the I/O completion queue is routed to; entry 0 is left masked, as the admin queue is polled.
+const IO_QUEUE_ENTRY: u16 = 1;
+
+/// Commands in flight at once on the I/O queue, each with its own bounce buffer, and identified by its slot.
+const SLOTS: usize = 8;
+
+/// Longest an admin command may take to complete.
+const ADMIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Name bounce buffers are tagged with in the DMA audit.
+const DMA_OWNER: &str = "nvme";
+
+static DRIVER: Driver = Driver {
+    name: "nvme",
+    matches: &[Match::Class {
+        class: CLASS_MASS_STORAGE,
+        subclass: SUBCLASS_NVM,
+        prog_if: Some(PROG_IF_NVME),
+    }],
+    probe,
+};
+
+/// Registers the NVMe driver.
+pub fn register() {
+    if let Err(error) = crate::devices::pci::driver::register(&DRIVER) {
+        warn!("Failed to register the NVMe driver: {error}");
+    }
+}
+
+/// Every controller the driver has brought up, searched by the completion handler for its vector.
+static CONTROLLERS: SpinMutex<Vec<Arc<Controller>>> = SpinMutex::new(Vec::new());
+
+/// Index the next controller is named by.
+static NEXT_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+fn probe(device: &'static Device) -> bool {
+    match bring_up(device) {
+        Ok(namespaces) => {
+            for namespace in namespaces {
+                block::register(Arc::new(namespace));
+            }
+
+            true
+        }
+
+        Err(error) => {
+            warn!(
+                "Failed to initialize NVMe controller {}: {error}",
+                device.address()
+            );
+            false
+        }
+    }
+}
+
+/// Handles the completion interrupt of a controller's I/O queue.
+fn complete(vector: u8, _: IrqTimestamp) {
+    if let Some(controller) = CONTROLLERS
+        .lock()
+        .iter()
+        .find(|controller| controller.vector == Some(vector))
+    {
+        controller.io.with(|io| io.lock().reap());
+    }
+}
+
+/// The controller's memory-mapped registers.
+struct Registers {
+    base: NonNull<u8>,
+    doorbell_stride: usize,
+}
+
+impl Registers {
+    fn read<T: Copy>(&self, register: Register) -> T {
+        // Safety: Register offset is within the mapped registers, and naturally aligned.
+        unsafe {
+            self.base
+                .byte_add(usize::from(register))
+                .cast::<T>()
+                .read_volatile()
+        }
+    }
+
+    fn write<T: Copy>(&self, register: Register, value: T) {
+        // Safety: Register offset is within the mapped registers, and naturally aligned.
+        unsafe {
+            self.base
+                .byte_add(usize::from(register))
+                .cast::<T>()
+                .write_volatile(value);
+        }
+    }
+
+    /// Submission queue tail & completion queue head doorbells of the queue pair `id`.
+    fn doorbells(&self, id: u16) -> (NonNull<u32>, NonNull<u32>) {
+        let doorbell = |index: usize| {
+            // Safety: The mapping covers the doorbells of every queue the driver creates.
+            unsafe {
+                self.base
+                    .byte_add(DOORBELL_BASE + (index * self.doorbell_stride))
+                    .cast::<u32>()
+            }
+        };
+
+        (
+            doorbell(2 * usize::from(id)),
+            doorbell((2 * usize::from(id)) + 1),
+        )
+    }
+
+    /// Waits for the controller's ready status to become `ready`.
+    fn wait_ready(&self, ready: bool, timeout: Duration) -> Result<(), Error> {
+        poll_for(timeout, || {
+            let status: u32 = self.read(Register::CSTS);
+
+            ((status & CSTS_READY != 0) == ready).then_some(status)
+        })
+        .ok_or(Error::Timeout(if ready { "enabling" } else { "resetting" }))
+        .and_then(|status| {
+            if (status & CSTS_FATAL) == 0 {
+                Ok(())
+            } else {
+                Err(Error::Fatal)
+            }
+        })
+    }
+}
+
+/// The admin queue, which is only used (and polled) while probing.
+struct Admin<'a> {
+    registers: &'a Registers,
+    queue: QueuePair,
+    /// A page of DMA memory, for the data of identify commands.
+    buffer_frame: Address<Frame>,
+    next_cid: u16,
+}
+
+impl Admin<'_> {
+    /// Submits `command`, and waits for it to complete successfully.
+    fn execute(&mut self, mut command: Command) -> Result<Completion, Error> {
+        let opcode = u8::try_from(command.cdw0 & 0xFF).unwrap();
+        let cid = self.next_cid;
+        self.next_cid = self.next_cid.wrapping_add(1);
+        command.cdw0 |= u32::from(cid) << 16;
+
+        self.queue.submit(command);
+        let completion = poll_for(ADMIN_TIMEOUT, || {
+            // Completions of commands which previously timed out are skipped.
+            core::iter::from_fn(|| self.queue.pop_completion())
+                .find(|completion| completion.cid == cid)
+        })
+        .ok_or(Error::Timeout("executing an admin command"))?;
+
+        match completion.status_code() {
+            0 => Ok(completion),
+            status => Err(Error::Command { opcode, status }),
+        }
+    }
+
+    fn buffer_physical(&self) -> u64 {
+        u64::try_from(self.buffer_frame.get().get()).unwrap()
+    }
+
+    /// Identifies the structure `cns` of the namespace `nsid`, returning it as the page it was written to.
+    fn identify(&mut self, cns: u32, nsid: u32) -> Result<&[u8], Error> {
+        let mut command = Command::new(ADMIN_IDENTIFY, 0, self.buffer_physical());
+        command.nsid = nsid;
+        command.cdw10 = cns;
+        self.execute(command)?;
+
+        // Safety: The buffer is a page of DMA memory, which the controller has finished writing.
+        Ok(unsafe {
+            core::slice::from_raw_parts(
+                HigherHalfDirectMap::frame_to_page(self.buffer_frame).as_ptr(),
+                page_size(),
+            )
+        })
+    }
+
+    /// Creates the I/O queue pair `queue` as `id`, with completions signalled on the MSI-X table entry `entry`, or
+    /// not at all if it's `None`.
+    fn create_io_queue(
+        &mut self,
+        id: u16,
+        queue: &QueuePair,
+        entry: Option<u16>,
+    ) -> Result<(), Error> {
+        let (submission, completion) = queue.physical_addresses();
+        let queue_id = (u32::from(queue.size() - 1) << 16) | u32::from(id);
+
+        let mut command = Command::new(ADMIN_CREATE_IO_CQ, 0, completion);
+        command.cdw10 = queue_id;
+        command.cdw11 = match entry {
+            Some(entry) => {
+                (u32::from(entry) << 16) | QUEUE_INTERRUPTS_ENABLED | QUEUE_PHYSICALLY_CONTIGUOUS
+            }
+            None => QUEUE_PHYSICALLY_CONTIGUOUS,
+        };
+        self.execute(command)?;
+
+        let mut command = Command::new(ADMIN_CREATE_IO_SQ, 0, submission);
+        command.cdw10 = queue_id;
+        command.cdw11 = (u32::from(id) << 16) | QUEUE_PHYSICALLY_CONTIGUOUS;
+        self.execute(command)?;
+
+        Ok(())
+    }
+}
+
+impl Drop for Admin<'_> {
+    fn drop(&mut self) {
+        if let Err(error) =
+            crate::mem::dma::deallocate(self.buffer_frame, NonZero::<usize>::MIN, DMA_OWNER)
+        {
+            warn!("Failed to free the NVMe identify buffer: {error}");
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SlotState {
+    Free,
+    InFlight,
+    /// Completed by the controller with this status, which is yet to be taken.
+    Complete(u16),
+}
+
+/// Data transferred by a single command.
+enum Transfer<'a> {
+    Read(&'a mut [u8]),
+    Write(&'a [u8]),
+}
+
+/// The I/O queue, and the command slots submitted to it.
+struct Io {
+    queue: QueuePair,
+    /// First of the slots' bounce buffers, one frame each.
+    frame: Address<Frame>,
+    memory: NonNull<u8>,
+    slots: [SlotState; SLOTS],
+}
+
+// Safety: Queue & bounce buffer memory are only accessed through the I/O queue's lock.
+unsafe impl Send for Io {}
+
+impl Io {
+    fn bounce_physical(&self, slot: usize) -> u64 {
+        u64::try_from(self.frame.get().get() + (slot * page_size())).unwrap()
+    }
+
+    /// Bounce buffer of `slot`, which is a page long.
+    fn bounce(&mut self, slot: usize) -> &mut [u8] {
+        // Safety: Each slot has a page, which the controller doesn't access unless the slot is in flight.
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                self.memory.byte_add(slot * page_size()).as_ptr(),
+                page_size(),
+            )
+        }
+    }
+
+    /// Marks the slot of every command the controller has completed.
+    fn reap(&mut self) {
+        while let Some(completion) = self.queue.pop_completion() {
+            if let Some(state) = self.slots.get_mut(usize::from(completion.cid))
+                && *state == SlotState::InFlight
+            {
+                *state = SlotState::Complete(completion.status_code());
+            }
+        }
+    }
+}
+
+/// A controller, shared by its namespaces.
+struct Controller {
+    address: PciAddress,
+    /// Vector I/O completions are signalled on, or `None` if the I/O queue is polled.
+    vector: Option<u8>,
+    io: InterruptCell<SpinMutex<Io>>,
+}
+
+impl Controller {
+    /// Repeatedly calls `func` with the I/O queue, having reaped any completed commands, until it returns `Some`.
+    fn wait_until<T>(&self, mut func: impl FnMut(&mut Io) -> Option<T>) -> T {
+        loop {
+            let value = self.io.with(|io| {
+                let mut io = io.lock();
+                io.reap();
+
+                func(&mut io)
+            });
+
+            if let Some(value) = value {
+                return value;
+            }
+
+            if self.vector.is_some() && crate::interrupts::is_enabled() {
+                // A completion wakes the hardware thread it's routed to; any other (or one which missed the completion
+                // as it checked) is woken by its next timer tick.
+                crate::interrupts::wait_next();
+            } else {
+                core::hint::spin_loop();
+            }
+        }
+    }
+
+    /// Performs `transfer` (of at most a page, and a whole number of blocks) from the block `lba` of the namespace
+    /// `nsid`, as a single command, waiting for it to complete.
+    fn request(
+        &self,
+        nsid: u32,
+        lba: u64,
+        block_count: u32,
+        mut transfer: Transfer<'_>,
+    ) -> Result<(), block::Error> {
+        let opcode = match &transfer {
+            Transfer::Read(_) => IO_READ,
+            Transfer::Write(_) => IO_WRITE,
+        };
+
+        let slot = self.wait_until(|io| {
+            let slot = io
+                .slots
+                .iter()
+                .position(|state| *state == SlotState::Free)?;
+            if let Transfer::Write(data) = &transfer {
+                io.bounce(slot)[..data.len()].copy_from_slice(data);
+            }
+
+            let mut command = Command::new(
+                opcode,
+                u16::try_from(slot).unwrap(),
+                io.bounce_physical(slot),
+            );
+            command.nsid = nsid;
+            command.cdw10 = u32::try_from(lba & 0xFFFF_FFFF).unwrap();
+            command.cdw11 = u32::try_from(lba >> 32).unwrap();
+            command.cdw12 = block_count - 1;
+
+            io.slots[slot] = SlotState::InFlight;
+            io.queue.submit(command);
+
+            Some(slot)
+        });
+
+        let status = self.wait_until(|io| {
+            let SlotState::Complete(status) = io.slots[slot] else {
+                return None;
+            };
+
+            if let Transfer::Read(buf) = &mut transfer {
+                let len = buf.len();
+                buf.copy_from_slice(&io.bounce(slot)[..len]);
+            }
+            io.slots[slot] = SlotState::Free;
+
+            Some(status)
+        });
+
+        if status == 0 {
+            Ok(())
+        } else {
+            warn!(
+                "NVMe controller {} failed a command at LBA {lba}: status {status:#X}",
+                self.address
+            );
+            Err(block::Error::Io)
+        }
+    }
+}
+
+/// An active namespace of a controller, as a [`BlockDevice`].
+pub struct Namespace {
+    name: String,
+    controller: Arc<Controller>,
+    nsid: u32,
+    /// Block size, as a power of two.
+    block_shift: u32,
+    block_count: u64,
+}
+
+/// First block of a transfer of `len` bytes from `sector`, if it's a whole number of blocks of `1 << block_shift`
+/// bytes, starting on a block boundary.
+fn first_block(sector: u64, len: usize, block_shift: u32) -> Result<u64, block::Error> {
+    let sectors_per_block = (1 << block_shift) / u64::try_from(SECTOR_SIZE).unwrap();
+    if (sector % sectors_per_block) != 0 || (len & ((1 << block_shift) - 1)) != 0 {
+        return Err(block::Error::Misaligned(len));
+    }
+
+    Ok(sector / sectors_per_block)
+}
+
+impl BlockDevice for Namespace {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn sector_count(&self) -> u64 {
+        self.block_count << (self.block_shift - SECTOR_SIZE.trailing_zeros())
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn read(&self, sector: u64, buf: &mut [u8]) -> Result<(), block::Error> {
+        block::check_range(self.sector_count(), sector, buf.len())?;
+        let first_block = first_block(sector, buf.len(), self.block_shift)?;
+
+        (first_block..)
+            .step_by(page_size() >> self.block_shift)
+            .zip(buf.chunks_mut(page_size()))
+            .try_for_each(|(lba, chunk)| {
+                let block_count = u32::try_from(chunk.len() >> self.block_shift).unwrap();
+
+                self.controller
+                    .request(self.nsid, lba, block_count, Transfer::Read(chunk))
+            })
+    }
+
+    fn write(&self, sector: u64, buf: &[u8]) -> Result<(), block::Error> {
+        block::check_range(self.sector_count(), sector, buf.len())?;
+        let first_block = first_block(sector, buf.len(), self.block_shift)?;
+
+        (first_block..)
+            .step_by(page_size() >> self.block_shift)
+            .zip(buf.chunks(page_size()))
+            .try_for_each(|(lba, chunk)| {
+                let block_count = u32::try_from(chunk.len() >> self.block_shift).unwrap();
+
+                self.controller
+                    .request(self.nsid, lba, block_count, Transfer::Write(chunk))
+            })
+    }
+}
+
+/// Maps the controller's registers from BAR0.
+fn map_registers(device: &Device) -> Result<NonNull<u8>, Error> {
+    let (address, size) = device
+        .bars()
+        .iter()
+        .find(|decoded_bar| decoded_bar.index == 0)
+        .and_then(|decoded_bar| match decoded_bar.bar {
+            Bar::Memory32 { address, .. } => {
+                Some((usize::try_from(address).ok()?, decoded_bar.size))
+            }
+            Bar::Memory64 { address, .. } => {
+                Some((usize::try_from(address).ok()?, decoded_bar.size))
+            }
+            Bar::Io { .. } => None,
+        })
+        .ok_or(Error::UnusableBar)?;
+
+    let frame = Address::<Frame>::new(address).ok_or(Error::UnusableBar)?;
+    let size = usize::try_from(size).map_err(|_| Error::UnusableBar)?;
+
+    Ok(crate::mem::map_mmio(frame, size)?)
+}
+
+/// Resets & enables the controller of `device`, creates its I/O queue, and identifies its active namespaces.
+fn bring_up(device: &Device) -> Result<Vec<Namespace>, Error> {
+    let mut config = device.config()?;
+    // Interrupts are only taken through MSI-X, so the legacy pin is disabled.
+    config.set_command(
+        config.command()
+            | PciCommand::MEMORY_SPACE
+            | PciCommand::BUS_MASTER
+            | PciCommand::INTERRUPT_DISABLE,
+    );
+
+    let mut registers = Registers {
+        base: map_registers(device)?,
+        doorbell_stride: 0,
+    };
+    let capabilities: u64 = registers.read(Register::CAP);
+    registers.doorbell_stride = 4 << ((capabilities >> 32) & 0xF);
+
+    // The minimum memory page size (as a power of two, over 4KiB) must be the kernel's.
+    if (capabilities & CAP_CSS_NVM) == 0
+        || ((capabilities >> 48) & 0xF) != 0
+        || page_size() != 0x1000
+    {
+        return Err(Error::Unsupported(page_size()));
+    }
+
+    let max_queue_size = u16::try_from(capabilities & 0xFFFF)
+        .unwrap()
+        .saturating_add(1);
+    let ready_timeout = Duration::from_millis(((capabilities >> 24) & 0xFF) * 500);
+
+    registers.write(Register::CC, 0u32);
+    registers.wait_ready(false, ready_timeout)?;
+
+    let admin_size = ADMIN_QUEUE_SIZE.min(max_queue_size);
+    let (submission_doorbell, completion_doorbell) = registers.doorbells(0);
+    let mut admin = Admin {
+        registers: &registers,
+        queue: QueuePair::new(admin_size, submission_doorbell, completion_doorbell)?,
+        buffer_frame: crate::mem::dma::allocate(NonZero::<usize>::MIN, DMA_OWNER)?,
+        next_cid: 0,
+    };
+
+    let (submission, completion) = admin.queue.physical_addresses();
+    let admin_sizes = u32::from(admin_size - 1);
+    registers.write(Register::AQA, admin_sizes | (admin_sizes << 16));
+    registers.write(Register::ASQ, submission);
+    registers.write(Register::ACQ, completion);
+    registers.write(Register::CC, CC_ENABLE | CC_IO_ENTRY_SIZES);
+    registers.wait_ready(true, ready_timeout)?;
+
+    let identity = admin.identify(IDENTIFY_CONTROLLER, 0)?;
+    let serial: String = String::from_utf8_lossy(&identity[4..24]).trim().into();
+    let model: String = String::from_utf8_lossy(&identity[24..64]).trim().into();
+
+    // A single I/O queue pair is requested (the counts are zero-based).
+    let mut command = Command::new(ADMIN_SET_FEATURES, 0, 0);
+    command.cdw10 = FEATURE_NUMBER_OF_QUEUES;
+    admin.execute(command)?;
+
+    let vector = route_interrupts(device);
+    let io_size = IO_QUEUE_SIZE.min(max_queue_size).min(QueuePair::max_size());
+    let (submission_doorbell, completion_doorbell) = admin.registers.doorbells(IO_QUEUE_ID);
+    let io_queue = QueuePair::new(io_size, submission_doorbell, completion_doorbell)?;
+    admin.create_io_queue(IO_QUEUE_ID, &io_queue, vector.map(|_| IO_QUEUE_ENTRY))?;
+
+    let nsids = admin
+        .identify(IDENTIFY_ACTIVE_NAMESPACES, 0)?
+        .chunks_exact(4)
+        .map(|nsid| u32::from_le_bytes(nsid.try_into().unwrap()))
+        .take_while(|nsid| *nsid != 0)
+        .collect::<Vec<_>>();
+
+    let mut namespaces = Vec::new();
+    for nsid in nsids {
+        let identity = admin.identify(IDENTIFY_NAMESPACE, nsid)?;
+        let block_count = u64::from_le_bytes(identity[..8].try_into().unwrap());
+        let format = usize::from(identity[26] & 0xF);
+        let block_shift = u32::from(identity[128 + (format * 4) + 2]);
+
+        if block_count == 0 {
+            continue;
+        }
+
+        if block_shift < SECTOR_SIZE.trailing_zeros() || block_shift > page_size().trailing_zeros()
+        {
+            warn!(
+                "NVMe namespace {nsid} has unsupported {}-byte blocks.",
+                1u64 << block_shift.min(63)
+            );
+            continue;
+        }
+
+        namespaces.push((nsid, block_shift, block_count));
+    }
+
+    drop(admin);
+
+    let slot_count = NonZero::new(SLOTS).unwrap();
+    let frame = crate::mem::dma::allocate(slot_count, DMA_OWNER)?;
+    // The I/O queue can't hold more commands than one fewer than its size.
+    let slots = core::array::from_fn(|slot| {
+        if slot < usize::from(io_size - 1) {
+            SlotState::Free
+        } else {
+            SlotState::InFlight
+        }
+    });
+
+    let controller = Arc::new(Controller {
+        address: device.address(),
+        vector,
+        io: InterruptCell::new(SpinMutex::new(Io {
+            queue: io_queue,
+            frame,
+            memory: NonNull::new(HigherHalfDirectMap::frame_to_page(frame).as_ptr()).unwrap(),
+            slots,
+        })),
+    });
+    crate::interrupts::uninterruptable(|| CONTROLLERS.lock().push(controller.clone()));
+
+    let index = NEXT_INDEX.fetch_add(1, Ordering::Relaxed);
+    info!(
+        "NVMe controller {} is nvme{index}: {model} ({serial}), {} namespaces, {}.",
+        device.address(),
+        namespaces.len(),
+        if vector.is_some() {
+            "interrupt-driven"
+        } else {
+            "polled"
+        }
+    );
+
+    Ok(namespaces
+        .into_iter()
+        .map(|(nsid, block_shift, block_count)| Namespace {
+            name: format!("nvme{index}n{nsid}"),
+            controller: controller.clone(),
+            nsid,
+            block_shift,
+            block_count,
+        })
+        .collect())
+}
+
+/// Routes the I/O completion queue's MSI-X table entry to a newly registered vector, returning it, or `None` if the
+/// queue must be polled.
+fn route_interrupts(device: &Device) -> Option<u8> {
+    let msix = MsiX::new(device)
+        .inspect_err(|error| debug!("NVMe controller {} is polled: {error}", device.address()))
+        .ok()?;
+    let vector = crate::interrupts::irq::register(complete)
+        .inspect_err(|error| warn!("Failed to register an NVMe vector: {error}"))
+        .ok()?;
+
+    if let Err(error) = msix
+        .route(IO_QUEUE_ENTRY, vector, crate::cpu::get_id())
+        .and_then(|()| msix.enable())
+    {
+        warn!(
+            "Failed to route NVMe controller {} interrupts: {error}",
+            device.address()
+        );
+
+        let _ = msix.set_masked(IO_QUEUE_ENTRY, true);
+        crate::interrupts::irq::unregister(vector);
+
+        return None;
+    }
+
+    Some(vector)
+}
+
+crate::kernel_test! {
+    /// Transfers must start on a block boundary, and be a whole number of blocks.
+    fn maps_sectors_to_blocks() -> crate::tests::Outcome {
+        if first_block(24, 0x2000, 12) != Ok(3) || first_block(7, SECTOR_SIZE, 9) != Ok(7) {
+            return Err("aligned transfer was mapped to the wrong block");
+        }
+
+        if first_block(4, 0x1000, 12).is_ok() || first_block(8, SECTOR_SIZE, 12).is_ok() {
+            return Err("transfer not aligned to blocks was accepted");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use super::Error;
+use crate::mem::HigherHalfDirectMap;
+use core::{
+    num::NonZero,
+    ptr::NonNull,
+    sync::atomic::{Ordering, fence},
+};
+use libsys::{Address, Frame, page_size};
+
+/// Name queue memory is tagged with in the DMA audit.
+const DMA_OWNER: &str = "nvme";
+
+/// Completion status: the phase tag, which the controller inverts each time it wraps the queue.
+const STATUS_PHASE: u16 = 1 << 0;
+
+/// A submission queue entry.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Command {
+    /// Opcode, fused operation, PRP/SGL selection, and command identifier.
+    pub cdw0: u32,
+    pub nsid: u32,
+    pub reserved: [u32; 2],
+    pub metadata: u64,
+    pub prp1: u64,
+    pub prp2: u64,
+    pub cdw10: u32,
+    pub cdw11: u32,
+    pub cdw12: u32,
+    pub cdw13: u32,
+    pub cdw14: u32,
+    pub cdw15: u32,
+}
+
+impl Command {
+    /// Command with `opcode`, identified by `cid`, whose data is the single page at `prp1`.
+    pub fn new(opcode: u8, cid: u16, prp1: u64) -> Self {
+        Self {
+            cdw0: u32::from(opcode) | (u32::from(cid) << 16),
+            prp1,
+            ..Self::default()
+        }
+    }
+}
+
+/// A completion queue entry.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Completion {
+    pub result: u32,
+    pub reserved: u32,
+    pub sq_head: u16,
+    pub sq_id: u16,
+    pub cid: u16,
+    /// Status field, above the phase tag.
+    pub status: u16,
+}
+
+impl Completion {
+    /// Status code type & status code; zero on success.
+    #[inline]
+    pub const fn status_code(&self) -> u16 {
+        (self.status >> 1) & 0x7FF
+    }
+}
+
+/// A submission queue, and the completion queue its commands complete to, each in their own frame of DMA memory.
+///
+/// # Remarks
+///
+/// The caller is responsible for never having more commands in flight than one fewer than the queue's size.
+pub struct QueuePair {
+    size: u16,
+    submission_frame: Address<Frame>,
+    submission: NonNull<Command>,
+    completion_frame: Address<Frame>,
+    completion: NonNull<Completion>,
+    submission_tail: u16,
+    completion_head: u16,
+    /// Phase tag of completions not yet taken.
+    phase: bool,
+    submission_doorbell: NonNull<u32>,
+    completion_doorbell: NonNull<u32>,
+}
+
+// Safety: Queue memory is only accessed through the owning driver.
+unsafe impl Send for QueuePair {}
+
+impl QueuePair {
+    /// Largest queue which fits each ring in a single frame.
+    pub fn max_size() -> u16 {
+        u16::try_from(page_size() / size_of::<Command>()).unwrap()
+    }
+
+    pub fn new(
+        size: u16,
+        submission_doorbell: NonNull<u32>,
+        completion_doorbell: NonNull<u32>,
+    ) -> Result<Self, Error> {
+        debug_assert!(size <= Self::max_size());
+
+        let one = NonZero::<usize>::MIN;
+        let submission_frame = crate::mem::dma::allocate(one, DMA_OWNER)?;
+        let completion_frame = crate::mem::dma::allocate(one, DMA_OWNER)?;
+
+        let ring = |frame| {
+            let ring = NonNull::new(HigherHalfDirectMap::frame_to_page(frame).as_ptr()).unwrap();
+            // Safety: The frame was just allocated, and is accessible through the HHDM.
+            unsafe { ring.write_bytes(0, page_size()) };
+
+            ring
+        };
+
+        Ok(Self {
+            size,
+            submission_frame,
+            submission: ring(submission_frame).cast(),
+            completion_frame,
+            completion: ring(completion_frame).cast(),
+            submission_tail: 0,
+            completion_head: 0,
+            phase: true,
+            submission_doorbell,
+            completion_doorbell,
+        })
+    }
+
+    #[inline]
+    pub const fn size(&self) -> u16 {
+        self.size
+    }
+
+    /// Physical addresses of the submission and completion queues.
+    pub fn physical_addresses(&self) -> (u64, u64) {
+        (
+            u64::try_from(self.submission_frame.get().get()).unwrap(),
+            u64::try_from(self.completion_frame.get().get()).unwrap(),
+        )
+    }
+
+    /// Places `command` in the submission queue, and rings its doorbell.
+    pub fn submit(&mut self, command: Command) {
+        // Safety: The tail is always within the queue, and its entry isn't owned by the controller.
+        unsafe {
+            self.submission
+                .add(usize::from(self.submission_tail))
+                .write_volatile(command);
+        }
+        self.submission_tail = (self.submission_tail + 1) % self.size;
+
+        // The entry must be visible before the doorbell that publishes it.
+        fence(Ordering::SeqCst);
+        // Safety: Doorbell was located from the controller's registers for this queue.
+        unsafe {
+            self.submission_doorbell
+                .write_volatile(u32::from(self.submission_tail));
+        }
+    }
+
+    /// Takes the next completion the controller has posted, and releases its entry back to the controller.
+    pub fn pop_completion(&mut self) -> Option<Completion> {
+        // Safety: The head is always within the queue.
+        let entry = unsafe { self.completion.add(usize::from(self.completion_head)) };
+        // Safety: The status is the entry's last field.
+        let status = unsafe {
+            entry
+                .byte_add(core::mem::offset_of!(Completion, status))
+                .cast::<u16>()
+                .read_volatile()
+        };
+        if ((status & STATUS_PHASE) != 0) != self.phase {
+            return None;
+        }
+
+        // The rest of the entry must be read after the phase tag that published it.
+        fence(Ordering::Acquire);
+        // Safety: The controller has posted the entry.
+        let completion = unsafe { entry.read_volatile() };
+
+        self.completion_head = (self.completion_head + 1) % self.size;
+        if self.completion_head == 0 {
+            self.phase = !self.phase;
+        }
+
+        // Safety: Doorbell was located from the controller's registers for this queue.
+        unsafe {
+            self.completion_doorbell
+                .write_volatile(u32::from(self.completion_head));
+        }
+
+        Some(completion)
+    }
+}
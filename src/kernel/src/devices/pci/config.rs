@@ -0,0 +1,375 @@
+use super::{PciAddress, ecam, legacy};
+use core::ptr::NonNull;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("ECAM configuration space is unavailable")]
+    Ecam(#[from] ecam::Error),
+
+    #[error("no configuration space access mechanism can reach {0}")]
+    Unreachable(PciAddress),
+}
+
+/// Registers of the common configuration space header.
+#[repr(u16)]
+#[derive(Debug, IntoPrimitive, Clone, Copy)]
+#[allow(non_camel_case_types)]
+#[rustfmt::skip]
+pub enum Register {
+    VENDOR_ID           = 0x00,
+    DEVICE_ID           = 0x02,
+    COMMAND             = 0x04,
+    STATUS              = 0x06,
+    REVISION_ID         = 0x08,
+    PROG_IF             = 0x09,
+    SUBCLASS            = 0x0A,
+    CLASS               = 0x0B,
+    HEADER_TYPE         = 0x0E,
+    BAR0                = 0x10,
+    PRIMARY_BUS         = 0x18,
+    SECONDARY_BUS       = 0x19,
+    SUBORDINATE_BUS     = 0x1A,
+    CAPABILITIES_PTR    = 0x34,
+    INTERRUPT_LINE      = 0x3C,
+    INTERRUPT_PIN       = 0x3D,
+}
+
+bitflags! {
+    #[repr(transparent)]
+    #[derive(Debug, Clone, Copy)]
+    pub struct Command: u16 {
+        const IO_SPACE = 1 << 0;
+        const MEMORY_SPACE = 1 << 1;
+        const BUS_MASTER = 1 << 2;
+        const INTERRUPT_DISABLE = 1 << 10;
+    }
+}
+
+bitflags! {
+    #[repr(transparent)]
+    #[derive(Debug, Clone, Copy)]
+    pub struct Status: u16 {
+        const INTERRUPT = 1 << 3;
+        const CAPABILITIES_LIST = 1 << 4;
+    }
+}
+
+/// Kind of configuration space header a function provides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderKind {
+    Standard,
+    PciToPciBridge,
+    CardBusBridge,
+    Unknown(u8),
+}
+
+/// Legacy interrupt pin used by a function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptPin {
+    IntA,
+    IntB,
+    IntC,
+    IntD,
+}
+
+/// A decoded base address register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bar {
+    Memory32 { address: u32, prefetchable: bool },
+    Memory64 { address: u64, prefetchable: bool },
+    Io { port: u32 },
+}
+
+/// An entry in a function's capabilities list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capability {
+    pub id: u8,
+    /// Offset of the capability within the configuration space.
+    pub offset: u16,
+}
+
+/// Mechanism by which a function's configuration space is accessed.
+enum Access {
+    Ecam(NonNull<u32>),
+    Legacy,
+}
+
+/// Configuration space of a single PCI function.
+pub struct ConfigSpace {
+    address: PciAddress,
+    access: Access,
+}
+
+// Safety: Configuration space is mapped in the kernel address space, which is shared by all hardware threads.
+unsafe impl Send for ConfigSpace {}
+
+impl ConfigSpace {
+    /// Opens the configuration space for the function at `address`.
+    ///
+    /// # Remarks
+    ///
+    /// ECAM is preferred; the legacy I/O port mechanism is used only if no ECAM region covers `address`.
+    pub fn new(address: PciAddress) -> Result<Self, Error> {
+        match ecam::map(address) {
+            Ok(ptr) => Ok(Self {
+                address,
+                access: Access::Ecam(ptr),
+            }),
+
+            Err(ecam::Error::NoRegion(_)) if legacy::can_access(address) => Ok(Self {
+                address,
+                access: Access::Legacy,
+            }),
+
+            Err(ecam::Error::NoRegion(_)) => Err(Error::Unreachable(address)),
+            Err(error) => Err(Error::Ecam(error)),
+        }
+    }
+
+    /// Size of the configuration space reachable through this function's access mechanism.
+    pub const fn size(&self) -> usize {
+        match self.access {
+            Access::Ecam(_) => ecam::FUNCTION_CONFIG_SIZE,
+            Access::Legacy => legacy::FUNCTION_CONFIG_SIZE,
+        }
+    }
+
+    #[inline]
+    pub const fn address(&self) -> PciAddress {
+        self.address
+    }
+
+    /// Reads the dword containing `offset`.
+    pub fn read_u32(&self, offset: u16) -> u32 {
+        let dword_offset = usize::from(offset & !0b11);
+        debug_assert!(dword_offset < self.size());
+
+        match self.access {
+            // Safety: Offset is dword-aligned, and within the mapped configuration space.
+            Access::Ecam(ptr) => unsafe { ptr.byte_add(dword_offset).read_volatile() },
+            Access::Legacy => legacy::read_u32(self.address, offset),
+        }
+    }
+
+    /// Writes the dword containing `offset`.
+    pub fn write_u32(&mut self, offset: u16, value: u32) {
+        let dword_offset = usize::from(offset & !0b11);
+        debug_assert!(dword_offset < self.size());
+
+        match self.access {
+            // Safety: Offset is dword-aligned, and within the mapped configuration space.
+            Access::Ecam(ptr) => unsafe { ptr.byte_add(dword_offset).write_volatile(value) },
+            Access::Legacy => legacy::write_u32(self.address, offset, value),
+        }
+    }
+
+    pub fn read_u16(&self, offset: u16) -> u16 {
+        debug_assert_eq!(offset & 0b1, 0, "unaligned word read");
+
+        let shift = u32::from(offset & 0b10) * 8;
+        u16::try_from((self.read_u32(offset) >> shift) & 0xFFFF).unwrap()
+    }
+
+    pub fn write_u16(&mut self, offset: u16, value: u16) {
+        debug_assert_eq!(offset & 0b1, 0, "unaligned word write");
+
+        let shift = u32::from(offset & 0b10) * 8;
+        let dword = self.read_u32(offset) & !(0xFFFF << shift);
+        self.write_u32(offset, dword | (u32::from(value) << shift));
+    }
+
+    pub fn read_u8(&self, offset: u16) -> u8 {
+        let shift = u32::from(offset & 0b11) * 8;
+        u8::try_from((self.read_u32(offset) >> shift) & 0xFF).unwrap()
+    }
+
+    pub fn read(&self, register: Register) -> u8 {
+        self.read_u8(register.into())
+    }
+
+    pub fn vendor_id(&self) -> u16 {
+        self.read_u16(Register::VENDOR_ID.into())
+    }
+
+    pub fn device_id(&self) -> u16 {
+        self.read_u16(Register::DEVICE_ID.into())
+    }
+
+    /// Whether a function is present at this address.
+    pub fn is_present(&self) -> bool {
+        self.vendor_id() != 0xFFFF
+    }
+
+    /// Class, subclass, and programming interface of the function.
+    pub fn class(&self) -> (u8, u8, u8) {
+        (
+            self.read(Register::CLASS),
+            self.read(Register::SUBCLASS),
+            self.read(Register::PROG_IF),
+        )
+    }
+
+    pub fn header_kind(&self) -> HeaderKind {
+        match self.read(Register::HEADER_TYPE) & 0x7F {
+            0x0 => HeaderKind::Standard,
+            0x1 => HeaderKind::PciToPciBridge,
+            0x2 => HeaderKind::CardBusBridge,
+            kind => HeaderKind::Unknown(kind),
+        }
+    }
+
+    /// Whether the device implements more than one function.
+    pub fn is_multifunction(&self) -> bool {
+        (self.read(Register::HEADER_TYPE) & 0x80) > 0
+    }
+
+    pub fn command(&self) -> Command {
+        Command::from_bits_retain(self.read_u16(Register::COMMAND.into()))
+    }
+
+    pub fn set_command(&mut self, command: Command) {
+        self.write_u16(Register::COMMAND.into(), command.bits());
+    }
+
+    pub fn status(&self) -> Status {
+        Status::from_bits_retain(self.read_u16(Register::STATUS.into()))
+    }
+
+    /// Bus number immediately downstream of a PCI-to-PCI bridge, or `None` if the function isn't a bridge.
+    pub fn secondary_bus(&self) -> Option<u8> {
+        (self.header_kind() == HeaderKind::PciToPciBridge)
+            .then(|| self.read(Register::SECONDARY_BUS))
+    }
+
+    /// Legacy interrupt pin used by the function, if any.
+    pub fn interrupt_pin(&self) -> Option<InterruptPin> {
+        match self.read(Register::INTERRUPT_PIN) {
+            0x1 => Some(InterruptPin::IntA),
+            0x2 => Some(InterruptPin::IntB),
+            0x3 => Some(InterruptPin::IntC),
+            0x4 => Some(InterruptPin::IntD),
+            _ => None,
+        }
+    }
+
+    /// Interrupt line assigned by firmware, or `None` if unassigned.
+    pub fn interrupt_line(&self) -> Option<u8> {
+        Some(self.read(Register::INTERRUPT_LINE)).filter(|line| *line != 0xFF)
+    }
+
+    /// Number of base address registers provided by the function's header.
+    pub fn bar_count(&self) -> u8 {
+        match self.header_kind() {
+            HeaderKind::Standard => 6,
+            HeaderKind::PciToPciBridge => 2,
+            HeaderKind::CardBusBridge | HeaderKind::Unknown(_) => 0,
+        }
+    }
+
+    fn bar_offset(index: u8) -> u16 {
+        u16::from(Register::BAR0) + (u16::from(index) * 4)
+    }
+
+    /// Decodes the base address register at `index`.
+    ///
+    /// # Remarks
+    ///
+    /// Returns `None` if the BAR is unimplemented, or is the upper half of a 64-bit BAR.
+    pub fn bar(&self, index: u8) -> Option<Bar> {
+        if index >= self.bar_count() {
+            return None;
+        }
+
+        let value = self.read_u32(Self::bar_offset(index));
+        if value == 0 {
+            return None;
+        }
+
+        if (value & 0b1) > 0 {
+            return Some(Bar::Io {
+                port: value & !0b11,
+            });
+        }
+
+        let prefetchable = (value & 0b1000) > 0;
+        match (value >> 1) & 0b11 {
+            0b00 => Some(Bar::Memory32 {
+                address: value & !0b1111,
+                prefetchable,
+            }),
+
+            0b10 if (index + 1) < self.bar_count() => {
+                let high = self.read_u32(Self::bar_offset(index + 1));
+
+                Some(Bar::Memory64 {
+                    address: (u64::from(high) << 32) | u64::from(value & !0b1111),
+                    prefetchable,
+                })
+            }
+
+            _ => None,
+        }
+    }
+
+    /// Determines the size (in bytes) of the region decoded by the base address register at `index`.
+    ///
+    /// # Remarks
+    ///
+    /// Decoding is disabled for the duration of the probe, so the device does not respond to the
+    /// temporary all-ones address.
+    pub fn bar_size(&mut self, index: u8) -> Option<u64> {
+        let bar = self.bar(index)?;
+
+        let command = self.command();
+        self.set_command(command - (Command::IO_SPACE | Command::MEMORY_SPACE));
+
+        let offset = Self::bar_offset(index);
+        let original = self.read_u32(offset);
+        self.write_u32(offset, u32::MAX);
+        let probed = self.read_u32(offset);
+        self.write_u32(offset, original);
+
+        let size = match bar {
+            Bar::Io { port: _ } => u64::from(!(probed & !0b11)) + 1,
+            Bar::Memory32 { .. } => u64::from(!(probed & !0b1111)) + 1,
+            Bar::Memory64 { .. } => {
+                let high_offset = Self::bar_offset(index + 1);
+                let original_high = self.read_u32(high_offset);
+                self.write_u32(high_offset, u32::MAX);
+                let probed_high = self.read_u32(high_offset);
+                self.write_u32(high_offset, original_high);
+
+                (!((u64::from(probed_high) << 32) | u64::from(probed & !0b1111))).wrapping_add(1)
+            }
+        };
+
+        self.set_command(command);
+
+        Some(size)
+    }
+
+    /// Iterates the function's capabilities list.
+    pub fn capabilities(&self) -> impl Iterator<Item = Capability> + '_ {
+        let first_offset = if self.status().contains(Status::CAPABILITIES_LIST) {
+            u16::from(self.read(Register::CAPABILITIES_PTR) & !0b11)
+        } else {
+            0
+        };
+
+        // Bound the traversal, so a malformed (cyclic) list can't iterate forever.
+        let mut remaining = 48;
+        core::iter::successors(
+            Some(first_offset).filter(|offset| *offset != 0),
+            move |offset| {
+                remaining -= 1;
+                let next_offset = u16::from(self.read_u8(offset + 1) & !0b11);
+
+                (remaining > 0 && next_offset != 0).then_some(next_offset)
+            },
+        )
+        .map(|offset| Capability {
+            id: self.read_u8(offset),
+            offset,
+        })
+    }
+}
@@ -0,0 +1,154 @@
+use super::{PciAddress, tree::Device};
+use crate::sync::SpinMutex;
+use alloc::{collections::BTreeMap, vec::Vec};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("a driver named {0:?} is already registered")]
+    AlreadyRegistered(&'static str),
+
+    #[error("no device was enumerated at PCI {0}")]
+    NoSuchDevice(PciAddress),
+
+    #[error("PCI {0} is already bound to {1:?}")]
+    AlreadyBound(PciAddress, &'static str),
+}
+
+/// Criterion by which a driver claims devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Match {
+    /// Matches a specific vendor:device pair.
+    Id { vendor_id: u16, device_id: u16 },
+    /// Matches a class & subclass, and optionally a programming interface.
+    Class {
+        class: u8,
+        subclass: u8,
+        prog_if: Option<u8>,
+    },
+}
+
+impl Match {
+    pub fn matches(&self, device: &Device) -> bool {
+        match *self {
+            Self::Id {
+                vendor_id,
+                device_id,
+            } => device.vendor_id() == vendor_id && device.device_id() == device_id,
+
+            Self::Class {
+                class,
+                subclass,
+                prog_if,
+            } => {
+                let class_code = device.class();
+
+                class_code.class == class
+                    && class_code.subclass == subclass
+                    && prog_if.is_none_or(|prog_if| class_code.prog_if == prog_if)
+            }
+        }
+    }
+}
+
+/// A driver which claims PCI devices.
+#[derive(Debug)]
+pub struct Driver {
+    pub name: &'static str,
+    /// Devices the driver is able to claim.
+    pub matches: &'static [Match],
+    /// Initializes a matching device, returning whether the driver claimed it.
+    ///
+    /// # Remarks
+    ///
+    /// The driver registry is locked for the duration of the probe, so it must not register drivers.
+    pub probe: fn(&'static Device) -> bool,
+}
+
+struct Registry {
+    drivers: Vec<&'static Driver>,
+    /// Name of the driver bound to each claimed device.
+    bindings: BTreeMap<PciAddress, &'static str>,
+}
+
+impl Registry {
+    fn try_bind(&mut self, driver: &'static Driver, device: &'static Device) {
+        if self.bindings.contains_key(&device.address())
+            || !driver
+                .matches
+                .iter()
+                .any(|criterion| criterion.matches(device))
+        {
+            return;
+        }
+
+        if (driver.probe)(device) {
+            info!("PCI {} bound to driver {:?}", device.address(), driver.name);
+
+            self.bindings.insert(device.address(), driver.name);
+        }
+    }
+}
+
+static REGISTRY: SpinMutex<Registry> = SpinMutex::new(Registry {
+    drivers: Vec::new(),
+    bindings: BTreeMap::new(),
+});
+
+/// Registers `driver`, and binds it to any already-enumerated unclaimed devices it matches.
+pub fn register(driver: &'static Driver) -> Result<(), Error> {
+    let mut registry = REGISTRY.lock();
+
+    if registry
+        .drivers
+        .iter()
+        .any(|registered| registered.name == driver.name)
+    {
+        return Err(Error::AlreadyRegistered(driver.name));
+    }
+
+    registry.drivers.push(driver);
+    super::tree::for_each_device(|device| registry.try_bind(driver, device));
+
+    Ok(())
+}
+
+/// Binds every registered driver to the unclaimed devices it matches.
+pub fn bind_all() {
+    let mut registry = REGISTRY.lock();
+    let drivers = registry.drivers.clone();
+
+    super::tree::for_each_device(|device| {
+        for driver in &drivers {
+            registry.try_bind(driver, device);
+        }
+    });
+}
+
+/// Binds the device at `address` to `name` without probing it, so no driver claims it, e.g. for a userspace driver.
+pub fn claim(address: PciAddress, name: &'static str) -> Result<&'static Device, Error> {
+    let mut registry = REGISTRY.lock();
+    let device = super::tree::find(address).ok_or(Error::NoSuchDevice(address))?;
+
+    if let Some(bound) = registry.bindings.get(&address) {
+        return Err(Error::AlreadyBound(address, bound));
+    }
+
+    info!("PCI {address} claimed by {name:?}");
+    registry.bindings.insert(address, name);
+
+    Ok(device)
+}
+
+/// Unbinds the device at `address`, which was claimed by [`claim`].
+///
+/// # Remarks
+///
+/// The device isn't offered to the registered drivers again until the next [`bind_all`].
+pub fn release(address: PciAddress) {
+    REGISTRY.lock().bindings.remove(&address);
+}
+
+/// Name of the driver bound to the device at `address`, if any.
+pub fn bound_driver(address: PciAddress) -> Option<&'static str> {
+    REGISTRY.lock().bindings.get(&address).copied()
+}
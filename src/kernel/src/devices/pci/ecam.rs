@@ -0,0 +1,60 @@
+use super::PciAddress;
+use crate::acpi::mcfg::EcamRegion;
+use alloc::vec::Vec;
+use core::ptr::NonNull;
+use libsys::{Address, Frame};
+use spin::Once;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no ECAM region provides the configuration space for {0}")]
+    NoRegion(PciAddress),
+
+    #[error("configuration space address is invalid: {0:#X}")]
+    InvalidAddress(u64),
+
+    #[error("failed to map the configuration space")]
+    Paging(#[from] crate::mem::paging::Error),
+}
+
+/// Size of a single function's memory-mapped configuration space.
+pub const FUNCTION_CONFIG_SIZE: usize = 0x1000;
+
+static REGIONS: Once<Vec<EcamRegion>> = Once::new();
+
+/// Records the ECAM regions described by the MCFG, for later configuration space access.
+pub fn init() {
+    REGIONS.call_once(|| {
+        crate::acpi::mcfg::ecam_regions().unwrap_or_else(|error| {
+            warn!("PCIe ECAM is unavailable: {error}");
+
+            Vec::new()
+        })
+    });
+}
+
+/// ECAM regions available for configuration space access.
+pub fn regions() -> &'static [EcamRegion] {
+    REGIONS.get().map_or(&[], Vec::as_slice)
+}
+
+/// Maps the configuration space for the function at `address`.
+pub fn map(address: PciAddress) -> Result<NonNull<u32>, Error> {
+    let region = regions()
+        .iter()
+        .find(|region| region.contains(address.segment(), address.bus()))
+        .ok_or(Error::NoRegion(address))?;
+
+    let offset = (u64::from(address.bus() - region.bus_start) << 20)
+        | (u64::from(address.device()) << 15)
+        | (u64::from(address.function()) << 12);
+    let physical_address = region.base_address + offset;
+
+    let frame = usize::try_from(physical_address)
+        .ok()
+        .and_then(Address::<Frame>::new)
+        .ok_or(Error::InvalidAddress(physical_address))?;
+    let ptr = crate::mem::map_mmio(frame, FUNCTION_CONFIG_SIZE)?;
+
+    Ok(ptr.cast::<u32>())
+}
@@ -0,0 +1,65 @@
+//! Configuration space access via the legacy `0xCF8`/`0xCFC` I/O port mechanism.
+
+use super::PciAddress;
+use crate::sync::SpinMutex;
+use ioports::{ReadOnlyPort, WriteOnlyPort};
+use spin::Lazy;
+
+const ADDRESS_PORT: u16 = 0xCF8;
+const DATA_PORT: u16 = 0xCFC;
+
+/// Size of a single function's configuration space, as reachable through the legacy mechanism.
+pub const FUNCTION_CONFIG_SIZE: usize = 0x100;
+
+struct Ports {
+    address: WriteOnlyPort<u32>,
+    data_read: ReadOnlyPort<u32>,
+    data_write: WriteOnlyPort<u32>,
+}
+
+// Safety: Ports are only accessed behind a lock.
+unsafe impl Send for Ports {}
+
+static PORTS: Lazy<SpinMutex<Ports>> = Lazy::new(|| {
+    // Safety: The configuration address & data ports are fixed by the platform.
+    SpinMutex::new(unsafe {
+        Ports {
+            address: WriteOnlyPort::new(ADDRESS_PORT),
+            data_read: ReadOnlyPort::new(DATA_PORT),
+            data_write: WriteOnlyPort::new(DATA_PORT),
+        }
+    })
+});
+
+/// Whether the legacy mechanism can reach `address`.
+///
+/// # Remarks
+///
+/// Only segment group 0 is reachable.
+pub fn can_access(address: PciAddress) -> bool {
+    address.segment() == 0
+}
+
+fn config_address(address: PciAddress, offset: u16) -> u32 {
+    debug_assert!(usize::from(offset) < FUNCTION_CONFIG_SIZE);
+
+    (1 << 31)
+        | (u32::from(address.bus()) << 16)
+        | (u32::from(address.device()) << 11)
+        | (u32::from(address.function()) << 8)
+        | u32::from(offset & 0xFC)
+}
+
+/// Reads the dword containing `offset` from the configuration space of `address`.
+pub fn read_u32(address: PciAddress, offset: u16) -> u32 {
+    let mut ports = PORTS.lock();
+    ports.address.write(config_address(address, offset));
+    ports.data_read.read()
+}
+
+/// Writes the dword containing `offset` to the configuration space of `address`.
+pub fn write_u32(address: PciAddress, offset: u16, value: u32) {
+    let mut ports = PORTS.lock();
+    ports.address.write(config_address(address, offset));
+    ports.data_write.write(value);
+}
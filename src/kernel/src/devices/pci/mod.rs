@@ -0,0 +1,70 @@
+pub mod config;
+pub mod driver;
+pub mod ecam;
+mod legacy;
+pub mod msix;
+pub mod tree;
+
+/// Enumerates the PCI device tree, and binds any registered drivers to it.
+pub fn init() {
+    tree::enumerate();
+    driver::bind_all();
+}
+
+/// Location of a PCI function, in segment:bus:device.function form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PciAddress {
+    segment: u16,
+    bus: u8,
+    device: u8,
+    function: u8,
+}
+
+impl PciAddress {
+    pub const MAX_DEVICES: u8 = 32;
+    pub const MAX_FUNCTIONS: u8 = 8;
+
+    /// Constructs a new [`PciAddress`], or `None` if the device or function is out of range.
+    pub const fn new(segment: u16, bus: u8, device: u8, function: u8) -> Option<Self> {
+        if device < Self::MAX_DEVICES && function < Self::MAX_FUNCTIONS {
+            Some(Self {
+                segment,
+                bus,
+                device,
+                function,
+            })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub const fn segment(self) -> u16 {
+        self.segment
+    }
+
+    #[inline]
+    pub const fn bus(self) -> u8 {
+        self.bus
+    }
+
+    #[inline]
+    pub const fn device(self) -> u8 {
+        self.device
+    }
+
+    #[inline]
+    pub const fn function(self) -> u8 {
+        self.function
+    }
+}
+
+impl core::fmt::Display for PciAddress {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:04X}:{:02X}:{:02X}.{}",
+            self.segment, self.bus, self.device, self.function
+        )
+    }
+}
@@ -0,0 +1,176 @@
+//! MSI-X: message-signalled interrupts, which a function raises by writing to a local APIC, through a table of
+//! independently-targeted (and maskable) entries in one of its memory BARs.
+
+use super::{
+    PciAddress,
+    config::{Bar, ConfigSpace},
+    tree::Device,
+};
+use core::ptr::NonNull;
+use libsys::{Address, Frame, page_size};
+
+/// Capability ID of the MSI-X capability.
+const CAPABILITY_ID: u8 = 0x11;
+
+/// Message control: MSI-X is enabled for the function.
+const CONTROL_ENABLE: u16 = 1 << 15;
+/// Message control: every entry is masked, regardless of its own mask.
+const CONTROL_FUNCTION_MASK: u16 = 1 << 14;
+/// Message control: table size, encoded as N-1.
+const CONTROL_TABLE_SIZE: u16 = 0x7FF;
+
+/// Length of a table entry, in bytes.
+const ENTRY_LEN: usize = 16;
+/// Entry vector control: the entry is masked.
+const ENTRY_MASKED: u32 = 1 << 0;
+
+/// Base of the physical address range which, when written, interrupts the local APIC named in bits 12..20.
+const MESSAGE_ADDRESS_BASE: u32 = 0xFEE0_0000;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to access the PCI configuration space: {0}")]
+    Config(#[from] super::config::Error),
+
+    #[error("function does not provide the MSI-X capability")]
+    Unsupported,
+
+    #[error("MSI-X table lies in an unusable BAR: {0}")]
+    UnusableBar(u8),
+
+    #[error("failed to map the MSI-X table")]
+    Paging(#[from] crate::mem::paging::Error),
+
+    #[error("MSI-X table has no entry {0}")]
+    NoSuchEntry(u16),
+
+    #[error("local APIC {0} can't be targeted by a message")]
+    InvalidDestination(u32),
+}
+
+/// The MSI-X capability & table of a single function.
+pub struct MsiX {
+    address: PciAddress,
+    /// Offset of the capability within the configuration space.
+    capability: u16,
+    table: NonNull<u8>,
+    table_size: u16,
+}
+
+// Safety: The table is mapped in the kernel address space, which is shared by all hardware threads.
+unsafe impl Send for MsiX {}
+
+impl MsiX {
+    /// Locates & maps the MSI-X table of `device`, leaving MSI-X disabled.
+    pub fn new(device: &Device) -> Result<Self, Error> {
+        let config = device.config()?;
+        let capability = device
+            .capabilities()
+            .iter()
+            .find(|capability| capability.id == CAPABILITY_ID)
+            .ok_or(Error::Unsupported)?
+            .offset;
+
+        let control = config.read_u16(capability + 2);
+        let table_size = (control & CONTROL_TABLE_SIZE) + 1;
+        let table_location = config.read_u32(capability + 4);
+        let bar_index = u8::try_from(table_location & 0b111).unwrap();
+        let table_offset = usize::try_from(table_location & !0b111).unwrap();
+
+        let bar_address = device
+            .bars()
+            .iter()
+            .find(|decoded_bar| decoded_bar.index == bar_index)
+            .and_then(|decoded_bar| match decoded_bar.bar {
+                Bar::Memory32 { address, .. } => usize::try_from(address).ok(),
+                Bar::Memory64 { address, .. } => usize::try_from(address).ok(),
+                Bar::Io { .. } => None,
+            })
+            .ok_or(Error::UnusableBar(bar_index))?;
+
+        let address = bar_address + table_offset;
+        let page_offset = address & (page_size() - 1);
+        let frame =
+            Address::<Frame>::new(address - page_offset).ok_or(Error::UnusableBar(bar_index))?;
+        let mapped =
+            crate::mem::map_mmio(frame, page_offset + (usize::from(table_size) * ENTRY_LEN))?;
+
+        Ok(Self {
+            address: device.address(),
+            capability,
+            // Safety: The mapping covers `page_offset` bytes, followed by the table.
+            table: unsafe { mapped.byte_add(page_offset) },
+            table_size,
+        })
+    }
+
+    /// Number of entries in the table.
+    #[inline]
+    pub const fn table_size(&self) -> u16 {
+        self.table_size
+    }
+
+    /// Pointer to the `field`th 32-bit field of the table entry `entry`.
+    fn entry_field(&self, entry: u16, field: usize) -> Result<NonNull<u32>, Error> {
+        if entry >= self.table_size {
+            return Err(Error::NoSuchEntry(entry));
+        }
+
+        // Safety: The entry is within the mapped table, and each entry is four 32-bit fields.
+        Ok(unsafe {
+            self.table
+                .byte_add(usize::from(entry) * ENTRY_LEN)
+                .cast::<u32>()
+                .add(field)
+        })
+    }
+
+    /// Targets the table entry `entry` at `vector` of the local APIC `apic_id`, and unmasks it.
+    pub fn route(&self, entry: u16, vector: u8, apic_id: u32) -> Result<(), Error> {
+        let destination = u8::try_from(apic_id).map_err(|_| Error::InvalidDestination(apic_id))?;
+
+        self.set_masked(entry, true)?;
+
+        // Safety: Fields are within the entry, and it's masked while it's retargeted.
+        unsafe {
+            // Destination mode (physical) and redirection hint are both zero.
+            self.entry_field(entry, 0)?
+                .write_volatile(MESSAGE_ADDRESS_BASE | (u32::from(destination) << 12));
+            self.entry_field(entry, 1)?.write_volatile(0);
+            // Delivery mode (fixed) and trigger mode (edge) are both zero.
+            self.entry_field(entry, 2)?
+                .write_volatile(u32::from(vector));
+        }
+
+        self.set_masked(entry, false)
+    }
+
+    /// Masks or unmasks the table entry `entry`.
+    pub fn set_masked(&self, entry: u16, masked: bool) -> Result<(), Error> {
+        let vector_control = self.entry_field(entry, 3)?;
+
+        // Safety: Vector control is the entry's fourth field.
+        unsafe {
+            let value = vector_control.read_volatile();
+            vector_control.write_volatile(if masked {
+                value | ENTRY_MASKED
+            } else {
+                value & !ENTRY_MASKED
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Enables MSI-X for the function, which also disables its legacy interrupt pin.
+    pub fn enable(&self) -> Result<(), Error> {
+        let mut config = ConfigSpace::new(self.address)?;
+        let control = config.read_u16(self.capability + 2);
+        config.write_u16(
+            self.capability + 2,
+            (control | CONTROL_ENABLE) & !CONTROL_FUNCTION_MASK,
+        );
+
+        Ok(())
+    }
+}
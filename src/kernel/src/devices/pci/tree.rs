@@ -0,0 +1,287 @@
+use super::{
+    PciAddress,
+    config::{Bar, Capability, ConfigSpace, HeaderKind, InterruptPin},
+    ecam,
+};
+use alloc::{collections::BTreeSet, vec::Vec};
+use spin::Once;
+
+/// Class, subclass, and programming interface of a function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClassCode {
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+}
+
+impl ClassCode {
+    const BRIDGE: u8 = 0x06;
+    const HOST_BRIDGE: u8 = 0x00;
+
+    fn read(config: &ConfigSpace) -> Self {
+        let (class, subclass, prog_if) = config.class();
+
+        Self {
+            class,
+            subclass,
+            prog_if,
+        }
+    }
+
+    fn is_host_bridge(self) -> bool {
+        self.class == Self::BRIDGE && self.subclass == Self::HOST_BRIDGE
+    }
+}
+
+/// A base address register, along with the size of the region it decodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedBar {
+    pub index: u8,
+    pub bar: Bar,
+    pub size: u64,
+}
+
+/// A PCI function discovered during enumeration.
+#[derive(Debug)]
+pub struct Device {
+    address: PciAddress,
+    vendor_id: u16,
+    device_id: u16,
+    class: ClassCode,
+    header_kind: HeaderKind,
+    bars: Vec<DecodedBar>,
+    interrupt_pin: Option<InterruptPin>,
+    interrupt_line: Option<u8>,
+    capabilities: Vec<Capability>,
+    /// Functions on the bus downstream of this function, if it is a bridge.
+    children: Vec<Device>,
+}
+
+impl Device {
+    fn probe(mut config: ConfigSpace, visited: &mut BTreeSet<(u16, u8)>) -> Self {
+        let mut bars = Vec::new();
+        let mut index = 0;
+        while index < config.bar_count() {
+            let Some(bar) = config.bar(index) else {
+                index += 1;
+                continue;
+            };
+
+            if let Some(size) = config.bar_size(index) {
+                bars.push(DecodedBar { index, bar, size });
+            }
+
+            // 64-bit BARs consume the following register as their upper half.
+            index += if matches!(bar, Bar::Memory64 { .. }) {
+                2
+            } else {
+                1
+            };
+        }
+
+        let address = config.address();
+        let children = config
+            .secondary_bus()
+            .map(|bus| scan_bus(address.segment(), bus, visited))
+            .unwrap_or_default();
+
+        Self {
+            address,
+            vendor_id: config.vendor_id(),
+            device_id: config.device_id(),
+            class: ClassCode::read(&config),
+            header_kind: config.header_kind(),
+            bars,
+            interrupt_pin: config.interrupt_pin(),
+            interrupt_line: config.interrupt_line(),
+            capabilities: config.capabilities().collect(),
+            children,
+        }
+    }
+
+    #[inline]
+    pub const fn address(&self) -> PciAddress {
+        self.address
+    }
+
+    #[inline]
+    pub const fn vendor_id(&self) -> u16 {
+        self.vendor_id
+    }
+
+    #[inline]
+    pub const fn device_id(&self) -> u16 {
+        self.device_id
+    }
+
+    #[inline]
+    pub const fn class(&self) -> ClassCode {
+        self.class
+    }
+
+    #[inline]
+    pub const fn header_kind(&self) -> HeaderKind {
+        self.header_kind
+    }
+
+    #[inline]
+    pub fn bars(&self) -> &[DecodedBar] {
+        &self.bars
+    }
+
+    #[inline]
+    pub const fn interrupt_pin(&self) -> Option<InterruptPin> {
+        self.interrupt_pin
+    }
+
+    #[inline]
+    pub const fn interrupt_line(&self) -> Option<u8> {
+        self.interrupt_line
+    }
+
+    #[inline]
+    pub fn capabilities(&self) -> &[Capability] {
+        &self.capabilities
+    }
+
+    #[inline]
+    pub fn children(&self) -> &[Device] {
+        &self.children
+    }
+
+    /// Re-opens the configuration space of this function.
+    pub fn config(&self) -> Result<ConfigSpace, super::config::Error> {
+        ConfigSpace::new(self.address)
+    }
+}
+
+fn open_present(segment: u16, bus: u8, device: u8, function: u8) -> Option<ConfigSpace> {
+    let address = PciAddress::new(segment, bus, device, function)?;
+
+    match ConfigSpace::new(address) {
+        Ok(config) => Some(config).filter(ConfigSpace::is_present),
+        Err(error) => {
+            trace!("Skipping {address}: {error}");
+
+            None
+        }
+    }
+}
+
+fn scan_bus(segment: u16, bus: u8, visited: &mut BTreeSet<(u16, u8)>) -> Vec<Device> {
+    // A misconfigured bridge could point back to an already-scanned bus, so guard against revisiting.
+    if !visited.insert((segment, bus)) {
+        warn!("PCI bus {segment:04X}:{bus:02X} was reached more than once; skipping.");
+        return Vec::new();
+    }
+
+    let mut devices = Vec::new();
+    for device in 0..PciAddress::MAX_DEVICES {
+        let Some(config) = open_present(segment, bus, device, 0) else {
+            continue;
+        };
+
+        let function_count = if config.is_multifunction() {
+            PciAddress::MAX_FUNCTIONS
+        } else {
+            1
+        };
+
+        devices.push(Device::probe(config, visited));
+        for function in 1..function_count {
+            if let Some(config) = open_present(segment, bus, device, function) {
+                devices.push(Device::probe(config, visited));
+            }
+        }
+    }
+
+    devices
+}
+
+/// Root buses of each segment group, as `(segment, bus)` pairs.
+fn root_buses() -> Vec<(u16, u8)> {
+    let mut roots = ecam::regions()
+        .iter()
+        .map(|region| (region.segment_group, region.bus_start))
+        .collect::<Vec<_>>();
+
+    if roots.is_empty() {
+        roots.push((0, 0));
+    }
+
+    // A multi-function host bridge has one host controller per function, each responsible for the bus
+    // numbered by its function.
+    roots
+        .into_iter()
+        .flat_map(|(segment, bus)| {
+            let host_functions = open_present(segment, bus, 0, 0)
+                .filter(ConfigSpace::is_multifunction)
+                .map_or(1, |_| PciAddress::MAX_FUNCTIONS);
+
+            (0..host_functions).filter_map(move |function| {
+                let config = open_present(segment, bus, 0, function)?;
+                if function > 0 && !ClassCode::read(&config).is_host_bridge() {
+                    return None;
+                }
+
+                Some((segment, bus.checked_add(function)?))
+            })
+        })
+        .collect()
+}
+
+static TREE: Once<Vec<Device>> = Once::new();
+
+/// Enumerates every reachable PCI function, building the device tree.
+pub fn enumerate() {
+    TREE.call_once(|| {
+        let mut visited = BTreeSet::new();
+        let tree = root_buses()
+            .into_iter()
+            .flat_map(|(segment, bus)| scan_bus(segment, bus, &mut visited))
+            .collect::<Vec<_>>();
+
+        for_each_in(&tree, &mut |device| {
+            debug!(
+                "PCI {} {:04X}:{:04X} class {:02X}:{:02X}:{:02X}",
+                device.address(),
+                device.vendor_id(),
+                device.device_id(),
+                device.class.class,
+                device.class.subclass,
+                device.class.prog_if
+            );
+        });
+
+        tree
+    });
+}
+
+/// Root devices of the device tree, or an empty slice if enumeration has not occurred.
+pub fn roots() -> &'static [Device] {
+    TREE.get().map_or(&[], Vec::as_slice)
+}
+
+fn for_each_in<'a>(devices: &'a [Device], func: &mut impl FnMut(&'a Device)) {
+    for device in devices {
+        func(device);
+        for_each_in(&device.children, func);
+    }
+}
+
+/// Invokes `func` on every device in the device tree, parents before children.
+pub fn for_each_device(mut func: impl FnMut(&'static Device)) {
+    for_each_in(roots(), &mut func);
+}
+
+/// Device at `address` in the device tree, if one was enumerated.
+pub fn find(address: PciAddress) -> Option<&'static Device> {
+    let mut found = None;
+    for_each_device(|device| {
+        if device.address() == address {
+            found = Some(device);
+        }
+    });
+
+    found
+}
@@ -0,0 +1,368 @@
+//! USB devices, as enumerated by the host controller drivers.
+//!
+//! Host controller drivers address each device attached to their root ports, then hand its default control pipe to
+//! [`enumerate`], which reads its device, configuration, and string descriptors, and [`attach`]es it. No class drivers
+//! exist yet, so devices aren't configured beyond being addressed.
+
+pub mod xhci;
+
+use crate::sync::SpinMutex;
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+use core::fmt;
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("device stalled the request")]
+    Stall,
+
+    #[error("request timed out")]
+    Timeout,
+
+    #[error("transfer failed with completion code {0}")]
+    Transfer(u8),
+
+    #[error("transfer of {0} bytes is longer than the pipe's buffer")]
+    TooLong(usize),
+
+    #[error("device returned a malformed descriptor")]
+    Malformed,
+}
+
+/// Speed a device is operating at, as reported by its root port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Speed {
+    Low,
+    Full,
+    High,
+    Super,
+    SuperPlus,
+}
+
+impl Speed {
+    /// Largest packet the default control pipe can be assumed to carry before the device descriptor is read.
+    pub const fn default_max_packet_size(self) -> u16 {
+        match self {
+            Self::Low | Self::Full => 8,
+            Self::High => 64,
+            Self::Super | Self::SuperPlus => 512,
+        }
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Low => "low-speed",
+            Self::Full => "full-speed",
+            Self::High => "high-speed",
+            Self::Super => "SuperSpeed",
+            Self::SuperPlus => "SuperSpeedPlus",
+        })
+    }
+}
+
+/// Descriptor types.
+pub const DESCRIPTOR_DEVICE: u8 = 0x01;
+pub const DESCRIPTOR_CONFIGURATION: u8 = 0x02;
+pub const DESCRIPTOR_STRING: u8 = 0x03;
+pub const DESCRIPTOR_INTERFACE: u8 = 0x04;
+
+/// Standard request: read a descriptor.
+const REQUEST_GET_DESCRIPTOR: u8 = 0x06;
+/// Request type: device-to-host, standard, addressed to the device.
+const REQUEST_TYPE_STANDARD_IN: u8 = 0x80;
+
+/// Language ID string descriptors are requested in: English (United States).
+const LANGUAGE_EN_US: u16 = 0x0409;
+
+/// Length of the device descriptor, and of a configuration descriptor's header.
+pub const DEVICE_DESCRIPTOR_LEN: usize = 18;
+const CONFIGURATION_HEADER_LEN: usize = 9;
+
+/// The setup stage of a control transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetupPacket {
+    pub request_type: u8,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    pub length: u16,
+}
+
+impl SetupPacket {
+    /// Request for the first `length` bytes of the descriptor `kind` at `index`.
+    pub fn get_descriptor(kind: u8, index: u8, language: u16, length: u16) -> Self {
+        Self {
+            request_type: REQUEST_TYPE_STANDARD_IN,
+            request: REQUEST_GET_DESCRIPTOR,
+            value: (u16::from(kind) << 8) | u16::from(index),
+            index: language,
+            length,
+        }
+    }
+
+    /// Whether the data stage transfers from the device to the host.
+    pub const fn is_in(&self) -> bool {
+        (self.request_type & 0x80) != 0
+    }
+
+    /// The packet as it's sent on the bus.
+    pub fn to_bytes(&self) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0] = self.request_type;
+        bytes[1] = self.request;
+        bytes[2..4].copy_from_slice(&self.value.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.index.to_le_bytes());
+        bytes[6..8].copy_from_slice(&self.length.to_le_bytes());
+
+        bytes
+    }
+}
+
+/// A device's default control pipe, through which it's enumerated.
+pub trait ControlPipe: Send + Sync {
+    /// Performs the device-to-host control transfer `setup`, reading its data stage into `buf`, which is
+    /// `setup.length` bytes long.
+    fn control_in(&self, setup: &SetupPacket, buf: &mut [u8]) -> Result<(), Error>;
+}
+
+/// The standard device descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceDescriptor {
+    /// USB specification release, in binary-coded decimal.
+    pub usb_version: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub protocol: u8,
+    pub max_packet_size: u8,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub manufacturer_index: u8,
+    pub product_index: u8,
+    pub configuration_count: u8,
+}
+
+impl DeviceDescriptor {
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.first_chunk::<DEVICE_DESCRIPTOR_LEN>()?;
+        if usize::from(bytes[0]) < DEVICE_DESCRIPTOR_LEN || bytes[1] != DESCRIPTOR_DEVICE {
+            return None;
+        }
+
+        let word = |index: usize| u16::from_le_bytes([bytes[index], bytes[index + 1]]);
+
+        Some(Self {
+            usb_version: word(2),
+            class: bytes[4],
+            subclass: bytes[5],
+            protocol: bytes[6],
+            max_packet_size: bytes[7],
+            vendor_id: word(8),
+            product_id: word(10),
+            manufacturer_index: bytes[14],
+            product_index: bytes[15],
+            configuration_count: bytes[17],
+        })
+    }
+}
+
+/// Class, subclass, and protocol of an interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceClass {
+    pub class: u8,
+    pub subclass: u8,
+    pub protocol: u8,
+}
+
+/// Interfaces described by the configuration descriptor (and its trailing descriptors) `configuration`.
+pub fn interfaces(configuration: &[u8]) -> Vec<InterfaceClass> {
+    let mut interfaces = Vec::new();
+    let mut remaining = configuration;
+    while let [len, kind, ..] = *remaining {
+        let len = usize::from(len);
+        if len < 2 || len > remaining.len() {
+            break;
+        }
+
+        if kind == DESCRIPTOR_INTERFACE
+            && let Some(descriptor) = remaining.get(..9)
+        {
+            interfaces.push(InterfaceClass {
+                class: descriptor[5],
+                subclass: descriptor[6],
+                protocol: descriptor[7],
+            });
+        }
+
+        remaining = &remaining[len..];
+    }
+
+    interfaces
+}
+
+/// Decodes the UTF-16 text of the string descriptor `descriptor`.
+fn decode_string(descriptor: &[u8]) -> Option<String> {
+    let [len, DESCRIPTOR_STRING, ..] = *descriptor else {
+        return None;
+    };
+    let text = descriptor.get(2..usize::from(len))?;
+
+    Some(
+        char::decode_utf16(
+            text.chunks_exact(2)
+                .map(|unit| u16::from_le_bytes([unit[0], unit[1]])),
+        )
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect(),
+    )
+}
+
+/// An enumerated device.
+pub struct Device {
+    pub name: String,
+    /// Root port the device is attached to, and its speed.
+    pub port: u8,
+    pub speed: Speed,
+    pub descriptor: DeviceDescriptor,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    /// Interfaces of the device's first configuration.
+    pub interfaces: Vec<InterfaceClass>,
+    pub pipe: Arc<dyn ControlPipe>,
+}
+
+impl Device {
+    /// Reads the string descriptor `index`, if the device has it.
+    pub fn string(&self, index: u8) -> Option<String> {
+        read_string(self.pipe.as_ref(), index)
+    }
+}
+
+fn read_string(pipe: &dyn ControlPipe, index: u8) -> Option<String> {
+    if index == 0 {
+        return None;
+    }
+
+    let mut header = [0; 2];
+    let setup = SetupPacket::get_descriptor(DESCRIPTOR_STRING, index, LANGUAGE_EN_US, 2);
+    pipe.control_in(&setup, &mut header).ok()?;
+
+    let mut descriptor = vec![0; usize::from(header[0])];
+    let setup = SetupPacket::get_descriptor(
+        DESCRIPTOR_STRING,
+        index,
+        LANGUAGE_EN_US,
+        u16::from(header[0]),
+    );
+    pipe.control_in(&setup, &mut descriptor).ok()?;
+
+    decode_string(&descriptor)
+}
+
+/// Reads the descriptors of the addressed device behind `pipe`, attached to `port` at `speed`.
+pub fn enumerate(
+    name: String,
+    port: u8,
+    speed: Speed,
+    pipe: Arc<dyn ControlPipe>,
+) -> Result<Device, Error> {
+    let mut bytes = [0; DEVICE_DESCRIPTOR_LEN];
+    let setup = SetupPacket::get_descriptor(
+        DESCRIPTOR_DEVICE,
+        0,
+        0,
+        u16::try_from(DEVICE_DESCRIPTOR_LEN).unwrap(),
+    );
+    pipe.control_in(&setup, &mut bytes)?;
+    let descriptor = DeviceDescriptor::parse(&bytes).ok_or(Error::Malformed)?;
+
+    let interfaces = if descriptor.configuration_count > 0 {
+        let mut header = [0; CONFIGURATION_HEADER_LEN];
+        let setup = SetupPacket::get_descriptor(
+            DESCRIPTOR_CONFIGURATION,
+            0,
+            0,
+            u16::try_from(CONFIGURATION_HEADER_LEN).unwrap(),
+        );
+        pipe.control_in(&setup, &mut header)?;
+
+        let total_len = u16::from_le_bytes([header[2], header[3]]);
+        let mut configuration = vec![0; usize::from(total_len)];
+        let setup = SetupPacket::get_descriptor(DESCRIPTOR_CONFIGURATION, 0, 0, total_len);
+        pipe.control_in(&setup, &mut configuration)?;
+
+        interfaces(&configuration)
+    } else {
+        Vec::new()
+    };
+
+    Ok(Device {
+        name,
+        port,
+        speed,
+        descriptor,
+        manufacturer: read_string(pipe.as_ref(), descriptor.manufacturer_index),
+        product: read_string(pipe.as_ref(), descriptor.product_index),
+        interfaces,
+        pipe,
+    })
+}
+
+static DEVICES: SpinMutex<Vec<Arc<Device>>> = SpinMutex::new(Vec::new());
+
+/// Adds the enumerated `device` to the devices list.
+pub fn attach(device: Arc<Device>) {
+    info!(
+        "USB device {} ({:04x}:{:04x}, {}): {} {}, {} interfaces.",
+        device.name,
+        device.descriptor.vendor_id,
+        device.descriptor.product_id,
+        device.speed,
+        device.manufacturer.as_deref().unwrap_or("unknown"),
+        device.product.as_deref().unwrap_or("device"),
+        device.interfaces.len()
+    );
+
+    crate::interrupts::uninterruptable(|| DEVICES.lock().push(device));
+}
+
+/// Every enumerated device, in order of enumeration.
+pub fn devices() -> Vec<Arc<Device>> {
+    crate::interrupts::uninterruptable(|| DEVICES.lock().clone())
+}
+
+crate::kernel_test! {
+    /// Device, configuration, and string descriptors are decoded from their wire format.
+    fn parses_usb_descriptors() -> crate::tests::Outcome {
+        let device = [
+            18, DESCRIPTOR_DEVICE, 0x00, 0x02, 0, 0, 0, 64, 0x27, 0x06, 0x01, 0x00, 0x00, 0x01,
+            1, 2, 3, 1,
+        ];
+        let descriptor = DeviceDescriptor::parse(&device).ok_or("device descriptor was rejected")?;
+        if descriptor.usb_version != 0x0200
+            || descriptor.max_packet_size != 64
+            || descriptor.vendor_id != 0x0627
+            || descriptor.product_id != 0x0001
+            || descriptor.product_index != 2
+        {
+            return Err("device descriptor was decoded incorrectly");
+        }
+
+        // A configuration with one HID interface (and its endpoint).
+        let configuration = [
+            9, DESCRIPTOR_CONFIGURATION, 25, 0, 1, 1, 0, 0xA0, 50,
+            9, DESCRIPTOR_INTERFACE, 0, 0, 1, 0x03, 0x01, 0x02, 0,
+            7, 0x05, 0x81, 0x03, 8, 0, 10,
+        ];
+        let expected = InterfaceClass { class: 0x03, subclass: 0x01, protocol: 0x02 };
+        if interfaces(&configuration) != [expected] {
+            return Err("interfaces were decoded incorrectly");
+        }
+
+        if decode_string(&[8, DESCRIPTOR_STRING, b'U', 0, b'S', 0, b'B', 0]).as_deref() != Some("USB") {
+            return Err("string descriptor was decoded incorrectly");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,961 @@
+//! xHCI (USB 3) host controller driver.
+//!
+//! Controllers are taken over from the firmware, reset, and given a device context base address array, a command
+//! ring, and a single interrupter's event ring. The event ring is routed an MSI-X vector (through
+//! [`crate::interrupts::irq`]) whose handler files each completion by the address of the TRB it completes; without
+//! MSI-X, the event ring is polled by whoever waits on a completion.
+//!
+//! Each root port with a device connected is reset, and the device is given a slot & addressed, then handed to
+//! [`crate::devices::usb::enumerate`] through its default control pipe. Devices connected later are only logged, as
+//! enumerating them would block in deferred work.
+//!
+//! Control transfers read their data stage into a page-sized buffer per slot, so at most a page is transferred at once.
+
+mod ring;
+
+use crate::{
+    devices::{
+        pci::{
+            PciAddress,
+            config::{Bar, Command as PciCommand},
+            driver::{Driver, Match},
+            msix::MsiX,
+            tree::Device,
+        },
+        poll_for,
+        usb::{self, ControlPipe, SetupPacket, Speed},
+    },
+    interrupts::{InterruptCell, irq::IrqTimestamp},
+    mem::HigherHalfDirectMap,
+    sync::SpinMutex,
+};
+use alloc::{collections::BTreeMap, format, string::String, sync::Arc, vec::Vec};
+use core::{
+    num::NonZero,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use libsys::{Address, Frame, page_size};
+use ring::{EventRing, Ring, Trb};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to access the PCI configuration space: {0}")]
+    Config(#[from] crate::devices::pci::config::Error),
+
+    #[error("controller registers lie in an unusable BAR")]
+    UnusableBar,
+
+    #[error("failed to map the controller's registers")]
+    Paging(#[from] crate::mem::paging::Error),
+
+    #[error("failed to allocate controller memory: {0}")]
+    Dma(#[from] crate::mem::dma::Error),
+
+    #[error("controller doesn't support {0}")]
+    Unsupported(&'static str),
+
+    #[error("controller timed out {0}")]
+    Timeout(&'static str),
+
+    #[error("command of TRB type {trb_type} failed with completion code {code}")]
+    Command { trb_type: u8, code: u8 },
+
+    #[error("port reported an unknown speed ({0})")]
+    UnknownSpeed(u8),
+
+    #[error("failed to enumerate the device: {0}")]
+    Usb(#[from] usb::Error),
+}
+
+/// Class, subclass, and programming interface of xHCI controllers.
+const CLASS_SERIAL_BUS: u8 = 0x0C;
+const SUBCLASS_USB: u8 = 0x03;
+const PROG_IF_XHCI: u8 = 0x30;
+
+/// Capability registers, at the start of BAR0.
+#[repr(usize)]
+#[derive(Debug, IntoPrimitive, Clone, Copy)]
+#[allow(non_camel_case_types)]
+#[rustfmt::skip]
+enum Capability {
+    CAPLENGTH   = 0x00,
+    HCSPARAMS1  = 0x04,
+    HCSPARAMS2  = 0x08,
+    HCCPARAMS1  = 0x10,
+    DBOFF       = 0x14,
+    RTSOFF      = 0x18,
+}
+
+/// Operational registers, after the capability registers.
+#[repr(usize)]
+#[derive(Debug, IntoPrimitive, Clone, Copy)]
+#[allow(non_camel_case_types)]
+#[rustfmt::skip]
+enum Operational {
+    USBCMD      = 0x00,
+    USBSTS      = 0x04,
+    PAGESIZE    = 0x08,
+    CRCR        = 0x18,
+    DCBAAP      = 0x30,
+    CONFIG      = 0x38,
+}
+
+/// Registers of the first interrupter, in the runtime registers.
+#[repr(usize)]
+#[derive(Debug, IntoPrimitive, Clone, Copy)]
+#[allow(non_camel_case_types)]
+#[rustfmt::skip]
+enum Interrupter {
+    IMAN        = 0x20,
+    IMOD        = 0x24,
+    ERSTSZ      = 0x28,
+    ERSTBA      = 0x30,
+    ERDP        = 0x38,
+}
+
+/// Offset of the first port's registers (from the operational registers), and the stride between ports.
+const PORT_BASE: usize = 0x400;
+const PORT_STRIDE: usize = 0x10;
+
+/// Capability parameters: 64-bit addressing, and 64-byte contexts.
+const HCC_64BIT: u32 = 1 << 0;
+const HCC_CONTEXT_64: u32 = 1 << 2;
+
+/// Command: run, reset, and interrupter enable.
+const USBCMD_RUN: u32 = 1 << 0;
+const USBCMD_RESET: u32 = 1 << 1;
+const USBCMD_INTERRUPTS: u32 = 1 << 2;
+
+/// Status: halted, event interrupt (write to clear), and controller not ready.
+const USBSTS_HALTED: u32 = 1 << 0;
+const USBSTS_EVENT_INTERRUPT: u32 = 1 << 3;
+const USBSTS_NOT_READY: u32 = 1 << 11;
+
+/// Command ring control: the ring cycle state.
+const CRCR_CYCLE: u64 = 1 << 0;
+
+/// Interrupter management: interrupt pending (write to clear), and interrupt enable.
+const IMAN_PENDING: u32 = 1 << 0;
+const IMAN_ENABLE: u32 = 1 << 1;
+/// Interrupt moderation interval, in 250ns units (1ms).
+const IMOD_INTERVAL: u32 = 4000;
+
+/// Port status & control: connected, enabled, reset, and the port's speed.
+const PORTSC_CONNECTED: u32 = 1 << 0;
+const PORTSC_ENABLED: u32 = 1 << 1;
+const PORTSC_RESET: u32 = 1 << 4;
+const PORTSC_SPEED_SHIFT: u32 = 10;
+/// Port status & control: reset change.
+const PORTSC_RESET_CHANGE: u32 = 1 << 21;
+/// Port status & control: every change bit, each written to clear.
+const PORTSC_CHANGES: u32 = 0x7F << 17;
+/// Port status & control: the read-write bits which are preserved by writes (port power, indicators, and wake
+/// enables); every other bit is written as zero, so changes aren't cleared & the port isn't disabled by accident.
+const PORTSC_PRESERVE: u32 = (1 << 9) | (0x3 << 14) | (0x7 << 25);
+
+/// Extended capability: USB legacy support, and its BIOS & OS ownership semaphores.
+const EXTENDED_LEGACY_SUPPORT: u32 = 1;
+const LEGACY_BIOS_OWNED: u32 = 1 << 16;
+const LEGACY_OS_OWNED: u32 = 1 << 24;
+
+/// Slot context: the speed & context entries fields, and the root hub port number.
+const SLOT_SPEED_SHIFT: u32 = 20;
+const SLOT_CONTEXT_ENTRIES_SHIFT: u32 = 27;
+const SLOT_ROOT_PORT_SHIFT: u32 = 16;
+/// Endpoint context: three retries on error, of a control endpoint.
+const ENDPOINT_ERROR_COUNT: u32 = 3 << 1;
+const ENDPOINT_TYPE_CONTROL: u32 = 4 << 3;
+const ENDPOINT_MAX_PACKET_SHIFT: u32 = 16;
+/// Endpoint context: average TRB length of a control endpoint.
+const ENDPOINT_CONTROL_AVERAGE_TRB: u32 = 8;
+/// Endpoint context: the dequeue cycle state.
+const ENDPOINT_DEQUEUE_CYCLE: u64 = 1 << 0;
+
+/// Input control context: add the slot context, and the default control endpoint's.
+const ADD_SLOT: u32 = 1 << 0;
+const ADD_ENDPOINT_0: u32 = 1 << 1;
+
+/// Device context index of the default control endpoint, which is its doorbell target.
+const ENDPOINT_0: u8 = 1;
+
+/// Setup stage: transfer type (an IN data stage).
+const SETUP_TRANSFER_IN: u32 = 3 << 16;
+/// Data & status stage: the stage transfers from the device.
+const STAGE_DIRECTION_IN: u32 = 1 << 16;
+
+/// Longest the controller may take to halt, reset, or complete a command, and a port to reset.
+const CONTROLLER_TIMEOUT: Duration = Duration::from_secs(1);
+const PORT_RESET_TIMEOUT: Duration = Duration::from_millis(500);
+/// Longest a control transfer may take to complete.
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Name controller memory is tagged with in the DMA audit.
+const DMA_OWNER: &str = "xhci";
+
+/// Frames of each slot: its output device context, its input context, and its control transfer buffer.
+const SLOT_FRAMES: usize = 3;
+
+static DRIVER: Driver = Driver {
+    name: "xhci",
+    matches: &[Match::Class {
+        class: CLASS_SERIAL_BUS,
+        subclass: SUBCLASS_USB,
+        prog_if: Some(PROG_IF_XHCI),
+    }],
+    probe,
+};
+
+/// Registers the xHCI driver.
+pub fn register() {
+    if let Err(error) = crate::devices::pci::driver::register(&DRIVER) {
+        warn!("Failed to register the xHCI driver: {error}");
+    }
+}
+
+/// Every controller the driver has brought up, searched by the event handler for its vector.
+static CONTROLLERS: SpinMutex<Vec<Arc<Controller>>> = SpinMutex::new(Vec::new());
+
+/// Index the next controller is named by.
+static NEXT_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+fn probe(device: &'static Device) -> bool {
+    match bring_up(device) {
+        Ok(controller) => {
+            controller.enumerate_ports();
+            true
+        }
+
+        Err(error) => {
+            warn!(
+                "Failed to initialize xHCI controller {}: {error}",
+                device.address()
+            );
+            false
+        }
+    }
+}
+
+/// Handles the event interrupt of a controller's interrupter.
+fn on_event(vector: u8, _: IrqTimestamp) {
+    if let Some(controller) = CONTROLLERS
+        .lock()
+        .iter()
+        .find(|controller| controller.vector == Some(vector))
+    {
+        controller
+            .registers
+            .write_interrupter(Interrupter::IMAN, IMAN_ENABLE | IMAN_PENDING);
+        controller
+            .registers
+            .write_op(Operational::USBSTS, USBSTS_EVENT_INTERRUPT);
+
+        controller.state.with(|state| state.lock().reap());
+    }
+}
+
+/// Speed of the protocol speed ID `id`, as the default speed IDs are assigned.
+fn speed(id: u8) -> Option<Speed> {
+    match id {
+        1 => Some(Speed::Full),
+        2 => Some(Speed::Low),
+        3 => Some(Speed::High),
+        4 => Some(Speed::Super),
+        5 => Some(Speed::SuperPlus),
+        _ => None,
+    }
+}
+
+/// The controller's memory-mapped registers.
+struct Registers {
+    operational: NonNull<u8>,
+    runtime: NonNull<u8>,
+    doorbells: NonNull<u32>,
+}
+
+// Safety: Registers are mapped in the kernel address space, and each access is a single volatile read or write.
+unsafe impl Send for Registers {}
+// Safety: Registers are mapped in the kernel address space, and each access is a single volatile read or write.
+unsafe impl Sync for Registers {}
+
+impl Registers {
+    fn read_op<T: Copy>(&self, register: Operational) -> T {
+        // Safety: Register offset is within the operational registers, and naturally aligned.
+        unsafe {
+            self.operational
+                .byte_add(usize::from(register))
+                .cast::<T>()
+                .read_volatile()
+        }
+    }
+
+    fn write_op<T: Copy>(&self, register: Operational, value: T) {
+        // Safety: Register offset is within the operational registers, and naturally aligned.
+        unsafe {
+            self.operational
+                .byte_add(usize::from(register))
+                .cast::<T>()
+                .write_volatile(value);
+        }
+    }
+
+    fn interrupter<T>(&self, register: Interrupter) -> NonNull<T> {
+        // Safety: Register offset is within the runtime registers.
+        unsafe { self.runtime.byte_add(usize::from(register)).cast() }
+    }
+
+    fn write_interrupter<T: Copy>(&self, register: Interrupter, value: T) {
+        // Safety: Register offset is within the runtime registers, and naturally aligned.
+        unsafe { self.interrupter::<T>(register).write_volatile(value) };
+    }
+
+    fn port_register(&self, port: u8) -> NonNull<u32> {
+        // Safety: Ports are numbered from one, and the mapping covers every port's registers.
+        unsafe {
+            self.operational
+                .byte_add(PORT_BASE + ((usize::from(port) - 1) * PORT_STRIDE))
+                .cast()
+        }
+    }
+
+    /// Status & control of the root port `port` (numbered from one).
+    fn port_status(&self, port: u8) -> u32 {
+        // Safety: Register lies within the port's registers.
+        unsafe { self.port_register(port).read_volatile() }
+    }
+
+    fn set_port_status(&self, port: u8, value: u32) {
+        // Safety: Register lies within the port's registers.
+        unsafe { self.port_register(port).write_volatile(value) };
+    }
+
+    /// Rings the doorbell of `slot` (or the command ring's, if it's zero) for `target`.
+    fn ring_doorbell(&self, slot: u8, target: u8) {
+        // Safety: The mapping covers the doorbell of every slot.
+        unsafe {
+            self.doorbells
+                .add(usize::from(slot))
+                .write_volatile(u32::from(target));
+        }
+    }
+
+    /// Waits for `func` to hold of the status register.
+    fn wait_status(
+        &self,
+        context: &'static str,
+        mut func: impl FnMut(u32) -> bool,
+    ) -> Result<(), Error> {
+        poll_for(CONTROLLER_TIMEOUT, || {
+            func(self.read_op(Operational::USBSTS)).then_some(())
+        })
+        .ok_or(Error::Timeout(context))
+    }
+}
+
+/// Command ring, event ring, and device context base address array, which are shared by the controller's slots.
+struct State {
+    commands: Ring,
+    events: EventRing,
+    dcbaa: NonNull<u64>,
+    /// Events completing commands & transfers, by the address of the TRB they complete, which are yet to be taken.
+    completions: BTreeMap<u64, Trb>,
+}
+
+// Safety: Ring & array memory are only accessed through the state's lock.
+unsafe impl Send for State {}
+
+impl State {
+    /// Files every event the controller has posted.
+    fn reap(&mut self) {
+        let mut reaped = false;
+        while let Some(event) = self.events.pop() {
+            reaped = true;
+
+            match event.trb_type() {
+                ring::TRB_COMMAND_COMPLETION | ring::TRB_TRANSFER_EVENT => {
+                    self.completions.insert(event.parameter, event);
+                }
+
+                ring::TRB_PORT_STATUS_CHANGE => {
+                    let port = event.parameter >> 24;
+                    debug!("xHCI port {port} changed status.");
+                }
+
+                trb_type => trace!("Ignoring xHCI event of TRB type {trb_type}."),
+            }
+        }
+
+        if reaped {
+            self.events.acknowledge();
+        }
+    }
+}
+
+struct Controller {
+    name: String,
+    address: PciAddress,
+    registers: Registers,
+    /// Size of each context in a device or input context.
+    context_size: usize,
+    port_count: u8,
+    /// Vector events are signalled on, or `None` if the event ring is polled.
+    vector: Option<u8>,
+    state: InterruptCell<SpinMutex<State>>,
+}
+
+impl Controller {
+    /// Waits for the event completing any of the TRBs at `addresses`, reaping events as they're posted.
+    fn wait_for(&self, addresses: &[u64], timeout: Duration) -> Option<Trb> {
+        poll_for(timeout, || {
+            self.state.with(|state| {
+                let mut state = state.lock();
+                state.reap();
+
+                addresses
+                    .iter()
+                    .find_map(|address| state.completions.remove(address))
+            })
+        })
+    }
+
+    /// Issues the command `trb`, and waits for it to complete successfully.
+    fn execute(&self, trb: Trb) -> Result<Trb, Error> {
+        let trb_type = trb.trb_type();
+        let address = self.state.with(|state| state.lock().commands.push(trb));
+        self.registers.ring_doorbell(0, 0);
+
+        let event = self
+            .wait_for(&[address], CONTROLLER_TIMEOUT)
+            .ok_or(Error::Timeout("executing a command"))?;
+        match event.completion_code() {
+            ring::COMPLETION_SUCCESS => Ok(event),
+            code => Err(Error::Command { trb_type, code }),
+        }
+    }
+
+    /// Resets the root port `port` if it has a device connected (and isn't already enabled, as SuperSpeed ports
+    /// are), returning the speed ID of the enabled port.
+    fn reset_port(&self, port: u8) -> Result<Option<u8>, Error> {
+        let status = self.registers.port_status(port);
+        if (status & PORTSC_CONNECTED) == 0 {
+            return Ok(None);
+        }
+
+        if (status & PORTSC_ENABLED) == 0 {
+            self.registers
+                .set_port_status(port, (status & PORTSC_PRESERVE) | PORTSC_RESET);
+            poll_for(PORT_RESET_TIMEOUT, || {
+                ((self.registers.port_status(port) & PORTSC_RESET_CHANGE) != 0).then_some(())
+            })
+            .ok_or(Error::Timeout("resetting a port"))?;
+        }
+
+        let status = self.registers.port_status(port);
+        self.registers
+            .set_port_status(port, (status & PORTSC_PRESERVE) | PORTSC_CHANGES);
+        if (status & PORTSC_ENABLED) == 0 {
+            return Err(Error::Timeout("enabling a port"));
+        }
+
+        Ok(Some(
+            u8::try_from((status >> PORTSC_SPEED_SHIFT) & 0xF).unwrap(),
+        ))
+    }
+
+    /// Resets, addresses, and enumerates the device on every connected root port.
+    fn enumerate_ports(self: &Arc<Self>) {
+        for port in 1..=self.port_count {
+            let enumerate = || -> Result<Option<usb::Device>, Error> {
+                let Some(speed_id) = self.reset_port(port)? else {
+                    return Ok(None);
+                };
+                let speed = speed(speed_id).ok_or(Error::UnknownSpeed(speed_id))?;
+                let slot = Slot::address(self, port, speed_id, speed)?;
+
+                Ok(Some(usb::enumerate(
+                    format!("{}-{port}", self.name),
+                    port,
+                    speed,
+                    Arc::new(slot),
+                )?))
+            };
+
+            match enumerate() {
+                Ok(Some(device)) => usb::attach(Arc::new(device)),
+                Ok(None) => {}
+                Err(error) => warn!("Failed to enumerate {} port {port}: {error}", self.name),
+            }
+        }
+    }
+}
+
+/// A device's slot: its contexts, and its default control pipe.
+struct Slot {
+    controller: Arc<Controller>,
+    id: u8,
+    frame: Address<Frame>,
+    memory: NonNull<u8>,
+    /// The default control endpoint's transfer ring, which is held locked for the whole of each transfer, as they
+    /// share the slot's buffer.
+    ring: SpinMutex<Ring>,
+}
+
+// Safety: Context & buffer memory are only accessed while the ring is locked, or before the slot is shared.
+unsafe impl Send for Slot {}
+// Safety: Context & buffer memory are only accessed while the ring is locked, or before the slot is shared.
+unsafe impl Sync for Slot {}
+
+impl Slot {
+    /// Enables a slot for the device on `port`, at the speed `speed_id`, and addresses it.
+    fn address(
+        controller: &Arc<Controller>,
+        port: u8,
+        speed_id: u8,
+        speed: Speed,
+    ) -> Result<Self, Error> {
+        let transfer_ring = Ring::new()?;
+        let id = controller
+            .execute(Trb::new(ring::TRB_ENABLE_SLOT, 0, 0, 0))?
+            .slot_id();
+
+        let frame = match crate::mem::dma::allocate(NonZero::new(SLOT_FRAMES).unwrap(), DMA_OWNER) {
+            Ok(frame) => frame,
+            Err(error) => {
+                let _ =
+                    controller.execute(Trb::new(ring::TRB_DISABLE_SLOT, 0, 0, u32::from(id) << 24));
+                return Err(error.into());
+            }
+        };
+        let memory = NonNull::new(HigherHalfDirectMap::frame_to_page(frame).as_ptr()).unwrap();
+        // Safety: The frames were just allocated, and are accessible through the HHDM.
+        unsafe { memory.write_bytes(0, SLOT_FRAMES * page_size()) };
+
+        // From here, dropping the slot disables it.
+        let slot = Self {
+            controller: controller.clone(),
+            id,
+            frame,
+            memory,
+            ring: SpinMutex::new(transfer_ring),
+        };
+
+        let output_context = slot.physical(0);
+        controller.state.with(|state| {
+            // Safety: The array holds an entry for every slot the controller enables.
+            unsafe {
+                state
+                    .lock()
+                    .dcbaa
+                    .add(usize::from(id))
+                    .write_volatile(output_context)
+            };
+        });
+
+        let max_packet_size = u32::from(speed.default_max_packet_size());
+        slot.write_input(0, 1, ADD_SLOT | ADD_ENDPOINT_0);
+        slot.write_input(
+            1,
+            0,
+            (u32::from(speed_id) << SLOT_SPEED_SHIFT) | (1 << SLOT_CONTEXT_ENTRIES_SHIFT),
+        );
+        slot.write_input(1, 1, u32::from(port) << SLOT_ROOT_PORT_SHIFT);
+        slot.write_endpoint_0(max_packet_size);
+        let dequeue = slot.ring.lock().physical() | ENDPOINT_DEQUEUE_CYCLE;
+        slot.write_input(2, 2, u32::try_from(dequeue & 0xFFFF_FFFF).unwrap());
+        slot.write_input(2, 3, u32::try_from(dequeue >> 32).unwrap());
+        slot.write_input(2, 4, ENDPOINT_CONTROL_AVERAGE_TRB);
+
+        controller.execute(Trb::new(
+            ring::TRB_ADDRESS_DEVICE,
+            slot.physical(1),
+            0,
+            u32::from(id) << 24,
+        ))?;
+
+        // Full-speed devices' default pipes carry packets of up to 64 bytes, so their real size is read from the
+        // start of the device descriptor, and the endpoint's context updated to match.
+        if speed == Speed::Full {
+            let mut header = [0; 8];
+            let setup = SetupPacket::get_descriptor(usb::DESCRIPTOR_DEVICE, 0, 0, 8);
+            slot.control_in(&setup, &mut header)?;
+
+            let actual = u32::from(header[7]);
+            if actual != max_packet_size && actual != 0 {
+                slot.write_input(0, 1, ADD_ENDPOINT_0);
+                slot.write_endpoint_0(actual);
+                controller.execute(Trb::new(
+                    ring::TRB_EVALUATE_CONTEXT,
+                    slot.physical(1),
+                    0,
+                    u32::from(id) << 24,
+                ))?;
+            }
+        }
+
+        Ok(slot)
+    }
+
+    /// Physical address of the slot's frame `index`.
+    fn physical(&self, index: usize) -> u64 {
+        u64::try_from(self.frame.get().get() + (index * page_size())).unwrap()
+    }
+
+    /// Writes the dword `dword` of the input context's context `context` (the input control context being zero).
+    fn write_input(&self, context: usize, dword: usize, value: u32) {
+        // Safety: The input context is the slot's second frame, and the context lies within it.
+        unsafe {
+            self.memory
+                .byte_add(page_size() + (context * self.controller.context_size))
+                .cast::<u32>()
+                .add(dword)
+                .write_volatile(value);
+        }
+    }
+
+    fn write_endpoint_0(&self, max_packet_size: u32) {
+        self.write_input(
+            2,
+            1,
+            ENDPOINT_ERROR_COUNT
+                | ENDPOINT_TYPE_CONTROL
+                | (max_packet_size << ENDPOINT_MAX_PACKET_SHIFT),
+        );
+    }
+
+    /// The slot's control transfer buffer.
+    fn buffer(&self) -> &[u8] {
+        // Safety: The buffer is the slot's third frame, which the controller only writes during a transfer.
+        unsafe {
+            core::slice::from_raw_parts(self.memory.byte_add(2 * page_size()).as_ptr(), page_size())
+        }
+    }
+}
+
+impl ControlPipe for Slot {
+    fn control_in(&self, setup: &SetupPacket, buf: &mut [u8]) -> Result<(), usb::Error> {
+        debug_assert!(setup.is_in() && buf.len() == usize::from(setup.length));
+        if buf.len() > page_size() {
+            return Err(usb::Error::TooLong(buf.len()));
+        }
+
+        let mut transfers = self.ring.lock();
+        let len = u32::try_from(buf.len()).unwrap();
+        let setup_stage = Trb::new(
+            ring::TRB_SETUP,
+            u64::from_le_bytes(setup.to_bytes()),
+            8,
+            ring::CONTROL_IDT | if len > 0 { SETUP_TRANSFER_IN } else { 0 },
+        );
+        transfers.push(setup_stage);
+
+        let data_stage = (len > 0).then(|| {
+            transfers.push(Trb::new(
+                ring::TRB_DATA,
+                self.physical(2),
+                len,
+                STAGE_DIRECTION_IN,
+            ))
+        });
+        // The status stage runs opposite to the data stage.
+        let status_direction = if len > 0 { 0 } else { STAGE_DIRECTION_IN };
+        let status_stage = transfers.push(Trb::new(
+            ring::TRB_STATUS,
+            0,
+            0,
+            ring::CONTROL_IOC | status_direction,
+        ));
+        self.controller.registers.ring_doorbell(self.id, ENDPOINT_0);
+
+        // A failed data stage completes (with an event) in place of the status stage.
+        let addresses = [status_stage, data_stage.unwrap_or(status_stage)];
+        let event = self
+            .controller
+            .wait_for(&addresses, TRANSFER_TIMEOUT)
+            .ok_or(usb::Error::Timeout)?;
+
+        match event.completion_code() {
+            ring::COMPLETION_SUCCESS | ring::COMPLETION_SHORT_PACKET => {
+                buf.copy_from_slice(&self.buffer()[..buf.len()]);
+                Ok(())
+            }
+
+            ring::COMPLETION_STALL => Err(usb::Error::Stall),
+            code => Err(usb::Error::Transfer(code)),
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if let Err(error) = self.controller.execute(Trb::new(
+            ring::TRB_DISABLE_SLOT,
+            0,
+            0,
+            u32::from(self.id) << 24,
+        )) {
+            warn!(
+                "Failed to disable {} slot {}: {error}",
+                self.controller.name, self.id
+            );
+        }
+
+        self.controller.state.with(|state| {
+            // Safety: The array holds an entry for every slot the controller enables.
+            unsafe {
+                state
+                    .lock()
+                    .dcbaa
+                    .add(usize::from(self.id))
+                    .write_volatile(0)
+            };
+        });
+
+        if let Err(error) =
+            crate::mem::dma::deallocate(self.frame, NonZero::new(SLOT_FRAMES).unwrap(), DMA_OWNER)
+        {
+            warn!(
+                "Failed to free {} slot {} memory: {error}",
+                self.controller.name, self.id
+            );
+        }
+    }
+}
+
+/// Maps the controller's registers from BAR0.
+fn map_registers(device: &Device) -> Result<NonNull<u8>, Error> {
+    let (address, size) = device
+        .bars()
+        .iter()
+        .find(|decoded_bar| decoded_bar.index == 0)
+        .and_then(|decoded_bar| match decoded_bar.bar {
+            Bar::Memory32 { address, .. } => {
+                Some((usize::try_from(address).ok()?, decoded_bar.size))
+            }
+            Bar::Memory64 { address, .. } => {
+                Some((usize::try_from(address).ok()?, decoded_bar.size))
+            }
+            Bar::Io { .. } => None,
+        })
+        .ok_or(Error::UnusableBar)?;
+
+    let frame = Address::<Frame>::new(address).ok_or(Error::UnusableBar)?;
+    let size = usize::try_from(size).map_err(|_| Error::UnusableBar)?;
+
+    Ok(crate::mem::map_mmio(frame, size)?)
+}
+
+/// Takes ownership of the controller from the firmware, if it claims it through the legacy support capability.
+fn take_ownership(base: NonNull<u8>, capabilities: u32) {
+    let mut offset = usize::try_from(capabilities >> 16).unwrap() * 4;
+    while offset != 0 {
+        // Safety: Extended capabilities lie within the controller's registers, and are dword aligned.
+        let capability = unsafe { base.byte_add(offset).cast::<u32>() };
+        // Safety: See above.
+        let header = unsafe { capability.read_volatile() };
+
+        if (header & 0xFF) == EXTENDED_LEGACY_SUPPORT {
+            // Safety: See above.
+            unsafe { capability.write_volatile(header | LEGACY_OS_OWNED) };
+
+            let released = poll_for(CONTROLLER_TIMEOUT, || {
+                // Safety: See above.
+                let header = unsafe { capability.read_volatile() };
+                ((header & LEGACY_BIOS_OWNED) == 0).then_some(())
+            });
+            if released.is_none() {
+                warn!("Firmware didn't release the xHCI controller; taking it regardless.");
+            }
+
+            return;
+        }
+
+        offset = match usize::try_from((header >> 8) & 0xFF).unwrap() {
+            0 => 0,
+            next => offset + (next * 4),
+        };
+    }
+}
+
+/// Allocates the controller's scratchpad buffers, returning the physical address of the array naming them.
+fn allocate_scratchpad(count: usize) -> Result<u64, Error> {
+    let array = crate::mem::dma::allocate(NonZero::<usize>::MIN, DMA_OWNER)?;
+    let buffers = crate::mem::dma::allocate(NonZero::new(count).unwrap(), DMA_OWNER)?;
+
+    let entries = HigherHalfDirectMap::frame_to_page(array)
+        .as_ptr()
+        .cast::<u64>();
+    for index in 0..count {
+        let buffer = u64::try_from(buffers.get().get() + (index * page_size())).unwrap();
+        // Safety: The array is a page, which holds an entry for every buffer (of at most 1023).
+        unsafe { entries.add(index).write_volatile(buffer) };
+    }
+
+    Ok(u64::try_from(array.get().get()).unwrap())
+}
+
+/// Takes over & resets the controller of `device`, and starts it with its command & event rings.
+fn bring_up(device: &Device) -> Result<Arc<Controller>, Error> {
+    let mut config = device.config()?;
+    // Interrupts are only taken through MSI-X, so the legacy pin is disabled.
+    config.set_command(
+        config.command()
+            | PciCommand::MEMORY_SPACE
+            | PciCommand::BUS_MASTER
+            | PciCommand::INTERRUPT_DISABLE,
+    );
+
+    let base = map_registers(device)?;
+    let read_capability = |register: Capability| {
+        // Safety: Register offset is within the capability registers, and naturally aligned.
+        unsafe {
+            base.byte_add(usize::from(register))
+                .cast::<u32>()
+                .read_volatile()
+        }
+    };
+
+    let structural = read_capability(Capability::HCSPARAMS1);
+    let scratchpad = read_capability(Capability::HCSPARAMS2);
+    let capabilities = read_capability(Capability::HCCPARAMS1);
+    let operational_offset =
+        usize::try_from(read_capability(Capability::CAPLENGTH) & 0xFF).unwrap();
+    let doorbell_offset = usize::try_from(read_capability(Capability::DBOFF) & !0x3).unwrap();
+    let runtime_offset = usize::try_from(read_capability(Capability::RTSOFF) & !0x1F).unwrap();
+
+    // Safety: Offsets are reported by the controller, and lie within its registers.
+    let registers = unsafe {
+        Registers {
+            operational: base.byte_add(operational_offset),
+            runtime: base.byte_add(runtime_offset),
+            doorbells: base.byte_add(doorbell_offset).cast(),
+        }
+    };
+
+    if (capabilities & HCC_64BIT) == 0 {
+        return Err(Error::Unsupported("64-bit addressing"));
+    }
+    if (registers.read_op::<u32>(Operational::PAGESIZE) & 1) == 0 || page_size() != 0x1000 {
+        return Err(Error::Unsupported("4KiB pages"));
+    }
+
+    take_ownership(base, capabilities);
+
+    registers.wait_status("becoming ready", |status| (status & USBSTS_NOT_READY) == 0)?;
+    let command: u32 = registers.read_op(Operational::USBCMD);
+    registers.write_op(Operational::USBCMD, command & !USBCMD_RUN);
+    registers.wait_status("halting", |status| (status & USBSTS_HALTED) != 0)?;
+
+    registers.write_op(Operational::USBCMD, USBCMD_RESET);
+    poll_for(CONTROLLER_TIMEOUT, || {
+        ((registers.read_op::<u32>(Operational::USBCMD) & USBCMD_RESET) == 0).then_some(())
+    })
+    .ok_or(Error::Timeout("resetting"))?;
+    registers.wait_status("becoming ready", |status| (status & USBSTS_NOT_READY) == 0)?;
+
+    let max_slots = structural & 0xFF;
+    let port_count = u8::try_from(structural >> 24).unwrap();
+    registers.write_op(Operational::CONFIG, max_slots);
+
+    let dcbaa_frame = crate::mem::dma::allocate(NonZero::<usize>::MIN, DMA_OWNER)?;
+    let dcbaa = NonNull::new(HigherHalfDirectMap::frame_to_page(dcbaa_frame).as_ptr())
+        .unwrap()
+        .cast::<u64>();
+    // Safety: The frame was just allocated, and is accessible through the HHDM.
+    unsafe { dcbaa.write_bytes(0, page_size() / size_of::<u64>()) };
+
+    let scratchpad_count =
+        usize::try_from((((scratchpad >> 21) & 0x1F) << 5) | (scratchpad >> 27)).unwrap();
+    if scratchpad_count > 0 {
+        let array = allocate_scratchpad(scratchpad_count)?;
+        // Safety: The array's first entry names the scratchpad buffer array.
+        unsafe { dcbaa.write_volatile(array) };
+    }
+    registers.write_op(
+        Operational::DCBAAP,
+        u64::try_from(dcbaa_frame.get().get()).unwrap(),
+    );
+
+    let commands = Ring::new()?;
+    registers.write_op(Operational::CRCR, commands.physical() | CRCR_CYCLE);
+
+    let events = EventRing::new(registers.interrupter(Interrupter::ERDP))?;
+    let (segment_table, segment_count) = events.segment_table();
+    registers.write_interrupter(Interrupter::ERSTSZ, segment_count);
+    registers.write_interrupter(Interrupter::ERDP, events.physical());
+    registers.write_interrupter(Interrupter::ERSTBA, segment_table);
+
+    let vector = route_interrupts(device);
+    registers.write_interrupter(Interrupter::IMOD, IMOD_INTERVAL);
+    registers.write_interrupter(Interrupter::IMAN, IMAN_ENABLE | IMAN_PENDING);
+
+    let interrupts = if vector.is_some() {
+        USBCMD_INTERRUPTS
+    } else {
+        0
+    };
+    registers.write_op(Operational::USBCMD, USBCMD_RUN | interrupts);
+    registers.wait_status("starting", |status| (status & USBSTS_HALTED) == 0)?;
+
+    let index = NEXT_INDEX.fetch_add(1, Ordering::Relaxed);
+    let controller = Arc::new(Controller {
+        name: format!("usb{index}"),
+        address: device.address(),
+        registers,
+        context_size: if (capabilities & HCC_CONTEXT_64) != 0 {
+            64
+        } else {
+            32
+        },
+        port_count,
+        vector,
+        state: InterruptCell::new(SpinMutex::new(State {
+            commands,
+            events,
+            dcbaa,
+            completions: BTreeMap::new(),
+        })),
+    });
+    crate::interrupts::uninterruptable(|| CONTROLLERS.lock().push(controller.clone()));
+
+    info!(
+        "xHCI controller {} is {}: {max_slots} slots, {port_count} ports, {}.",
+        controller.address,
+        controller.name,
+        if vector.is_some() {
+            "interrupt-driven"
+        } else {
+            "polled"
+        }
+    );
+
+    Ok(controller)
+}
+
+/// Routes the first interrupter's MSI-X table entry to a newly registered vector, returning it, or `None` if the
+/// event ring must be polled.
+fn route_interrupts(device: &Device) -> Option<u8> {
+    let msix = MsiX::new(device)
+        .inspect_err(|error| debug!("xHCI controller {} is polled: {error}", device.address()))
+        .ok()?;
+    let vector = crate::interrupts::irq::register(on_event)
+        .inspect_err(|error| warn!("Failed to register an xHCI vector: {error}"))
+        .ok()?;
+
+    if let Err(error) = msix
+        .route(0, vector, crate::cpu::get_id())
+        .and_then(|()| msix.enable())
+    {
+        warn!(
+            "Failed to route xHCI controller {} interrupts: {error}",
+            device.address()
+        );
+
+        let _ = msix.set_masked(0, true);
+        crate::interrupts::irq::unregister(vector);
+
+        return None;
+    }
+
+    Some(vector)
+}
@@ -0,0 +1,290 @@
+//! Transfer request blocks, and the rings they're exchanged with the controller through.
+
+use super::Error;
+use crate::mem::HigherHalfDirectMap;
+use core::{
+    num::NonZero,
+    ptr::NonNull,
+    sync::atomic::{Ordering, fence},
+};
+use libsys::{Address, Frame, page_size};
+
+/// Name ring memory is tagged with in the DMA audit.
+const DMA_OWNER: &str = "xhci";
+
+/// TRB types.
+pub const TRB_NORMAL: u8 = 1;
+pub const TRB_SETUP: u8 = 2;
+pub const TRB_DATA: u8 = 3;
+pub const TRB_STATUS: u8 = 4;
+pub const TRB_LINK: u8 = 6;
+pub const TRB_ENABLE_SLOT: u8 = 9;
+pub const TRB_DISABLE_SLOT: u8 = 10;
+pub const TRB_ADDRESS_DEVICE: u8 = 11;
+pub const TRB_EVALUATE_CONTEXT: u8 = 13;
+pub const TRB_TRANSFER_EVENT: u8 = 32;
+pub const TRB_COMMAND_COMPLETION: u8 = 33;
+pub const TRB_PORT_STATUS_CHANGE: u8 = 34;
+
+/// TRB control: the cycle bit, which marks the TRBs owned by the ring's consumer.
+pub const CONTROL_CYCLE: u32 = 1 << 0;
+/// Link TRB control: the consumer toggles its cycle state as it follows the link.
+const CONTROL_TOGGLE_CYCLE: u32 = 1 << 1;
+/// TRB control: generate an event when the TRB completes.
+pub const CONTROL_IOC: u32 = 1 << 5;
+/// TRB control: the parameter holds data immediately, rather than pointing to it.
+pub const CONTROL_IDT: u32 = 1 << 6;
+
+/// Completion codes.
+pub const COMPLETION_SUCCESS: u8 = 1;
+pub const COMPLETION_STALL: u8 = 6;
+pub const COMPLETION_SHORT_PACKET: u8 = 13;
+
+/// A transfer request block.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Trb {
+    pub parameter: u64,
+    pub status: u32,
+    pub control: u32,
+}
+
+impl Trb {
+    /// TRB of `trb_type`, with the (cycle-less) control `flags`.
+    pub fn new(trb_type: u8, parameter: u64, status: u32, flags: u32) -> Self {
+        Self {
+            parameter,
+            status,
+            control: (u32::from(trb_type) << 10) | (flags & !CONTROL_CYCLE),
+        }
+    }
+
+    pub fn trb_type(&self) -> u8 {
+        u8::try_from((self.control >> 10) & 0x3F).unwrap()
+    }
+
+    /// Completion code of an event.
+    pub fn completion_code(&self) -> u8 {
+        u8::try_from(self.status >> 24).unwrap()
+    }
+
+    /// Slot the TRB (a command, or an event) concerns.
+    pub fn slot_id(&self) -> u8 {
+        u8::try_from(self.control >> 24).unwrap()
+    }
+}
+
+/// Allocates & zeroes a frame of ring memory.
+fn allocate() -> Result<(Address<Frame>, NonNull<Trb>), Error> {
+    let frame = crate::mem::dma::allocate(NonZero::<usize>::MIN, DMA_OWNER)?;
+    let trbs = NonNull::new(HigherHalfDirectMap::frame_to_page(frame).as_ptr()).unwrap();
+    // Safety: The frame was just allocated, and is accessible through the HHDM.
+    unsafe { trbs.write_bytes(0, page_size()) };
+
+    Ok((frame, trbs.cast()))
+}
+
+fn free(frame: Address<Frame>) {
+    if let Err(error) = crate::mem::dma::deallocate(frame, NonZero::<usize>::MIN, DMA_OWNER) {
+        warn!("Failed to free an xHCI ring: {error}");
+    }
+}
+
+/// Writes `trb` to `slot`, handing it to the consumer by writing its cycle bit last.
+///
+/// # Safety
+///
+/// `slot` must be a TRB of ring memory not owned by the consumer.
+unsafe fn write_trb(slot: NonNull<Trb>, trb: Trb, cycle: bool) {
+    let cycle = if cycle { CONTROL_CYCLE } else { 0 };
+
+    // Safety: Caller is required to provide a TRB the consumer doesn't own.
+    unsafe {
+        slot.write_volatile(Trb {
+            control: (trb.control & !CONTROL_CYCLE) | (cycle ^ CONTROL_CYCLE),
+            ..trb
+        });
+        // The TRB must be whole before its cycle bit hands it over.
+        fence(Ordering::SeqCst);
+        slot.cast::<u32>()
+            .add(3)
+            .write_volatile((trb.control & !CONTROL_CYCLE) | cycle);
+    }
+}
+
+/// A producer ring (a command or transfer ring) of a single frame, whose last TRB links back to its first.
+pub struct Ring {
+    frame: Address<Frame>,
+    trbs: NonNull<Trb>,
+    enqueue: usize,
+    /// Producer cycle state.
+    cycle: bool,
+}
+
+// Safety: Ring memory is only accessed through the owning driver's lock.
+unsafe impl Send for Ring {}
+
+impl Ring {
+    pub fn new() -> Result<Self, Error> {
+        let (frame, trbs) = allocate()?;
+
+        Ok(Self {
+            frame,
+            trbs,
+            enqueue: 0,
+            cycle: true,
+        })
+    }
+
+    fn capacity() -> usize {
+        page_size() / size_of::<Trb>()
+    }
+
+    /// Physical address of the ring, as the consumer's initial dequeue pointer (whose cycle state is set).
+    pub fn physical(&self) -> u64 {
+        u64::try_from(self.frame.get().get()).unwrap()
+    }
+
+    fn physical_of(&self, index: usize) -> u64 {
+        self.physical() + u64::try_from(index * size_of::<Trb>()).unwrap()
+    }
+
+    /// Enqueues `trb`, returning its physical address (which events about it refer to).
+    pub fn push(&mut self, trb: Trb) -> u64 {
+        let address = self.physical_of(self.enqueue);
+        // Safety: The enqueue index is always within the ring, and its TRB isn't owned by the consumer.
+        unsafe { write_trb(self.trbs.add(self.enqueue), trb, self.cycle) };
+
+        self.enqueue += 1;
+        if self.enqueue == (Self::capacity() - 1) {
+            let link = Trb::new(TRB_LINK, self.physical(), 0, CONTROL_TOGGLE_CYCLE);
+            // Safety: The last TRB of the ring is reserved for the link.
+            unsafe { write_trb(self.trbs.add(self.enqueue), link, self.cycle) };
+
+            self.enqueue = 0;
+            self.cycle = !self.cycle;
+        }
+
+        address
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        free(self.frame);
+    }
+}
+
+/// Layout of an event ring segment table entry.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct SegmentTableEntry {
+    base: u64,
+    size: u32,
+    reserved: u32,
+}
+
+/// The consumer ring of an interrupter: a single segment, which shares its frame with the segment table naming it.
+pub struct EventRing {
+    frame: Address<Frame>,
+    trbs: NonNull<Trb>,
+    dequeue: usize,
+    /// Consumer cycle state.
+    cycle: bool,
+    /// The interrupter's dequeue pointer register.
+    dequeue_register: NonNull<u64>,
+}
+
+// Safety: Ring memory & the dequeue register are only accessed through the owning driver's lock.
+unsafe impl Send for EventRing {}
+
+/// Event ring dequeue pointer: the event handler is busy, which writing clears.
+const DEQUEUE_HANDLER_BUSY: u64 = 1 << 3;
+
+impl EventRing {
+    /// TRBs at the end of the frame given over to the segment table, which must be 64-byte aligned.
+    const TABLE_TRBS: usize = 4;
+
+    pub fn new(dequeue_register: NonNull<u64>) -> Result<Self, Error> {
+        let (frame, trbs) = allocate()?;
+        let ring = Self {
+            frame,
+            trbs,
+            dequeue: 0,
+            cycle: true,
+            dequeue_register,
+        };
+
+        // Safety: The segment table lies at the end of the ring's frame, past the segment.
+        unsafe {
+            ring.trbs
+                .add(Self::segment_size())
+                .cast::<SegmentTableEntry>()
+                .write_volatile(SegmentTableEntry {
+                    base: ring.physical(),
+                    size: u32::try_from(Self::segment_size()).unwrap(),
+                    reserved: 0,
+                });
+        }
+
+        Ok(ring)
+    }
+
+    fn segment_size() -> usize {
+        (page_size() / size_of::<Trb>()) - Self::TABLE_TRBS
+    }
+
+    /// Physical address of the segment.
+    pub fn physical(&self) -> u64 {
+        u64::try_from(self.frame.get().get()).unwrap()
+    }
+
+    /// Physical address of the segment table, and its entry count.
+    pub fn segment_table(&self) -> (u64, u32) {
+        (
+            self.physical() + u64::try_from(Self::segment_size() * size_of::<Trb>()).unwrap(),
+            1,
+        )
+    }
+
+    /// Takes the next event the controller has posted.
+    pub fn pop(&mut self) -> Option<Trb> {
+        // Safety: The dequeue index is always within the segment.
+        let slot = unsafe { self.trbs.add(self.dequeue) };
+        // Safety: The control word is the TRB's last field.
+        let control = unsafe { slot.cast::<u32>().add(3).read_volatile() };
+        if ((control & CONTROL_CYCLE) != 0) != self.cycle {
+            return None;
+        }
+
+        // The rest of the event must be read after the cycle bit that published it.
+        fence(Ordering::Acquire);
+        // Safety: The controller has posted the event.
+        let event = unsafe { slot.read_volatile() };
+
+        self.dequeue += 1;
+        if self.dequeue == Self::segment_size() {
+            self.dequeue = 0;
+            self.cycle = !self.cycle;
+        }
+
+        Some(event)
+    }
+
+    /// Tells the controller every popped event has been handled, freeing their TRBs.
+    pub fn acknowledge(&self) {
+        let dequeue = self.physical() + u64::try_from(self.dequeue * size_of::<Trb>()).unwrap();
+
+        // Safety: Register was located from the controller's runtime registers for this interrupter.
+        unsafe {
+            self.dequeue_register
+                .write_volatile(dequeue | DEQUEUE_HANDLER_BUSY);
+        }
+    }
+}
+
+impl Drop for EventRing {
+    fn drop(&mut self) {
+        free(self.frame);
+    }
+}
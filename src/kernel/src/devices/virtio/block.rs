@@ -0,0 +1,518 @@
+//! Virtio block device driver, for the disks QEMU provides during development.
+//!
+//! Each request is a chain of three descriptors: a header naming the operation and first sector, the data, and a
+//! status byte the device writes as it completes the request. Every request in flight has a slot of DMA memory holding
+//! those, so transfers larger than a slot's data are split into several requests.
+//!
+//! Completions are signalled by an MSI-X interrupt, whose handler reaps the used ring, and requesters halt their
+//! hardware thread until a completion wakes it. Without MSI-X (or with interrupts disabled, as during early boot),
+//! requesters poll the used ring instead.
+//!
+//! Requests [submitted](BlockDevice::submit) through the block layer don't wait: each is held in its slot until the
+//! handler reaps it, and is then completed as deferred work. Without MSI-X, they're transferred synchronously.
+
+use super::{Queue, Segment, Transport};
+use crate::{
+    devices::{
+        block::{self, BlockDevice, Direction, Request},
+        pci::{
+            driver::{Driver, Match},
+            tree::Device,
+        },
+    },
+    interrupts::{InterruptCell, irq::IrqTimestamp},
+    mem::HigherHalfDirectMap,
+    sync::SpinMutex,
+};
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+use core::{
+    num::NonZero,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use libsys::{Address, Frame, page_size};
+
+/// Virtio device type of block devices.
+const DEVICE_TYPE: u16 = 2;
+
+/// Device is read-only.
+const F_RO: u64 = 1 << 5;
+
+/// Offset of `capacity` (in sectors) within the device configuration structure.
+const CAPACITY_OFFSET: usize = 0;
+
+/// The device's only queue, as multiple queues aren't negotiated.
+const REQUEST_QUEUE: u16 = 0;
+/// MSI-X table entry the request queue is routed to.
+const REQUEST_QUEUE_ENTRY: u16 = 0;
+
+/// Request types.
+const T_IN: u32 = 0;
+const T_OUT: u32 = 1;
+
+/// Request status written by the device on success.
+const S_OK: u8 = 0;
+
+/// Requests in flight at once; each takes three of the queue's descriptors.
+const SLOTS: usize = 4;
+/// Bytes between the headers of consecutive slots, in the first frame of request memory.
+const SLOT_STRIDE: usize = 32;
+/// Length of a request header.
+const HEADER_LEN: usize = 16;
+/// Offset of a slot's status byte from its header.
+const STATUS_OFFSET: usize = HEADER_LEN;
+
+/// Name request memory is tagged with in the DMA audit.
+const DMA_OWNER: &str = "virtio-blk";
+
+static DRIVER: Driver = Driver {
+    name: "virtio-blk",
+    matches: &[
+        Match::Id {
+            vendor_id: super::VENDOR_ID,
+            device_id: super::MODERN_DEVICE_ID_BASE + DEVICE_TYPE,
+        },
+        // Transitional devices also provide the modern transport.
+        Match::Id {
+            vendor_id: super::VENDOR_ID,
+            device_id: 0x1001,
+        },
+    ],
+    probe,
+};
+
+/// Registers the virtio block driver.
+pub fn register() {
+    if let Err(error) = crate::devices::pci::driver::register(&DRIVER) {
+        warn!("Failed to register the virtio block driver: {error}");
+    }
+}
+
+/// Every device the driver has brought up, searched by the completion handler for its vector.
+static DISKS: SpinMutex<Vec<Arc<VirtioBlock>>> = SpinMutex::new(Vec::new());
+
+/// Index the next device is named by.
+static NEXT_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+fn probe(device: &'static Device) -> bool {
+    match VirtioBlock::new(device) {
+        Ok(disk) => {
+            let disk = Arc::new(disk);
+            crate::interrupts::uninterruptable(|| DISKS.lock().push(disk.clone()));
+
+            block::register(disk)
+        }
+
+        Err(error) => {
+            warn!(
+                "Failed to initialize virtio block device {}: {error}",
+                device.address()
+            );
+            false
+        }
+    }
+}
+
+/// Handles the completion interrupt of a device's request queue.
+fn complete(vector: u8, _: IrqTimestamp) {
+    let Some(disk) = DISKS
+        .lock()
+        .iter()
+        .find(|disk| disk.vector == Some(vector))
+        .cloned()
+    else {
+        return;
+    };
+
+    let finished = disk.disk.with(|disk| disk.lock().reap());
+    if !finished.is_empty() {
+        crate::task::workqueue::defer(move || disk.finish(finished));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SlotState {
+    Free,
+    /// Submitted as the descriptor chain with this head.
+    InFlight(u16),
+    /// Completed by the device, with its status yet to be taken.
+    Complete,
+}
+
+/// Data transferred by a single request.
+enum Transfer<'a> {
+    Read(&'a mut [u8]),
+    Write(&'a [u8]),
+}
+
+/// Submitted requests reaped from the used ring, with their statuses.
+type Finished = heapless::Vec<(Request, u8), SLOTS>;
+
+struct Disk {
+    transport: Transport,
+    queue: Queue,
+    /// First of the request memory's frames: one for every slot's header & status, followed by one of data per slot.
+    frame: Address<Frame>,
+    memory: NonNull<u8>,
+    slots: [SlotState; SLOTS],
+    /// Request each slot is transferring, if it was submitted through the block layer rather than waited on.
+    requests: [Option<Request>; SLOTS],
+}
+
+// Safety: Device & request memory are only accessed through the disk's lock.
+unsafe impl Send for Disk {}
+
+impl Disk {
+    fn frame_count() -> NonZero<usize> {
+        NonZero::new(1 + SLOTS).unwrap()
+    }
+
+    fn data_offset(slot: usize) -> usize {
+        (1 + slot) * page_size()
+    }
+
+    fn physical(&self, offset: usize) -> u64 {
+        u64::try_from(self.frame.get().get() + offset).unwrap()
+    }
+
+    /// Data buffer of `slot`, which is a page long.
+    fn data(&mut self, slot: usize) -> &mut [u8] {
+        // Safety: Each slot has a page of data, which the device doesn't access unless the slot is in flight.
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                self.memory.byte_add(Self::data_offset(slot)).as_ptr(),
+                page_size(),
+            )
+        }
+    }
+
+    fn status(&self, slot: usize) -> u8 {
+        // Safety: Each slot's status byte follows its header, in the first frame.
+        unsafe {
+            self.memory
+                .byte_add((slot * SLOT_STRIDE) + STATUS_OFFSET)
+                .read_volatile()
+        }
+    }
+
+    /// Submits a request of `kind` for `len` bytes from `sector` in `slot`, whose data must already be written for
+    /// writes.
+    ///
+    /// Returns `false` if the queue has too few free descriptors.
+    fn submit(&mut self, slot: usize, kind: u32, sector: u64, len: usize) -> bool {
+        let header_offset = slot * SLOT_STRIDE;
+
+        let mut header = [0; HEADER_LEN];
+        header[..4].copy_from_slice(&kind.to_le_bytes());
+        header[8..].copy_from_slice(&sector.to_le_bytes());
+
+        // Safety: The slot's header & status lie within the first frame, and the slot isn't in flight.
+        unsafe {
+            let header_ptr = self.memory.byte_add(header_offset);
+            core::ptr::copy_nonoverlapping(header.as_ptr(), header_ptr.as_ptr(), HEADER_LEN);
+            // Any status other than `S_OK` fails the request, in case the device never writes it.
+            header_ptr.byte_add(STATUS_OFFSET).write_volatile(u8::MAX);
+        }
+
+        let segments = [
+            Segment {
+                address: self.physical(header_offset),
+                len: u32::try_from(HEADER_LEN).unwrap(),
+                device_writable: false,
+            },
+            Segment {
+                address: self.physical(Self::data_offset(slot)),
+                len: u32::try_from(len).unwrap(),
+                device_writable: kind == T_IN,
+            },
+            Segment {
+                address: self.physical(header_offset + STATUS_OFFSET),
+                len: 1,
+                device_writable: true,
+            },
+        ];
+
+        match self.queue.submit(&segments) {
+            Some(head) => {
+                self.slots[slot] = SlotState::InFlight(head);
+                true
+            }
+
+            None => false,
+        }
+    }
+
+    /// Marks the slot of every request the device has completed, freeing those of submitted requests, which are
+    /// returned for completion.
+    fn reap(&mut self) -> Finished {
+        let mut finished = Finished::new();
+
+        while let Some((head, _)) = self.queue.pop_used_chain() {
+            let Some(slot) = self
+                .slots
+                .iter()
+                .position(|state| *state == SlotState::InFlight(head))
+            else {
+                continue;
+            };
+
+            match self.requests[slot].take() {
+                Some(mut request) => {
+                    if request.direction == Direction::Read {
+                        let len = request.buf.len();
+                        request.buf.copy_from_slice(&self.data(slot)[..len]);
+                    }
+
+                    // Every submitted request holds a slot, so there are never more than there are slots.
+                    finished.push((request, self.status(slot))).ok().unwrap();
+                    self.slots[slot] = SlotState::Free;
+                }
+
+                None => self.slots[slot] = SlotState::Complete,
+            }
+        }
+
+        finished
+    }
+}
+
+/// A virtio block device, as a [`BlockDevice`].
+pub struct VirtioBlock {
+    name: String,
+    sector_count: u64,
+    read_only: bool,
+    /// Vector completions are signalled on, or `None` if the device is polled.
+    vector: Option<u8>,
+    disk: InterruptCell<SpinMutex<Disk>>,
+}
+
+impl VirtioBlock {
+    fn new(device: &Device) -> Result<Self, super::Error> {
+        let transport = Transport::new(device)?;
+        let features = transport.negotiate(F_RO)?;
+
+        let vector = super::route_queue(
+            device,
+            &transport,
+            REQUEST_QUEUE,
+            REQUEST_QUEUE_ENTRY,
+            complete,
+        )
+        .inspect_err(|error| {
+            debug!(
+                "Virtio block device {} is polled: {error}",
+                device.address()
+            )
+        })
+        .ok();
+        let mut queue = transport.setup_queue(REQUEST_QUEUE)?;
+        queue.set_interrupts(vector.is_some());
+
+        let frame = crate::mem::dma::allocate(Disk::frame_count(), DMA_OWNER)?;
+        let memory = NonNull::new(HigherHalfDirectMap::frame_to_page(frame).as_ptr()).unwrap();
+
+        transport.finish();
+
+        let sector_count = transport.read_device_config::<u64>(CAPACITY_OFFSET);
+        let name = format!("vblk{}", NEXT_INDEX.fetch_add(1, Ordering::Relaxed));
+
+        debug!(
+            "Virtio block device {} is {name}, {}.",
+            device.address(),
+            if vector.is_some() {
+                "interrupt-driven"
+            } else {
+                "polled"
+            }
+        );
+
+        Ok(Self {
+            name,
+            sector_count,
+            read_only: (features & F_RO) != 0,
+            vector,
+            disk: InterruptCell::new(SpinMutex::new(Disk {
+                transport,
+                queue,
+                frame,
+                memory,
+                slots: [SlotState::Free; SLOTS],
+                requests: [const { None }; SLOTS],
+            })),
+        })
+    }
+
+    /// Repeatedly calls `func` with the disk, having reaped any completed requests, until it returns `Some`.
+    fn wait_until<T>(&self, mut func: impl FnMut(&mut Disk) -> Option<T>) -> T {
+        loop {
+            let (value, finished) = self.disk.with(|disk| {
+                let mut disk = disk.lock();
+                let finished = disk.reap();
+
+                (func(&mut disk), finished)
+            });
+            self.finish(finished);
+
+            if let Some(value) = value {
+                return value;
+            }
+
+            if self.vector.is_some() && crate::interrupts::is_enabled() {
+                // A completion wakes the hardware thread it's routed to; any other (or one which missed the completion
+                // as it checked) is woken by its next timer tick.
+                crate::interrupts::wait_next();
+            } else {
+                core::hint::spin_loop();
+            }
+        }
+    }
+
+    /// Performs `transfer` (of at most a page) from `sector` as a single request, waiting for it to complete.
+    fn request(&self, sector: u64, mut transfer: Transfer<'_>) -> Result<(), block::Error> {
+        let (kind, len) = match &transfer {
+            Transfer::Read(buf) => (T_IN, buf.len()),
+            Transfer::Write(data) => (T_OUT, data.len()),
+        };
+
+        let slot = self.wait_until(|disk| {
+            let slot = disk
+                .slots
+                .iter()
+                .position(|state| *state == SlotState::Free)?;
+            if let Transfer::Write(data) = &transfer {
+                disk.data(slot)[..len].copy_from_slice(data);
+            }
+
+            disk.submit(slot, kind, sector, len).then_some(slot)
+        });
+
+        let status = self.wait_until(|disk| {
+            if disk.slots[slot] != SlotState::Complete {
+                return None;
+            }
+
+            if let Transfer::Read(buf) = &mut transfer {
+                buf.copy_from_slice(&disk.data(slot)[..len]);
+            }
+            disk.slots[slot] = SlotState::Free;
+
+            Some(disk.status(slot))
+        });
+
+        self.status_result(sector, status)
+    }
+
+    fn status_result(&self, sector: u64, status: u8) -> Result<(), block::Error> {
+        if status == S_OK {
+            Ok(())
+        } else {
+            warn!(
+                "{} failed a request at sector {sector}: status {status}",
+                self.name
+            );
+            Err(block::Error::Io)
+        }
+    }
+
+    /// Completes the submitted requests reaped from the used ring.
+    fn finish(&self, finished: Finished) {
+        for (request, status) in finished {
+            let result = self.status_result(request.sector, status);
+            request.complete(result);
+        }
+    }
+}
+
+/// Sectors transferred by each request, which is a slot's data.
+fn sectors_per_request() -> u64 {
+    u64::try_from(page_size() / block::SECTOR_SIZE).unwrap()
+}
+
+impl BlockDevice for VirtioBlock {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn sector_count(&self) -> u64 {
+        self.sector_count
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn read(&self, sector: u64, buf: &mut [u8]) -> Result<(), block::Error> {
+        block::check_range(self.sector_count, sector, buf.len())?;
+
+        (sector..)
+            .step_by(usize::try_from(sectors_per_request()).unwrap())
+            .zip(buf.chunks_mut(page_size()))
+            .try_for_each(|(sector, chunk)| self.request(sector, Transfer::Read(chunk)))
+    }
+
+    fn write(&self, sector: u64, buf: &[u8]) -> Result<(), block::Error> {
+        if self.read_only {
+            return Err(block::Error::ReadOnly);
+        }
+
+        block::check_range(self.sector_count, sector, buf.len())?;
+
+        (sector..)
+            .step_by(usize::try_from(sectors_per_request()).unwrap())
+            .zip(buf.chunks(page_size()))
+            .try_for_each(|(sector, chunk)| self.request(sector, Transfer::Write(chunk)))
+    }
+
+    fn submit(&self, request: Request) -> Result<(), Request> {
+        // Without its interrupt, nothing would reap the request unless it was polled for; requests larger than a
+        // slot's data would need several slots.
+        if self.vector.is_none() || request.buf.len() > page_size() {
+            block::transfer(self, request);
+            return Ok(());
+        }
+
+        let (kind, checked) = match request.direction {
+            Direction::Read => (T_IN, Ok(())),
+            Direction::Write if self.read_only => (T_OUT, Err(block::Error::ReadOnly)),
+            Direction::Write => (T_OUT, Ok(())),
+        };
+        if let Err(error) = checked.and_then(|()| {
+            block::check_range(self.sector_count, request.sector, request.buf.len()).map(drop)
+        }) {
+            request.complete(Err(error));
+            return Ok(());
+        }
+
+        self.disk.with(|disk| {
+            let mut disk = disk.lock();
+            let Some(slot) = disk
+                .slots
+                .iter()
+                .position(|state| *state == SlotState::Free)
+            else {
+                return Err(request);
+            };
+
+            let len = request.buf.len();
+            if request.direction == Direction::Write {
+                disk.data(slot)[..len].copy_from_slice(&request.buf);
+            }
+            if !disk.submit(slot, kind, request.sector, len) {
+                return Err(request);
+            }
+
+            disk.requests[slot] = Some(request);
+
+            Ok(())
+        })
+    }
+
+    fn max_request_sectors(&self) -> u64 {
+        sectors_per_request()
+    }
+
+    fn poll(&self) {
+        let finished = self.disk.with(|disk| disk.lock().reap());
+        self.finish(finished);
+    }
+}
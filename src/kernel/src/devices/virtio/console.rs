@@ -0,0 +1,339 @@
+//! Virtio console (virtio-serial) driver, exposing the device's named multiport ports as byte streams.
+
+use super::{BUFFER_SIZE, Queue, Transport};
+use crate::{
+    devices::pci::{
+        driver::{Driver, Match},
+        tree::Device,
+    },
+    interrupts::InterruptCell,
+    sync::SpinMutex,
+};
+use alloc::{collections::vec_deque::VecDeque, string::String, vec::Vec};
+use spin::Once;
+
+/// Virtio device type of the console.
+const DEVICE_TYPE: u16 = 3;
+
+/// Device supports multiple (named) ports, and a control queue to manage them.
+const F_MULTIPORT: u64 = 1 << 1;
+
+/// Offset of `max_nr_ports` within the device configuration structure.
+const MAX_PORTS_OFFSET: usize = 4;
+
+/// Most ports the driver will set up queues for.
+const MAX_PORTS: u32 = 4;
+
+/// Control queues are placed after port 0's queue pair.
+const CONTROL_RECEIVE_QUEUE: u16 = 2;
+const CONTROL_TRANSMIT_QUEUE: u16 = 3;
+
+/// Maximum number of bytes of received data buffered for a port before the oldest are dropped.
+const MAX_BUFFERED: usize = 0x4000;
+
+/// Events carried by control messages.
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+enum Event {
+    DeviceReady = 0,
+    DeviceAdd = 1,
+    DeviceRemove = 2,
+    PortReady = 3,
+    ConsolePort = 4,
+    Resize = 5,
+    PortOpen = 6,
+    PortName = 7,
+}
+
+struct Port {
+    id: u32,
+    receive: Queue,
+    transmit: Queue,
+    name: Option<String>,
+    /// Whether the host side of the port is connected.
+    host_connected: bool,
+    received: VecDeque<u8>,
+}
+
+struct Console {
+    transport: Transport,
+    control_receive: Queue,
+    control_transmit: Queue,
+    ports: Vec<Port>,
+}
+
+// Safety: Device memory is only accessed through the console's lock.
+unsafe impl Send for Console {}
+
+static CONSOLE: Once<InterruptCell<SpinMutex<Console>>> = Once::new();
+
+static DRIVER: Driver = Driver {
+    name: "virtio-console",
+    matches: &[
+        Match::Id {
+            vendor_id: super::VENDOR_ID,
+            device_id: super::MODERN_DEVICE_ID_BASE + DEVICE_TYPE,
+        },
+        // Transitional devices also provide the modern transport.
+        Match::Id {
+            vendor_id: super::VENDOR_ID,
+            device_id: 0x1003,
+        },
+    ],
+    probe,
+};
+
+/// Registers the virtio console driver.
+pub fn register() {
+    if let Err(error) = crate::devices::pci::driver::register(&DRIVER) {
+        warn!("Failed to register the virtio console driver: {error}");
+    }
+}
+
+/// Queue indexes used by the port `id`.
+fn port_queues(id: u32) -> (u16, u16) {
+    let receive = if id == 0 {
+        0
+    } else {
+        u16::try_from((id * 2) + 2).unwrap()
+    };
+
+    (receive, receive + 1)
+}
+
+fn probe(device: &'static Device) -> bool {
+    if CONSOLE.is_completed() {
+        debug!("Ignoring additional virtio console: {}", device.address());
+        return false;
+    }
+
+    match Console::new(device) {
+        Ok(console) => {
+            CONSOLE.call_once(|| InterruptCell::new(SpinMutex::new(console)));
+            true
+        }
+
+        Err(error) => {
+            warn!(
+                "Failed to initialize virtio console {}: {error}",
+                device.address()
+            );
+            false
+        }
+    }
+}
+
+impl Console {
+    fn new(device: &Device) -> Result<Self, super::Error> {
+        let transport = Transport::new(device)?;
+        let features = transport.negotiate(F_MULTIPORT)?;
+        if (features & F_MULTIPORT) == 0 {
+            // Only named ports are of use, so a single-port console isn't driven.
+            transport.set_status(super::Status::FAILED);
+            return Err(super::Error::FeaturesRejected(features));
+        }
+
+        let max_ports = transport
+            .read_device_config::<u32>(MAX_PORTS_OFFSET)
+            .min(MAX_PORTS);
+
+        let mut control_receive = transport.setup_queue(CONTROL_RECEIVE_QUEUE)?;
+        let control_transmit = transport.setup_queue(CONTROL_TRANSMIT_QUEUE)?;
+
+        let ports = (0..max_ports)
+            .map(|id| {
+                let (receive, transmit) = port_queues(id);
+
+                Ok(Port {
+                    id,
+                    receive: transport.setup_queue(receive)?,
+                    transmit: transport.setup_queue(transmit)?,
+                    name: None,
+                    host_connected: false,
+                    received: VecDeque::new(),
+                })
+            })
+            .collect::<Result<Vec<_>, super::Error>>()?;
+
+        control_receive.fill_receive();
+        transport.finish();
+
+        let mut console = Self {
+            transport,
+            control_receive,
+            control_transmit,
+            ports,
+        };
+
+        for port in &mut console.ports {
+            port.receive.fill_receive();
+        }
+
+        console.send_control(0, Event::DeviceReady, 1);
+
+        debug!("Virtio console ready with up to {max_ports} ports.");
+
+        Ok(console)
+    }
+
+    fn send_control(&mut self, id: u32, event: Event, value: u16) {
+        let mut message = [0; 8];
+        message[..4].copy_from_slice(&id.to_le_bytes());
+        message[4..6].copy_from_slice(&u16::from(event).to_le_bytes());
+        message[6..].copy_from_slice(&value.to_le_bytes());
+
+        // Reclaim any control messages the device has already consumed.
+        while self.control_transmit.pop_used(|_| ()).is_some() {}
+
+        if !self.control_transmit.send(&message) {
+            warn!("Virtio console control queue is full; dropped {event:?} for port {id}.");
+        }
+    }
+
+    fn handle_control(&mut self, message: &[u8]) {
+        let Some((header, payload)) = message.split_first_chunk::<8>() else {
+            return;
+        };
+
+        let id = u32::from_le_bytes(header[..4].try_into().unwrap());
+        let event = u16::from_le_bytes(header[4..6].try_into().unwrap());
+        let value = u16::from_le_bytes(header[6..].try_into().unwrap());
+
+        let Ok(event) = Event::try_from(event) else {
+            trace!("Ignoring unknown virtio console event: {event}");
+            return;
+        };
+
+        match event {
+            Event::DeviceAdd => {
+                let ready = self.ports.iter().any(|port| port.id == id);
+                self.send_control(id, Event::PortReady, u16::from(ready));
+            }
+
+            Event::PortName => {
+                if let Some(port) = self.ports.iter_mut().find(|port| port.id == id) {
+                    let name = String::from_utf8_lossy(payload);
+                    let name = name.trim_end_matches('\0');
+
+                    debug!("Virtio console port {id} is named {name:?}.");
+                    port.name = Some(String::from(name));
+                }
+            }
+
+            Event::PortOpen => {
+                if let Some(port) = self.ports.iter_mut().find(|port| port.id == id) {
+                    port.host_connected = value == 1;
+                }
+
+                // Ports are always open on the guest side.
+                self.send_control(id, Event::PortOpen, 1);
+            }
+
+            Event::ConsolePort => self.send_control(id, Event::PortOpen, 1),
+
+            Event::DeviceRemove => {
+                if let Some(port) = self.ports.iter_mut().find(|port| port.id == id) {
+                    port.name = None;
+                    port.host_connected = false;
+                }
+            }
+
+            Event::DeviceReady | Event::PortReady | Event::Resize => {}
+        }
+    }
+
+    /// Processes any control messages & received data the device has delivered.
+    fn poll(&mut self) {
+        while let Some(message) = self.control_receive.pop_used(|message| Vec::from(message)) {
+            self.handle_control(&message);
+        }
+        self.control_receive.fill_receive();
+
+        for port in &mut self.ports {
+            let received = &mut port.received;
+            let mut any = false;
+            while port
+                .receive
+                .pop_used(|data| {
+                    received.extend(data);
+
+                    let excess = received.len().saturating_sub(MAX_BUFFERED);
+                    received.drain(..excess);
+                })
+                .is_some()
+            {
+                any = true;
+            }
+
+            if any {
+                port.receive.fill_receive();
+            }
+        }
+    }
+
+    fn port_mut(&mut self, name: &str) -> Option<&mut Port> {
+        self.ports
+            .iter_mut()
+            .find(|port| port.name.as_deref() == Some(name))
+    }
+}
+
+fn with_console<T>(func: impl FnOnce(&mut Console) -> T) -> Option<T> {
+    CONSOLE
+        .get()
+        .map(|console| console.with(|console| func(&mut console.lock())))
+}
+
+/// Whether a port named `name` exists.
+pub fn has_port(name: &str) -> bool {
+    with_console(|console| {
+        console.poll();
+        console.port_mut(name).is_some()
+    })
+    .unwrap_or(false)
+}
+
+/// Takes up to `buffer.len()` bytes received on the port `name`, returning how many were taken.
+pub fn read(name: &str, buffer: &mut [u8]) -> usize {
+    with_console(|console| {
+        console.poll();
+
+        let Some(port) = console.port_mut(name) else {
+            return 0;
+        };
+
+        let len = buffer.len().min(port.received.len());
+        for (dest, byte) in buffer.iter_mut().zip(port.received.drain(..len)) {
+            *dest = byte;
+        }
+
+        len
+    })
+    .unwrap_or(0)
+}
+
+/// Writes `data` to the port `name`, waiting for the device to consume it if the transmit queue is full.
+///
+/// Returns `false` if there is no such port, or the device stopped consuming data.
+pub fn write(name: &str, data: &[u8]) -> bool {
+    /// Attempts to reclaim a transmit descriptor before giving up.
+    const MAX_RECLAIM_ATTEMPTS: usize = 1_000_000;
+
+    with_console(|console| {
+        let Some(port) = console.port_mut(name) else {
+            return false;
+        };
+
+        data.chunks(BUFFER_SIZE).all(|chunk| {
+            (0..MAX_RECLAIM_ATTEMPTS).any(|_| {
+                while port.transmit.pop_used(|_| ()).is_some() {}
+
+                port.transmit.send(chunk) || {
+                    core::hint::spin_loop();
+                    false
+                }
+            })
+        })
+    })
+    .unwrap_or(false)
+}
@@ -0,0 +1,380 @@
+//! Virtio devices, over the modern (virtio 1.0+) PCI transport.
+//!
+//! Queues are polled unless their driver routes them an MSI-X vector (see [`route_queue`]) and enables their
+//! interrupts; the console is polled, while block and network devices are interrupt-driven.
+
+pub mod block;
+pub mod console;
+pub mod net;
+
+mod queue;
+pub use queue::*;
+
+use crate::{
+    devices::pci::{
+        config::{Bar, Command},
+        msix::MsiX,
+        tree::Device,
+    },
+    interrupts::irq,
+};
+use core::ptr::NonNull;
+use libsys::{Address, Frame, page_size};
+
+pub const VENDOR_ID: u16 = 0x1AF4;
+
+/// Modern (non-transitional) device IDs are the virtio device type, offset by this base.
+pub const MODERN_DEVICE_ID_BASE: u16 = 0x1040;
+
+/// Capability ID of the vendor-specific capabilities which locate the transport's structures.
+const VENDOR_CAPABILITY_ID: u8 = 0x09;
+
+/// Vector register value which routes no MSI-X table entry.
+pub const NO_VECTOR: u16 = 0xFFFF;
+
+/// Device supports the virtio 1.0+ interface.
+pub const F_VERSION_1: u64 = 1 << 32;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to access the PCI configuration space: {0}")]
+    Config(#[from] crate::devices::pci::config::Error),
+
+    #[error("device does not provide the {0} configuration structure")]
+    MissingStructure(&'static str),
+
+    #[error("configuration structure lies in an unusable BAR: {0}")]
+    UnusableBar(u8),
+
+    #[error("failed to map the device's registers")]
+    Paging(#[from] crate::mem::paging::Error),
+
+    #[error("device rejected the negotiated features: {0:#X}")]
+    FeaturesRejected(u64),
+
+    #[error("device does not provide queue {0}")]
+    NoSuchQueue(u16),
+
+    #[error("failed to set up MSI-X: {0}")]
+    Msix(#[from] crate::devices::pci::msix::Error),
+
+    #[error("device rejected MSI-X table entry {0}")]
+    VectorRejected(u16),
+
+    #[error("failed to register an interrupt vector: {0}")]
+    Irq(#[from] irq::Error),
+
+    #[error("failed to allocate queue memory: {0}")]
+    Dma(#[from] crate::mem::dma::Error),
+}
+
+bitflags! {
+    #[repr(transparent)]
+    #[derive(Debug, Clone, Copy)]
+    pub struct Status: u8 {
+        const ACKNOWLEDGE = 1 << 0;
+        const DRIVER = 1 << 1;
+        const DRIVER_OK = 1 << 2;
+        const FEATURES_OK = 1 << 3;
+        const NEEDS_RESET = 1 << 6;
+        const FAILED = 1 << 7;
+    }
+}
+
+/// Kinds of structure located by the transport's vendor-specific capabilities.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
+enum StructureKind {
+    Common = 1,
+    Notify = 2,
+    Isr = 3,
+    Device = 4,
+}
+
+/// Registers of the common configuration structure.
+#[repr(usize)]
+#[derive(Debug, IntoPrimitive, Clone, Copy)]
+#[allow(non_camel_case_types)]
+#[rustfmt::skip]
+enum Register {
+    DEVICE_FEATURE_SELECT   = 0x00,
+    DEVICE_FEATURE          = 0x04,
+    DRIVER_FEATURE_SELECT   = 0x08,
+    DRIVER_FEATURE          = 0x0C,
+    MSIX_CONFIG             = 0x10,
+    DEVICE_STATUS           = 0x14,
+    QUEUE_SELECT            = 0x16,
+    QUEUE_SIZE              = 0x18,
+    QUEUE_MSIX_VECTOR       = 0x1A,
+    QUEUE_ENABLE            = 0x1C,
+    QUEUE_NOTIFY_OFF        = 0x1E,
+    QUEUE_DESC              = 0x20,
+    QUEUE_DRIVER            = 0x28,
+    QUEUE_DEVICE            = 0x30,
+}
+
+/// The modern virtio PCI transport of a single device.
+pub struct Transport {
+    common: NonNull<u8>,
+    notify: NonNull<u8>,
+    notify_multiplier: u32,
+    device_config: NonNull<u8>,
+}
+
+// Safety: Register blocks are mapped in the kernel address space, which is shared by all hardware threads.
+unsafe impl Send for Transport {}
+
+impl Transport {
+    /// Locates & maps the transport structures of `device`, and enables its memory decoding & bus mastering.
+    pub fn new(device: &Device) -> Result<Self, Error> {
+        let mut config = device.config()?;
+
+        let mut common = None;
+        let mut notify = None;
+        let mut device_config = None;
+        for capability in device
+            .capabilities()
+            .iter()
+            .filter(|capability| capability.id == VENDOR_CAPABILITY_ID)
+        {
+            let Ok(kind) = StructureKind::try_from(config.read_u8(capability.offset + 3)) else {
+                continue;
+            };
+
+            let bar_index = config.read_u8(capability.offset + 4);
+            let offset = config.read_u32(capability.offset + 8);
+            let length = config.read_u32(capability.offset + 12);
+
+            let structure = || map_structure(device, bar_index, offset, length);
+            match kind {
+                StructureKind::Common if common.is_none() => common = Some(structure()?),
+                StructureKind::Notify if notify.is_none() => {
+                    notify = Some((structure()?, config.read_u32(capability.offset + 16)));
+                }
+                StructureKind::Device if device_config.is_none() => {
+                    device_config = Some(structure()?);
+                }
+
+                _ => {}
+            }
+        }
+
+        config.set_command(config.command() | Command::MEMORY_SPACE | Command::BUS_MASTER);
+
+        let (notify, notify_multiplier) = notify.ok_or(Error::MissingStructure("notify"))?;
+
+        Ok(Self {
+            common: common.ok_or(Error::MissingStructure("common"))?,
+            notify,
+            notify_multiplier,
+            device_config: device_config.ok_or(Error::MissingStructure("device"))?,
+        })
+    }
+
+    fn read<T: Copy>(&self, register: Register) -> T {
+        // Safety: Register offset is within the mapped common configuration structure, and naturally aligned.
+        unsafe {
+            self.common
+                .byte_add(usize::from(register))
+                .cast::<T>()
+                .read_volatile()
+        }
+    }
+
+    fn write<T: Copy>(&self, register: Register, value: T) {
+        // Safety: Register offset is within the mapped common configuration structure, and naturally aligned.
+        unsafe {
+            self.common
+                .byte_add(usize::from(register))
+                .cast::<T>()
+                .write_volatile(value);
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        Status::from_bits_retain(self.read(Register::DEVICE_STATUS))
+    }
+
+    pub fn set_status(&self, status: Status) {
+        self.write(Register::DEVICE_STATUS, status.bits());
+    }
+
+    /// Resets the device, then acknowledges it and negotiates the intersection of its features and `features`.
+    ///
+    /// Returns the negotiated features. [`F_VERSION_1`] is always required.
+    pub fn negotiate(&self, features: u64) -> Result<u64, Error> {
+        self.set_status(Status::empty());
+        while !self.status().is_empty() {
+            core::hint::spin_loop();
+        }
+
+        self.set_status(Status::ACKNOWLEDGE);
+        self.set_status(Status::ACKNOWLEDGE | Status::DRIVER);
+
+        let device_features = (0..2u32).fold(0, |device_features, select| {
+            self.write(Register::DEVICE_FEATURE_SELECT, select);
+
+            device_features
+                | (u64::from(self.read::<u32>(Register::DEVICE_FEATURE)) << (select * 32))
+        });
+
+        let negotiated = device_features & (features | F_VERSION_1);
+        if (negotiated & F_VERSION_1) == 0 {
+            self.set_status(Status::FAILED);
+            return Err(Error::FeaturesRejected(negotiated));
+        }
+
+        for select in 0..2u32 {
+            self.write(Register::DRIVER_FEATURE_SELECT, select);
+            self.write(
+                Register::DRIVER_FEATURE,
+                u32::try_from((negotiated >> (select * 32)) & 0xFFFF_FFFF).unwrap(),
+            );
+        }
+
+        self.set_status(Status::ACKNOWLEDGE | Status::DRIVER | Status::FEATURES_OK);
+        if !self.status().contains(Status::FEATURES_OK) {
+            self.set_status(Status::FAILED);
+            return Err(Error::FeaturesRejected(negotiated));
+        }
+
+        Ok(negotiated)
+    }
+
+    /// Allocates and enables the queue at `index`.
+    ///
+    /// # Remarks
+    ///
+    /// Queues must be set up after negotiating features, and before [`Self::finish`].
+    pub fn setup_queue(&self, index: u16) -> Result<Queue, Error> {
+        self.write(Register::QUEUE_SELECT, index);
+
+        let max_size: u16 = self.read(Register::QUEUE_SIZE);
+        if max_size == 0 {
+            return Err(Error::NoSuchQueue(index));
+        }
+
+        let size = max_size.min(MAX_QUEUE_SIZE);
+        self.write(Register::QUEUE_SIZE, size);
+
+        let notify_offset = usize::from(self.read::<u16>(Register::QUEUE_NOTIFY_OFF))
+            * usize::try_from(self.notify_multiplier).unwrap();
+        // Safety: The notify structure covers every queue's notification address.
+        let notify = unsafe { self.notify.byte_add(notify_offset).cast::<u16>() };
+
+        let queue = Queue::new(index, size, notify)?;
+        let (desc, driver, device) = queue.physical_addresses();
+        self.write(Register::QUEUE_DESC, desc);
+        self.write(Register::QUEUE_DRIVER, driver);
+        self.write(Register::QUEUE_DEVICE, device);
+        self.write(Register::QUEUE_ENABLE, 1u16);
+
+        Ok(queue)
+    }
+
+    /// Routes the queue at `index`'s interrupts to the MSI-X table entry `entry` (or none, for [`NO_VECTOR`]).
+    ///
+    /// # Remarks
+    ///
+    /// MSI-X must be enabled for the function first, and the queue routed before it's set up.
+    pub fn set_queue_vector(&self, index: u16, entry: u16) -> Result<(), Error> {
+        self.write(Register::QUEUE_SELECT, index);
+        self.write(Register::QUEUE_MSIX_VECTOR, entry);
+
+        // The device reads back `NO_VECTOR` if it couldn't allocate resources for the entry.
+        if self.read::<u16>(Register::QUEUE_MSIX_VECTOR) == entry {
+            Ok(())
+        } else {
+            Err(Error::VectorRejected(entry))
+        }
+    }
+
+    /// Routes configuration change interrupts to the MSI-X table entry `entry` (or none, for [`NO_VECTOR`]).
+    pub fn set_config_vector(&self, entry: u16) -> Result<(), Error> {
+        self.write(Register::MSIX_CONFIG, entry);
+
+        if self.read::<u16>(Register::MSIX_CONFIG) == entry {
+            Ok(())
+        } else {
+            Err(Error::VectorRejected(entry))
+        }
+    }
+
+    /// Marks the driver as ready, after which the device may use its queues.
+    pub fn finish(&self) {
+        self.set_status(self.status() | Status::DRIVER_OK);
+    }
+
+    /// Reads a field at `offset` of the device-specific configuration structure.
+    pub fn read_device_config<T: Copy>(&self, offset: usize) -> T {
+        // Safety: Callers provide offsets of naturally-aligned fields within the device configuration structure.
+        unsafe {
+            self.device_config
+                .byte_add(offset)
+                .cast::<T>()
+                .read_volatile()
+        }
+    }
+}
+
+/// Routes the interrupts of the queue at `index` through the MSI-X table entry `entry` of `device`, to a newly
+/// registered vector which invokes `handler`, and returns the vector.
+///
+/// # Remarks
+///
+/// This must be done after negotiating features, and before the queue is set up. Configuration change interrupts are
+/// left unrouted.
+pub fn route_queue(
+    device: &Device,
+    transport: &Transport,
+    index: u16,
+    entry: u16,
+    handler: irq::Handler,
+) -> Result<u8, Error> {
+    let msix = MsiX::new(device)?;
+    let vector = irq::register(handler)?;
+
+    let routed = msix
+        .route(entry, vector, crate::cpu::get_id())
+        .and_then(|()| msix.enable())
+        .map_err(Error::from)
+        .and_then(|()| transport.set_config_vector(NO_VECTOR))
+        .and_then(|()| transport.set_queue_vector(index, entry));
+
+    if routed.is_err() {
+        // The device may still interrupt, so its entry is masked before the vector can be reused.
+        let _ = msix.set_masked(entry, true);
+        let _ = transport.set_queue_vector(index, NO_VECTOR);
+        irq::unregister(vector);
+    }
+
+    routed.map(|()| vector)
+}
+
+/// Maps `length` bytes at `offset` into the memory BAR `bar_index` of `device`.
+fn map_structure(
+    device: &Device,
+    bar_index: u8,
+    offset: u32,
+    length: u32,
+) -> Result<NonNull<u8>, Error> {
+    let bar_address = device
+        .bars()
+        .iter()
+        .find(|decoded_bar| decoded_bar.index == bar_index)
+        .and_then(|decoded_bar| match decoded_bar.bar {
+            Bar::Memory32 { address, .. } => usize::try_from(address).ok(),
+            Bar::Memory64 { address, .. } => usize::try_from(address).ok(),
+            Bar::Io { .. } => None,
+        })
+        .ok_or(Error::UnusableBar(bar_index))?;
+
+    let address = bar_address + usize::try_from(offset).unwrap();
+    let page_offset = address & (page_size() - 1);
+    let frame =
+        Address::<Frame>::new(address - page_offset).ok_or(Error::UnusableBar(bar_index))?;
+    let mapped = crate::mem::map_mmio(frame, page_offset + usize::try_from(length).unwrap())?;
+
+    // Safety: The mapping covers `page_offset + length` bytes.
+    Ok(unsafe { mapped.byte_add(page_offset) })
+}
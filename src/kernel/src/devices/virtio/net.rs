@@ -0,0 +1,329 @@
+//! Virtio network device driver, which attaches each device to the [network stack](crate::net).
+//!
+//! Each queue has a packet buffer of DMA memory per descriptor, large enough for the virtio-net header and a whole
+//! Ethernet frame. Every receive buffer is kept available to the device; when it fills some, it interrupts, and the
+//! handler defers their processing (see [`crate::task::workqueue`]), which hands each frame to the network stack
+//! before making the buffer available again. Transmit buffers are reclaimed as frames are sent.
+
+use super::{Queue, Segment, Transport};
+use crate::{
+    devices::pci::{
+        driver::{Driver, Match},
+        tree::Device,
+    },
+    interrupts::{InterruptCell, irq::IrqTimestamp},
+    mem::HigherHalfDirectMap,
+    net::{self, MacAddress, ethernet},
+    sync::SpinMutex,
+};
+use alloc::{collections::btree_map::BTreeMap, format, string::String, sync::Arc, vec::Vec};
+use core::{
+    num::NonZero,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use libsys::{Address, Frame, page_size};
+
+/// Virtio device type of network devices.
+const DEVICE_TYPE: u16 = 1;
+
+/// Device provides its hardware address.
+const F_MAC: u64 = 1 << 5;
+
+/// Offset of `mac` within the device configuration structure.
+const MAC_OFFSET: usize = 0;
+
+const RECEIVE_QUEUE: u16 = 0;
+const TRANSMIT_QUEUE: u16 = 1;
+/// MSI-X table entry the receive queue is routed to.
+const RECEIVE_QUEUE_ENTRY: u16 = 0;
+
+/// Length of the header preceding every packet, with [`super::F_VERSION_1`] negotiated.
+const HEADER_LEN: usize = 12;
+
+/// Length of each packet buffer, which holds the header and a whole Ethernet frame.
+const PACKET_BUFFER_SIZE: usize = 2048;
+
+/// Packet buffers of each queue; one per descriptor of a queue of [`super::MAX_QUEUE_SIZE`].
+const BUFFERS: usize = 16;
+
+/// Name packet buffers are tagged with in the DMA audit.
+const DMA_OWNER: &str = "virtio-net";
+
+static DRIVER: Driver = Driver {
+    name: "virtio-net",
+    matches: &[
+        Match::Id {
+            vendor_id: super::VENDOR_ID,
+            device_id: super::MODERN_DEVICE_ID_BASE + DEVICE_TYPE,
+        },
+        // Transitional devices also provide the modern transport.
+        Match::Id {
+            vendor_id: super::VENDOR_ID,
+            device_id: 0x1000,
+        },
+    ],
+    probe,
+};
+
+/// Registers the virtio network driver.
+pub fn register() {
+    if let Err(error) = crate::devices::pci::driver::register(&DRIVER) {
+        warn!("Failed to register the virtio network driver: {error}");
+    }
+}
+
+/// Every device the driver has brought up, searched by the receive handler for its vector.
+static NICS: SpinMutex<Vec<Arc<VirtioNet>>> = SpinMutex::new(Vec::new());
+
+/// Index the next device is named by.
+static NEXT_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+fn probe(device: &'static Device) -> bool {
+    match VirtioNet::new(device) {
+        Ok(nic) => {
+            let nic = Arc::new(nic);
+            crate::interrupts::uninterruptable(|| NICS.lock().push(nic.clone()));
+            net::attach(nic.clone());
+
+            // Frames may have arrived before the vector was registered.
+            nic.poll();
+
+            true
+        }
+
+        Err(error) => {
+            warn!(
+                "Failed to initialize virtio network device {}: {error}",
+                device.address()
+            );
+            false
+        }
+    }
+}
+
+/// Handles the receive interrupt of a device, deferring the processing of whatever it received.
+fn on_receive(vector: u8, _: IrqTimestamp) {
+    let nic = NICS.lock().iter().find(|nic| nic.vector == vector).cloned();
+
+    if let Some(nic) = nic {
+        crate::task::workqueue::defer(move || nic.poll());
+    }
+}
+
+/// Packet buffers of a queue, in contiguous DMA memory.
+struct Buffers {
+    frame: Address<Frame>,
+    memory: NonNull<u8>,
+}
+
+impl Buffers {
+    fn frame_count() -> NonZero<usize> {
+        NonZero::new((BUFFERS * PACKET_BUFFER_SIZE).div_ceil(page_size())).unwrap()
+    }
+
+    fn new() -> Result<Self, super::Error> {
+        let frame = crate::mem::dma::allocate(Self::frame_count(), DMA_OWNER)?;
+
+        Ok(Self {
+            frame,
+            memory: NonNull::new(HigherHalfDirectMap::frame_to_page(frame).as_ptr()).unwrap(),
+        })
+    }
+
+    fn physical(&self, index: usize) -> u64 {
+        u64::try_from(self.frame.get().get() + (index * PACKET_BUFFER_SIZE)).unwrap()
+    }
+
+    /// Packet buffer `index`, which the device mustn't currently own.
+    fn get(&mut self, index: usize) -> &mut [u8] {
+        debug_assert!(index < BUFFERS);
+
+        // Safety: There are `BUFFERS` buffers of `PACKET_BUFFER_SIZE` bytes, and the driver owns this one.
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                self.memory.byte_add(index * PACKET_BUFFER_SIZE).as_ptr(),
+                PACKET_BUFFER_SIZE,
+            )
+        }
+    }
+}
+
+struct Nic {
+    transport: Transport,
+    receive: Queue,
+    transmit: Queue,
+    receive_buffers: Buffers,
+    transmit_buffers: Buffers,
+    /// Buffer each chain owned by the device was submitted with, by the chain's head.
+    receiving: BTreeMap<u16, usize>,
+    transmitting: BTreeMap<u16, usize>,
+    /// Transmit buffers owned by the driver.
+    free_transmit: Vec<usize>,
+}
+
+// Safety: Device memory & packet buffers are only accessed through the device's lock.
+unsafe impl Send for Nic {}
+
+impl Nic {
+    /// Makes the receive buffer `index` available to the device.
+    fn post_receive(&mut self, index: usize) {
+        let segment = Segment {
+            address: self.receive_buffers.physical(index),
+            len: u32::try_from(PACKET_BUFFER_SIZE).unwrap(),
+            device_writable: true,
+        };
+
+        match self.receive.submit(&[segment]) {
+            Some(head) => {
+                self.receiving.insert(head, index);
+            }
+
+            None => warn!("Virtio network receive queue is full; buffer {index} was lost."),
+        }
+    }
+
+    /// Takes every frame the device has received, making their buffers available again.
+    fn take_received(&mut self) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        while let Some((head, len)) = self.receive.pop_used_chain() {
+            let Some(index) = self.receiving.remove(&head) else {
+                continue;
+            };
+
+            let len = len.min(PACKET_BUFFER_SIZE);
+            if let Some(frame) = self.receive_buffers.get(index).get(HEADER_LEN..len) {
+                frames.push(Vec::from(frame));
+            }
+
+            self.post_receive(index);
+        }
+
+        frames
+    }
+
+    /// Returns the buffers of every frame the device has sent to the free list.
+    fn reclaim_transmit(&mut self) {
+        while let Some((head, _)) = self.transmit.pop_used_chain() {
+            if let Some(index) = self.transmitting.remove(&head) {
+                self.free_transmit.push(index);
+            }
+        }
+    }
+
+    fn transmit(&mut self, frame: &[u8]) -> Result<(), net::Error> {
+        if frame.len() > ethernet::MAX_FRAME_LEN {
+            return Err(net::Error::TooLong(frame.len()));
+        }
+
+        self.reclaim_transmit();
+        let index = self.free_transmit.pop().ok_or(net::Error::QueueFull)?;
+
+        let buffer = self.transmit_buffers.get(index);
+        // No offloads are negotiated, so the header is empty.
+        buffer[..HEADER_LEN].fill(0);
+        buffer[HEADER_LEN..][..frame.len()].copy_from_slice(frame);
+
+        let segment = Segment {
+            address: self.transmit_buffers.physical(index),
+            len: u32::try_from(HEADER_LEN + frame.len()).unwrap(),
+            device_writable: false,
+        };
+
+        match self.transmit.submit(&[segment]) {
+            Some(head) => {
+                self.transmitting.insert(head, index);
+                Ok(())
+            }
+
+            None => {
+                self.free_transmit.push(index);
+                Err(net::Error::QueueFull)
+            }
+        }
+    }
+}
+
+/// A virtio network device, as a network [`Interface`](net::Interface).
+pub struct VirtioNet {
+    name: String,
+    mac: MacAddress,
+    /// Vector received frames are signalled on.
+    vector: u8,
+    nic: InterruptCell<SpinMutex<Nic>>,
+}
+
+impl VirtioNet {
+    fn new(device: &Device) -> Result<Self, super::Error> {
+        let transport = Transport::new(device)?;
+        let features = transport.negotiate(F_MAC)?;
+        if (features & F_MAC) == 0 {
+            // Without a hardware address of its own, the device couldn't be addressed on the network.
+            transport.set_status(super::Status::FAILED);
+            return Err(super::Error::FeaturesRejected(features));
+        }
+
+        // Frames are only ever processed as they're signalled, so the device must be able to interrupt.
+        let vector = super::route_queue(
+            device,
+            &transport,
+            RECEIVE_QUEUE,
+            RECEIVE_QUEUE_ENTRY,
+            on_receive,
+        )?;
+
+        let mut receive = transport.setup_queue(RECEIVE_QUEUE)?;
+        receive.set_interrupts(true);
+        let transmit = transport.setup_queue(TRANSMIT_QUEUE)?;
+
+        let mut nic = Nic {
+            receive,
+            transmit,
+            receive_buffers: Buffers::new()?,
+            transmit_buffers: Buffers::new()?,
+            receiving: BTreeMap::new(),
+            transmitting: BTreeMap::new(),
+            free_transmit: (0..BUFFERS).collect(),
+            transport,
+        };
+
+        nic.transport.finish();
+        for index in 0..BUFFERS {
+            nic.post_receive(index);
+        }
+
+        let mut mac = [0; 6];
+        for (offset, byte) in mac.iter_mut().enumerate() {
+            *byte = nic.transport.read_device_config::<u8>(MAC_OFFSET + offset);
+        }
+
+        Ok(Self {
+            name: format!("vnet{}", NEXT_INDEX.fetch_add(1, Ordering::Relaxed)),
+            mac: MacAddress(mac),
+            vector,
+            nic: InterruptCell::new(SpinMutex::new(nic)),
+        })
+    }
+
+    /// Hands every frame the device has received to the network stack.
+    fn poll(&self) {
+        // Frames are taken with the device unlocked, so replies can be transmitted through it.
+        for frame in self.nic.with(|nic| nic.lock().take_received()) {
+            net::receive(self, &frame);
+        }
+    }
+}
+
+impl net::Interface for VirtioNet {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn mac(&self) -> MacAddress {
+        self.mac
+    }
+
+    fn transmit(&self, frame: &[u8]) -> Result<(), net::Error> {
+        self.nic.with(|nic| nic.lock().transmit(frame))
+    }
+}
@@ -0,0 +1,345 @@
+use super::Error;
+use crate::mem::HigherHalfDirectMap;
+use alloc::vec::Vec;
+use core::{
+    num::NonZero,
+    ptr::NonNull,
+    sync::atomic::{Ordering, fence},
+};
+use libsys::{Address, Frame, page_size};
+
+/// Largest queue the driver will set up, regardless of what the device supports.
+pub const MAX_QUEUE_SIZE: u16 = 16;
+/// [`MAX_QUEUE_SIZE`], for sizing the rings.
+const MAX_QUEUE_LEN: usize = 16;
+
+/// Size of the buffer backing each descriptor.
+pub const BUFFER_SIZE: usize = 256;
+
+/// Name queue memory is tagged with in the DMA audit.
+const DMA_OWNER: &str = "virtio";
+
+/// Descriptor continues in the descriptor named by its `next` field.
+const DESC_F_NEXT: u16 = 1 << 0;
+/// Descriptor is device-writable (otherwise device-readable).
+const DESC_F_WRITE: u16 = 1 << 1;
+
+/// Asks the device not to interrupt when it uses a descriptor, so the queue is polled.
+const AVAIL_F_NO_INTERRUPT: u16 = 1 << 0;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Descriptor {
+    address: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+/// Driver-provided buffer, submitted as one descriptor of a chain by [`Queue::submit`].
+#[derive(Debug, Clone, Copy)]
+pub struct Segment {
+    /// Physical address of the buffer, which must be DMA memory.
+    pub address: u64,
+    pub len: u32,
+    pub device_writable: bool,
+}
+
+/// Byte offsets of the rings within the queue's first frame.
+const DESCRIPTORS_OFFSET: usize = 0;
+const AVAILABLE_OFFSET: usize = size_of::<Descriptor>() * MAX_QUEUE_LEN;
+const USED_OFFSET: usize = (AVAILABLE_OFFSET + 6 + (2 * MAX_QUEUE_LEN)).next_multiple_of(4);
+
+/// A split virtqueue, in which every descriptor has its own fixed [`BUFFER_SIZE`] buffer.
+///
+/// Descriptors can instead be chained over buffers the driver provides (see [`Queue::submit`]), for requests made of
+/// several parts, or larger than [`BUFFER_SIZE`].
+///
+/// # Remarks
+///
+/// Rings are laid out in the queue's first frame, and descriptor buffers fill the frames after it. Queues start out
+/// polled; the device only interrupts once [`Queue::set_interrupts`] enables it.
+pub struct Queue {
+    index: u16,
+    size: u16,
+    frame: Address<Frame>,
+    rings: NonNull<u8>,
+    buffers: NonNull<u8>,
+    notify: NonNull<u16>,
+
+    /// Descriptors not currently owned by the device.
+    free: Vec<u16>,
+    /// Value of the used ring index when it was last read.
+    last_used: u16,
+}
+
+// Safety: Queue memory is only accessed through the owning driver.
+unsafe impl Send for Queue {}
+
+impl Queue {
+    fn frame_count() -> NonZero<usize> {
+        NonZero::new(1 + (BUFFER_SIZE * MAX_QUEUE_LEN).div_ceil(page_size())).unwrap()
+    }
+
+    pub(super) fn new(index: u16, size: u16, notify: NonNull<u16>) -> Result<Self, Error> {
+        let frame_count = Self::frame_count();
+        let frame = crate::mem::dma::allocate(frame_count, DMA_OWNER)?;
+        let rings = NonNull::new(HigherHalfDirectMap::frame_to_page(frame).as_ptr()).unwrap();
+
+        // Safety: The frames were just allocated, and are accessible through the HHDM.
+        unsafe { rings.write_bytes(0, frame_count.get() * page_size()) };
+
+        let queue = Self {
+            index,
+            size,
+            frame,
+            rings,
+            // Safety: The buffers begin in the frame after the rings.
+            buffers: unsafe { rings.byte_add(page_size()) },
+            notify,
+            free: (0..size).rev().collect(),
+            last_used: 0,
+        };
+
+        // Safety: The available ring's flags are its first field.
+        unsafe {
+            queue
+                .available_field(0)
+                .write_volatile(AVAIL_F_NO_INTERRUPT)
+        };
+
+        Ok(queue)
+    }
+
+    #[inline]
+    pub const fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// Physical addresses of the descriptor table, available ring, and used ring.
+    pub(super) fn physical_addresses(&self) -> (u64, u64, u64) {
+        let base = u64::try_from(self.frame.get().get()).unwrap();
+
+        (
+            base + u64::try_from(DESCRIPTORS_OFFSET).unwrap(),
+            base + u64::try_from(AVAILABLE_OFFSET).unwrap(),
+            base + u64::try_from(USED_OFFSET).unwrap(),
+        )
+    }
+
+    fn descriptor(&self, id: u16) -> NonNull<Descriptor> {
+        debug_assert!(id < self.size);
+
+        // Safety: The descriptor table holds `MAX_QUEUE_SIZE` descriptors.
+        unsafe {
+            self.rings
+                .byte_add(DESCRIPTORS_OFFSET)
+                .cast::<Descriptor>()
+                .add(usize::from(id))
+        }
+    }
+
+    fn buffer_ptr(&self, id: u16) -> NonNull<u8> {
+        // Safety: There is a buffer for each of `MAX_QUEUE_SIZE` descriptors.
+        unsafe { self.buffers.byte_add(usize::from(id) * BUFFER_SIZE) }
+    }
+
+    fn buffer_physical(&self, id: u16) -> u64 {
+        u64::try_from(self.frame.get().get() + page_size() + (usize::from(id) * BUFFER_SIZE))
+            .unwrap()
+    }
+
+    /// Pointer to the available ring's `offset`th 16-bit field.
+    fn available_field(&self, offset: usize) -> NonNull<u16> {
+        // Safety: The available ring is `6 + (2 * MAX_QUEUE_SIZE)` bytes long.
+        unsafe {
+            self.rings
+                .byte_add(AVAILABLE_OFFSET)
+                .cast::<u16>()
+                .add(offset)
+        }
+    }
+
+    /// Asks the device to interrupt (or not) when it uses descriptors.
+    ///
+    /// # Remarks
+    ///
+    /// This is only a hint; the device may interrupt regardless, and the used ring should be drained either way.
+    pub fn set_interrupts(&mut self, enabled: bool) {
+        let flags = if enabled { 0 } else { AVAIL_F_NO_INTERRUPT };
+
+        // Safety: The available ring's flags are its first field.
+        unsafe { self.available_field(0).write_volatile(flags) };
+    }
+
+    /// Makes descriptor `id` (of `len` bytes, and `flags`) available to the device.
+    fn make_available(&mut self, id: u16, len: usize, flags: u16) {
+        // Safety: Descriptor is owned by the driver until it's made available.
+        unsafe {
+            self.descriptor(id).write_volatile(Descriptor {
+                address: self.buffer_physical(id),
+                len: u32::try_from(len).unwrap(),
+                flags,
+                next: 0,
+            });
+        }
+
+        self.publish(id);
+    }
+
+    /// Publishes the descriptor (chain) `id` in the available ring.
+    fn publish(&mut self, id: u16) {
+        // Safety: The available ring's index is its second field, followed by the ring itself.
+        unsafe {
+            let available_index = self.available_field(1).read_volatile();
+            self.available_field(2 + usize::from(available_index % self.size))
+                .write_volatile(id);
+
+            // The ring entry must be visible before the index that publishes it.
+            fence(Ordering::Release);
+            self.available_field(1)
+                .write_volatile(available_index.wrapping_add(1));
+        }
+    }
+
+    /// Notifies the device that descriptors have been made available.
+    pub fn notify(&self) {
+        fence(Ordering::SeqCst);
+
+        // Safety: Notification address was provided by the device for this queue.
+        unsafe { self.notify.write_volatile(self.index) };
+    }
+
+    /// Makes every free descriptor available to the device to write into.
+    pub fn fill_receive(&mut self) {
+        while let Some(id) = self.free.pop() {
+            self.make_available(id, BUFFER_SIZE, DESC_F_WRITE);
+        }
+
+        self.notify();
+    }
+
+    /// Hands `data` (at most [`BUFFER_SIZE`] bytes) to the device to read, returning `false` if no descriptor is free.
+    pub fn send(&mut self, data: &[u8]) -> bool {
+        debug_assert!(data.len() <= BUFFER_SIZE);
+
+        let Some(id) = self.free.pop() else {
+            return false;
+        };
+
+        // Safety: Descriptor is owned by the driver, and its buffer is `BUFFER_SIZE` bytes long.
+        unsafe {
+            core::ptr::copy_nonoverlapping(data.as_ptr(), self.buffer_ptr(id).as_ptr(), data.len());
+        }
+
+        self.make_available(id, data.len(), 0);
+        self.notify();
+
+        true
+    }
+
+    /// Hands the chain of `segments` to the device, returning the ID of its first descriptor, or `None` if too few
+    /// descriptors are free.
+    ///
+    /// # Remarks
+    ///
+    /// Segments the device reads must precede those it writes. The buffers must remain valid until the chain is
+    /// returned by [`Self::pop_used_chain`].
+    pub fn submit(&mut self, segments: &[Segment]) -> Option<u16> {
+        if segments.is_empty() || self.free.len() < segments.len() {
+            return None;
+        }
+
+        let ids = self.free.split_off(self.free.len() - segments.len());
+        for (index, (id, segment)) in ids.iter().zip(segments).enumerate() {
+            let next = ids.get(index + 1).copied();
+            let mut flags = 0;
+            if segment.device_writable {
+                flags |= DESC_F_WRITE;
+            }
+            if next.is_some() {
+                flags |= DESC_F_NEXT;
+            }
+
+            // Safety: Descriptor is owned by the driver until it's made available.
+            unsafe {
+                self.descriptor(*id).write_volatile(Descriptor {
+                    address: segment.address,
+                    len: segment.len,
+                    flags,
+                    next: next.unwrap_or(0),
+                });
+            }
+        }
+
+        self.publish(ids[0]);
+        self.notify();
+
+        Some(ids[0])
+    }
+
+    /// Takes the next chain the device has finished with, returning the ID of its first descriptor, and the number of
+    /// bytes the device wrote.
+    ///
+    /// Every descriptor of the chain is returned to the driver's free list.
+    pub fn pop_used_chain(&mut self) -> Option<(u16, usize)> {
+        let (head, len) = self.next_used()?;
+
+        let mut id = head;
+        loop {
+            // Safety: The device has finished with the chain, so its descriptors are owned by the driver.
+            let descriptor = unsafe { self.descriptor(id).read_volatile() };
+            self.free.push(id);
+
+            if (descriptor.flags & DESC_F_NEXT) == 0 || descriptor.next >= self.size {
+                break;
+            }
+            id = descriptor.next;
+        }
+
+        Some((head, len))
+    }
+
+    /// Takes the next element of the used ring, as the ID of the descriptor (chain) it returns, and the number of bytes
+    /// the device wrote.
+    fn next_used(&mut self) -> Option<(u16, usize)> {
+        // Safety: The used ring begins with 16-bit flags & index fields, followed by 8-byte elements.
+        let used = unsafe { self.rings.byte_add(USED_OFFSET) };
+        // Safety: See above.
+        let used_index = unsafe { used.cast::<u16>().add(1).read_volatile() };
+        if used_index == self.last_used {
+            return None;
+        }
+
+        // The element must be read after the index that published it.
+        fence(Ordering::Acquire);
+
+        let slot = usize::from(self.last_used % self.size);
+        // Safety: See above.
+        let [id, len] = unsafe {
+            used.byte_add(4)
+                .cast::<[u32; 2]>()
+                .add(slot)
+                .read_volatile()
+        };
+        self.last_used = self.last_used.wrapping_add(1);
+
+        Some((u16::try_from(id).unwrap(), usize::try_from(len).unwrap()))
+    }
+
+    /// Takes the next descriptor the device has finished with, passing its buffer (as written by the device) to `func`.
+    ///
+    /// The descriptor is returned to the driver's free list.
+    pub fn pop_used<T>(&mut self, func: impl FnOnce(&[u8]) -> T) -> Option<T> {
+        let (id, len) = self.next_used()?;
+        let len = len.min(BUFFER_SIZE);
+
+        // Safety: The device has finished with the buffer, and wrote `len` bytes into it.
+        let buffer = unsafe { core::slice::from_raw_parts(self.buffer_ptr(id).as_ptr(), len) };
+        let result = func(buffer);
+
+        self.free.push(id);
+
+        Some(result)
+    }
+}
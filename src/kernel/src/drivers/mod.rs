@@ -1,6 +1,4 @@
 #![allow(unused)]
 
-// pub mod ahci;
 // pub mod graphics;
-// pub mod nvme;
 // pub mod sata;
@@ -0,0 +1,618 @@
+//! Read-only FAT32 filesystem over a block device, by which the EFI system partition is mounted at [`ESP_MOUNT_PATH`].
+//!
+//! Directories are read whole as they're looked up in, with long file names (VFAT) preferred over the short (8.3)
+//! names they accompany; names are matched without regard to ASCII case, as FAT does. Files are read a cluster at a
+//! time, following their chain through the first FAT, whose most recently read page is cached. Every read goes
+//! through the device's [request queue](RequestQueue). Nothing is ever written back, so the filesystem's dirty flags &
+//! FSInfo sector are ignored.
+
+use crate::{
+    devices::block::{self, BlockDevice, RequestQueue, SECTOR_SIZE, gpt},
+    fs::vfs::{self, FileHandle, NodeKind, Stat},
+    sync::SpinMutex,
+};
+use alloc::{boxed::Box, string::String, sync::Arc, vec, vec::Vec};
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("failed to read the device: {0}")]
+    Block(#[from] block::Error),
+
+    #[error("volume is not FAT32: {0}")]
+    NotFat32(&'static str),
+
+    #[error("cluster chain is malformed at cluster {0:#X}")]
+    Chain(u32),
+
+    #[error("file's cluster chain ends before its length")]
+    Truncated,
+}
+
+/// Path the EFI system partition is mounted at.
+pub const ESP_MOUNT_PATH: &str = "/boot";
+
+/// Boot sector signature, in its last two bytes.
+const BOOT_SIGNATURE: [u8; 2] = [0x55, 0xAA];
+
+/// FAT entries are 28 bits; the top four are reserved.
+const ENTRY_MASK: u32 = 0x0FFF_FFFF;
+/// FAT entries marking a bad cluster, and (at or above) the end of a chain.
+const ENTRY_BAD: u32 = 0x0FFF_FFF7;
+const ENTRY_END: u32 = 0x0FFF_FFF8;
+/// First cluster of the data region; lower numbers are reserved.
+const FIRST_CLUSTER: u32 = 2;
+
+const DIR_ENTRY_SIZE: usize = 32;
+/// First byte of the name of the entry ending a directory, and of a deleted entry.
+const DIR_END: u8 = 0x00;
+const DIR_DELETED: u8 = 0xE5;
+
+/// Directory entry attributes.
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+/// Attributes of a long file name entry.
+const ATTR_LONG_NAME: u8 = 0x0F;
+
+/// Short name case flags (as set by Windows NT): the base name, or the extension, is lowercase.
+const CASE_LOWER_BASE: u8 = 0x08;
+const CASE_LOWER_EXTENSION: u8 = 0x10;
+
+/// Long file name entries: the flag on the sequence number of the last (first stored) entry, the mask of the number
+/// itself, and the UTF-16 code units each holds.
+const LFN_LAST: u8 = 0x40;
+const LFN_SEQUENCE_MASK: u8 = 0x1F;
+const LFN_UNITS: usize = 13;
+/// Offsets of the code units within a long file name entry.
+const LFN_UNIT_OFFSETS: [usize; LFN_UNITS] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+/// Sectors of the FAT read (and cached) at once.
+const FAT_CACHE_SECTORS: usize = 8;
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(*bytes[offset..].first_chunk().unwrap())
+}
+
+/// Layout of a volume, in the device's sectors, as described by its BIOS parameter block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Geometry {
+    /// First sector of the first FAT.
+    fat_sector: u64,
+    /// First sector of cluster 2.
+    data_sector: u64,
+    sectors_per_cluster: u32,
+    /// Number of data clusters, so the last cluster is this plus one.
+    cluster_count: u32,
+    root_cluster: u32,
+}
+
+impl Geometry {
+    fn parse(boot_sector: &[u8]) -> Result<Self, Error> {
+        if boot_sector[510..512] != BOOT_SIGNATURE {
+            return Err(Error::NotFat32("boot sector signature is missing"));
+        }
+
+        // Volumes with larger logical sectors are addressed in the device's sectors.
+        let bytes_per_sector = usize::from(u16_at(boot_sector, 11));
+        if !bytes_per_sector.is_power_of_two() || !(SECTOR_SIZE..=4096).contains(&bytes_per_sector)
+        {
+            return Err(Error::NotFat32("sector size is unsupported"));
+        }
+        let scale = u32::try_from(bytes_per_sector / SECTOR_SIZE).unwrap();
+
+        let sectors_per_cluster = u32::from(boot_sector[13]);
+        let reserved_sectors = u32::from(u16_at(boot_sector, 14));
+        let fat_count = u32::from(boot_sector[16]);
+        if !sectors_per_cluster.is_power_of_two() || reserved_sectors == 0 || fat_count == 0 {
+            return Err(Error::NotFat32("BIOS parameter block is malformed"));
+        }
+
+        // FAT12 & FAT16 volumes have a fixed root directory, and a 16-bit FAT size.
+        if u16_at(boot_sector, 17) != 0 || u16_at(boot_sector, 22) != 0 {
+            return Err(Error::NotFat32("volume is FAT12 or FAT16"));
+        }
+
+        let total_sectors = match u16_at(boot_sector, 19) {
+            0 => u32_at(boot_sector, 32),
+            total_sectors => u32::from(total_sectors),
+        };
+        let fat_sectors = u32_at(boot_sector, 36);
+        let data_start = fat_sectors
+            .checked_mul(fat_count)
+            .and_then(|fats| fats.checked_add(reserved_sectors))
+            .filter(|data_start| *data_start < total_sectors)
+            .ok_or(Error::NotFat32("FATs lie beyond the end of the volume"))?;
+
+        let cluster_count = (total_sectors - data_start) / sectors_per_cluster;
+        // Each FAT must have an entry for every cluster, including the two reserved.
+        if (u64::from(cluster_count) + 2) * 4
+            > u64::from(fat_sectors) * u64::try_from(bytes_per_sector).unwrap()
+        {
+            return Err(Error::NotFat32("FAT is too small for the volume"));
+        }
+
+        let root_cluster = u32_at(boot_sector, 44);
+        if !(FIRST_CLUSTER..(cluster_count + FIRST_CLUSTER)).contains(&root_cluster) {
+            return Err(Error::NotFat32("root directory cluster is out of range"));
+        }
+
+        Ok(Self {
+            fat_sector: u64::from(reserved_sectors) * u64::from(scale),
+            data_sector: u64::from(data_start) * u64::from(scale),
+            sectors_per_cluster: sectors_per_cluster * scale,
+            cluster_count,
+            root_cluster,
+        })
+    }
+
+    fn cluster_size(&self) -> usize {
+        usize::try_from(self.sectors_per_cluster).unwrap() * SECTOR_SIZE
+    }
+
+    fn is_data_cluster(&self, cluster: u32) -> bool {
+        (FIRST_CLUSTER..(self.cluster_count + FIRST_CLUSTER)).contains(&cluster)
+    }
+
+    fn cluster_sector(&self, cluster: u32) -> u64 {
+        self.data_sector
+            + (u64::from(cluster - FIRST_CLUSTER) * u64::from(self.sectors_per_cluster))
+    }
+}
+
+/// An entry of a directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DirEntry {
+    name: String,
+    /// First cluster of the entry's contents, which is zero for empty files.
+    cluster: u32,
+    /// Length of the file in bytes, which is zero for directories.
+    len: u32,
+    is_directory: bool,
+}
+
+/// Checksum of a short name, which its long file name entries hold.
+fn short_name_checksum(name: &[u8; 11]) -> u8 {
+    name.iter()
+        .fold(0u8, |sum, byte| sum.rotate_right(1).wrapping_add(*byte))
+}
+
+/// Short name of an entry, as `BASE.EXT`.
+fn short_name(name: &[u8; 11], case: u8) -> String {
+    let part = |bytes: &[u8], lower: bool| -> String {
+        let part = bytes
+            .iter()
+            .map(|byte| {
+                if lower {
+                    byte.to_ascii_lowercase()
+                } else {
+                    *byte
+                }
+            })
+            .map(char::from)
+            .collect::<String>();
+
+        String::from(part.trim_end_matches(' '))
+    };
+
+    let base = part(&name[..8], (case & CASE_LOWER_BASE) != 0);
+    let extension = part(&name[8..], (case & CASE_LOWER_EXTENSION) != 0);
+    if extension.is_empty() {
+        base
+    } else {
+        base + "." + &extension
+    }
+}
+
+/// Decodes a directory's entries, assembling each long file name from the entries preceding its short name.
+#[derive(Debug, Default)]
+struct DirReader {
+    long_name: Vec<u16>,
+    /// Sequence number of the long file name entry expected next (counting down to 1), and their checksum.
+    expected: Option<(u8, u8)>,
+}
+
+impl DirReader {
+    /// Decodes the 32-byte entry `bytes`, returning `None` at the end of the directory, and `Some(None)` for entries
+    /// which don't complete a directory entry.
+    fn feed(&mut self, bytes: &[u8]) -> Option<Option<DirEntry>> {
+        let attributes = bytes[11];
+        match bytes[0] {
+            DIR_END => return None,
+            DIR_DELETED => {
+                self.expected = None;
+                return Some(None);
+            }
+            _ if attributes == ATTR_LONG_NAME => {
+                self.feed_long_name(bytes);
+                return Some(None);
+            }
+            _ => {}
+        }
+
+        let expected = self.expected.take();
+        if (attributes & ATTR_VOLUME_ID) != 0 {
+            return Some(None);
+        }
+
+        let raw_name = bytes[..11].first_chunk::<11>().unwrap();
+        let name = match expected {
+            Some((1, checksum)) if checksum == short_name_checksum(raw_name) => {
+                let units = self.long_name.iter().copied().take_while(|unit| *unit != 0);
+                char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+            _ => short_name(raw_name, bytes[12]),
+        };
+
+        Some(Some(DirEntry {
+            name,
+            cluster: (u32::from(u16_at(bytes, 20)) << 16) | u32::from(u16_at(bytes, 26)),
+            len: u32_at(bytes, 28),
+            is_directory: (attributes & ATTR_DIRECTORY) != 0,
+        }))
+    }
+
+    fn feed_long_name(&mut self, bytes: &[u8]) {
+        let sequence = bytes[0] & LFN_SEQUENCE_MASK;
+        let checksum = bytes[13];
+
+        if (bytes[0] & LFN_LAST) != 0 {
+            self.long_name = vec![0xFFFF; usize::from(sequence) * LFN_UNITS];
+        } else if self.expected != Some((sequence + 1, checksum)) {
+            // An entry out of sequence orphans the name assembled so far.
+            self.expected = None;
+            return;
+        }
+
+        if sequence == 0 {
+            self.expected = None;
+            return;
+        }
+
+        let start = usize::from(sequence - 1) * LFN_UNITS;
+        for (index, offset) in LFN_UNIT_OFFSETS.iter().enumerate() {
+            self.long_name[start + index] = u16_at(bytes, *offset);
+        }
+        self.expected = Some((sequence, checksum));
+    }
+}
+
+/// A FAT32 volume, which its nodes share.
+struct Volume {
+    queue: Arc<RequestQueue>,
+    geometry: Geometry,
+    /// First sector of the most recently read sectors of the FAT, and their contents.
+    fat_cache: SpinMutex<Option<(u64, Box<[u8; SECTOR_SIZE * FAT_CACHE_SECTORS]>)>>,
+}
+
+impl Volume {
+    fn new(queue: Arc<RequestQueue>) -> Result<Self, Error> {
+        let mut boot_sector = vec![0u8; SECTOR_SIZE];
+        queue.read(0, &mut boot_sector)?;
+        let geometry = Geometry::parse(&boot_sector)?;
+
+        let clusters_end = geometry.cluster_sector(geometry.cluster_count + FIRST_CLUSTER);
+        if clusters_end > queue.device().sector_count() {
+            return Err(Error::NotFat32("volume is larger than its device"));
+        }
+
+        Ok(Self {
+            queue,
+            geometry,
+            fat_cache: SpinMutex::new(None),
+        })
+    }
+
+    /// Cluster following `cluster` in its chain, or `None` at the end of the chain.
+    fn next_cluster(&self, cluster: u32) -> Result<Option<u32>, Error> {
+        const CACHE_LEN: usize = SECTOR_SIZE * FAT_CACHE_SECTORS;
+
+        let offset = usize::try_from(cluster).unwrap() * 4;
+        let sector = self.geometry.fat_sector
+            + u64::try_from((offset / CACHE_LEN) * FAT_CACHE_SECTORS).unwrap();
+        let offset = offset % CACHE_LEN;
+
+        let cached = crate::interrupts::uninterruptable(|| {
+            self.fat_cache
+                .lock()
+                .as_ref()
+                .filter(|(cached, _)| *cached == sector)
+                .map(|(_, contents)| u32_at(contents.as_slice(), offset))
+        });
+
+        // The device isn't read under the cache's lock, as it may wait on the transfer.
+        let entry = match cached {
+            Some(entry) => entry,
+            None => {
+                // FAT32 volumes hold far more than a page of data past their FATs, so these sectors lie within the volume.
+                let mut contents = Box::new([0u8; CACHE_LEN]);
+                self.queue.read(sector, contents.as_mut_slice())?;
+                let entry = u32_at(contents.as_slice(), offset);
+                crate::interrupts::uninterruptable(|| {
+                    *self.fat_cache.lock() = Some((sector, contents));
+                });
+
+                entry
+            }
+        } & ENTRY_MASK;
+
+        match entry {
+            ENTRY_END.. => Ok(None),
+            entry if entry != ENTRY_BAD && self.geometry.is_data_cluster(entry) => Ok(Some(entry)),
+            _ => Err(Error::Chain(cluster)),
+        }
+    }
+
+    /// Reads the whole of `cluster` into `buf`, which must be a cluster long.
+    fn read_cluster(&self, cluster: u32, buf: &mut [u8]) -> Result<(), Error> {
+        if !self.geometry.is_data_cluster(cluster) {
+            return Err(Error::Chain(cluster));
+        }
+
+        self.queue
+            .read(self.geometry.cluster_sector(cluster), buf)
+            .map_err(Error::from)
+    }
+
+    /// Entries of the directory starting at `cluster`, besides `.` and `..`.
+    fn read_dir(&self, cluster: u32) -> Result<Vec<DirEntry>, Error> {
+        let mut entries = Vec::new();
+        let mut reader = DirReader::default();
+        let mut contents = vec![0u8; self.geometry.cluster_size()];
+        let mut cluster = Some(cluster);
+        // A chain can't be longer than the volume, so a longer one must loop.
+        let mut remaining = self.geometry.cluster_count;
+
+        while let Some(current) = cluster {
+            remaining = remaining.checked_sub(1).ok_or(Error::Chain(current))?;
+            self.read_cluster(current, &mut contents)?;
+
+            for bytes in contents.chunks_exact(DIR_ENTRY_SIZE) {
+                match reader.feed(bytes) {
+                    None => return Ok(entries),
+                    Some(Some(entry)) if entry.name != "." && entry.name != ".." => {
+                        entries.push(entry)
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            cluster = self.next_cluster(current)?;
+        }
+
+        Ok(entries)
+    }
+
+    fn root_entry(&self) -> DirEntry {
+        DirEntry {
+            name: String::new(),
+            cluster: self.geometry.root_cluster,
+            len: 0,
+            is_directory: true,
+        }
+    }
+}
+
+fn to_vfs_error(error: Error) -> vfs::Error {
+    warn!("FAT32 I/O failed: {error}");
+
+    vfs::Error::Io
+}
+
+/// A FAT32 volume, as a filesystem of the VFS.
+pub struct Fat32(Arc<Volume>);
+
+impl Fat32 {
+    /// Reads the boot sector of the volume on the device of `queue`.
+    pub fn new(queue: Arc<RequestQueue>) -> Result<Self, Error> {
+        Volume::new(queue).map(|volume| Self(Arc::new(volume)))
+    }
+}
+
+impl vfs::FileSystem for Fat32 {
+    fn name(&self) -> &'static str {
+        "fat32"
+    }
+
+    fn root(&self) -> Result<Arc<dyn vfs::Node>, vfs::Error> {
+        Ok(Arc::new(FatNode {
+            fs: self.0.clone(),
+            entry: self.0.root_entry(),
+        }))
+    }
+}
+
+struct FatNode {
+    fs: Arc<Volume>,
+    entry: DirEntry,
+}
+
+impl vfs::Node for FatNode {
+    fn stat(&self) -> Stat {
+        if self.entry.is_directory {
+            Stat::new(NodeKind::Directory, 0)
+        } else {
+            Stat::new(NodeKind::File, usize::try_from(self.entry.len).unwrap())
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn vfs::Node>, vfs::Error> {
+        if !self.entry.is_directory {
+            return Err(vfs::Error::NotADirectory);
+        }
+
+        let entry = self
+            .fs
+            .read_dir(self.entry.cluster)
+            .map_err(to_vfs_error)?
+            .into_iter()
+            .find(|entry| entry.name.eq_ignore_ascii_case(name))
+            .ok_or(vfs::Error::NotFound)?;
+
+        Ok(Arc::new(FatNode {
+            fs: self.fs.clone(),
+            entry,
+        }))
+    }
+
+    fn open(&self) -> Result<Box<dyn FileHandle>, vfs::Error> {
+        if self.entry.is_directory {
+            return Err(vfs::Error::IsADirectory);
+        }
+
+        Ok(Box::new(FatFile {
+            fs: self.fs.clone(),
+            len: usize::try_from(self.entry.len).unwrap(),
+            offset: 0,
+            cluster: Some(self.entry.cluster).filter(|cluster| *cluster != 0),
+            contents: Vec::new(),
+        }))
+    }
+}
+
+struct FatFile {
+    fs: Arc<Volume>,
+    len: usize,
+    offset: usize,
+    /// Cluster holding the read position, and its contents once read.
+    cluster: Option<u32>,
+    contents: Vec<u8>,
+}
+
+impl FatFile {
+    /// Reads from the read position to the end of its cluster (or the file) into `buf`, returning the bytes read.
+    fn read_within_cluster(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let cluster_size = self.fs.geometry.cluster_size();
+        let Some(cluster) = self.cluster else {
+            return Err(Error::Truncated);
+        };
+
+        if self.contents.is_empty() {
+            self.contents.resize(cluster_size, 0);
+            self.fs.read_cluster(cluster, &mut self.contents)?;
+        }
+
+        let start = self.offset % cluster_size;
+        let len = buf
+            .len()
+            .min(cluster_size - start)
+            .min(self.len - self.offset);
+        buf[..len].copy_from_slice(&self.contents[start..(start + len)]);
+        self.offset += len;
+
+        if self.offset % cluster_size == 0 && self.offset < self.len {
+            self.cluster = self.fs.next_cluster(cluster)?;
+            self.contents.clear();
+        }
+
+        Ok(len)
+    }
+}
+
+impl FileHandle for FatFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, vfs::Error> {
+        let mut read = 0;
+        while read < buf.len() && self.offset < self.len {
+            read += self
+                .read_within_cluster(&mut buf[read..])
+                .map_err(to_vfs_error)?;
+        }
+
+        Ok(read)
+    }
+}
+
+/// Mounts the first EFI system partition (see [`gpt`]) holding a FAT32 volume at [`ESP_MOUNT_PATH`].
+pub fn mount_esp() {
+    let Some((partition, fs)) = gpt::partitions()
+        .into_iter()
+        .filter(|partition| partition.is_efi_system())
+        .filter_map(|partition| block::queue(partition.name()).map(|queue| (partition, queue)))
+        .find_map(|(partition, queue)| match Fat32::new(queue) {
+            Ok(fs) => Some((partition, fs)),
+            Err(error) => {
+                warn!(
+                    "EFI system partition {} is unusable: {error}",
+                    partition.name()
+                );
+                None
+            }
+        })
+    else {
+        info!("No EFI system partition to mount.");
+        return;
+    };
+
+    debug!("Mounting EFI system partition {}.", partition.name());
+    if let Err(error) = vfs::mount(ESP_MOUNT_PATH, Arc::new(fs)) {
+        warn!("Failed to mount the EFI system partition: {error}");
+    }
+}
+
+crate::kernel_test! {
+    /// Boot sectors are decoded into the volume's layout, and directory entries into names, preferring long ones.
+    fn parses_fat32_structures() -> crate::tests::Outcome {
+        let mut boot_sector = [0u8; SECTOR_SIZE];
+        boot_sector[11..13].copy_from_slice(&512u16.to_le_bytes());
+        boot_sector[13] = 8;
+        boot_sector[14..16].copy_from_slice(&32u16.to_le_bytes());
+        boot_sector[16] = 2;
+        boot_sector[32..36].copy_from_slice(&0x10_0000u32.to_le_bytes());
+        boot_sector[36..40].copy_from_slice(&0x400u32.to_le_bytes());
+        boot_sector[44..48].copy_from_slice(&2u32.to_le_bytes());
+        boot_sector[510..].copy_from_slice(&BOOT_SIGNATURE);
+
+        let geometry = Geometry::parse(&boot_sector).map_err(|_| "valid boot sector was rejected")?;
+        if geometry.fat_sector != 32 || geometry.data_sector != 0x820 || geometry.cluster_sector(3) != 0x828 {
+            return Err("boot sector was decoded incorrectly");
+        }
+
+        boot_sector[22] = 1;
+        if !matches!(Geometry::parse(&boot_sector), Err(Error::NotFat32(_))) {
+            return Err("FAT16 boot sector was accepted");
+        }
+
+        // `README.TXT`, with a single long file name entry naming it `ReadMe.txt`.
+        let short = *b"README  TXT";
+        let mut long = [0u8; DIR_ENTRY_SIZE];
+        long[0] = LFN_LAST | 1;
+        long[11] = ATTR_LONG_NAME;
+        long[13] = short_name_checksum(&short);
+        for (unit, offset) in "ReadMe.txt".encode_utf16().chain([0]).zip(LFN_UNIT_OFFSETS) {
+            long[offset..(offset + 2)].copy_from_slice(&unit.to_le_bytes());
+        }
+
+        let mut entry = [0u8; DIR_ENTRY_SIZE];
+        entry[..11].copy_from_slice(&short);
+        entry[26..28].copy_from_slice(&5u16.to_le_bytes());
+        entry[28..32].copy_from_slice(&1234u32.to_le_bytes());
+
+        let mut reader = DirReader::default();
+        if reader.feed(&long) != Some(None) {
+            return Err("long file name entry completed an entry");
+        }
+        match reader.feed(&entry) {
+            Some(Some(DirEntry { name, cluster: 5, len: 1234, is_directory: false })) if name == "ReadMe.txt" => {}
+            _ => return Err("entry with a long file name was decoded incorrectly"),
+        }
+
+        // Without its long file name (whose checksum no longer matches), the short name is used.
+        long[13] ^= 1;
+        reader.feed(&long);
+        entry[12] = CASE_LOWER_EXTENSION;
+        match reader.feed(&entry) {
+            Some(Some(DirEntry { name, .. })) if name == "README.txt" => {}
+            _ => return Err("short name was decoded incorrectly"),
+        }
+
+        if reader.feed(&[0u8; DIR_ENTRY_SIZE]).is_some() {
+            return Err("end of the directory wasn't recognized");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+//! Per-task tables of file descriptors, through which userspace refers to the files it has opened.
+//!
+//! Each task's descriptors are its own, and are closed when it exits. A new descriptor is the lowest which is free,
+//! as with POSIX.
+
+use crate::{
+    cpu::local_state::LocalState,
+    fs::vfs::{self, FileHandle},
+    sync::SpinMutex,
+    task::Task,
+};
+use alloc::{boxed::Box, collections::btree_map::BTreeMap, sync::Arc};
+use uuid::Uuid;
+
+/// Maximum number of files a single task may have open.
+pub const MAX_FILES: usize = 64;
+
+/// Identifies an open file within the task which opened it.
+pub type Fd = usize;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no active task")]
+    NoActiveTask,
+
+    #[error("task has no open file with descriptor {0}")]
+    BadDescriptor(Fd),
+
+    #[error("task already has the maximum of {MAX_FILES} files open")]
+    TooManyFiles,
+
+    #[error(transparent)]
+    Vfs(#[from] vfs::Error),
+}
+
+/// File opened by a task, which is shared with a read in progress while its descriptor is closed.
+type OpenFile = Arc<SpinMutex<Box<dyn FileHandle>>>;
+
+static TABLES: SpinMutex<BTreeMap<Uuid, BTreeMap<Fd, OpenFile>>> = SpinMutex::new(BTreeMap::new());
+
+fn active_task_id() -> Result<Uuid, Error> {
+    LocalState::with_scheduler(|scheduler| scheduler.process().map(Task::id))
+        .ok_or(Error::NoActiveTask)
+}
+
+/// Opens the file at the absolute `path` for the active task, returning its descriptor.
+pub fn open(path: &str) -> Result<Fd, Error> {
+    let task_id = active_task_id()?;
+    let file = Arc::new(SpinMutex::new(vfs::open(path)?));
+
+    crate::interrupts::uninterruptable(|| {
+        let mut tables = TABLES.lock();
+        let files = tables.entry(task_id).or_default();
+        let fd = (0..MAX_FILES)
+            .find(|fd| !files.contains_key(fd))
+            .ok_or(Error::TooManyFiles)?;
+        files.insert(fd, file);
+
+        Ok(fd)
+    })
+}
+
+/// Reads from the active task's file `fd` into `buf`, returning the number of bytes read.
+pub fn read(fd: Fd, buf: &mut [u8]) -> Result<usize, Error> {
+    let task_id = active_task_id()?;
+    let file = crate::interrupts::uninterruptable(|| {
+        TABLES
+            .lock()
+            .get(&task_id)
+            .and_then(|files| files.get(&fd))
+            .cloned()
+            .ok_or(Error::BadDescriptor(fd))
+    })?;
+
+    crate::interrupts::uninterruptable(|| file.lock().read(buf)).map_err(Error::from)
+}
+
+/// Closes the active task's file `fd`.
+pub fn close(fd: Fd) -> Result<(), Error> {
+    let task_id = active_task_id()?;
+
+    // The file is dropped once the tables are unlocked, in case it's the last reference.
+    let _file = crate::interrupts::uninterruptable(|| {
+        TABLES
+            .lock()
+            .get_mut(&task_id)
+            .and_then(|files| files.remove(&fd))
+            .ok_or(Error::BadDescriptor(fd))
+    })?;
+
+    Ok(())
+}
+
+/// Closes every file the task `id` has open.
+pub fn on_exit(id: Uuid) {
+    let _files = crate::interrupts::uninterruptable(|| TABLES.lock().remove(&id));
+}
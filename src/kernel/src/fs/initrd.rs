@@ -0,0 +1,253 @@
+//! Read-only filesystem over the initial RAM filesystem's archive (see [`crate::initramfs`]).
+//!
+//! The archive is indexed into a tree of directories as it's mounted, at boot, so lookups walk the tree rather than
+//! scanning the archive. Directories the archive doesn't list are implied by the paths of the entries within them, and
+//! file contents are read straight from the archive, which the bootloader never reclaims. Once indexed, it's mounted
+//! into the [VFS](crate::fs::vfs) as [`Initrd`], at the root.
+
+use crate::{
+    fs::vfs::{self, FileHandle, NodeKind, Stat},
+    initramfs::{EntryKind, Initramfs},
+};
+use alloc::{boxed::Box, collections::btree_map::BTreeMap, sync::Arc, vec::Vec};
+use spin::Once;
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("initrd has not been mounted")]
+    NotMounted,
+
+    #[error("no such file or directory")]
+    NotFound,
+
+    #[error("path component is not a directory")]
+    NotADirectory,
+
+    #[error("path is a directory")]
+    IsADirectory,
+}
+
+/// A file or directory within the initrd.
+#[derive(Debug)]
+pub enum Node {
+    File(&'static [u8]),
+    Directory(BTreeMap<&'static str, Node>),
+}
+
+impl Node {
+    #[inline]
+    pub const fn kind(&self) -> EntryKind {
+        match self {
+            Self::File(_) => EntryKind::File,
+            Self::Directory(_) => EntryKind::Directory,
+        }
+    }
+
+    /// Inserts `node` at the path of `components` below this directory, creating any directories along it.
+    fn insert(&mut self, mut components: impl Iterator<Item = &'static str>, node: Self) {
+        let Self::Directory(entries) = self else {
+            warn!("Initrd entry is nested below a file.");
+            return;
+        };
+        let Some(name) = components.next() else {
+            return;
+        };
+
+        let mut components = components.peekable();
+        if components.peek().is_some() {
+            entries
+                .entry(name)
+                .or_insert_with(|| Self::Directory(BTreeMap::new()))
+                .insert(components, node);
+        } else if !matches!(
+            (entries.get(name), &node),
+            (Some(Self::Directory(_)), Self::Directory(_))
+        ) {
+            // An archive may list a directory after the entries within it, which mustn't be replaced.
+            entries.insert(name, node);
+        }
+    }
+}
+
+/// Entry of a directory, as listed by [`read_dir`].
+#[derive(Debug, Clone, Copy)]
+pub struct DirEntry {
+    pub name: &'static str,
+    pub kind: EntryKind,
+    /// Length of the file in bytes, or zero for directories.
+    pub len: usize,
+}
+
+static ROOT: Once<Node> = Once::new();
+
+/// Indexes the initramfs archive, and mounts it at the root of the VFS.
+pub fn mount() {
+    ROOT.call_once(|| {
+        let mut root = Node::Directory(BTreeMap::new());
+        for entry in Initramfs::entries() {
+            let node = match entry.kind {
+                EntryKind::File => Node::File(entry.data),
+                EntryKind::Directory => Node::Directory(BTreeMap::new()),
+            };
+
+            root.insert(entry.components(), node);
+        }
+
+        root
+    });
+
+    if let Err(error) = vfs::mount("/", Arc::new(Initrd)) {
+        warn!("Failed to mount the initrd: {error}");
+    }
+}
+
+/// Node at `path` below `root`, where `.` and empty components are ignored, and `..` refers to the parent directory
+/// (or `root`, at the root).
+fn resolve<'a>(root: &'a Node, path: &str) -> Result<&'a Node, Error> {
+    let mut parents = Vec::new();
+    let mut node = root;
+
+    for component in path
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+    {
+        if component == ".." {
+            node = parents.pop().unwrap_or(root);
+            continue;
+        }
+
+        let Node::Directory(entries) = node else {
+            return Err(Error::NotADirectory);
+        };
+
+        parents.push(node);
+        node = entries.get(component).ok_or(Error::NotFound)?;
+    }
+
+    Ok(node)
+}
+
+/// Node at `path`, which is relative to the root of the initrd (with or without a leading `/`).
+pub fn lookup(path: &str) -> Result<&'static Node, Error> {
+    resolve(ROOT.get().ok_or(Error::NotMounted)?, path)
+}
+
+/// Contents of the file at `path`.
+pub fn open(path: &str) -> Result<&'static [u8], Error> {
+    match lookup(path)? {
+        Node::File(data) => Ok(*data),
+        Node::Directory(_) => Err(Error::IsADirectory),
+    }
+}
+
+/// Reads the file at `path` from `offset` into `buf`, returning the number of bytes read (which is zero at or past
+/// the end of the file).
+pub fn read(path: &str, offset: usize, buf: &mut [u8]) -> Result<usize, Error> {
+    let remaining = open(path)?.get(offset..).unwrap_or_default();
+    let len = remaining.len().min(buf.len());
+    buf[..len].copy_from_slice(&remaining[..len]);
+
+    Ok(len)
+}
+
+/// Iterates the entries of the directory at `path`, in order of name.
+pub fn read_dir(path: &str) -> Result<impl Iterator<Item = DirEntry>, Error> {
+    let Node::Directory(entries) = lookup(path)? else {
+        return Err(Error::NotADirectory);
+    };
+
+    Ok(entries.iter().map(|(name, node)| DirEntry {
+        name: *name,
+        kind: node.kind(),
+        len: match node {
+            Node::File(data) => data.len(),
+            Node::Directory(_) => 0,
+        },
+    }))
+}
+
+/// The initrd, as a filesystem of the VFS.
+pub struct Initrd;
+
+impl vfs::FileSystem for Initrd {
+    fn name(&self) -> &'static str {
+        "initrd"
+    }
+
+    fn root(&self) -> Result<Arc<dyn vfs::Node>, vfs::Error> {
+        let root = ROOT.get().ok_or(vfs::Error::NotFound)?;
+
+        Ok(Arc::new(InitrdNode(root)))
+    }
+}
+
+struct InitrdNode(&'static Node);
+
+impl vfs::Node for InitrdNode {
+    fn stat(&self) -> Stat {
+        match self.0 {
+            Node::File(data) => Stat::new(NodeKind::File, data.len()),
+            Node::Directory(_) => Stat::new(NodeKind::Directory, 0),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn vfs::Node>, vfs::Error> {
+        let Node::Directory(entries) = self.0 else {
+            return Err(vfs::Error::NotADirectory);
+        };
+
+        let node = entries.get(name).ok_or(vfs::Error::NotFound)?;
+
+        Ok(Arc::new(InitrdNode(node)))
+    }
+
+    fn open(&self) -> Result<Box<dyn FileHandle>, vfs::Error> {
+        match self.0 {
+            Node::File(data) => Ok(Box::new(InitrdFile { data, offset: 0 })),
+            Node::Directory(_) => Err(vfs::Error::IsADirectory),
+        }
+    }
+}
+
+struct InitrdFile {
+    data: &'static [u8],
+    offset: usize,
+}
+
+impl FileHandle for InitrdFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, vfs::Error> {
+        let remaining = self.data.get(self.offset..).unwrap_or_default();
+        let len = remaining.len().min(buf.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        self.offset += len;
+
+        Ok(len)
+    }
+}
+
+crate::kernel_test! {
+    /// Paths resolve through implied directories, `.` and `..`, and not through files.
+    fn resolves_paths() -> crate::tests::Outcome {
+        let mut root = Node::Directory(BTreeMap::new());
+        root.insert(["bin", "init"].into_iter(), Node::File(b"init"));
+        root.insert(["bin"].into_iter(), Node::Directory(BTreeMap::new()));
+
+        if !matches!(resolve(&root, "/bin/../bin/./init"), Ok(Node::File(b"init"))) {
+            return Err("file wasn't found through an implied directory");
+        }
+
+        if !matches!(resolve(&root, "../bin"), Ok(Node::Directory(entries)) if entries.len() == 1) {
+            return Err("directory listed after its entries replaced them");
+        }
+
+        if resolve(&root, "bin/init/init").err() != Some(Error::NotADirectory) {
+            return Err("path resolved through a file");
+        }
+
+        if resolve(&root, "sbin").err() != Some(Error::NotFound) {
+            return Err("missing path was resolved");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+//! Kernel information filesystem, which exposes kernel state as read-only text files, so userspace can observe the
+//! kernel without a system call for each statistic.
+//!
+//! It's mounted into the [VFS](crate::fs::vfs) at [`MOUNT_PATH`], as a single directory of files:
+//!
+//! - `meminfo`: physical memory and heap usage.
+//! - `tasks`: every task, with its state and CPU time.
+//! - `interrupts`: the count of each vector taken, on each hardware thread.
+//! - `clock`: the system clock's source, frequency, and uptime.
+//! - `params`: the value of each kernel parameter.
+//! - `block`: the requests each block device's queue has transferred, merged, and holds.
+//!
+//! Each file holds `key: value` lines (or a header line followed by a line per record), and its contents are a snapshot
+//! taken as it's opened, so reads see a consistent view of the kernel's state at that time.
+
+use crate::{
+    devices::block::BlockDevice,
+    fs::vfs::{self, FileHandle, NodeKind, Stat},
+    mem::pmm::PhysicalMemoryManager,
+    time::SystemClock,
+};
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use core::fmt::{self, Write};
+
+/// Path the kernel information filesystem is mounted at.
+pub const MOUNT_PATH: &str = "/kinfo";
+
+/// File of the filesystem, rendered each time it's opened.
+struct Entry {
+    name: &'static str,
+    render: fn(&mut String) -> fmt::Result,
+}
+
+const ENTRIES: &[Entry] = &[
+    Entry {
+        name: "meminfo",
+        render: meminfo,
+    },
+    Entry {
+        name: "tasks",
+        render: tasks,
+    },
+    Entry {
+        name: "interrupts",
+        render: interrupts,
+    },
+    Entry {
+        name: "clock",
+        render: clock,
+    },
+    Entry {
+        name: "params",
+        render: params,
+    },
+    Entry {
+        name: "block",
+        render: block,
+    },
+];
+
+fn meminfo(out: &mut String) -> fmt::Result {
+    let page_size = libsys::page_size();
+    let total_frames = PhysicalMemoryManager::total_frames();
+    let free_frames = PhysicalMemoryManager::free_frames();
+    let heap = crate::mem::alloc::stats();
+
+    writeln!(out, "page_size: {page_size}")?;
+    writeln!(out, "total_frames: {total_frames}")?;
+    writeln!(out, "free_frames: {free_frames}")?;
+    writeln!(out, "used_frames: {}", total_frames - free_frames)?;
+    writeln!(out, "heap_bytes: {}", heap.allocated_bytes)?;
+    writeln!(out, "heap_huge_bytes: {}", heap.huge_bytes)?;
+    writeln!(out, "heap_split_bytes: {}", heap.split_bytes)
+}
+
+fn tasks(out: &mut String) -> fmt::Result {
+    writeln!(
+        out,
+        "id name state hwthread priority cpu_time_ns user_time_ns kernel_time_ns"
+    )?;
+
+    for info in crate::task::snapshot() {
+        let name = match info.name() {
+            "" => "-",
+            name => name,
+        };
+
+        write!(out, "{} {name} ", info.id())?;
+        match info.state() {
+            Some(state) => write!(out, "{state:?} ")?,
+            None => write!(out, "? ")?,
+        }
+        match info.hwthread_id {
+            u32::MAX => write!(out, "- ")?,
+            hwthread_id => write!(out, "{hwthread_id} ")?,
+        }
+        match info.priority() {
+            Some(priority) => write!(out, "{priority:?} ")?,
+            None => write!(out, "? ")?,
+        }
+        writeln!(
+            out,
+            "{} {} {}",
+            info.cpu_time_ns, info.user_time_ns, info.kernel_time_ns
+        )?;
+    }
+
+    Ok(())
+}
+
+fn interrupts(out: &mut String) -> fmt::Result {
+    writeln!(out, "vector count hwthread:count...")?;
+
+    for vector in u8::MIN..=u8::MAX {
+        let count = crate::interrupts::irq::count(vector);
+        if count == 0 {
+            continue;
+        }
+
+        write!(out, "{vector:#04X} {count}")?;
+        for (hwthread_id, count) in
+            crate::interrupts::irq::counts(vector).filter(|(_, count)| *count > 0)
+        {
+            write!(out, " {hwthread_id}:{count}")?;
+        }
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+fn clock(out: &mut String) -> fmt::Result {
+    match SystemClock::source() {
+        Some(source) => {
+            writeln!(out, "source: {source:?}")?;
+            writeln!(out, "frequency_hz: {}", source.frequency())?;
+        }
+        None => writeln!(out, "source: -")?,
+    }
+
+    writeln!(out, "uptime_ns: {}", SystemClock::now_ns())
+}
+
+fn params(out: &mut String) -> fmt::Result {
+    for parameter in crate::params::registry() {
+        writeln!(
+            out,
+            "{}: {}",
+            parameter.name,
+            crate::params::value(parameter)
+        )?;
+    }
+
+    Ok(())
+}
+
+fn block(out: &mut String) -> fmt::Result {
+    writeln!(
+        out,
+        "device reads sectors_read writes sectors_written merges errors latency_ns queued in_flight"
+    )?;
+
+    for queue in crate::devices::block::queues() {
+        let statistics = queue.statistics();
+        writeln!(
+            out,
+            "{} {} {} {} {} {} {} {} {} {}",
+            queue.device().name(),
+            statistics.reads,
+            statistics.sectors_read,
+            statistics.writes,
+            statistics.sectors_written,
+            statistics.merges,
+            statistics.errors,
+            statistics.latency_ns,
+            statistics.queued,
+            statistics.in_flight
+        )?;
+    }
+
+    Ok(())
+}
+
+impl Entry {
+    fn contents(&self) -> Vec<u8> {
+        let mut contents = String::new();
+        // Writing to a string never fails.
+        (self.render)(&mut contents).unwrap();
+
+        contents.into_bytes()
+    }
+}
+
+/// The kernel information filesystem, as a filesystem of the VFS.
+pub struct KernelInfo;
+
+impl vfs::FileSystem for KernelInfo {
+    fn name(&self) -> &'static str {
+        "kinfo"
+    }
+
+    fn root(&self) -> Result<Arc<dyn vfs::Node>, vfs::Error> {
+        Ok(Arc::new(Root))
+    }
+}
+
+struct Root;
+
+impl vfs::Node for Root {
+    fn stat(&self) -> Stat {
+        Stat::new(NodeKind::Directory, 0)
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn vfs::Node>, vfs::Error> {
+        let entry = ENTRIES
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or(vfs::Error::NotFound)?;
+
+        Ok(Arc::new(File(entry)))
+    }
+
+    fn open(&self) -> Result<Box<dyn FileHandle>, vfs::Error> {
+        Err(vfs::Error::IsADirectory)
+    }
+}
+
+struct File(&'static Entry);
+
+impl vfs::Node for File {
+    fn stat(&self) -> Stat {
+        Stat::new(NodeKind::File, self.0.contents().len())
+    }
+
+    fn lookup(&self, _: &str) -> Result<Arc<dyn vfs::Node>, vfs::Error> {
+        Err(vfs::Error::NotADirectory)
+    }
+
+    fn open(&self) -> Result<Box<dyn FileHandle>, vfs::Error> {
+        Ok(Box::new(Snapshot {
+            contents: self.0.contents(),
+            offset: 0,
+        }))
+    }
+}
+
+/// Contents of a file as it was opened.
+struct Snapshot {
+    contents: Vec<u8>,
+    offset: usize,
+}
+
+impl FileHandle for Snapshot {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, vfs::Error> {
+        let remaining = self.contents.get(self.offset..).unwrap_or_default();
+        let len = remaining.len().min(buf.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        self.offset += len;
+
+        Ok(len)
+    }
+}
+
+/// Mounts the kernel information filesystem at [`MOUNT_PATH`].
+pub fn mount() {
+    if let Err(error) = vfs::mount(MOUNT_PATH, Arc::new(KernelInfo)) {
+        warn!("Failed to mount kinfo: {error}");
+    }
+}
+
+crate::kernel_test! {
+    /// Every file is found by name and renders, and names which aren't files aren't found.
+    fn renders_kinfo_files() -> crate::tests::Outcome {
+        use vfs::Node;
+
+        for entry in ENTRIES {
+            let file = Root.lookup(entry.name).map_err(|_| "file wasn't found")?;
+            let mut buf = [0; 16];
+            if file.open().map_err(|_| "file wasn't opened")?.read(&mut buf) == Ok(0) {
+                return Err("file rendered empty");
+            }
+        }
+
+        if Root.lookup("kernel").is_ok() {
+            return Err("nonexistent file was found");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+pub mod fat32;
+pub mod fd;
+pub mod initrd;
+pub mod kinfo;
+pub mod vfs;
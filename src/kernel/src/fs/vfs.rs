@@ -0,0 +1,188 @@
+//! Virtual filesystem: a single tree of absolute paths over the filesystems mounted into it.
+//!
+//! A [`FileSystem`] is mounted at a path, and provides the [`Node`]s below it; a path is served by the mount whose own
+//! path is the longest which prefixes it. Paths are normalized before they're resolved, so `..` is lexical. Nodes are
+//! opened as [`FileHandle`]s, which keep their own read position; userspace refers to those through its table of file
+//! descriptors (see [`crate::fs::fd`]).
+
+use crate::sync::SpinMutex;
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+
+/// Maximum length of a path, in bytes.
+pub const MAX_PATH: usize = 0x1000;
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("no such file or directory")]
+    NotFound,
+
+    #[error("path component is not a directory")]
+    NotADirectory,
+
+    #[error("path is a directory")]
+    IsADirectory,
+
+    #[error("path is not absolute, or is longer than {MAX_PATH} bytes")]
+    InvalidPath,
+
+    #[error("a filesystem is already mounted at the path")]
+    AlreadyMounted,
+
+    #[error("filesystem failed to read its device")]
+    Io,
+}
+
+/// Kind of a node; as a [`Stat`] field, in declaration order from `0`.
+#[repr(usize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive)]
+pub enum NodeKind {
+    File,
+    Directory,
+}
+
+/// Metadata of a node, as written to userspace.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable)]
+pub struct Stat {
+    /// [`NodeKind`] of the node.
+    pub kind: usize,
+    /// Length of the node's contents in bytes, which is zero for directories.
+    pub len: usize,
+}
+
+impl Stat {
+    pub fn new(kind: NodeKind, len: usize) -> Self {
+        Self {
+            kind: usize::from(kind),
+            len,
+        }
+    }
+}
+
+/// A filesystem which can be mounted into the VFS.
+pub trait FileSystem: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Directory at the root of the filesystem.
+    fn root(&self) -> Result<Arc<dyn Node>, Error>;
+}
+
+/// A file or directory within a [`FileSystem`].
+pub trait Node: Send + Sync {
+    fn stat(&self) -> Stat;
+
+    /// Entry of this directory named `name`.
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Node>, Error>;
+
+    /// Opens this file for reading from its start.
+    fn open(&self) -> Result<Box<dyn FileHandle>, Error>;
+}
+
+/// An open file, with its own read position.
+pub trait FileHandle: Send {
+    /// Reads from the read position into `buf`, advancing it, and returning the number of bytes read (which is zero
+    /// at the end of the file).
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+}
+
+struct Mount {
+    components: Vec<String>,
+    fs: Arc<dyn FileSystem>,
+}
+
+static MOUNTS: SpinMutex<Vec<Mount>> = SpinMutex::new(Vec::new());
+
+/// Components of the absolute `path`, without any empty or `.` components, and with each `..` removing the component
+/// before it.
+fn normalize(path: &str) -> Result<Vec<&str>, Error> {
+    if !path.starts_with('/') || path.len() > MAX_PATH {
+        return Err(Error::InvalidPath);
+    }
+
+    let mut components = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+
+    Ok(components)
+}
+
+/// Mounts `fs` at the absolute `path`.
+pub fn mount(path: &str, fs: Arc<dyn FileSystem>) -> Result<(), Error> {
+    let components = normalize(path)?
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    crate::interrupts::uninterruptable(|| {
+        let mut mounts = MOUNTS.lock();
+        if mounts.iter().any(|mount| mount.components == components) {
+            return Err(Error::AlreadyMounted);
+        }
+
+        info!("Mounted {} at {path:?}", fs.name());
+        mounts.push(Mount { components, fs });
+
+        Ok(())
+    })
+}
+
+/// Node at the absolute `path`.
+pub fn lookup(path: &str) -> Result<Arc<dyn Node>, Error> {
+    let components = normalize(path)?;
+    let (mount_len, fs) = crate::interrupts::uninterruptable(|| {
+        MOUNTS
+            .lock()
+            .iter()
+            .filter(|mount| {
+                mount.components.len() <= components.len()
+                    && mount
+                        .components
+                        .iter()
+                        .zip(&components)
+                        .all(|(a, b)| a == b)
+            })
+            .max_by_key(|mount| mount.components.len())
+            .map(|mount| (mount.components.len(), mount.fs.clone()))
+            .ok_or(Error::NotFound)
+    })?;
+
+    components[mount_len..]
+        .iter()
+        .try_fold(fs.root()?, |node, component| node.lookup(component))
+}
+
+/// Opens the file at the absolute `path`.
+pub fn open(path: &str) -> Result<Box<dyn FileHandle>, Error> {
+    lookup(path)?.open()
+}
+
+/// Metadata of the node at the absolute `path`.
+pub fn stat(path: &str) -> Result<Stat, Error> {
+    lookup(path).map(|node| node.stat())
+}
+
+crate::kernel_test! {
+    /// Paths are normalized lexically, and refused unless they're absolute.
+    fn normalizes_paths() -> crate::tests::Outcome {
+        if normalize("/bin/./../etc//init.conf") != Ok(alloc::vec!["etc", "init.conf"]) {
+            return Err("path was normalized incorrectly");
+        }
+
+        if normalize("/..") != Ok(Vec::new()) {
+            return Err("path escaped the root");
+        }
+
+        if normalize("bin/init") != Err(Error::InvalidPath) {
+            return Err("relative path was accepted");
+        }
+
+        Ok(())
+    }
+}
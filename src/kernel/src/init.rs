@@ -0,0 +1,110 @@
+//! Kernel init stages, and the order they run in.
+//!
+//! Each stage names the stages it must run after. Stages run in the order they're declared, and [`validate`] (which
+//! is evaluated at compile time) rejects any stage declared before one it depends on, so a new subsystem can't be
+//! slotted in too early by accident.
+
+/// Most stages timed; any beyond this still run, but aren't reported.
+const MAX_STAGES: usize = 32;
+
+/// A step of kernel init.
+pub struct Stage {
+    pub name: &'static str,
+    /// Stages which must run before this one.
+    pub after: &'static [&'static str],
+    pub run: fn(),
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut index = 0;
+    while index < a.len() {
+        if a[index] != b[index] {
+            return false;
+        }
+
+        index += 1;
+    }
+
+    true
+}
+
+/// Whether a stage named `name` is within `stages`.
+const fn contains(stages: &[Stage], name: &str) -> bool {
+    let mut index = 0;
+    while index < stages.len() {
+        if str_eq(stages[index].name, name) {
+            return true;
+        }
+
+        index += 1;
+    }
+
+    false
+}
+
+/// Checks every stage is uniquely named, and only depends on stages declared before it.
+///
+/// # Panics
+///
+/// If `stages` is invalid; this is intended to be evaluated in a `const` context, making this a compile error.
+pub const fn validate(stages: &[Stage]) {
+    let mut index = 0;
+    while index < stages.len() {
+        let (before, after) = stages.split_at(index);
+        let stage = &after[0];
+
+        if contains(before, stage.name) {
+            panic!("init stage is declared more than once");
+        }
+
+        let mut dependency = 0;
+        while dependency < stage.after.len() {
+            if !contains(before, stage.after[dependency]) {
+                panic!("init stage depends on a stage which isn't declared before it");
+            }
+
+            dependency += 1;
+        }
+
+        index += 1;
+    }
+}
+
+fn read_tsc() -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    // Safety: The kernel already depends upon the processor having TSC capability.
+    unsafe {
+        core::arch::x86_64::_rdtsc()
+    }
+}
+
+/// Runs `stages` in order, then reports how long each took.
+pub fn run(stages: &[Stage]) {
+    let mut timings = heapless::Vec::<(&'static str, u64), MAX_STAGES>::new();
+
+    for stage in stages {
+        trace!("Init stage `{}`...", stage.name);
+
+        let start_ticks = read_tsc();
+        (stage.run)();
+        let elapsed_ticks = read_tsc().saturating_sub(start_ticks);
+
+        timings.push((stage.name, elapsed_ticks)).ok();
+    }
+
+    // The timestamp counter's frequency may only be known once the `time` stage has run, so timings are reported
+    // after every stage.
+    let frequency = crate::time::tsc_frequency().max(1);
+    for (name, elapsed_ticks) in timings {
+        let elapsed_us =
+            u64::try_from((u128::from(elapsed_ticks) * 1_000_000) / u128::from(frequency))
+                .unwrap_or(u64::MAX);
+
+        debug!("Init stage `{name}` took {elapsed_us}us.");
+    }
+}
@@ -0,0 +1,155 @@
+//! Read-only access to the initial RAM filesystem, provided by the bootloader as a USTAR archive module.
+//!
+//! This only parses the archive; it's mounted as a filesystem by [`crate::fs::initrd`].
+
+/// Size of a USTAR header, and the alignment of each entry's data.
+const BLOCK_SIZE: usize = 512;
+
+const NAME_RANGE: core::ops::Range<usize> = 0..100;
+const SIZE_RANGE: core::ops::Range<usize> = 124..136;
+const TYPE_FLAG_OFFSET: usize = 156;
+const MAGIC_RANGE: core::ops::Range<usize> = 257..263;
+const PREFIX_RANGE: core::ops::Range<usize> = 345..500;
+
+/// Module path suffix the initramfs archive is identified by.
+const MODULE_SUFFIX: &[u8] = b"initramfs";
+
+crate::singleton! {
+    pub Initramfs {
+        archive: &'static [u8],
+    }
+
+    fn init(module_request: &limine::request::ModuleRequest) {
+        let archive = module_request
+            .get_response()
+            .and_then(|response| {
+                response
+                    .modules()
+                    .iter()
+                    .find(|module| module.path().ends_with(MODULE_SUFFIX))
+            })
+            .map_or_else(
+                || {
+                    warn!("Bootloader provided no initramfs module.");
+
+                    &[][..]
+                },
+                |module| {
+                    // Safety: Bootloader guarantees the module's address and size are correct, and that
+                    //         module memory is never reclaimed.
+                    unsafe {
+                        core::slice::from_raw_parts::<'static>(
+                            module.addr(),
+                            usize::try_from(module.size()).unwrap(),
+                        )
+                    }
+                },
+            );
+
+        debug!("Initramfs: {:#X} bytes", archive.len());
+
+        Self { archive }
+    }
+}
+
+/// Kind of an entry within the initramfs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+}
+
+/// A regular file or directory within the initramfs.
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    /// Leading part of the entry's path, for paths too long for `name` alone; usually empty.
+    pub prefix: &'static str,
+    /// Path of the entry (following `prefix`).
+    pub name: &'static str,
+    pub kind: EntryKind,
+    /// Contents of the entry, which is empty for directories.
+    pub data: &'static [u8],
+}
+
+impl Entry {
+    /// Components of the entry's path, without any empty or `.` components.
+    pub fn components(&self) -> impl Iterator<Item = &'static str> {
+        self.prefix
+            .split('/')
+            .chain(self.name.split('/'))
+            .filter(|component| !component.is_empty() && *component != ".")
+    }
+}
+
+fn field_str(field: &[u8]) -> Option<&str> {
+    let len = field
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(field.len());
+
+    core::str::from_utf8(&field[..len]).ok()
+}
+
+fn parse_octal(field: &[u8]) -> Option<usize> {
+    field_str(field)?
+        .trim_matches(|c: char| c == ' ' || c == '\0')
+        .chars()
+        .try_fold(0usize, |value, digit| {
+            value
+                .checked_mul(8)?
+                .checked_add(usize::try_from(digit.to_digit(8)?).ok()?)
+        })
+}
+
+/// Iterates the regular files and directories of a USTAR archive, stopping at the end-of-archive marker or the first
+/// malformed header.
+fn entries(mut archive: &'static [u8]) -> impl Iterator<Item = Entry> {
+    core::iter::from_fn(move || {
+        loop {
+            let header = archive.get(..BLOCK_SIZE)?;
+            if header.iter().all(|byte| *byte == 0) {
+                return None;
+            }
+
+            if !header[MAGIC_RANGE].starts_with(b"ustar") {
+                warn!("Initramfs contains a malformed archive header.");
+                return None;
+            }
+
+            let size = parse_octal(&header[SIZE_RANGE])?;
+            let data_end = BLOCK_SIZE.checked_add(size)?;
+            let data = archive.get(BLOCK_SIZE..data_end)?;
+            let type_flag = header[TYPE_FLAG_OFFSET];
+
+            let (prefix, name) = (
+                field_str(&header[PREFIX_RANGE])?,
+                field_str(&header[NAME_RANGE])?,
+            );
+
+            archive = archive
+                .get(data_end.next_multiple_of(BLOCK_SIZE)..)
+                .unwrap_or_default();
+
+            // Only regular files and directories are exposed; links and special files are skipped.
+            let kind = match type_flag {
+                b'0' | 0 => EntryKind::File,
+                b'5' => EntryKind::Directory,
+                _ => continue,
+            };
+
+            return Some(Entry {
+                prefix,
+                name,
+                kind,
+                data,
+            });
+        }
+    })
+}
+
+impl Initramfs {
+    /// Iterates every regular file and directory in the initramfs.
+    pub fn entries() -> impl Iterator<Item = Entry> {
+        entries(Self::get_static().archive)
+    }
+}
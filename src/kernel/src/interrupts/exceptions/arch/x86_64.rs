@@ -1,9 +1,29 @@
 use crate::{
-    arch::x86_64::structures::idt::{InterruptStackFrame, PageFaultErrorCode, SelectorErrorCode},
-    interrupts::exceptions::Exception,
+    arch::x86_64::{
+        cet::ReturnMismatch,
+        mca::MachineCheckReport,
+        registers::RFlags,
+        structures::{
+            gdt::{LoadedSelectors, PrivilegeLevel},
+            idt::{
+                ControlProtectionErrorCode, InterruptStackFrame, PageFaultErrorCode,
+                SelectorErrorCode,
+            },
+        },
+    },
+    interrupts::exceptions::{
+        Exception,
+        policy::ExceptionClass,
+        triage::{self, AddressRegion},
+    },
+    mem::{
+        HigherHalfDirectMap,
+        mapper::Mapper,
+        paging::{TableDepth, TableEntryFlags},
+    },
     task::Registers,
 };
-use libsys::{Address, Virtual};
+use libsys::{Address, Frame, Virtual};
 
 /// Exception wrapper type.
 #[repr(C)]
@@ -88,7 +108,11 @@ pub enum ArchException<'a> {
     ///
     /// REMARK: It uses model-specific registers (MSRs) to provide error information.
     ///         It is disabled by default. Set `CR4.MCE` to enable it.
-    MachineCheck(&'a InterruptStackFrame, &'a Registers),
+    MachineCheck(
+        &'a InterruptStackFrame,
+        &'a Registers,
+        &'a MachineCheckReport,
+    ),
 
     /* VIRTUALIZATION EXCEPTIONS (not supported) */
     /// Occurs when an unmasked 128-bit media floating-point exception occurs and the `CR4.OSXMMEXCPT` bit
@@ -99,7 +123,15 @@ pub enum ArchException<'a> {
     Virtualization(&'a InterruptStackFrame, &'a Registers),
 
     /// Occurs under several conditions on the `ret`/`iret`/`rstorssp`/`setssbsy` instructions.
-    ControlProtection(&'a InterruptStackFrame, &'a Registers),
+    ///
+    /// REMARK: For a near `ret` in the kernel with shadow stacks enabled, the disagreeing return addresses are
+    ///         read from both stacks.
+    ControlProtection(
+        &'a InterruptStackFrame,
+        ControlProtectionErrorCode,
+        Option<ReturnMismatch>,
+        &'a Registers,
+    ),
 
     HypervisorInjection(&'a InterruptStackFrame, &'a Registers),
 
@@ -109,6 +141,314 @@ pub enum ArchException<'a> {
     TripleFault,
 }
 
+/// Kernel-mode access to a user page, which supervisor-mode access or execution prevention refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupervisorViolation {
+    /// Kernel read or wrote user memory outside of a [`crate::mem::user::UserAccess`] guard.
+    Smap,
+    /// Kernel fetched an instruction from user memory.
+    Smep,
+}
+
+impl core::fmt::Display for SupervisorViolation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::Smap => {
+                "SMAP violation: kernel accessed user memory outside of a user access guard"
+            }
+            Self::Smep => "SMEP violation: kernel executed user memory",
+        })
+    }
+}
+
+/// Explains a page fault by comparing its error code to the translation of the faulting address.
+fn page_fault_cause(
+    error_code: PageFaultErrorCode,
+    translation: Option<(Address<Frame>, TableEntryFlags, TableDepth)>,
+) -> &'static str {
+    let Some((_, attributes, _)) = translation else {
+        return "address is not mapped";
+    };
+
+    if error_code.contains(PageFaultErrorCode::MALFORMED_TABLE) {
+        "a page table entry has reserved bits set"
+    } else if error_code.contains(PageFaultErrorCode::PROTECTION_KEY) {
+        "the page's protection key denied the access"
+    } else if error_code.contains(PageFaultErrorCode::SHADOW_STACK) {
+        "shadow stack access to a page which isn't a shadow stack"
+    } else if error_code.contains(PageFaultErrorCode::USER_MODE)
+        && !attributes.contains(TableEntryFlags::USER)
+    {
+        "user mode access to a supervisor page"
+    } else if error_code.contains(PageFaultErrorCode::INSTRUCTION_FETCH)
+        && attributes.contains(TableEntryFlags::NO_EXECUTE)
+    {
+        "instruction fetch from a non-executable page"
+    } else if error_code.contains(PageFaultErrorCode::CAUSED_BY_WRITE)
+        && !attributes.contains(TableEntryFlags::WRITABLE)
+    {
+        "write to a read-only page"
+    } else if !error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION) {
+        "page was not present, but has been mapped since"
+    } else {
+        "the page tables permit the access (a SMAP/SMEP violation, or a stale TLB entry)"
+    }
+}
+
+impl ArchException<'_> {
+    fn stack_frame(&self) -> Option<&InterruptStackFrame> {
+        match self {
+            Self::DivideError(isf, _)
+            | Self::Debug(isf, _)
+            | Self::NonMaskable(isf, _)
+            | Self::Breakpoint(isf, _)
+            | Self::Overflow(isf, _)
+            | Self::BoundRangeExceeded(isf, _)
+            | Self::InvalidOpcode(isf, _)
+            | Self::DeviceNotAvailable(isf, _)
+            | Self::DoubleFault(isf, _)
+            | Self::InvalidTSS(isf, _, _)
+            | Self::SegmentNotPresent(isf, _, _)
+            | Self::StackSegmentFault(isf, _, _)
+            | Self::GeneralProtectionFault(isf, _, _)
+            | Self::PageFault(isf, _, _, _)
+            | Self::x87FloatingPoint(isf, _)
+            | Self::AlignmentCheck(isf, _, _)
+            | Self::MachineCheck(isf, _, _)
+            | Self::SimdFlaotingPoint(isf, _)
+            | Self::Virtualization(isf, _)
+            | Self::ControlProtection(isf, _, _, _)
+            | Self::HypervisorInjection(isf, _)
+            | Self::VMMCommunication(isf, _) => Some(isf),
+
+            Self::TripleFault => None,
+        }
+    }
+
+    /// Architectural mnemonic of the exception.
+    pub const fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::DivideError(..) => "#DE",
+            Self::Debug(..) => "#DB",
+            Self::NonMaskable(..) => "NMI",
+            Self::Breakpoint(..) => "#BP",
+            Self::Overflow(..) => "#OF",
+            Self::BoundRangeExceeded(..) => "#BR",
+            Self::InvalidOpcode(..) => "#UD",
+            Self::DeviceNotAvailable(..) => "#NM",
+            Self::DoubleFault(..) => "#DF",
+            Self::InvalidTSS(..) => "#TS",
+            Self::SegmentNotPresent(..) => "#NP",
+            Self::StackSegmentFault(..) => "#SS",
+            Self::GeneralProtectionFault(..) => "#GP",
+            Self::PageFault(..) => "#PF",
+            Self::x87FloatingPoint(..) => "#MF",
+            Self::AlignmentCheck(..) => "#AC",
+            Self::MachineCheck(..) => "#MC",
+            Self::SimdFlaotingPoint(..) => "#XM",
+            Self::Virtualization(..) => "#VE",
+            Self::ControlProtection(..) => "#CP",
+            Self::HypervisorInjection(..) => "#HV",
+            Self::VMMCommunication(..) => "#VC",
+            Self::TripleFault => "triple fault",
+        }
+    }
+
+    /// Vector the exception is raised on, if it's raised at all.
+    pub const fn vector(&self) -> Option<u8> {
+        match self {
+            Self::DivideError(..) => Some(0x00),
+            Self::Debug(..) => Some(0x01),
+            Self::NonMaskable(..) => Some(0x02),
+            Self::Breakpoint(..) => Some(0x03),
+            Self::Overflow(..) => Some(0x04),
+            Self::BoundRangeExceeded(..) => Some(0x05),
+            Self::InvalidOpcode(..) => Some(0x06),
+            Self::DeviceNotAvailable(..) => Some(0x07),
+            Self::DoubleFault(..) => Some(0x08),
+            Self::InvalidTSS(..) => Some(0x0A),
+            Self::SegmentNotPresent(..) => Some(0x0B),
+            Self::StackSegmentFault(..) => Some(0x0C),
+            Self::GeneralProtectionFault(..) => Some(0x0D),
+            Self::PageFault(..) => Some(0x0E),
+            Self::x87FloatingPoint(..) => Some(0x10),
+            Self::AlignmentCheck(..) => Some(0x11),
+            Self::MachineCheck(..) => Some(0x12),
+            Self::SimdFlaotingPoint(..) => Some(0x13),
+            Self::Virtualization(..) => Some(0x14),
+            Self::ControlProtection(..) => Some(0x15),
+            Self::HypervisorInjection(..) => Some(0x1C),
+            Self::VMMCommunication(..) => Some(0x1D),
+            Self::TripleFault => None,
+        }
+    }
+
+    /// Whether the exception occurred while executing in user mode.
+    pub fn is_from_user(&self) -> bool {
+        self.stack_frame()
+            .is_some_and(|isf| isf.get_code_segment().privilege_level() == PrivilegeLevel::Ring3)
+    }
+
+    /// Address the exception is reported at: the faulting address of a page fault, otherwise the faulting
+    /// instruction.
+    pub fn fault_address(&self) -> usize {
+        match self {
+            Self::PageFault(_, _, _, address) => address.get(),
+            exception => exception
+                .stack_frame()
+                .map_or(0, |isf| isf.get_instruction_pointer().get()),
+        }
+    }
+
+    /// Decodes a kernel-mode page fault on a present user page as a SMAP or SMEP violation.
+    pub fn supervisor_violation(&self) -> Option<SupervisorViolation> {
+        let Self::PageFault(isf, _, error_code, address) = self else {
+            return None;
+        };
+
+        if error_code.contains(PageFaultErrorCode::USER_MODE)
+            || !error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION)
+            || triage::classify(address.get()) != AddressRegion::UserSpace
+        {
+            return None;
+        }
+
+        if error_code.contains(PageFaultErrorCode::INSTRUCTION_FETCH) {
+            Some(SupervisorViolation::Smep)
+        } else if !isf.get_cpu_flags().contains(RFlags::ALIGNMENT_CHECK) {
+            Some(SupervisorViolation::Smap)
+        } else {
+            None
+        }
+    }
+
+    /// The policy class of the exception, or `None` if the exception is never recoverable.
+    pub fn class(&self) -> Option<ExceptionClass> {
+        match self {
+            Self::DivideError(..) => Some(ExceptionClass::DivideError),
+            Self::Debug(..) => Some(ExceptionClass::Debug),
+            Self::Breakpoint(..) => Some(ExceptionClass::Breakpoint),
+            Self::Overflow(..) => Some(ExceptionClass::Overflow),
+            Self::BoundRangeExceeded(..) => Some(ExceptionClass::BoundRangeExceeded),
+            Self::InvalidOpcode(..) => Some(ExceptionClass::InvalidOpcode),
+            Self::DeviceNotAvailable(..) => Some(ExceptionClass::DeviceNotAvailable),
+            Self::InvalidTSS(..) => Some(ExceptionClass::InvalidTSS),
+            Self::SegmentNotPresent(..) => Some(ExceptionClass::SegmentNotPresent),
+            Self::StackSegmentFault(..) => Some(ExceptionClass::StackSegmentFault),
+            Self::GeneralProtectionFault(..) => Some(ExceptionClass::GeneralProtectionFault),
+            Self::PageFault(..) => Some(ExceptionClass::PageFault),
+            Self::x87FloatingPoint(..) => Some(ExceptionClass::x87FloatingPoint),
+            Self::AlignmentCheck(..) => Some(ExceptionClass::AlignmentCheck),
+            Self::SimdFlaotingPoint(..) => Some(ExceptionClass::SimdFloatingPoint),
+            Self::Virtualization(..) => Some(ExceptionClass::Virtualization),
+            Self::ControlProtection(..) => Some(ExceptionClass::ControlProtection),
+
+            Self::NonMaskable(..)
+            | Self::DoubleFault(..)
+            | Self::MachineCheck(..)
+            | Self::HypervisorInjection(..)
+            | Self::VMMCommunication(..)
+            | Self::TripleFault => None,
+        }
+    }
+}
+
+/// Formats a triage report of the exception, decoding its error code & addresses, followed by the
+/// full exception state.
+impl core::fmt::Display for ArchException<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let origin = if self.is_from_user() {
+            "user"
+        } else {
+            "kernel"
+        };
+        writeln!(f, "{} in {origin} mode", self.mnemonic())?;
+
+        match self {
+            Self::InvalidTSS(_, error_code, _)
+            | Self::SegmentNotPresent(_, error_code, _)
+            | Self::StackSegmentFault(_, error_code, _)
+            | Self::GeneralProtectionFault(_, error_code, _) => {
+                writeln!(f, "  error code: {error_code:#X?}")?;
+            }
+
+            Self::PageFault(_, _, error_code, address) => {
+                writeln!(f, "  error code: {error_code:?}")?;
+                writeln!(
+                    f,
+                    "  faulting address: {:#X} ({})",
+                    address.get(),
+                    triage::classify(address.get())
+                )?;
+
+                if let Some(violation) = self.supervisor_violation() {
+                    writeln!(f, "  {violation}")?;
+                }
+
+                // The page tables are only reachable through the HHDM.
+                if HigherHalfDirectMap::is_initialized() {
+                    // Safety: The page tables are only read.
+                    let mapper = unsafe { Mapper::active() };
+
+                    writeln!(
+                        f,
+                        "  cause: {}",
+                        page_fault_cause(*error_code, mapper.translate(*address))
+                    )?;
+                    mapper.dump_range(*address..=*address, f)?;
+                }
+            }
+
+            Self::MachineCheck(_, _, report) => {
+                writeln!(f, "{report}")?;
+            }
+
+            Self::ControlProtection(_, error_code, mismatch, _) => {
+                writeln!(f, "  error code: {error_code:?}")?;
+
+                if let Some(ReturnMismatch {
+                    stack,
+                    shadow_stack,
+                }) = mismatch
+                {
+                    writeln!(
+                        f,
+                        "  stack return address: {stack:#X} ({})",
+                        triage::classify(*stack)
+                    )?;
+                    writeln!(
+                        f,
+                        "  shadow stack return address: {shadow_stack:#X} ({})",
+                        triage::classify(*shadow_stack)
+                    )?;
+                }
+            }
+
+            _ => {}
+        }
+
+        if let Some(isf) = self.stack_frame() {
+            let instruction_pointer = isf.get_instruction_pointer().get();
+            let stack_pointer = isf.get_stack_pointer().get();
+
+            writeln!(
+                f,
+                "  instruction pointer: {instruction_pointer:#X} ({})",
+                triage::classify(instruction_pointer)
+            )?;
+            writeln!(
+                f,
+                "  stack pointer: {stack_pointer:#X} ({})",
+                triage::classify(stack_pointer)
+            )?;
+        }
+
+        writeln!(f, "  loaded selectors: {:?}", LoadedSelectors::read())?;
+
+        write!(f, "{self:#X?}")
+    }
+}
+
 impl From<ArchException<'_>> for Exception {
     fn from(value: ArchException) -> Self {
         use crate::interrupts::exceptions::{ExceptionKind, PageFaultReason};
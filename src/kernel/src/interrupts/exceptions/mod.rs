@@ -1,22 +1,99 @@
 mod page_fault;
 
+pub mod policy;
+use policy::Policy;
+
+pub mod triage;
+
 mod arch;
 pub use arch::*;
 
 use core::ptr::NonNull;
 
+/// Action the interrupted context must take once an exception has been handled.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// Resume the interrupted context.
+    Resume,
+    /// Deliver a fault (at the given address) to the active task's handler, or kill the task if it can't handle it.
+    Fault(usize),
+}
+
 #[doc(hidden)]
 #[inline(never)]
-pub fn handle(exception: &ArchException) {
+pub fn handle(exception: &ArchException) -> Recovery {
+    if let Some(vector) = exception.vector() {
+        super::irq::account(vector);
+    }
+
+    // An overrun is likely what caused the exception, and is better reported as such. If the scheduler is locked by
+    // whatever faulted, its canaries are left for the next context switch.
+    if !crate::panic::is_panicking() {
+        crate::cpu::local_state::LocalState::try_with_scheduler(|scheduler| {
+            scheduler.check_canaries();
+        });
+    }
+
     match exception {
-        // Safety: Function is called once per this page fault exception.
-        ArchException::PageFault(_, _, _, address) => unsafe {
-            if let Err(err) = page_fault::handler(*address) {
-                panic!("error handling page fault: {}", err)
+        // The page is mapped, so demand mapping it would only obscure the violation.
+        ArchException::PageFault(..) if exception.supervisor_violation().is_some() => {
+            apply_policy(exception, format_args!("{exception}"))
+        }
+
+        ArchException::PageFault(_, _, _, address) => {
+            // Safety: Function is called once per this page fault exception.
+            match unsafe { page_fault::handler(*address) } {
+                Ok(()) => Recovery::Resume,
+                Err(err) => apply_policy(
+                    exception,
+                    format_args!("error handling page fault: {err}\n{exception}"),
+                ),
             }
-        },
+        }
+
+        // Another hardware thread has panicked, and has signalled every other hardware thread to stop.
+        ArchException::NonMaskable(_, _) if crate::panic::is_panicking() => {
+            crate::cpu::halt_and_catch_fire()
+        }
+
+        // The debug shell has asked this hardware thread for its backtrace.
+        ArchException::NonMaskable(stack_frame, gprs)
+            if crate::debugger::is_backtrace_requested() =>
+        {
+            crate::debugger::capture_requested(stack_frame, gprs);
+
+            Recovery::Resume
+        }
+
+        exception => apply_policy(exception, format_args!("{exception}")),
+    }
+}
+
+/// Applies the configured policy for `exception`, panicking if it originated in kernel mode or has no policy.
+fn apply_policy(exception: &ArchException, message: core::fmt::Arguments) -> Recovery {
+    let Some(class) = exception.class() else {
+        panic!("{message}")
+    };
+
+    if !exception.is_from_user() {
+        panic!("{message}")
+    }
+
+    match policy::get(class) {
+        Policy::Panic => panic!("{message}"),
+
+        Policy::KillTask => {
+            warn!("Task caused {class:?} exception, faulting it: {message}");
+
+            Recovery::Fault(exception.fault_address())
+        }
+
+        Policy::LogAndContinue => {
+            warn!("Task caused {class:?} exception, continuing: {message}");
 
-        exception => panic!("{exception:#X?}"),
+            Recovery::Resume
+        }
     }
 }
 
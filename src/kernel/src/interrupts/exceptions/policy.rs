@@ -0,0 +1,219 @@
+use core::sync::atomic::{AtomicU8, Ordering};
+
+crate::parameters! {
+    /// Policies for exceptions caused by user tasks, as `class:policy` pairs (i.e. `page-fault:panic,breakpoint:log`).
+    /// Policies are `panic`, `kill`, or `log`; `log` is only valid for traps.
+    "exception-policy": str = "",
+}
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("{policy:?} is not architecturally valid for {class:?}")]
+    InvalidPolicy {
+        class: ExceptionClass,
+        policy: Policy,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum ParseError<'a> {
+    #[error("{0:?} is not a `class:policy` pair")]
+    Invalid(&'a str),
+
+    #[error("{0:?} is not an exception class")]
+    UnknownClass(&'a str),
+
+    #[error("{0:?} is not a policy")]
+    UnknownPolicy(&'a str),
+
+    #[error(transparent)]
+    Policy(#[from] Error),
+}
+
+/// Classes of exceptions which may have a recovery policy configured.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+#[allow(non_camel_case_types)]
+pub enum ExceptionClass {
+    DivideError,
+    Debug,
+    Breakpoint,
+    Overflow,
+    BoundRangeExceeded,
+    InvalidOpcode,
+    DeviceNotAvailable,
+    InvalidTSS,
+    SegmentNotPresent,
+    StackSegmentFault,
+    GeneralProtectionFault,
+    PageFault,
+    x87FloatingPoint,
+    AlignmentCheck,
+    SimdFloatingPoint,
+    Virtualization,
+    ControlProtection,
+}
+
+impl ExceptionClass {
+    const COUNT: usize = 17;
+
+    /// Parses the name of an exception class, as given to the `exception-policy` parameter.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let class = match name {
+            "divide-error" => Self::DivideError,
+            "debug" => Self::Debug,
+            "breakpoint" => Self::Breakpoint,
+            "overflow" => Self::Overflow,
+            "bound-range-exceeded" => Self::BoundRangeExceeded,
+            "invalid-opcode" => Self::InvalidOpcode,
+            "device-not-available" => Self::DeviceNotAvailable,
+            "invalid-tss" => Self::InvalidTSS,
+            "segment-not-present" => Self::SegmentNotPresent,
+            "stack-segment-fault" => Self::StackSegmentFault,
+            "general-protection-fault" => Self::GeneralProtectionFault,
+            "page-fault" => Self::PageFault,
+            "x87-floating-point" => Self::x87FloatingPoint,
+            "alignment-check" => Self::AlignmentCheck,
+            "simd-floating-point" => Self::SimdFloatingPoint,
+            "virtualization" => Self::Virtualization,
+            "control-protection" => Self::ControlProtection,
+            _ => return None,
+        };
+
+        Some(class)
+    }
+
+    /// Whether the exception is a trap, and so execution can resume after the faulting instruction.
+    pub const fn is_trap(self) -> bool {
+        matches!(self, Self::Debug | Self::Breakpoint | Self::Overflow)
+    }
+}
+
+/// Action taken when a user task causes an exception.
+///
+/// # Remarks
+///
+/// Exceptions originating in kernel mode always panic, regardless of policy.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+pub enum Policy {
+    /// Panic the kernel.
+    Panic,
+    /// Kill the task which caused the exception.
+    KillTask,
+    /// Log the exception, and resume the task.
+    LogAndContinue,
+}
+
+impl Policy {
+    /// Parses the name of a policy, as given to the `exception-policy` parameter.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "panic" => Some(Self::Panic),
+            "kill" => Some(Self::KillTask),
+            "log" => Some(Self::LogAndContinue),
+            _ => None,
+        }
+    }
+
+    /// Whether this policy can be applied to `class` without re-executing the faulting instruction forever.
+    pub const fn is_valid_for(self, class: ExceptionClass) -> bool {
+        match self {
+            Self::Panic | Self::KillTask => true,
+            Self::LogAndContinue => class.is_trap(),
+        }
+    }
+}
+
+#[allow(clippy::as_conversions)]
+static POLICIES: [AtomicU8; ExceptionClass::COUNT] =
+    [const { AtomicU8::new(Policy::KillTask as u8) }; ExceptionClass::COUNT];
+
+/// Gets the policy for exceptions of `class` which originate from a user task.
+pub fn get(class: ExceptionClass) -> Policy {
+    let policy = POLICIES[usize::from(u8::from(class))].load(Ordering::Relaxed);
+
+    Policy::try_from(policy).unwrap()
+}
+
+/// Sets the policy for exceptions of `class` which originate from a user task.
+pub fn set(class: ExceptionClass, policy: Policy) -> Result<(), Error> {
+    if !policy.is_valid_for(class) {
+        return Err(Error::InvalidPolicy { class, policy });
+    }
+
+    POLICIES[usize::from(u8::from(class))].store(policy.into(), Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// Sets the policies in a comma-separated list of `class:policy` pairs, i.e. `page-fault:panic,breakpoint:log`.
+///
+/// # Remarks
+///
+/// Pairs before an invalid one are still set.
+pub fn set_from_list(list: &str) -> Result<(), ParseError<'_>> {
+    for pair in list
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+    {
+        let (class, policy) = pair.split_once(':').ok_or(ParseError::Invalid(pair))?;
+        let class =
+            ExceptionClass::from_name(class.trim()).ok_or(ParseError::UnknownClass(class))?;
+        let policy = Policy::from_name(policy.trim()).ok_or(ParseError::UnknownPolicy(policy))?;
+
+        set(class, policy)?;
+    }
+
+    Ok(())
+}
+
+/// Sets the policies given by the `exception-policy` parameter.
+pub fn init() {
+    let list = crate::params::get::<&str>("exception-policy");
+
+    match set_from_list(list) {
+        Ok(()) if !list.is_empty() => info!("Exception policies: {list}"),
+        Ok(()) => {}
+        Err(error) => warn!("Ignoring exception policies {list:?}: {error}"),
+    }
+}
+
+crate::kernel_test! {
+    /// Policies set from a list are read back, and invalid ones are refused without changing the policy.
+    fn sets_policies_from_list() -> crate::tests::Outcome {
+        let breakpoint = get(ExceptionClass::Breakpoint);
+        let page_fault = get(ExceptionClass::PageFault);
+
+        let outcome = (|| {
+            set_from_list("breakpoint:log, page-fault:panic").map_err(|_| "failed to set a valid list")?;
+            if get(ExceptionClass::Breakpoint) != Policy::LogAndContinue
+                || get(ExceptionClass::PageFault) != Policy::Panic
+            {
+                return Err("policies weren't set from the list");
+            }
+
+            if !matches!(set_from_list("page-fault:log"), Err(ParseError::Policy(_))) {
+                return Err("continuing after a fault was accepted");
+            }
+            if get(ExceptionClass::PageFault) != Policy::Panic {
+                return Err("refused policy changed the page fault policy");
+            }
+
+            if !matches!(set_from_list("page-fault:ignore"), Err(ParseError::UnknownPolicy(_)))
+                || !matches!(set_from_list("pagefault:kill"), Err(ParseError::UnknownClass(_)))
+                || !matches!(set_from_list("page-fault"), Err(ParseError::Invalid(_)))
+            {
+                return Err("malformed list was accepted");
+            }
+
+            Ok(())
+        })();
+
+        set(ExceptionClass::Breakpoint, breakpoint).unwrap();
+        set(ExceptionClass::PageFault, page_fault).unwrap();
+
+        outcome
+    }
+}
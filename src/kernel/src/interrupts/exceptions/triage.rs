@@ -0,0 +1,105 @@
+use crate::{
+    LinkerSymbol,
+    mem::{HigherHalfDirectMap, paging, pmm::PhysicalMemoryManager},
+    task::{DEFAULT_USERSPACE_SIZE, STACK_START},
+};
+use core::ops::Range;
+
+unsafe extern "C" {
+    unsafe static __kernel_start: LinkerSymbol;
+    unsafe static __text_start: LinkerSymbol;
+    unsafe static __etext: LinkerSymbol;
+    unsafe static __kernel_end: LinkerSymbol;
+}
+
+/// Region of the virtual address space an address falls within.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressRegion {
+    /// Unmapped page(s) at the bottom of the address space, below the user stack.
+    ///
+    /// Accesses here are typically null pointer dereferences, or a user stack overflow.
+    GuardPage,
+    UserSpace,
+    /// Address is not sign-extended from the highest implemented bit.
+    NonCanonical,
+    HigherHalfDirectMap,
+    KernelText,
+    /// Non-executable sections of the kernel image (i.e. read-only data, data, and BSS).
+    KernelData,
+    /// Higher-half address outside of any known kernel region.
+    KernelSpace,
+}
+
+impl core::fmt::Display for AddressRegion {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::GuardPage => "guard page",
+            Self::UserSpace => "user space",
+            Self::NonCanonical => "non-canonical",
+            Self::HigherHalfDirectMap => "higher-half direct map",
+            Self::KernelText => "kernel text",
+            Self::KernelData => "kernel data",
+            Self::KernelSpace => "kernel space",
+        })
+    }
+}
+
+fn hhdm_range() -> Option<Range<usize>> {
+    // The HHDM may not be initialized if the exception occurred very early in boot.
+    if !HigherHalfDirectMap::is_initialized() || !PhysicalMemoryManager::is_initialized() {
+        return None;
+    }
+
+    let start = HigherHalfDirectMap::offset(0).get();
+    let end = start.checked_add(PhysicalMemoryManager::total_memory())?;
+
+    Some(start..end)
+}
+
+/// Classifies `address` by the region of the virtual address space it falls within.
+pub fn classify(address: usize) -> AddressRegion {
+    // Safety: Symbols are provided by the linker script.
+    let (kernel_start, text_start, text_end, kernel_end) = unsafe {
+        (
+            __kernel_start.as_usize(),
+            __text_start.as_usize(),
+            __etext.as_usize(),
+            __kernel_end.as_usize(),
+        )
+    };
+
+    let canonical_bits = paging::virtual_address_bits();
+    let sign_extension = address >> (canonical_bits - 1);
+    if sign_extension != 0 && sign_extension != (usize::MAX >> (canonical_bits - 1)) {
+        AddressRegion::NonCanonical
+    } else if address < STACK_START.get() {
+        AddressRegion::GuardPage
+    } else if address < DEFAULT_USERSPACE_SIZE.get() {
+        AddressRegion::UserSpace
+    } else if (text_start..text_end).contains(&address) {
+        AddressRegion::KernelText
+    } else if (kernel_start..kernel_end).contains(&address) {
+        AddressRegion::KernelData
+    } else if hhdm_range().is_some_and(|hhdm| hhdm.contains(&address)) {
+        AddressRegion::HigherHalfDirectMap
+    } else {
+        AddressRegion::KernelSpace
+    }
+}
+
+crate::kernel_test! {
+    /// Kernel addresses are canonical for the paging mode handed over by the bootloader.
+    fn kernel_addresses_canonical() -> crate::tests::Outcome {
+        let stack_variable = 0u64;
+        let addresses = [
+            core::ptr::from_ref(&stack_variable).addr(),
+            HigherHalfDirectMap::offset(0).get(),
+        ];
+
+        if addresses.into_iter().any(|address| classify(address) == AddressRegion::NonCanonical) {
+            return Err("kernel address classified as non-canonical");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{cpu::local_state::LocalState, sync::Rcu};
+use core::sync::atomic::{AtomicU64, Ordering};
+use spin::Lazy;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no dynamic vectors are free")]
+    NoFreeVectors,
+
+    #[error("failed to route interrupt: {0}")]
+    #[cfg(target_arch = "x86_64")]
+    Route(#[from] crate::arch::x86_64::devices::ioapic::Error),
+}
+
+/// Timestamp counter value captured on entry to an interrupt stub, before any handler runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IrqTimestamp(u64);
+
+impl IrqTimestamp {
+    pub(crate) const fn from_ticks(ticks: u64) -> Self {
+        Self(ticks)
+    }
+
+    /// Raw timestamp counter value.
+    pub const fn ticks(self) -> u64 {
+        self.0
+    }
+
+    /// Nanoseconds since the timestamp counter was reset.
+    pub fn as_ns(self) -> u64 {
+        ticks_to_ns(self.0)
+    }
+
+    /// Nanoseconds elapsed since the timestamp was captured, i.e. the interrupt-to-handler latency when called
+    /// from a handler.
+    pub fn elapsed_ns(self) -> u64 {
+        ticks_to_ns(read_tsc().saturating_sub(self.0))
+    }
+}
+
+fn read_tsc() -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    // Safety: Interrupt stubs already depend upon the processor having TSC capability.
+    unsafe {
+        core::arch::x86_64::_rdtsc()
+    }
+}
+
+fn ticks_to_ns(ticks: u64) -> u64 {
+    let frequency = crate::time::tsc_frequency();
+
+    u64::try_from((u128::from(ticks) * 1_000_000_000) / u128::from(frequency.max(1)))
+        .unwrap_or(u64::MAX)
+}
+
+/// Timestamp of the interrupt most recently taken on the current hardware thread.
+///
+/// # Remarks
+///
+/// Within an IRQ handler, this is the timestamp of the interrupt being handled.
+pub fn irq_timestamp() -> IrqTimestamp {
+    LocalState::irq_timestamp()
+}
+
+/// Function invoked (in interrupt context) when a registered vector fires.
+pub type Handler = fn(vector: u8, timestamp: IrqTimestamp);
+
+type Handlers = [Option<Handler>; 256];
+
+/// Registered handlers, which are read on every dispatch, so are read-copy-updated rather than locked.
+static HANDLERS: Lazy<Rcu<Handlers>> = Lazy::new(|| Rcu::new([None; 256]));
+
+fn set_handler(vector: u8, handler: Option<Handler>) {
+    HANDLERS.update(|handlers| {
+        let mut handlers = *handlers;
+        handlers[usize::from(vector)] = handler;
+
+        handlers
+    });
+}
+
+/// Allocates a dynamic vector, and invokes `handler` whenever it fires.
+pub fn register(handler: Handler) -> Result<u8, Error> {
+    let vector = super::allocate_vector().ok_or(Error::NoFreeVectors)?;
+
+    set_handler(vector, Some(handler));
+
+    trace!("Registered IRQ handler for vector {vector:#X}.");
+
+    Ok(vector)
+}
+
+/// Allocates a dynamic vector for the legacy ISA IRQ `isa_irq`, routes the IRQ to the current hardware thread, and
+/// invokes `handler` whenever it fires.
+pub fn register_isa(isa_irq: u8, handler: Handler) -> Result<u8, Error> {
+    let vector = register(handler)?;
+    let (gsi, polarity, trigger_mode) = crate::acpi::madt::Topology::isa_irq_to_gsi(isa_irq);
+
+    #[cfg(target_arch = "x86_64")]
+    if let Err(error) = crate::arch::x86_64::devices::ioapic::route(
+        gsi,
+        vector,
+        polarity,
+        trigger_mode,
+        crate::cpu::get_id(),
+    ) {
+        unregister(vector);
+
+        return Err(error.into());
+    }
+
+    debug!("Routed ISA IRQ {isa_irq} (GSI {gsi}) to vector {vector:#X}.");
+
+    Ok(vector)
+}
+
+/// Removes the handler for `vector`, and returns the vector to the dynamic ranges.
+pub fn unregister(vector: u8) {
+    set_handler(vector, None);
+
+    super::free_vector(vector);
+}
+
+/// Number of times `vector` has fired since boot, across every hardware thread.
+pub fn count(vector: u8) -> u64 {
+    counts(vector).map(|(_, count)| count).sum()
+}
+
+/// Number of times `vector` has been taken on each hardware thread, by hardware thread ID.
+pub fn counts(vector: u8) -> impl Iterator<Item = (u32, u64)> {
+    crate::cpu::percpu::blocks().map(move |block| {
+        (
+            block.hwthread_id.load(Ordering::Relaxed),
+            block.vector_counts[usize::from(vector)].load(Ordering::Relaxed),
+        )
+    })
+}
+
+/// Counts an occurrence of `vector` (an interrupt or an exception) on the current hardware thread.
+pub(crate) fn account(vector: u8) {
+    crate::percpu!(vector_counts)[usize::from(vector)].fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts an occurrence of `vector`, and records its timestamp for the current hardware thread.
+pub(crate) fn record(vector: u8, timestamp: IrqTimestamp) {
+    account(vector);
+
+    LocalState::set_irq_timestamp(timestamp);
+    crate::rand::add_interrupt(vector, timestamp);
+}
+
+/// Invokes the handler registered for `vector`, returning whether one was registered.
+pub(crate) fn dispatch(vector: u8, timestamp: IrqTimestamp) -> bool {
+    let handler = HANDLERS.read(|handlers| handlers[usize::from(vector)]);
+
+    handler
+        .inspect(|handler| handler(vector, timestamp))
+        .is_some()
+}
@@ -1,4 +1,7 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
 pub mod exceptions;
+pub mod irq;
 pub mod syscall;
 
 #[repr(u8)]
@@ -12,6 +15,7 @@ pub enum Vector {
     ThermalSensor = 0x24,
     CMCI = 0x25,
     External = 0x26,
+    TlbShootdown = 0x27,
 
     Syscall = 0x80,
 
@@ -21,8 +25,191 @@ pub enum Vector {
     Unknown = 0,
 }
 
+/// Classification of a vector within the vector map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorKind {
+    /// Reserved by the architecture for exceptions.
+    Exception,
+    /// Statically assigned to a kernel [`Vector`].
+    Fixed(Vector),
+    /// Raised by the (masked) legacy PICs, which are remapped here; see [`LEGACY_PIC_VECTORS`].
+    LegacyPic,
+    /// Available for runtime allocation via [`allocate_vector`].
+    Dynamic,
+    /// Neither assigned nor allocatable.
+    Unassigned,
+}
+
+/// Vectors reserved by the architecture for exceptions.
+pub const EXCEPTION_VECTORS: core::ops::RangeInclusive<u8> = 0x00..=0x1F;
+
+/// Vectors statically assigned to the kernel.
+///
+/// # Remarks
+///
+/// [`Vector::Syscall`] is fixed at `0x80` by the userspace ABI, which is why the dynamic ranges are split around it.
+pub const FIXED_VECTORS: &[Vector] = &[
+    Vector::Watchdog,
+    Vector::Timer,
+    Vector::Error,
+    Vector::PerformanceCounter,
+    Vector::ThermalSensor,
+    Vector::CMCI,
+    Vector::External,
+    Vector::TlbShootdown,
+    Vector::Syscall,
+    Vector::Spurious,
+];
+
+/// Vector ranges which may be allocated at runtime (i.e. for device interrupts).
+pub const DYNAMIC_VECTORS: &[core::ops::RangeInclusive<u8>] =
+    &[0x30..=0x7F, 0x81..=0xDF, 0xF0..=0xFE];
+
+/// Vectors the legacy PICs are remapped to, master then slave; only spurious interrupts are raised on them.
+pub const LEGACY_PIC_VECTORS: core::ops::RangeInclusive<u8> = 0xE0..=0xEF;
+
+const fn range_contains(range: &core::ops::RangeInclusive<u8>, vector: u8) -> bool {
+    *range.start() <= vector && vector <= *range.end()
+}
+
+const fn ranges_overlap(
+    a: &core::ops::RangeInclusive<u8>,
+    b: &core::ops::RangeInclusive<u8>,
+) -> bool {
+    *a.start() <= *b.end() && *b.start() <= *a.end()
+}
+
+/// Validates the vector map, so conflicting assignments fail the build rather than misrouting interrupts.
+#[allow(clippy::as_conversions)]
+const _: () = {
+    let mut index = 0;
+    while index < FIXED_VECTORS.len() {
+        let vector = FIXED_VECTORS[index] as u8;
+
+        assert!(
+            !range_contains(&EXCEPTION_VECTORS, vector),
+            "fixed vector overlaps exception vectors"
+        );
+        assert!(
+            !range_contains(&LEGACY_PIC_VECTORS, vector),
+            "fixed vector overlaps legacy PIC vectors"
+        );
+
+        let mut other_index = index + 1;
+        while other_index < FIXED_VECTORS.len() {
+            assert!(
+                vector != FIXED_VECTORS[other_index] as u8,
+                "fixed vector is assigned twice"
+            );
+            other_index += 1;
+        }
+
+        let mut range_index = 0;
+        while range_index < DYNAMIC_VECTORS.len() {
+            assert!(
+                !range_contains(&DYNAMIC_VECTORS[range_index], vector),
+                "fixed vector overlaps dynamic vectors"
+            );
+            range_index += 1;
+        }
+
+        index += 1;
+    }
+
+    let mut range_index = 0;
+    while range_index < DYNAMIC_VECTORS.len() {
+        let range = &DYNAMIC_VECTORS[range_index];
+
+        assert!(
+            *range.start() <= *range.end(),
+            "dynamic vector range is empty"
+        );
+        assert!(
+            !ranges_overlap(range, &EXCEPTION_VECTORS),
+            "dynamic vectors overlap exception vectors"
+        );
+        assert!(
+            !ranges_overlap(range, &LEGACY_PIC_VECTORS),
+            "dynamic vectors overlap legacy PIC vectors"
+        );
+
+        let mut other_index = range_index + 1;
+        while other_index < DYNAMIC_VECTORS.len() {
+            assert!(
+                !ranges_overlap(range, &DYNAMIC_VECTORS[other_index]),
+                "dynamic vector ranges overlap"
+            );
+            other_index += 1;
+        }
+
+        range_index += 1;
+    }
+
+    assert!(
+        LEGACY_PIC_VECTORS.start().is_multiple_of(8)
+            && (*LEGACY_PIC_VECTORS.end() - *LEGACY_PIC_VECTORS.start()) == 15,
+        "legacy PIC vectors must be two aligned blocks of eight"
+    );
+    assert!(
+        !ranges_overlap(&LEGACY_PIC_VECTORS, &EXCEPTION_VECTORS),
+        "legacy PIC vectors overlap exception vectors"
+    );
+};
+
+/// Classifies `vector` according to the vector map.
+pub fn vector_kind(vector: u8) -> VectorKind {
+    if range_contains(&EXCEPTION_VECTORS, vector) {
+        VectorKind::Exception
+    } else if let Some(fixed_vector) = FIXED_VECTORS
+        .iter()
+        .copied()
+        .find(|fixed_vector| u8::from(*fixed_vector) == vector)
+    {
+        VectorKind::Fixed(fixed_vector)
+    } else if DYNAMIC_VECTORS.iter().any(|range| range.contains(&vector)) {
+        VectorKind::Dynamic
+    } else if LEGACY_PIC_VECTORS.contains(&vector) {
+        VectorKind::LegacyPic
+    } else {
+        VectorKind::Unassigned
+    }
+}
+
+/// Bitmap of allocated vectors, indexed by vector number.
+static ALLOCATED_VECTORS: [AtomicU64; 4] = [const { AtomicU64::new(0) }; 4];
+
+/// Allocates a vector from the dynamic ranges, or `None` if all are in use.
+pub fn allocate_vector() -> Option<u8> {
+    DYNAMIC_VECTORS.iter().cloned().flatten().find(|vector| {
+        let word = &ALLOCATED_VECTORS[usize::from(*vector / 64)];
+        let bit = 1 << (*vector % 64);
+
+        (word.fetch_or(bit, Ordering::AcqRel) & bit) == 0
+    })
+}
+
+/// Returns a vector previously returned by [`allocate_vector`] to the dynamic ranges.
+pub fn free_vector(vector: u8) {
+    assert_eq!(
+        vector_kind(vector),
+        VectorKind::Dynamic,
+        "only dynamic vectors can be freed"
+    );
+
+    let bit = 1 << (vector % 64);
+    let previous = ALLOCATED_VECTORS[usize::from(vector / 64)].fetch_and(!bit, Ordering::AcqRel);
+
+    debug_assert!(
+        (previous & bit) > 0,
+        "freed vector was not allocated: {vector:#X}"
+    );
+}
+
 /// Enables interrupts for the current hardware thread.
 pub fn enable() {
+    #[cfg(feature = "lockdep")]
+    crate::sync::lockdep::enabling_interrupts();
+
     #[cfg(target_arch = "x86_64")]
     crate::arch::x86_64::instructions::__sti();
 
@@ -53,6 +240,11 @@ pub fn is_enabled() -> bool {
     }
 }
 
+/// Whether the current hardware thread is handling an interrupt.
+pub fn in_interrupt_context() -> bool {
+    crate::cpu::local_state::LocalState::in_interrupt()
+}
+
 /// Waits for the next interrupt on the current hardware thread.
 pub fn wait_next() {
     #[cfg(target_arch = "x86_64")]
@@ -0,0 +1,1137 @@
+use crate::{
+    arch::x86_64::structures::idt::InterruptStackFrame,
+    cpu::local_state::LocalState,
+    mem::user::{copy_from_user, copy_to_user, read_user, write_user},
+    task::{
+        MmapPermissions, Registers,
+        handles::{Handle, Rights},
+        hardware::Source,
+        signals::Events,
+        timers::ArmFlags,
+    },
+};
+use core::time::Duration;
+use libsys::syscall::{Error, Result, Success, Vector};
+use table::{UserLen, UserPtr};
+use zerocopy::{FromZeros, IntoBytes};
+
+pub mod table;
+pub mod trace;
+
+crate::syscalls! {
+    /// Logs a string at the info level.
+    KlogInfo(str: UserPtr, len: UserLen) = libsys_number(Vector::KlogInfo) => process_klog_info;
+    /// Logs a string at the error level.
+    KlogError(str: UserPtr, len: UserLen) = libsys_number(Vector::KlogError) => process_klog_error;
+    /// Logs a string at the debug level.
+    KlogDebug(str: UserPtr, len: UserLen) = libsys_number(Vector::KlogDebug) => process_klog_debug;
+    /// Logs a string at the trace level.
+    KlogTrace(str: UserPtr, len: UserLen) = libsys_number(Vector::KlogTrace) => process_klog_trace;
+    /// Exits the task with the default exit code.
+    TaskExit() = libsys_number(Vector::TaskExit) => process_task_exit [context];
+    /// Yields the rest of the task's time slice.
+    TaskYield() = libsys_number(Vector::TaskYield) => process_task_yield [context];
+
+    /// Fills a buffer with random bytes.
+    GetRandom(buf: UserPtr, len: UserLen, flags: GetRandomFlags) = 0x1000 => process_getrandom;
+    /// Fills a buffer with random bytes, once the entropy pool has been seeded.
+    GetEntropy(buf: UserPtr, len: UserLen) = 0x1001 => process_getentropy;
+    /// Sleeps for at least a duration (in nanoseconds).
+    TaskSleep(duration: Duration) = 0x1002 => process_task_sleep [context];
+    /// Reads a clock.
+    ClockGetTime(clock_id: ClockId, timespec_ptr: UserPtr) = 0x1003 => process_clock_gettime;
+    /// Resolves addresses in the task's binary to symbols.
+    Symbolize(records_ptr: UserPtr, count: usize) = 0x1004 => process_symbolize;
+    /// Creates a timer.
+    TimerCreate(clock_id: ClockId, handle_ptr: UserPtr) = 0x1005 => process_timer_create;
+    /// Arms (or disarms) a timer.
+    TimerArm(
+        handle: Handle,
+        initial: Duration,
+        interval: Duration,
+        flags: ArmFlags,
+    ) = 0x1006 => process_timer_arm;
+    /// Waits for the next timer expiration.
+    TimerWait(expiration_ptr: UserPtr) = 0x1008 => process_timer_wait [context];
+    /// Reads records retained by the log ring.
+    KlogRead(buf: UserPtr, len: UserLen, cursor_ptr: UserPtr) = 0x1009 => process_klog_read;
+    /// Reads the count of each interrupt vector on each hardware thread.
+    IrqStats(records_ptr: UserPtr, capacity: usize, total_ptr: UserPtr) = 0x100A => process_irq_stats;
+    /// Exits the task with an exit code.
+    TaskExitWith(code: usize) = 0x100B => process_task_exit_with [context];
+    /// Waits for a child task to exit.
+    TaskWait(status_ptr: UserPtr) = 0x100C => process_task_wait [context];
+    /// Restricts the hardware threads the task may run on.
+    TaskSetAffinity(affinity_ptr: UserPtr) = 0x100D => process_task_set_affinity [context];
+    /// Reads a snapshot of every task.
+    TaskInfo(records_ptr: UserPtr, capacity: usize, total_ptr: UserPtr) = 0x100E => process_task_info;
+    /// Creates a thread in the task's process.
+    ThreadCreate(entry: usize, stack: usize, arg: usize, id_ptr: UserPtr) = 0x100F => process_thread_create;
+    /// Registers the task's event handler.
+    SignalHandler(entry: usize, trampoline: usize, events: Events) = 0x1010 => process_signal_handler;
+    /// Returns from the task's event handler.
+    SignalReturn() = 0x1011 => process_signal_return [context];
+    /// Posts a message event to a task.
+    SignalPost(id_ptr: UserPtr, info: usize) = 0x1012 => process_signal_post;
+    /// Creates a port.
+    IpcCreate(handle_ptr: UserPtr) = 0x1013 => process_ipc_create;
+    /// Sends a message to a port.
+    IpcSend(handle: Handle, message_ptr: UserPtr) = 0x1015 => process_ipc_send [context];
+    /// Receives a message from a port.
+    IpcRecv(handle: Handle, message_ptr: UserPtr) = 0x1016 => process_ipc_recv [context];
+    /// Sends a message to a port, and receives its reply.
+    IpcCall(handle: Handle, message_ptr: UserPtr) = 0x1017 => process_ipc_call [context];
+    /// Creates a shared memory object.
+    ShmCreate(page_count: usize, handle_ptr: UserPtr) = 0x1018 => process_shm_create;
+    /// Maps a shared memory object.
+    ShmMap(handle: Handle, address: UserPtr, permissions: MmapPermissions) = 0x101A => process_shm_map;
+    /// Unmaps a shared memory object.
+    ShmUnmap(address: UserPtr) = 0x101B => process_shm_unmap;
+    /// Derives a handle with fewer rights.
+    HandleDuplicate(handle: Handle, rights: Rights, handle_ptr: UserPtr) = 0x101C => process_handle_duplicate;
+    /// Closes a handle.
+    HandleClose(handle: Handle) = 0x101D => process_handle_close;
+    /// Binds an interrupt to a port.
+    IrqBind(
+        authority: Handle,
+        source: Source,
+        line: usize,
+        port: Handle,
+        key: u64,
+        binding_ptr: UserPtr,
+    ) = 0x101E => process_irq_bind;
+    /// Acknowledges a bound interrupt, so it may be delivered again.
+    IrqAck(handle: Handle) = 0x101F => process_irq_ack;
+    /// Claims a PCI function.
+    DeviceClaim(authority: Handle, address: usize, handle_ptr: UserPtr) = 0x1020 => process_device_claim;
+    /// Grants access to I/O ports.
+    IoPortGrant(handle: Handle, first: usize, count: usize) = 0x1021 => process_io_port_grant;
+    /// Maps the device memory of a claimed function.
+    MmioMap(
+        handle: Handle,
+        physical: usize,
+        page_count: usize,
+        address: UserPtr,
+    ) = 0x1022 => process_mmio_map;
+    /// Unmaps device memory.
+    MmioUnmap(address: UserPtr) = 0x1023 => process_mmio_unmap;
+    /// Opens a file.
+    FsOpen(path_ptr: UserPtr, path_len: UserLen, fd_ptr: UserPtr) = 0x1024 => process_fs_open;
+    /// Reads from a file.
+    FsRead(fd: usize, buf: UserPtr, len: UserLen, count_ptr: UserPtr) = 0x1025 => process_fs_read;
+    /// Closes a file.
+    FsClose(fd: usize) = 0x1026 => process_fs_close;
+    /// Reads the metadata of a node.
+    FsStat(path_ptr: UserPtr, path_len: UserLen, stat_ptr: UserPtr) = 0x1027 => process_fs_stat;
+    /// Sets whether the task's system calls are traced.
+    TaskTrace(enable: bool) = 0x1028 => process_task_trace;
+    /// Reads the task's CPU time.
+    GetRusage(usage_ptr: UserPtr) = 0x1029 => process_getrusage;
+}
+
+/// Number `libsys` gives `vector`, so calls it describes are numbered alike by the kernel and userspace.
+const fn libsys_number(vector: Vector) -> usize {
+    #[allow(clippy::as_conversions)]
+    let number = vector as usize;
+
+    number
+}
+
+/// Length of the `int 0x80` instruction which enters a system call.
+const INSTRUCTION_LEN: usize = 2;
+
+/// Rewinds `isf` over the instruction which entered the current system call, so the call is issued again the next
+/// time the task runs (i.e. once it's woken, after being blocked by the call).
+///
+/// # Safety
+///
+/// `isf` must be the interrupt stack frame of the current system call, and the task's registers must be left as they
+/// were on entry, so the same call is issued.
+pub unsafe fn restart(isf: &mut InterruptStackFrame) {
+    let instruction_ptr = isf.get_instruction_pointer().get();
+
+    // Safety: Caller is required to pass the frame of a system call, which was entered by the rewound instruction.
+    unsafe {
+        isf.set_instruction_pointer(
+            libsys::Address::new(instruction_ptr - INSTRUCTION_LEN).unwrap(),
+        );
+    }
+}
+
+/// Clocks which may be read by [`Syscall::ClockGetTime`], or armed against by [`Syscall::TimerArm`].
+#[repr(usize)]
+#[derive(Debug, Clone, Copy, TryFromPrimitive)]
+pub enum ClockId {
+    /// Wall-clock time, since the Unix epoch.
+    Realtime = 0,
+    /// Monotonic time, since the system clock started.
+    Monotonic = 1,
+}
+
+/// Time value written to userspace by [`Syscall::ClockGetTime`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable)]
+pub struct Timespec {
+    pub seconds: u64,
+    pub nanoseconds: u64,
+}
+
+/// CPU time of the calling task, written to userspace by [`Syscall::GetRusage`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable)]
+pub struct Rusage {
+    /// Time spent running in userspace.
+    pub user_time_ns: u64,
+    /// Time spent running in the kernel, servicing the task's system calls.
+    pub kernel_time_ns: u64,
+}
+
+/// Read position exchanged with userspace by [`Syscall::KlogRead`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable)]
+pub struct KlogCursor {
+    /// Sequence number of the next record to read; updated to follow the last record written.
+    pub sequence: u64,
+    /// Number of bytes written to the buffer, set by the kernel.
+    pub len: usize,
+}
+
+/// Count of a vector on a hardware thread, written to userspace by [`Syscall::IrqStats`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable)]
+pub struct IrqStatRecord {
+    pub hwthread_id: u32,
+    /// Interrupt or exception vector.
+    pub vector: u32,
+    /// Number of times the vector has been taken on the hardware thread.
+    pub count: u64,
+}
+
+/// Maximum length (in bytes, including the NUL terminator) of a name written by [`Syscall::Symbolize`].
+pub const SYMBOL_NAME_LEN: usize = 128;
+
+/// Maximum number of records accepted by a single [`Syscall::Symbolize`] call.
+pub const MAX_SYMBOLIZE_RECORDS: usize = 256;
+
+/// Address resolution record exchanged with userspace by [`Syscall::Symbolize`].
+///
+/// Userspace provides `address`, and the kernel fills in the remaining fields.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable)]
+pub struct SymbolRecord {
+    /// Code address within the calling task.
+    pub address: usize,
+    /// Offset of `address` from the start of its symbol, or `usize::MAX` if it could not be resolved.
+    pub offset: usize,
+    /// NUL-terminated (and possibly truncated) symbol name, or empty if unresolved.
+    pub name: [u8; SYMBOL_NAME_LEN],
+}
+
+bitflags! {
+    /// Flags accepted by [`Syscall::GetRandom`].
+    #[repr(transparent)]
+    #[derive(Debug, Clone, Copy)]
+    pub struct GetRandomFlags: usize {
+        /// Do not wait for the entropy pool to be seeded.
+        const NONBLOCK = 1 << 0;
+        /// Return random bytes even if the entropy pool has not been seeded.
+        const INSECURE = 1 << 2;
+    }
+}
+
+/// Services the system call made by the active task, whose number and arguments are in `regs`, writing its result
+/// back to `regs`.
+pub fn process(state: &mut InterruptStackFrame, regs: &mut Registers) {
+    let vector = regs.rax;
+    let args = [regs.rdi, regs.rsi, regs.rdx, regs.rcx, regs.r8, regs.r9];
+
+    trace!("Syscall Args: Vector:{vector:X?}   {args:X?}");
+
+    LocalState::with_scheduler(crate::task::Scheduler::enter_syscall);
+
+    let traced_id = trace::enter(vector, &args);
+
+    let syscall = Syscall::try_from(vector);
+    let (result, caller_active) = match syscall {
+        Ok(syscall) => dispatch(syscall, args, state, regs),
+
+        Err(err) => {
+            warn!("Unhandled system call vector: {err:X?}");
+            (Err(Error::InvalidVector), true)
+        }
+    };
+
+    trace!("Syscall Result: {result:X?}");
+
+    if let Some(traced_id) = traced_id {
+        trace::exit(traced_id, vector, &result);
+    }
+
+    LocalState::with_scheduler(crate::task::Scheduler::exit_syscall);
+
+    // If the call switched tasks, `regs` are now the next task's: the caller either wrote its result to the registers
+    // it was saved with (see `write_result`), is restarted once it's woken, or has exited.
+    if !caller_active {
+        return;
+    }
+
+    // Returning from an event handler restores the task's registers wholesale, so there's no result to pass.
+    if result.is_ok() && matches!(syscall, Ok(Syscall::SignalReturn)) {
+        return;
+    }
+
+    write_result(regs, result);
+}
+
+/// ID of the task active on this hardware thread, if any.
+#[doc(hidden)]
+pub fn active_task_id() -> Option<uuid::Uuid> {
+    LocalState::with_scheduler(|scheduler| scheduler.process().map(crate::task::Task::id))
+}
+
+/// Writes `result` to `regs`, as the result of the system call they made.
+///
+/// # Remarks
+///
+/// Calls which switch out the active task without restarting (i.e. by sleeping or yielding) have to write their
+/// result before they do, so it's saved with the task's registers.
+fn write_result(regs: &mut Registers, result: Result) {
+    let (rdi, rsi) =
+        <libsys::syscall::Result as libsys::syscall::ResultConverter>::into_registers(result);
+    regs.rdi = rdi;
+    regs.rsi = rsi;
+}
+
+/// Error returned for arguments which are out of range, or otherwise invalid.
+// TODO `libsys` has no invalid argument error, so the closest available error is used.
+fn invalid_argument() -> Error {
+    Error::InvalidVector
+}
+
+fn user_error(error: crate::mem::user::Error) -> Error {
+    use crate::mem::user::Error as UserError;
+
+    match error {
+        UserError::NoActiveTask => Error::NoActiveTask,
+
+        error => {
+            debug!("System call user access failed: {error}");
+            Error::UnmappedMemory
+        }
+    }
+}
+
+/// Maximum number of bytes logged by a single `Klog*` call; longer strings are truncated.
+const MAX_KLOG_LEN: usize = 0x1000;
+
+fn process_klog(level: log::Level, str_ptr_arg: usize, str_len: usize) -> Result {
+    let mut str_bytes = alloc::vec![0; str_len.min(MAX_KLOG_LEN)];
+    copy_from_user(&mut str_bytes, str_ptr_arg).map_err(user_error)?;
+
+    let str = match core::str::from_utf8(&str_bytes) {
+        Ok(str) => str,
+
+        // Truncation may have split the last character, so log up to it.
+        Err(error) if str_len > MAX_KLOG_LEN && error.error_len().is_none() => {
+            core::str::from_utf8(&str_bytes[..error.valid_up_to()]).map_err(Error::from)?
+        }
+
+        Err(error) => return Err(Error::from(error)),
+    };
+
+    log!(level, "[KLOG]: {str}");
+
+    Ok(Success::Ok)
+}
+
+fn process_klog_info(str_ptr_arg: usize, str_len: usize) -> Result {
+    process_klog(log::Level::Info, str_ptr_arg, str_len)
+}
+
+fn process_klog_error(str_ptr_arg: usize, str_len: usize) -> Result {
+    process_klog(log::Level::Error, str_ptr_arg, str_len)
+}
+
+fn process_klog_debug(str_ptr_arg: usize, str_len: usize) -> Result {
+    process_klog(log::Level::Debug, str_ptr_arg, str_len)
+}
+
+fn process_klog_trace(str_ptr_arg: usize, str_len: usize) -> Result {
+    process_klog(log::Level::Trace, str_ptr_arg, str_len)
+}
+
+/// Writes as many retained log records (one per line) as fit into the userspace buffer
+/// `[buf_ptr_arg, buf_ptr_arg + buf_len)`, starting from the userspace [`KlogCursor`] at `cursor_ptr_arg`.
+///
+/// # Remarks
+///
+/// Records which have been overwritten since the cursor's sequence number are skipped.
+fn process_klog_read(buf_ptr_arg: usize, buf_len: usize, cursor_ptr_arg: usize) -> Result {
+    use core::fmt::Write;
+
+    let mut cursor = read_user::<KlogCursor>(cursor_ptr_arg).map_err(user_error)?;
+
+    cursor.len = 0;
+    let mut line = alloc::string::String::new();
+    for record in crate::logging::ring::records_from(cursor.sequence) {
+        line.clear();
+        writeln!(line, "{record}").ok();
+
+        if cursor.len + line.len() > buf_len {
+            break;
+        }
+
+        copy_to_user(buf_ptr_arg + cursor.len, line.as_bytes()).map_err(user_error)?;
+        cursor.len += line.len();
+        cursor.sequence = record.sequence + 1;
+    }
+
+    // Records may have been overwritten before the cursor reached them.
+    cursor.sequence = cursor.sequence.max(crate::logging::ring::tail());
+
+    write_user(cursor_ptr_arg, &cursor).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Writes a record for each vector taken on each hardware thread (up to `capacity` of them) to `records_ptr_arg`,
+/// and the number of records there are in total to `total_ptr_arg`, so userspace can retry with a larger buffer.
+fn process_irq_stats(records_ptr_arg: usize, capacity: usize, total_ptr_arg: usize) -> Result {
+    let records = (u8::MIN..=u8::MAX).flat_map(|vector| {
+        crate::interrupts::irq::counts(vector)
+            .filter(|(_, count)| *count > 0)
+            .map(move |(hwthread_id, count)| IrqStatRecord {
+                hwthread_id,
+                vector: u32::from(vector),
+                count,
+            })
+    });
+
+    let mut total = 0usize;
+    for record in records {
+        if total < capacity {
+            write_user(
+                records_ptr_arg + (total * size_of::<IrqStatRecord>()),
+                &record,
+            )
+            .map_err(user_error)?;
+        }
+
+        total += 1;
+    }
+
+    write_user(total_ptr_arg, &total).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Exits the active task with the default exit code.
+fn process_task_exit(state: &mut InterruptStackFrame, regs: &mut Registers) -> Result {
+    crate::task::exit(crate::task::lifecycle::DEFAULT_CODE, state, regs);
+
+    Ok(Success::Ok)
+}
+
+/// Exits the active task with `code`.
+fn process_task_exit_with(
+    code: usize,
+    state: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result {
+    crate::task::exit(code, state, regs);
+
+    Ok(Success::Ok)
+}
+
+fn process_task_yield(state: &mut InterruptStackFrame, regs: &mut Registers) -> Result {
+    write_result(regs, Ok(Success::Ok));
+    LocalState::with_scheduler(|scheduler| scheduler.yield_task(state, regs));
+
+    Ok(Success::Ok)
+}
+
+fn process_task_sleep(
+    duration: Duration,
+    state: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result {
+    write_result(regs, Ok(Success::Ok));
+    crate::task::sleep(duration, state, regs);
+
+    Ok(Success::Ok)
+}
+
+/// Waits for one of the active task's children to exit, writing its [`ExitStatus`] to `status_ptr_arg`.
+///
+/// [`ExitStatus`]: crate::task::lifecycle::ExitStatus
+fn process_task_wait(
+    status_ptr_arg: usize,
+    state: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result {
+    use crate::task::lifecycle::{Error as LifecycleError, ExitStatus};
+
+    // The pointer is validated up front, so an exit status is never consumed without being delivered.
+    write_user(status_ptr_arg, &ExitStatus::new_zeroed()).map_err(user_error)?;
+
+    match crate::task::lifecycle::wait(state, regs) {
+        Ok(Some(status)) => {
+            write_user(status_ptr_arg, &status).map_err(user_error)?;
+        }
+
+        Ok(None) => {}
+
+        Err(LifecycleError::NoActiveTask) => return Err(Error::NoActiveTask),
+
+        Err(error) => {
+            debug!("Task wait system call failed: {error}");
+            return Err(invalid_argument());
+        }
+    }
+
+    Ok(Success::Ok)
+}
+
+/// Writes a [`TaskInfo`] for each task (up to `capacity` of them) to `records_ptr_arg`, and the number of tasks there
+/// are in total to `total_ptr_arg`, so userspace can retry with a larger buffer.
+///
+/// [`TaskInfo`]: crate::task::TaskInfo
+fn process_task_info(records_ptr_arg: usize, capacity: usize, total_ptr_arg: usize) -> Result {
+    let infos = crate::task::snapshot();
+
+    for (index, info) in infos.iter().take(capacity).enumerate() {
+        write_user(
+            records_ptr_arg + (index * size_of::<crate::task::TaskInfo>()),
+            info,
+        )
+        .map_err(user_error)?;
+    }
+
+    write_user(total_ptr_arg, &infos.len()).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Creates a thread in the active task's process, which enters `entry` with `arg` on the stack ending at `stack`,
+/// writing its ID (as the bytes of its UUID) to `id_ptr_arg`.
+///
+/// The creating task is made the thread's parent, so it can join the thread with [`Syscall::TaskWait`].
+fn process_thread_create(entry: usize, stack: usize, arg: usize, id_ptr_arg: usize) -> Result {
+    // The pointer is validated up front, so a thread is never created without its ID being delivered.
+    write_user(id_ptr_arg, &[0u8; 16]).map_err(user_error)?;
+
+    let (thread, creator_id) = LocalState::with_scheduler(|scheduler| {
+        let task = scheduler.task_mut().ok_or(Error::NoActiveTask)?;
+
+        let thread = task.new_thread(entry, stack, arg).map_err(|error| {
+            debug!("Thread create system call failed: {error}");
+            invalid_argument()
+        })?;
+
+        Ok((thread, task.id()))
+    })?;
+
+    let thread_id = thread.id();
+    crate::task::init::set_parent(thread_id, creator_id);
+    #[cfg(feature = "time_warp")]
+    crate::time::warp::set_group(thread_id, crate::time::warp::group_of(creator_id));
+    crate::task::enqueue_task(thread);
+
+    write_user(id_ptr_arg, &thread_id.into_bytes()).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+fn signal_error(error: crate::task::signals::Error) -> Error {
+    use crate::task::signals::Error as SignalError;
+
+    match error {
+        SignalError::NoActiveTask => Error::NoActiveTask,
+        SignalError::User(error) => user_error(error),
+
+        error => {
+            debug!("Signal system call failed: {error}");
+            invalid_argument()
+        }
+    }
+}
+
+/// Registers `entry` (returning through `trampoline`) as the active task's handler for `events`.
+fn process_signal_handler(entry: usize, trampoline: usize, events: Events) -> Result {
+    crate::task::signals::register(entry, trampoline, events).map_err(signal_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Returns from the active task's event handler, restoring the registers it was interrupted with.
+fn process_signal_return(state: &mut InterruptStackFrame, regs: &mut Registers) -> Result {
+    crate::task::signals::restore(state, regs).map_err(signal_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Posts a message event with `info` to the task whose ID (as the bytes of its UUID) is at `id_ptr_arg`.
+///
+/// # Remarks
+///
+/// Messages are discarded if the task doesn't handle them, or doesn't exist.
+fn process_signal_post(id_ptr_arg: usize, info: usize) -> Result {
+    let id = read_user::<[u8; 16]>(id_ptr_arg).map_err(user_error)?;
+    crate::task::signals::post(
+        uuid::Uuid::from_bytes(id),
+        crate::task::signals::Event::Message,
+        info,
+    );
+
+    Ok(Success::Ok)
+}
+
+fn handle_error(error: crate::task::handles::Error) -> Error {
+    use crate::task::handles::Error as HandleError;
+
+    match error {
+        HandleError::NoActiveTask => Error::NoActiveTask,
+
+        error => {
+            debug!("Handle system call failed: {error}");
+            invalid_argument()
+        }
+    }
+}
+
+/// Derives a handle to the object of `handle` granting only `rights`, writing it to `handle_ptr_arg`.
+fn process_handle_duplicate(handle: Handle, rights: Rights, handle_ptr_arg: usize) -> Result {
+    use crate::task::handles::NO_HANDLE;
+
+    // The handle is checked to be writable first, so a handle is never created without being returned.
+    write_user(handle_ptr_arg, &NO_HANDLE).map_err(user_error)?;
+
+    let handle = crate::task::handles::duplicate(handle, rights).map_err(handle_error)?;
+    write_user(handle_ptr_arg, &handle).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+fn process_handle_close(handle: Handle) -> Result {
+    crate::task::handles::close(handle).map_err(handle_error)?;
+
+    Ok(Success::Ok)
+}
+
+fn ipc_error(error: crate::task::ipc::Error) -> Error {
+    use crate::task::ipc::Error as IpcError;
+
+    match error {
+        IpcError::NoActiveTask => Error::NoActiveTask,
+        IpcError::Handles(error) => handle_error(error),
+
+        error => {
+            debug!("IPC system call failed: {error}");
+            invalid_argument()
+        }
+    }
+}
+
+/// Creates a port, writing the active task's handle to it to `handle_ptr_arg`.
+fn process_ipc_create(handle_ptr_arg: usize) -> Result {
+    // The handle is checked to be writable first, so a port is never created without its handle being returned.
+    write_user(handle_ptr_arg, &crate::task::handles::NO_HANDLE).map_err(user_error)?;
+
+    let handle = crate::task::ipc::create().map_err(ipc_error)?;
+    write_user(handle_ptr_arg, &handle).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Sends the [`Message`] at `message_ptr_arg` to the port of `handle`, blocking the task until there's room for it.
+///
+/// [`Message`]: crate::task::ipc::Message
+fn process_ipc_send(
+    handle: Handle,
+    message_ptr_arg: usize,
+    state: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result {
+    let message = read_user::<crate::task::ipc::Message>(message_ptr_arg).map_err(user_error)?;
+    crate::task::ipc::send(handle, &message, state, regs).map_err(ipc_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Receives the next [`Message`] from the port of `handle` into `message_ptr_arg`, blocking the task until one
+/// arrives.
+///
+/// [`Message`]: crate::task::ipc::Message
+fn process_ipc_recv(
+    handle: Handle,
+    message_ptr_arg: usize,
+    state: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result {
+    use crate::task::ipc::Message;
+
+    // The pointer is validated up front, so a message is never received without being delivered.
+    write_user(message_ptr_arg, &Message::new_zeroed()).map_err(user_error)?;
+
+    if let Some(message) = crate::task::ipc::recv(handle, state, regs).map_err(ipc_error)? {
+        write_user(message_ptr_arg, &message).map_err(user_error)?;
+    }
+
+    Ok(Success::Ok)
+}
+
+/// Sends the [`Message`] at `message_ptr_arg` to the port of `handle` with a reply port, then receives the reply into
+/// `message_ptr_arg`.
+///
+/// [`Message`]: crate::task::ipc::Message
+fn process_ipc_call(
+    handle: Handle,
+    message_ptr_arg: usize,
+    state: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result {
+    use crate::task::ipc::Message;
+
+    let message = read_user::<Message>(message_ptr_arg).map_err(user_error)?;
+    write_user(message_ptr_arg, &Message::new_zeroed()).map_err(user_error)?;
+
+    let Some(reply) = crate::task::ipc::call(handle, &message, state, regs).map_err(ipc_error)?
+    else {
+        return Ok(Success::Ok);
+    };
+
+    // The call continues as a receive on its reply port, so that's what's issued again if the task blocks for it.
+    regs.rax = usize::from(Syscall::IpcRecv);
+    regs.rdi = reply;
+
+    process_ipc_recv(reply, message_ptr_arg, state, regs)
+}
+
+fn shm_error(error: crate::task::shared_memory::Error) -> Error {
+    use crate::task::shared_memory::Error as ShmError;
+
+    match error {
+        ShmError::NoActiveTask => Error::NoActiveTask,
+        ShmError::Handles(error) => handle_error(error),
+
+        error => {
+            debug!("Shared memory system call failed: {error}");
+            invalid_argument()
+        }
+    }
+}
+
+/// Creates a shared memory object of `page_count` pages, writing the active task's handle to it to `handle_ptr_arg`.
+fn process_shm_create(page_count: usize, handle_ptr_arg: usize) -> Result {
+    // The handle is checked to be writable first, so an object is never created without its handle being returned.
+    write_user(handle_ptr_arg, &crate::task::handles::NO_HANDLE).map_err(user_error)?;
+
+    let handle = crate::task::shared_memory::create(page_count).map_err(shm_error)?;
+    write_user(handle_ptr_arg, &handle).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Maps the shared memory object of `handle` at `address` in the active task, with `permissions`.
+fn process_shm_map(handle: Handle, address: usize, permissions: MmapPermissions) -> Result {
+    crate::task::shared_memory::map(handle, address, permissions).map_err(shm_error)?;
+
+    Ok(Success::Ok)
+}
+
+fn process_shm_unmap(address: usize) -> Result {
+    crate::task::shared_memory::unmap(address).map_err(shm_error)?;
+
+    Ok(Success::Ok)
+}
+
+fn hardware_error(error: crate::task::hardware::Error) -> Error {
+    use crate::task::hardware::Error as HardwareError;
+
+    match error {
+        HardwareError::NoActiveTask => Error::NoActiveTask,
+        HardwareError::Handles(error) => handle_error(error),
+        HardwareError::Ipc(error) => ipc_error(error),
+
+        error => {
+            debug!("Hardware system call failed: {error}");
+            invalid_argument()
+        }
+    }
+}
+
+/// Binds the interrupt `line` of `source` to the port of `port`, with the hardware authority of `authority`, writing
+/// the [`Binding`] to `binding_ptr_arg`.
+///
+/// [`Binding`]: crate::task::hardware::Binding
+fn process_irq_bind(
+    authority: Handle,
+    source: Source,
+    line: usize,
+    port: Handle,
+    key: u64,
+    binding_ptr_arg: usize,
+) -> Result {
+    use crate::task::hardware::Binding;
+
+    // The pointer is validated up front, so an interrupt is never bound without its handle being returned.
+    write_user(binding_ptr_arg, &Binding::new_zeroed()).map_err(user_error)?;
+
+    let binding =
+        crate::task::hardware::bind(authority, source, line, port, key).map_err(hardware_error)?;
+    write_user(binding_ptr_arg, &binding).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+fn process_irq_ack(handle: Handle) -> Result {
+    crate::task::hardware::ack(handle).map_err(hardware_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Claims the PCI function at `address` with the hardware authority of `authority`, writing the active task's handle
+/// to the claim to `handle_ptr_arg`.
+fn process_device_claim(authority: Handle, address: usize, handle_ptr_arg: usize) -> Result {
+    // The pointer is validated up front, so a function is never claimed without its handle being returned.
+    write_user(handle_ptr_arg, &crate::task::handles::NO_HANDLE).map_err(user_error)?;
+
+    let handle = crate::task::hardware::claim(authority, address).map_err(hardware_error)?;
+    write_user(handle_ptr_arg, &handle).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Grants the active task access to the `count` I/O ports from `first`, with the hardware authority of `handle`.
+fn process_io_port_grant(handle: Handle, first: usize, count: usize) -> Result {
+    crate::task::hardware::grant_ports(handle, first, count).map_err(hardware_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Maps the `page_count` pages of MMIO from `physical` at `address` in the active task, with the claim of `handle`.
+fn process_mmio_map(handle: Handle, physical: usize, page_count: usize, address: usize) -> Result {
+    crate::task::hardware::map_mmio(handle, physical, page_count, address)
+        .map_err(hardware_error)?;
+
+    Ok(Success::Ok)
+}
+
+fn process_mmio_unmap(address: usize) -> Result {
+    crate::task::hardware::unmap_mmio(address).map_err(hardware_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Maximum number of bytes read by a single [`Syscall::FsRead`] call.
+const MAX_FS_READ: usize = 0x10000;
+
+fn fd_error(error: crate::fs::fd::Error) -> Error {
+    use crate::fs::fd::Error as FdError;
+
+    match error {
+        FdError::NoActiveTask => Error::NoActiveTask,
+
+        error => {
+            debug!("Filesystem system call failed: {error}");
+            invalid_argument()
+        }
+    }
+}
+
+/// Copies the path `[path_ptr_arg, path_ptr_arg + path_len)` from userspace.
+fn read_user_path(
+    path_ptr_arg: usize,
+    path_len: usize,
+) -> core::result::Result<alloc::string::String, Error> {
+    if path_len > crate::fs::vfs::MAX_PATH {
+        return Err(fd_error(crate::fs::vfs::Error::InvalidPath.into()));
+    }
+
+    let mut path_bytes = alloc::vec![0; path_len];
+    copy_from_user(&mut path_bytes, path_ptr_arg).map_err(user_error)?;
+
+    alloc::string::String::from_utf8(path_bytes).map_err(|error| Error::from(error.utf8_error()))
+}
+
+/// Opens the file at the path `[path_ptr_arg, path_ptr_arg + path_len)`, writing its descriptor to `fd_ptr_arg`.
+fn process_fs_open(path_ptr_arg: usize, path_len: usize, fd_ptr_arg: usize) -> Result {
+    let path = read_user_path(path_ptr_arg, path_len)?;
+
+    // The pointer is validated up front, so a file is never opened without its descriptor being returned.
+    write_user(fd_ptr_arg, &usize::MAX).map_err(user_error)?;
+
+    let fd = crate::fs::fd::open(&path).map_err(fd_error)?;
+    write_user(fd_ptr_arg, &fd).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Reads up to `buf_len` bytes (at most [`MAX_FS_READ`]) from the file `fd` into `buf_ptr_arg`, writing the number
+/// of bytes read to `count_ptr_arg`.
+fn process_fs_read(fd: usize, buf_ptr_arg: usize, buf_len: usize, count_ptr_arg: usize) -> Result {
+    // The pointer is validated up front, so the file isn't read from without the count being returned.
+    write_user(count_ptr_arg, &0usize).map_err(user_error)?;
+
+    let mut buf = alloc::vec![0; buf_len.min(MAX_FS_READ)];
+    let count = crate::fs::fd::read(fd, &mut buf).map_err(fd_error)?;
+    copy_to_user(buf_ptr_arg, &buf[..count]).map_err(user_error)?;
+    write_user(count_ptr_arg, &count).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+fn process_fs_close(fd: usize) -> Result {
+    crate::fs::fd::close(fd).map_err(fd_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Writes the [`Stat`] of the node at the path `[path_ptr_arg, path_ptr_arg + path_len)` to `stat_ptr_arg`.
+///
+/// [`Stat`]: crate::fs::vfs::Stat
+fn process_fs_stat(path_ptr_arg: usize, path_len: usize, stat_ptr_arg: usize) -> Result {
+    let path = read_user_path(path_ptr_arg, path_len)?;
+    let stat = crate::fs::vfs::stat(&path)
+        .map_err(crate::fs::fd::Error::from)
+        .map_err(fd_error)?;
+    write_user(stat_ptr_arg, &stat).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Restricts the active task to the hardware threads in the [`Affinity`] at `affinity_ptr_arg`, migrating it
+/// straight away if the current hardware thread isn't one of them.
+///
+/// [`Affinity`]: crate::task::Affinity
+fn process_task_set_affinity(
+    affinity_ptr_arg: usize,
+    state: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result {
+    let affinity = read_user::<crate::task::Affinity>(affinity_ptr_arg).map_err(user_error)?;
+
+    if !affinity.is_runnable() {
+        debug!("Task affinity names no running hardware thread: {affinity:X?}");
+        return Err(invalid_argument());
+    }
+
+    LocalState::with_scheduler(|scheduler| {
+        let task = scheduler.task_mut().ok_or(Error::NoActiveTask)?;
+        task.set_affinity(affinity);
+
+        if !affinity.allows(crate::cpu::get_id()) {
+            write_result(regs, Ok(Success::Ok));
+            scheduler.yield_task(state, regs);
+        }
+
+        Ok(Success::Ok)
+    })
+}
+
+/// Sets whether the active task's system calls are traced (see [`trace`]).
+///
+/// # Remarks
+///
+/// Threads the task creates while it's traced are traced too.
+fn process_task_trace(enable: bool) -> Result {
+    LocalState::with_scheduler(|scheduler| {
+        scheduler
+            .task_mut()
+            .ok_or(Error::NoActiveTask)?
+            .set_traced(enable);
+
+        Ok(Success::Ok)
+    })
+}
+
+/// Writes the active task's CPU time, up to now, into the userspace [`Rusage`] at `usage_ptr_arg`.
+fn process_getrusage(usage_ptr_arg: usize) -> Result {
+    let cpu_time =
+        LocalState::with_scheduler(crate::task::Scheduler::cpu_time).ok_or(Error::NoActiveTask)?;
+
+    write_user(
+        usage_ptr_arg,
+        &Rusage {
+            user_time_ns: u64::try_from(cpu_time.user.as_nanos()).unwrap_or(u64::MAX),
+            kernel_time_ns: u64::try_from(cpu_time.kernel.as_nanos()).unwrap_or(u64::MAX),
+        },
+    )
+    .map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Fills the userspace buffer `[buf_ptr_arg, buf_ptr_arg + buf_len)` with random bytes.
+///
+/// # Remarks
+///
+/// - Unless [`GetRandomFlags::INSECURE`] is provided, this will wait for the entropy pool
+///   to be seeded before producing any output. With it, output is produced regardless (see
+///   [`crate::rand::fill_insecure`]).
+/// - With [`GetRandomFlags::NONBLOCK`], this fails rather than waiting if the pool hasn't been seeded.
+/// - Unknown flag bits are ignored.
+fn process_getrandom(buf_ptr_arg: usize, buf_len: usize, flags: GetRandomFlags) -> Result {
+    let is_insecure = flags.contains(GetRandomFlags::INSECURE);
+    if !is_insecure {
+        if flags.contains(GetRandomFlags::NONBLOCK) && !crate::rand::is_seeded() {
+            // TODO `libsys` has no would-block error, so the invalid argument error is used.
+            return Err(invalid_argument());
+        }
+
+        crate::rand::wait_until_seeded();
+    }
+
+    let mut chunk = [0u8; 256];
+    for chunk_offset in (0..buf_len).step_by(chunk.len()) {
+        let chunk_len = usize::min(buf_len - chunk_offset, chunk.len());
+        let chunk = &mut chunk[..chunk_len];
+
+        if is_insecure {
+            crate::rand::fill_insecure(chunk);
+        } else {
+            crate::rand::fill(chunk);
+        }
+
+        copy_to_user(buf_ptr_arg + chunk_offset, chunk).map_err(user_error)?;
+    }
+
+    Ok(Success::Ok)
+}
+
+/// Most bytes a single [`Syscall::GetEntropy`] call may request.
+pub const MAX_ENTROPY_LEN: usize = 256;
+
+/// Fills the userspace buffer `[buf_ptr_arg, buf_ptr_arg + buf_len)` (at most [`MAX_ENTROPY_LEN`] bytes) with random
+/// bytes, waiting for the entropy pool to be seeded.
+fn process_getentropy(buf_ptr_arg: usize, buf_len: usize) -> Result {
+    if buf_len > MAX_ENTROPY_LEN {
+        return Err(invalid_argument());
+    }
+
+    process_getrandom(buf_ptr_arg, buf_len, GetRandomFlags::empty())
+}
+
+/// Writes the current time of the clock `clock_id` into the userspace [`Timespec`] at `timespec_ptr_arg`.
+fn process_clock_gettime(clock_id: ClockId, timespec_ptr_arg: usize) -> Result {
+    let time_ns = match clock_id {
+        ClockId::Realtime => crate::time::realtime_ns(),
+        ClockId::Monotonic => crate::time::now_ns(),
+    };
+
+    write_user(
+        timespec_ptr_arg,
+        &Timespec {
+            seconds: time_ns / 1_000_000_000,
+            nanoseconds: time_ns % 1_000_000_000,
+        },
+    )
+    .map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Resolves each of the `count` userspace [`SymbolRecord`]s at `records_ptr_arg` against the symbols
+/// of the active task's binary.
+fn process_symbolize(records_ptr_arg: usize, count: usize) -> Result {
+    if count > MAX_SYMBOLIZE_RECORDS {
+        return Err(invalid_argument());
+    }
+
+    let mut records = SymbolRecord::new_vec_zeroed(count).map_err(|_| Error::UnmappedMemory)?;
+    copy_from_user(records.as_mut_bytes(), records_ptr_arg).map_err(user_error)?;
+
+    let (symbols, load_offset) = LocalState::with_scheduler(|scheduler| {
+        let task = scheduler.task_mut().ok_or(Error::NoActiveTask)?;
+
+        // Kernel threads don't make system calls, so the calling task always has a process.
+        let process = task.process().ok_or(Error::NoActiveTask)?;
+
+        Ok(process
+            .with_image(|image| (crate::task::symbols::for_image(image), image.load_offset())))
+    })?;
+
+    for record in &mut records {
+        let resolved = record
+            .address
+            .checked_sub(load_offset)
+            .and_then(|address| u64::try_from(address).ok())
+            .and_then(|address| symbols.resolve(address));
+
+        record.name = [0; SYMBOL_NAME_LEN];
+        if let Some((name, offset)) = resolved {
+            let name_len = name.len().min(SYMBOL_NAME_LEN - 1);
+            record.name[..name_len].copy_from_slice(&name.as_bytes()[..name_len]);
+            record.offset = usize::try_from(offset).unwrap_or(usize::MAX);
+        } else {
+            record.offset = usize::MAX;
+        }
+    }
+
+    copy_to_user(records_ptr_arg, records.as_bytes()).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+fn timer_error(error: crate::task::timers::Error) -> Error {
+    use crate::task::timers::Error as TimerError;
+
+    match error {
+        TimerError::NoActiveTask => Error::NoActiveTask,
+        TimerError::Handles(error) => handle_error(error),
+
+        error => {
+            debug!("Timer system call failed: {error}");
+            invalid_argument()
+        }
+    }
+}
+
+/// Creates a timer on the clock `clock_id` for the active task, writing its handle to `handle_ptr_arg`.
+fn process_timer_create(clock_id: ClockId, handle_ptr_arg: usize) -> Result {
+    // The handle is checked to be writable first, so a timer is never created without its handle being returned.
+    write_user(handle_ptr_arg, &crate::task::handles::NO_HANDLE).map_err(user_error)?;
+
+    let handle = crate::task::timers::create(clock_id).map_err(timer_error)?;
+    write_user(handle_ptr_arg, &handle).map_err(user_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Arms the timer of `handle` (see [`crate::task::timers::arm`]).
+fn process_timer_arm(
+    handle: Handle,
+    initial: Duration,
+    interval: Duration,
+    flags: ArmFlags,
+) -> Result {
+    crate::task::timers::arm(handle, initial, interval, flags).map_err(timer_error)?;
+
+    Ok(Success::Ok)
+}
+
+/// Writes the active task's next timer [`Expiration`](crate::task::timers::Expiration) to `expiration_ptr_arg`,
+/// blocking the task until one is available.
+fn process_timer_wait(
+    expiration_ptr_arg: usize,
+    state: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result {
+    use crate::task::timers::Expiration;
+
+    // The pointer is validated up front, so an expiration is never consumed without being delivered.
+    write_user(
+        expiration_ptr_arg,
+        &Expiration {
+            handle: crate::task::handles::NO_HANDLE,
+            count: 0,
+        },
+    )
+    .map_err(user_error)?;
+
+    if let Some(expiration) = crate::task::timers::wait(state, regs).map_err(timer_error)? {
+        write_user(expiration_ptr_arg, &expiration).map_err(user_error)?;
+    }
+
+    Ok(Success::Ok)
+}
@@ -0,0 +1,246 @@
+//! Decoding of system call arguments, and the [`syscalls!`] macro which the system call table is declared with.
+//!
+//! Each argument is declared with the type it's decoded as (see [`FromArg`]), which rejects values that aren't valid
+//! for it before the handler is called; the handler takes the decoded value, or anything it converts [`Into`] (i.e.
+//! [`UserPtr`] is passed as a `usize`).
+
+use crate::{
+    interrupts::syscall::{ClockId, GetRandomFlags},
+    task::{DEFAULT_USERSPACE_SIZE, MmapPermissions},
+};
+use libsys::syscall::Error;
+
+/// Decoding of a raw argument register into a system call argument.
+pub trait FromArg: Sized {
+    /// Decodes `arg`, or returns the error the call fails with if it isn't a valid `Self`.
+    fn from_arg(arg: usize) -> Result<Self, Error>;
+}
+
+/// Decodes the argument `arg_name` of `syscall_name`, logging why it was rejected if it isn't valid.
+#[doc(hidden)]
+pub fn decode<T: FromArg>(arg: usize, syscall_name: &str, arg_name: &str) -> Result<T, Error> {
+    T::from_arg(arg).inspect_err(|_| {
+        debug!("Rejected `{arg_name}` argument of {syscall_name} system call: {arg:#X}");
+    })
+}
+
+impl FromArg for usize {
+    fn from_arg(arg: usize) -> Result<Self, Error> {
+        Ok(arg)
+    }
+}
+
+impl FromArg for u64 {
+    fn from_arg(arg: usize) -> Result<Self, Error> {
+        Ok(u64::try_from(arg).unwrap())
+    }
+}
+
+impl FromArg for bool {
+    fn from_arg(arg: usize) -> Result<Self, Error> {
+        Ok(arg != 0)
+    }
+}
+
+/// Durations are passed in nanoseconds, saturating at the longest representable.
+impl FromArg for core::time::Duration {
+    fn from_arg(arg: usize) -> Result<Self, Error> {
+        Ok(Self::from_nanos(u64::try_from(arg).unwrap_or(u64::MAX)))
+    }
+}
+
+/// Address of userspace memory, which is rejected if it lies in the kernel's half of the address space.
+#[derive(Debug, Clone, Copy)]
+pub struct UserPtr(usize);
+
+impl FromArg for UserPtr {
+    fn from_arg(arg: usize) -> Result<Self, Error> {
+        if arg < DEFAULT_USERSPACE_SIZE.get() {
+            Ok(Self(arg))
+        } else {
+            Err(Error::UnmappedMemory)
+        }
+    }
+}
+
+impl From<UserPtr> for usize {
+    fn from(ptr: UserPtr) -> Self {
+        ptr.0
+    }
+}
+
+/// Length of userspace memory, which is rejected if it's longer than userspace is, so it can't overflow an address
+/// it's added to.
+#[derive(Debug, Clone, Copy)]
+pub struct UserLen(usize);
+
+impl FromArg for UserLen {
+    fn from_arg(arg: usize) -> Result<Self, Error> {
+        if arg <= DEFAULT_USERSPACE_SIZE.get() {
+            Ok(Self(arg))
+        } else {
+            Err(Error::UnmappedMemory)
+        }
+    }
+}
+
+impl From<UserLen> for usize {
+    fn from(len: UserLen) -> Self {
+        len.0
+    }
+}
+
+/// Decodes enumerations which are passed as their discriminant.
+macro_rules! from_arg_discriminant {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl FromArg for $ty {
+                fn from_arg(arg: usize) -> Result<Self, Error> {
+                    Self::try_from(arg).map_err(|_| super::invalid_argument())
+                }
+            }
+        )*
+    };
+}
+
+from_arg_discriminant!(ClockId, MmapPermissions, crate::task::hardware::Source);
+
+/// Decodes flags, ignoring unknown bits.
+macro_rules! from_arg_flags_truncate {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl FromArg for $ty {
+                fn from_arg(arg: usize) -> Result<Self, Error> {
+                    Ok(Self::from_bits_truncate(arg))
+                }
+            }
+        )*
+    };
+}
+
+from_arg_flags_truncate!(
+    GetRandomFlags,
+    crate::task::signals::Events,
+    crate::task::timers::ArmFlags,
+);
+
+/// Rights are never granted by accident, so unknown bits are rejected rather than ignored.
+impl FromArg for crate::task::handles::Rights {
+    fn from_arg(arg: usize) -> Result<Self, Error> {
+        Self::from_bits(arg).ok_or_else(super::invalid_argument)
+    }
+}
+
+/// Declares the system call table: each call's stable number, name, arguments (and the types they're decoded as),
+/// and handler.
+///
+/// ```ignore
+/// crate::syscalls! {
+///     /// Sleeps for `duration`.
+///     TaskSleep(duration: Duration) = 0x1002 => process_task_sleep [context];
+/// }
+/// ```
+///
+/// This generates the `Syscall` enumeration (numbered as declared, so a number reused by mistake fails to compile),
+/// its `name` and `arg_names`, and `dispatch`, which decodes each argument in turn before calling the handler with
+/// them. Handlers marked `[context]` are also passed the caller's interrupt stack frame and registers, as they may
+/// switch it out (i.e. by blocking it), or redirect it; `dispatch` reports whether the caller is still the active
+/// task afterwards, as its result mustn't be written to the registers of whichever task was switched in.
+#[macro_export]
+macro_rules! syscalls {
+    (@call $handler:ident($($arg:ident),*) [context] $state:ident, $regs:ident) => {{
+        let caller_id = $crate::interrupts::syscall::active_task_id();
+        let result = $handler($($arg.into(),)* $state, $regs);
+
+        (result, $crate::interrupts::syscall::active_task_id() == caller_id)
+    }};
+    (@call $handler:ident($($arg:ident),*) $state:ident, $regs:ident) => {
+        ($handler($($arg.into()),*), true)
+    };
+
+    ($(
+        $(#[$meta:meta])*
+        $name:ident($($arg:ident: $ty:ty),* $(,)?) = $number:expr => $handler:ident $([$context:ident])?;
+    )*) => {
+        /// System calls, by their stable number.
+        ///
+        /// # Remarks
+        ///
+        /// A call's number never changes, and numbers missing from the table are reserved, so they're never reused.
+        #[repr(usize)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+        pub enum Syscall {
+            $(
+                $(#[$meta])*
+                $name = $number,
+            )*
+        }
+
+        impl Syscall {
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $(Self::$name => stringify!($name),)*
+                }
+            }
+
+            /// Names of the arguments the call takes, in order.
+            pub const fn arg_names(self) -> &'static [&'static str] {
+                match self {
+                    $(Self::$name => &[$(stringify!($arg)),*],)*
+                }
+            }
+        }
+
+        /// Decodes the arguments of `syscall` from `args`, and calls its handler with them, returning its result and
+        /// whether the caller is still the active task (so the result is its to return).
+        #[allow(clippy::useless_conversion)]
+        fn dispatch(
+            syscall: Syscall,
+            args: [usize; 6],
+            state: &mut InterruptStackFrame,
+            regs: &mut Registers,
+        ) -> (Result, bool) {
+            match syscall {
+                $(
+                    Syscall::$name => {
+                        #[allow(unused_variables, unused_mut)]
+                        let mut args = args.into_iter();
+                        $(
+                            let $arg = match $crate::interrupts::syscall::table::decode::<$ty>(
+                                args.next().unwrap(),
+                                stringify!($name),
+                                stringify!($arg),
+                            ) {
+                                Ok($arg) => $arg,
+                                Err(error) => return (Err(error), true),
+                            };
+                        )*
+
+                        $crate::syscalls!(@call $handler($($arg),*) $([$context])? state, regs)
+                    }
+                )*
+            }
+        }
+    };
+}
+
+crate::kernel_test! {
+    /// Arguments are rejected if they're invalid for the type they're decoded as.
+    fn decodes_syscall_arguments() -> crate::tests::Outcome {
+        let kernel_address = DEFAULT_USERSPACE_SIZE.get();
+        if UserPtr::from_arg(0x1000).is_err() || UserPtr::from_arg(kernel_address).is_ok() {
+            return Err("user pointer was decoded by whether it's in the kernel's half");
+        }
+        if UserLen::from_arg(kernel_address).is_err() || UserLen::from_arg(kernel_address + 1).is_ok() {
+            return Err("user length was decoded by whether it could overflow an address");
+        }
+        if crate::task::handles::Rights::from_arg(usize::MAX).is_ok() {
+            return Err("rights with unknown bits were accepted");
+        }
+        if !matches!(ClockId::from_arg(1), Ok(ClockId::Monotonic)) || ClockId::from_arg(2).is_ok() {
+            return Err("clock ID wasn't decoded from its discriminant");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+//! System call tracing, for diagnosing userspace tasks (i.e. drivers) without a debugger.
+//!
+//! Each system call made by a traced task (see [`Task::set_traced`]) is recorded in the log ring (see
+//! [`crate::logging::ring`]) as it's entered, with its decoded arguments, and as it returns, with its result. Records
+//! are only appended to the ring, so they're read back with `dmesg` (or [`Syscall::KlogRead`]), rather than
+//! slowing the traced task down by writing to every log sink.
+//!
+//! Tracing is rate limited: beyond [`RATE_LIMIT`] calls in a [`RATE_WINDOW_NS`] window, calls go unrecorded, and the
+//! number which did is recorded once the next window opens.
+//!
+//! [`Task::set_traced`]: crate::task::Task::set_traced
+//! [`Syscall::KlogRead`]: super::Syscall::KlogRead
+
+use super::Syscall;
+use crate::{cpu::local_state::LocalState, task::Task};
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use libsys::syscall::Result;
+use uuid::Uuid;
+
+crate::parameters! {
+    /// Whether the init task (and every thread it creates) has its system calls traced from launch.
+    "strace": bool = false,
+}
+
+/// Most system calls recorded in each window.
+const RATE_LIMIT: u32 = 128;
+const RATE_WINDOW_NS: u64 = 1_000_000_000;
+
+/// Target the records are logged with.
+const TARGET: &str = "strace";
+
+/// Whether any task has been traced, so calls from untraced tasks needn't look at the scheduler until one has.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static LIMITER: RateLimiter = RateLimiter::new();
+
+struct RateLimiter {
+    window_start_ns: AtomicU64,
+    admitted: AtomicU32,
+    /// Calls which have gone unrecorded since they were last reported.
+    dropped: AtomicU64,
+}
+
+impl RateLimiter {
+    const fn new() -> Self {
+        Self {
+            window_start_ns: AtomicU64::new(0),
+            admitted: AtomicU32::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Admits a call at `now_ns` if its window has room for it, returning the number of calls dropped since the last
+    /// was admitted (so they can be reported), or `None` if it's dropped itself.
+    fn admit(&self, now_ns: u64) -> Option<u64> {
+        let window_start_ns = self.window_start_ns.load(Ordering::Relaxed);
+        if now_ns.saturating_sub(window_start_ns) >= RATE_WINDOW_NS
+            && self
+                .window_start_ns
+                .compare_exchange(
+                    window_start_ns,
+                    now_ns,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                )
+                .is_ok()
+        {
+            self.admitted.store(0, Ordering::Relaxed);
+        }
+
+        if self.admitted.fetch_add(1, Ordering::Relaxed) < RATE_LIMIT {
+            Some(self.dropped.swap(0, Ordering::Relaxed))
+        } else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+
+            None
+        }
+    }
+}
+
+/// Marks that a task is traced, so calls are checked against their task's flag from now on.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether tasks launched by the kernel (i.e. init) should be traced from launch.
+pub fn is_traced_from_launch() -> bool {
+    crate::params::get::<bool>("strace")
+}
+
+fn append(args: core::fmt::Arguments) {
+    crate::logging::ring::append(
+        &log::Record::builder()
+            .level(log::Level::Info)
+            .target(TARGET)
+            .args(args)
+            .build(),
+    );
+}
+
+/// Name of `vector`, and of the arguments it takes.
+fn describe(vector: usize) -> Option<(&'static str, &'static [&'static str])> {
+    let syscall = Syscall::try_from(vector).ok()?;
+
+    Some((syscall.name(), syscall.arg_names()))
+}
+
+/// Writes `vector` and the arguments it takes, i.e. `FsRead(fd=0x3, buf=0x7F00, len=0x200, count_ptr=0x7E00)`.
+struct Call<'a> {
+    vector: usize,
+    args: &'a [usize; 6],
+}
+
+impl core::fmt::Display for Call<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let Some((name, arg_names)) = describe(self.vector) else {
+            // Unknown vectors are traced with every argument, as it isn't known which are used.
+            return write!(f, "{:#X}{:X?}", self.vector, self.args);
+        };
+
+        write!(f, "{name}(")?;
+        for (index, (arg_name, arg)) in arg_names.iter().zip(self.args).enumerate() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+
+            write!(f, "{arg_name}={arg:#X}")?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Records the entry of a system call, if the active task is traced, returning its ID so the call's result can be
+/// recorded alongside it.
+pub fn enter(vector: usize, args: &[usize; 6]) -> Option<Uuid> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    let id = LocalState::with_scheduler(|scheduler| {
+        scheduler
+            .process()
+            .filter(|task| task.is_traced())
+            .map(Task::id)
+    })?;
+
+    let dropped = LIMITER.admit(crate::time::now_ns())?;
+    if dropped > 0 {
+        append(format_args!(
+            "[{dropped} traced system calls were not recorded]"
+        ));
+    }
+
+    append(format_args!("{id} {}", Call { vector, args }));
+
+    Some(id)
+}
+
+/// Records the result of a system call entered by the traced task `id`.
+///
+/// # Remarks
+///
+/// The call may have switched `id` out (i.e. by blocking), so it's recorded with the ID taken on entry.
+pub fn exit(id: Uuid, vector: usize, result: &Result) {
+    let name = describe(vector).map_or("?", |(name, _)| name);
+
+    append(format_args!("{id} {name} -> {result:X?}"));
+}
+
+crate::kernel_test! {
+    /// Calls beyond the rate limit are dropped, and reported once the next window opens.
+    fn rate_limits_traced_calls() -> crate::tests::Outcome {
+        let limiter = RateLimiter::new();
+        let start_ns = RATE_WINDOW_NS;
+
+        if (0..RATE_LIMIT).any(|_| limiter.admit(start_ns) != Some(0)) {
+            return Err("call within the rate limit was dropped");
+        }
+        if limiter.admit(start_ns + 1).is_some() || limiter.admit(start_ns + 2).is_some() {
+            return Err("call beyond the rate limit was admitted");
+        }
+        if limiter.admit(start_ns + RATE_WINDOW_NS) != Some(2) {
+            return Err("dropped calls weren't reported once the next window opened");
+        }
+
+        Ok(())
+    }
+}
@@ -1,10 +1,10 @@
-use crate::interrupts::InterruptCell;
+use crate::{interrupts::InterruptCell, sync::SpinMutex};
 use core::fmt::Write;
 use ioports::WriteOnlyPort;
-use spin::{Mutex, Once};
+use spin::Once;
 
 /// A debug output utilizing QEMU's port 0xE9 hack.
-pub struct Logger(InterruptCell<Mutex<Writer>>);
+pub struct Logger(InterruptCell<SpinMutex<Writer>>);
 
 impl Logger {
     /// Initialized the QEMU 0xE9-hack debug logger.
@@ -14,7 +14,7 @@ impl Logger {
         static DEBUG_LOGGER: Once<Logger> = Once::new();
 
         DEBUG_LOGGER.call_once(|| {
-            Self(InterruptCell::new(Mutex::new(Writer({
+            Self(InterruptCell::new(SpinMutex::new(Writer({
                 // Safety: It's assumed that this port exists if the kernel was compiled and run in debug mode.
                 unsafe { WriteOnlyPort::new(0xE9) }
             }))))
@@ -22,6 +22,17 @@ impl Logger {
     }
 }
 
+impl Logger {
+    /// Writes a formatted log line.
+    pub fn write(&self, _: log::Level, args: core::fmt::Arguments) {
+        self.0.with(|writer| {
+            let mut writer = writer.lock();
+
+            writer.write_fmt(args).ok();
+        });
+    }
+}
+
 impl log::Log for Logger {
     fn enabled(&self, _: &log::Metadata) -> bool {
         true
@@ -29,13 +40,7 @@ impl log::Log for Logger {
 
     fn log(&self, record: &log::Record) {
         if self.enabled(record.metadata()) {
-            super::with_formatted_log_record(record, |args| {
-                self.0.with(|writer| {
-                    let mut writer = writer.lock();
-
-                    writer.write_fmt(args).ok();
-                });
-            });
+            super::with_formatted_log_record(record, |args| self.write(record.level(), args));
         }
     }
 
@@ -0,0 +1,114 @@
+//! Per-target log level filtering, i.e. `trace,mem=debug,local_apic=warn`.
+//!
+//! A filter is a comma-separated list of directives. A bare level sets the default level; `path=level` sets the level
+//! of any target containing `path` as a contiguous run of module path segments (so `local_apic` matches
+//! `kernel::arch::x86_64::devices::local_apic`). The directive with the most segments wins, and among equals, the last.
+
+use log::LevelFilter;
+
+/// Most per-target directives a filter may hold.
+pub const MAX_DIRECTIVES: usize = 16;
+
+/// Longest target path a directive may hold.
+pub const MAX_PATH_LEN: usize = 64;
+
+#[derive(Debug, Error)]
+pub enum Error<'a> {
+    #[error("invalid log level: {0:?}")]
+    InvalidLevel(&'a str),
+
+    #[error("log directive has an empty target path")]
+    EmptyPath,
+
+    #[error("log directive target path is too long (maximum is {MAX_PATH_LEN} bytes): {0:?}")]
+    PathTooLong(&'a str),
+
+    #[error("too many log directives (maximum is {MAX_DIRECTIVES})")]
+    TooManyDirectives,
+}
+
+/// Level of the targets matching `path`, which is copied out of the filter's spec, as the spec may not outlive it
+/// (i.e. the command line is freed along with the rest of bootloader memory).
+#[derive(Debug, Clone)]
+struct Directive {
+    path: heapless::String<MAX_PATH_LEN>,
+    level: LevelFilter,
+}
+
+impl Directive {
+    fn segments(&self) -> usize {
+        self.path.split("::").count()
+    }
+
+    fn matches(&self, target: &str) -> bool {
+        let path = self.path.as_str();
+
+        target.match_indices(path).any(|(index, _)| {
+            let (before, after) = (&target[..index], &target[index + path.len()..]);
+
+            (before.is_empty() || before.ends_with("::"))
+                && (after.is_empty() || after.starts_with("::"))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Filter {
+    default: LevelFilter,
+    directives: heapless::Vec<Directive, MAX_DIRECTIVES>,
+}
+
+impl Filter {
+    pub fn parse(spec: &str) -> Result<Self, Error<'_>> {
+        let mut filter = Self {
+            default: LevelFilter::Trace,
+            directives: heapless::Vec::new(),
+        };
+
+        for directive in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|directive| !directive.is_empty())
+        {
+            match directive.split_once('=') {
+                Some(("", _)) => return Err(Error::EmptyPath),
+
+                Some((path, level)) => {
+                    let level = level.parse().map_err(|_| Error::InvalidLevel(level))?;
+                    let path =
+                        heapless::String::try_from(path).map_err(|()| Error::PathTooLong(path))?;
+
+                    filter
+                        .directives
+                        .push(Directive { path, level })
+                        .map_err(|_| Error::TooManyDirectives)?;
+                }
+
+                None => {
+                    filter.default = directive
+                        .parse()
+                        .map_err(|_| Error::InvalidLevel(directive))?;
+                }
+            }
+        }
+
+        Ok(filter)
+    }
+
+    /// Most verbose level enabled for `target`.
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .filter(|directive| directive.matches(target))
+            .max_by_key(|directive| directive.segments())
+            .map_or(self.default, |directive| directive.level)
+    }
+
+    /// Most verbose level enabled for any target.
+    pub fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .map(|directive| directive.level)
+            .fold(self.default, LevelFilter::max)
+    }
+}
@@ -0,0 +1,261 @@
+use super::font::{GLYPH_HEIGHT, GLYPH_WIDTH, glyph};
+use crate::{interrupts::InterruptCell, sync::SpinMutex};
+use core::{fmt::Write, ptr::NonNull};
+use limine::{framebuffer::MemoryModel, request::FramebufferRequest};
+use spin::Once;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("bootloader did not provide a framebuffer")]
+    NoFramebuffer,
+
+    #[error("unsupported framebuffer format: {bpp} bits per pixel, memory model {memory_model:?}")]
+    UnsupportedFormat { bpp: u16, memory_model: MemoryModel },
+
+    #[error("framebuffer is too small to fit any text")]
+    TooSmall,
+}
+
+/// Framebuffer width (in pixels) from which glyphs are drawn at double size.
+const SCALE_UP_WIDTH: usize = 1600;
+
+const TAB_WIDTH: usize = 4;
+
+pub struct Logger(InterruptCell<SpinMutex<Console>>);
+
+impl Logger {
+    /// Initializes the framebuffer logger on the first framebuffer provided by the bootloader.
+    ///
+    /// # Remarks
+    ///
+    /// The framebuffer's memory is expected to remain mapped (at the same address) in the kernel's page tables.
+    pub fn init(framebuffer_request: &FramebufferRequest) -> Result<&'static Self, Error> {
+        static FRAMEBUFFER_LOGGER: Once<Logger> = Once::new();
+
+        FRAMEBUFFER_LOGGER.try_call_once(|| {
+            let framebuffer = framebuffer_request
+                .get_response()
+                .and_then(|response| response.framebuffers().next())
+                .ok_or(Error::NoFramebuffer)?;
+
+            let bpp = framebuffer.bpp();
+            let memory_model = framebuffer.memory_model();
+            if memory_model != MemoryModel::RGB || !matches!(bpp, 16 | 24 | 32) {
+                return Err(Error::UnsupportedFormat { bpp, memory_model });
+            }
+
+            let width = usize::try_from(framebuffer.width()).unwrap();
+            let height = usize::try_from(framebuffer.height()).unwrap();
+            let scale = if width >= SCALE_UP_WIDTH { 2 } else { 1 };
+
+            let mut console = Console {
+                buffer: NonNull::new(framebuffer.addr()).ok_or(Error::NoFramebuffer)?,
+                pitch: usize::try_from(framebuffer.pitch()).unwrap(),
+                bytes_per_pixel: usize::from(bpp / 8),
+                masks: [
+                    (framebuffer.red_mask_size(), framebuffer.red_mask_shift()),
+                    (
+                        framebuffer.green_mask_size(),
+                        framebuffer.green_mask_shift(),
+                    ),
+                    (framebuffer.blue_mask_size(), framebuffer.blue_mask_shift()),
+                ],
+                scale,
+                columns: width / (GLYPH_WIDTH * scale),
+                rows: height / (GLYPH_HEIGHT * scale),
+                column: 0,
+                row: 0,
+                foreground: 0,
+            };
+
+            if console.columns == 0 || console.rows == 0 {
+                return Err(Error::TooSmall);
+            }
+
+            console.clear();
+
+            Ok(Self(InterruptCell::new(SpinMutex::new(console))))
+        })
+    }
+
+    /// Writes a formatted log line, in the color of `level`.
+    pub fn write(&self, level: log::Level, args: core::fmt::Arguments) {
+        self.0.with(|console| {
+            let mut console = console.lock();
+
+            console.foreground = console.encode(level_color(level));
+            console.write_fmt(args).ok();
+        });
+    }
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Info
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            super::with_formatted_log_record(record, |args| self.write(record.level(), args));
+        }
+    }
+
+    /// Records are drawn to the framebuffer as they're logged, so there's nothing to flush.
+    fn flush(&self) {}
+}
+
+const fn level_color(level: log::Level) -> [u8; 3] {
+    match level {
+        log::Level::Error => [0xFF, 0x55, 0x55],
+        log::Level::Warn => [0xFF, 0xFF, 0x55],
+        log::Level::Info => [0xFF, 0xFF, 0xFF],
+        log::Level::Debug => [0xAA, 0xAA, 0xAA],
+        log::Level::Trace => [0x77, 0x77, 0x77],
+    }
+}
+
+/// Text console rendered directly into the framebuffer.
+struct Console {
+    buffer: NonNull<u8>,
+    pitch: usize,
+    bytes_per_pixel: usize,
+
+    /// Size and shift of the red, green, and blue channels within a pixel.
+    masks: [(u8, u8); 3],
+
+    /// Size (in pixels) of each glyph pixel.
+    scale: usize,
+    columns: usize,
+    rows: usize,
+
+    column: usize,
+    row: usize,
+
+    /// Pixel value glyphs are drawn in.
+    foreground: u32,
+}
+
+// Safety: The framebuffer is only accessed through the logger's lock.
+unsafe impl Send for Console {}
+
+impl Console {
+    /// Encodes `rgb` as a pixel value in the framebuffer's format.
+    fn encode(&self, rgb: [u8; 3]) -> u32 {
+        rgb.into_iter()
+            .zip(self.masks)
+            .fold(0, |pixel, (channel, (size, shift))| {
+                let channel = u32::from(channel) >> 8u32.saturating_sub(u32::from(size));
+
+                pixel | (channel << shift)
+            })
+    }
+
+    /// Height of a line of text, in bytes of framebuffer.
+    fn line_len(&self) -> usize {
+        self.pitch * GLYPH_HEIGHT * self.scale
+    }
+
+    fn clear(&mut self) {
+        // Safety: The framebuffer spans `pitch` bytes for every row of pixels.
+        unsafe {
+            self.buffer
+                .as_ptr()
+                .write_bytes(0, self.line_len() * self.rows);
+        }
+
+        self.column = 0;
+        self.row = 0;
+    }
+
+    fn put_pixel(&mut self, x: usize, y: usize, pixel: u32) {
+        let offset = (y * self.pitch) + (x * self.bytes_per_pixel);
+
+        // Safety: Callers only draw within the console's rows & columns, which lie within the framebuffer.
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                pixel.to_le_bytes().as_ptr(),
+                self.buffer.as_ptr().add(offset),
+                self.bytes_per_pixel,
+            );
+        }
+    }
+
+    fn draw_glyph(&mut self, c: char) {
+        let origin_x = self.column * GLYPH_WIDTH * self.scale;
+        let origin_y = self.row * GLYPH_HEIGHT * self.scale;
+
+        for (glyph_y, glyph_row) in glyph(c).iter().enumerate() {
+            for glyph_x in 0..GLYPH_WIDTH {
+                // Background is black, which is zero in every RGB format.
+                let pixel = if ((glyph_row >> glyph_x) & 1) == 1 {
+                    self.foreground
+                } else {
+                    0
+                };
+
+                for y in 0..self.scale {
+                    for x in 0..self.scale {
+                        self.put_pixel(
+                            origin_x + (glyph_x * self.scale) + x,
+                            origin_y + (glyph_y * self.scale) + y,
+                            pixel,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn new_line(&mut self) {
+        self.column = 0;
+
+        if (self.row + 1) < self.rows {
+            self.row += 1;
+        } else {
+            self.scroll();
+        }
+    }
+
+    /// Moves every line up by one, clearing the last.
+    fn scroll(&mut self) {
+        let line_len = self.line_len();
+        let text_len = line_len * self.rows;
+
+        // Safety: Both ranges lie within the text area of the framebuffer.
+        unsafe {
+            let buffer = self.buffer.as_ptr();
+
+            core::ptr::copy(buffer.add(line_len), buffer, text_len - line_len);
+            buffer.add(text_len - line_len).write_bytes(0, line_len);
+        }
+    }
+}
+
+impl core::fmt::Write for Console {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        s.chars().try_for_each(|c| self.write_char(c))
+    }
+
+    fn write_char(&mut self, c: char) -> core::fmt::Result {
+        match c {
+            '\n' => self.new_line(),
+            '\r' => self.column = 0,
+            '\t' => {
+                for _ in 0..(TAB_WIDTH - (self.column % TAB_WIDTH)) {
+                    self.write_char(' ')?;
+                }
+            }
+
+            c => {
+                if self.column >= self.columns {
+                    self.new_line();
+                }
+
+                self.draw_glyph(c);
+                self.column += 1;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,23 +1,40 @@
-mod serial;
+pub mod filter;
+pub mod ring;
+pub mod serial;
+pub mod staging;
 
 #[cfg(debug_assertions)]
 mod debug;
 
+mod font;
+mod framebuffer;
+
+crate::parameters! {
+    /// Per-target log level filter, i.e. `--log=trace,mem=debug,local_apic=warn`.
+    "log": str = "",
+}
+
+static LOGGER: spin::Once<Logger> = spin::Once::new();
+
 /// The kernel logger.
 pub struct Logger {
+    /// Set once the kernel command line has been parsed; until then, every record is logged.
+    filter: spin::Once<filter::Filter>,
+
     serial: Option<&'static serial::Logger>,
+    framebuffer: Option<&'static framebuffer::Logger>,
 
     #[cfg(debug_assertions)]
     debug: &'static debug::Logger,
 }
 
 impl Logger {
-    pub fn init() {
+    pub fn init(framebuffer_request: &limine::request::FramebufferRequest) {
         crate::interrupts::uninterruptable(|| {
-            static LOGGER: spin::Once<Logger> = spin::Once::new();
-
             let static_logger = LOGGER.call_once(|| Self {
+                filter: spin::Once::new(),
                 serial: serial::Logger::init().ok(),
+                framebuffer: framebuffer::Logger::init(framebuffer_request).ok(),
 
                 #[cfg(debug_assertions)]
                 debug: debug::Logger::init(),
@@ -27,30 +44,144 @@ impl Logger {
             log::set_logger(static_logger).unwrap();
         });
     }
+
+    /// Filters subsequent records by `filter`.
+    ///
+    /// # Remarks
+    ///
+    /// The filter can only be set once; subsequent calls are ignored.
+    pub fn set_filter(filter: filter::Filter) {
+        let Some(logger) = LOGGER.get() else {
+            return;
+        };
+
+        let filter = logger.filter.call_once(|| filter);
+        log::set_max_level(filter.max_level());
+    }
+}
+
+/// Filters subsequent records by the `--log` parameter, if it was given.
+pub fn apply_filter() {
+    let spec = crate::params::get::<&str>("log");
+    if spec.is_empty() {
+        return;
+    }
+
+    match filter::Filter::parse(spec) {
+        Ok(filter) => Logger::set_filter(filter),
+        Err(error) => warn!("Ignoring log filter {spec:?}: {error}"),
+    }
 }
 
 impl log::Log for Logger {
-    fn enabled(&self, _: &log::Metadata) -> bool {
-        unimplemented!()
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.filter
+            .get()
+            .is_none_or(|filter| metadata.level() <= filter.level_for(metadata.target()))
     }
 
     fn log(&self, record: &log::Record) {
-        #[cfg(debug_assertions)]
-        self.debug.log(record);
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        ring::append(record);
 
-        if let Some(serial_logger) = self.serial {
-            serial_logger.log(record);
+        // Records from interrupt context are staged, unless the kernel is panicking (they may never be flushed).
+        if crate::interrupts::in_interrupt_context() && !crate::panic::is_panicking() {
+            crate::cpu::local_state::LocalState::with_log_staging(|staging| staging.stage(record));
+            return;
         }
+
+        // Staged records are older, so they're written first.
+        self.flush();
+
+        with_formatted_log_record(record, |args| self.write(record.metadata(), args));
     }
 
+    /// Writes out the records staged on the current hardware thread.
     fn flush(&self) {
-        unimplemented!()
+        let Some((staged, dropped)) =
+            crate::cpu::local_state::LocalState::with_log_staging(staging::Staging::take)
+        else {
+            return;
+        };
+
+        for staged in &staged {
+            let metadata = log::Metadata::builder().level(staged.level).build();
+
+            self.write(&metadata, format_args!("{}", staged.line()));
+        }
+
+        if dropped > 0 {
+            let metadata = log::Metadata::builder().level(log::Level::Warn).build();
+
+            self.write(
+                &metadata,
+                format_args!(
+                    "[{dropped} records from interrupt context were dropped; see `dmesg`]\n"
+                ),
+            );
+        }
+    }
+}
+
+impl Logger {
+    /// Writes a formatted line to every sink which is enabled for `metadata`.
+    fn write(&self, metadata: &log::Metadata, args: core::fmt::Arguments) {
+        #[cfg(debug_assertions)]
+        if self.debug.enabled(metadata) {
+            self.debug.write(metadata.level(), args);
+        }
+
+        if let Some(serial_logger) = self.serial
+            && serial_logger.enabled(metadata)
+        {
+            serial_logger.write(metadata.level(), args);
+        }
+
+        if let Some(framebuffer_logger) = self.framebuffer
+            && framebuffer_logger.enabled(metadata)
+        {
+            framebuffer_logger.write(metadata.level(), args);
+        }
+    }
+}
+
+/// Admits periodic flushing of records staged on the current hardware thread.
+///
+/// # Remarks
+///
+/// Flushing runs in a kernel thread of the hardware thread's deadline class (see [`crate::task::deadline`]), so it
+/// takes the sinks' locks as any other task does, and is preempted if rendering runs past its budget.
+pub fn start_flusher() {
+    const PERIOD: core::time::Duration = core::time::Duration::from_millis(50);
+    const BUDGET: core::time::Duration = core::time::Duration::from_millis(1);
+
+    fn flush() {
+        log::logger().flush();
+    }
+
+    let admitted = crate::cpu::local_state::LocalState::with_scheduler(|scheduler| {
+        scheduler
+            .deadline_class_mut()
+            .admit("log-flush", PERIOD, BUDGET, flush)
+    });
+
+    if let Err(error) = admitted {
+        warn!(
+            "Records logged from interrupt context will only be flushed by later logging: {error}"
+        );
     }
 }
 
 fn with_formatted_log_record(record: &log::Record, func: impl FnOnce(core::fmt::Arguments)) {
+    let now_us = crate::time::now_ns() / 1000;
+
     func(format_args!(
-        "[#{hwthread_id}][{level}][{target}] {args}\n",
+        "[{secs:>5}.{micros:06}][#{hwthread_id}][{level}][{target}] {args}\n",
+        secs = now_us / 1_000_000,
+        micros = now_us % 1_000_000,
         hwthread_id = crate::cpu::get_id(),
         level = record.level(),
         target = record.target(),
@@ -0,0 +1,229 @@
+//! In-memory ring of recent log records, so they can be retrieved after the fact (i.e. `dmesg`).
+//!
+//! Every slot is made of atomics, and guarded by its own sequence number, so appending never takes a lock, and is
+//! safe from any context (including NMIs). Readers copy a slot out, and discard it if its sequence number changed
+//! while doing so.
+
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering, fence},
+};
+
+/// Number of records retained before the oldest are overwritten.
+pub const SLOTS: usize = 256;
+
+/// Maximum length (in bytes) of a record's text; longer text is truncated.
+pub const TEXT_LEN: usize = TEXT_WORDS * size_of::<u64>();
+const TEXT_WORDS: usize = 16;
+
+/// Sequence number of the next record to be appended.
+static HEAD: AtomicU64 = AtomicU64::new(0);
+
+static RING: [Slot; SLOTS] = [const { Slot::new() }; SLOTS];
+
+struct Slot {
+    /// `(sequence * 2) + 1` while the record `sequence` is being written, then `(sequence * 2) + 2` once complete.
+    state: AtomicU64,
+    timestamp_ns: AtomicU64,
+    /// Hardware thread ID, level, and text length, packed by [`pack_meta`].
+    meta: AtomicU64,
+    text: [AtomicU64; TEXT_WORDS],
+}
+
+impl Slot {
+    const fn new() -> Self {
+        Self {
+            state: AtomicU64::new(0),
+            timestamp_ns: AtomicU64::new(0),
+            meta: AtomicU64::new(0),
+            text: [const { AtomicU64::new(0) }; TEXT_WORDS],
+        }
+    }
+}
+
+fn pack_meta(hwthread_id: u32, level: log::Level, len: usize) -> u64 {
+    let level = match level {
+        log::Level::Error => 1,
+        log::Level::Warn => 2,
+        log::Level::Info => 3,
+        log::Level::Debug => 4,
+        log::Level::Trace => 5,
+    };
+
+    (u64::from(hwthread_id) << 32) | (level << 16) | u64::try_from(len).unwrap()
+}
+
+fn unpack_meta(meta: u64) -> (u32, log::Level, usize) {
+    let hwthread_id = u32::try_from(meta >> 32).unwrap();
+    let level = match (meta >> 16) & 0xFF {
+        1 => log::Level::Error,
+        2 => log::Level::Warn,
+        3 => log::Level::Info,
+        4 => log::Level::Debug,
+        _ => log::Level::Trace,
+    };
+    let len = usize::try_from(meta & 0xFFFF).unwrap().min(TEXT_LEN);
+
+    (hwthread_id, level, len)
+}
+
+/// Formats into a fixed-size buffer, silently truncating anything that doesn't fit.
+struct TruncatingWriter {
+    buffer: [u8; TEXT_LEN],
+    len: usize,
+}
+
+impl Write for TruncatingWriter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let remaining = &mut self.buffer[self.len..];
+        let copy_len = s.len().min(remaining.len());
+        remaining[..copy_len].copy_from_slice(&s.as_bytes()[..copy_len]);
+        self.len += copy_len;
+
+        Ok(())
+    }
+}
+
+/// A log record copied out of the ring.
+pub struct Record {
+    pub sequence: u64,
+    pub timestamp_ns: u64,
+    pub hwthread_id: u32,
+    pub level: log::Level,
+    text: [u8; TEXT_LEN],
+    len: usize,
+}
+
+impl Record {
+    /// Text of the record, as `[target] message`.
+    ///
+    /// # Remarks
+    ///
+    /// Truncation may have split a character, in which case the text ends before it.
+    pub fn text(&self) -> &str {
+        let text = &self.text[..self.len];
+
+        core::str::from_utf8(text).unwrap_or_else(|error| {
+            // Safety: Bytes up to `valid_up_to()` are valid UTF-8.
+            unsafe { core::str::from_utf8_unchecked(&text[..error.valid_up_to()]) }
+        })
+    }
+}
+
+impl core::fmt::Display for Record {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let timestamp_us = self.timestamp_ns / 1000;
+
+        write!(
+            f,
+            "[{secs:>5}.{micros:06}][#{hwthread_id}][{level}]{text}",
+            secs = timestamp_us / 1_000_000,
+            micros = timestamp_us % 1_000_000,
+            hwthread_id = self.hwthread_id,
+            level = self.level,
+            text = self.text(),
+        )
+    }
+}
+
+/// Appends `record` to the ring, overwriting the oldest record if it's full.
+pub fn append(record: &log::Record) {
+    let mut writer = TruncatingWriter {
+        buffer: [0; TEXT_LEN],
+        len: 0,
+    };
+    write!(writer, "[{}] {}", record.target(), record.args()).ok();
+
+    let sequence = HEAD.fetch_add(1, Ordering::Relaxed);
+    let slot = &RING[usize::try_from(sequence).unwrap() % SLOTS];
+
+    slot.state.store((sequence * 2) + 1, Ordering::Relaxed);
+    fence(Ordering::Release);
+
+    slot.timestamp_ns
+        .store(crate::time::now_ns(), Ordering::Relaxed);
+    slot.meta.store(
+        pack_meta(crate::cpu::get_id(), record.level(), writer.len),
+        Ordering::Relaxed,
+    );
+    for (word, bytes) in slot
+        .text
+        .iter()
+        .zip(writer.buffer.chunks_exact(size_of::<u64>()))
+    {
+        word.store(
+            u64::from_ne_bytes(bytes.try_into().unwrap()),
+            Ordering::Relaxed,
+        );
+    }
+
+    slot.state.store((sequence * 2) + 2, Ordering::Release);
+}
+
+/// Copies the record `sequence` out of the ring, if it is complete and has not been overwritten.
+fn read(sequence: u64) -> Option<Record> {
+    let slot = &RING[usize::try_from(sequence).unwrap() % SLOTS];
+
+    let state = slot.state.load(Ordering::Acquire);
+    if state != (sequence * 2) + 2 {
+        return None;
+    }
+
+    let timestamp_ns = slot.timestamp_ns.load(Ordering::Relaxed);
+    let (hwthread_id, level, len) = unpack_meta(slot.meta.load(Ordering::Relaxed));
+    let mut text = [0; TEXT_LEN];
+    for (word, bytes) in slot
+        .text
+        .iter()
+        .zip(text.chunks_exact_mut(size_of::<u64>()))
+    {
+        bytes.copy_from_slice(&word.load(Ordering::Relaxed).to_ne_bytes());
+    }
+
+    // A writer may have started overwriting the slot while it was copied.
+    fence(Ordering::Acquire);
+    if slot.state.load(Ordering::Relaxed) != state {
+        return None;
+    }
+
+    Some(Record {
+        sequence,
+        timestamp_ns,
+        hwthread_id,
+        level,
+        text,
+        len,
+    })
+}
+
+/// Sequence number of the next record to be appended.
+pub fn head() -> u64 {
+    HEAD.load(Ordering::Acquire)
+}
+
+/// Sequence number of the oldest record that may still be in the ring.
+pub fn tail() -> u64 {
+    head().saturating_sub(u64::try_from(SLOTS).unwrap())
+}
+
+/// Iterates the retained records, oldest first, starting from sequence number `from`.
+///
+/// # Remarks
+///
+/// Records which are overwritten (or still being written) as they're read are skipped.
+pub fn records_from(from: u64) -> impl Iterator<Item = Record> {
+    (from.max(tail())..head()).filter_map(read)
+}
+
+crate::kernel_test! {
+    /// Logged records are retained in the ring.
+    fn retains_records() -> crate::tests::Outcome {
+        let from = head();
+        info!("Log ring probe.");
+
+        records_from(from)
+            .any(|record| record.text().ends_with("Log ring probe."))
+            .then_some(())
+            .ok_or("record was not retained")
+    }
+}
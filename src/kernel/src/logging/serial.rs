@@ -1,8 +1,13 @@
-use crate::interrupts::InterruptCell;
-use core::{fmt::Write, num::NonZero};
-use spin::{Mutex, Once};
+use crate::{interrupts::InterruptCell, sync::SpinMutex};
+use core::{
+    fmt::Write,
+    num::NonZero,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use spin::Once;
 use uart::{
-    Baud, Data, FifoControl, LineControl, LineStatus, ModemControl, Uart, address::PortAddress,
+    Baud, Data, FifoControl, InterruptEnable, LineControl, LineStatus, ModemControl, Uart,
+    address::PortAddress,
 };
 
 #[derive(Debug, Error)]
@@ -13,13 +18,19 @@ pub enum Error {
 
 const UART_FIFO_SIZE: usize = 16;
 
-pub struct Logger(InterruptCell<Mutex<Writer>>);
+/// ISA IRQ raised by the first serial port.
+pub const COM1_IRQ: u8 = 4;
+
+static UART_LOGGER: Once<Logger> = Once::new();
+
+/// Whether log records are kept off the serial line, e.g. while it carries the GDB remote protocol.
+static MUTED: AtomicBool = AtomicBool::new(false);
+
+pub struct Logger(InterruptCell<SpinMutex<Writer>>);
 
 impl Logger {
     /// Initializes the UART-based serial logging device.
     pub fn init() -> Result<&'static Self, Error> {
-        static UART_LOGGER: Once<Logger> = Once::new();
-
         UART_LOGGER.try_call_once(|| {
             // Safety: Value is >0.
             let port_address = unsafe { NonZero::new_unchecked(0x3F8) };
@@ -66,25 +77,80 @@ impl Logger {
                 uart.write_byte(byte);
             });
 
-            Ok(Self(InterruptCell::new(Mutex::new(Writer(uart)))))
+            Ok(Self(InterruptCell::new(SpinMutex::new(Writer(uart)))))
         })
     }
 }
 
+/// Gets the serial device, if it initialized successfully.
+pub fn get() -> Option<&'static Logger> {
+    UART_LOGGER.get()
+}
+
+/// Stops (or resumes) writing log records to the serial line.
+///
+/// # Remarks
+///
+/// Records are still written while the kernel is panicking.
+pub fn set_muted(muted: bool) {
+    MUTED.store(muted, Ordering::Release);
+}
+
+impl Logger {
+    /// Enables (or disables) the received-data interrupt.
+    pub fn set_receive_interrupt(&self, enabled: bool) {
+        self.0.with(|writer| {
+            writer.lock().0.write_interrupt_enable(if enabled {
+                InterruptEnable::RECEIVED_DATA
+            } else {
+                InterruptEnable::empty()
+            });
+        });
+    }
+
+    /// Drains the receive FIFO, passing each byte to `func`.
+    pub fn receive(&self, mut func: impl FnMut(u8)) {
+        self.0.with(|writer| {
+            let mut writer = writer.lock();
+
+            while writer.0.read_line_status().contains(LineStatus::DATA_READY) {
+                func(writer.0.read_byte());
+            }
+        });
+    }
+
+    /// Writes raw `bytes`, bypassing log formatting.
+    pub fn write_bytes(&self, bytes: &[u8]) {
+        self.0.with(|writer| {
+            let mut writer = writer.lock();
+
+            for byte in bytes.iter().copied() {
+                writer.wait_for_empty();
+                writer.0.write_byte(byte);
+            }
+        });
+    }
+
+    /// Writes a formatted log line.
+    pub fn write(&self, _: log::Level, args: core::fmt::Arguments) {
+        self.0.with(|writer| {
+            let mut writer = writer.lock();
+
+            writer.write_fmt(args).ok();
+        });
+    }
+}
+
 impl log::Log for Logger {
     fn enabled(&self, metadata: &log::Metadata) -> bool {
-        metadata.level() <= log::Level::Debug
+        let muted = MUTED.load(Ordering::Acquire) && !crate::panic::is_panicking();
+
+        !muted && metadata.level() <= log::Level::Debug
     }
 
     fn log(&self, record: &log::Record) {
         if self.enabled(record.metadata()) {
-            super::with_formatted_log_record(record, |args| {
-                self.0.with(|writer| {
-                    let mut writer = writer.lock();
-
-                    writer.write_fmt(args).ok();
-                });
-            });
+            super::with_formatted_log_record(record, |args| self.write(record.level(), args));
         }
     }
 
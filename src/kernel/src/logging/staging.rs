@@ -0,0 +1,84 @@
+//! Per-hardware-thread staging of records logged from interrupt context.
+//!
+//! An interrupt handler must not write to the log sinks directly: anything it interrupted (a non-maskable interrupt
+//! or an exception can interrupt anything) may already hold a sink's lock on the same hardware thread. Instead, the
+//! record is formatted into the hardware thread's staging buffer, which is written out by the next log call made
+//! outside of interrupt context, or by the periodic flusher.
+
+use core::fmt::Write;
+use heapless::Deque;
+
+/// Maximum length (in bytes) of a staged record's formatted line; longer lines are truncated.
+const LINE_LEN: usize = 192;
+
+/// Records staged before further records are dropped.
+const CAPACITY: usize = 32;
+
+/// A formatted record, awaiting being written to the sinks.
+pub struct Staged {
+    pub level: log::Level,
+    line: [u8; LINE_LEN],
+    len: usize,
+}
+
+impl Staged {
+    pub fn line(&self) -> &str {
+        let line = &self.line[..self.len];
+
+        core::str::from_utf8(line).unwrap_or_else(|error| {
+            // Safety: Bytes up to `valid_up_to()` are valid UTF-8.
+            unsafe { core::str::from_utf8_unchecked(&line[..error.valid_up_to()]) }
+        })
+    }
+}
+
+impl Write for Staged {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let remaining = &mut self.line[self.len..];
+        let copy_len = s.len().min(remaining.len());
+        remaining[..copy_len].copy_from_slice(&s.as_bytes()[..copy_len]);
+        self.len += copy_len;
+
+        Ok(())
+    }
+}
+
+/// Records staged on a single hardware thread.
+pub struct Staging {
+    records: Deque<Staged, CAPACITY>,
+    /// Number of records dropped since the buffer was last drained, as it was full.
+    dropped: usize,
+}
+
+impl Staging {
+    pub const fn new() -> Self {
+        Self {
+            records: Deque::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Formats `record` into the buffer, dropping it if the buffer is full.
+    pub fn stage(&mut self, record: &log::Record) {
+        let mut staged = Staged {
+            level: record.level(),
+            line: [0; LINE_LEN],
+            len: 0,
+        };
+        super::with_formatted_log_record(record, |args| {
+            staged.write_fmt(args).ok();
+        });
+
+        if self.records.push_back(staged).is_err() {
+            self.dropped += 1;
+        }
+    }
+
+    /// Takes every staged record, along with the number of records which were dropped.
+    pub fn take(&mut self) -> (Deque<Staged, CAPACITY>, usize) {
+        (
+            core::mem::take(&mut self.records),
+            core::mem::take(&mut self.dropped),
+        )
+    }
+}
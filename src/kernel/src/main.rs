@@ -54,23 +54,37 @@ use limine::{
     mp::RequestFlags,
     request::{
         BootloaderInfoRequest, ExecutableAddressRequest, ExecutableCmdlineRequest,
-        ExecutableFileRequest, HhdmRequest, MemoryMapRequest, MpRequest, RsdpRequest,
-        StackSizeRequest,
+        ExecutableFileRequest, FramebufferRequest, HhdmRequest, MemoryMapRequest, ModuleRequest,
+        MpRequest, PagingModeRequest, RsdpRequest, StackSizeRequest,
     },
 };
 
 mod acpi;
 mod arch;
+mod bench;
+mod console;
 mod cpu;
+mod debugger;
+mod devices;
+mod fs;
+mod init;
+mod initramfs;
 mod interrupts;
 mod logging;
 mod mem;
+mod net;
 mod panic;
 mod params;
+mod profiler;
+mod qemu_exit;
 mod rand;
+mod sync;
 mod task;
+mod test_rpc;
+mod tests;
 mod time;
 mod util;
+mod watchdog;
 
 extern crate alloc;
 
@@ -143,9 +157,22 @@ unsafe extern "C" fn _entry() -> ! {
     static MEMORY_MAP_REQUEST: MemoryMapRequest = MemoryMapRequest::new();
     static RSDP_REQUEST: RsdpRequest = RsdpRequest::new();
     static MP_REQUEST: MpRequest = MpRequest::new().with_flags(RequestFlags::X2APIC);
+    static MODULE_REQUEST: ModuleRequest = ModuleRequest::new();
+    static FRAMEBUFFER_REQUEST: FramebufferRequest = FramebufferRequest::new();
+    // 5-level paging is used wherever it's supported; the bootloader falls back to 4-level paging otherwise.
+    static PAGING_MODE_REQUEST: PagingModeRequest = PagingModeRequest::new()
+        .with_mode(limine::paging::Mode::FIVE_LEVEL)
+        .with_max_mode(limine::paging::Mode::FIVE_LEVEL)
+        .with_min_mode(limine::paging::Mode::FOUR_LEVEL);
+
+    // Safety: This is the first thing run on the bootstrap processor; everything after reads the hardware thread's
+    //         ID through it, including logging.
+    unsafe {
+        crate::cpu::percpu::init();
+    }
 
     // Enable logging first, so we can get feedback on the entire init process.
-    crate::logging::Logger::init();
+    crate::logging::Logger::init(&FRAMEBUFFER_REQUEST);
 
     // Safety: Function is run only once for this hardware thread.
     unsafe {
@@ -153,37 +180,227 @@ unsafe extern "C" fn _entry() -> ! {
         crate::arch::x86_64::configure_hwthread();
     }
 
-    print_boot_info(&BOOTLOADER_INFO_REQUEST);
-
-    let (kernel_physical_address, kernel_virtual_address) = KERNEL_ADDRESS_REQUEST
-        .get_response()
-        .map(|response| {
-            (
-                usize::try_from(response.physical_base()).unwrap(),
-                usize::try_from(response.virtual_base()).unwrap(),
-            )
-        })
-        .expect("bootloader did not provide a response to kernel address request");
-    debug!("Kernel physical address: {kernel_physical_address:#X?}");
-    debug!("Kernel virtual address: {kernel_virtual_address:#X?}");
-
-    crate::params::parse(&KERNEL_CMDLINE_REQUEST);
-
-    #[cfg(feature = "panic_traces")]
-    if crate::params::keep_symbol_info() {
-        crate::panic::tracing::symbols::Symbols::init(&KERNEL_FILE_REQUEST);
-    }
+    // Stages run in the order they're declared; each must be declared after every stage it names in `after`.
+    const STAGES: &[crate::init::Stage] = &[
+        crate::init::Stage {
+            name: "boot-info",
+            after: &[],
+            run: || {
+                print_boot_info(&BOOTLOADER_INFO_REQUEST);
+
+                let (kernel_physical_address, kernel_virtual_address) = KERNEL_ADDRESS_REQUEST
+                    .get_response()
+                    .map(|response| {
+                        (
+                            usize::try_from(response.physical_base()).unwrap(),
+                            usize::try_from(response.virtual_base()).unwrap(),
+                        )
+                    })
+                    .expect("bootloader did not provide a response to kernel address request");
+                debug!("Kernel physical address: {kernel_physical_address:#X?}");
+                debug!("Kernel virtual address: {kernel_virtual_address:#X?}");
+            },
+        },
+        crate::init::Stage {
+            name: "params",
+            after: &[],
+            run: || {
+                crate::params::parse(&KERNEL_CMDLINE_REQUEST);
+                crate::logging::apply_filter();
+            },
+        },
+        #[cfg(feature = "panic_traces")]
+        crate::init::Stage {
+            name: "symbols",
+            after: &["params"],
+            run: || {
+                if crate::params::get::<bool>("keep-symbols") {
+                    crate::panic::tracing::symbols::Symbols::init(&KERNEL_FILE_REQUEST);
+                }
+            },
+        },
+        crate::init::Stage {
+            name: "hhdm",
+            after: &[],
+            run: || crate::mem::HigherHalfDirectMap::init(&HHDM_REQUEST),
+        },
+        crate::init::Stage {
+            name: "pmm",
+            after: &["hhdm"],
+            run: || crate::mem::pmm::PhysicalMemoryManager::init(&MEMORY_MAP_REQUEST),
+        },
+        crate::init::Stage {
+            name: "crashdump",
+            after: &["params", "pmm"],
+            run: || crate::panic::crashdump::init(&MEMORY_MAP_REQUEST),
+        },
+        crate::init::Stage {
+            name: "mem",
+            // The crash dump region must be reserved before anything else allocates frames.
+            after: &["params", "pmm", "crashdump"],
+            run: || {
+                crate::mem::init(
+                    &MEMORY_MAP_REQUEST,
+                    &KERNEL_FILE_REQUEST,
+                    &KERNEL_ADDRESS_REQUEST,
+                );
+            },
+        },
+        crate::init::Stage {
+            name: "dma",
+            after: &["params", "mem"],
+            run: crate::mem::dma::init,
+        },
+        crate::init::Stage {
+            name: "legacy-pic",
+            after: &[],
+            run: crate::arch::x86_64::devices::pic8259::init,
+        },
+        crate::init::Stage {
+            name: "local-apic",
+            // The legacy PICs are masked before the local APIC is enabled, so nothing they raise lands on a kernel
+            // vector.
+            after: &["mem", "legacy-pic"],
+            run: crate::arch::x86_64::devices::local_apic::LocalApic::init,
+        },
+        crate::init::Stage {
+            name: "acpi",
+            after: &["mem"],
+            run: || {
+                crate::acpi::Tables::init(&RSDP_REQUEST);
+                crate::acpi::madt::Topology::init();
+            },
+        },
+        crate::init::Stage {
+            name: "numa",
+            after: &["acpi", "pmm"],
+            run: crate::mem::numa::NumaTopology::init,
+        },
+        crate::init::Stage {
+            name: "pci",
+            // Drivers time out waiting on their devices with the stopwatch.
+            after: &["acpi", "mem", "time"],
+            run: || {
+                crate::devices::pci::ecam::init();
+                crate::devices::virtio::block::register();
+                crate::devices::virtio::console::register();
+                crate::devices::virtio::net::register();
+                crate::devices::nvme::register();
+                crate::devices::ahci::register();
+                crate::devices::usb::xhci::register();
+                crate::devices::pci::init();
+            },
+        },
+        crate::init::Stage {
+            name: "partitions",
+            after: &["pci"],
+            run: crate::devices::block::gpt::scan,
+        },
+        crate::init::Stage {
+            name: "esp",
+            after: &["partitions"],
+            run: crate::fs::fat32::mount_esp,
+        },
+        crate::init::Stage {
+            name: "keyboard",
+            after: &["acpi", "time"],
+            run: crate::devices::i8042::init,
+        },
+        crate::init::Stage {
+            name: "console",
+            after: &["pci"],
+            run: crate::console::init,
+        },
+        crate::init::Stage {
+            name: "time",
+            after: &["acpi"],
+            run: || {
+                crate::time::Stopwatch::init();
+                trace!("System stopwatch initialized.");
+
+                if let Err(error) = crate::time::hpet::Hpet::init() {
+                    warn!("HPET is unavailable: {error}");
+                }
 
-    crate::mem::HigherHalfDirectMap::init(&HHDM_REQUEST);
-    crate::mem::pmm::PhysicalMemoryManager::init(&MEMORY_MAP_REQUEST);
-    crate::mem::init(
-        &MEMORY_MAP_REQUEST,
-        &KERNEL_FILE_REQUEST,
-        &KERNEL_ADDRESS_REQUEST,
-    );
+                crate::time::SystemClock::init();
+                crate::time::vdso::init();
+
+                crate::time::rtc::Rtc::init();
+                crate::time::rtc::Rtc::synchronize();
+            },
+        },
+        crate::init::Stage {
+            name: "initramfs",
+            after: &["mem"],
+            run: || crate::initramfs::Initramfs::init(&MODULE_REQUEST),
+        },
+        crate::init::Stage {
+            name: "initrd",
+            after: &["initramfs"],
+            run: crate::fs::initrd::mount,
+        },
+        crate::init::Stage {
+            name: "kinfo",
+            after: &["initrd"],
+            run: crate::fs::kinfo::mount,
+        },
+        crate::init::Stage {
+            name: "exception-policy",
+            after: &["params"],
+            run: crate::interrupts::exceptions::policy::init,
+        },
+        crate::init::Stage {
+            name: "init-task",
+            after: &["params", "initrd", "time", "exception-policy"],
+            run: || {
+                if let Err(error) = crate::task::init::spawn() {
+                    warn!("No init task: {error}");
+                }
+            },
+        },
+        crate::init::Stage {
+            name: "debug-shell",
+            after: &["params", "console"],
+            run: || {
+                if crate::params::get::<bool>("debug-shell") {
+                    crate::debugger::enter();
+                }
+            },
+        },
+        crate::init::Stage {
+            name: "gdb",
+            after: &["params", "console", "debug-shell"],
+            run: || {
+                if crate::params::get::<bool>("gdb") {
+                    crate::debugger::gdb::attach();
+                }
+            },
+        },
+        crate::init::Stage {
+            name: "ro-after-init",
+            // Nothing may write `ro_after_init` data after this, so it runs after every stage which does.
+            after: &["mem", "local-apic", "init-task", "debug-shell", "gdb"],
+            run: crate::mem::protect_ro_after_init,
+        },
+        crate::init::Stage {
+            name: "tests",
+            after: &["params", "mem", "time", "ro-after-init"],
+            run: || {
+                if crate::tests::is_requested() {
+                    let exit_code = if crate::tests::run_all() {
+                        crate::qemu_exit::ExitCode::Success
+                    } else {
+                        crate::qemu_exit::ExitCode::Failure
+                    };
+
+                    crate::qemu_exit::exit(exit_code);
+                }
+            },
+        },
+    ];
+    const { crate::init::validate(STAGES) };
 
-    crate::time::Stopwatch::init(&RSDP_REQUEST);
-    trace!("System stopwatch initialized.");
+    crate::init::run(STAGES);
 
     // Safety: We've reached the end of the kernel init phase.
     unsafe { crate::cpu::synchronize(Some((&MP_REQUEST, &MEMORY_MAP_REQUEST))) }
@@ -212,8 +429,7 @@ fn print_boot_info(bootloader_info_request: &BootloaderInfoRequest) {
 }
 
 // fn load_drivers() {
-//     use crate::task::{AddressSpace, Priority, Task};
-//     use elf::endian::AnyEndian;
+//     use crate::task::{Priority, Startup, Task};
 
 //     #[limine::limine_tag]
 //     static LIMINE_MODULES: limine::ModuleRequest = limine::ModuleRequest::new(crate::init::boot::LIMINE_REV);
@@ -233,77 +449,22 @@ fn print_boot_info(bootloader_info_request: &BootloaderInfoRequest) {
 //     };
 
 //     let archive = tar_no_std::TarArchiveRef::new(drivers_module.data());
-//     archive
-//         .entries()
-//         .filter_map(|entry| {
-//             debug!("Attempting to parse driver blob: {}", entry.filename());
-
-//             match elf::ElfBytes::<AnyEndian>::minimal_parse(entry.data()) {
-//                 Ok(elf) => Some((entry, elf)),
-//                 Err(err) => {
-//                     error!("Failed to parse driver blob into ELF: {:?}", err);
-//                     None
-//                 }
-//             }
-//         })
-//         .for_each(|(entry, elf)| {
-//             // Get and copy the ELF segments into a small box.
-//             let Some(segments_copy) = elf.segments().map(|segments| segments.into_iter().collect()) else {
-//                 error!("ELF has no segments.");
-//                 return;
-//             };
-
-//             // Safety: In-place transmutation of initialized bytes for the purpose of copying safely.
-//             // let (_, archive_data, _) = unsafe { entry.data().align_to::<MaybeUninit<u8>>() };
-//             trace!("Allocating ELF data into memory...");
-//             let elf_data = alloc::boxed::Box::from(entry.data());
-//             trace!("ELF data allocated into memory.");
-
-//             let Ok((Some(shdrs), Some(_))) = elf.section_headers_with_strtab() else {
-//                 panic!("Error retrieving ELF relocation metadata.")
-//             };
-
-//             let load_offset = crate::task::MIN_LOAD_OFFSET;
-
-//             trace!("Processing relocations localized to fault page.");
-//             let mut relas = alloc::vec::Vec::with_capacity(shdrs.len());
-
-//             shdrs
-//                 .iter()
-//                 .filter(|shdr| shdr.sh_type == elf::abi::SHT_RELA)
-//                 .flat_map(|shdr| elf.section_data_as_relas(&shdr).unwrap())
-//                 .for_each(|rela| {
-//                     use crate::task::ElfRela;
-
-//                     match rela.r_type {
-//                         elf::abi::R_X86_64_RELATIVE => relas.push(ElfRela {
-//                             address: Address::new(usize::try_from(rela.r_offset).unwrap()).unwrap(),
-//                             value: load_offset + usize::try_from(rela.r_addend).unwrap(),
-//                         }),
-
-//                         _ => unimplemented!(),
-//                     }
-//                 });
-
-//             trace!("Finished processing relocations, pushing task.");
-
-//             let task = Task::new(
-//                 Priority::Normal,
-//                 AddressSpace::new_userspace(),
-//                 load_offset,
-//                 elf.ehdr,
-//                 segments_copy,
-//                 relas,
-//                 crate::task::ElfData::Memory(elf_data),
-//             );
-
-//             crate::task::PROCESSES.lock().push_back(task);
-//         });
+//     archive.entries().for_each(|entry| {
+//         debug!("Loading driver blob: {}", entry.filename());
+
+//         let startup = Startup { args: &[entry.filename().as_str()], env: &[] };
+//         match Task::from_elf(Priority::Normal, entry.data(), &startup) {
+//             Ok(task) => crate::task::enqueue_task(task),
+//             Err(error) => error!("Failed to load driver blob: {error}"),
+//         }
+//     });
 // }
 
 #[macro_export]
 macro_rules! singleton {
     (
+        @static [$($static_attrs:tt)*]
+
         $(#[$struct_attrs:meta])*
         $struct_scope:vis $struct_name:ident {
             $(
@@ -318,6 +479,7 @@ macro_rules! singleton {
     ) => {
         paste! {
             #[allow(non_upper_case_globals)]
+            $($static_attrs)*
             static [< STATIC_ $struct_name >]: spin::Once<$struct_name> = spin::Once::new();
 
             $(#[$struct_attrs])*
@@ -358,4 +520,37 @@ macro_rules! singleton {
             }
         }
     };
+
+    // The singleton is only initialized during boot, so is remapped read-only once boot completes.
+    (#[ro_after_init] $($rest:tt)*) => {
+        $crate::singleton! {
+            @static [#[unsafe(link_section = ".data.ro_after_init")]]
+            $($rest)*
+        }
+    };
+
+    ($($rest:tt)*) => {
+        $crate::singleton! {
+            @static []
+            $($rest)*
+        }
+    };
+}
+
+/// Declares statics which are only written during boot, and so are remapped read-only once it completes (see
+/// [`crate::mem::protect_ro_after_init`]).
+///
+/// # Remarks
+///
+/// Any write after boot completes faults, including lazy initialization; so e.g. a [`spin::Lazy`] declared here must
+/// be forced during boot.
+#[macro_export]
+macro_rules! ro_after_init {
+    ($($(#[$attrs:meta])* $vis:vis static $name:ident: $ty:ty = $init:expr;)*) => {
+        $(
+            $(#[$attrs])*
+            #[unsafe(link_section = ".data.ro_after_init")]
+            $vis static $name: $ty = $init;
+        )*
+    };
 }
@@ -1,13 +1,74 @@
-use crate::mem::{HigherHalfDirectMap, pmm::PhysicalMemoryManager};
+use crate::mem::{
+    HigherHalfDirectMap, paging,
+    pmm::{self, PhysicalMemoryManager},
+};
 use alloc::boxed::Box;
 use core::{
     alloc::{AllocError, Allocator, Layout},
     num::NonZero,
     ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
 };
-use libsys::{Address, page_shift, page_size};
+use libsys::{Address, Frame, mega_page_size, page_shift, page_size};
 use zerocopy::FromZeros;
 
+/// Bytes of frames currently allocated to the heap.
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+/// Bytes of large allocations which begin on a 2MiB boundary, and so span the fewest 2MiB mappings.
+static HUGE_BYTES: AtomicUsize = AtomicUsize::new(0);
+/// Bytes of large allocations which had to fall back to a run of frames that isn't 2MiB-aligned.
+static SPLIT_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Snapshot of the heap's usage.
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    pub allocated_bytes: usize,
+    pub huge_bytes: usize,
+    pub split_bytes: usize,
+}
+
+pub fn stats() -> Stats {
+    Stats {
+        allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+        huge_bytes: HUGE_BYTES.load(Ordering::Relaxed),
+        split_bytes: SPLIT_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+/// Whether an allocation of `frame_count` frames is large enough to be placed on 2MiB boundaries.
+fn is_large(frame_count: usize) -> bool {
+    (frame_count * page_size()) >= mega_page_size() && paging::use_mega_pages()
+}
+
+/// Counter a large allocation at `frame` is accounted against.
+fn large_counter(frame: Address<Frame>) -> &'static AtomicUsize {
+    if frame.get().get().is_multiple_of(mega_page_size()) {
+        &HUGE_BYTES
+    } else {
+        &SPLIT_BYTES
+    }
+}
+
+/// Allocates a run of `frame_count` frames for a large allocation.
+///
+/// # Remarks
+///
+/// Usable memory is direct-mapped with 2MiB pages wherever its alignment permits, so a run starting on a 2MiB
+/// boundary is covered by the fewest TLB entries. If no such run is free, any run will do.
+fn next_large_frames(frame_count: NonZero<usize>) -> Result<Address<Frame>, pmm::Error> {
+    let align = NonZero::new(u32::try_from(mega_page_size()).unwrap());
+
+    match PhysicalMemoryManager::next_frames(frame_count, align) {
+        Err(pmm::Error::NoneFree) => {
+            trace!("No 2MiB-aligned run of {frame_count} frames is free; splitting allocation.");
+
+            PhysicalMemoryManager::next_frames(frame_count, None)
+        }
+
+        result => result,
+    }
+}
+
 #[global_allocator]
 pub static KERNEL_ALLOCATOR: KernelAllocator = KernelAllocator;
 
@@ -35,6 +96,16 @@ unsafe impl Allocator for KernelAllocator {
 
             1 => PhysicalMemoryManager::next_frame(),
 
+            frame_count if is_large(frame_count) => {
+                // Safety: `frame_count` is already checked to be >0.
+                next_large_frames(unsafe { NonZero::<usize>::new_unchecked(frame_count) }).inspect(
+                    |frame| {
+                        large_counter(*frame)
+                            .fetch_add(frame_count * page_size(), Ordering::Relaxed);
+                    },
+                )
+            }
+
             frame_count => PhysicalMemoryManager::next_frames(
                 // Safety: `frame_count` is already checked to be >0.
                 unsafe { NonZero::<usize>::new_unchecked(frame_count) },
@@ -44,6 +115,8 @@ unsafe impl Allocator for KernelAllocator {
         .map(|frame| {
             trace!("Allocate @ {frame:?}:{frame_count}");
 
+            ALLOCATED_BYTES.fetch_add(frame_count * page_size(), Ordering::Relaxed);
+
             NonNull::slice_from_raw_parts(
                 NonNull::without_provenance(HigherHalfDirectMap::offset(frame.get().get())),
                 layout.size(),
@@ -70,10 +143,15 @@ unsafe impl Allocator for KernelAllocator {
         let physical_offset_aligned = libsys::align_down(physical_offset, page_shift());
         let frame_address = Address::new(physical_offset_aligned).unwrap();
 
+        let frame_count = libsys::align_up_div(layout.size(), page_shift());
+        ALLOCATED_BYTES.fetch_sub(frame_count * page_size(), Ordering::Relaxed);
+        if is_large(frame_count) {
+            large_counter(frame_address).fetch_sub(frame_count * page_size(), Ordering::Relaxed);
+        }
+
         if layout.size() <= page_size() {
             PhysicalMemoryManager::free_frame(frame_address).ok();
         } else {
-            let frame_count = libsys::align_up_div(layout.size(), page_shift());
             let frames_start = frame_address.index();
             let frames_end = frames_start + frame_count;
 
@@ -145,3 +223,15 @@ impl KernelAllocator {
         Ok(t_box)
     }
 }
+
+crate::kernel_test! {
+    /// Heap allocations hold what was written to them.
+    fn heap() -> crate::tests::Outcome {
+        let values = (0..0x1000u32).collect::<alloc::vec::Vec<_>>();
+        if values.iter().copied().sum::<u32>() != (0..0x1000u32).sum() {
+            return Err("heap allocation was corrupted");
+        }
+
+        Ok(())
+    }
+}
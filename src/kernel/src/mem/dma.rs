@@ -0,0 +1,260 @@
+//! DMA-safety audit mode.
+//!
+//! When enabled (via `--dma-audit`), every frame handed to a device is tagged with its owning driver. The physical
+//! memory manager then refuses to free or re-lock tagged frames until the owner releases them, so a driver that
+//! frees a buffer the device may still write to is reported at the point of the free, rather than surfacing later
+//! as unrelated memory corruption.
+
+use crate::{
+    interrupts::InterruptCell,
+    mem::pmm::{self, PhysicalMemoryManager},
+    sync::SpinMutex,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    num::NonZero,
+    ops::Range,
+    panic::Location,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use libsys::{Address, Frame};
+use spin::Once;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Pmm(#[from] pmm::Error),
+
+    #[error("frame is not tagged for DMA: {0:#X?}")]
+    NotTagged(Address<Frame>),
+
+    #[error("frame is already tagged for DMA by `{owner}`: {address:#X?}")]
+    AlreadyTagged {
+        address: Address<Frame>,
+        owner: &'static str,
+    },
+
+    #[error("frame is tagged for DMA by `{owner}`, not `{releaser}`: {address:#X?}")]
+    WrongOwner {
+        address: Address<Frame>,
+        owner: &'static str,
+        releaser: &'static str,
+    },
+}
+
+/// Records which driver handed a run of frames to a device, and where.
+#[derive(Debug, Clone, Copy)]
+pub struct Tag {
+    pub owner: &'static str,
+    pub location: &'static Location<'static>,
+}
+
+const BITS: usize = size_of::<usize>() * 8;
+
+struct Audit {
+    /// One bit per frame, set while the frame is owned by a device.
+    tagged: Box<[AtomicUsize]>,
+    /// Details for each tagged run of frames, by frame index.
+    tags: InterruptCell<SpinMutex<Vec<(Range<usize>, Tag)>>>,
+}
+
+impl Audit {
+    fn is_tagged(&self, index: usize) -> bool {
+        self.tagged
+            .get(index / BITS)
+            .is_some_and(|word| (word.load(Ordering::Acquire) & (1 << (index % BITS))) > 0)
+    }
+
+    fn set_tagged(&self, indexes: Range<usize>, tagged: bool) {
+        for index in indexes {
+            let bit = 1 << (index % BITS);
+            if tagged {
+                self.tagged[index / BITS].fetch_or(bit, Ordering::AcqRel);
+            } else {
+                self.tagged[index / BITS].fetch_and(!bit, Ordering::AcqRel);
+            }
+        }
+    }
+
+    fn tag_of(&self, index: usize) -> Option<(Range<usize>, Tag)> {
+        // Never block here: the caller may be the allocator, invoked while the tag list is locked.
+        self.tags.with(|tags| {
+            tags.try_lock()?
+                .iter()
+                .find(|(frames, _)| frames.contains(&index))
+                .cloned()
+        })
+    }
+}
+
+crate::parameters! {
+    /// Whether frames owned by devices are tracked, and freeing them before release is refused.
+    "dma-audit": bool = false,
+}
+
+static AUDIT: Once<Audit> = Once::new();
+
+/// Enables the audit, if requested on the kernel command line.
+///
+/// Must be called after the kernel heap is available.
+pub fn init() {
+    if !crate::params::get::<bool>("dma-audit") {
+        return;
+    }
+
+    AUDIT.call_once(|| {
+        let words = PhysicalMemoryManager::total_frames().div_ceil(BITS);
+
+        info!("DMA-safety audit enabled.");
+
+        Audit {
+            tagged: (0..words).map(|_| AtomicUsize::new(0)).collect(),
+            tags: InterruptCell::new(SpinMutex::new(Vec::new())),
+        }
+    });
+}
+
+pub fn is_enabled() -> bool {
+    AUDIT.is_completed()
+}
+
+fn frame_range(address: Address<Frame>, count: NonZero<usize>) -> Range<usize> {
+    address.index()..(address.index() + count.get())
+}
+
+/// Marks `count` frames starting at `address` as owned by a device on behalf of `owner`.
+///
+/// This is a no-op when the audit is disabled.
+#[track_caller]
+pub fn tag(
+    address: Address<Frame>,
+    count: NonZero<usize>,
+    owner: &'static str,
+) -> Result<(), Error> {
+    let Some(audit) = AUDIT.get() else {
+        return Ok(());
+    };
+
+    let location = Location::caller();
+    let frames = frame_range(address, count);
+
+    audit.tags.with(|tags| {
+        let mut tags = tags.lock();
+
+        if let Some((_, tag)) = tags
+            .iter()
+            .find(|(tagged, _)| tagged.start < frames.end && frames.start < tagged.end)
+        {
+            return Err(Error::AlreadyTagged {
+                address,
+                owner: tag.owner,
+            });
+        }
+
+        tags.push((frames.clone(), Tag { owner, location }));
+        audit.set_tagged(frames, true);
+
+        Ok(())
+    })
+}
+
+/// Returns `count` frames starting at `address` from the device to the kernel, allowing them to be freed.
+///
+/// `owner` must match the owner the frames were tagged with. This is a no-op when the audit is disabled.
+pub fn release(
+    address: Address<Frame>,
+    count: NonZero<usize>,
+    owner: &'static str,
+) -> Result<(), Error> {
+    let Some(audit) = AUDIT.get() else {
+        return Ok(());
+    };
+
+    let frames = frame_range(address, count);
+
+    audit.tags.with(|tags| {
+        let mut tags = tags.lock();
+
+        let position = tags
+            .iter()
+            .position(|(tagged, _)| *tagged == frames)
+            .ok_or(Error::NotTagged(address))?;
+
+        let tag = tags[position].1;
+        if tag.owner != owner {
+            return Err(Error::WrongOwner {
+                address,
+                owner: tag.owner,
+                releaser: owner,
+            });
+        }
+
+        tags.swap_remove(position);
+        audit.set_tagged(frames, false);
+
+        Ok(())
+    })
+}
+
+/// Allocates `count` contiguous frames and tags them as owned by a device on behalf of `owner`.
+#[track_caller]
+pub fn allocate(count: NonZero<usize>, owner: &'static str) -> Result<Address<Frame>, Error> {
+    let address = PhysicalMemoryManager::next_frames(count, None)?;
+
+    if let Err(error) = tag(address, count, owner) {
+        for index in frame_range(address, count) {
+            PhysicalMemoryManager::free_frame(Address::from_index(index).unwrap())?;
+        }
+
+        return Err(error);
+    }
+
+    Ok(address)
+}
+
+/// Releases `count` frames starting at `address` from `owner`, then frees them.
+pub fn deallocate(
+    address: Address<Frame>,
+    count: NonZero<usize>,
+    owner: &'static str,
+) -> Result<(), Error> {
+    release(address, count, owner)?;
+
+    for index in frame_range(address, count) {
+        PhysicalMemoryManager::free_frame(Address::from_index(index).unwrap())?;
+    }
+
+    Ok(())
+}
+
+/// Checks that the physical memory manager may `operation` the frame at `address`, reporting the violation if
+/// it's still owned by a device.
+pub(super) fn check(
+    address: Address<Frame>,
+    operation: &str,
+    caller: &'static Location<'static>,
+) -> Result<(), pmm::Error> {
+    let Some(audit) = AUDIT.get() else {
+        return Ok(());
+    };
+
+    if !audit.is_tagged(address.index()) {
+        return Ok(());
+    }
+
+    match audit.tag_of(address.index()) {
+        Some((frames, tag)) => error!(
+            "DMA audit: refused to {operation} frame {address:#X?} at {caller}; frames {:#X?}..{:#X?} are owned by `{}` (tagged at {}).",
+            frames.start << libsys::page_shift().get(),
+            frames.end << libsys::page_shift().get(),
+            tag.owner,
+            tag.location
+        ),
+
+        None => error!(
+            "DMA audit: refused to {operation} frame {address:#X?} at {caller}; frame is owned by a device."
+        ),
+    }
+
+    Err(pmm::Error::DmaTagged(address))
+}
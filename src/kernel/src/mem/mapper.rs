@@ -1,12 +1,17 @@
 use crate::{
     mem::{
-        HigherHalfDirectMap,
+        HigherHalfDirectMap, PagingRegister,
         paging::{Error, FlagsModify, PageTable, PageTableEntry, TableDepth, TableEntryFlags},
         pmm::PhysicalMemoryManager,
     },
     util::{Mut, Ref},
 };
-use libsys::{Address, Frame, Page};
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    ops::{Range, RangeInclusive},
+};
+use libsys::{Address, Frame, Page, Virtual};
 
 pub struct Mapper {
     depth: TableDepth,
@@ -53,6 +58,17 @@ impl Mapper {
         }
     }
 
+    /// Constructs a mapper over the active page tables, so they can be inspected without taking the kernel mapper's
+    /// lock (i.e. from the debugger, or an exception handler).
+    ///
+    /// # Safety
+    ///
+    /// The returned mapper must only be used to read the page tables.
+    pub unsafe fn active() -> Self {
+        // Safety: The active page tables are valid, and caller is required to only read them.
+        unsafe { Self::new_unsafe(TableDepth::max(), PagingRegister::read().frame()) }
+    }
+
     fn root_table(&self) -> PageTable<'_, Ref> {
         // Safety: `Self` requires that the entry be valid.
         unsafe { PageTable::<Ref>::new(self.depth, &self.entry) }
@@ -100,42 +116,120 @@ impl Mapper {
             })
     }
 
-    /// Unmaps the given page, optionally freeing the frame the page points to within the given [`FrameManager`].
+    /// Unmaps every page within `range`, optionally freeing the frames they mapped.
+    ///
+    /// Huge pages which `range` only partly covers are split, so the remainder stays mapped, and page tables left
+    /// empty are freed. Nothing is freed until other hardware threads using the address space have invalidated their
+    /// translations of `range` (see [`crate::mem::tlb`]).
     ///
     /// # Safety
     ///
-    /// Caller must ensure calling this function does not cause memory corruption.
+    /// Caller must ensure nothing still uses the memory mapped within `range`.
     pub unsafe fn unmap(
         &mut self,
-        page: Address<Page>,
-        to_depth: Option<TableDepth>,
-        free_frame: bool,
+        range: Range<Address<Page>>,
+        free_frames: bool,
     ) -> Result<(), Error> {
-        self.root_table_mut()
-            .with_entry_mut(page, to_depth, |entry| {
-                // Safety: Caller must ensure invariants are maintained.
-                unsafe {
-                    entry.set_attributes(TableEntryFlags::PRESENT, FlagsModify::Remove);
-                }
+        // Safety: Caller is required to maintain safety invariants.
+        unsafe { self.unmap_range(range.start.get().get()..range.end.get().get(), free_frames) }
+    }
 
+    /// Unmaps every page within the address range `range`, as [`Self::unmap`] does.
+    ///
+    /// # Remarks
+    ///
+    /// `range` needn't end at a canonical address, so it can cover the whole lower half.
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure nothing still uses the memory mapped within `range`.
+    pub unsafe fn unmap_range(
+        &mut self,
+        range: Range<usize>,
+        free_frames: bool,
+    ) -> Result<(), Error> {
+        // Frames, and how many follow them, freed once the translations are shot down.
+        let mut freed_frames = Vec::new();
+        let mut freed_tables = Vec::new();
+
+        let result = self.root_table_mut().for_each_leaf_in(
+            0,
+            &range,
+            &mut freed_tables,
+            &mut |entry, depth| {
                 let frame = entry.get_frame();
+                *entry = PageTableEntry::empty();
 
-                // Safety: Caller must ensure invariants are maintained.
-                unsafe {
-                    entry.set_frame(Address::new_truncate(0));
+                if free_frames {
+                    freed_frames.push((frame, depth.align() / libsys::page_size()));
                 }
 
-                if free_frame {
-                    PhysicalMemoryManager::free_frame(frame)?;
-                }
+                Ok(())
+            },
+        );
 
-                // Invalidate the page in the TLB.
-                #[cfg(target_arch = "x86_64")]
-                crate::arch::x86_64::instructions::__invlpg(page);
+        // Even if the walk failed partway, entries before the failure were removed.
+        crate::mem::tlb::shootdown(self.root_frame, &range);
+
+        let freed_frames = freed_frames.into_iter().flat_map(|(frame, count)| {
+            (0..count)
+                .map(move |index_offset| Address::from_index(frame.index() + index_offset).unwrap())
+        });
+        for frame in freed_frames.chain(freed_tables) {
+            PhysicalMemoryManager::free_frame(frame)?;
+        }
+
+        result
+    }
+
+    /// Modifies the attributes of every page mapped within `range` with `attributes`, according to `modify_mode`.
+    ///
+    /// Huge pages which `range` only partly covers are split, so the remainder keeps its attributes. Other hardware
+    /// threads using the address space have their translations of `range` invalidated (see [`crate::mem::tlb`]).
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure the new attributes don't invalidate any use of the memory mapped within `range`.
+    pub unsafe fn protect(
+        &mut self,
+        range: Range<Address<Page>>,
+        attributes: TableEntryFlags,
+        modify_mode: FlagsModify,
+    ) -> Result<(), Error> {
+        let range = range.start.get().get()..range.end.get().get();
+
+        let mut freed_tables = Vec::new();
+        let result = self.root_table_mut().for_each_leaf_in(
+            0,
+            &range,
+            &mut freed_tables,
+            &mut |entry, depth| {
+                // Safety: Caller is required to maintain safety invariants.
+                unsafe {
+                    entry.set_attributes(attributes, modify_mode);
+
+                    // Whether an entry is a huge page isn't an attribute the caller can change.
+                    entry.set_attributes(
+                        TableEntryFlags::HUGE,
+                        if depth.is_min() {
+                            FlagsModify::Remove
+                        } else {
+                            FlagsModify::Insert
+                        },
+                    );
+                }
 
                 Ok(())
-            })
-            .flatten()
+            },
+        );
+
+        crate::mem::tlb::shootdown(self.root_frame, &range);
+
+        for table in freed_tables {
+            PhysicalMemoryManager::free_frame(table)?;
+        }
+
+        result
     }
 
     pub fn auto_map(&mut self, page: Address<Page>, flags: TableEntryFlags) -> Result<(), Error> {
@@ -164,6 +258,23 @@ impl Mapper {
             .ok()
     }
 
+    /// Translates `address` through the page tables; see [`PageTable::translate`].
+    pub fn translate(
+        &self,
+        address: Address<Virtual>,
+    ) -> Option<(Address<Frame>, TableEntryFlags, TableDepth)> {
+        self.root_table().translate(address)
+    }
+
+    /// Writes the entries which map `range`; see [`PageTable::dump_range`].
+    pub fn dump_range(
+        &self,
+        range: RangeInclusive<Address<Virtual>>,
+        out: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        self.root_table().dump_range(range, out)
+    }
+
     /* STATE CHANGING */
 
     pub fn get_page_attributes(&self, page: Address<Page>) -> Option<TableEntryFlags> {
@@ -190,7 +301,12 @@ impl Mapper {
 
             #[cfg(target_arch = "x86_64")]
             crate::arch::x86_64::instructions::__invlpg(page);
-        })
+        })?;
+
+        let address = page.get().get();
+        crate::mem::tlb::shootdown(self.root_frame, &(address..(address + libsys::page_size())));
+
+        Ok(())
     }
 
     /// # Safety
@@ -199,6 +315,8 @@ impl Mapper {
     pub unsafe fn swap_into(&self) {
         trace!("Swapping CR3: {:X?}", self.root_frame);
 
+        crate::mem::tlb::set_active_root(self.root_frame);
+
         // Safety: Caller is required to maintain safety invariants.
         unsafe {
             #[cfg(target_arch = "x86_64")]
@@ -226,3 +344,101 @@ impl Mapper {
         unsafe { table.try_into().unwrap_unchecked() }
     }
 }
+
+crate::kernel_test! {
+    /// A page mapped in a fresh address space translates to its frame, with its attributes, until it's unmapped.
+    fn round_trip() -> crate::tests::Outcome {
+        // The address space isn't active, so any canonical address will do.
+        let page = Address::<Page>::new(0x4000_0000).unwrap();
+        let attributes = TableEntryFlags::PRESENT | TableEntryFlags::RW;
+
+        // The address space's table frames are leaked, as `Mapper` doesn't free them.
+        let mut mapper = Mapper::new(TableDepth::max());
+        let frame = PhysicalMemoryManager::next_frame().map_err(|_| "failed to allocate a frame")?;
+
+        mapper
+            .map(page, TableDepth::min(), frame, false, attributes)
+            .map_err(|_| "failed to map the page")?;
+        if mapper.get_mapped_to(page) != Some(frame) {
+            return Err("page does not translate to its frame");
+        }
+        if !mapper
+            .get_page_attributes(page)
+            .is_some_and(|flags| flags.contains(attributes))
+        {
+            return Err("page is missing its attributes");
+        }
+
+        let next_page = Address::<Page>::from_index(page.index() + 1).unwrap();
+        // Safety: The address space isn't active, so nothing can be using the page.
+        unsafe { mapper.unmap(page..next_page, true) }.map_err(|_| "failed to unmap the page")?;
+        if mapper.is_mapped(page, None) {
+            return Err("page is still mapped");
+        }
+
+        Ok(())
+    }
+}
+
+crate::kernel_test! {
+    /// Addresses in the HHDM translate back to their frames, including those mapped by huge pages.
+    fn translate_hhdm() -> crate::tests::Outcome {
+        let frame = PhysicalMemoryManager::next_frame().map_err(|_| "failed to allocate a frame")?;
+        let address = HigherHalfDirectMap::frame_to_page(frame).get();
+
+        let translation = crate::mem::with_kernel_mapper(|kmapper| kmapper.translate(address));
+        PhysicalMemoryManager::free_frame(frame).map_err(|_| "failed to free the frame")?;
+
+        match translation {
+            Some((translated, attributes, _))
+                if translated == frame && attributes.contains(TableEntryFlags::PRESENT) =>
+            {
+                Ok(())
+            }
+            Some(_) => Err("HHDM address translated to the wrong frame"),
+            None => Err("HHDM address is not mapped"),
+        }
+    }
+}
+
+crate::kernel_test! {
+    /// Unmapping or protecting part of a huge page splits it, leaving the remainder mapped as it was.
+    fn split_huge_page() -> crate::tests::Outcome {
+        // The address space is never active, so the frames are never accessed.
+        let huge_page = Address::<Page>::new(0x4000_0000).unwrap();
+        let huge_frame = Address::<Frame>::new(0x4000_0000).unwrap();
+        let page_at = |index| Address::<Page>::from_index(huge_page.index() + index).unwrap();
+        let frame_at = |index| Address::<Frame>::from_index(huge_frame.index() + index).unwrap();
+
+        let mut mapper = Mapper::new(TableDepth::max());
+        mapper
+            .map(huge_page, TableDepth::mega(), huge_frame, false, TableEntryFlags::RW | TableEntryFlags::HUGE)
+            .map_err(|_| "failed to map the huge page")?;
+
+        // Safety: The address space isn't active, and the frames aren't freed.
+        unsafe {
+            mapper.unmap(page_at(1)..page_at(2), false).map_err(|_| "failed to unmap a page")?;
+            mapper
+                .protect(page_at(2)..page_at(3), TableEntryFlags::WRITABLE, FlagsModify::Remove)
+                .map_err(|_| "failed to protect a page")?;
+        }
+
+        if mapper.translate(page_at(1).get()).is_some() {
+            return Err("unmapped page is still mapped");
+        }
+
+        if !mapper.translate(page_at(2).get()).is_some_and(|(frame, attributes, depth)| {
+            frame == frame_at(2) && depth.is_min() && !attributes.contains(TableEntryFlags::WRITABLE)
+        }) {
+            return Err("protected page was not split from the huge page");
+        }
+
+        if !mapper.translate(page_at(3).get()).is_some_and(|(frame, attributes, _)| {
+            frame == frame_at(3) && attributes.contains(TableEntryFlags::WRITABLE)
+        }) {
+            return Err("remainder of the huge page was not left mapped");
+        }
+
+        Ok(())
+    }
+}
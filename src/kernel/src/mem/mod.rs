@@ -3,23 +3,34 @@ pub use hhdm::*;
 
 // pub mod io;
 pub mod alloc;
+pub mod dma;
 pub mod mapper;
+pub mod numa;
 pub mod paging;
 pub mod pmm;
 pub mod stack;
+pub mod tlb;
+pub mod user;
 
 use crate::{
     interrupts::InterruptCell,
     mem::{
         mapper::Mapper,
-        paging::{PageTableEntry, TableDepth, TableEntryFlags},
+        paging::{FlagsModify, PageTableEntry, TableDepth, TableEntryFlags},
         pmm::PhysicalMemoryManager,
     },
+    sync::SpinMutex,
 };
+use core::ptr::NonNull;
 use libsys::{Address, Frame, Page, giga_page_size, mega_page_size, page_size, table_index_size};
-use spin::{Mutex, Once};
+use spin::Once;
 
-static KERNEL_MAPPER: Once<InterruptCell<Mutex<Mapper>>> = Once::new();
+crate::parameters! {
+    /// Whether the kernel should use low-memory mode.
+    "lomem": bool = false,
+}
+
+static KERNEL_MAPPER: Once<InterruptCell<SpinMutex<Mapper>>> = Once::new();
 
 /// Initialize the kernel memory. This will:
 /// - set up the kernel page table mapper
@@ -99,9 +110,10 @@ pub fn init(
     KERNEL_MAPPER.call_once(|| {
         debug!("Preparing kernel memory...");
         debug!(
-            "Paging Setup Info: MEGA:{}, GIGA:{}",
+            "Paging Setup Info: MEGA:{}, GIGA:{}, LEVELS:{}",
             paging::use_mega_pages(),
-            paging::use_giga_pages()
+            paging::use_giga_pages(),
+            TableDepth::max().get()
         );
 
         let mut kernel_mapper = Mapper::new(TableDepth::max());
@@ -121,8 +133,11 @@ pub fn init(
                         limine::memory_map::EntryType::USABLE
                         | limine::memory_map::EntryType::ACPI_NVS
                         | limine::memory_map::EntryType::ACPI_RECLAIMABLE
-                        | limine::memory_map::EntryType::BOOTLOADER_RECLAIMABLE
-                        | limine::memory_map::EntryType::FRAMEBUFFER => TableEntryFlags::RW,
+                        | limine::memory_map::EntryType::BOOTLOADER_RECLAIMABLE => {
+                            TableEntryFlags::RW
+                        }
+
+                        limine::memory_map::EntryType::FRAMEBUFFER => TableEntryFlags::FRAMEBUFFER,
 
                         limine::memory_map::EntryType::RESERVED
                         | limine::memory_map::EntryType::EXECUTABLE_AND_MODULES => {
@@ -135,6 +150,30 @@ pub fn init(
                     }
                 };
 
+                // Write-back and write-combining mappings defer to the MTRRs (with write-combining only overriding
+                // them where there's a PAT), so memory firmware typed otherwise is mysteriously slow.
+                #[cfg(target_arch = "x86_64")]
+                {
+                    use crate::arch::x86_64::{mtrr, pat::{self, MemoryType}};
+
+                    let expected = match entry.entry_type {
+                        limine::memory_map::EntryType::USABLE => Some(MemoryType::WriteBack),
+                        limine::memory_map::EntryType::FRAMEBUFFER if !pat::is_supported() => {
+                            Some(MemoryType::WriteCombining)
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(expected) = expected
+                        && let Some((address, memory_type)) =
+                            mtrr::check(entry.base..(entry.base + entry.length), expected)
+                    {
+                        warn!(
+                            "Memory at {address:#X} is typed {memory_type:?} by the MTRRs, rather than {expected:?}."
+                        );
+                    }
+                }
+
                 map_range(
                     &mut kernel_mapper,
                     entry_page,
@@ -209,10 +248,44 @@ pub fn init(
 
         trace!("Kernel has finalized control of memory system.");
 
-        InterruptCell::new(Mutex::new(kernel_mapper))
+        InterruptCell::new(SpinMutex::new(kernel_mapper))
     });
 }
 
+unsafe extern "C" {
+    unsafe static __ro_after_init_start: u8;
+    unsafe static __ro_after_init_end: u8;
+}
+
+/// Remaps the statics declared with [`crate::ro_after_init!`] (or as `#[ro_after_init]` singletons) read-only, so
+/// any later write to them faults.
+///
+/// # Remarks
+///
+/// This should be called once boot completes. Any other hardware thread already running has its translations of the
+/// region shot down (see [`tlb`]).
+pub fn protect_ro_after_init() {
+    let start = (&raw const __ro_after_init_start).addr();
+    let end = (&raw const __ro_after_init_end).addr();
+    let pages = Address::<Page>::new(start).unwrap()
+        ..Address::<Page>::new(end.next_multiple_of(page_size())).unwrap();
+
+    // Whichever bits make an entry writable on this architecture.
+    let write_flags = TableEntryFlags::RW.difference(TableEntryFlags::RO);
+
+    // Safety: The region only holds statics which are no longer written. Huge pages covering it are split, so
+    //         unrelated (writable) data sharing them stays writable.
+    unsafe {
+        with_kernel_mapper(|kmapper| kmapper.protect(pages, write_flags, FlagsModify::Remove))
+            .expect("failed to remap read-only after init data");
+    }
+
+    debug!(
+        "Remapped {:#X} bytes of read-only after init data as read-only.",
+        end - start
+    );
+}
+
 pub fn with_kernel_mapper<T>(func: impl FnOnce(&mut Mapper) -> T) -> T {
     KERNEL_MAPPER.wait().with(|mapper| {
         let mut mapper = mapper.lock();
@@ -220,6 +293,62 @@ pub fn with_kernel_mapper<T>(func: impl FnOnce(&mut Mapper) -> T) -> T {
     })
 }
 
+/// Switches into the kernel's own address space, if it isn't already current.
+///
+/// # Remarks
+///
+/// Tasks without an address space of their own (kernel threads, and the idle task) run in the kernel's, so the
+/// address space of an exited task is never left current while it's reaped.
+///
+/// # Safety
+///
+/// Caller must not rely on any mappings in the lower half of the current address space.
+pub unsafe fn swap_into_kernel() {
+    with_kernel_mapper(|kmapper| {
+        if kmapper.root_frame() != PagingRegister::read().frame() {
+            // Safety: Caller is required to not rely on the current address space's lower half; the kernel's half is
+            //         shared by every address space.
+            unsafe {
+                kmapper.swap_into();
+            }
+        }
+    });
+}
+
+/// Maps the physical memory-mapped IO region `frame..(frame + length)` into the higher-half
+/// direct map as uncacheable memory, returning a pointer to the start of the region.
+///
+/// # Remarks
+///
+/// The bootloader memory map doesn't include most MMIO regions, so they have to be mapped
+/// explicitly before they can be accessed through the HHDM. Regions which are already
+/// mapped are left as-is.
+pub fn map_mmio(frame: Address<Frame>, length: usize) -> Result<NonNull<u8>, paging::Error> {
+    let page_count = libsys::align_up_div(length, libsys::page_shift());
+
+    with_kernel_mapper(|kmapper| {
+        (0..page_count)
+            .map(|index_offset| Address::<Frame>::from_index(frame.index() + index_offset).unwrap())
+            .try_for_each(|offset_frame| {
+                let offset_page = HigherHalfDirectMap::frame_to_page(offset_frame);
+
+                if kmapper.is_mapped_to(offset_page, offset_frame) {
+                    Ok(())
+                } else {
+                    kmapper.map(
+                        offset_page,
+                        TableDepth::min(),
+                        offset_frame,
+                        false,
+                        TableEntryFlags::MMIO,
+                    )
+                }
+            })
+    })?;
+
+    Ok(NonNull::new(HigherHalfDirectMap::frame_to_page(frame).as_ptr()).unwrap())
+}
+
 pub fn copy_kernel_page_table() -> Result<Address<Frame>, pmm::Error> {
     let table_frame = PhysicalMemoryManager::next_frame()?;
     let table_ptr = core::ptr::with_exposed_provenance_mut(
@@ -0,0 +1,140 @@
+//! Memory affinity domains (NUMA nodes), used to prefer physical memory local to the requesting hardware thread.
+
+use crate::acpi::slit::Distances;
+use alloc::{collections::btree_map::BTreeMap, vec::Vec};
+use core::ops::Range;
+use libsys::page_size;
+
+/// A memory affinity domain.
+#[derive(Debug)]
+pub struct Node {
+    pub proximity_domain: u32,
+    /// Frame index ranges local to this node.
+    pub frames: Vec<Range<usize>>,
+    /// Indexes of every node (including this one), ordered from nearest to farthest.
+    pub fallback_order: Vec<usize>,
+}
+
+crate::singleton! {
+    pub NumaTopology {
+        nodes: Vec<Node>,
+        /// Index of the node local to each processor, keyed by APIC ID.
+        processor_nodes: BTreeMap<u32, usize>,
+    }
+
+    fn init() {
+        let affinities = crate::acpi::srat::affinities().unwrap_or_else(|error| {
+            debug!("NUMA topology is unavailable: {error}");
+
+            crate::acpi::srat::Affinities::default()
+        });
+
+        fn node_index(nodes: &mut Vec<Node>, proximity_domain: u32) -> usize {
+            nodes
+                .iter()
+                .position(|node| node.proximity_domain == proximity_domain)
+                .unwrap_or_else(|| {
+                    nodes.push(Node {
+                        proximity_domain,
+                        frames: Vec::new(),
+                        fallback_order: Vec::new(),
+                    });
+
+                    nodes.len() - 1
+                })
+        }
+
+        let mut nodes = Vec::<Node>::new();
+
+        for memory in &affinities.memory {
+            let (Ok(start), Ok(length)) =
+                (usize::try_from(memory.base_address), usize::try_from(memory.length))
+            else {
+                continue;
+            };
+
+            let index = node_index(&mut nodes, memory.proximity_domain);
+            nodes[index]
+                .frames
+                .push((start / page_size())..(start.saturating_add(length) / page_size()));
+        }
+
+        let processor_nodes = affinities
+            .processors
+            .iter()
+            .map(|processor| (processor.apic_id, node_index(&mut nodes, processor.proximity_domain)))
+            .collect::<BTreeMap<_, _>>();
+
+        let distances = crate::acpi::slit::distances()
+            .inspect_err(|error| debug!("NUMA distances are unavailable: {error}"))
+            .ok();
+
+        let proximity_domains = nodes.iter().map(|node| node.proximity_domain).collect::<Vec<_>>();
+        for node in &mut nodes {
+            let mut fallback_order = (0..proximity_domains.len()).collect::<Vec<_>>();
+
+            // Without a SLIT, every remote node is considered equidistant.
+            fallback_order.sort_by_key(|index| {
+                let proximity_domain = proximity_domains[*index];
+
+                if proximity_domain == node.proximity_domain {
+                    Distances::LOCAL
+                } else {
+                    distances
+                        .as_ref()
+                        .and_then(|distances| distances.distance(node.proximity_domain, proximity_domain))
+                        .unwrap_or(Distances::UNREACHABLE - 1)
+                }
+            });
+
+            node.fallback_order = fallback_order;
+        }
+
+        for node in &nodes {
+            debug!(
+                "NUMA node {}: {} frame range(s), fallback order {:?}",
+                node.proximity_domain,
+                node.frames.len(),
+                node.fallback_order
+            );
+        }
+
+        Self {
+            nodes,
+            processor_nodes,
+        }
+    }
+}
+
+impl NumaTopology {
+    pub fn nodes() -> &'static [Node] {
+        &Self::get_static().nodes
+    }
+
+    /// Index of the node local to the hardware thread with `apic_id`, if known.
+    pub fn node_of(apic_id: u32) -> Option<usize> {
+        Self::get_static().processor_nodes.get(&apic_id).copied()
+    }
+
+    /// Frame ranges to search for the hardware thread with `apic_id`, ordered from nearest to farthest.
+    ///
+    /// # Remarks
+    ///
+    /// Returns `None` if the topology is not initialized, or has no more than one node, as then there
+    /// is no locality to prefer.
+    pub fn preferred_frames(apic_id: u32) -> Option<impl Iterator<Item = Range<usize>>> {
+        if !Self::is_initialized() || Self::nodes().len() < 2 {
+            return None;
+        }
+
+        let nodes = Self::nodes();
+        let local_node = Self::node_of(apic_id)?;
+
+        Some(
+            nodes[local_node]
+                .fallback_order
+                .iter()
+                .flat_map(move |index| nodes[*index].frames.iter().cloned()),
+        )
+    }
+}
@@ -1,7 +1,12 @@
 use crate::mem::{HigherHalfDirectMap, pmm::PhysicalMemoryManager};
 use crate::util::{InteriorRef, Mut, Ref};
+use alloc::vec::Vec;
 use bit_field::BitField;
-use core::{fmt, iter::Step};
+use core::{
+    fmt,
+    iter::Step,
+    ops::{Range, RangeInclusive},
+};
 use libsys::{
     Address, Frame, Page, Virtual, page_shift, table_index_mask, table_index_shift,
     table_index_size,
@@ -28,6 +33,32 @@ pub fn use_giga_pages() -> bool {
     }
 }
 
+/// Whether the current environment is using 5-level paging (57-bit virtual addresses).
+///
+/// # Remarks
+///
+/// The paging mode can't be changed while paging is enabled, so this is whichever mode the bootloader handed over
+/// in; it's only 5-level if the bootloader was asked for it, and the hardware thread supports it.
+pub fn use_five_levels() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        crate::arch::x86_64::registers::control::CR4::read()
+            .contains(crate::arch::x86_64::registers::control::CR4Flags::LA57)
+    }
+}
+
+/// Width (in bits) of a canonical virtual address; addresses are sign-extended from the highest of these bits.
+pub fn virtual_address_bits() -> u32 {
+    libsys::page_size().trailing_zeros() + (table_index_shift().get() * TableDepth::max().get())
+}
+
+/// Sign-extends `address` from the highest implemented bit of a virtual address.
+fn canonicalize(address: usize) -> usize {
+    let shift = usize::BITS - virtual_address_bits();
+
+    ((address << shift).cast_signed() >> shift).cast_unsigned()
+}
+
 #[repr(transparent)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct TableDepth(u32);
@@ -45,19 +76,9 @@ impl TableDepth {
         Self(2)
     }
 
+    /// Depth of the root table, i.e. the number of paging levels.
     pub fn max() -> Self {
-        Self({
-            #[cfg(target_arch = "x86_64")]
-            {
-                use crate::arch::x86_64::registers::control::{CR4, CR4Flags};
-
-                if CR4::read().contains(CR4Flags::LA57) {
-                    5
-                } else {
-                    4
-                }
-            }
-        })
+        Self(if use_five_levels() { 5 } else { 4 })
     }
 
     pub fn min_align() -> usize {
@@ -156,8 +177,11 @@ bitflags! {
         const PRESENT = 1 << 0;
         const WRITABLE = 1 << 1;
         const USER = 1 << 2;
-        const WRITE_THROUGH = 1 << 3;
-        const UNCACHEABLE = 1 << 4;
+        /// Page-level write-through; with `PCD`, selects the page's memory type from the PAT (see
+        /// [`crate::arch::x86_64::pat`]).
+        const PWT = 1 << 3;
+        /// Page-level cache disable; see `PWT`.
+        const PCD = 1 << 4;
         const ACCESSED = 1 << 5;
         const DIRTY = 1 << 6;
         const HUGE = 1 << 7;
@@ -170,7 +194,13 @@ bitflags! {
         const RX = Self::PRESENT.bits();
         const PTE = Self::PRESENT.bits() | Self::WRITABLE.bits() | Self::USER.bits();
 
+        /// Write-combining memory type, for framebuffers.
+        const WRITE_COMBINING = Self::PWT.bits();
+        /// Uncacheable memory type, for memory-mapped IO.
+        const UNCACHEABLE = Self::PWT.bits() | Self::PCD.bits();
+
         const MMIO = Self::RW.bits() | Self::UNCACHEABLE.bits();
+        const FRAMEBUFFER = Self::RW.bits() | Self::WRITE_COMBINING.bits();
     }
 }
 
@@ -193,6 +223,7 @@ bitflags! {
         const PTE = Self::VALID.bits() | Self::READ.bits() | Self::WRITE.bits();
 
         const MMIO = Self::RW.bits();
+        const FRAMEBUFFER = Self::RW.bits();
     }
 }
 
@@ -389,6 +420,121 @@ impl<'a> PageTable<'a, Ref> {
     }
 }
 
+impl PageTable<'_, Ref> {
+    /// Walks down to the entry which maps `address`, returning the frame `address` falls within, the entry's
+    /// attributes, and the entry's depth (the mapping is `depth.align()` bytes, so it's a huge page if the depth isn't
+    /// the minimum).
+    pub fn translate(
+        &self,
+        address: Address<Virtual>,
+    ) -> Option<(Address<Frame>, TableEntryFlags, TableDepth)> {
+        let entry_depth = self.depth().next_checked()?;
+        let entry = self.entries().get(self.depth().index_of(address)?)?;
+
+        if !entry.is_present() {
+            None
+        } else if entry_depth.is_min() || entry.is_huge() {
+            let frame_offset = (address.get() & (entry_depth.align() - 1)) / libsys::page_size();
+            let frame = Address::from_index(entry.get_frame().index() + frame_offset)?;
+
+            Some((frame, entry.get_attributes(), entry_depth))
+        } else {
+            // Safety: The entry is present, and isn't a leaf, so it points to a page table of the next depth.
+            unsafe { PageTable::<Ref>::new(entry_depth, entry) }.translate(address)
+        }
+    }
+
+    /// Writes the entries at each depth which map `range`, one per line and indented by depth.
+    ///
+    /// Tables are descended into, leaves are written with the frame they map, and runs of non-present entries are
+    /// collapsed into a single line.
+    pub fn dump_range(
+        &self,
+        range: RangeInclusive<Address<Virtual>>,
+        out: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        self.dump_range_impl(0, range.start().get()..=range.end().get(), 1, out)
+    }
+
+    fn dump_range_impl(
+        &self,
+        table_start: usize,
+        range: RangeInclusive<usize>,
+        indent: usize,
+        out: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        let Some(entry_depth) = self.depth().next_checked() else {
+            return Ok(());
+        };
+
+        let entry_size = entry_depth.align();
+        let table_depth = self.depth().get();
+        let mut absent: Option<(usize, usize)> = None;
+        let flush_absent =
+            |absent: &mut Option<(usize, usize)>, out: &mut dyn fmt::Write| match absent.take() {
+                Some((first, last)) if first == last => {
+                    writeln!(
+                        out,
+                        "{:indent$}L{table_depth} [{first:>3}] not present",
+                        "",
+                        indent = indent * 2
+                    )
+                }
+                Some((first, last)) => writeln!(
+                    out,
+                    "{:indent$}L{table_depth} [{first:>3}..={last:>3}] not present",
+                    "",
+                    indent = indent * 2
+                ),
+                None => Ok(()),
+            };
+
+        for (index, entry) in self.entries().iter().enumerate() {
+            let entry_start = canonicalize(table_start.wrapping_add(index * entry_size));
+            let entry_end = entry_start + (entry_size - 1);
+            if entry_end < *range.start() || entry_start > *range.end() {
+                continue;
+            }
+
+            if !entry.is_present() {
+                absent = Some(absent.map_or((index, index), |(first, _)| (first, index)));
+                continue;
+            }
+
+            flush_absent(&mut absent, out)?;
+
+            let frame = entry.get_frame().get().get();
+            let attributes = entry.get_attributes();
+            if entry_depth.is_min() || entry.is_huge() {
+                writeln!(
+                    out,
+                    "{:indent$}L{table_depth} [{index:>3}] {entry_start:#X} -> {frame:#X} ({} KiB page) {attributes:?}",
+                    "",
+                    entry_size / 1024,
+                    indent = indent * 2
+                )?;
+            } else {
+                writeln!(
+                    out,
+                    "{:indent$}L{table_depth} [{index:>3}] {entry_start:#X} -> table {frame:#X} {attributes:?}",
+                    "",
+                    indent = indent * 2
+                )?;
+
+                // Safety: The entry is present, and isn't a leaf, so it points to a page table of the next depth.
+                unsafe { PageTable::<Ref>::new(entry_depth, entry) }.dump_range_impl(
+                    entry_start,
+                    range.clone(),
+                    indent + 1,
+                    out,
+                )?;
+            }
+        }
+
+        flush_absent(&mut absent, out)
+    }
+}
+
 impl<'a> PageTable<'a, Mut> {
     /// ## Safety
     ///
@@ -484,4 +630,103 @@ impl<'a> PageTable<'a, Mut> {
             Err(Error::HugePageEncountered)
         }
     }
+
+    /// Calls `func` with every present leaf entry (and its depth) whose mapping lies wholly within `range`, where
+    /// `table_start` is the first address this table maps. Huge pages which `range` only partly covers are split
+    /// into pages of the next depth first, and tables left with no present entries are removed, and pushed to
+    /// `freed_tables` (to be freed once no hardware thread can still be walking them).
+    ///
+    /// # Remarks
+    ///
+    /// Tables referenced by the root table are never removed, as other address spaces share the kernel's (see
+    /// [`crate::mem::copy_kernel_page_table`]).
+    pub fn for_each_leaf_in(
+        &mut self,
+        table_start: usize,
+        range: &Range<usize>,
+        freed_tables: &mut Vec<Address<Frame>>,
+        func: &mut impl FnMut(&mut PageTableEntry, TableDepth) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let Some(entry_depth) = self.depth().next_checked() else {
+            return Ok(());
+        };
+
+        let entry_size = entry_depth.align();
+        let is_root = self.depth().is_max();
+
+        for (index, entry) in self.entries_mut().iter_mut().enumerate() {
+            let entry_start = canonicalize(table_start.wrapping_add(index * entry_size));
+            let entry_last = entry_start + (entry_size - 1);
+            if entry_last < range.start || entry_start >= range.end || !entry.is_present() {
+                continue;
+            }
+
+            let is_leaf = entry_depth.is_min() || entry.is_huge();
+            if is_leaf && range.start <= entry_start && entry_last < range.end {
+                func(entry, entry_depth)?;
+
+                #[cfg(target_arch = "x86_64")]
+                crate::arch::x86_64::instructions::__invlpg(Address::new_truncate(entry_start));
+
+                continue;
+            }
+
+            if is_leaf {
+                split_huge(entry, entry_depth)?;
+
+                #[cfg(target_arch = "x86_64")]
+                crate::arch::x86_64::instructions::__invlpg(Address::new_truncate(entry_start));
+            }
+
+            let is_empty = {
+                // Safety: The entry is present, and isn't (or is no longer) a leaf, so it points to a page table of the
+                //         next depth.
+                let mut table = unsafe { PageTable::<Mut>::new(entry_depth, entry) };
+                table.for_each_leaf_in(entry_start, range, freed_tables, func)?;
+
+                table.entries().iter().all(|entry| !entry.is_present())
+            };
+
+            if is_empty && !is_root {
+                freed_tables.push(entry.get_frame());
+                *entry = PageTableEntry::empty();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Replaces the huge page mapped by `entry` (at `entry_depth`) with a table of pages of the next depth, which map the
+/// same frames with the same attributes.
+fn split_huge(entry: &mut PageTableEntry, entry_depth: TableDepth) -> Result<(), Error> {
+    let sub_depth = entry_depth.next();
+    let frames_per_entry = sub_depth.align() / libsys::page_size();
+
+    let mut attributes = entry.get_attributes();
+    if sub_depth.is_min() {
+        attributes.remove(TableEntryFlags::HUGE);
+    }
+
+    let table_frame = PhysicalMemoryManager::next_frame()?;
+    // Safety: The frame was just allocated, and is accessible through the HHDM.
+    let table = unsafe {
+        core::slice::from_raw_parts_mut(
+            core::ptr::with_exposed_provenance_mut::<PageTableEntry>(
+                HigherHalfDirectMap::frame_to_page(table_frame).get().get(),
+            ),
+            table_index_size(),
+        )
+    };
+
+    let first_frame = entry.get_frame().index();
+    for (index, sub_entry) in table.iter_mut().enumerate() {
+        let frame = Address::from_index(first_frame + (index * frames_per_entry)).unwrap();
+
+        *sub_entry = PageTableEntry::new(frame, attributes);
+    }
+
+    *entry = PageTableEntry::new(table_frame, TableEntryFlags::PTE);
+
+    Ok(())
 }
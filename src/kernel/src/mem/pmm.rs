@@ -1,8 +1,15 @@
-use crate::{interrupts::InterruptCell, mem::HigherHalfDirectMap};
+use crate::{
+    interrupts::InterruptCell,
+    mem::{HigherHalfDirectMap, numa::NumaTopology},
+    sync::SpinRwLock,
+    util::CachePadded,
+};
 use bitvec::slice::BitSlice;
-use core::{num::NonZero, sync::atomic::AtomicUsize};
+use core::{
+    num::NonZero,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 use libsys::{Address, Frame, align_up_div, page_mask, page_shift, page_size};
-use spin::RwLock;
 
 #[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
 pub enum Error {
@@ -20,14 +27,19 @@ pub enum Error {
 
     #[error("cannot free; frame not locked: {0:#X?}")]
     NotLocked(Address<Frame>),
+
+    #[error("frame is owned by a device: {0:#X?}")]
+    DmaTagged(Address<Frame>),
 }
 
-type FrameTable = RwLock<&'static mut BitSlice<AtomicUsize>>;
+type FrameTable = SpinRwLock<&'static mut BitSlice<AtomicUsize>>;
 
 crate::singleton! {
     pub PhysicalMemoryManager {
-        table: InterruptCell<FrameTable>,
+        table: CachePadded<InterruptCell<FrameTable>>,
         total_frames: usize,
+        /// Number of frames which are currently free; padded, as every allocation & free writes it.
+        free_frames: CachePadded<AtomicUsize>,
     }
 
     /// Initializes the static physical memory manager with the provided bootloader memory map request.
@@ -169,9 +181,13 @@ crate::singleton! {
                 prev_entry_range_end = Some(entry_range.end);
             });
 
+        let free_frames = table[..total_frames].count_zeros();
+        trace!("Free frames: {free_frames}");
+
         Self {
-            table: InterruptCell::new(spin::RwLock::new(table)),
+            table: CachePadded::new(InterruptCell::new(SpinRwLock::new(table))),
             total_frames,
+            free_frames: CachePadded::new(AtomicUsize::new(free_frames)),
         }
     }
 }
@@ -195,16 +211,46 @@ impl PhysicalMemoryManager {
         Self::total_frames() * libsys::page_size()
     }
 
+    /// Number of frames which are currently free.
+    pub fn free_frames() -> usize {
+        Self::get_static().free_frames.load(Ordering::Relaxed)
+    }
+
+    /// Accounts for `count` free frames having been locked.
+    fn count_locked(count: usize) {
+        Self::get_static()
+            .free_frames
+            .fetch_sub(count, Ordering::Relaxed);
+    }
+
     pub fn next_frame() -> Result<Address<Frame>, Error> {
         Self::with_table(|table| {
             let mut table = table.write();
-            let index = table.first_zero().ok_or(Error::NoneFree)?;
+
+            // Prefer frames local to this hardware thread, falling back to the nearest remote nodes, and
+            // finally to frames which belong to no node at all.
+            let index = NumaTopology::preferred_frames(crate::cpu::get_id())
+                .and_then(|mut ranges| {
+                    ranges.find_map(|range| {
+                        let start = range.start;
+                        let end = range.end.min(table.len());
+
+                        table
+                            .get(start..end)?
+                            .first_zero()
+                            .map(|index| start + index)
+                    })
+                })
+                .or_else(|| table.first_zero())
+                .ok_or(Error::NoneFree)?;
 
             // Safety: `index` is returned from a search function on `Self`.
             unsafe {
                 table.set_unchecked(index, true);
             }
 
+            Self::count_locked(1);
+
             trace!("Frame Locked: {:#X?}", index << page_shift().get());
 
             Ok(Address::new(index << page_shift().get()).unwrap())
@@ -235,6 +281,8 @@ impl PhysicalMemoryManager {
                 .unwrap();
             free_frames.fill(true);
 
+            Self::count_locked(count.get());
+
             trace!(
                 "Frames Locked: {:#X?}..{:#X?}",
                 free_frames_index,
@@ -245,7 +293,10 @@ impl PhysicalMemoryManager {
         })
     }
 
+    #[track_caller]
     pub fn lock_frame(address: Address<Frame>) -> Result<(), Error> {
+        super::dma::check(address, "lock", core::panic::Location::caller())?;
+
         Self::with_table(|table| {
             let table = table.read();
             let index = address.index();
@@ -260,6 +311,8 @@ impl PhysicalMemoryManager {
                         table.set_aliased_unchecked(index, true);
                     }
 
+                    Self::count_locked(1);
+
                     trace!("Frame Locked: {:#X?}", index << page_shift().get());
 
                     Ok(())
@@ -272,7 +325,10 @@ impl PhysicalMemoryManager {
         })
     }
 
+    #[track_caller]
     pub fn free_frame(address: Address<Frame>) -> Result<(), Error> {
+        super::dma::check(address, "free", core::panic::Location::caller())?;
+
         Self::with_table(|table| {
             let table = table.read();
             let index = address.index();
@@ -287,6 +343,10 @@ impl PhysicalMemoryManager {
                         table.set_aliased_unchecked(index, false);
                     }
 
+                    Self::get_static()
+                        .free_frames
+                        .fetch_add(1, Ordering::Relaxed);
+
                     trace!("Freed: {:#X?}", index << page_shift().get());
 
                     Ok(())
@@ -362,3 +422,20 @@ fn report_total_usable_memory(memory_map: &[&limine::memory_map::Entry]) {
         total_usable_memory / 1_000_000
     );
 }
+
+crate::kernel_test! {
+    /// Allocated frames are locked until they're freed.
+    fn allocation() -> crate::tests::Outcome {
+        let frame = PhysicalMemoryManager::next_frame().map_err(|_| "failed to allocate a frame")?;
+        if !PhysicalMemoryManager::is_locked(frame).map_err(|_| "allocated frame is untracked")? {
+            return Err("allocated frame is not locked");
+        }
+
+        PhysicalMemoryManager::free_frame(frame).map_err(|_| "failed to free the frame")?;
+        if PhysicalMemoryManager::is_locked(frame).map_err(|_| "freed frame is untracked")? {
+            return Err("freed frame is still locked");
+        }
+
+        Ok(())
+    }
+}
@@ -1,4 +1,21 @@
-use core::{mem::MaybeUninit, ptr::NonNull};
+use alloc::boxed::Box;
+use core::{alloc::AllocError, mem::MaybeUninit, ptr::NonNull};
+use spin::Once;
+use zerocopy::FromZeros;
+
+static CANARY: Once<u64> = Once::new();
+
+/// Value placed at the base of every kernel stack, and below every task's saved context, so overruns are caught
+/// before whatever they clobbered is used.
+///
+/// # Remarks
+///
+/// The canary is random for each boot, and (unlike an address-derived value) reveals nothing of where the kernel was
+/// loaded, so leaking it doesn't weaken KASLR. Its lowest byte is always zero, so it can't be reproduced by an overrun
+/// copying a string.
+pub fn canary() -> u64 {
+    *CANARY.call_once(|| crate::rand::prng::next_u64() & !0xFF)
+}
 
 /// A process or kernel stack, aligned to a 16-byte boundary.
 #[repr(C, align(0x10))]
@@ -6,6 +23,14 @@ use core::{mem::MaybeUninit, ptr::NonNull};
 pub struct Stack<const N: usize>([MaybeUninit<u8>; N]);
 
 impl<const N: usize> Stack<N> {
+    /// Allocates a zeroed stack, with its canary armed.
+    pub fn new_box() -> Result<Box<Self>, AllocError> {
+        let mut stack = Self::new_box_zeroed().map_err(|_| AllocError)?;
+        stack.arm_canary();
+
+        Ok(stack)
+    }
+
     /// The top of the stack (traditional grow-down 'stack pointer').
     pub fn top(&self) -> NonNull<MaybeUninit<u8>> {
         let ptr = self.0.as_ptr().cast_mut();
@@ -16,4 +41,45 @@ impl<const N: usize> Stack<N> {
         // Safety: `self` cannot be null.
         unsafe { NonNull::new_unchecked(top_ptr) }
     }
+
+    /// Writes the [`canary`] to the base of the stack, which is the last of it to be used.
+    pub fn arm_canary(&mut self) {
+        for (byte, canary) in self.0.iter_mut().zip(canary().to_ne_bytes()) {
+            *byte = MaybeUninit::new(canary);
+        }
+    }
+
+    /// Whether the canary at the base of the stack is intact, which it isn't if the stack has overflowed.
+    ///
+    /// # Remarks
+    ///
+    /// The stack must have been armed (see [`Self::arm_canary`]).
+    pub fn canary_intact(&self) -> bool {
+        // Safety: The base of the stack is 16-byte aligned, and was initialized when it was armed. The read is
+        //         volatile, as the stack is written without the compiler's knowledge.
+        let canary = unsafe { self.0.as_ptr().cast::<u64>().read_volatile() };
+
+        canary == self::canary()
+    }
+}
+
+crate::kernel_test! {
+    /// Armed stacks keep their canary until their base is written to.
+    fn detects_clobbered_canary() -> crate::tests::Outcome {
+        if canary() & 0xFF != 0 {
+            return Err("canary's lowest byte isn't zero");
+        }
+
+        let mut stack = Stack::<0x100>::new_box().map_err(|_| "failed to allocate a stack")?;
+        if !stack.canary_intact() {
+            return Err("freshly armed stack's canary isn't intact");
+        }
+
+        stack.0[1] = MaybeUninit::new(!canary().to_ne_bytes()[1]);
+        if stack.canary_intact() {
+            return Err("clobbered canary was reported intact");
+        }
+
+        Ok(())
+    }
 }
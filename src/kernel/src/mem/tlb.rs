@@ -0,0 +1,155 @@
+//! TLB shootdown, which invalidates the translations other hardware threads may have cached for page table entries
+//! that were removed or changed.
+//!
+//! Each hardware thread records the root page table it last loaded. When a range is unmapped or protected, every other
+//! hardware thread which could have cached its translations (those with the same root loaded, or all of them for the
+//! kernel's half, which every address space shares) is sent a [`Vector::TlbShootdown`] interrupt, and the initiator
+//! waits until each has invalidated the range. Frames the range mapped, and page tables left empty, mustn't be freed
+//! until then.
+//!
+//! One shootdown is in flight at a time. A hardware thread which spins on a [`SpinMutex`] (i.e. with interrupts
+//! disabled) services requests while it spins, so it can't deadlock with an initiator waiting on it while holding the
+//! lock.
+//!
+//! [`Vector::TlbShootdown`]: crate::interrupts::Vector::TlbShootdown
+
+use crate::{
+    cpu::percpu::{self, PerCpu},
+    mem::{PagingRegister, paging::virtual_address_bits},
+    sync::SpinMutex,
+};
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use libsys::{Address, Frame, page_size};
+
+/// Ranges of more pages than this are invalidated by flushing every translation, rather than page by page.
+const MAX_INVALIDATED_PAGES: usize = 32;
+
+/// Held by the initiator of the shootdown in flight.
+static INITIATOR: SpinMutex<()> = SpinMutex::new(());
+
+/// Address range of the shootdown in flight.
+static REQUEST_START: AtomicUsize = AtomicUsize::new(0);
+static REQUEST_END: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of hardware threads yet to invalidate the range of the shootdown in flight.
+static REMAINING: AtomicUsize = AtomicUsize::new(0);
+
+/// Begins accepting shootdowns on the current hardware thread.
+///
+/// # Remarks
+///
+/// Every hardware thread should call this before it enables interrupts. Until then shootdowns pass it over, so it
+/// flushes every translation it has cached as it joins.
+pub fn online() {
+    crate::percpu!(accepts_shootdowns).store(true, Ordering::SeqCst);
+
+    flush_all();
+}
+
+/// Records `root_frame` as the root page table the current hardware thread is about to load.
+pub fn set_active_root(root_frame: Address<Frame>) {
+    crate::percpu!(active_root).store(root_frame.get().get(), Ordering::SeqCst);
+}
+
+/// Invalidates the translations of `range` in the address space rooted at `root_frame` on every other hardware thread
+/// which could have cached them, and waits until each has.
+///
+/// # Remarks
+///
+/// The current hardware thread's translations aren't invalidated; callers invalidate them as they change each entry.
+pub fn shootdown(root_frame: Address<Frame>, range: &Range<usize>) {
+    if range.is_empty() {
+        return;
+    }
+
+    // The entries were changed before the targets are found, so a hardware thread which loads the root after it's
+    // passed over here only ever sees the changed entries.
+    core::sync::atomic::fence(Ordering::SeqCst);
+
+    let is_kernel_half = range.end > (1 << (virtual_address_bits() - 1));
+    let local_id = crate::cpu::get_id();
+    let targets = || {
+        percpu::blocks().filter(move |block| {
+            block.hwthread_id.load(Ordering::Relaxed) != local_id
+                && block.accepts_shootdowns.load(Ordering::SeqCst)
+                && (is_kernel_half
+                    || block.active_root.load(Ordering::SeqCst) == root_frame.get().get())
+        })
+    };
+
+    if targets().next().is_none() {
+        return;
+    }
+
+    let _initiator = INITIATOR.lock();
+
+    REQUEST_START.store(range.start, Ordering::Relaxed);
+    REQUEST_END.store(range.end, Ordering::Relaxed);
+
+    for block in targets() {
+        REMAINING.fetch_add(1, Ordering::SeqCst);
+        block.tlb_shootdown_pending.store(true, Ordering::SeqCst);
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            use crate::{
+                arch::x86_64::devices::local_apic::{
+                    LocalApic, interrupt_command::InterruptCommand,
+                },
+                interrupts::Vector,
+            };
+
+            let vector = core::num::NonZeroU8::new(u8::from(Vector::TlbShootdown)).unwrap();
+            LocalApic::send_interrupt_command(InterruptCommand::new_fixed(
+                vector,
+                block.hwthread_id.load(Ordering::Relaxed),
+            ));
+        }
+    }
+
+    while REMAINING.load(Ordering::Acquire) > 0 {
+        core::hint::spin_loop();
+    }
+}
+
+/// Invalidates the range of the shootdown in flight, if the current hardware thread has yet to, and acknowledges it.
+///
+/// # Remarks
+///
+/// This is called by the [`Vector::TlbShootdown`] handler, and while spinning with interrupts disabled.
+///
+/// [`Vector::TlbShootdown`]: crate::interrupts::Vector::TlbShootdown
+pub fn service() {
+    if !PerCpu::current()
+        .tlb_shootdown_pending
+        .swap(false, Ordering::AcqRel)
+    {
+        return;
+    }
+
+    let start = REQUEST_START.load(Ordering::Relaxed);
+    let end = REQUEST_END.load(Ordering::Relaxed);
+    let page_count = (end - start).div_ceil(page_size());
+
+    if page_count > MAX_INVALIDATED_PAGES {
+        flush_all();
+    } else {
+        for address in (start..end).step_by(page_size()) {
+            #[cfg(target_arch = "x86_64")]
+            crate::arch::x86_64::instructions::__invlpg(Address::new_truncate(address));
+        }
+    }
+
+    REMAINING.fetch_sub(1, Ordering::Release);
+}
+
+/// Flushes every translation the current hardware thread has cached.
+fn flush_all() {
+    // Safety: Reloading the active root changes no mappings; the kernel's aren't global, so it flushes them too.
+    unsafe {
+        PagingRegister::write(&PagingRegister::read());
+    }
+}
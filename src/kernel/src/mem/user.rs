@@ -0,0 +1,222 @@
+//! Copying to and from the active task's memory.
+//!
+//! With SMAP enabled, the kernel faults on any access to a user page unless `RFLAGS.AC` is set. [`UserAccess`] sets
+//! it for as long as it's held, and only the copies here hold one; so a stray dereference of a user pointer anywhere
+//! else in the kernel faults (and is reported as a SMAP violation), rather than silently trusting userspace.
+
+use crate::{
+    cpu::local_state::LocalState,
+    mem::paging::TableEntryFlags,
+    task::{AddressSpace, DEFAULT_USERSPACE_SIZE},
+};
+use core::marker::PhantomData;
+use libsys::{Address, Page, page_size};
+use zerocopy::{FromBytes, Immutable, IntoBytes};
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("range {0:#X}+{1:#X} is not within user space")]
+    NotUserSpace(usize, usize),
+
+    #[error("there's no active task")]
+    NoActiveTask,
+
+    #[error("failed to demand map memory: {0}")]
+    Unmapped(crate::task::Error),
+
+    #[error("page {0:X?} is not mapped for user {1:?} access")]
+    Inaccessible(Address<Page>, Access),
+}
+
+/// Kind of access the kernel makes to user memory, on the task's behalf.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+impl Access {
+    /// Flags every page of the range must be mapped with for the access to be made.
+    fn required_flags(self) -> TableEntryFlags {
+        match self {
+            Self::Read => TableEntryFlags::PRESENT | TableEntryFlags::USER,
+            Self::Write => {
+                TableEntryFlags::PRESENT | TableEntryFlags::USER | TableEntryFlags::WRITABLE
+            }
+        }
+    }
+}
+
+/// Whether supervisor-mode access prevention is enabled (see `configure_hwthread`).
+fn is_smap_enabled() -> bool {
+    crate::arch::x86_64::cpuid::extended_feature_info()
+        .is_some_and(raw_cpuid::ExtendedFeatures::has_smap)
+}
+
+/// Permits the kernel to access user pages until dropped.
+///
+/// # Remarks
+///
+/// The guard must be dropped on the hardware thread it was created on, so it isn't [`Send`].
+pub struct UserAccess(PhantomData<*const ()>);
+
+impl UserAccess {
+    pub fn begin() -> Self {
+        if is_smap_enabled() {
+            crate::arch::x86_64::instructions::__stac();
+        }
+
+        Self(PhantomData)
+    }
+}
+
+impl Drop for UserAccess {
+    fn drop(&mut self) {
+        if is_smap_enabled() {
+            crate::arch::x86_64::instructions::__clac();
+        }
+    }
+}
+
+/// Pages spanned by `[start, end)`.
+fn pages(start: usize, end: usize) -> impl Iterator<Item = Address<Page>> {
+    (start & !(page_size() - 1)..end)
+        .step_by(page_size())
+        .map(Address::new_truncate)
+}
+
+/// Checks every page of `[start, end)` is mapped in `address_space` with the flags `access` requires, so copying it
+/// can't fault in the kernel (i.e. by writing through a read-only user page).
+fn check_access(
+    address_space: &AddressSpace,
+    start: usize,
+    end: usize,
+    access: Access,
+) -> Result<(), Error> {
+    let required_flags = access.required_flags();
+
+    pages(start, end).try_for_each(|page| match address_space.get_flags(page) {
+        Ok(flags) if flags.contains(required_flags) => Ok(()),
+        _ => Err(Error::Inaccessible(page, access)),
+    })
+}
+
+/// Checks `[address, address + len)` is within user space, ensures every page of it is mapped for the active task,
+/// and checks each is mapped for `access`.
+fn demand_map_user_range(address: usize, len: usize, access: Access) -> Result<(), Error> {
+    let end = address
+        .checked_add(len)
+        .filter(|end| *end <= DEFAULT_USERSPACE_SIZE.get())
+        .ok_or(Error::NotUserSpace(address, len))?;
+
+    LocalState::with_scheduler(|scheduler| {
+        let task = scheduler.task_mut().ok_or(Error::NoActiveTask)?;
+        for page in pages(address, end) {
+            match task.demand_map(page.get()) {
+                Ok(()) | Err(crate::task::Error::AlreadyMapped) => {}
+                Err(err) => return Err(Error::Unmapped(err)),
+            }
+        }
+
+        // Kernel threads fail to demand map above, so the task has a process if the range is non-empty.
+        task.process().map_or(Ok(()), |process| {
+            process.with_image(|image| check_access(image.address_space(), address, end, access))
+        })
+    })
+}
+
+/// Copies `dest.len()` bytes from the active task's memory at `src` into `dest`.
+pub fn copy_from_user(dest: &mut [u8], src: usize) -> Result<(), Error> {
+    demand_map_user_range(src, dest.len(), Access::Read)?;
+
+    let _user_access = UserAccess::begin();
+
+    // Safety: The source range is within user space, and mapped readable for the active task, so it can't overlap
+    //         `dest`.
+    unsafe {
+        core::ptr::copy_nonoverlapping(
+            core::ptr::with_exposed_provenance::<u8>(src),
+            dest.as_mut_ptr(),
+            dest.len(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Copies `src` into the active task's memory at `dest`.
+pub fn copy_to_user(dest: usize, src: &[u8]) -> Result<(), Error> {
+    demand_map_user_range(dest, src.len(), Access::Write)?;
+
+    let _user_access = UserAccess::begin();
+
+    // Safety: The destination range is within user space, and mapped writable for the active task, so it can't
+    //         overlap `src`.
+    unsafe {
+        core::ptr::copy_nonoverlapping(
+            src.as_ptr(),
+            core::ptr::with_exposed_provenance_mut::<u8>(dest),
+            src.len(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Reads a `T` from the active task's memory at `src`.
+pub fn read_user<T: FromBytes + IntoBytes>(src: usize) -> Result<T, Error> {
+    let mut value = T::new_zeroed();
+    copy_from_user(value.as_mut_bytes(), src)?;
+
+    Ok(value)
+}
+
+/// Writes `value` to the active task's memory at `dest`.
+pub fn write_user<T: IntoBytes + Immutable>(dest: usize, value: &T) -> Result<(), Error> {
+    copy_to_user(dest, value.as_bytes())
+}
+
+crate::kernel_test! {
+    /// Ranges outside of user space are refused before anything is mapped or copied.
+    fn rejects_kernel_range() -> crate::tests::Outcome {
+        let value = 0u64;
+        let address = core::ptr::from_ref(&value).addr();
+
+        if write_user(address, &1u64) != Err(Error::NotUserSpace(address, size_of::<u64>())) {
+            return Err("copy to a kernel address was not refused");
+        }
+
+        if value != 0 {
+            return Err("kernel memory was written");
+        }
+
+        Ok(())
+    }
+}
+
+crate::kernel_test! {
+    /// Read-only user pages may be copied from, but copying to them is refused rather than faulting.
+    fn rejects_read_only_user_page() -> crate::tests::Outcome {
+        use crate::task::MmapPermissions;
+
+        let page = Address::<Page>::new_truncate(0x4000_0000);
+        let mut address_space = AddressSpace::new_userspace();
+        address_space
+            .mmap(Some(page), core::num::NonZeroUsize::MIN, MmapPermissions::ReadOnly)
+            .map_err(|_| "failed to map a read-only user page")?;
+
+        let start = page.get().get();
+        let end = start + page_size();
+        if check_access(&address_space, start, end, Access::Read).is_err() {
+            return Err("read from a read-only user page was refused");
+        }
+        if check_access(&address_space, start, end, Access::Write) != Err(Error::Inaccessible(page, Access::Write)) {
+            return Err("write to a read-only user page was not refused");
+        }
+        if check_access(&address_space, end, end + 1, Access::Read).is_ok() {
+            return Err("read from an unmapped user page was not refused");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+//! Address Resolution Protocol responder, which answers requests for the kernel's IPv4 address.
+//!
+//! The kernel never resolves addresses itself (every reply goes to the sender's hardware address), so it keeps no
+//! cache of the requests it sees.
+
+use super::{Ipv4Address, MacAddress, ethernet};
+use alloc::vec::Vec;
+
+/// Length of an ARP packet for IPv4 over Ethernet.
+const PACKET_LEN: usize = 28;
+
+const HARDWARE_ETHERNET: u16 = 1;
+
+const OPERATION_REQUEST: u16 = 1;
+const OPERATION_REPLY: u16 = 2;
+
+/// Reply to the ARP `packet` from the interface `mac`, if it's a request for `ip`.
+pub fn respond(mac: MacAddress, ip: Ipv4Address, packet: &[u8]) -> Option<Vec<u8>> {
+    let packet = packet.first_chunk::<PACKET_LEN>()?;
+
+    let hardware_kind = u16::from_be_bytes([packet[0], packet[1]]);
+    let protocol_kind = u16::from_be_bytes([packet[2], packet[3]]);
+    let operation = u16::from_be_bytes([packet[6], packet[7]]);
+    if hardware_kind != HARDWARE_ETHERNET
+        || protocol_kind != ethernet::ETHERTYPE_IPV4
+        || packet[4] != 6
+        || packet[5] != 4
+        || operation != OPERATION_REQUEST
+    {
+        return None;
+    }
+
+    let sender_mac = &packet[8..14];
+    let sender_ip = &packet[14..18];
+    let target_ip = &packet[24..28];
+    if target_ip != ip.0 {
+        return None;
+    }
+
+    let mut reply = Vec::with_capacity(PACKET_LEN);
+    reply.extend_from_slice(&packet[..6]);
+    reply.extend_from_slice(&OPERATION_REPLY.to_be_bytes());
+    reply.extend_from_slice(&mac.0);
+    reply.extend_from_slice(&ip.0);
+    reply.extend_from_slice(sender_mac);
+    reply.extend_from_slice(sender_ip);
+
+    Some(reply)
+}
+
+crate::kernel_test! {
+    /// Requests for the local address are answered with the interface's hardware address, and others are ignored.
+    fn answers_arp_requests() -> crate::tests::Outcome {
+        let mac = MacAddress([0x52, 0x54, 0, 0x12, 0x34, 0x56]);
+        let ip = Ipv4Address([10, 0, 2, 15]);
+
+        let mut request = alloc::vec![0, 1, 0x08, 0, 6, 4, 0, 1];
+        request.extend_from_slice(&[0x52, 0x55, 10, 0, 2, 2, 10, 0, 2, 2]);
+        request.extend_from_slice(&[0; 6]);
+        request.extend_from_slice(&ip.0);
+
+        let reply = respond(mac, ip, &request).ok_or("request wasn't answered")?;
+        if reply[6..8] != OPERATION_REPLY.to_be_bytes() || reply[8..14] != mac.0 || reply[14..18] != ip.0 {
+            return Err("reply doesn't carry the local addresses");
+        }
+        if reply[18..] != request[8..18] {
+            return Err("reply isn't addressed to the requester");
+        }
+
+        request[27] = 16;
+        if respond(mac, ip, &request).is_some() {
+            return Err("request for another address was answered");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+//! Ethernet II framing.
+
+use super::MacAddress;
+use alloc::vec::Vec;
+
+/// Length of the frame header: destination, source, and ethertype.
+pub const HEADER_LEN: usize = 14;
+
+/// Shortest frame, excluding the frame check sequence; shorter frames are padded.
+pub const MIN_FRAME_LEN: usize = 60;
+
+/// Longest frame with a standard MTU, excluding the frame check sequence.
+pub const MAX_FRAME_LEN: usize = 1514;
+
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_ARP: u16 = 0x0806;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub destination: MacAddress,
+    pub source: MacAddress,
+    pub ethertype: u16,
+}
+
+/// Splits `frame` into its header and payload.
+pub fn parse(frame: &[u8]) -> Option<(Header, &[u8])> {
+    let (header, payload) = frame.split_first_chunk::<HEADER_LEN>()?;
+
+    Some((
+        Header {
+            destination: MacAddress(header[..6].try_into().unwrap()),
+            source: MacAddress(header[6..12].try_into().unwrap()),
+            ethertype: u16::from_be_bytes([header[12], header[13]]),
+        },
+        payload,
+    ))
+}
+
+/// Frames `payload` from `source` to `destination`, padding it to [`MIN_FRAME_LEN`].
+pub fn build(
+    destination: MacAddress,
+    source: MacAddress,
+    ethertype: u16,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut frame = Vec::with_capacity((HEADER_LEN + payload.len()).max(MIN_FRAME_LEN));
+    frame.extend_from_slice(&destination.0);
+    frame.extend_from_slice(&source.0);
+    frame.extend_from_slice(&ethertype.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame.resize(frame.len().max(MIN_FRAME_LEN), 0);
+
+    frame
+}
@@ -0,0 +1,55 @@
+//! ICMP echo responder, which answers pings.
+
+use super::ipv4;
+use alloc::vec::Vec;
+
+/// Length of the message header: type, code, checksum, and the (type-specific) rest of the header.
+const HEADER_LEN: usize = 8;
+
+const TYPE_ECHO_REPLY: u8 = 0;
+const TYPE_ECHO_REQUEST: u8 = 8;
+
+/// Reply to the ICMP `message`, if it's an echo request.
+///
+/// The reply echoes the request's identifier, sequence number, and data.
+pub fn respond(message: &[u8]) -> Option<Vec<u8>> {
+    if message.len() < HEADER_LEN
+        || message[0] != TYPE_ECHO_REQUEST
+        || message[1] != 0
+        || ipv4::checksum(message) != 0
+    {
+        return None;
+    }
+
+    let mut reply = Vec::from(message);
+    reply[0] = TYPE_ECHO_REPLY;
+    reply[2..4].fill(0);
+    let reply_checksum = ipv4::checksum(&reply);
+    reply[2..4].copy_from_slice(&reply_checksum.to_be_bytes());
+
+    Some(reply)
+}
+
+crate::kernel_test! {
+    /// Echo requests are answered with a checksummed reply carrying the same data, and corrupt requests are ignored.
+    fn answers_echo_requests() -> crate::tests::Outcome {
+        let mut request = alloc::vec![TYPE_ECHO_REQUEST, 0, 0, 0, 0x12, 0x34, 0, 1, b'p', b'i', b'n', b'g', b'!'];
+        let request_checksum = ipv4::checksum(&request);
+        request[2..4].copy_from_slice(&request_checksum.to_be_bytes());
+
+        let reply = respond(&request).ok_or("echo request wasn't answered")?;
+        if reply[0] != TYPE_ECHO_REPLY || reply[4..] != request[4..] {
+            return Err("reply doesn't echo the request");
+        }
+        if ipv4::checksum(&reply) != 0 {
+            return Err("reply checksum is invalid");
+        }
+
+        request[8] ^= 0xFF;
+        if respond(&request).is_some() {
+            return Err("corrupt request was answered");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+//! IPv4, as far as delivering ICMP to the kernel and replying to it.
+//!
+//! Packets carrying options are accepted (the options are ignored), but fragments are dropped rather than reassembled.
+
+use super::{Ipv4Address, icmp};
+use alloc::vec::Vec;
+
+/// Length of a header without options.
+const HEADER_LEN: usize = 20;
+
+pub const PROTOCOL_ICMP: u8 = 1;
+
+/// Time-to-live of the packets the kernel sends.
+const DEFAULT_TTL: u8 = 64;
+
+/// Flags & fragment offset: don't fragment.
+const FLAG_DONT_FRAGMENT: u16 = 1 << 14;
+/// Flags & fragment offset: more fragments follow.
+const FLAG_MORE_FRAGMENTS: u16 = 1 << 13;
+const FRAGMENT_OFFSET_MASK: u16 = 0x1FFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub source: Ipv4Address,
+    pub destination: Ipv4Address,
+    pub protocol: u8,
+}
+
+/// Internet checksum (the ones' complement of the ones' complement sum of 16-bit words) of `data`.
+///
+/// Data which includes a valid checksum sums to zero.
+pub fn checksum(data: &[u8]) -> u16 {
+    let mut sum = data
+        .chunks(2)
+        .map(|word| {
+            u32::from(u16::from_be_bytes([
+                word[0],
+                word.get(1).copied().unwrap_or(0),
+            ]))
+        })
+        .sum::<u32>();
+    while (sum >> 16) != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+
+    !u16::try_from(sum).unwrap()
+}
+
+/// Splits `packet` into its header and payload, if it's a whole, unfragmented IPv4 packet with a valid header
+/// checksum.
+pub fn parse(packet: &[u8]) -> Option<(Header, &[u8])> {
+    let fixed = packet.first_chunk::<HEADER_LEN>()?;
+    if (fixed[0] >> 4) != 4 {
+        return None;
+    }
+
+    let header_len = usize::from(fixed[0] & 0xF) * 4;
+    let total_len = usize::from(u16::from_be_bytes([fixed[2], fixed[3]]));
+    if header_len < HEADER_LEN || total_len < header_len || total_len > packet.len() {
+        return None;
+    }
+
+    let fragment = u16::from_be_bytes([fixed[6], fixed[7]]);
+    if (fragment & (FLAG_MORE_FRAGMENTS | FRAGMENT_OFFSET_MASK)) != 0 {
+        trace!("Dropping IPv4 fragment.");
+        return None;
+    }
+
+    if checksum(&packet[..header_len]) != 0 {
+        return None;
+    }
+
+    Some((
+        Header {
+            source: Ipv4Address(fixed[12..16].try_into().unwrap()),
+            destination: Ipv4Address(fixed[16..20].try_into().unwrap()),
+            protocol: fixed[9],
+        },
+        // Frames may be padded past the end of the packet.
+        &packet[header_len..total_len],
+    ))
+}
+
+/// Builds a packet carrying `payload` of `protocol` from `source` to `destination`.
+pub fn build(
+    source: Ipv4Address,
+    destination: Ipv4Address,
+    protocol: u8,
+    payload: &[u8],
+) -> Vec<u8> {
+    let total_len = u16::try_from(HEADER_LEN + payload.len()).unwrap();
+
+    let mut packet = Vec::with_capacity(HEADER_LEN + payload.len());
+    // Version 4, with a header of five 32-bit words, and the default service class.
+    packet.extend_from_slice(&[0x45, 0]);
+    packet.extend_from_slice(&total_len.to_be_bytes());
+    // Identification is unused, as the packet can't be fragmented.
+    packet.extend_from_slice(&[0, 0]);
+    packet.extend_from_slice(&FLAG_DONT_FRAGMENT.to_be_bytes());
+    packet.extend_from_slice(&[DEFAULT_TTL, protocol, 0, 0]);
+    packet.extend_from_slice(&source.0);
+    packet.extend_from_slice(&destination.0);
+
+    let header_checksum = checksum(&packet);
+    packet[10..12].copy_from_slice(&header_checksum.to_be_bytes());
+    packet.extend_from_slice(payload);
+
+    packet
+}
+
+/// Reply to the IPv4 `packet`, if it's addressed to `ip` and carries a request the kernel answers.
+pub fn respond(ip: Ipv4Address, packet: &[u8]) -> Option<Vec<u8>> {
+    let (header, payload) = parse(packet)?;
+    if header.destination != ip {
+        return None;
+    }
+
+    match header.protocol {
+        PROTOCOL_ICMP => Some(build(
+            ip,
+            header.source,
+            PROTOCOL_ICMP,
+            &icmp::respond(payload)?,
+        )),
+
+        protocol => {
+            trace!("Dropping IPv4 packet of unhandled protocol {protocol}.");
+            None
+        }
+    }
+}
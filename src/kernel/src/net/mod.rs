@@ -0,0 +1,205 @@
+//! Minimal network stack: Ethernet framing, with responders for ARP and ICMP echo (over IPv4), so the kernel can be
+//! pinged.
+//!
+//! Network drivers [`attach`] each interface they bring up, then hand every frame it receives to [`receive`], which
+//! transmits any reply back through the same interface. Every interface answers for the single IPv4 address given by
+//! the `net-ip` parameter.
+//!
+//! # Remarks
+//!
+//! Frames are received from deferred work (see [`crate::task::workqueue`]), so nothing here may block.
+
+pub mod arp;
+pub mod ethernet;
+pub mod icmp;
+pub mod ipv4;
+
+use crate::sync::SpinMutex;
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use spin::Once;
+
+crate::parameters! {
+    /// IPv4 address the kernel's network interfaces answer for, in dotted-decimal form (QEMU's user networking assigns
+    /// the guest 10.0.2.15).
+    "net-ip": str = "10.0.2.15",
+}
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("frame of {0} bytes is longer than the interface can transmit")]
+    TooLong(usize),
+
+    #[error("interface's transmit queue is full")]
+    QueueFull,
+}
+
+/// A 48-bit Ethernet hardware address.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct MacAddress(pub [u8; 6]);
+
+impl MacAddress {
+    pub const BROADCAST: Self = Self([0xFF; 6]);
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+impl fmt::Debug for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// An IPv4 address.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Ipv4Address(pub [u8; 4]);
+
+impl Ipv4Address {
+    /// Parses an address in dotted-decimal form.
+    pub fn parse(address: &str) -> Option<Self> {
+        let mut octets = [0; 4];
+        let mut parts = address.split('.');
+        for octet in &mut octets {
+            *octet = parts.next()?.parse().ok()?;
+        }
+
+        parts.next().is_none().then_some(Self(octets))
+    }
+}
+
+impl fmt::Display for Ipv4Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d] = self.0;
+
+        write!(f, "{a}.{b}.{c}.{d}")
+    }
+}
+
+impl fmt::Debug for Ipv4Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// A network interface, which transmits Ethernet frames.
+pub trait Interface: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn mac(&self) -> MacAddress;
+
+    /// Transmits the Ethernet `frame`, without waiting for it to be sent.
+    fn transmit(&self, frame: &[u8]) -> Result<(), Error>;
+}
+
+static INTERFACES: SpinMutex<Vec<Arc<dyn Interface>>> = SpinMutex::new(Vec::new());
+
+/// Frames received, replies transmitted, and frames dropped (as they were malformed, or not for the kernel), across
+/// every interface.
+static RECEIVED: AtomicU64 = AtomicU64::new(0);
+static REPLIED: AtomicU64 = AtomicU64::new(0);
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// IPv4 address of the kernel's interfaces, or `None` if the `net-ip` parameter isn't a valid address.
+pub fn local_ip() -> Option<Ipv4Address> {
+    static LOCAL_IP: Once<Option<Ipv4Address>> = Once::new();
+
+    *LOCAL_IP.call_once(|| {
+        let address = crate::params::get::<&str>("net-ip");
+        let parsed = Ipv4Address::parse(address);
+        if parsed.is_none() {
+            warn!("Invalid `net-ip` address: {address:?}");
+        }
+
+        parsed
+    })
+}
+
+/// Attaches `interface` to the network stack.
+pub fn attach(interface: Arc<dyn Interface>) {
+    match local_ip() {
+        Some(ip) => info!(
+            "Attached network interface {} ({}) as {ip}.",
+            interface.name(),
+            interface.mac()
+        ),
+        None => info!(
+            "Attached network interface {} ({}) without an address.",
+            interface.name(),
+            interface.mac()
+        ),
+    }
+
+    crate::interrupts::uninterruptable(|| INTERFACES.lock().push(interface));
+}
+
+/// Every attached interface, in order of attachment.
+pub fn interfaces() -> Vec<Arc<dyn Interface>> {
+    crate::interrupts::uninterruptable(|| INTERFACES.lock().clone())
+}
+
+/// Frames received, replies transmitted, and frames dropped, since boot.
+pub fn stats() -> (u64, u64, u64) {
+    (
+        RECEIVED.load(Ordering::Relaxed),
+        REPLIED.load(Ordering::Relaxed),
+        DROPPED.load(Ordering::Relaxed),
+    )
+}
+
+/// Handles the Ethernet `frame` received by `interface`, transmitting any reply through it.
+pub fn receive(interface: &dyn Interface, frame: &[u8]) {
+    RECEIVED.fetch_add(1, Ordering::Relaxed);
+
+    let reply = respond(interface.mac(), frame);
+    match reply {
+        Some(reply) => match interface.transmit(&reply) {
+            Ok(()) => {
+                REPLIED.fetch_add(1, Ordering::Relaxed);
+            }
+
+            Err(error) => {
+                debug!("{} failed to transmit a reply: {error}", interface.name());
+            }
+        },
+
+        None => {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Reply to the Ethernet `frame` received by the interface `mac`, if it's a request the kernel answers.
+fn respond(mac: MacAddress, frame: &[u8]) -> Option<Vec<u8>> {
+    let (header, payload) = ethernet::parse(frame)?;
+    if header.destination != mac && header.destination != MacAddress::BROADCAST {
+        return None;
+    }
+
+    let ip = local_ip()?;
+    let reply = match header.ethertype {
+        ethernet::ETHERTYPE_ARP => arp::respond(mac, ip, payload)?,
+        ethernet::ETHERTYPE_IPV4 => ipv4::respond(ip, payload)?,
+
+        ethertype => {
+            trace!("Dropping frame of unhandled ethertype {ethertype:#06X}.");
+            return None;
+        }
+    };
+
+    // Replies go straight back to the sender, which is either on-link or the gateway that forwarded the request.
+    Some(ethernet::build(
+        header.source,
+        mac,
+        header.ethertype,
+        &reply,
+    ))
+}
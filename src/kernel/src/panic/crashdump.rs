@@ -0,0 +1,316 @@
+//! Crash dumps, written to a reserved physical memory region on panic, and reported at the next boot.
+//!
+//! The region is at a fixed physical address (see `--crashdump`), so it's found again after a warm reboot, which
+//! typically leaves memory contents intact. A dump is a [`Header`] followed by its text: the registers at the point
+//! of the panic, the panic message, the stack trace, and the tail of the log ring. Each boot reports any valid dump
+//! it finds, then invalidates it, so it isn't reported twice.
+
+use crate::mem::{HigherHalfDirectMap, pmm::PhysicalMemoryManager};
+use core::{fmt::Write, ptr::NonNull};
+use libsys::{Address, Frame, page_size};
+use spin::Once;
+
+/// Size (in bytes) of the crash dump region, including its header.
+const REGION_SIZE: usize = 64 * 1024;
+
+/// Identifies a crash dump: `LZCRASH` and a format version.
+const MAGIC: u64 = u64::from_le_bytes(*b"LZCRASH\x01");
+
+/// Longest a log record may be once formatted, used to estimate how many fit in the dump.
+const LOG_LINE_LEN: usize = crate::logging::ring::TEXT_LEN + 32;
+
+/// Stack frames captured into the dump.
+#[cfg(feature = "panic_traces")]
+const MAX_FRAMES: usize = 32;
+
+crate::parameters! {
+    /// Physical address of the 64 KiB crash dump region, which must be usable memory (0 disables crash dumps).
+    "crashdump": u64 = 0x100_0000,
+}
+
+#[repr(C)]
+struct Header {
+    magic: u64,
+    /// Length (in bytes) of the text following the header.
+    len: u64,
+    /// FNV-1a hash of the text, so a dump partially overwritten across the reboot is rejected.
+    checksum: u64,
+}
+
+const TEXT_CAPACITY: usize = REGION_SIZE - size_of::<Header>();
+
+/// Higher-half direct mapped address of the reserved region.
+static REGION: Once<usize> = Once::new();
+
+#[derive(Debug, Error)]
+enum Error {
+    #[error("{0:#X} is not page-aligned")]
+    Unaligned(usize),
+
+    #[error("{0:#X?} is not within usable memory")]
+    NotUsable(core::ops::Range<usize>),
+
+    #[error("frames of {0:#X?} are already in use")]
+    InUse(core::ops::Range<usize>),
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01B3)
+    })
+}
+
+/// Reserves the crash dump region, reporting (then invalidating) any crash dump left in it.
+///
+/// # Remarks
+///
+/// This must be called after the physical memory manager is initialized, but before anything else allocates frames,
+/// as the region is only reserved here.
+pub fn init(memory_map_request: &limine::request::MemoryMapRequest) {
+    let address = usize::try_from(crate::params::get::<u64>("crashdump")).unwrap();
+    if address == 0 {
+        return;
+    }
+
+    match reserve(memory_map_request, address) {
+        Ok(region) => {
+            report_previous(region);
+            REGION.call_once(|| region.as_ptr().expose_provenance());
+
+            debug!("Crash dump region reserved at {address:#X}.");
+        }
+
+        Err(error) => warn!("Crash dumps are disabled: {error}"),
+    }
+}
+
+fn reserve(
+    memory_map_request: &limine::request::MemoryMapRequest,
+    address: usize,
+) -> Result<NonNull<u8>, Error> {
+    if address % page_size() != 0 {
+        return Err(Error::Unaligned(address));
+    }
+
+    let region = address..(address + REGION_SIZE);
+
+    let is_usable = memory_map_request
+        .get_response()
+        .expect("no response to memory map request")
+        .entries()
+        .iter()
+        .filter(|entry| entry.entry_type == limine::memory_map::EntryType::USABLE)
+        .any(|entry| {
+            let entry_start = usize::try_from(entry.base).unwrap();
+            let entry_end = usize::try_from(entry.base + entry.length).unwrap();
+
+            entry_start <= region.start && region.end <= entry_end
+        });
+    if !is_usable {
+        return Err(Error::NotUsable(region));
+    }
+
+    // Frames already in use (i.e. by the frame table) may have overwritten a previous dump, so check them all before
+    // locking any.
+    let frames = || {
+        region
+            .clone()
+            .step_by(page_size())
+            .map(|address| Address::<Frame>::new(address).unwrap())
+    };
+    if frames().any(|frame| PhysicalMemoryManager::is_locked(frame) != Ok(false)) {
+        return Err(Error::InUse(region));
+    }
+
+    for frame in frames() {
+        PhysicalMemoryManager::lock_frame(frame).map_err(|_| Error::InUse(region.clone()))?;
+    }
+
+    Ok(NonNull::new(core::ptr::with_exposed_provenance_mut(
+        HigherHalfDirectMap::offset(address).get(),
+    ))
+    .unwrap())
+}
+
+fn report_previous(region: NonNull<u8>) {
+    let header = region.cast::<Header>();
+
+    // Safety: The region is reserved, mapped by the higher-half direct map, and any bit pattern is a valid header.
+    let (magic, len) = unsafe {
+        let header = header.as_ptr();
+        (
+            (*header).magic,
+            usize::try_from((*header).len).unwrap_or(usize::MAX),
+        )
+    };
+    if magic != MAGIC {
+        return;
+    }
+
+    if len > TEXT_CAPACITY {
+        warn!("Ignoring crash dump with invalid length: {len:#X}");
+    } else {
+        // Safety: The text is within the region, after the header.
+        let text =
+            unsafe { core::slice::from_raw_parts(region.as_ptr().add(size_of::<Header>()), len) };
+
+        // Safety: See above.
+        let checksum = unsafe { (*header.as_ptr()).checksum };
+
+        if fnv1a(text) == checksum {
+            warn!("Crash dump from the previous boot:");
+
+            // Truncation may have split a character, in which case the text ends before it.
+            let text = core::str::from_utf8(text).unwrap_or_else(|error| {
+                // Safety: Bytes up to `valid_up_to()` are valid UTF-8.
+                unsafe { core::str::from_utf8_unchecked(&text[..error.valid_up_to()]) }
+            });
+
+            for line in text.lines() {
+                warn!("| {line}");
+            }
+        } else {
+            warn!("Ignoring crash dump with mismatched checksum.");
+        }
+    }
+
+    // Safety: The region is reserved for crash dumps.
+    unsafe {
+        (*header.as_ptr()).magic = 0;
+    }
+}
+
+/// Writes into the text area of the region, silently truncating anything that doesn't fit.
+struct TextWriter<'a> {
+    text: &'a mut [u8],
+    len: usize,
+}
+
+impl TextWriter<'_> {
+    fn remaining(&self) -> usize {
+        self.text.len() - self.len
+    }
+}
+
+impl Write for TextWriter<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let remaining = &mut self.text[self.len..];
+        let copy_len = s.len().min(remaining.len());
+        remaining[..copy_len].copy_from_slice(&s.as_bytes()[..copy_len]);
+        self.len += copy_len;
+
+        Ok(())
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn write_registers(writer: &mut TextWriter) -> core::fmt::Result {
+    use crate::arch::x86_64::registers::{
+        RFlags,
+        control::{CR0, CR2, CR3, CR4},
+    };
+
+    let rip: u64;
+    let rsp: u64;
+    let rbp: u64;
+
+    // Safety: Only reads registers.
+    unsafe {
+        core::arch::asm!(
+            "lea {rip}, [rip]",
+            "mov {rsp}, rsp",
+            "mov {rbp}, rbp",
+            rip = out(reg) rip,
+            rsp = out(reg) rsp,
+            rbp = out(reg) rbp,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+
+    writeln!(writer, "rip={rip:#018X} rsp={rsp:#018X} rbp={rbp:#018X}")?;
+    writeln!(writer, "rflags={:?}", RFlags::read())?;
+    writeln!(writer, "cr0={:?}", CR0::read())?;
+    writeln!(writer, "cr2={:#018X}", CR2::read().get())?;
+    writeln!(writer, "cr3={:#X?}", CR3::read())?;
+    writeln!(writer, "cr4={:?}", CR4::read())
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn write_registers(writer: &mut TextWriter) -> core::fmt::Result {
+    writeln!(writer, "(registers are not captured on this architecture)")
+}
+
+fn write_text(
+    writer: &mut TextWriter,
+    hwthread_id: u32,
+    info: &core::panic::PanicInfo,
+) -> core::fmt::Result {
+    writeln!(
+        writer,
+        "KERNEL PANIC (hwthread #{hwthread_id} at {}): {}",
+        info.location().unwrap_or(core::panic::Location::caller()),
+        info.message()
+    )?;
+
+    writeln!(writer, "--- registers")?;
+    write_registers(writer)?;
+
+    #[cfg(feature = "panic_traces")]
+    {
+        writeln!(writer, "--- stack trace")?;
+
+        let mut frames = [Address::new_truncate(0); MAX_FRAMES];
+        let captured = super::tracing::capture(&mut frames);
+        for (depth, address) in frames[..captured].iter().enumerate() {
+            super::tracing::write_frame(&mut *writer, depth, *address)?;
+        }
+    }
+
+    writeln!(writer, "--- log")?;
+
+    // Write only as many of the most recent records as will fit, so the newest aren't truncated away.
+    let fits = u64::try_from(writer.remaining() / LOG_LINE_LEN).unwrap();
+    let from = crate::logging::ring::head().saturating_sub(fits);
+    for record in crate::logging::ring::records_from(from) {
+        writeln!(writer, "{record}")?;
+    }
+
+    Ok(())
+}
+
+/// Writes a crash dump for the panic described by `info`, if the crash dump region is reserved.
+///
+/// # Remarks
+///
+/// This should only be called by the panicking hardware thread, once every other hardware thread is halted.
+pub fn write(hwthread_id: u32, info: &core::panic::PanicInfo) {
+    let Some(region) = REGION
+        .get()
+        .copied()
+        .and_then(NonNull::<u8>::with_exposed_provenance)
+    else {
+        return;
+    };
+
+    let header = region.cast::<Header>();
+
+    // Safety: The region is reserved for crash dumps, and only the panicking hardware thread writes to it.
+    let text = unsafe {
+        (*header.as_ptr()).magic = 0;
+
+        core::slice::from_raw_parts_mut(region.as_ptr().add(size_of::<Header>()), TEXT_CAPACITY)
+    };
+
+    let mut writer = TextWriter { text, len: 0 };
+    write_text(&mut writer, hwthread_id, info).ok();
+
+    let len = writer.len;
+    let checksum = fnv1a(&writer.text[..len]);
+
+    // Safety: See above. The magic is written last, so a dump interrupted while being written is never reported.
+    unsafe {
+        (*header.as_ptr()).len = u64::try_from(len).unwrap();
+        (*header.as_ptr()).checksum = checksum;
+        core::ptr::write_volatile(&raw mut (*header.as_ptr()).magic, MAGIC);
+    }
+}
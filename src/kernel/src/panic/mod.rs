@@ -1,13 +1,55 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+
+pub mod crashdump;
 #[cfg(feature = "panic_traces")]
 pub mod tracing;
 
+/// Sentinel value for [`PANICKING_HWTHREAD`] when no hardware thread has panicked.
+const NO_PANICKING_HWTHREAD: u32 = u32::MAX;
+
+/// ID of the first hardware thread to panic, or [`NO_PANICKING_HWTHREAD`].
+static PANICKING_HWTHREAD: AtomicU32 = AtomicU32::new(NO_PANICKING_HWTHREAD);
+
+/// Whether any hardware thread in the system has panicked.
+pub fn is_panicking() -> bool {
+    PANICKING_HWTHREAD.load(Ordering::Acquire) != NO_PANICKING_HWTHREAD
+}
+
 /// # Remarks
 ///
 /// This function should *never* panic or abort.
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
+    // Avoid any further interrupts from scheduling over the panic context.
+    crate::interrupts::disable();
+
+    let hwthread_id = crate::cpu::get_id();
+
+    if let Err(panicking_hwthread_id) = PANICKING_HWTHREAD.compare_exchange(
+        NO_PANICKING_HWTHREAD,
+        hwthread_id,
+        Ordering::AcqRel,
+        Ordering::Acquire,
+    ) {
+        // Another hardware thread (or this one, recursively) is already handling a panic, so
+        // this hardware thread will bow out to avoid interleaving output with the original.
+        if panicking_hwthread_id == hwthread_id {
+            error!(
+                "KERNEL PANIC WHILE PANICKING (at {}): {}",
+                info.location().unwrap_or(core::panic::Location::caller()),
+                info.message()
+            );
+        }
+
+        crate::cpu::halt_and_catch_fire()
+    }
+
+    // Stop all other hardware threads before emitting anything, so they can't scribble
+    // over the state we're about to report on.
+    crate::cpu::halt_other_hwthreads();
+
     error!(
-        "KERNEL PANIC (at {}): {}",
+        "KERNEL PANIC (hwthread #{hwthread_id} at {}): {}",
         info.location().unwrap_or(core::panic::Location::caller()),
         info.message()
     );
@@ -15,5 +57,11 @@ fn panic(info: &core::panic::PanicInfo) -> ! {
     #[cfg(feature = "panic_traces")]
     tracing::emit_stack_trace();
 
+    crashdump::write(hwthread_id, info);
+
+    if crate::qemu_exit::is_test_mode() {
+        crate::qemu_exit::exit(crate::qemu_exit::ExitCode::Failure);
+    }
+
     crate::cpu::halt_and_catch_fire()
 }
@@ -0,0 +1,108 @@
+//! Reading of the primitive encodings shared by DWARF sections.
+
+/// Cursor over a section's bytes. Every read returns `None` once the bytes run out.
+#[derive(Clone)]
+pub struct Reader {
+    pub bytes: &'static [u8],
+}
+
+impl Reader {
+    pub fn take(&mut self, len: usize) -> Option<&'static [u8]> {
+        if len > self.bytes.len() {
+            return None;
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Some(taken)
+    }
+
+    pub fn skip(&mut self, len: usize) -> Option<()> {
+        self.take(len).map(|_| ())
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        self.take(2)?.try_into().ok().map(u16::from_le_bytes)
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        self.take(4)?.try_into().ok().map(u32::from_le_bytes)
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        self.take(8)?.try_into().ok().map(u64::from_le_bytes)
+    }
+
+    /// Reads a `len`-byte little-endian unsigned integer.
+    pub fn uint(&mut self, len: usize) -> Option<u64> {
+        let bytes = self.take(len)?;
+        if len > 8 {
+            return None;
+        }
+
+        let mut buffer = [0; 8];
+        buffer[..len].copy_from_slice(bytes);
+
+        Some(u64::from_le_bytes(buffer))
+    }
+
+    pub fn uleb128(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        let mut shift = 0u32;
+
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= u64::from(byte & 0x7F) << shift;
+            }
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    }
+
+    pub fn sleb128(&mut self) -> Option<i64> {
+        let mut value = 0i64;
+        let mut shift = 0u32;
+
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= i64::from(byte & 0x7F) << shift;
+            }
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                if shift < 64 && (byte & 0x40) != 0 {
+                    value |= -1i64 << shift;
+                }
+
+                return Some(value);
+            }
+        }
+    }
+
+    pub fn usize_uleb128(&mut self) -> Option<usize> {
+        usize::try_from(self.uleb128()?).ok()
+    }
+
+    /// Reads a section offset, which is 4 or 8 bytes wide depending on the unit's format.
+    pub fn offset(&mut self, offset_size: usize) -> Option<usize> {
+        usize::try_from(self.uint(offset_size)?).ok()
+    }
+
+    pub fn cstr(&mut self) -> Option<&'static str> {
+        let len = self.bytes.iter().position(|&byte| byte == 0)?;
+        let string = self.take(len)?;
+        self.skip(1)?;
+
+        core::str::from_utf8(string).ok()
+    }
+}
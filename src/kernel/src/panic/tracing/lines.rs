@@ -0,0 +1,409 @@
+//! Address to source line resolution, by interpreting the DWARF line number programs in `.debug_line`.
+//!
+//! Nothing is indexed ahead of time (the kernel heap may not exist yet, or may be what panicked), so every lookup
+//! runs each line number program until a row covering the address is found. Versions 2 through 5 are supported,
+//! though only for little-endian targets. Paths may be relative to the compilation directory, which is recorded only
+//! in `.debug_info`, so isn't resolved.
+
+use super::dwarf::Reader;
+
+/// Sections holding the line number programs, and the strings they refer to.
+#[derive(Debug, Clone, Copy)]
+pub struct Sections {
+    pub debug_line: &'static [u8],
+    pub debug_line_str: &'static [u8],
+    pub debug_str: &'static [u8],
+}
+
+/// Source location of an instruction.
+#[derive(Debug, Clone, Copy)]
+pub struct Location {
+    pub directory: Option<&'static str>,
+    pub file: &'static str,
+    pub line: u64,
+}
+
+impl core::fmt::Display for Location {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.directory {
+            Some(directory) if !self.file.starts_with('/') => {
+                write!(f, "{directory}/{}:{}", self.file, self.line)
+            }
+
+            _ => write!(f, "{}:{}", self.file, self.line),
+        }
+    }
+}
+
+const DW_LNS_COPY: u8 = 0x01;
+const DW_LNS_ADVANCE_PC: u8 = 0x02;
+const DW_LNS_ADVANCE_LINE: u8 = 0x03;
+const DW_LNS_SET_FILE: u8 = 0x04;
+const DW_LNS_CONST_ADD_PC: u8 = 0x08;
+const DW_LNS_FIXED_ADVANCE_PC: u8 = 0x09;
+
+const DW_LNE_END_SEQUENCE: u8 = 0x01;
+const DW_LNE_SET_ADDRESS: u8 = 0x02;
+
+const DW_LNCT_PATH: u64 = 0x1;
+const DW_LNCT_DIRECTORY_INDEX: u64 = 0x2;
+
+const DW_FORM_BLOCK: u64 = 0x09;
+const DW_FORM_DATA1: u64 = 0x0B;
+const DW_FORM_DATA2: u64 = 0x05;
+const DW_FORM_DATA4: u64 = 0x06;
+const DW_FORM_DATA8: u64 = 0x07;
+const DW_FORM_DATA16: u64 = 0x1E;
+const DW_FORM_STRING: u64 = 0x08;
+const DW_FORM_STRP: u64 = 0x0E;
+const DW_FORM_UDATA: u64 = 0x0F;
+const DW_FORM_LINE_STRP: u64 = 0x1F;
+
+/// Reads the NUL-terminated string at `offset` in `section`.
+fn str_at(section: &'static [u8], offset: usize) -> Option<&'static str> {
+    Reader {
+        bytes: section.get(offset..)?,
+    }
+    .cstr()
+}
+
+/// Header of a line number program, referring to (rather than copying) its directory and file tables.
+struct Header {
+    version: u16,
+    offset_size: usize,
+    min_instruction_length: u8,
+    line_base: i8,
+    line_range: u8,
+    opcode_base: u8,
+    standard_opcode_lengths: &'static [u8],
+    /// Bytes from the start of the directory table to the start of the program.
+    tables: &'static [u8],
+}
+
+/// An entry of a version 5 directory or file table, as much as is needed.
+#[derive(Default)]
+struct Entry {
+    path: Option<&'static str>,
+    directory_index: u64,
+}
+
+impl Header {
+    fn parse(unit: &mut Reader, offset_size: usize) -> Option<Self> {
+        let version = unit.u16()?;
+        if !(2..=5).contains(&version) {
+            return None;
+        }
+
+        if version >= 5 {
+            let _address_size = unit.u8()?;
+            let _segment_selector_size = unit.u8()?;
+        }
+
+        let header_length = unit.offset(offset_size)?;
+        let mut header = Reader {
+            bytes: unit.take(header_length)?,
+        };
+
+        let min_instruction_length = header.u8()?;
+        if version >= 4 {
+            let _max_ops_per_instruction = header.u8()?;
+        }
+        let _default_is_stmt = header.u8()?;
+        let line_base = i8::from_le_bytes([header.u8()?]);
+        let line_range = header.u8()?;
+        let opcode_base = header.u8()?;
+        let standard_opcode_lengths = header.take(usize::from(opcode_base.checked_sub(1)?))?;
+
+        if line_range == 0 {
+            return None;
+        }
+
+        Some(Self {
+            version,
+            offset_size,
+            min_instruction_length,
+            line_base,
+            line_range,
+            opcode_base,
+            standard_opcode_lengths,
+            tables: header.bytes,
+        })
+    }
+
+    /// Resolves file `index` of this program into a location (with a placeholder line).
+    fn file(&self, sections: &Sections, index: u64) -> Option<Location> {
+        let mut tables = Reader { bytes: self.tables };
+        let mut directories = tables.clone();
+
+        if self.version >= 5 {
+            self.v5_table(sections, &mut tables, 0)?;
+            let file = self.v5_table(sections, &mut tables, index)??;
+            let directory = self
+                .v5_table(sections, &mut directories, file.directory_index)
+                .flatten()
+                .and_then(|directory| directory.path);
+
+            Some(Location {
+                directory,
+                file: file.path?,
+                line: 0,
+            })
+        } else {
+            while !tables.cstr()?.is_empty() {}
+
+            let (file, directory_index) = core::iter::from_fn(|| {
+                let name = tables.cstr()?;
+                let directory_index = tables.usize_uleb128()?;
+                let _modification_time = tables.uleb128()?;
+                let _length = tables.uleb128()?;
+
+                Some((name, directory_index))
+            })
+            .take_while(|(name, _)| !name.is_empty())
+            .nth(usize::try_from(index.checked_sub(1)?).ok()?)?;
+
+            // Directory 0 is the compilation directory, which is only recorded in `.debug_info`.
+            let directory = directory_index.checked_sub(1).and_then(|directory_index| {
+                core::iter::from_fn(|| directories.cstr())
+                    .take_while(|directory| !directory.is_empty())
+                    .nth(directory_index)
+            });
+
+            Some(Location {
+                directory,
+                file,
+                line: 0,
+            })
+        }
+    }
+
+    /// Reads a version 5 directory or file table, returning its entry `index` (if it has one).
+    ///
+    /// Returns `None` if the table is malformed, as then whatever follows it can't be found.
+    fn v5_table(
+        &self,
+        sections: &Sections,
+        tables: &mut Reader,
+        index: u64,
+    ) -> Option<Option<Entry>> {
+        let format_count = tables.u8()?;
+        let format = tables.clone();
+        for _ in 0..format_count {
+            tables.uleb128()?;
+            tables.uleb128()?;
+        }
+
+        let count = tables.uleb128()?;
+        let mut found = None;
+
+        for current in 0..count {
+            let mut entry = Entry::default();
+            let mut format = format.clone();
+
+            for _ in 0..format_count {
+                let content_type = format.uleb128()?;
+                let form = format.uleb128()?;
+
+                match (content_type, form) {
+                    (DW_LNCT_PATH, DW_FORM_STRING) => entry.path = tables.cstr(),
+                    (DW_LNCT_PATH, DW_FORM_LINE_STRP) => {
+                        entry.path =
+                            str_at(sections.debug_line_str, tables.offset(self.offset_size)?);
+                    }
+                    (DW_LNCT_PATH, DW_FORM_STRP) => {
+                        entry.path = str_at(sections.debug_str, tables.offset(self.offset_size)?);
+                    }
+                    (DW_LNCT_DIRECTORY_INDEX, DW_FORM_UDATA) => {
+                        entry.directory_index = tables.uleb128()?
+                    }
+                    (DW_LNCT_DIRECTORY_INDEX, DW_FORM_DATA1) => {
+                        entry.directory_index = tables.uint(1)?
+                    }
+                    (DW_LNCT_DIRECTORY_INDEX, DW_FORM_DATA2) => {
+                        entry.directory_index = tables.uint(2)?
+                    }
+                    (_, form) => skip_form(tables, form, self.offset_size)?,
+                }
+            }
+
+            if current == index {
+                found = Some(entry);
+            }
+        }
+
+        Some(found)
+    }
+}
+
+/// Skips an attribute value of `form`, failing for any form which can't appear in a line table header.
+fn skip_form(reader: &mut Reader, form: u64, offset_size: usize) -> Option<()> {
+    match form {
+        DW_FORM_STRING => reader.cstr().map(|_| ()),
+        DW_FORM_STRP | DW_FORM_LINE_STRP => reader.skip(offset_size),
+        DW_FORM_UDATA => reader.uleb128().map(|_| ()),
+        DW_FORM_DATA1 => reader.skip(1),
+        DW_FORM_DATA2 => reader.skip(2),
+        DW_FORM_DATA4 => reader.skip(4),
+        DW_FORM_DATA8 => reader.skip(8),
+        DW_FORM_DATA16 => reader.skip(16),
+        DW_FORM_BLOCK => {
+            let len = reader.usize_uleb128()?;
+            reader.skip(len)
+        }
+
+        _ => None,
+    }
+}
+
+/// Row of the line number matrix; only the columns needed for lookups are tracked.
+#[derive(Clone, Copy)]
+struct Row {
+    address: u64,
+    file: u64,
+    line: u64,
+}
+
+/// Runs the line number program of `header`, returning the row covering `address`, if any.
+fn run_program(header: &Header, program: &mut Reader, address: u64) -> Option<Row> {
+    let initial = Row {
+        address: 0,
+        file: 1,
+        line: 1,
+    };
+    let min_instruction_length = u64::from(header.min_instruction_length);
+
+    let mut row = initial;
+    let mut previous: Option<Row> = None;
+
+    // Rows of a sequence are ordered by address, so the row covering `address` is the last one at or before it.
+    let emit = |row: Row, previous: &mut Option<Row>| -> Option<Row> {
+        let covering =
+            previous.filter(|previous| (previous.address..row.address).contains(&address));
+        *previous = Some(row);
+
+        covering
+    };
+
+    while !program.bytes.is_empty() {
+        let opcode = program.u8()?;
+
+        if opcode >= header.opcode_base {
+            let adjusted = opcode - header.opcode_base;
+            let operation_advance = u64::from(adjusted / header.line_range);
+            let line_advance =
+                i64::from(header.line_base) + i64::from(adjusted % header.line_range);
+
+            row.address = row
+                .address
+                .wrapping_add(operation_advance * min_instruction_length);
+            row.line = row.line.wrapping_add_signed(line_advance);
+
+            if let Some(covering) = emit(row, &mut previous) {
+                return Some(covering);
+            }
+
+            continue;
+        }
+
+        match opcode {
+            0 => {
+                let len = program.usize_uleb128()?;
+                let mut instruction = Reader {
+                    bytes: program.take(len)?,
+                };
+
+                match instruction.u8()? {
+                    DW_LNE_END_SEQUENCE => {
+                        if let Some(covering) = emit(row, &mut previous) {
+                            return Some(covering);
+                        }
+
+                        row = initial;
+                        previous = None;
+                    }
+
+                    DW_LNE_SET_ADDRESS => {
+                        row.address = instruction.uint(instruction.bytes.len())?;
+                    }
+
+                    // Remaining extended opcodes (`define_file`, `set_discriminator`, or vendor) don't affect lookups.
+                    _ => {}
+                }
+            }
+
+            DW_LNS_COPY => {
+                if let Some(covering) = emit(row, &mut previous) {
+                    return Some(covering);
+                }
+            }
+
+            DW_LNS_ADVANCE_PC => {
+                let operation_advance = program.uleb128()?;
+                row.address = row
+                    .address
+                    .wrapping_add(operation_advance.wrapping_mul(min_instruction_length));
+            }
+
+            DW_LNS_ADVANCE_LINE => {
+                row.line = row.line.wrapping_add_signed(program.sleb128()?);
+            }
+
+            DW_LNS_SET_FILE => {
+                row.file = program.uleb128()?;
+            }
+
+            DW_LNS_CONST_ADD_PC => {
+                let adjusted = 255 - header.opcode_base;
+                let operation_advance = u64::from(adjusted / header.line_range);
+                row.address = row
+                    .address
+                    .wrapping_add(operation_advance * min_instruction_length);
+            }
+
+            DW_LNS_FIXED_ADVANCE_PC => {
+                row.address = row.address.wrapping_add(u64::from(program.u16()?));
+            }
+
+            // Any other standard opcode only affects columns which aren't tracked, so skip its operands.
+            opcode => {
+                let operand_count = header.standard_opcode_lengths[usize::from(opcode - 1)];
+                for _ in 0..operand_count {
+                    program.uleb128()?;
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Finds the source location of the instruction at `address`.
+pub fn find(sections: &Sections, address: u64) -> Option<Location> {
+    let mut units = Reader {
+        bytes: sections.debug_line,
+    };
+
+    while !units.bytes.is_empty() {
+        let (unit_length, offset_size) = match units.u32()? {
+            0xFFFF_FFFF => (usize::try_from(units.u64()?).ok()?, 8),
+            unit_length => (usize::try_from(unit_length).ok()?, 4),
+        };
+        let mut unit = Reader {
+            bytes: units.take(unit_length)?,
+        };
+
+        // A malformed unit can't be interpreted, but the next may still be fine.
+        let Some(header) = Header::parse(&mut unit, offset_size) else {
+            continue;
+        };
+
+        if let Some(row) = run_program(&header, &mut unit, address) {
+            return header.file(sections, row.file).map(|location| Location {
+                line: row.line,
+                ..location
+            });
+        }
+    }
+
+    None
+}
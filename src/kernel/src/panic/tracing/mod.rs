@@ -1,14 +1,16 @@
-use core::{
-    fmt::{Result, Write},
-    ptr::NonNull,
-};
+use crate::interrupts::exceptions::triage::{AddressRegion, classify};
+use core::fmt::{Result, Write};
 use heapless::String;
 use libsys::{Address, Virtual};
 use spin::Mutex;
 
+mod dwarf;
+mod lines;
 pub mod symbols;
+mod unwind;
 
 pub(super) fn emit_stack_trace() {
+    // A plain spin lock (see `crate::sync`), as it's taken while panicking.
     static PANIC_BUFFER: Mutex<String<0x4000>> = Mutex::new(String::new());
 
     let mut panic_buffer = PANIC_BUFFER.lock();
@@ -18,60 +20,99 @@ pub(super) fn emit_stack_trace() {
     }
 }
 
-#[repr(C)]
-#[derive(Debug)]
-struct StackFrame {
-    prev_frame_ptr: Option<NonNull<StackFrame>>,
-    return_address: Address<Virtual>,
+/// Reads the word at `address`, if it's aligned and within the kernel's address space.
+fn read_stack(address: usize) -> Option<usize> {
+    let is_readable = address.is_multiple_of(align_of::<usize>())
+        && matches!(
+            classify(address),
+            AddressRegion::HigherHalfDirectMap
+                | AddressRegion::KernelData
+                | AddressRegion::KernelSpace
+        );
+
+    // Safety: Address is aligned, and within the kernel's address space.
+    is_readable
+        .then(|| unsafe { core::ptr::with_exposed_provenance::<usize>(address).read_volatile() })
 }
 
+/// Walks the call stack, yielding each frame's return address.
+///
+/// Frames are unwound by following the frame pointer chain. Where it terminates early (i.e. a frame was built
+/// without frame pointers, or by an assembly stub), the call frame information in the kernel file is used instead.
 struct StackTracer {
-    frame_ptr: Option<NonNull<StackFrame>>,
+    registers: Option<unwind::Registers>,
+    /// Whether the instruction pointer is a return address, rather than the address of the instruction executing.
+    is_return_address: bool,
 }
 
 impl StackTracer {
     /// # Safety
     ///
-    /// The provided frame pointer must point to a valid call stack frame.
-    const unsafe fn new(frame_ptr: NonNull<StackFrame>) -> Self {
+    /// The provided registers must be those of a frame on the current call stack.
+    const unsafe fn new(registers: unwind::Registers) -> Self {
         Self {
-            frame_ptr: Some(frame_ptr),
+            registers: Some(registers),
+            is_return_address: false,
         }
     }
+
+    /// Unwinds a frame using its frame pointer, which points to the caller's frame pointer, then the return address.
+    fn step_frame_pointer(registers: unwind::Registers) -> Option<unwind::Registers> {
+        let fp = read_stack(registers.fp)?;
+        let ip = read_stack(registers.fp + size_of::<usize>())?;
+
+        (ip != 0).then_some(unwind::Registers {
+            ip,
+            sp: registers.fp + (2 * size_of::<usize>()),
+            fp,
+        })
+    }
+
+    fn step_call_frame_info(
+        registers: unwind::Registers,
+        is_return_address: bool,
+    ) -> Option<unwind::Registers> {
+        let sections = symbols::Symbols::call_frame_info()?;
+
+        unwind::step(sections, registers, is_return_address, read_stack)
+    }
 }
 
 impl Iterator for StackTracer {
     type Item = Address<Virtual>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // Safety: Stack frame pointer will be valid if the correct value is provided to `Self::new()`.
-        let stack_frame = unsafe { self.frame_ptr?.as_ref() };
-        self.frame_ptr = stack_frame.prev_frame_ptr;
+        let registers = self.registers.take()?;
+        let caller = Self::step_frame_pointer(registers)
+            .or_else(|| Self::step_call_frame_info(registers, self.is_return_address))?;
+
+        // The stack only grows downwards, so a caller's frame not above its callee's is a corrupt (or terminating)
+        // chain; its return address is still yielded, but nothing beyond it.
+        if caller.sp > registers.sp {
+            self.registers = Some(caller);
+            self.is_return_address = true;
+        }
 
-        Some(stack_frame.return_address)
+        Address::new(caller.ip)
     }
 }
 
+/// Captures the return addresses of the current call stack into `frames`, returning how many were captured.
 #[inline(always)]
-fn get_stack_frame_ptr() -> *mut StackFrame {
-    #[cfg(target_arch = "x86_64")]
-    {
-        let base_ptr: usize;
-
-        // Safety: We're just reading a register.
-        unsafe {
-            core::arch::asm!(
-                "mov {}, rbp",
-                out(reg) base_ptr,
-                options(nostack, nomem, preserves_flags)
-            );
-        }
-
-        core::ptr::without_provenance_mut::<StackFrame>(base_ptr)
-    }
+pub fn capture(frames: &mut [Address<Virtual>]) -> usize {
+    // Safety: Registers are read directly from the current frame.
+    (unsafe { StackTracer::new(get_registers()) })
+        .zip(frames.iter_mut())
+        .map(|(trace_address, frame)| *frame = trace_address)
+        .count()
 }
 
-fn construct_panic_message(mut buffer: impl Write) -> Result {
+/// Writes the address of a stack frame, along with its symbol name and source location (if known).
+pub fn write_frame(
+    mut buffer: impl Write,
+    depth: usize,
+    trace_address: Address<Virtual>,
+) -> Result {
     fn print_stack_trace_entry<D: core::fmt::Display>(
         mut buffer: impl Write,
         entry_num: usize,
@@ -85,40 +126,66 @@ fn construct_panic_message(mut buffer: impl Write) -> Result {
         )
     }
 
-    let Some(frame_ptr) = NonNull::new(get_stack_frame_ptr()) else {
-        writeln!(
+    if symbols::Symbols::is_initialized()
+        && let Some(symbol_name) = symbols::Symbols::get_name(trace_address)
+    {
+        if let Ok(demangled) = rustc_demangle::try_demangle(symbol_name) {
+            print_stack_trace_entry(&mut buffer, depth, trace_address, demangled)?;
+        } else {
+            print_stack_trace_entry(&mut buffer, depth, trace_address, symbol_name)?;
+        }
+
+        // Trace addresses are return addresses, so the call itself is the instruction just before.
+        if let Some(location) = Address::new(trace_address.get().saturating_sub(1))
+            .and_then(symbols::Symbols::get_location)
+        {
+            writeln!(buffer, "     at {location}")?;
+        }
+
+        Ok(())
+    } else {
+        print_stack_trace_entry(
             &mut buffer,
-            "No stack frame pointer was found; stack trace will not be emitted."
-        )?;
+            depth,
+            trace_address,
+            "!!! no function found !!!",
+        )
+    }
+}
+
+/// Reads the registers of the current frame (i.e. the frame of the function this is inlined into).
+#[inline(always)]
+fn get_registers() -> unwind::Registers {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let (ip, sp, fp): (usize, usize, usize);
+
+        // Safety: We're just reading registers.
+        unsafe {
+            core::arch::asm!(
+                "
+                lea {ip}, [rip]
+                mov {sp}, rsp
+                mov {fp}, rbp
+                ",
+                ip = out(reg) ip,
+                sp = out(reg) sp,
+                fp = out(reg) fp,
+                options(nostack, nomem, preserves_flags)
+            );
+        }
 
-        return Ok(());
-    };
+        unwind::Registers { ip, sp, fp }
+    }
+}
 
+fn construct_panic_message(mut buffer: impl Write) -> Result {
     writeln!(&mut buffer, "----------STACK-TRACE---------")?;
 
-    // Safety: Frame pointer is pulled directly from the frame pointer register.
-    (unsafe { StackTracer::new(frame_ptr) })
+    // Safety: Registers are read directly from the current frame.
+    (unsafe { StackTracer::new(get_registers()) })
         .enumerate()
-        .try_for_each(|(depth, trace_address)| {
-            const SYMBOL_TYPE_FUNCTION: u8 = 2;
-
-            if symbols::Symbols::is_initialized()
-                && let Some(symbol_name) = symbols::Symbols::get_name(trace_address)
-            {
-                if let Ok(demangled) = rustc_demangle::try_demangle(symbol_name) {
-                    print_stack_trace_entry(&mut buffer, depth, trace_address, demangled)
-                } else {
-                    print_stack_trace_entry(&mut buffer, depth, trace_address, symbol_name)
-                }
-            } else {
-                print_stack_trace_entry(
-                    &mut buffer,
-                    depth,
-                    trace_address,
-                    "!!! no function found !!!",
-                )
-            }
-        })?;
+        .try_for_each(|(depth, trace_address)| write_frame(&mut buffer, depth, trace_address))?;
 
     writeln!(&mut buffer, "----------STACK-TRACE----------")?;
 
@@ -1,3 +1,7 @@
+use super::{
+    lines::{self, Location},
+    unwind,
+};
 use elf::{ElfBytes, endian::AnyEndian, string_table::StringTable, symbol::SymbolTable};
 use libsys::{Address, Virtual};
 
@@ -13,15 +17,22 @@ pub enum Error {
     NoSymbolTable,
 }
 
+crate::parameters! {
+    /// Whether to keep the kernel symbol info (for stack traces).
+    "keep-symbols": bool = true,
+}
+
 crate::singleton! {
     pub Symbols {
         tables: Option<(SymbolTable<'static, AnyEndian>, StringTable<'static>)>,
+        lines: Option<lines::Sections>,
+        frames: Option<unwind::Sections>,
     }
 
     fn init(kernel_file_request: &limine::request::ExecutableFileRequest) {
         let Some(response) = kernel_file_request.get_response() else {
             error!("Bootloader didn't provide response to kernel file request.");
-            return Self { tables: None };
+            return Self { tables: None, lines: None };
         };
 
         // Safety: Bootloader guarantees the address and size of the executable file will be correct.
@@ -38,22 +49,34 @@ crate::singleton! {
                 error!("Failed to parse kernel ELF: {error:?}");
             })
         else {
-            return Self { tables: None };
+            return Self { tables: None, lines: None };
         };
 
+        let lines = debug_line_sections(&kernel_elf);
+        if lines.is_none() {
+            debug!("Kernel file has no line number info; stack traces will not include source locations.");
+        }
+
+        let frames = call_frame_info_sections(&kernel_elf);
+        if frames.is_none() {
+            debug!("Kernel file has no call frame info; stack traces rely on frame pointers alone.");
+        }
+
         let Ok(symbol_table) = kernel_elf.symbol_table().inspect_err(|error| {
             error!("Failed to parse kernel symbol table: {error:?}");
         }) else {
-            return Self { tables: None };
+            return Self { tables: None, lines };
         };
 
         let Some(symbol_table) = symbol_table else {
             error!("Kernel file has no symbol table.");
-            return Self { tables: None };
+            return Self { tables: None, lines };
         };
 
         Self {
-            tables: Some(symbol_table)
+            tables: Some(symbol_table),
+            lines,
+            frames,
         }
     }
 }
@@ -74,4 +97,56 @@ impl Symbols {
 
         Some(string)
     }
+
+    /// Source location of the instruction at `address`, if the kernel file has line number info.
+    pub fn get_location(address: Address<Virtual>) -> Option<Location> {
+        let sections = Symbols::get_static().lines.as_ref()?;
+
+        lines::find(sections, address.get().try_into().unwrap())
+    }
+
+    /// Call frame information sections of the kernel file, if it has any (and symbols are initialized).
+    pub(super) fn call_frame_info() -> Option<&'static unwind::Sections> {
+        Symbols::is_initialized()
+            .then(|| Symbols::get_static().frames.as_ref())
+            .flatten()
+    }
+}
+
+/// Finds the section `name`, returning its data and the address it's loaded at.
+fn section(kernel_elf: &ElfBytes<'static, AnyEndian>, name: &str) -> Option<(&'static [u8], u64)> {
+    let header = kernel_elf.section_header_by_name(name).ok()??;
+
+    match kernel_elf.section_data(&header).ok()? {
+        (data, None) => Some((data, header.sh_addr)),
+
+        (_, Some(_)) => {
+            warn!("Kernel section {name} is compressed, which is unsupported.");
+            None
+        }
+    }
+}
+
+/// Finds the sections needed to resolve source locations; only `.debug_line` itself is required.
+fn debug_line_sections(kernel_elf: &ElfBytes<'static, AnyEndian>) -> Option<lines::Sections> {
+    let data = |name| section(kernel_elf, name).map(|(data, _)| data);
+
+    Some(lines::Sections {
+        debug_line: data(".debug_line")?,
+        debug_line_str: data(".debug_line_str").unwrap_or_default(),
+        debug_str: data(".debug_str").unwrap_or_default(),
+    })
+}
+
+/// Finds the sections holding call frame information, of which there must be at least one.
+fn call_frame_info_sections(kernel_elf: &ElfBytes<'static, AnyEndian>) -> Option<unwind::Sections> {
+    let cfi_section =
+        |name| section(kernel_elf, name).map(|(data, address)| unwind::Section { data, address });
+
+    let sections = unwind::Sections {
+        eh_frame: cfi_section(".eh_frame"),
+        debug_frame: cfi_section(".debug_frame"),
+    };
+
+    (sections.eh_frame.is_some() || sections.debug_frame.is_some()).then_some(sections)
 }
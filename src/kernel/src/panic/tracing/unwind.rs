@@ -0,0 +1,601 @@
+//! Call frame information (CFI) based unwinding, using `.eh_frame` or `.debug_frame`.
+//!
+//! This recovers the caller's registers without relying on frame pointers: the frame description entry (FDE)
+//! covering an instruction is found by a linear search, and its (and its CIE's) instructions are run up to that
+//! instruction, giving the rules to recover the canonical frame address (CFA), frame pointer, and return address.
+//! Only rules which can be evaluated from the stack and the tracked registers are supported; anything else (i.e.
+//! DWARF expressions) ends the unwind.
+
+use super::dwarf::Reader;
+
+/// x86-64 DWARF register numbers.
+const RBP: u16 = 6;
+const RSP: u16 = 7;
+
+/// Registers which rules are tracked for (up to and including the x86-64 return address, 16); all others are
+/// ignored.
+const TRACKED_REGISTERS: usize = 17;
+
+/// Deepest `DW_CFA_remember_state` nesting supported.
+const MAX_REMEMBERED_STATES: usize = 4;
+
+const DW_CFA_ADVANCE_LOC: u8 = 0x1;
+const DW_CFA_OFFSET: u8 = 0x2;
+const DW_CFA_RESTORE: u8 = 0x3;
+
+const DW_CFA_NOP: u8 = 0x00;
+const DW_CFA_SET_LOC: u8 = 0x01;
+const DW_CFA_ADVANCE_LOC1: u8 = 0x02;
+const DW_CFA_ADVANCE_LOC2: u8 = 0x03;
+const DW_CFA_ADVANCE_LOC4: u8 = 0x04;
+const DW_CFA_OFFSET_EXTENDED: u8 = 0x05;
+const DW_CFA_RESTORE_EXTENDED: u8 = 0x06;
+const DW_CFA_UNDEFINED: u8 = 0x07;
+const DW_CFA_SAME_VALUE: u8 = 0x08;
+const DW_CFA_REGISTER: u8 = 0x09;
+const DW_CFA_REMEMBER_STATE: u8 = 0x0A;
+const DW_CFA_RESTORE_STATE: u8 = 0x0B;
+const DW_CFA_DEF_CFA: u8 = 0x0C;
+const DW_CFA_DEF_CFA_REGISTER: u8 = 0x0D;
+const DW_CFA_DEF_CFA_OFFSET: u8 = 0x0E;
+const DW_CFA_DEF_CFA_EXPRESSION: u8 = 0x0F;
+const DW_CFA_EXPRESSION: u8 = 0x10;
+const DW_CFA_OFFSET_EXTENDED_SF: u8 = 0x11;
+const DW_CFA_DEF_CFA_SF: u8 = 0x12;
+const DW_CFA_DEF_CFA_OFFSET_SF: u8 = 0x13;
+const DW_CFA_VAL_OFFSET: u8 = 0x14;
+const DW_CFA_VAL_OFFSET_SF: u8 = 0x15;
+const DW_CFA_VAL_EXPRESSION: u8 = 0x16;
+const DW_CFA_GNU_ARGS_SIZE: u8 = 0x2E;
+const DW_CFA_GNU_NEGATIVE_OFFSET_EXTENDED: u8 = 0x2F;
+
+const DW_EH_PE_OMIT: u8 = 0xFF;
+const DW_EH_PE_ABSPTR: u8 = 0x00;
+const DW_EH_PE_ULEB128: u8 = 0x01;
+const DW_EH_PE_UDATA2: u8 = 0x02;
+const DW_EH_PE_UDATA4: u8 = 0x03;
+const DW_EH_PE_UDATA8: u8 = 0x04;
+const DW_EH_PE_SLEB128: u8 = 0x09;
+const DW_EH_PE_SDATA2: u8 = 0x0A;
+const DW_EH_PE_SDATA4: u8 = 0x0B;
+const DW_EH_PE_SDATA8: u8 = 0x0C;
+const DW_EH_PE_PCREL: u8 = 0x10;
+
+/// A section of call frame information, along with the address it's loaded at (which `.eh_frame` pointers may be
+/// relative to).
+#[derive(Debug, Clone, Copy)]
+pub struct Section {
+    pub data: &'static [u8],
+    pub address: u64,
+}
+
+/// Sections which call frame information may be found in. `.eh_frame` is searched first.
+#[derive(Debug, Clone, Copy)]
+pub struct Sections {
+    pub eh_frame: Option<Section>,
+    pub debug_frame: Option<Section>,
+}
+
+/// Registers needed to unwind a frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Registers {
+    pub ip: usize,
+    pub sp: usize,
+    pub fp: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    Undefined,
+    SameValue,
+    /// Saved on the stack, at the CFA plus the offset.
+    Offset(i64),
+    /// Is the CFA plus the offset.
+    ValOffset(i64),
+    /// Saved in another register.
+    Register(u16),
+    /// Described by a DWARF expression, which isn't supported.
+    Expression,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cfa {
+    RegisterOffset(u16, i64),
+    Expression,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct State {
+    cfa: Cfa,
+    rules: [Rule; TRACKED_REGISTERS],
+}
+
+impl State {
+    fn set(&mut self, register: u64, rule: Rule) {
+        if let Some(slot) = usize::try_from(register)
+            .ok()
+            .and_then(|register| self.rules.get_mut(register))
+        {
+            *slot = rule;
+        }
+    }
+
+    fn set_cfa_register(&mut self, register: u64) -> Option<()> {
+        let register = u16::try_from(register).ok()?;
+        self.cfa = match self.cfa {
+            Cfa::RegisterOffset(_, offset) => Cfa::RegisterOffset(register, offset),
+            Cfa::Expression => Cfa::RegisterOffset(register, 0),
+        };
+
+        Some(())
+    }
+
+    fn set_cfa_offset(&mut self, offset: i64) {
+        if let Cfa::RegisterOffset(register, _) = self.cfa {
+            self.cfa = Cfa::RegisterOffset(register, offset);
+        }
+    }
+}
+
+/// Common information entry, shared by the FDEs which refer to it.
+struct Cie {
+    code_alignment: u64,
+    data_alignment: i64,
+    return_address_register: u64,
+    pointer_encoding: u8,
+    has_augmentation_data: bool,
+    instructions: &'static [u8],
+}
+
+/// Frame description entry, covering the instructions from `start`.
+struct Fde {
+    cie: Cie,
+    start: u64,
+    instructions: &'static [u8],
+}
+
+/// Which format a section is in; they differ in how CIEs are identified and referred to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    EhFrame,
+    DebugFrame,
+}
+
+/// Reads a pointer of `encoding` from `reader`, which is positioned within `section`.
+fn read_pointer(reader: &mut Reader, section: Section, encoding: u8) -> Option<u64> {
+    if encoding == DW_EH_PE_OMIT {
+        return None;
+    }
+
+    let position =
+        u64::try_from(reader.bytes.as_ptr().addr() - section.data.as_ptr().addr()).ok()?;
+
+    let value = match encoding & 0x0F {
+        DW_EH_PE_ABSPTR | DW_EH_PE_UDATA8 => reader.u64()?,
+        DW_EH_PE_ULEB128 => reader.uleb128()?,
+        DW_EH_PE_UDATA2 => u64::from(reader.u16()?),
+        DW_EH_PE_UDATA4 => u64::from(reader.u32()?),
+        DW_EH_PE_SLEB128 => reader.sleb128()?.cast_unsigned(),
+        DW_EH_PE_SDATA2 => i64::from(reader.u16()?.cast_signed()).cast_unsigned(),
+        DW_EH_PE_SDATA4 => i64::from(reader.u32()?.cast_signed()).cast_unsigned(),
+        DW_EH_PE_SDATA8 => reader.u64()?,
+        _ => return None,
+    };
+
+    match encoding & 0x70 {
+        0 => Some(value),
+        DW_EH_PE_PCREL => Some(value.wrapping_add(section.address + position)),
+        // Text, data, and function relative pointers aren't produced for the kernel.
+        _ => None,
+    }
+}
+
+/// Reads the length of an entry, returning it along with the size of section offsets within the entry.
+fn read_length(reader: &mut Reader) -> Option<(usize, usize)> {
+    match reader.u32()? {
+        0xFFFF_FFFF => Some((usize::try_from(reader.u64()?).ok()?, 8)),
+        length => Some((usize::try_from(length).ok()?, 4)),
+    }
+}
+
+fn parse_cie(format: Format, section: Section, offset: usize) -> Option<Cie> {
+    let mut entry = Reader {
+        bytes: section.data.get(offset..)?,
+    };
+    let (length, offset_size) = read_length(&mut entry)?;
+    let mut cie = Reader {
+        bytes: entry.take(length)?,
+    };
+
+    let _id = cie.uint(offset_size)?;
+    let version = cie.u8()?;
+    let augmentation = cie.cstr()?;
+
+    if format == Format::DebugFrame && version >= 4 {
+        let _address_size = cie.u8()?;
+        let _segment_selector_size = cie.u8()?;
+    }
+
+    let code_alignment = cie.uleb128()?;
+    let data_alignment = cie.sleb128()?;
+    let return_address_register = if version == 1 {
+        u64::from(cie.u8()?)
+    } else {
+        cie.uleb128()?
+    };
+
+    let mut pointer_encoding = match format {
+        Format::EhFrame => DW_EH_PE_ABSPTR,
+        Format::DebugFrame => DW_EH_PE_UDATA8,
+    };
+
+    let has_augmentation_data = augmentation.starts_with('z');
+    if has_augmentation_data {
+        let augmentation_length = cie.usize_uleb128()?;
+        let mut augmentation_data = Reader {
+            bytes: cie.take(augmentation_length)?,
+        };
+
+        for character in augmentation.chars().skip(1) {
+            match character {
+                'R' => pointer_encoding = augmentation_data.u8()?,
+                'L' => {
+                    augmentation_data.u8()?;
+                }
+                'P' => {
+                    let encoding = augmentation_data.u8()?;
+                    read_pointer(&mut augmentation_data, section, encoding)?;
+                }
+                // Signal frames are unwound the same as any other.
+                'S' => {}
+                _ => return None,
+            }
+        }
+    } else if !augmentation.is_empty() {
+        // Without `z`, the augmentation's data can't be skipped.
+        return None;
+    }
+
+    Some(Cie {
+        code_alignment,
+        data_alignment,
+        return_address_register,
+        pointer_encoding,
+        has_augmentation_data,
+        instructions: cie.bytes,
+    })
+}
+
+/// Finds the FDE covering `address` in `section`.
+fn find_fde(format: Format, section: Section, address: u64) -> Option<Fde> {
+    let mut entries = Reader {
+        bytes: section.data,
+    };
+
+    while !entries.bytes.is_empty() {
+        let entry_offset = section.data.len() - entries.bytes.len();
+        let (length, offset_size) = read_length(&mut entries)?;
+
+        // A zero-length entry terminates `.eh_frame`.
+        if length == 0 {
+            return None;
+        }
+
+        let mut entry = Reader {
+            bytes: entries.take(length)?,
+        };
+        let id_offset = entry_offset + if offset_size == 8 { 12 } else { 4 };
+        let id = entry.uint(offset_size)?;
+
+        let cie_offset = match format {
+            Format::EhFrame if id == 0 => continue,
+            Format::DebugFrame if id == 0xFFFF_FFFF || id == u64::MAX => continue,
+
+            // `.eh_frame` refers to the CIE relative to the ID itself; `.debug_frame` from the section start.
+            Format::EhFrame => id_offset.checked_sub(usize::try_from(id).ok()?)?,
+            Format::DebugFrame => usize::try_from(id).ok()?,
+        };
+
+        let Some(cie) = parse_cie(format, section, cie_offset) else {
+            continue;
+        };
+
+        let start = read_pointer(&mut entry, section, cie.pointer_encoding)?;
+        // The range is only ever a length, so any relative application doesn't apply to it.
+        let length = read_pointer(&mut entry, section, cie.pointer_encoding & 0x0F)?;
+
+        if !(start..start.saturating_add(length)).contains(&address) {
+            continue;
+        }
+
+        if cie.has_augmentation_data {
+            let augmentation_length = entry.usize_uleb128()?;
+            entry.skip(augmentation_length)?;
+        }
+
+        return Some(Fde {
+            cie,
+            start,
+            instructions: entry.bytes,
+        });
+    }
+
+    None
+}
+
+/// Runs `instructions`, stopping once the location passes `address`.
+fn execute(
+    cie: &Cie,
+    section: Section,
+    instructions: &'static [u8],
+    state: &mut State,
+    initial: &State,
+    location: &mut u64,
+    address: u64,
+) -> Option<()> {
+    let mut instructions = Reader {
+        bytes: instructions,
+    };
+    let mut remembered = heapless::Vec::<State, MAX_REMEMBERED_STATES>::new();
+
+    let offset = |factored: u64| {
+        i64::try_from(factored)
+            .ok()?
+            .checked_mul(cie.data_alignment)
+    };
+    let offset_sf = |factored: i64| factored.checked_mul(cie.data_alignment);
+
+    while !instructions.bytes.is_empty() {
+        let instruction = instructions.u8()?;
+        let operand = instruction & 0x3F;
+
+        let advance = match instruction >> 6 {
+            DW_CFA_ADVANCE_LOC => Some(u64::from(operand)),
+
+            DW_CFA_OFFSET => {
+                state.set(
+                    u64::from(operand),
+                    Rule::Offset(offset(instructions.uleb128()?)?),
+                );
+                None
+            }
+
+            DW_CFA_RESTORE => {
+                let register = usize::from(operand);
+                state.set(
+                    u64::from(operand),
+                    *initial.rules.get(register).unwrap_or(&Rule::Undefined),
+                );
+                None
+            }
+
+            _ => match instruction {
+                DW_CFA_NOP => None,
+
+                DW_CFA_SET_LOC => {
+                    *location = read_pointer(&mut instructions, section, cie.pointer_encoding)?;
+                    None
+                }
+                DW_CFA_ADVANCE_LOC1 => Some(u64::from(instructions.u8()?)),
+                DW_CFA_ADVANCE_LOC2 => Some(u64::from(instructions.u16()?)),
+                DW_CFA_ADVANCE_LOC4 => Some(u64::from(instructions.u32()?)),
+
+                DW_CFA_OFFSET_EXTENDED => {
+                    let register = instructions.uleb128()?;
+                    state.set(register, Rule::Offset(offset(instructions.uleb128()?)?));
+                    None
+                }
+                DW_CFA_OFFSET_EXTENDED_SF => {
+                    let register = instructions.uleb128()?;
+                    state.set(register, Rule::Offset(offset_sf(instructions.sleb128()?)?));
+                    None
+                }
+                DW_CFA_GNU_NEGATIVE_OFFSET_EXTENDED => {
+                    let register = instructions.uleb128()?;
+                    state.set(
+                        register,
+                        Rule::Offset(offset(instructions.uleb128()?)?.checked_neg()?),
+                    );
+                    None
+                }
+                DW_CFA_VAL_OFFSET => {
+                    let register = instructions.uleb128()?;
+                    state.set(register, Rule::ValOffset(offset(instructions.uleb128()?)?));
+                    None
+                }
+                DW_CFA_VAL_OFFSET_SF => {
+                    let register = instructions.uleb128()?;
+                    state.set(
+                        register,
+                        Rule::ValOffset(offset_sf(instructions.sleb128()?)?),
+                    );
+                    None
+                }
+                DW_CFA_RESTORE_EXTENDED => {
+                    let register = instructions.uleb128()?;
+                    let rule = usize::try_from(register)
+                        .ok()
+                        .and_then(|register| initial.rules.get(register))
+                        .copied()
+                        .unwrap_or(Rule::Undefined);
+                    state.set(register, rule);
+                    None
+                }
+                DW_CFA_UNDEFINED => {
+                    state.set(instructions.uleb128()?, Rule::Undefined);
+                    None
+                }
+                DW_CFA_SAME_VALUE => {
+                    state.set(instructions.uleb128()?, Rule::SameValue);
+                    None
+                }
+                DW_CFA_REGISTER => {
+                    let register = instructions.uleb128()?;
+                    let from = u16::try_from(instructions.uleb128()?).ok()?;
+                    state.set(register, Rule::Register(from));
+                    None
+                }
+                DW_CFA_EXPRESSION | DW_CFA_VAL_EXPRESSION => {
+                    let register = instructions.uleb128()?;
+                    let length = instructions.usize_uleb128()?;
+                    instructions.skip(length)?;
+                    state.set(register, Rule::Expression);
+                    None
+                }
+
+                DW_CFA_REMEMBER_STATE => {
+                    remembered.push(*state).ok()?;
+                    None
+                }
+                DW_CFA_RESTORE_STATE => {
+                    *state = remembered.pop()?;
+                    None
+                }
+
+                DW_CFA_DEF_CFA => {
+                    let register = u16::try_from(instructions.uleb128()?).ok()?;
+                    let offset = i64::try_from(instructions.uleb128()?).ok()?;
+                    state.cfa = Cfa::RegisterOffset(register, offset);
+                    None
+                }
+                DW_CFA_DEF_CFA_SF => {
+                    let register = u16::try_from(instructions.uleb128()?).ok()?;
+                    state.cfa = Cfa::RegisterOffset(register, offset_sf(instructions.sleb128()?)?);
+                    None
+                }
+                DW_CFA_DEF_CFA_REGISTER => {
+                    state.set_cfa_register(instructions.uleb128()?)?;
+                    None
+                }
+                DW_CFA_DEF_CFA_OFFSET => {
+                    state.set_cfa_offset(i64::try_from(instructions.uleb128()?).ok()?);
+                    None
+                }
+                DW_CFA_DEF_CFA_OFFSET_SF => {
+                    state.set_cfa_offset(offset_sf(instructions.sleb128()?)?);
+                    None
+                }
+                DW_CFA_DEF_CFA_EXPRESSION => {
+                    let length = instructions.usize_uleb128()?;
+                    instructions.skip(length)?;
+                    state.cfa = Cfa::Expression;
+                    None
+                }
+
+                DW_CFA_GNU_ARGS_SIZE => {
+                    instructions.uleb128()?;
+                    None
+                }
+
+                _ => return None,
+            },
+        };
+
+        if let Some(delta) = advance {
+            *location = location.wrapping_add(delta.wrapping_mul(cie.code_alignment));
+
+            if *location > address {
+                break;
+            }
+        }
+    }
+
+    Some(())
+}
+
+/// Finds the rules in effect at `address`.
+fn rules_at(sections: &Sections, address: u64) -> Option<(State, u64)> {
+    let (section, fde) = [
+        (Format::EhFrame, sections.eh_frame),
+        (Format::DebugFrame, sections.debug_frame),
+    ]
+    .into_iter()
+    .find_map(|(format, section)| {
+        let section = section?;
+        find_fde(format, section, address).map(|fde| (section, fde))
+    })?;
+
+    let mut state = State {
+        cfa: Cfa::RegisterOffset(RSP, 0),
+        rules: [Rule::SameValue; TRACKED_REGISTERS],
+    };
+    let mut location = fde.start;
+
+    // The CIE's instructions give the initial rules, which `DW_CFA_restore` returns registers to.
+    let unset = state;
+    execute(
+        &fde.cie,
+        section,
+        fde.cie.instructions,
+        &mut state,
+        &unset,
+        &mut location,
+        u64::MAX,
+    )?;
+    let initial = state;
+
+    location = fde.start;
+    execute(
+        &fde.cie,
+        section,
+        fde.instructions,
+        &mut state,
+        &initial,
+        &mut location,
+        address,
+    )?;
+
+    Some((state, fde.cie.return_address_register))
+}
+
+/// Unwinds one frame: given the registers of a frame, recovers its caller's.
+///
+/// `is_return_address` should be set when `registers.ip` is a return address (rather than the address of the
+/// instruction executing), as then the call itself is the instruction before it.
+///
+/// `read` reads a word of the stack, or returns `None` if it can't be read.
+pub fn step(
+    sections: &Sections,
+    registers: Registers,
+    is_return_address: bool,
+    read: impl Fn(usize) -> Option<usize>,
+) -> Option<Registers> {
+    let ip = u64::try_from(registers.ip).ok()?;
+    let lookup_address = if is_return_address {
+        ip.checked_sub(1)?
+    } else {
+        ip
+    };
+    let (state, return_address_register) = rules_at(sections, lookup_address)?;
+
+    let register_value = |register: u16| match register {
+        RBP => Some(registers.fp),
+        RSP => Some(registers.sp),
+        _ => None,
+    };
+
+    let cfa = match state.cfa {
+        Cfa::RegisterOffset(register, offset) => {
+            register_value(register)?.checked_add_signed(isize::try_from(offset).ok()?)?
+        }
+        Cfa::Expression => return None,
+    };
+
+    let recover = |rule: Rule, current: Option<usize>| match rule {
+        Rule::Undefined | Rule::Expression => None,
+        Rule::SameValue => current,
+        Rule::Offset(offset) => read(cfa.checked_add_signed(isize::try_from(offset).ok()?)?),
+        Rule::ValOffset(offset) => cfa.checked_add_signed(isize::try_from(offset).ok()?),
+        Rule::Register(register) => register_value(register),
+    };
+
+    // The return address has no value of its own to carry over, so `SameValue` (as for any unmentioned register)
+    // leaves it unrecoverable.
+    let return_address_rule = *state
+        .rules
+        .get(usize::try_from(return_address_register).ok()?)?;
+    let ip = recover(return_address_rule, None)?;
+    let fp = recover(state.rules[usize::from(RBP)], Some(registers.fp))?;
+
+    Some(Registers { ip, sp: cfa, fp })
+}
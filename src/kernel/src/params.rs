@@ -1,52 +1,231 @@
+//! Kernel command line parameters.
+//!
+//! Subsystems declare the parameters they read with [`crate::parameters!`], which places each in the `.params` linker
+//! section, so the full set is known before anything is parsed. Parameters are given on the command line as
+//! `--name` (a `bool` set to `true`) or `--name=value`, and read anywhere with [`get`].
+
 use core::ffi::CStr;
 use limine::{request::ExecutableCmdlineRequest, response::ExecutableCmdlineResponse};
 use spin::Once;
 
-static PARAMS: Once<Parameters> = Once::new();
+/// Most parameters the command line may set.
+const MAX_ARGS: usize = 32;
+
+/// Longest value a `str` parameter may be set to.
+const MAX_STR_LEN: usize = 128;
+
+/// Value of a parameter, either its default or as parsed from the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    U64(u64),
+    Str(&'static str),
+}
 
-#[derive(Debug, Clone, Copy)]
-pub struct Parameters {
-    /// Whether the kernel should utilize multi-processing.
-    pub use_multiprocessing: bool,
+impl core::fmt::Display for Value {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::U64(value) => write!(f, "{value}"),
+            Value::Str(value) => write!(f, "{value:?}"),
+        }
+    }
+}
 
-    /// Whether to keep the kernel symbol info (for stack traces).
-    pub keep_symbol_info: bool,
+/// A registered parameter; see [`crate::parameters!`].
+#[derive(Debug)]
+pub struct Parameter {
+    pub name: &'static str,
+    pub doc: &'static str,
+    pub default: Value,
+}
 
-    /// Whether the kernel should use low-memory mode.
-    pub low_memory_mode: bool,
+/// Types parameters may be read as.
+pub trait Kind: Sized {
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+impl Kind for bool {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Kind for u64 {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::U64(value) => Some(value),
+            _ => None,
+        }
+    }
 }
 
-impl Default for Parameters {
-    fn default() -> Self {
-        Parameters {
-            use_multiprocessing: true,
-            keep_symbol_info: true,
-            low_memory_mode: false,
+impl Kind for &'static str {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Str(value) => Some(value),
+            _ => None,
         }
     }
 }
 
-pub fn parse(kernel_cmdline_request: &ExecutableCmdlineRequest) {
-    fn parse_impl(kernel_cmdline_request: &ExecutableCmdlineRequest) -> Parameters {
-        let mut params = Parameters::default();
+/// Declares command line parameters, along with their types, defaults, and documentation.
+///
+/// ```ignore
+/// crate::parameters! {
+///     /// Whether to enter the kernel debug shell at the end of boot.
+///     "debug-shell": bool = false,
+/// }
+/// ```
+///
+/// Parameters may be of type `bool`, `u64`, or `str` (read as `&'static str`).
+#[macro_export]
+macro_rules! parameters {
+    (@value bool $default:expr) => { $crate::params::Value::Bool($default) };
+    (@value u64 $default:expr) => { $crate::params::Value::U64($default) };
+    (@value str $default:expr) => { $crate::params::Value::Str($default) };
+
+    ($($(#[doc = $doc:literal])* $name:literal: $kind:ident = $default:expr),* $(,)?) => {
+        $(
+            const _: () = {
+                #[used]
+                #[unsafe(link_section = ".params")]
+                static PARAMETER: $crate::params::Parameter = $crate::params::Parameter {
+                    name: $name,
+                    doc: concat!($($doc),*),
+                    default: $crate::parameters!(@value $kind $default),
+                };
+            };
+        )*
+    };
+}
+
+unsafe extern "C" {
+    unsafe static __params_start: u8;
+    unsafe static __params_end: u8;
+}
+
+/// Every registered parameter.
+pub fn registry() -> &'static [Parameter] {
+    let start = (&raw const __params_start).cast::<Parameter>();
+    let end = (&raw const __params_end).cast::<Parameter>();
+
+    // Safety: The linker script places the `.params` section, which only holds `Parameter`s, between the symbols.
+    unsafe { core::slice::from_raw_parts(start, usize::try_from(end.offset_from(start)).unwrap()) }
+}
+
+fn find(name: &str) -> Option<&'static Parameter> {
+    registry().iter().find(|parameter| parameter.name == name)
+}
+
+/// Value set on the command line, which owns its copy of any string, as the command line is freed along with the
+/// rest of bootloader memory.
+#[derive(Debug)]
+enum Parsed {
+    Bool(bool),
+    U64(u64),
+    Str(heapless::String<MAX_STR_LEN>),
+}
+
+impl Parsed {
+    fn as_value(&'static self) -> Value {
+        match self {
+            Parsed::Bool(value) => Value::Bool(*value),
+            Parsed::U64(value) => Value::U64(*value),
+            Parsed::Str(value) => Value::Str(value.as_str()),
+        }
+    }
+}
+
+/// Values set on the command line.
+static PARSED: Once<heapless::Vec<(&'static str, Parsed), MAX_ARGS>> = Once::new();
+
+#[derive(Debug, Error)]
+enum ParseError {
+    #[error("unknown parameter")]
+    Unknown,
+
+    #[error("parameters must begin with `--`")]
+    MissingPrefix,
+
+    #[error("invalid value for a `{0}` parameter")]
+    InvalidValue(&'static str),
+
+    #[error("value is too long (maximum is {MAX_STR_LEN} bytes)")]
+    TooLong,
+
+    #[error("too many parameters (maximum is {MAX_ARGS})")]
+    TooMany,
+}
+
+fn parse_value(default: Value, value: Option<&str>) -> Result<Parsed, ParseError> {
+    match (default, value) {
+        (Value::Bool(_), None | Some("true" | "on" | "1")) => Ok(Parsed::Bool(true)),
+        (Value::Bool(_), Some("false" | "off" | "0")) => Ok(Parsed::Bool(false)),
+        (Value::Bool(_), Some(_)) => Err(ParseError::InvalidValue("bool")),
+
+        (Value::U64(_), Some(value)) => value
+            .strip_prefix("0x")
+            .map_or_else(|| value.parse(), |hex| u64::from_str_radix(hex, 16))
+            .map(Parsed::U64)
+            .map_err(|_| ParseError::InvalidValue("u64")),
+        (Value::U64(_), None) => Err(ParseError::InvalidValue("u64")),
+
+        (Value::Str(_), Some(value)) => heapless::String::try_from(value)
+            .map(Parsed::Str)
+            .map_err(|()| ParseError::TooLong),
+        (Value::Str(_), None) => Err(ParseError::InvalidValue("str")),
+    }
+}
+
+fn parse_arg(
+    parsed: &mut heapless::Vec<(&'static str, Parsed), MAX_ARGS>,
+    arg: &str,
+) -> Result<(), ParseError> {
+    let arg = arg.strip_prefix("--").ok_or(ParseError::MissingPrefix)?;
+    let (name, value) = arg
+        .split_once('=')
+        .map_or((arg, None), |(name, value)| (name, Some(value)));
+
+    let parameter = find(name).ok_or(ParseError::Unknown)?;
+    let value = parse_value(parameter.default, value)?;
+
+    // Later arguments override earlier ones.
+    if let Some((_, existing)) = parsed
+        .iter_mut()
+        .find(|(parsed_name, _)| *parsed_name == name)
+    {
+        *existing = value;
+
+        Ok(())
+    } else {
+        parsed
+            .push((parameter.name, value))
+            .map_err(|_| ParseError::TooMany)
+    }
+}
+
+pub fn parse(kernel_cmdline_request: &'static ExecutableCmdlineRequest) {
+    fn parse_impl(
+        kernel_cmdline_request: &'static ExecutableCmdlineRequest,
+    ) -> heapless::Vec<(&'static str, Parsed), MAX_ARGS> {
+        let mut parsed = heapless::Vec::new();
 
         match kernel_cmdline_request
             .get_response()
             .map(ExecutableCmdlineResponse::cmdline)
             .map(CStr::to_str)
         {
-            Some(Ok("")) => {
-                // Ignore accidental extra spaces
-            }
-
-            Some(Ok("--nomp")) => params.use_multiprocessing = false,
-
-            Some(Ok("--keep-symbols")) => params.keep_symbol_info = true,
-
-            Some(Ok("--lomem")) => params.low_memory_mode = true,
-
-            Some(Ok(arg)) => {
-                warn!("Unknown command line argument: {arg:?}");
+            Some(Ok(cmdline)) => {
+                // Ignore accidental extra spaces.
+                for arg in cmdline.split(' ').filter(|arg| !arg.is_empty()) {
+                    if let Err(error) = parse_arg(&mut parsed, arg) {
+                        warn!("Ignoring command line argument {arg:?}: {error}");
+                    }
+                }
             }
 
             Some(Err(error)) => {
@@ -58,22 +237,42 @@ pub fn parse(kernel_cmdline_request: &ExecutableCmdlineRequest) {
             }
         }
 
-        debug!("Kernel Parameters:\n{params:#?}");
-
-        params
+        parsed
     }
 
-    PARAMS.call_once(|| parse_impl(kernel_cmdline_request));
+    PARSED.call_once(|| parse_impl(kernel_cmdline_request));
+
+    for parameter in registry() {
+        debug!("Parameter `{}`: {}", parameter.name, value(parameter));
+    }
 }
 
-pub fn use_multiprocessing() -> bool {
-    PARAMS.wait().use_multiprocessing
+/// Whether the command line has been parsed; until it is, reading a parameter waits for it.
+pub fn is_parsed() -> bool {
+    PARSED.is_completed()
 }
 
-pub fn keep_symbol_info() -> bool {
-    PARAMS.wait().keep_symbol_info
+/// Value of `parameter`, as set on the command line or by its default.
+pub fn value(parameter: &Parameter) -> Value {
+    PARSED
+        .wait()
+        .iter()
+        .find(|(name, _)| *name == parameter.name)
+        .map_or(parameter.default, |(_, value)| value.as_value())
 }
 
-pub fn use_low_memory() -> bool {
-    PARAMS.wait().low_memory_mode
+/// Reads the parameter `name`, as set on the command line or by its default.
+///
+/// # Panics
+///
+/// If no parameter `name` is registered, or it isn't of type `T`.
+pub fn get<T: Kind>(name: &str) -> T {
+    let parameter = find(name).unwrap_or_else(|| panic!("no parameter `{name}` is registered"));
+
+    T::from_value(value(parameter)).unwrap_or_else(|| {
+        panic!(
+            "parameter `{name}` is not of type `{}`",
+            core::any::type_name::<T>()
+        )
+    })
 }
@@ -0,0 +1,169 @@
+//! Sampling profiler, driven by the performance counter overflow interrupt.
+//!
+//! When enabled with `--profile=<period>`, every hardware thread counts unhalted core cycles, and records the
+//! instruction pointer it was interrupted at (and its own ID) every `period` cycles. Samples are kept in a fixed ring
+//! shared by all hardware threads, newer samples overwriting older ones; the debug shell's `profile` command
+//! summarizes them.
+//!
+//! Profiling is disabled (with the performance counter interrupt left masked) if the hardware thread's performance
+//! monitoring unit can't be used, as is common under hypervisors which don't virtualize it.
+
+use core::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use libsys::{Address, Virtual};
+
+/// Samples retained before the oldest are overwritten.
+const CAPACITY: usize = 4096;
+
+crate::parameters! {
+    /// Record a profiling sample every this many unhalted core cycles (0 disables the profiler).
+    "profile": u64 = 0,
+}
+
+/// A recorded sample.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub instruction_pointer: Address<Virtual>,
+    pub hwthread_id: u32,
+}
+
+/// Slot of the sample ring.
+///
+/// # Remarks
+///
+/// The fields are written separately, so a slot read while it's being overwritten may pair an instruction pointer
+/// with the wrong hardware thread; this is tolerated, as samples are only ever summarized.
+struct Slot {
+    instruction_pointer: AtomicUsize,
+    hwthread_id: AtomicU32,
+}
+
+static SAMPLES: [Slot; CAPACITY] = [const {
+    Slot {
+        instruction_pointer: AtomicUsize::new(0),
+        hwthread_id: AtomicU32::new(0),
+    }
+}; CAPACITY];
+
+/// Number of samples ever recorded; the next sample is written to this index (modulo [`CAPACITY`]).
+static RECORDED: AtomicU64 = AtomicU64::new(0);
+
+/// Sampling period (in unhalted core cycles), or 0 if the profiler isn't running.
+static PERIOD: AtomicU64 = AtomicU64::new(0);
+
+/// Starts sampling on the current hardware thread, if it was requested with `--profile` and is supported.
+///
+/// # Remarks
+///
+/// Every hardware thread should call this once its local interrupt controller is enabled. Only the bootstrap
+/// processor reports why profiling is unavailable, to avoid repeating it for every hardware thread.
+pub fn start(is_bsp: bool) {
+    let period = crate::params::get::<u64>("profile");
+    if period == 0 {
+        return;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        use crate::arch::x86_64::{cpuid::hypervisor_info, pmu};
+
+        let counter = match pmu::check_support() {
+            Ok(counter) => counter,
+
+            Err(error) => {
+                if is_bsp {
+                    match hypervisor_info() {
+                        Some(hypervisor) => warn!(
+                            "Profiler disabled: {error} (running under {:?}, which may not virtualize the PMU).",
+                            hypervisor.identify()
+                        ),
+
+                        None => warn!("Profiler disabled: {error}."),
+                    }
+                }
+
+                return;
+            }
+        };
+
+        let clamped_period = period.min(pmu::MAX_PERIOD);
+        if is_bsp {
+            if clamped_period < period {
+                warn!("Profiler period clamped to {clamped_period} cycles.");
+            }
+
+            info!("Profiler sampling every {clamped_period} cycles with {counter:?}.");
+        }
+
+        PERIOD.store(clamped_period, Ordering::Relaxed);
+        pmu::start(counter, clamped_period);
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    if is_bsp {
+        warn!("Profiler disabled: not supported on this architecture.");
+    }
+}
+
+/// Handles a performance counter overflow interrupt which interrupted `instruction_pointer`.
+pub fn handle_overflow(instruction_pointer: Address<Virtual>) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        use crate::arch::x86_64::pmu;
+
+        let period = PERIOD.load(Ordering::Relaxed);
+
+        if period == 0 {
+            // The profiler never started, so nothing should be overflowing; don't let it happen again.
+            pmu::stop();
+            warn!("Unexpected performance counter interrupt; masked it.");
+
+            return;
+        }
+
+        if pmu::rearm(period) {
+            record(instruction_pointer);
+        }
+    }
+}
+
+fn record(instruction_pointer: Address<Virtual>) {
+    let index = RECORDED.fetch_add(1, Ordering::Relaxed);
+    let slot = usize::try_from(index % u64::try_from(CAPACITY).unwrap()).unwrap();
+
+    SAMPLES[slot]
+        .instruction_pointer
+        .store(instruction_pointer.get(), Ordering::Relaxed);
+    SAMPLES[slot].hwthread_id.store(
+        crate::percpu!(hwthread_id).load(Ordering::Relaxed),
+        Ordering::Relaxed,
+    );
+}
+
+/// Number of samples ever recorded (of which at most [`CAPACITY`] are retained).
+pub fn recorded() -> u64 {
+    RECORDED.load(Ordering::Relaxed)
+}
+
+/// Sampling period (in unhalted core cycles), if the profiler is running.
+pub fn period() -> Option<u64> {
+    Some(PERIOD.load(Ordering::Relaxed)).filter(|period| *period > 0)
+}
+
+/// Calls `func` with every retained sample, in no particular order.
+pub fn for_each_sample(func: impl FnMut(Sample)) {
+    let retained = usize::try_from(recorded())
+        .unwrap_or(usize::MAX)
+        .min(CAPACITY);
+
+    SAMPLES[..retained]
+        .iter()
+        .filter_map(|slot| {
+            Some(Sample {
+                instruction_pointer: Address::new(
+                    slot.instruction_pointer.load(Ordering::Relaxed),
+                )?,
+                hwthread_id: slot.hwthread_id.load(Ordering::Relaxed),
+            })
+        })
+        .for_each(func);
+}
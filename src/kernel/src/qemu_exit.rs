@@ -0,0 +1,49 @@
+//! Exits QEMU with a status code, via its `isa-debug-exit` device, so automated runs can report pass or fail.
+//!
+//! The device is attached with `-device isa-debug-exit,iobase=0xF4,iosize=0x04` (`xtask run --test` does so), and
+//! QEMU exits with the status `(code << 1) | 1` when `code` is written to it.
+
+use ioports::WriteOnlyPort;
+
+/// I/O port the `isa-debug-exit` device is attached at.
+const PORT: u16 = 0xF4;
+
+crate::parameters! {
+    /// Whether this is an automated test run, which runs the kernel tests (as `--run-tests`), and exits QEMU on panic.
+    "test-mode": bool = false,
+}
+
+#[repr(u32)]
+#[derive(Debug, IntoPrimitive, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// QEMU exits with status 33.
+    Success = 0x10,
+    /// QEMU exits with status 35.
+    Failure = 0x11,
+}
+
+/// Whether the kernel was booted for an automated test run (with `--test-mode`).
+///
+/// # Remarks
+///
+/// This is `false` until the command line is parsed, so it's safe to call from any context (i.e. when panicking).
+pub fn is_test_mode() -> bool {
+    crate::params::is_parsed() && crate::params::get::<bool>("test-mode")
+}
+
+/// Exits QEMU with `code`.
+///
+/// # Remarks
+///
+/// If the `isa-debug-exit` device isn't attached (i.e. not running in QEMU), the write is ignored, and the current
+/// hardware thread is halted instead.
+pub fn exit(code: ExitCode) -> ! {
+    #[cfg(target_arch = "x86_64")]
+    {
+        // Safety: Writes to the port are ignored if the device isn't attached.
+        let mut port = unsafe { WriteOnlyPort::<u32>::new(PORT) };
+        port.write(u32::from(code));
+    }
+
+    crate::cpu::halt_and_catch_fire()
+}
@@ -0,0 +1,160 @@
+//! The ChaCha20 block function (RFC 8439), and a generator over its keystream.
+
+/// `"expand 32-byte k"`, which fills the first row of every block's input.
+const CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646E, 0x7962_2D32, 0x6B20_6574];
+
+pub const KEY_LEN: usize = 32;
+pub const BLOCK_LEN: usize = 64;
+
+type Key = [u32; KEY_LEN / 4];
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+/// Applies ChaCha's 20 rounds to `state`, without adding the input back in.
+pub fn permute(state: &mut [u32; 16]) {
+    for _ in 0..10 {
+        quarter_round(state, 0, 4, 8, 12);
+        quarter_round(state, 1, 5, 9, 13);
+        quarter_round(state, 2, 6, 10, 14);
+        quarter_round(state, 3, 7, 11, 15);
+        quarter_round(state, 0, 5, 10, 15);
+        quarter_round(state, 1, 6, 11, 12);
+        quarter_round(state, 2, 7, 8, 13);
+        quarter_round(state, 3, 4, 9, 14);
+    }
+}
+
+fn key_from_bytes(bytes: &[u8; KEY_LEN]) -> Key {
+    core::array::from_fn(|index| u32::from_le_bytes(*bytes[(index * 4)..].first_chunk().unwrap()))
+}
+
+fn split_u64(value: u64) -> [u32; 2] {
+    let [low, high] =
+        [value, value >> 32].map(|half| u32::try_from(half & u64::from(u32::MAX)).unwrap());
+
+    [low, high]
+}
+
+/// Keystream block `counter` of `key` under `nonce`, in the original layout of a 64-bit counter & nonce.
+fn block(key: &Key, counter: u64, nonce: u64) -> [u8; BLOCK_LEN] {
+    let mut input = [0u32; 16];
+    input[..4].copy_from_slice(&CONSTANTS);
+    input[4..12].copy_from_slice(key);
+    input[12..14].copy_from_slice(&split_u64(counter));
+    input[14..].copy_from_slice(&split_u64(nonce));
+
+    let mut state = input;
+    permute(&mut state);
+
+    let mut block = [0u8; BLOCK_LEN];
+    for (bytes, (word, input)) in block.chunks_exact_mut(4).zip(state.iter().zip(&input)) {
+        bytes.copy_from_slice(&word.wrapping_add(*input).to_le_bytes());
+    }
+
+    block
+}
+
+/// A generator over the ChaCha20 keystream, which replaces its key with keystream before any of that keystream is
+/// output (fast key erasure), so its past outputs can't be recovered from its state.
+pub struct ChaCha20Rng {
+    /// Key of the next block, which is only ever used once, so every block is the first of its keystream.
+    key: Key,
+    /// Keystream generated alongside the current key, which is output from its end, and zeroed as it is.
+    buffer: [u8; BLOCK_LEN - KEY_LEN],
+    available: usize,
+}
+
+impl ChaCha20Rng {
+    pub fn new(seed: &[u8; KEY_LEN]) -> Self {
+        Self {
+            key: key_from_bytes(seed),
+            buffer: [0; BLOCK_LEN - KEY_LEN],
+            available: 0,
+        }
+    }
+
+    /// Mixes `seed` into the key, discarding any buffered keystream.
+    pub fn reseed(&mut self, seed: &[u8; KEY_LEN]) {
+        let keystream = block(&self.key, 0, 0);
+        let mut key = [0u8; KEY_LEN];
+        for (key, (keystream, seed)) in key.iter_mut().zip(keystream.iter().zip(seed)) {
+            *key = keystream ^ seed;
+        }
+
+        self.key = key_from_bytes(&key);
+        self.buffer.fill(0);
+        self.available = 0;
+    }
+
+    /// Generates a block of keystream, half of which becomes the next key, and half of which is buffered.
+    fn refill(&mut self) {
+        let keystream = block(&self.key, 0, 0);
+        let (key, buffer) = keystream.split_first_chunk::<KEY_LEN>().unwrap();
+
+        self.key = key_from_bytes(key);
+        self.buffer.copy_from_slice(buffer);
+        self.available = self.buffer.len();
+    }
+
+    pub fn fill(&mut self, mut buf: &mut [u8]) {
+        while !buf.is_empty() {
+            if self.available == 0 {
+                self.refill();
+            }
+
+            let len = buf.len().min(self.available);
+            let start = self.available - len;
+            let (chunk, rest) = buf.split_at_mut(len);
+            chunk.copy_from_slice(&self.buffer[start..self.available]);
+            self.buffer[start..self.available].fill(0);
+            self.available = start;
+
+            buf = rest;
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill(&mut bytes);
+
+        u32::from_ne_bytes(bytes)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill(&mut bytes);
+
+        u64::from_ne_bytes(bytes)
+    }
+}
+
+crate::kernel_test! {
+    /// The block function matches RFC 8439's test vector, and the generator never repeats its output.
+    fn chacha20_block() -> crate::tests::Outcome {
+        let key = key_from_bytes(&core::array::from_fn(|index| u8::try_from(index).unwrap()));
+        // RFC 8439 §2.3.2 uses a 32-bit counter of 1, and the nonce `00:00:00:09:00:00:00:4a:00:00:00:00`.
+        let block = block(&key, 0x0900_0000_0000_0001, 0x4A00_0000);
+        if block[..8] != [0x10, 0xF1, 0xE7, 0xE4, 0xD1, 0x3B, 0x59, 0x15] || block[56..] != [0xCB, 0xD0, 0x83, 0xE8, 0xA2, 0x50, 0x3C, 0x4E] {
+            return Err("block function output differs from the test vector");
+        }
+
+        let mut rng = ChaCha20Rng::new(&[0; KEY_LEN]);
+        let (mut first, mut second) = ([0; 48], [0; 48]);
+        rng.fill(&mut first);
+        rng.fill(&mut second);
+        if first == second {
+            return Err("generator repeated its output");
+        }
+
+        Ok(())
+    }
+}
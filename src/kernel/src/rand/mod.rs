@@ -0,0 +1,281 @@
+//! Kernel randomness: an entropy pool, seeding a ChaCha20 CSPRNG which every consumer draws from.
+//!
+//! The pool (see [`pool`]) is fed by `RDSEED` & `RDRAND` (when CPUID advertises them), by timestamp counter jitter as
+//! it's first seeded, and by the timing of every interrupt. The global generator is seeded from it on first use,
+//! blocking until the pool has been credited with a full seed, and is reseeded from it (at most every
+//! [`RESEED_INTERVAL_NS`]) once it has been again. Each hardware thread draws from a generator of its own (see
+//! [`prng`]), seeded from the global generator, so consumers don't contend for it.
+//!
+//! Randomness is drawn with [`fill`], and by userspace through the `GetRandom` system call.
+
+mod chacha20;
+mod pool;
+pub mod prng;
+
+use crate::{
+    arch::x86_64::instructions::{__rdrand, __rdseed},
+    interrupts::{InterruptCell, irq::IrqTimestamp},
+    sync::SpinMutex,
+    time::SystemClock,
+};
+use chacha20::ChaCha20Rng;
+use core::sync::atomic::{AtomicU64, Ordering};
+use pool::Pool;
+use spin::Once;
+
+/// Entropy credited for each sample of the hardware sources: `RDSEED` is conditioned entropy, whereas `RDRAND` is the
+/// output of a DRBG, which is only partly trusted.
+const RDSEED_BITS: u32 = 64;
+const RDRAND_BITS: u32 = 32;
+/// Samples drawn from the hardware sources as the pool is seeded, or reseeded.
+const HARDWARE_SAMPLES: usize = 4;
+
+/// Timestamp counter jitter samples absorbed at once, which are credited with a single bit.
+const JITTER_BATCH: usize = 16;
+/// Most batches of jitter sampled as the pool is first seeded, which is enough to seed it alone.
+const JITTER_BATCHES: u32 = pool::FULL_BITS;
+
+/// Interrupts whose timings are folded together before they're absorbed, and credited with a single bit.
+const INTERRUPT_BATCH: u64 = 64;
+
+/// Shortest interval between reseeds of the global generator.
+const RESEED_INTERVAL_NS: u64 = 60_000_000_000;
+
+static POOL: InterruptCell<SpinMutex<Pool>> = InterruptCell::new(SpinMutex::new(Pool::new()));
+
+/// Interrupt timings folded together since they were last absorbed, and the number of interrupts taken.
+static INTERRUPT_TIMINGS: AtomicU64 = AtomicU64::new(0);
+static INTERRUPT_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// The global generator, and when it was last (re)seeded.
+struct Global {
+    rng: ChaCha20Rng,
+    seeded_ns: u64,
+}
+
+static GLOBAL: Once<InterruptCell<SpinMutex<Global>>> = Once::new();
+
+/// Number of times the global generator has been reseeded, so hardware thread-local generators know to reseed.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn read_tsc() -> u64 {
+    // Safety: The kernel already depends upon the processor having TSC capability.
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
+
+/// Absorbs samples of the hardware sources, preferring `RDSEED`.
+fn add_hardware(pool: &mut Pool) {
+    for _ in 0..HARDWARE_SAMPLES {
+        match __rdseed()
+            .map(|sample| (sample, RDSEED_BITS))
+            .or_else(|_| __rdrand().map(|sample| (sample, RDRAND_BITS)))
+        {
+            Ok((sample, bits)) => pool.add(&[sample], bits),
+            Err(_) => return,
+        }
+    }
+}
+
+/// Absorbs timestamp counter jitter until the pool is fully credited.
+///
+/// Each sample is the time taken to permute a scratch state, which varies with the state of the caches, pipeline, and
+/// clock of the processor.
+fn add_jitter(pool: &mut Pool) {
+    let mut scratch = [0u32; 16];
+    let mut samples = [0u64; JITTER_BATCH];
+
+    for _ in 0..JITTER_BATCHES {
+        if pool.credited_bits() >= pool::FULL_BITS {
+            break;
+        }
+
+        for sample in &mut samples {
+            let start = read_tsc();
+            chacha20::permute(&mut scratch);
+            *sample = read_tsc().wrapping_sub(start) ^ start.rotate_left(32);
+        }
+
+        pool.add(&samples, 1);
+    }
+}
+
+/// Folds the timing of an interrupt into the pool.
+///
+/// # Remarks
+///
+/// This is called for every interrupt, so only every [`INTERRUPT_BATCH`]th touches the pool; if the pool is locked,
+/// that batch is dropped rather than waited on.
+pub fn add_interrupt(vector: u8, timestamp: IrqTimestamp) {
+    let count = INTERRUPT_COUNT.fetch_add(1, Ordering::Relaxed);
+    let sample = timestamp.ticks() ^ (u64::from(vector) << 56);
+    INTERRUPT_TIMINGS.fetch_xor(
+        sample.rotate_left(u32::try_from(count % 64).unwrap()),
+        Ordering::Relaxed,
+    );
+
+    if (count % INTERRUPT_BATCH) == (INTERRUPT_BATCH - 1) {
+        let timings = INTERRUPT_TIMINGS.swap(0, Ordering::Relaxed);
+        POOL.with(|pool| {
+            if let Some(mut pool) = pool.try_lock() {
+                pool.add(&[timings], 1);
+            }
+        });
+    }
+}
+
+fn global() -> &'static InterruptCell<SpinMutex<Global>> {
+    GLOBAL.call_once(|| {
+        let seed = POOL.with(|pool| {
+            let mut pool = pool.lock();
+            add_hardware(&mut pool);
+            add_jitter(&mut pool);
+
+            if pool.credited_bits() < pool::FULL_BITS {
+                warn!(
+                    "Entropy pool was seeded with only {} bits.",
+                    pool.credited_bits()
+                );
+            }
+
+            pool.extract()
+        });
+
+        debug!("Entropy pool has been seeded.");
+
+        InterruptCell::new(SpinMutex::new(Global {
+            rng: ChaCha20Rng::new(&seed),
+            seeded_ns: SystemClock::now_ns(),
+        }))
+    })
+}
+
+impl Global {
+    /// Reseeds the generator if the interval has passed, and the pool has been credited with a full seed.
+    fn reseed_if_due(&mut self) {
+        let now_ns = SystemClock::now_ns();
+        if now_ns.saturating_sub(self.seeded_ns) < RESEED_INTERVAL_NS {
+            return;
+        }
+
+        let seed = POOL.with(|pool| {
+            let mut pool = pool.lock();
+            add_hardware(&mut pool);
+
+            (pool.credited_bits() >= pool::FULL_BITS).then(|| pool.extract())
+        });
+
+        if let Some(seed) = seed {
+            trace!("Reseeding global random state.");
+
+            self.rng.reseed(&seed);
+            self.seeded_ns = now_ns;
+            GENERATION.fetch_add(1, Ordering::Release);
+        }
+    }
+}
+
+/// Fills `buf` from the global generator, seeding it first if it hasn't been.
+fn fill_global(buf: &mut [u8]) {
+    global().with(|global| {
+        let mut global = global.lock();
+        global.reseed_if_due();
+        global.rng.fill(buf);
+    });
+}
+
+/// Whether the entropy pool has been seeded, so drawing random bytes won't block to seed it.
+pub fn is_seeded() -> bool {
+    GLOBAL.is_completed()
+}
+
+/// Blocks until the entropy pool has been seeded.
+pub fn wait_until_seeded() {
+    global();
+}
+
+/// Fills `buf` with cryptographically secure random bytes.
+pub fn fill(buf: &mut [u8]) {
+    use crate::cpu::local_state::LocalState;
+
+    // Before the local state is available, consumers are served directly from the global generator.
+    if LocalState::is_initialized() {
+        LocalState::with_rng(|rng| rng.fill(buf));
+    } else {
+        fill_global(buf);
+    }
+}
+
+/// Fills `buf` with random bytes, without waiting for the entropy pool to be seeded.
+///
+/// # Remarks
+///
+/// Until the pool is seeded, the bytes are drawn from a generator keyed by `RDRAND` (or, without it, only the timestamp
+/// counter), so they're unsuitable for cryptographic use.
+pub fn fill_insecure(buf: &mut [u8]) {
+    if is_seeded() {
+        fill(buf);
+        return;
+    }
+
+    let mut key = [0u8; chacha20::KEY_LEN];
+    for word in key.chunks_exact_mut(size_of::<u64>()) {
+        let sample = __rdrand().unwrap_or_else(|_| read_tsc());
+        word.copy_from_slice(&sample.to_le_bytes());
+    }
+
+    ChaCha20Rng::new(&key).fill(buf);
+}
+
+#[unsafe(no_mangle)]
+#[allow(clippy::unnecessary_wraps)]
+unsafe extern "Rust" fn __getrandom_v03_custom(
+    dst: *mut u8,
+    len: usize,
+) -> Result<(), getrandom::Error> {
+    // Safety: Caller is required to provide a `dst` valid for writes of `len` bytes.
+    fill(unsafe { core::slice::from_raw_parts_mut(dst, len) });
+
+    Ok(())
+}
+
+crate::kernel_test! {
+    /// The pool only yields a seed once, and credit is spent as it's extracted.
+    fn extracts_entropy_pool_seeds() -> crate::tests::Outcome {
+        let mut pool = Pool::new();
+        pool.add(&[1, 2, 3, 4, 5], 300);
+        if pool.credited_bits() != pool::FULL_BITS {
+            return Err("pool credit wasn't capped at a full seed");
+        }
+
+        let first = pool.extract();
+        if pool.credited_bits() != 0 {
+            return Err("extracting a seed didn't spend the pool's credit");
+        }
+        if first == pool.extract() {
+            return Err("pool yielded the same seed twice");
+        }
+
+        let (mut a, mut b) = ([0u8; 32], [0u8; 32]);
+        fill(&mut a);
+        fill(&mut b);
+        if a == b {
+            return Err("generator repeated its output");
+        }
+
+        Ok(())
+    }
+}
+
+crate::kernel_test! {
+    /// Insecure draws never wait on the pool, and don't repeat.
+    fn fills_insecure_without_blocking() -> crate::tests::Outcome {
+        let (mut a, mut b) = ([0u8; 32], [0u8; 32]);
+        fill_insecure(&mut a);
+        fill_insecure(&mut b);
+        if a == b {
+            return Err("insecure generator repeated its output");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+//! The entropy pool: a sponge over the ChaCha20 permutation, which absorbs samples from every entropy source, and
+//! squeezes out seeds for the generators.
+//!
+//! Samples are XORed into the first half of the state (its rate), which is then permuted, so the second half (its
+//! capacity) is never directly exposed. Each source credits the pool with a conservative estimate of the entropy its
+//! samples held, and a seed spends the pool's credit.
+
+use super::chacha20::{KEY_LEN, permute};
+
+/// Words of the state samples are absorbed into, and seeds squeezed from.
+const RATE_WORDS: usize = 8;
+
+/// Credit the pool holds once it's fully seeded.
+pub const FULL_BITS: u32 = 256;
+
+pub struct Pool {
+    state: [u32; 16],
+    credited_bits: u32,
+}
+
+impl Pool {
+    pub const fn new() -> Self {
+        Self {
+            state: [0; 16],
+            credited_bits: 0,
+        }
+    }
+
+    /// Entropy the pool has been credited with since its last seed was extracted, in bits.
+    pub fn credited_bits(&self) -> u32 {
+        self.credited_bits
+    }
+
+    /// Absorbs `samples`, crediting the pool with `bits` of entropy.
+    pub fn add(&mut self, samples: &[u64], bits: u32) {
+        for chunk in samples.chunks(RATE_WORDS / 2) {
+            for (words, sample) in self.state[..RATE_WORDS].chunks_exact_mut(2).zip(chunk) {
+                words[0] ^= u32::try_from(sample & u64::from(u32::MAX)).unwrap();
+                words[1] ^= u32::try_from(sample >> 32).unwrap();
+            }
+
+            permute(&mut self.state);
+        }
+
+        self.credited_bits = self.credited_bits.saturating_add(bits).min(FULL_BITS);
+    }
+
+    /// Squeezes a seed from the pool, spending its credit.
+    pub fn extract(&mut self) -> [u8; KEY_LEN] {
+        permute(&mut self.state);
+
+        let mut seed = [0u8; KEY_LEN];
+        for (bytes, word) in seed.chunks_exact_mut(4).zip(&self.state[..RATE_WORDS]) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+
+        // Overwriting the rate means the seed can't be recovered from the pool's state, should it later be exposed.
+        self.state[..RATE_WORDS].fill(0);
+        permute(&mut self.state);
+        self.credited_bits = 0;
+
+        seed
+    }
+}
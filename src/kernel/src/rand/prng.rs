@@ -0,0 +1,104 @@
+//! Hardware thread-local generators, each seeded from the global generator, so consumers don't contend for it.
+
+use super::{GENERATION, chacha20::ChaCha20Rng};
+use core::sync::atomic::Ordering;
+
+/// Number of outputs a hardware thread-local generator may produce before it
+/// is reseeded from the global generator.
+const RESEED_INTERVAL: u64 = 0x10000;
+
+/// A hardware thread-local generator, periodically reseeded from the global generator.
+pub struct LocalRng {
+    rng: ChaCha20Rng,
+    outputs_since_reseed: u64,
+    /// Generation of the global generator this was last seeded from.
+    generation: u64,
+    /// Generation of the hardware thread's generator this was last seeded at; see [`mark_local_stale`].
+    local_generation: u64,
+}
+
+impl LocalRng {
+    pub fn new() -> Self {
+        let generation = GENERATION.load(Ordering::Acquire);
+        let local_generation = crate::percpu!(rng_generation).load(Ordering::Relaxed);
+        let mut seed = [0; super::chacha20::KEY_LEN];
+        super::fill_global(&mut seed);
+
+        Self {
+            rng: ChaCha20Rng::new(&seed),
+            outputs_since_reseed: 0,
+            generation,
+            local_generation,
+        }
+    }
+
+    /// Discards the current generator state, and reseeds from the global generator.
+    ///
+    /// # Remarks
+    ///
+    /// This should be called whenever the generator's state may have been observed
+    /// or duplicated (e.g. after a fork), so that consumers do not receive the same
+    /// values twice.
+    pub fn reseed(&mut self) {
+        *self = Self::new();
+    }
+
+    fn reseed_if_due(&mut self) {
+        if self.outputs_since_reseed >= RESEED_INTERVAL
+            || self.generation != GENERATION.load(Ordering::Acquire)
+            || self.local_generation != crate::percpu!(rng_generation).load(Ordering::Relaxed)
+        {
+            trace!("Reseeding local random state.");
+
+            self.reseed();
+        }
+
+        self.outputs_since_reseed += 1;
+    }
+
+    pub fn fill(&mut self, buf: &mut [u8]) {
+        self.reseed_if_due();
+        self.rng.fill(buf);
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.reseed_if_due();
+        self.rng.next_u32()
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.reseed_if_due();
+        self.rng.next_u64()
+    }
+}
+
+impl Default for LocalRng {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Has the current hardware thread's generator reseed from the global generator before its next output, as its state
+/// may have been observed (e.g. by a task creating another, as with a fork).
+///
+/// # Remarks
+///
+/// This only bumps a per-hardware thread generation, so the global generator is only taken once the generator is next
+/// drawn from.
+pub fn mark_local_stale() {
+    crate::percpu!(rng_generation).fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn next_u32() -> u32 {
+    let mut bytes = [0; 4];
+    super::fill(&mut bytes);
+
+    u32::from_ne_bytes(bytes)
+}
+
+pub fn next_u64() -> u64 {
+    let mut bytes = [0; 8];
+    super::fill(&mut bytes);
+
+    u64::from_ne_bytes(bytes)
+}
@@ -0,0 +1,238 @@
+//! Lock dependency checking ("lockdep-lite"), enabled with the `lockdep` feature.
+//!
+//! Every hardware thread records which lock classes it holds, and every time a lock is acquired while others are
+//! held, the order is recorded as a dependency (with the stack it was first seen at). Two kinds of bug panic:
+//!
+//! - A circular dependency: acquiring `B` while holding `A`, when `A` has previously been acquired (directly or
+//!   transitively) while holding `B`. Two hardware threads taking those paths at once deadlock. Both stacks are
+//!   reported: the recorded one which established the opposite order, and the current one (by the panic).
+//! - Enabling interrupts while holding a lock acquired with interrupts disabled. Such a lock is expected to be taken
+//!   from interrupt handlers, which would then spin on it forever.
+//!
+//! # Remarks
+//!
+//! Nesting two locks of the same class isn't checked, as many classes have one lock per hardware thread or device,
+//! which are only ever nested in a fixed order. Bookkeeping is skipped (rather than blocking) when it would re-enter
+//! itself, i.e. from an NMI, so the checks are best effort.
+
+use crate::util::CachePadded;
+use core::{
+    panic::Location,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use heapless::Vec;
+use libsys::{Address, Virtual};
+
+/// Identifies a lock class: the place in the source its locks are constructed.
+pub type Class = &'static Location<'static>;
+
+/// Hardware threads tracked, by ID; locks taken by any with a higher ID aren't checked.
+const MAX_HWTHREADS: usize = 256;
+
+/// Most locks a hardware thread may hold at once before further acquisitions go unchecked.
+const MAX_HELD: usize = 16;
+
+/// Most dependencies recorded before new ones go unchecked.
+const MAX_DEPENDENCIES: usize = 512;
+
+/// Stack frames recorded for each dependency.
+const MAX_FRAMES: usize = 16;
+
+/// Most lock classes visited when searching for a circular dependency, bounding the stack used by the search.
+const MAX_SEARCH: usize = 128;
+
+#[derive(Clone, Copy)]
+struct Held {
+    class: Class,
+    acquired_at: &'static Location<'static>,
+    interrupts_disabled: bool,
+}
+
+/// `after` has been acquired while holding `before`.
+struct Dependency {
+    before: Class,
+    after: Class,
+    hwthread_id: u32,
+    frames: Vec<Address<Virtual>, MAX_FRAMES>,
+}
+
+/// Locks held by each hardware thread. Each is only locked by its own hardware thread, so it's only ever contended
+/// when the bookkeeping is re-entered.
+static HELD: [CachePadded<spin::Mutex<Vec<Held, MAX_HELD>>>; MAX_HWTHREADS] =
+    [const { CachePadded::new(spin::Mutex::new(Vec::new())) }; MAX_HWTHREADS];
+
+static DEPENDENCIES: spin::Mutex<Vec<Dependency, MAX_DEPENDENCIES>> = spin::Mutex::new(Vec::new());
+
+/// Set once a bug is reported (or tracking overflows), after which nothing more is checked.
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+fn is_checking() -> bool {
+    !DISABLED.load(Ordering::Relaxed) && !crate::panic::is_panicking()
+}
+
+/// Runs `func` with the locks held by the current hardware thread, unless that would re-enter the bookkeeping.
+fn with_held<T>(func: impl FnOnce(&mut Vec<Held, MAX_HELD>) -> T) -> Option<T> {
+    if !is_checking() {
+        return None;
+    }
+
+    let hwthread_id = usize::try_from(crate::cpu::get_id()).ok()?;
+    let mut held = HELD.get(hwthread_id)?.try_lock()?;
+
+    Some(func(&mut held))
+}
+
+fn capture_frames() -> Vec<Address<Virtual>, MAX_FRAMES> {
+    let mut frames = [Address::new_truncate(0); MAX_FRAMES];
+    let captured = crate::panic::tracing::capture(&mut frames);
+
+    frames[..captured].iter().copied().collect()
+}
+
+/// Finds the first dependency on a path from `from` to `to`, if `to` is reachable from `from`.
+fn find_path<'a>(dependencies: &'a [Dependency], from: Class, to: Class) -> Option<&'a Dependency> {
+    // Each entry is a class to visit, and the dependency out of `from` it was reached through.
+    let mut pending = Vec::<(Class, &Dependency), MAX_SEARCH>::new();
+    let mut visited = Vec::<Class, MAX_SEARCH>::new();
+
+    for dependency in dependencies
+        .iter()
+        .filter(|dependency| dependency.before == from)
+    {
+        pending.push((dependency.after, dependency)).ok()?;
+    }
+
+    while let Some((class, first)) = pending.pop() {
+        if class == to {
+            return Some(first);
+        }
+
+        if visited.contains(&class) {
+            continue;
+        }
+        visited.push(class).ok()?;
+
+        for dependency in dependencies
+            .iter()
+            .filter(|dependency| dependency.before == class)
+        {
+            pending.push((dependency.after, first)).ok()?;
+        }
+    }
+
+    None
+}
+
+/// Checks acquiring a lock of `class` (at `acquired_at`) against the order of those already held, then records it.
+///
+/// # Panics
+///
+/// If the acquisition is part of a circular dependency.
+pub fn acquire(class: Class, acquired_at: &'static Location<'static>) {
+    let inversion = with_held(|held| {
+        let mut dependencies = DEPENDENCIES.lock();
+
+        for holding in held.iter().filter(|holding| holding.class != class) {
+            let is_known = dependencies
+                .iter()
+                .any(|dependency| dependency.before == holding.class && dependency.after == class);
+            if is_known {
+                continue;
+            }
+
+            if let Some(dependency) = find_path(&dependencies, class, holding.class) {
+                report_dependency(dependency);
+
+                return Some(*holding);
+            }
+
+            let dependency = Dependency {
+                before: holding.class,
+                after: class,
+                hwthread_id: crate::cpu::get_id(),
+                frames: capture_frames(),
+            };
+            if dependencies.push(dependency).is_err() {
+                DISABLED.store(true, Ordering::Relaxed);
+                warn!("Lock dependency table is full; no longer checking lock order.");
+            }
+        }
+
+        push(held, class, acquired_at);
+
+        None
+    })
+    .flatten();
+
+    if let Some(holding) = inversion {
+        panic!(
+            "circular lock dependency: acquiring lock {class} at {acquired_at}, while holding lock {} (acquired at {})",
+            holding.class, holding.acquired_at
+        );
+    }
+}
+
+/// Records a lock of `class` as acquired (at `acquired_at`) without checking its order, as a successful `try_lock`
+/// can't deadlock.
+pub fn acquired(class: Class, acquired_at: &'static Location<'static>) {
+    with_held(|held| push(held, class, acquired_at));
+}
+
+fn push(held: &mut Vec<Held, MAX_HELD>, class: Class, acquired_at: &'static Location<'static>) {
+    let entry = Held {
+        class,
+        acquired_at,
+        interrupts_disabled: !crate::interrupts::is_enabled(),
+    };
+
+    if held.push(entry).is_err() {
+        DISABLED.store(true, Ordering::Relaxed);
+        warn!("More than {MAX_HELD} locks held at once; no longer checking lock order.");
+    }
+}
+
+/// Records a lock of `class` as released.
+pub fn release(class: Class) {
+    with_held(|held| {
+        // Locks aren't necessarily released in the reverse order they were acquired.
+        if let Some(index) = held.iter().rposition(|held| held.class == class) {
+            held.remove(index);
+        }
+    });
+}
+
+/// Checks no lock acquired with interrupts disabled is held, as interrupts are about to be enabled.
+///
+/// # Panics
+///
+/// If such a lock is held.
+pub fn enabling_interrupts() {
+    let held =
+        with_held(|held| held.iter().find(|held| held.interrupts_disabled).copied()).flatten();
+
+    if let Some(held) = held {
+        DISABLED.store(true, Ordering::Relaxed);
+
+        panic!(
+            "enabling interrupts while holding lock {} (acquired with interrupts disabled at {})",
+            held.class, held.acquired_at
+        );
+    }
+}
+
+/// Logs the stack `dependency` was recorded at, and stops checking, so the panic which follows isn't recursively
+/// reported.
+fn report_dependency(dependency: &Dependency) {
+    DISABLED.store(true, Ordering::Relaxed);
+
+    error!(
+        "Lock {} was previously acquired while holding lock {} (hwthread #{}):",
+        dependency.after, dependency.before, dependency.hwthread_id
+    );
+
+    for (depth, address) in dependency.frames.iter().enumerate() {
+        let mut line = heapless::String::<256>::new();
+        crate::panic::tracing::write_frame(&mut line, depth, *address).ok();
+        error!("{}", line.trim_end());
+    }
+}
@@ -0,0 +1,27 @@
+//! Kernel synchronization primitives.
+//!
+//! [`SpinMutex`] and [`SpinRwLock`] wrap their `spin` counterparts, and should be used in their place throughout the
+//! kernel. With the `lockdep` feature enabled, every acquisition is checked against the order locks have previously
+//! been acquired in, catching potential deadlocks on their first occurrence rather than their first hang; see
+//! [`lockdep`] for what is checked.
+//!
+//! [`Mutex`] and [`Semaphore`] instead put the active task to sleep on contention, for long critical sections in
+//! system calls, and [`Rcu`] lets read-mostly data be read without locking at all.
+//!
+//! # Remarks
+//!
+//! A handful of locks on the panic and NMI paths deliberately remain plain `spin` locks, so they can't recurse into
+//! the checks.
+
+#[cfg(feature = "lockdep")]
+pub mod lockdep;
+mod mutex;
+pub mod rcu;
+mod semaphore;
+mod spinlock;
+mod wait_queue;
+
+pub use mutex::*;
+pub use rcu::Rcu;
+pub use semaphore::*;
+pub use spinlock::*;
@@ -0,0 +1,120 @@
+use super::wait_queue::{Take, WaitQueue};
+use crate::{arch::x86_64::structures::idt::InterruptStackFrame, task::Registers};
+use core::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+};
+
+/// A mutual exclusion lock which puts the active task to sleep while it's held elsewhere, rather than spinning.
+///
+/// # Remarks
+///
+/// This is intended for long critical sections in system calls (i.e. driver I/O), where spinning would waste the
+/// hardware thread. Short critical sections, and any taken from interrupt handlers, should use a [`SpinMutex`].
+///
+/// [`SpinMutex`]: super::SpinMutex
+pub struct Mutex<T> {
+    /// Whether the lock is held.
+    queue: WaitQueue<bool>,
+    value: UnsafeCell<T>,
+}
+
+// Safety: Access to `value` is excluded by `queue`.
+unsafe impl<T: Send> Sync for Mutex<T> {}
+// Safety: See above.
+unsafe impl<T: Send> Send for Mutex<T> {}
+
+fn try_take(locked: &mut bool) -> bool {
+    !core::mem::replace(locked, true)
+}
+
+impl<T> Mutex<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            queue: WaitQueue::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Acquires the lock, putting the active task to sleep until it's released if it's held.
+    ///
+    /// Returns `None` if the task was put to sleep, in which case the system call is restarted once it's woken.
+    ///
+    /// # Remarks
+    ///
+    /// This must be called from a system call, as the active task may be switched out in place. If there's no active
+    /// task, this spins instead.
+    pub fn lock(
+        &self,
+        isf: &mut InterruptStackFrame,
+        regs: &mut Registers,
+    ) -> Option<MutexGuard<'_, T>> {
+        match self.queue.take_or_block(try_take, isf, regs) {
+            Take::Taken => Some(MutexGuard { mutex: self }),
+            Take::Blocked => None,
+        }
+    }
+
+    /// Acquires the lock if it isn't held.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        // The guard is only constructed once taken, as dropping it releases what it holds.
+        if self.queue.with_state(try_take) {
+            Some(MutexGuard { mutex: self })
+        } else {
+            None
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.queue.with_state(|locked| *locked)
+    }
+}
+
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: The guard holds the lock.
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: The guard holds the lock.
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.queue.give_and_wake(|locked| *locked = false);
+    }
+}
+
+crate::kernel_test! {
+    /// A held lock excludes other acquisitions until its guard is dropped.
+    fn exclusion() -> crate::tests::Outcome {
+        let mutex = Mutex::new(0);
+
+        let mut guard = mutex.try_lock().ok_or("unheld lock was not acquired")?;
+        *guard += 1;
+
+        if mutex.try_lock().is_some() {
+            return Err("held lock was acquired");
+        }
+
+        drop(guard);
+
+        let guard = mutex.try_lock().ok_or("released lock was not acquired")?;
+        if *guard != 1 {
+            return Err("value was not retained");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+//! Read-copy-update, for read-mostly data which is read from interrupt context.
+//!
+//! Readers of an [`Rcu`] take no lock: they disable interrupts, and dereference its current pointer. Writers copy the
+//! value, update the copy, and publish it in place of the old value, which is only freed once every hardware thread
+//! has passed through a quiescent state (i.e. a grace period has elapsed), as any reader which could have seen it
+//! must by then have finished.
+//!
+//! A hardware thread is quiescent whenever its scheduler runs, as that's an interrupt, which can't be taken within a
+//! read-side critical section. Each grace period is numbered; every hardware thread records the latest number it has
+//! seen when quiescent, and deferred work runs once every online hardware thread has seen its number.
+
+use super::SpinMutex;
+use crate::cpu::percpu::{self, PerCpu};
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
+use core::sync::atomic::{AtomicPtr, AtomicU64, Ordering};
+
+/// Work deferred until a grace period has elapsed.
+type Callback = Box<dyn FnOnce() + Send>;
+
+/// Number of the most recently started grace period.
+static STARTED: AtomicU64 = AtomicU64::new(0);
+
+/// Deferred work, and the grace period it waits on, in order of grace period.
+static CALLBACKS: SpinMutex<VecDeque<(u64, Callback)>> = SpinMutex::new(VecDeque::new());
+
+/// Brings the current hardware thread online, so grace periods wait on it.
+///
+/// # Remarks
+///
+/// Every hardware thread should call this once its scheduler is initialized. Until then, it must not read any
+/// [`Rcu`], which is the case as hardware threads don't take interrupts until then.
+pub fn online() {
+    let block = PerCpu::current();
+
+    block
+        .rcu_seen
+        .store(STARTED.load(Ordering::SeqCst), Ordering::SeqCst);
+    block.rcu_online.store(true, Ordering::SeqCst);
+}
+
+/// Reports a quiescent state for the current hardware thread, then runs any deferred work whose grace period has
+/// elapsed.
+///
+/// # Remarks
+///
+/// This must only be called outside of any read-side critical section; the scheduler calls it on every tick.
+pub fn quiescent() {
+    let block = PerCpu::current();
+    if !block.rcu_online.load(Ordering::Relaxed) {
+        return;
+    }
+
+    block
+        .rcu_seen
+        .store(STARTED.load(Ordering::SeqCst), Ordering::SeqCst);
+
+    run_elapsed();
+}
+
+/// The most recent grace period to have elapsed.
+fn completed() -> u64 {
+    // Every hardware thread which can run has a block, so none is left out.
+    percpu::blocks()
+        .filter(|block| block.rcu_online.load(Ordering::SeqCst))
+        .map(|block| block.rcu_seen.load(Ordering::SeqCst))
+        .min()
+        // With no hardware threads online, there are no readers to wait on.
+        .unwrap_or(u64::MAX)
+}
+
+fn run_elapsed() {
+    let completed = completed();
+
+    let elapsed = crate::interrupts::uninterruptable(|| {
+        let mut callbacks = CALLBACKS.lock();
+        let elapsed_count = callbacks
+            .iter()
+            .take_while(|(grace_period, _)| *grace_period <= completed)
+            .count();
+
+        callbacks.drain(..elapsed_count).collect::<Vec<_>>()
+    });
+
+    // Deferred work runs without the queue locked, as it may defer more.
+    for (_, callback) in elapsed {
+        callback();
+    }
+}
+
+/// Runs `callback` once a grace period has elapsed, i.e. once no reader can still see anything unpublished before
+/// this call.
+pub fn defer(callback: impl FnOnce() + Send + 'static) {
+    let grace_period = STARTED.fetch_add(1, Ordering::SeqCst) + 1;
+
+    crate::interrupts::uninterruptable(|| {
+        CALLBACKS
+            .lock()
+            .push_back((grace_period, Box::new(callback)));
+    });
+
+    // Hardware threads only run deferred work when quiescent, so it's run here if none are online.
+    if completed() == u64::MAX {
+        run_elapsed();
+    }
+}
+
+/// Deferred work which hasn't yet run.
+pub fn pending() -> usize {
+    crate::interrupts::uninterruptable(|| CALLBACKS.lock().len())
+}
+
+/// A value which is read without locking, and replaced (rather than modified) by writers.
+pub struct Rcu<T> {
+    value: AtomicPtr<T>,
+    /// Serializes writers, so no update is lost.
+    writer: SpinMutex<()>,
+}
+
+impl<T: Send + Sync + 'static> Rcu<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value: AtomicPtr::new(Box::into_raw(Box::new(value))),
+            writer: SpinMutex::new(()),
+        }
+    }
+
+    /// Calls `func` with the current value.
+    ///
+    /// # Remarks
+    ///
+    /// Interrupts are disabled while `func` runs, so it should be short.
+    pub fn read<U>(&self, func: impl FnOnce(&T) -> U) -> U {
+        crate::interrupts::uninterruptable(|| {
+            // Safety: A replaced value is only freed after a grace period, which can't elapse while this hardware
+            //         thread has interrupts disabled.
+            func(unsafe { &*self.value.load(Ordering::Acquire) })
+        })
+    }
+
+    /// Replaces the value with the one returned by `func`, which is passed the current value; the current value is
+    /// freed once no reader can see it.
+    pub fn update(&self, func: impl FnOnce(&T) -> T) {
+        let _writer = self.writer.lock();
+
+        // Safety: The current value is only freed once replaced, which only a writer can do.
+        let current = unsafe { &*self.value.load(Ordering::Acquire) };
+        let replaced = self
+            .value
+            .swap(Box::into_raw(Box::new(func(current))), Ordering::AcqRel)
+            .expose_provenance();
+
+        defer(move || {
+            // Safety: The value was allocated by `Box::into_raw`, and is no longer published, so after a grace
+            //         period it's unreachable.
+            drop(unsafe { Box::from_raw(core::ptr::with_exposed_provenance_mut::<T>(replaced)) });
+        });
+    }
+}
+
+impl<T> Drop for Rcu<T> {
+    fn drop(&mut self) {
+        // Safety: Readers borrow the `Rcu`, so there are none, and the value was allocated by `Box::into_raw`.
+        drop(unsafe { Box::from_raw(*self.value.get_mut()) });
+    }
+}
+
+crate::kernel_test! {
+    /// Readers see an update once it's published.
+    fn update() -> crate::tests::Outcome {
+        let rcu = Rcu::new(1u64);
+        rcu.update(|value| value + 1);
+
+        if rcu.read(|value| *value) != 2 {
+            return Err("update was not published");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+use super::wait_queue::{Take, WaitQueue};
+use crate::{arch::x86_64::structures::idt::InterruptStackFrame, task::Registers};
+
+/// A counting semaphore, which puts the active task to sleep while no permits are available, rather than spinning.
+///
+/// # Remarks
+///
+/// See [`Mutex`](super::Mutex) for the contexts this may be used from.
+pub struct Semaphore {
+    /// Permits available to be acquired.
+    queue: WaitQueue<usize>,
+}
+
+fn try_take(permits: &mut usize) -> bool {
+    permits
+        .checked_sub(1)
+        .map(|remaining| *permits = remaining)
+        .is_some()
+}
+
+impl Semaphore {
+    pub const fn new(permits: usize) -> Self {
+        Self {
+            queue: WaitQueue::new(permits),
+        }
+    }
+
+    /// Acquires a permit, putting the active task to sleep until one is released if there are none.
+    ///
+    /// Returns `None` if the task was put to sleep, in which case the system call is restarted once it's woken.
+    ///
+    /// # Remarks
+    ///
+    /// This must be called from a system call, as the active task may be switched out in place. If there's no active
+    /// task, this spins instead.
+    pub fn acquire(
+        &self,
+        isf: &mut InterruptStackFrame,
+        regs: &mut Registers,
+    ) -> Option<SemaphorePermit<'_>> {
+        match self.queue.take_or_block(try_take, isf, regs) {
+            Take::Taken => Some(SemaphorePermit { semaphore: self }),
+            Take::Blocked => None,
+        }
+    }
+
+    /// Acquires a permit if one is available.
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        // The permit is only constructed once taken, as dropping it releases what it holds.
+        if self.queue.with_state(try_take) {
+            Some(SemaphorePermit { semaphore: self })
+        } else {
+            None
+        }
+    }
+
+    /// Adds a permit, waking a task waiting for one.
+    ///
+    /// # Remarks
+    ///
+    /// Permits are usually released by dropping a [`SemaphorePermit`]; this is for producers which don't acquire
+    /// them (i.e. an interrupt handler signalling completed I/O).
+    pub fn release(&self) {
+        self.queue
+            .give_and_wake(|permits| *permits = permits.saturating_add(1));
+    }
+
+    /// Permits currently available.
+    pub fn available(&self) -> usize {
+        self.queue.with_state(|permits| *permits)
+    }
+}
+
+/// A permit acquired from a [`Semaphore`], released when dropped.
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl SemaphorePermit<'_> {
+    /// Consumes the permit without releasing it, i.e. when the resource it represents is used up.
+    pub fn forget(self) {
+        core::mem::forget(self);
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
+
+crate::kernel_test! {
+    /// Permits are counted out, and returned when dropped.
+    fn permits() -> crate::tests::Outcome {
+        let semaphore = Semaphore::new(2);
+
+        let first = semaphore.try_acquire().ok_or("first permit was not acquired")?;
+        let second = semaphore.try_acquire().ok_or("second permit was not acquired")?;
+        if semaphore.try_acquire().is_some() {
+            return Err("acquired more permits than exist");
+        }
+
+        drop(first);
+        second.forget();
+
+        if semaphore.available() != 1 {
+            return Err("dropped permit was not returned, or forgotten permit was");
+        }
+
+        Ok(())
+    }
+}
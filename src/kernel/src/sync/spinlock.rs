@@ -0,0 +1,221 @@
+use core::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+#[cfg(feature = "lockdep")]
+use super::lockdep::{self, Class};
+
+/// A spinning mutual exclusion lock; see [`spin::Mutex`].
+///
+/// # Remarks
+///
+/// Locks constructed at the same place in the source share a lock class, which is what the `lockdep` feature orders.
+pub struct SpinMutex<T> {
+    #[cfg(feature = "lockdep")]
+    class: Class,
+    inner: spin::Mutex<T>,
+}
+
+impl<T> SpinMutex<T> {
+    #[track_caller]
+    pub const fn new(value: T) -> Self {
+        Self {
+            #[cfg(feature = "lockdep")]
+            class: core::panic::Location::caller(),
+            inner: spin::Mutex::new(value),
+        }
+    }
+
+    /// Spins until the lock is acquired.
+    ///
+    /// # Remarks
+    ///
+    /// TLB shootdowns are serviced while spinning, as the holder may be waiting on this hardware thread to invalidate
+    /// its translations (see [`crate::mem::tlb`]).
+    #[track_caller]
+    pub fn lock(&self) -> SpinMutexGuard<'_, T> {
+        #[cfg(feature = "lockdep")]
+        lockdep::acquire(self.class, core::panic::Location::caller());
+
+        let inner = loop {
+            if let Some(inner) = self.inner.try_lock() {
+                break inner;
+            }
+
+            while self.inner.is_locked() {
+                crate::mem::tlb::service();
+                core::hint::spin_loop();
+            }
+        };
+
+        SpinMutexGuard {
+            #[cfg(feature = "lockdep")]
+            class: self.class,
+            inner,
+        }
+    }
+
+    /// Acquires the lock if it isn't already held.
+    #[track_caller]
+    pub fn try_lock(&self) -> Option<SpinMutexGuard<'_, T>> {
+        let inner = self.inner.try_lock()?;
+
+        // A failed attempt can't deadlock, so only successful ones are recorded.
+        #[cfg(feature = "lockdep")]
+        lockdep::acquired(self.class, core::panic::Location::caller());
+
+        Some(SpinMutexGuard {
+            #[cfg(feature = "lockdep")]
+            class: self.class,
+            inner,
+        })
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+}
+
+impl<T: Default> Default for SpinMutex<T> {
+    #[track_caller]
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SpinMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+pub struct SpinMutexGuard<'a, T> {
+    #[cfg(feature = "lockdep")]
+    class: Class,
+    inner: spin::MutexGuard<'a, T>,
+}
+
+impl<T> Deref for SpinMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for SpinMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+#[cfg(feature = "lockdep")]
+impl<T> Drop for SpinMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        lockdep::release(self.class);
+    }
+}
+
+/// A spinning reader-writer lock; see [`spin::RwLock`].
+///
+/// # Remarks
+///
+/// Readers and writers are ordered as one lock class, so a read acquisition in one order and a write acquisition in
+/// the other is reported, even though two readers wouldn't deadlock.
+pub struct SpinRwLock<T> {
+    #[cfg(feature = "lockdep")]
+    class: Class,
+    inner: spin::RwLock<T>,
+}
+
+impl<T> SpinRwLock<T> {
+    #[track_caller]
+    pub const fn new(value: T) -> Self {
+        Self {
+            #[cfg(feature = "lockdep")]
+            class: core::panic::Location::caller(),
+            inner: spin::RwLock::new(value),
+        }
+    }
+
+    /// Spins until a shared lock is acquired.
+    #[track_caller]
+    pub fn read(&self) -> SpinRwLockReadGuard<'_, T> {
+        #[cfg(feature = "lockdep")]
+        lockdep::acquire(self.class, core::panic::Location::caller());
+
+        SpinRwLockReadGuard {
+            #[cfg(feature = "lockdep")]
+            class: self.class,
+            inner: self.inner.read(),
+        }
+    }
+
+    /// Spins until the exclusive lock is acquired.
+    #[track_caller]
+    pub fn write(&self) -> SpinRwLockWriteGuard<'_, T> {
+        #[cfg(feature = "lockdep")]
+        lockdep::acquire(self.class, core::panic::Location::caller());
+
+        SpinRwLockWriteGuard {
+            #[cfg(feature = "lockdep")]
+            class: self.class,
+            inner: self.inner.write(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SpinRwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+pub struct SpinRwLockReadGuard<'a, T> {
+    #[cfg(feature = "lockdep")]
+    class: Class,
+    inner: spin::RwLockReadGuard<'a, T>,
+}
+
+impl<T> Deref for SpinRwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+#[cfg(feature = "lockdep")]
+impl<T> Drop for SpinRwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        lockdep::release(self.class);
+    }
+}
+
+pub struct SpinRwLockWriteGuard<'a, T> {
+    #[cfg(feature = "lockdep")]
+    class: Class,
+    inner: spin::RwLockWriteGuard<'a, T>,
+}
+
+impl<T> Deref for SpinRwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for SpinRwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+#[cfg(feature = "lockdep")]
+impl<T> Drop for SpinRwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        lockdep::release(self.class);
+    }
+}
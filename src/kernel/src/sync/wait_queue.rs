@@ -0,0 +1,92 @@
+use super::SpinMutex;
+use crate::{
+    arch::x86_64::structures::idt::InterruptStackFrame, cpu::local_state::LocalState,
+    task::Registers,
+};
+use alloc::collections::VecDeque;
+use uuid::Uuid;
+
+/// Outcome of [`WaitQueue::take_or_block`].
+pub(super) enum Take {
+    Taken,
+    /// The active task was blocked, and its system call will be restarted once it's woken.
+    Blocked,
+}
+
+/// State shared by the tasks sleeping on it, and the queue of those tasks.
+///
+/// # Remarks
+///
+/// A woken task retries from the start of its system call, rather than being handed what it waited on, so another
+/// task may take it first; the woken task then waits again.
+pub(super) struct WaitQueue<S> {
+    inner: SpinMutex<(S, VecDeque<Uuid>)>,
+}
+
+impl<S> WaitQueue<S> {
+    pub const fn new(state: S) -> Self {
+        Self {
+            inner: SpinMutex::new((state, VecDeque::new())),
+        }
+    }
+
+    pub fn with_state<T>(&self, func: impl FnOnce(&mut S) -> T) -> T {
+        crate::interrupts::uninterruptable(|| func(&mut self.inner.lock().0))
+    }
+
+    /// Calls `try_take` with the state, blocking the active task until it's woken if it fails.
+    ///
+    /// If there's no active task (i.e. during kernel init), there's nothing to block, so this spins instead.
+    ///
+    /// # Remarks
+    ///
+    /// This must be called from a system call, as the active task may be switched out in place.
+    pub fn take_or_block(
+        &self,
+        mut try_take: impl FnMut(&mut S) -> bool,
+        isf: &mut InterruptStackFrame,
+        regs: &mut Registers,
+    ) -> Take {
+        let blocked = LocalState::with_scheduler(|scheduler| {
+            let mut inner = self.inner.lock();
+            if try_take(&mut inner.0) {
+                return Some(Take::Taken);
+            }
+
+            let task_id = scheduler.process()?.id();
+            inner.1.push_back(task_id);
+
+            // Safety: The task's registers are unchanged, so the same call is issued when the task is woken.
+            unsafe {
+                crate::interrupts::syscall::restart(isf);
+            }
+
+            // The task is blocked with the queue locked, so a wake can't try to unblock it before it's blocked.
+            scheduler.block_task(isf, regs);
+
+            Some(Take::Blocked)
+        });
+
+        blocked.unwrap_or_else(|| {
+            while !self.with_state(&mut try_take) {
+                core::hint::spin_loop();
+            }
+
+            Take::Taken
+        })
+    }
+
+    /// Calls `give` with the state, then wakes the longest-waiting task.
+    pub fn give_and_wake(&self, give: impl FnOnce(&mut S)) {
+        let waiter = crate::interrupts::uninterruptable(|| {
+            let mut inner = self.inner.lock();
+            give(&mut inner.0);
+
+            inner.1.pop_front()
+        });
+
+        if let Some(task_id) = waiter {
+            crate::task::unblock_task(task_id);
+        }
+    }
+}
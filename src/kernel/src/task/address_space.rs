@@ -1,10 +1,14 @@
 use crate::mem::{
+    HigherHalfDirectMap,
     mapper::Mapper,
     paging,
     paging::{TableDepth, TableEntryFlags},
+    pmm::PhysicalMemoryManager,
 };
+use crate::task::shared_memory::SharedMemory;
+use alloc::{sync::Arc, vec::Vec};
 use core::{num::NonZeroUsize, ptr::NonNull};
-use libsys::{Address, Page, Virtual, page_size};
+use libsys::{Address, Frame, Page, Virtual, page_size};
 
 #[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
 #[allow(clippy::enum_variant_names)]
@@ -29,7 +33,9 @@ pub enum Error {
     Mapper(#[from] paging::Error),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Permissions of mapped memory; as a system call argument, in declaration order from `0`.
+#[repr(usize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
 #[allow(clippy::enum_variant_names)]
 pub enum MmapPermissions {
     ReadExecute,
@@ -49,12 +55,25 @@ impl From<MmapPermissions> for TableEntryFlags {
 
 pub const DEFAULT_USERSPACE_SIZE: NonZeroUsize = NonZeroUsize::new(1 << 47).unwrap();
 
-pub struct AddressSpace(Mapper);
+pub struct AddressSpace {
+    mapper: Mapper,
+    /// Pages mapped by [`Self::map_shared`], whose frames aren't freed with the address space.
+    shared: Vec<Address<Page>>,
+    /// Shared memory objects mapped by [`Self::map_object`], by the page they're mapped at.
+    objects: Vec<(Address<Page>, Arc<SharedMemory>)>,
+    /// Device memory mapped by [`Self::map_mmio`], by the page it's mapped at, along with its page count.
+    mmio: Vec<(Address<Page>, usize)>,
+}
 
 impl AddressSpace {
     #[inline]
     pub const fn new(mapper: Mapper) -> Self {
-        Self(mapper)
+        Self {
+            mapper,
+            shared: Vec::new(),
+            objects: Vec::new(),
+            mmio: Vec::new(),
+        }
     }
 
     pub fn new_userspace() -> Self {
@@ -70,7 +89,7 @@ impl AddressSpace {
     }
 
     pub fn is_current(&self) -> bool {
-        let root_frame = self.0.root_frame();
+        let root_frame = self.mapper.root_frame();
         let cr3_frame = crate::mem::PagingRegister::read().frame();
 
         root_frame == cr3_frame
@@ -100,7 +119,7 @@ impl AddressSpace {
     ) -> Result<NonNull<[u8]>, Error> {
         // let walker = unsafe {
         //     paging::walker::Walker::new(
-        //         self.0.view_page_table(),
+        //         self.mapper.view_page_table(),
         //         TableDepth::max(),
         //         TableDepth::min(),
         //     )
@@ -174,7 +193,7 @@ impl AddressSpace {
         (0..mapping_size)
             .step_by(page_size())
             .map(|offset| Address::new_truncate(address.get().get() + offset))
-            .try_for_each(|offset_page| self.0.auto_map(offset_page, flags))?;
+            .try_for_each(|offset_page| self.mapper.auto_map(offset_page, flags))?;
 
         Ok(NonNull::slice_from_raw_parts(
             NonNull::new(address.as_ptr()).unwrap(),
@@ -182,6 +201,161 @@ impl AddressSpace {
         ))
     }
 
+    /// Maps `frame` into the address space at `address` with `permissions`, without taking ownership of it.
+    ///
+    /// # Remarks
+    ///
+    /// This is intended for frames shared with the kernel (i.e. the time page), so it's up to the caller to ensure
+    /// userspace can't use `permissions` to corrupt the kernel's use of `frame`.
+    pub fn map_shared(
+        &mut self,
+        address: Address<Page>,
+        frame: Address<Frame>,
+        permissions: MmapPermissions,
+    ) -> Result<(), Error> {
+        self.mapper.map(
+            address,
+            TableDepth::min(),
+            frame,
+            false,
+            TableEntryFlags::PRESENT | TableEntryFlags::USER | TableEntryFlags::from(permissions),
+        )?;
+        self.shared.push(address);
+
+        Ok(())
+    }
+
+    /// Maps every frame of `memory` into the address space from `address` with `permissions`, holding a reference
+    /// to it until it's unmapped by [`Self::unmap_object`] (or the address space is dropped).
+    pub fn map_object(
+        &mut self,
+        address: Address<Page>,
+        memory: Arc<SharedMemory>,
+        permissions: MmapPermissions,
+    ) -> Result<(), Error> {
+        let pages = (0..memory.frames().len())
+            .map(|offset| Address::<Page>::from_index(address.index() + offset))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(Error::AddressRangeOverrun)?;
+        if pages.iter().any(|page| self.is_mmapped(*page)) {
+            return Err(Error::InvalidAddress);
+        }
+
+        let flags =
+            TableEntryFlags::PRESENT | TableEntryFlags::USER | TableEntryFlags::from(permissions);
+        let result = pages
+            .iter()
+            .zip(memory.frames())
+            .try_for_each(|(page, frame)| {
+                self.mapper
+                    .map(*page, TableDepth::min(), *frame, false, flags)
+            });
+
+        if let Err(error) = result {
+            let start = address.get().get();
+
+            // Safety: The range was unmapped before, so only the object's frames (which aren't freed) are unmapped.
+            unsafe {
+                self.mapper
+                    .unmap_range(start..(start + (pages.len() * page_size())), false)?;
+            }
+
+            return Err(error.into());
+        }
+
+        self.objects.push((address, memory));
+
+        Ok(())
+    }
+
+    /// Unmaps the shared memory object mapped at `address` by [`Self::map_object`], returning the reference to it.
+    pub fn unmap_object(&mut self, address: Address<Page>) -> Result<Arc<SharedMemory>, Error> {
+        let index = self
+            .objects
+            .iter()
+            .position(|(object_address, _)| *object_address == address)
+            .ok_or(Error::NotMapped(address.get()))?;
+
+        let start = address.get().get();
+        let len = self.objects[index].1.frames().len() * page_size();
+
+        // Safety: Userspace is responsible for its own use of the mapping; the frames are the object's, so they aren't
+        //         freed.
+        unsafe {
+            self.mapper.unmap_range(start..(start + len), false)?;
+        }
+
+        Ok(self.objects.swap_remove(index).1)
+    }
+
+    /// Maps the `page_count` frames of device memory from `frame` into the address space from `address`, readable and
+    /// writable by userspace, and uncacheable.
+    ///
+    /// # Remarks
+    ///
+    /// The frames aren't owned by the address space, so it's up to the caller to ensure they're device memory which
+    /// the address space's process is permitted to access.
+    pub fn map_mmio(
+        &mut self,
+        address: Address<Page>,
+        frame: Address<Frame>,
+        page_count: usize,
+    ) -> Result<(), Error> {
+        let pages = (0..page_count)
+            .map(|offset| {
+                Address::<Page>::from_index(address.index() + offset)
+                    .zip(Address::<Frame>::from_index(frame.index() + offset))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(Error::AddressRangeOverrun)?;
+        if pages.iter().any(|(page, _)| self.is_mmapped(*page)) {
+            return Err(Error::InvalidAddress);
+        }
+
+        let flags = TableEntryFlags::PRESENT | TableEntryFlags::USER | TableEntryFlags::MMIO;
+        let result = pages.iter().try_for_each(|(page, frame)| {
+            self.mapper
+                .map(*page, TableDepth::min(), *frame, false, flags)
+        });
+
+        if let Err(error) = result {
+            let start = address.get().get();
+
+            // Safety: The range was unmapped before, so only device memory (which isn't freed) is unmapped.
+            unsafe {
+                self.mapper
+                    .unmap_range(start..(start + (pages.len() * page_size())), false)?;
+            }
+
+            return Err(error.into());
+        }
+
+        self.mmio.push((address, page_count));
+
+        Ok(())
+    }
+
+    /// Unmaps the device memory mapped at `address` by [`Self::map_mmio`].
+    pub fn unmap_mmio(&mut self, address: Address<Page>) -> Result<(), Error> {
+        let index = self
+            .mmio
+            .iter()
+            .position(|(mmio_address, _)| *mmio_address == address)
+            .ok_or(Error::NotMapped(address.get()))?;
+
+        let start = address.get().get();
+        let len = self.mmio[index].1 * page_size();
+
+        // Safety: Userspace is responsible for its own use of the mapping; it's device memory, so it isn't freed.
+        unsafe {
+            self.mapper.unmap_range(start..(start + len), false)?;
+        }
+
+        self.mmio.swap_remove(index);
+
+        Ok(())
+    }
+
     /// # Safety
     ///
     /// TODO
@@ -191,33 +365,57 @@ impl AddressSpace {
         page_count: NonZeroUsize,
         flags: TableEntryFlags,
     ) -> Result<(), Error> {
-        for index_offset in 0..page_count.get() {
-            let offset_index = address.index() + index_offset;
-            let offset_address =
-                Address::from_index(offset_index).ok_or(Error::AddressRangeOverrun)?;
+        let end = Address::from_index(address.index() + page_count.get())
+            .ok_or(Error::AddressRangeOverrun)?;
+
+        // Safety: Caller is required to maintain safety invariants.
+        unsafe {
+            self.mapper
+                .protect(address..end, flags, paging::FlagsModify::Set)?;
+        }
+
+        Ok(())
+    }
 
-            // Safety: Caller is required to maintain safety invariants.
+    /// Copies `bytes` into the address space at `address`, through the higher-half direct map, so the address space
+    /// needn't be current.
+    pub fn write(&mut self, address: Address<Virtual>, bytes: &[u8]) -> Result<(), Error> {
+        let mut address = address.get();
+        let mut bytes = bytes;
+
+        while !bytes.is_empty() {
+            let page = Address::<Page>::new_truncate(address);
+            let frame = self
+                .mapper
+                .get_mapped_to(page)
+                .ok_or(Error::NotMapped(page.get()))?;
+
+            let page_offset = address - page.get().get();
+            let (chunk, remaining) = bytes.split_at(bytes.len().min(page_size() - page_offset));
+
+            // Safety: The frame is mapped into this address space, so it's owned by it, and the chunk is within it.
             unsafe {
-                self.0.set_page_attributes(
-                    offset_address,
-                    None,
-                    flags,
-                    paging::FlagsModify::Set,
-                )?;
+                HigherHalfDirectMap::frame_to_page(frame)
+                    .as_ptr()
+                    .add(page_offset)
+                    .copy_from_nonoverlapping(chunk.as_ptr(), chunk.len());
             }
+
+            address += chunk.len();
+            bytes = remaining;
         }
 
         Ok(())
     }
 
     pub fn get_flags(&self, address: Address<Page>) -> Result<TableEntryFlags, Error> {
-        self.0
+        self.mapper
             .get_page_attributes(address)
             .ok_or(Error::NotMapped(address.get()))
     }
 
     pub fn is_mmapped(&self, address: Address<Page>) -> bool {
-        self.0.is_mapped(address, None)
+        self.mapper.is_mapped(address, None)
     }
 
     /// # Safety
@@ -226,15 +424,70 @@ impl AddressSpace {
     pub unsafe fn swap_into(&self) {
         // Safety: Caller is required to maintain safety invariants.
         unsafe {
-            self.0.swap_into();
+            self.mapper.swap_into();
+        }
+    }
+}
+
+impl Drop for AddressSpace {
+    fn drop(&mut self) {
+        debug_assert!(!self.is_current(), "dropped the current address space");
+
+        // Shared pages, objects, and device memory are unmapped first, so their frames are left to their owners.
+        let shared = self.shared.iter().map(|page| (*page, page_size()));
+        let objects = self
+            .objects
+            .iter()
+            .map(|(page, memory)| (*page, memory.frames().len() * page_size()));
+        let mmio = self
+            .mmio
+            .iter()
+            .map(|(page, page_count)| (*page, page_count * page_size()));
+        let result = shared
+            .chain(objects)
+            .chain(mmio)
+            .try_for_each(|(page, len)| {
+                let start = page.get().get();
+
+                // Safety: The address space is being dropped, so nothing uses the memory mapped into it.
+                unsafe { self.mapper.unmap_range(start..(start + len), false) }
+            });
+
+        // Safety: The address space is being dropped, so nothing uses the memory mapped into it.
+        if let Err(error) = result.and_then(|()| unsafe {
+            self.mapper
+                .unmap_range(0..DEFAULT_USERSPACE_SIZE.get(), true)
+        }) {
+            warn!("Failed to free userspace memory of a dropped address space: {error}");
         }
+
+        // Unmapping leaves the tables the root table references, as the kernel's are shared by every address space;
+        // those which map userspace are this address space's own (unless the kernel happened to share one).
+        let userspace_entries = DEFAULT_USERSPACE_SIZE
+            .get()
+            .div_ceil(TableDepth::max().next().align());
+        crate::mem::with_kernel_mapper(|kmapper| {
+            self.mapper
+                .view_page_table()
+                .iter()
+                .zip(kmapper.view_page_table())
+                .take(userspace_entries)
+                .filter(|(entry, kernel_entry)| {
+                    entry.is_present() && entry.get_frame() != kernel_entry.get_frame()
+                })
+                .try_for_each(|(entry, _)| PhysicalMemoryManager::free_frame(entry.get_frame()))
+        })
+        .and_then(|()| PhysicalMemoryManager::free_frame(self.mapper.root_frame()))
+        .unwrap_or_else(|error| {
+            warn!("Failed to free page tables of a dropped address space: {error}")
+        });
     }
 }
 
 impl core::fmt::Debug for AddressSpace {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_tuple("AddressSpace")
-            .field(&self.0.view_page_table().as_ptr())
+            .field(&self.mapper.view_page_table().as_ptr())
             .finish()
     }
 }
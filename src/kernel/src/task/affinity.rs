@@ -0,0 +1,186 @@
+use spin::Once;
+
+crate::parameters! {
+    /// Hardware threads (by ID, i.e. `2,4-7`) isolated from scheduling; they only run tasks pinned to them.
+    "isolcpus": str = "",
+}
+
+/// Number of hardware thread IDs an [`Affinity`] can name individually.
+pub const AFFINITY_BITS: u32 = 256;
+
+/// Set of hardware threads a task may run on, as a bitmask indexed by hardware thread ID.
+///
+/// # Remarks
+///
+/// Hardware threads with IDs of [`AFFINITY_BITS`] or above can't be named, so they only run tasks whose affinity is
+/// [`Affinity::ALL`].
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromBytes, IntoBytes, Immutable)]
+pub struct Affinity([u64; 4]);
+
+#[derive(Debug, Error)]
+pub enum ParseError<'a> {
+    #[error("{0:?} is not a hardware thread ID, or range of them")]
+    Invalid(&'a str),
+
+    #[error("hardware thread #{0} can't be named in an affinity")]
+    Unnamed(u32),
+}
+
+impl Affinity {
+    /// Every hardware thread.
+    pub const ALL: Self = Self([u64::MAX; 4]);
+    /// No hardware threads.
+    pub const NONE: Self = Self([0; 4]);
+
+    /// Affinity for the single hardware thread `hwthread_id`, if it can be named.
+    pub fn only(hwthread_id: u32) -> Option<Self> {
+        let mut affinity = Self::NONE;
+        affinity.insert(hwthread_id).then_some(affinity)
+    }
+
+    /// Parses a comma-separated list of hardware thread IDs and inclusive ranges of them, i.e. `2,4-7`.
+    pub fn parse(list: &str) -> Result<Self, ParseError<'_>> {
+        let mut affinity = Self::NONE;
+
+        for item in list
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+        {
+            let parse_id = |id: &str| {
+                id.trim()
+                    .parse::<u32>()
+                    .map_err(|_| ParseError::Invalid(item))
+            };
+
+            let (first, last) = match item.split_once('-') {
+                Some((first, last)) => (parse_id(first)?, parse_id(last)?),
+                None => {
+                    let id = parse_id(item)?;
+                    (id, id)
+                }
+            };
+
+            if first > last {
+                return Err(ParseError::Invalid(item));
+            }
+
+            for hwthread_id in first..=last {
+                if !affinity.insert(hwthread_id) {
+                    return Err(ParseError::Unnamed(hwthread_id));
+                }
+            }
+        }
+
+        Ok(affinity)
+    }
+
+    fn position(hwthread_id: u32) -> Option<(usize, u32)> {
+        let index = usize::try_from(hwthread_id / u64::BITS).ok()?;
+
+        (hwthread_id < AFFINITY_BITS).then_some((index, hwthread_id % u64::BITS))
+    }
+
+    /// Adds `hwthread_id` to the set, returning whether it could be named.
+    pub fn insert(&mut self, hwthread_id: u32) -> bool {
+        let Some((index, bit)) = Self::position(hwthread_id) else {
+            return false;
+        };
+
+        self.0[index] |= 1 << bit;
+
+        true
+    }
+
+    /// Whether the hardware thread `hwthread_id` may run the task.
+    pub fn contains(&self, hwthread_id: u32) -> bool {
+        match Self::position(hwthread_id) {
+            Some((index, bit)) => (self.0[index] & (1 << bit)) != 0,
+            None => *self == Self::ALL,
+        }
+    }
+
+    /// Whether the hardware thread `hwthread_id` may schedule the task: it must be in the set, and if it's
+    /// isolated, the task must be pinned (i.e. its affinity must not be [`Self::ALL`]).
+    pub fn allows(&self, hwthread_id: u32) -> bool {
+        self.contains(hwthread_id) && (*self != Self::ALL || !is_isolated(hwthread_id))
+    }
+
+    /// Whether any hardware thread which is up may schedule the task.
+    pub fn is_runnable(&self) -> bool {
+        crate::cpu::percpu::blocks().any(|block| {
+            self.allows(
+                block
+                    .hwthread_id
+                    .load(core::sync::atomic::Ordering::Relaxed),
+            )
+        })
+    }
+}
+
+/// Hardware threads isolated by the `isolcpus` parameter.
+static ISOLATED: Once<Affinity> = Once::new();
+
+/// Whether the hardware thread `hwthread_id` is isolated, so it only schedules tasks which are pinned to it, and
+/// isn't counted as idle when balancing other tasks across hardware threads.
+pub fn is_isolated(hwthread_id: u32) -> bool {
+    ISOLATED
+        .call_once(|| {
+            let list = crate::params::get::<&str>("isolcpus");
+
+            match Affinity::parse(list) {
+                Ok(isolated) => {
+                    if isolated != Affinity::NONE {
+                        info!("Isolated hardware threads: {list}");
+                    }
+
+                    isolated
+                }
+
+                Err(error) => {
+                    warn!("Ignoring isolated hardware threads {list:?}: {error}");
+                    Affinity::NONE
+                }
+            }
+        })
+        .contains(hwthread_id)
+}
+
+crate::kernel_test! {
+    /// Hardware threads are only contained in affinities which name them, or name every hardware thread.
+    fn affinity_contains_named_threads() -> crate::tests::Outcome {
+        let affinity = Affinity::only(65).ok_or("failed to name hardware thread #65")?;
+
+        if !affinity.contains(65) || affinity.contains(64) || affinity.contains(1) {
+            return Err("single hardware thread affinity contains the wrong hardware threads");
+        }
+
+        if affinity.contains(AFFINITY_BITS) || !Affinity::ALL.contains(AFFINITY_BITS) {
+            return Err("unnamed hardware thread is contained in the wrong affinities");
+        }
+
+        Ok(())
+    }
+
+    /// Hardware thread lists parse to the hardware threads (and ranges of them) they name.
+    fn parses_affinity_lists() -> crate::tests::Outcome {
+        let affinity = Affinity::parse("1, 3-5").map_err(|_| "failed to parse a valid list")?;
+
+        if ![1, 3, 4, 5].into_iter().all(|id| affinity.contains(id))
+            || [0, 2, 6].into_iter().any(|id| affinity.contains(id))
+        {
+            return Err("list parsed to the wrong hardware threads");
+        }
+
+        if Affinity::parse("").ok() != Some(Affinity::NONE)
+            || Affinity::parse("5-3").is_ok()
+            || Affinity::parse("x").is_ok()
+            || Affinity::parse("255-256").is_ok()
+        {
+            return Err("invalid (or empty) list parsed incorrectly");
+        }
+
+        Ok(())
+    }
+}
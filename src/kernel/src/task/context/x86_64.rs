@@ -1,5 +1,11 @@
+use crate::arch::x86_64::{
+    registers::model_specific::{IA32_FS_BASE, IA32_KERNEL_GS_BASE},
+    xsave,
+};
+use core::ptr::NonNull;
+
 #[repr(C)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromBytes, IntoBytes, Immutable)]
 pub struct Registers {
     pub rax: usize,
     pub rbx: usize,
@@ -39,3 +45,78 @@ impl Registers {
         }
     }
 }
+
+/// A task's extended (x87, SSE, AVX) register state, and FS & GS bases, saved while it isn't running.
+pub struct ExtendedState {
+    area: NonNull<u8>,
+    /// Userspace's thread pointer (see [`crate::task::tls`]), which it may change itself with `wrfsbase`.
+    fs_base: u64,
+    /// Userspace's GS base, which it may change itself with `wrgsbase`; it's held in `IA32_KERNEL_GS_BASE` while the
+    /// kernel runs, as the tasks are switched.
+    gs_base: u64,
+}
+
+// Safety: The save area is owned, and only accessed through `&mut self` or by the hardware thread running the task.
+unsafe impl Send for ExtendedState {}
+// Safety: See above.
+unsafe impl Sync for ExtendedState {}
+
+impl ExtendedState {
+    /// Allocates a save area holding the initial state.
+    pub fn new() -> Self {
+        let layout = xsave::area_layout();
+
+        // Safety: The save area is never zero-sized.
+        let area = NonNull::new(unsafe { alloc::alloc::alloc_zeroed(layout) })
+            .unwrap_or_else(|| alloc::alloc::handle_alloc_error(layout));
+
+        // Safety: The area was just allocated with `layout`, and zeroed.
+        xsave::init_area(unsafe { core::slice::from_raw_parts_mut(area.as_ptr(), layout.size()) });
+
+        Self {
+            area,
+            fs_base: 0,
+            gs_base: 0,
+        }
+    }
+
+    /// Saves the current hardware thread's extended state.
+    pub fn save(&mut self) {
+        // Safety: The save area was allocated with the save area layout.
+        unsafe { xsave::save(self.area) }
+
+        self.fs_base = IA32_FS_BASE::read();
+        self.gs_base = IA32_KERNEL_GS_BASE::read();
+    }
+
+    /// Loads the saved extended state into the current hardware thread.
+    pub fn restore(&self) {
+        // Safety: The save area was allocated with the save area layout, and holds saved or initial state.
+        unsafe { xsave::restore(self.area) }
+
+        IA32_FS_BASE::write(self.fs_base);
+        IA32_KERNEL_GS_BASE::write(self.gs_base);
+    }
+
+    /// Sets the FS base the task is next switched in with.
+    pub fn set_fs_base(&mut self, fs_base: u64) {
+        self.fs_base = fs_base;
+    }
+}
+
+impl Drop for ExtendedState {
+    fn drop(&mut self) {
+        // Safety: The save area was allocated with the save area layout, which hasn't changed since.
+        unsafe { alloc::alloc::dealloc(self.area.as_ptr(), xsave::area_layout()) }
+    }
+}
+
+impl core::fmt::Debug for ExtendedState {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ExtendedState")
+            .field("area", &self.area)
+            .field("fs_base", &format_args!("{:#X}", self.fs_base))
+            .field("gs_base", &format_args!("{:#X}", self.gs_base))
+            .finish()
+    }
+}
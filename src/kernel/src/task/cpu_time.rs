@@ -0,0 +1,59 @@
+use core::time::Duration;
+
+/// Privilege level a task is running at, which its CPU time is charged to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    User,
+    Kernel,
+}
+
+/// Time a task has spent running, split by the privilege level it ran at.
+///
+/// # Remarks
+///
+/// Userspace tasks are charged kernel time while servicing their system calls; kernel threads are only ever charged
+/// kernel time. Interrupts are charged to whichever mode they interrupted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuTime {
+    pub user: Duration,
+    pub kernel: Duration,
+}
+
+impl CpuTime {
+    pub const ZERO: Self = Self {
+        user: Duration::ZERO,
+        kernel: Duration::ZERO,
+    };
+
+    /// Charges `duration` to `mode`.
+    pub fn charge(&mut self, mode: Mode, duration: Duration) {
+        match mode {
+            Mode::User => self.user = self.user.saturating_add(duration),
+            Mode::Kernel => self.kernel = self.kernel.saturating_add(duration),
+        }
+    }
+
+    /// Time spent running in either mode.
+    pub fn total(self) -> Duration {
+        self.user.saturating_add(self.kernel)
+    }
+}
+
+crate::kernel_test! {
+    /// Time is charged to the mode it was spent in, and totalled across both.
+    fn charges_cpu_time_by_mode() -> crate::tests::Outcome {
+        let mut cpu_time = CpuTime::ZERO;
+        cpu_time.charge(Mode::User, Duration::from_micros(3));
+        cpu_time.charge(Mode::Kernel, Duration::from_micros(2));
+        cpu_time.charge(Mode::User, Duration::from_micros(1));
+
+        if cpu_time.user != Duration::from_micros(4) || cpu_time.kernel != Duration::from_micros(2) {
+            return Err("time wasn't charged to the mode it was spent in");
+        }
+        if cpu_time.total() != Duration::from_micros(6) {
+            return Err("total wasn't the sum of both modes");
+        }
+
+        Ok(())
+    }
+}
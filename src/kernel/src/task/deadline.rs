@@ -0,0 +1,248 @@
+//! Earliest-deadline-first scheduling class for periodic kernel work.
+//!
+//! Each piece of admitted work runs in a kernel thread of its own, which the class holds in place of a run queue. Once
+//! an instance of the work is released, its thread is scheduled ahead of every queued task (in order of earliest
+//! deadline), and is preempted once it has run for its budget, which throttles it until its next release. The thread
+//! yields as each instance completes, which parks it until the next period.
+
+use crate::{
+    task::{Task, kthread},
+    time::now_ns,
+};
+use alloc::vec::Vec;
+use core::time::Duration;
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("period and budget must be non-zero, and the budget must not exceed the period")]
+    InvalidParameters,
+
+    #[error("admitting the work would exceed the deadline class utilization bound")]
+    Overcommitted,
+
+    #[error("failed to allocate the work's kernel thread")]
+    OutOfMemory,
+}
+
+/// Utilization is tracked in parts-per-million of a hardware thread.
+const UTILIZATION_SCALE: u64 = 1_000_000;
+
+/// Maximum share of a hardware thread the deadline class may reserve, so housekeeping
+/// can never starve regular tasks.
+const MAX_UTILIZATION: u64 = UTILIZATION_SCALE / 2;
+
+fn duration_ns(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+fn utilization(period_ns: u64, budget_ns: u64) -> u64 {
+    (budget_ns * UTILIZATION_SCALE).div_ceil(period_ns)
+}
+
+/// Periodic kernel work, which must complete within `budget` once every `period`.
+pub struct PeriodicWork {
+    name: &'static str,
+    period_ns: u64,
+    budget_ns: u64,
+    id: uuid::Uuid,
+    /// The work's kernel thread, unless it's running.
+    task: Option<Task>,
+
+    /// Time at which the current instance of the work becomes runnable.
+    release_ns: u64,
+    /// Time the current instance has run for.
+    consumed_ns: u64,
+    misses: u64,
+}
+
+impl PeriodicWork {
+    /// The deadline of the current instance is implicitly the start of the next period.
+    fn deadline_ns(&self) -> u64 {
+        self.release_ns.saturating_add(self.period_ns)
+    }
+
+    /// Ends the current instance, releasing the next at the start of the next period which hasn't elapsed.
+    fn end_instance(&mut self, now_ns: u64) {
+        self.release_ns = self.deadline_ns();
+        self.consumed_ns = 0;
+
+        // Skip any periods which have already fully elapsed, counting them as misses.
+        if self.deadline_ns() < now_ns {
+            let skipped_periods = (now_ns - self.release_ns) / self.period_ns;
+            self.misses += skipped_periods;
+            self.release_ns += skipped_periods * self.period_ns;
+
+            warn!(
+                "Periodic work `{}` skipped {skipped_periods} periods ({} total misses)",
+                self.name, self.misses
+            );
+        }
+    }
+}
+
+/// Earliest-deadline-first scheduling class for periodic kernel work.
+pub struct DeadlineClass {
+    work: Vec<PeriodicWork>,
+    utilization: u64,
+}
+
+impl DeadlineClass {
+    pub const fn new() -> Self {
+        Self {
+            work: Vec::new(),
+            utilization: 0,
+        }
+    }
+
+    /// Admits `work` to be run every `period`, provided the class is not overcommitted.
+    ///
+    /// # Remarks
+    ///
+    /// `work` runs in a kernel thread, so it may take locks as any task does, but it mustn't sleep or block; its
+    /// thread would leave the class until it's woken.
+    pub fn admit(
+        &mut self,
+        name: &'static str,
+        period: Duration,
+        budget: Duration,
+        work: fn(),
+    ) -> Result<(), Error> {
+        let period_ns = duration_ns(period);
+        let budget_ns = duration_ns(budget);
+
+        if period_ns == 0 || budget_ns == 0 || budget_ns > period_ns {
+            return Err(Error::InvalidParameters);
+        }
+
+        let utilization = self.utilization + utilization(period_ns, budget_ns);
+        if utilization > MAX_UTILIZATION {
+            return Err(Error::Overcommitted);
+        }
+
+        let mut task = kthread::new(name, move || {
+            loop {
+                work();
+                kthread::yield_now();
+            }
+        })
+        .map_err(|_| Error::OutOfMemory)?;
+        task.transition(crate::task::State::Ready);
+
+        debug!(
+            "Admitted periodic work `{name}`: {{ period: {period:?}, budget: {budget:?}, utilization: {utilization}ppm }}"
+        );
+
+        let work = PeriodicWork {
+            name,
+            period_ns,
+            budget_ns,
+            id: task.id(),
+            task: Some(task),
+            release_ns: now_ns(),
+            consumed_ns: 0,
+            misses: 0,
+        };
+
+        self.utilization = utilization;
+        self.work.push(work);
+
+        Ok(())
+    }
+
+    /// Takes the kernel thread of the released instance with the earliest deadline, along with the budget it has left
+    /// to run for.
+    pub fn pick(&mut self) -> Option<(Task, Duration)> {
+        let now_ns = now_ns();
+
+        // Instances which were never run before their deadline are missed.
+        for work in self
+            .work
+            .iter_mut()
+            .filter(|work| work.task.is_some() && work.deadline_ns() <= now_ns)
+        {
+            work.misses += 1;
+
+            warn!(
+                "Periodic work `{}` missed its deadline by {}ns ({} total misses)",
+                work.name,
+                now_ns - work.deadline_ns(),
+                work.misses
+            );
+
+            work.end_instance(now_ns);
+        }
+
+        let work = self
+            .work
+            .iter_mut()
+            .filter(|work| work.task.is_some() && work.release_ns <= now_ns)
+            .min_by_key(|work| work.deadline_ns())?;
+        let budget = Duration::from_nanos(work.budget_ns.saturating_sub(work.consumed_ns));
+
+        work.task.take().map(|task| (task, budget))
+    }
+
+    /// Returns `task` to the class as it's switched out, having run for `ran_for`; `is_complete` is whether it yielded,
+    /// completing its instance. Tasks which aren't the class's are returned.
+    pub fn reclaim(&mut self, task: Task, ran_for: Duration, is_complete: bool) -> Option<Task> {
+        let Some(work) = self.work.iter_mut().find(|work| work.id == task.id()) else {
+            return Some(task);
+        };
+
+        let now_ns = now_ns();
+        work.consumed_ns = work.consumed_ns.saturating_add(duration_ns(ran_for));
+
+        if is_complete {
+            if now_ns > work.deadline_ns() {
+                work.misses += 1;
+
+                warn!(
+                    "Periodic work `{}` missed its deadline by {}ns ({} total misses)",
+                    work.name,
+                    now_ns - work.deadline_ns(),
+                    work.misses
+                );
+            }
+
+            work.end_instance(now_ns);
+        } else if work.consumed_ns >= work.budget_ns {
+            // The instance is cut short, and the thread resumes where it was preempted once the next is released.
+            work.misses += 1;
+
+            warn!(
+                "Periodic work `{}` overran its budget of {}ns, and is throttled until its next period ({} total \
+                 misses)",
+                work.name, work.budget_ns, work.misses
+            );
+
+            work.end_instance(now_ns);
+        }
+
+        work.task = Some(task);
+
+        None
+    }
+
+    /// Time until the next instance of periodic work is released, if any is yet to be.
+    pub fn next_release(&self) -> Option<Duration> {
+        let now_ns = now_ns();
+
+        self.work
+            .iter()
+            .filter(|work| work.release_ns > now_ns)
+            .map(|work| work.release_ns - now_ns)
+            .min()
+            .map(Duration::from_nanos)
+    }
+
+    /// Total number of deadlines missed by all admitted work.
+    pub fn misses(&self) -> u64 {
+        self.work.iter().map(|work| work.misses).sum()
+    }
+}
+
+impl Default for DeadlineClass {
+    fn default() -> Self {
+        Self::new()
+    }
+}
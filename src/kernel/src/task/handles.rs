@@ -0,0 +1,324 @@
+//! Per-task handle tables, through which userspace refers to kernel objects.
+//!
+//! A handle names an object (an IPC port, a shared memory object, a timer, or a [hardware](crate::task::hardware)
+//! object) within the task which holds it, along
+//! with the [`Rights`] it grants over that object. Every system call which takes a handle checks that it grants the
+//! rights the call needs, so a task can only do with an object what its handle allows. [`duplicate`] derives a handle
+//! granting a subset of another's rights, so a task can pass others (over [IPC](crate::task::ipc)) less access than it
+//! has itself; handles without [`Rights::TRANSFER`] can't be passed at all.
+//!
+//! Objects live for as long as any handle to them does, so closing a handle only frees its object if it was the last.
+//! Timers deliver their expirations to the task which created them, so their handles can be neither duplicated nor
+//! transferred, and closing one destroys its timer.
+
+use crate::{
+    cpu::local_state::LocalState,
+    sync::SpinMutex,
+    task::{Task, hardware, ipc, shared_memory::SharedMemory, timers},
+};
+use alloc::{collections::btree_map::BTreeMap, sync::Arc};
+use uuid::Uuid;
+
+/// Maximum number of handles a single task may hold.
+pub const MAX_HANDLES: usize = 64;
+
+/// Handle value which refers to no object.
+pub const NO_HANDLE: Handle = 0;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no active task")]
+    NoActiveTask,
+
+    #[error("task holds no object with handle {0}")]
+    NoSuchHandle(Handle),
+
+    #[error("handle {0} refers to a different kind of object")]
+    WrongObject(Handle),
+
+    #[error("handle {0} doesn't grant {1:?}")]
+    NotGranted(Handle, Rights),
+
+    #[error("task already holds the maximum of {MAX_HANDLES} handles")]
+    TooManyHandles,
+}
+
+/// Identifies a kernel object within the task which holds it.
+pub type Handle = usize;
+
+bitflags! {
+    /// Rights a handle grants over its object.
+    #[repr(transparent)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Rights: usize {
+        /// Receive from a port, or map a shared memory object readable.
+        const READ = 1 << 0;
+        /// Send to a port, map a shared memory object writable, arm a timer, bind an interrupt or claim a device with
+        /// the hardware authority, acknowledge a bound interrupt, or access a claimed device.
+        const WRITE = 1 << 1;
+        /// Map a shared memory object executable.
+        const EXECUTE = 1 << 2;
+        /// Pass the handle to another task in a message.
+        const TRANSFER = 1 << 3;
+        /// Derive further handles from the handle with [`duplicate`].
+        const DUPLICATE = 1 << 4;
+    }
+}
+
+/// Kernel object a handle refers to.
+#[derive(Clone)]
+pub(super) enum Object {
+    Port(ipc::PortId),
+    Memory(Arc<SharedMemory>),
+    /// Timer owned by the task holding the handle, which is keyed by the handle itself (see [`timers`]).
+    Timer,
+    /// Authority over the system's hardware, which userspace drivers need to access their devices.
+    Hardware,
+    Interrupt(Arc<hardware::Interrupt>),
+    Device(Arc<hardware::Claim>),
+}
+
+impl Object {
+    /// Kind of resource a handle to the object is tracked as, while it's held (see [`crate::task::resources`]).
+    #[cfg(debug_assertions)]
+    fn resource_kind(&self) -> crate::task::resources::ResourceKind {
+        use crate::task::resources::ResourceKind;
+
+        match self {
+            Object::Memory(_) => ResourceKind::Frame,
+            Object::Timer => ResourceKind::Timer,
+            Object::Port(_) | Object::Hardware | Object::Interrupt(_) | Object::Device(_) => {
+                ResourceKind::Handle
+            }
+        }
+    }
+}
+
+/// Object held by a handle, along with the rights the handle grants.
+#[derive(Clone)]
+pub(super) struct Entry {
+    pub object: Object,
+    pub rights: Rights,
+}
+
+pub(super) struct Table {
+    /// Task which holds the table's handles.
+    #[cfg_attr(not(debug_assertions), allow(dead_code))]
+    task_id: Uuid,
+    next_handle: Handle,
+    entries: BTreeMap<Handle, Entry>,
+}
+
+impl Table {
+    pub fn new(task_id: Uuid) -> Self {
+        Self {
+            task_id,
+            next_handle: NO_HANDLE,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Object `handle` refers to, if the handle grants `rights`.
+    pub fn get(&self, handle: Handle, rights: Rights) -> Result<&Object, Error> {
+        let entry = self
+            .entries
+            .get(&handle)
+            .ok_or(Error::NoSuchHandle(handle))?;
+
+        if entry.rights.contains(rights) {
+            Ok(&entry.object)
+        } else {
+            Err(Error::NotGranted(handle, rights.difference(entry.rights)))
+        }
+    }
+
+    /// Whether `count` more handles can be inserted.
+    pub fn has_room(&self, count: usize) -> bool {
+        (self.entries.len() + count) <= MAX_HANDLES
+    }
+
+    pub fn insert(&mut self, entry: Entry) -> Result<Handle, Error> {
+        if !self.has_room(1) {
+            return Err(Error::TooManyHandles);
+        }
+
+        // Handles start from one, so none is `NO_HANDLE`.
+        self.next_handle += 1;
+
+        #[cfg(debug_assertions)]
+        crate::task::resources::acquire(
+            self.task_id,
+            entry.object.resource_kind(),
+            u64::try_from(self.next_handle).unwrap(),
+        );
+
+        self.entries.insert(self.next_handle, entry);
+
+        Ok(self.next_handle)
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Result<Entry, Error> {
+        let entry = self
+            .entries
+            .remove(&handle)
+            .ok_or(Error::NoSuchHandle(handle))?;
+
+        #[cfg(debug_assertions)]
+        crate::task::resources::release(
+            self.task_id,
+            entry.object.resource_kind(),
+            u64::try_from(handle).unwrap(),
+        );
+
+        Ok(entry)
+    }
+}
+
+static TABLES: SpinMutex<BTreeMap<Uuid, Table>> = SpinMutex::new(BTreeMap::new());
+
+fn active_task_id() -> Result<Uuid, Error> {
+    LocalState::with_scheduler(|scheduler| scheduler.process().map(Task::id))
+        .ok_or(Error::NoActiveTask)
+}
+
+/// Runs `func` with the handle table of the task `task_id`.
+///
+/// # Remarks
+///
+/// The tables are locked before any object's state (i.e. [`ipc`]'s ports, or [`timers`]), so `func` may lock those.
+pub(super) fn with_table<T>(task_id: Uuid, func: impl FnOnce(&mut Table) -> T) -> T {
+    crate::interrupts::uninterruptable(|| {
+        func(
+            TABLES
+                .lock()
+                .entry(task_id)
+                .or_insert_with(|| Table::new(task_id)),
+        )
+    })
+}
+
+/// Gives the task `task_id` a handle to `object`, granting `rights`.
+pub(super) fn insert_into(task_id: Uuid, object: Object, rights: Rights) -> Result<Handle, Error> {
+    with_table(task_id, |table| table.insert(Entry { object, rights }))
+}
+
+/// Gives the active task a handle to `object`, granting `rights`.
+pub(super) fn insert(object: Object, rights: Rights) -> Result<Handle, Error> {
+    insert_into(active_task_id()?, object, rights)
+}
+
+/// Object the active task's `handle` refers to, if the handle grants `rights`.
+pub(super) fn get(handle: Handle, rights: Rights) -> Result<Object, Error> {
+    with_table(active_task_id()?, |table| {
+        table.get(handle, rights).cloned()
+    })
+}
+
+/// Drops the reference to `object` held by the handle `handle` of the task `task_id`.
+fn release(task_id: Uuid, handle: Handle, object: Object) {
+    match object {
+        Object::Port(port_id) => ipc::release(port_id),
+        Object::Memory(_) | Object::Hardware | Object::Interrupt(_) | Object::Device(_) => {}
+        Object::Timer => timers::remove(task_id, handle),
+    }
+}
+
+/// Derives a handle to the object of the active task's `handle`, granting only `rights`, which `handle` must grant
+/// (along with [`Rights::DUPLICATE`]).
+pub fn duplicate(handle: Handle, rights: Rights) -> Result<Handle, Error> {
+    with_table(active_task_id()?, |table| {
+        let object = table.get(handle, rights | Rights::DUPLICATE)?.clone();
+        if !table.has_room(1) {
+            return Err(Error::TooManyHandles);
+        }
+
+        if let Object::Port(port_id) = object {
+            ipc::acquire(port_id);
+        }
+
+        table.insert(Entry { object, rights })
+    })
+}
+
+/// Closes the active task's `handle`.
+pub fn close(handle: Handle) -> Result<(), Error> {
+    let task_id = active_task_id()?;
+
+    with_table(task_id, |table| {
+        let entry = table.remove(handle)?;
+        release(task_id, handle, entry.object);
+
+        Ok(())
+    })
+}
+
+/// Closes every handle held by the task `id`.
+pub fn on_exit(id: Uuid) {
+    crate::interrupts::uninterruptable(|| {
+        let mut tables = TABLES.lock();
+        let Some(table) = tables.remove(&id) else {
+            return;
+        };
+
+        for (handle, entry) in table.entries {
+            release(id, handle, entry.object);
+        }
+    });
+}
+
+crate::kernel_test! {
+    /// Handles only give access to their objects with the rights they were granted.
+    fn checks_handle_rights() -> crate::tests::Outcome {
+        let mut table = Table::new(Uuid::new_v4());
+        let handle = table
+            .insert(Entry {
+                object: Object::Timer,
+                rights: Rights::WRITE,
+            })
+            .map_err(|_| "handle wasn't inserted")?;
+
+        if table.get(handle, Rights::WRITE).is_err() {
+            return Err("granted right was refused");
+        }
+
+        if !matches!(
+            table.get(handle, Rights::WRITE | Rights::TRANSFER),
+            Err(Error::NotGranted(_, missing)) if missing == Rights::TRANSFER
+        ) {
+            return Err("right which wasn't granted was allowed");
+        }
+
+        table.remove(handle).map_err(|_| "handle wasn't removed")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(debug_assertions)]
+crate::kernel_test! {
+    /// Handles still held when their task exits are reported as leaked, by the kind of object they hold.
+    fn reports_unclosed_handles() -> crate::tests::Outcome {
+        let task_id = Uuid::new_v4();
+        let mut table = Table::new(task_id);
+
+        let closed = table
+            .insert(Entry {
+                object: Object::Hardware,
+                rights: Rights::WRITE,
+            })
+            .map_err(|_| "handle wasn't inserted")?;
+        table
+            .insert(Entry {
+                object: Object::Timer,
+                rights: Rights::WRITE,
+            })
+            .map_err(|_| "handle wasn't inserted")?;
+        table.remove(closed).map_err(|_| "handle wasn't removed")?;
+
+        if crate::task::resources::on_exit(task_id) != 1 {
+            return Err("unclosed handle wasn't reported");
+        }
+
+        Ok(())
+    }
+}
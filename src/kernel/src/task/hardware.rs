@@ -0,0 +1,505 @@
+//! Hardware access for userspace drivers, which run their devices from user mode rather than in the kernel.
+//!
+//! Authority over the system's hardware is a kernel object like any other (see [`crate::task::handles`]): the init
+//! task is given a handle to it as [`AUTHORITY_HANDLE`], and passes it on (over IPC) to the drivers it launches.
+//!
+//! # Interrupt forwarding
+//!
+//! With the authority, a driver binds an interrupt (an ISA IRQ, a GSI, or an MSI) to a port, receiving a handle to
+//! the binding. When the interrupt fires, the kernel's handler masks its line, and sends a [`Notification`] to the
+//! port. The line stays masked until the driver acknowledges the interrupt with [`ack`], once it has serviced its
+//! device. MSIs can't be masked by the kernel, so any which fire before they're acknowledged are counted, and notified
+//! once they are. Closing the binding's handle unbinds the interrupt.
+//!
+//! # Device access
+//!
+//! With the authority, a driver also claims a PCI function which no kernel driver is bound to, receiving a handle to
+//! the claim; the function is then bound to no other driver (kernel or userspace) until every handle to the claim is
+//! closed. With that handle, the driver is granted ranges of the I/O ports its function's BARs decode, which its
+//! threads may then access with I/O instructions, and maps pages of the memory they decode into its address space,
+//! uncacheable. Nothing outside a claimed function's BARs is ever granted.
+//!
+//! # Remarks
+//!
+//! If a notification can't be sent (as the port's queue is full), it's dropped, and the line stays masked until the
+//! driver next acknowledges the interrupt, which sends the notification again.
+//!
+//! Port grants are loaded into the task state segment as a thread is switched in, so other hardware threads running
+//! the same process only see a new grant once they next switch task. Neither port grants nor mappings are revoked when
+//! a claim's handles are closed; they last as long as the process.
+
+use crate::{
+    acpi::madt::{Polarity, Topology, TriggerMode},
+    arch::x86_64::{
+        devices::ioapic,
+        structures::tss::{IoBitmap, TaskStateSegment},
+    },
+    devices::pci::{self, PciAddress, config::Bar},
+    interrupts::irq::{self, IrqTimestamp},
+    sync::SpinMutex,
+    task::{
+        DEFAULT_USERSPACE_SIZE, Process, Task,
+        handles::{self, Entry, Handle, Object, Rights, Table},
+        ipc::{self, PortId},
+    },
+};
+use alloc::{boxed::Box, collections::btree_map::BTreeMap, sync::Arc};
+use libsys::{Address, Frame, Page, page_size};
+use uuid::Uuid;
+
+/// Handle through which the init task holds authority over the system's hardware.
+pub const AUTHORITY_HANDLE: Handle = 1;
+
+/// Name PCI functions claimed by userspace drivers are bound to, in the PCI driver registry.
+const USERSPACE_DRIVER: &str = "userspace";
+
+/// Rights granted by a handle to a newly claimed PCI function.
+const CLAIM_RIGHTS: Rights = Rights::WRITE
+    .union(Rights::TRANSFER)
+    .union(Rights::DUPLICATE);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no active task")]
+    NoActiveTask,
+
+    #[error("interrupt line is out of range: {0}")]
+    InvalidLine(usize),
+
+    #[error("PCI address is malformed: {0:#X}")]
+    InvalidDevice(usize),
+
+    #[error("range isn't decoded by a BAR of the claimed function: {0:#X} ({1:#X} long)")]
+    NotDecoded(usize, usize),
+
+    #[error("address is not a free, page-aligned userspace range: {0:#X}")]
+    InvalidAddress(usize),
+
+    #[error("failed to claim the PCI function: {0}")]
+    Pci(#[from] pci::driver::Error),
+
+    #[error("failed to map the device's memory: {0}")]
+    AddressSpace(crate::task::address_space::Error),
+
+    #[error(transparent)]
+    Handles(#[from] handles::Error),
+
+    #[error(transparent)]
+    Ipc(#[from] ipc::Error),
+
+    #[error("failed to bind the interrupt: {0}")]
+    Irq(#[from] irq::Error),
+}
+
+/// Kind of interrupt bound by [`bind`].
+#[repr(usize)]
+#[derive(Debug, Clone, Copy, TryFromPrimitive)]
+pub enum Source {
+    /// A legacy ISA IRQ, routed according to the platform's source overrides.
+    Isa = 0,
+    /// A global system interrupt, i.e. a PCI interrupt pin, which is level-triggered and active-low.
+    Gsi = 1,
+    /// A message-signalled interrupt, which the driver programs its device to send to the bound vector.
+    Msi = 2,
+}
+
+/// Binding written to userspace by [`bind`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable)]
+pub struct Binding {
+    pub handle: Handle,
+    /// Vector the interrupt is delivered to, which is the data of an MSI.
+    pub vector: u32,
+    /// APIC ID of the hardware thread the interrupt is delivered to, which is the destination of an MSI.
+    pub apic_id: u32,
+}
+
+/// Message payload sent to a bound port when its interrupt fires.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable)]
+pub struct Notification {
+    /// Key the interrupt was bound with.
+    pub key: u64,
+    /// Number of times the interrupt has fired since it was last notified.
+    pub count: u64,
+}
+
+struct Bound {
+    /// Line masked while the interrupt is in service, if it isn't an MSI.
+    gsi: Option<u32>,
+    port: PortId,
+    key: u64,
+    /// Whether a notification awaits acknowledgement.
+    in_service: bool,
+    /// Number of times the interrupt has fired since it was last notified.
+    pending: u64,
+}
+
+impl Bound {
+    fn notify(&mut self) {
+        let notification = Notification {
+            key: self.key,
+            count: self.pending,
+        };
+
+        self.in_service = true;
+        if ipc::post(self.port, notification.as_bytes()) {
+            self.pending = 0;
+        }
+    }
+
+    fn set_masked(&self, masked: bool) {
+        if let Some(gsi) = self.gsi
+            && let Err(error) = ioapic::set_masked(gsi, masked)
+        {
+            warn!("Failed to set the mask of bound GSI {gsi}: {error}");
+        }
+    }
+}
+
+/// Bound interrupts, by vector; these are read from interrupt handlers, so are only locked without interrupts.
+static BOUND: SpinMutex<BTreeMap<u8, Bound>> = SpinMutex::new(BTreeMap::new());
+
+/// Interrupt bound by a driver, which is unbound once its handle is closed.
+pub struct Interrupt {
+    vector: u8,
+}
+
+impl Drop for Interrupt {
+    fn drop(&mut self) {
+        let bound = crate::interrupts::uninterruptable(|| BOUND.lock().remove(&self.vector));
+        if let Some(bound) = bound {
+            // The line is masked before its vector is freed, so it isn't delivered to a vector with no handler.
+            bound.set_masked(true);
+            ipc::release(bound.port);
+        }
+
+        irq::unregister(self.vector);
+    }
+}
+
+/// Handler of every bound vector, which forwards it to its port.
+fn forward(vector: u8, _: IrqTimestamp) {
+    let mut bound = BOUND.lock();
+    let Some(bound) = bound.get_mut(&vector) else {
+        return;
+    };
+
+    bound.set_masked(true);
+    bound.pending = bound.pending.saturating_add(1);
+
+    if !bound.in_service {
+        bound.notify();
+    }
+}
+
+/// PCI function claimed by a userspace driver, which is released once its last handle is closed.
+pub struct Claim {
+    device: &'static pci::tree::Device,
+}
+
+impl Claim {
+    /// Whether the `len` bytes from `start` are within a single BAR of the function which `is_bar` accepts.
+    fn decodes(&self, start: u64, len: u64, is_bar: impl Fn(Bar) -> Option<u64>) -> bool {
+        len > 0
+            && self.device.bars().iter().any(|decoded| {
+                is_bar(decoded.bar).is_some_and(|base| {
+                    start >= base
+                        && start
+                            .checked_add(len)
+                            .is_some_and(|end| end <= base.saturating_add(decoded.size))
+                })
+            })
+    }
+
+    /// Whether the `count` ports from `first` are decoded by an I/O BAR of the function.
+    fn decodes_ports(&self, first: u16, count: u16) -> bool {
+        self.decodes(u64::from(first), u64::from(count), |bar| match bar {
+            Bar::Io { port } => Some(u64::from(port)),
+            Bar::Memory32 { .. } | Bar::Memory64 { .. } => None,
+        })
+    }
+
+    /// Whether the `page_count` frames from `frame` are decoded by a memory BAR of the function.
+    fn decodes_frames(&self, frame: Address<Frame>, page_count: usize) -> bool {
+        let start = u64::try_from(frame.get().get()).unwrap();
+
+        u64::try_from(page_count)
+            .ok()
+            .and_then(|page_count| page_count.checked_mul(u64::try_from(page_size()).unwrap()))
+            .is_some_and(|len| {
+                self.decodes(start, len, |bar| match bar {
+                    Bar::Memory32 { address, .. } => Some(u64::from(address)),
+                    Bar::Memory64 { address, .. } => Some(address),
+                    Bar::Io { .. } => None,
+                })
+            })
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        pci::driver::release(self.device.address());
+    }
+}
+
+fn active_task_id() -> Result<Uuid, Error> {
+    crate::cpu::local_state::LocalState::with_scheduler(|scheduler| {
+        scheduler.process().map(Task::id)
+    })
+    .ok_or(Error::NoActiveTask)
+}
+
+fn active_process() -> Result<Arc<Process>, Error> {
+    crate::cpu::local_state::LocalState::with_scheduler(|scheduler| {
+        scheduler.process().and_then(Task::process).cloned()
+    })
+    .ok_or(Error::NoActiveTask)
+}
+
+/// Checks that `authority` is a handle in `table` to the hardware authority.
+fn check_authority(table: &Table, authority: Handle) -> Result<(), Error> {
+    match table.get(authority, Rights::WRITE)? {
+        Object::Hardware => Ok(()),
+        _ => Err(handles::Error::WrongObject(authority).into()),
+    }
+}
+
+/// Claim the active task's `handle` refers to.
+fn claim_of(handle: Handle) -> Result<Arc<Claim>, Error> {
+    match handles::get(handle, Rights::WRITE)? {
+        Object::Device(claim) => Ok(claim),
+        _ => Err(handles::Error::WrongObject(handle).into()),
+    }
+}
+
+/// Decodes a PCI address passed from userspace, which holds the segment in bits 16..32, the bus in bits 8..16, the
+/// device in bits 3..8, and the function in bits 0..3.
+fn decode_pci_address(address: usize) -> Option<PciAddress> {
+    let segment = u16::try_from(address >> 16).ok()?;
+    let bus = u8::try_from((address >> 8) & 0xFF).unwrap();
+    let device = u8::try_from((address >> 3) & 0x1F).unwrap();
+    let function = u8::try_from(address & 0x7).unwrap();
+
+    PciAddress::new(segment, bus, device, function)
+}
+
+/// Gives the task `task_id`, which must hold no handles yet, authority over the system's hardware as
+/// [`AUTHORITY_HANDLE`].
+pub(super) fn grant_authority(task_id: Uuid) -> Result<(), Error> {
+    let handle = handles::insert_into(
+        task_id,
+        Object::Hardware,
+        Rights::WRITE | Rights::TRANSFER | Rights::DUPLICATE,
+    )?;
+    debug_assert_eq!(handle, AUTHORITY_HANDLE);
+
+    Ok(())
+}
+
+/// Routes `gsi` to `vector` on the current hardware thread.
+fn route(gsi: u32, vector: u8, polarity: Polarity, trigger_mode: TriggerMode) -> Result<(), Error> {
+    ioapic::route(gsi, vector, polarity, trigger_mode, crate::cpu::get_id())
+        .map_err(irq::Error::from)
+        .map_err(Error::from)
+}
+
+/// Binds the interrupt `line` of `source` to the port of the active task's `port` handle, sending notifications
+/// carrying `key` when it fires; `authority` must be a handle to the hardware authority.
+pub fn bind(
+    authority: Handle,
+    source: Source,
+    line: usize,
+    port: Handle,
+    key: u64,
+) -> Result<Binding, Error> {
+    let gsi = match source {
+        Source::Isa => {
+            let isa_irq = u8::try_from(line)
+                .ok()
+                .filter(|isa_irq| *isa_irq < 16)
+                .ok_or(Error::InvalidLine(line))?;
+
+            Some(Topology::isa_irq_to_gsi(isa_irq))
+        }
+        Source::Gsi => {
+            let gsi = u32::try_from(line).map_err(|_| Error::InvalidLine(line))?;
+
+            Some((gsi, Polarity::ActiveLow, TriggerMode::Level))
+        }
+        Source::Msi => None,
+    };
+
+    handles::with_table(active_task_id()?, |table| {
+        check_authority(table, authority)?;
+
+        let port_id = ipc::port(table, port, Rights::WRITE)?;
+        if !table.has_room(1) {
+            return Err(handles::Error::TooManyHandles.into());
+        }
+
+        let vector = irq::register(forward)?;
+        ipc::acquire(port_id);
+        crate::interrupts::uninterruptable(|| {
+            BOUND.lock().insert(
+                vector,
+                Bound {
+                    gsi: gsi.map(|(gsi, _, _)| gsi),
+                    port: port_id,
+                    key,
+                    in_service: false,
+                    pending: 0,
+                },
+            );
+        });
+
+        // The binding is undone by dropping the interrupt, if it can't be completed.
+        let interrupt = Interrupt { vector };
+        if let Some((gsi, polarity, trigger_mode)) = gsi {
+            route(gsi, vector, polarity, trigger_mode)?;
+        }
+
+        let handle = table.insert(Entry {
+            object: Object::Interrupt(Arc::new(interrupt)),
+            rights: Rights::WRITE,
+        })?;
+
+        Ok(Binding {
+            handle,
+            vector: u32::from(vector),
+            apic_id: crate::cpu::get_id(),
+        })
+    })
+}
+
+/// Acknowledges the interrupt bound by the active task's `handle`, unmasking its line (or, if it has fired since it
+/// was last notified, notifying it again).
+pub fn ack(handle: Handle) -> Result<(), Error> {
+    let Object::Interrupt(interrupt) = handles::get(handle, Rights::WRITE)? else {
+        return Err(handles::Error::WrongObject(handle).into());
+    };
+
+    crate::interrupts::uninterruptable(|| {
+        let mut bound = BOUND.lock();
+        // Interrupts are bound for as long as their handles are open.
+        let bound = bound.get_mut(&interrupt.vector).unwrap();
+
+        bound.in_service = false;
+        if bound.pending > 0 {
+            bound.notify();
+        } else {
+            bound.set_masked(false);
+        }
+    });
+
+    Ok(())
+}
+
+/// Claims the PCI function at `address` (see [`decode_pci_address`]) for the active task, returning its handle to the
+/// claim; `authority` must be a handle to the hardware authority.
+pub fn claim(authority: Handle, address: usize) -> Result<Handle, Error> {
+    let pci_address = decode_pci_address(address).ok_or(Error::InvalidDevice(address))?;
+
+    handles::with_table(active_task_id()?, |table| {
+        check_authority(table, authority)?;
+        if !table.has_room(1) {
+            return Err(handles::Error::TooManyHandles.into());
+        }
+
+        let device = pci::driver::claim(pci_address, USERSPACE_DRIVER)?;
+
+        table
+            .insert(Entry {
+                object: Object::Device(Arc::new(Claim { device })),
+                rights: CLAIM_RIGHTS,
+            })
+            .map_err(Error::from)
+    })
+}
+
+/// Permits the active task's process to access the `count` ports from `first`, which must be decoded by an I/O BAR of
+/// the function claimed by `handle`.
+pub fn grant_ports(handle: Handle, first: usize, count: usize) -> Result<(), Error> {
+    let claim = claim_of(handle)?;
+    let (first, count) = u16::try_from(first)
+        .ok()
+        .zip(u16::try_from(count).ok())
+        .filter(|(first, count)| claim.decodes_ports(*first, *count))
+        .ok_or(Error::NotDecoded(first, count))?;
+
+    active_process()?.with_io_bitmap(|io_bitmap| {
+        let io_bitmap = io_bitmap.get_or_insert_with(|| Box::new(IoBitmap::new()));
+        io_bitmap.allow(first, count);
+
+        // The active task is running on this hardware thread, so it's granted the ports straight away.
+        TaskStateSegment::load_io_bitmap(Some(io_bitmap));
+    });
+
+    Ok(())
+}
+
+/// Maps the `page_count` frames of device memory from `physical`, which must be decoded by a memory BAR of the
+/// function claimed by `handle`, into the active task's address space at `address`.
+pub fn map_mmio(
+    handle: Handle,
+    physical: usize,
+    page_count: usize,
+    address: usize,
+) -> Result<(), Error> {
+    let claim = claim_of(handle)?;
+    let frame = Address::<Frame>::new(physical)
+        .filter(|frame| claim.decodes_frames(*frame, page_count))
+        .ok_or(Error::NotDecoded(
+            physical,
+            page_count.saturating_mul(page_size()),
+        ))?;
+
+    let page = Some(address)
+        .filter(|address| {
+            address
+                .checked_add(page_count * page_size())
+                .is_some_and(|end| end <= DEFAULT_USERSPACE_SIZE.get())
+        })
+        .and_then(Address::<Page>::new)
+        .ok_or(Error::InvalidAddress(address))?;
+
+    active_process()?.with_image(|image| {
+        image
+            .address_space_mut()
+            .map_mmio(page, frame, page_count)
+            .map_err(|error| match error {
+                crate::task::address_space::Error::InvalidAddress => Error::InvalidAddress(address),
+                error => Error::AddressSpace(error),
+            })
+    })
+}
+
+/// Unmaps the device memory mapped at `address` in the active task's address space.
+pub fn unmap_mmio(address: usize) -> Result<(), Error> {
+    let page = Address::<Page>::new(address).ok_or(Error::InvalidAddress(address))?;
+
+    active_process()?.with_image(|image| {
+        image
+            .address_space_mut()
+            .unmap_mmio(page)
+            .map_err(|error| match error {
+                crate::task::address_space::Error::NotMapped(_) => Error::InvalidAddress(address),
+                error => Error::AddressSpace(error),
+            })
+    })
+}
+
+crate::kernel_test! {
+    /// PCI addresses passed from userspace are decoded from their packed form, and refused if out of range.
+    fn decodes_pci_addresses() -> crate::tests::Outcome {
+        if decode_pci_address(0x0001_0219) != PciAddress::new(1, 2, 3, 1) {
+            return Err("PCI address was decoded incorrectly");
+        }
+
+        if decode_pci_address(1 << 32).is_some() {
+            return Err("out-of-range segment was accepted");
+        }
+
+        Ok(())
+    }
+}
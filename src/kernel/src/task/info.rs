@@ -0,0 +1,186 @@
+//! Snapshots of every task, for observability: the debug shell's `ps`, and [`Syscall::TaskInfo`].
+//!
+//! [`Syscall::TaskInfo`]: crate::interrupts::syscall::Syscall::TaskInfo
+
+use crate::task::{Mode, State, Task};
+use alloc::vec::Vec;
+use core::sync::atomic::Ordering;
+
+/// Maximum length (in bytes) of a name in a [`TaskInfo`].
+pub const TASK_NAME_LEN: usize = 32;
+
+/// State of a task, as reported by [`TaskInfo`].
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+pub enum InfoState {
+    Running = 0,
+    Ready = 1,
+    Blocked = 2,
+    /// Exited, with an exit status its parent hasn't collected yet.
+    Zombie = 3,
+}
+
+impl From<State> for InfoState {
+    fn from(state: State) -> Self {
+        match state {
+            State::Running => Self::Running,
+            State::Created | State::Ready => Self::Ready,
+            State::Blocked => Self::Blocked,
+            State::Exiting | State::Reaped => Self::Zombie,
+        }
+    }
+}
+
+/// Snapshot of a task, as listed by [`snapshot`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable)]
+pub struct TaskInfo {
+    /// ID of the task, as the bytes of its UUID.
+    pub id: [u8; 16],
+    /// NUL-padded (and possibly truncated) name, or empty if the task is unnamed.
+    pub name: [u8; TASK_NAME_LEN],
+    /// [`InfoState`] of the task.
+    pub state: u8,
+    /// [`Priority`](crate::task::Priority) the task is scheduled at.
+    pub priority: u8,
+    pub reserved: [u8; 2],
+    /// Hardware thread the task is running on, or last ran on; `u32::MAX` if it hasn't run.
+    pub hwthread_id: u32,
+    /// Time the task has spent running.
+    pub cpu_time_ns: u64,
+    /// Time the task has spent running in userspace, and in the kernel (i.e. servicing its system calls).
+    pub user_time_ns: u64,
+    pub kernel_time_ns: u64,
+}
+
+impl TaskInfo {
+    pub fn id(&self) -> uuid::Uuid {
+        uuid::Uuid::from_bytes(self.id)
+    }
+
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(TASK_NAME_LEN);
+
+        // Names are truncated on a character boundary, so they're always valid.
+        core::str::from_utf8(&self.name[..len]).unwrap_or_default()
+    }
+
+    pub fn state(&self) -> Option<InfoState> {
+        InfoState::try_from(self.state).ok()
+    }
+
+    pub fn priority(&self) -> Option<crate::task::Priority> {
+        crate::task::Priority::try_from(self.priority).ok()
+    }
+}
+
+impl Task {
+    /// Snapshot of the task in its current state.
+    pub fn info(&self) -> TaskInfo {
+        let mut name = [0; TASK_NAME_LEN];
+        if let Some(task_name) = self.name() {
+            let len = (0..=task_name.len().min(TASK_NAME_LEN))
+                .rev()
+                .find(|len| task_name.is_char_boundary(*len))
+                .unwrap_or(0);
+            name[..len].copy_from_slice(&task_name.as_bytes()[..len]);
+        }
+
+        let cpu_time = self.cpu_time();
+        let as_nanos =
+            |time: core::time::Duration| u64::try_from(time.as_nanos()).unwrap_or(u64::MAX);
+
+        TaskInfo {
+            id: self.id().into_bytes(),
+            name,
+            state: InfoState::from(self.state()).into(),
+            priority: self.effective_priority().into(),
+            reserved: [0; 2],
+            hwthread_id: self.hwthread_id().unwrap_or(u32::MAX),
+            cpu_time_ns: as_nanos(cpu_time.total()),
+            user_time_ns: as_nanos(cpu_time.user),
+            kernel_time_ns: as_nanos(cpu_time.kernel),
+        }
+    }
+}
+
+/// Task running on a hardware thread, as published by its scheduler.
+#[derive(Debug, Clone, Copy)]
+pub struct Running {
+    info: TaskInfo,
+    /// When the task was switched in, which its CPU time is counted up to.
+    switched_in_ns: u64,
+    /// Mode the task was switched into, which the time since is counted as.
+    mode: Mode,
+}
+
+/// Publishes `task` (or the idle task, if `None`) as the task running on the current hardware thread, having been
+/// switched into `mode` at `switched_in_ns`.
+pub fn publish_running(task: Option<&Task>, switched_in_ns: u64, mode: Mode) {
+    let running = task.map(|task| Running {
+        info: task.info(),
+        switched_in_ns,
+        mode,
+    });
+
+    crate::interrupts::uninterruptable(|| *crate::percpu!(running_task).lock() = running);
+}
+
+/// Snapshot of every task: those running on each hardware thread, then those which are queued, blocked, and exited
+/// but not yet collected (zombies). Returns `None` if any of them are locked.
+///
+/// # Remarks
+///
+/// This never waits on locks, so it's safe to call from contexts (i.e. the debug shell) which may have interrupted
+/// their holder.
+pub fn try_snapshot() -> Option<Vec<TaskInfo>> {
+    crate::interrupts::uninterruptable(|| {
+        let now_ns = crate::time::now_ns();
+        let mut infos = Vec::new();
+
+        for block in crate::cpu::percpu::blocks() {
+            let running_task = block.running_task.try_lock()?;
+
+            if let Some(Running {
+                mut info,
+                switched_in_ns,
+                mode,
+            }) = *running_task
+            {
+                let running_ns = now_ns.saturating_sub(switched_in_ns);
+                info.hwthread_id = block.hwthread_id.load(Ordering::Relaxed);
+                info.cpu_time_ns = info.cpu_time_ns.saturating_add(running_ns);
+                // System call boundaries aren't published, so the time since the task was switched in is counted as
+                // the mode it was switched into.
+                match mode {
+                    Mode::User => info.user_time_ns = info.user_time_ns.saturating_add(running_ns),
+                    Mode::Kernel => {
+                        info.kernel_time_ns = info.kernel_time_ns.saturating_add(running_ns);
+                    }
+                }
+
+                infos.push(info);
+            }
+        }
+
+        crate::task::try_for_each_queued(|task| infos.push(task.info())).then_some(())?;
+        crate::task::lifecycle::try_for_each_zombie(|info| infos.push(*info)).then_some(())?;
+
+        Some(infos)
+    })
+}
+
+/// Snapshot of every task, as [`try_snapshot`] takes, waiting on any locks.
+pub fn snapshot() -> Vec<TaskInfo> {
+    loop {
+        if let Some(infos) = try_snapshot() {
+            return infos;
+        }
+
+        core::hint::spin_loop();
+    }
+}
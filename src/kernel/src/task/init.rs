@@ -0,0 +1,173 @@
+//! Supervision of the init task (PID 1), the root of the userspace task lifecycle.
+
+use crate::{
+    fs::initrd,
+    sync::SpinMutex,
+    task::{LoadError, Priority, Startup, Task},
+};
+use alloc::collections::btree_map::BTreeMap;
+use spin::Once;
+use uuid::Uuid;
+
+/// Path of the init executable within the initramfs.
+pub const INIT_PATH: &str = "/init";
+
+crate::parameters! {
+    /// Whether the init task is relaunched when it exits, rather than the kernel panicking.
+    "init-restart": bool = false,
+}
+
+/// Action taken when the init task exits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitExitPolicy {
+    /// Panic the kernel, as userspace has lost its lifecycle root.
+    Panic,
+    /// Relaunch the init task from the initramfs.
+    Restart,
+}
+
+impl InitExitPolicy {
+    /// Policy requested on the kernel command line.
+    pub fn current() -> Self {
+        if crate::params::get::<bool>("init-restart") {
+            Self::Restart
+        } else {
+            Self::Panic
+        }
+    }
+}
+
+/// Maximum number of times init is restarted before the kernel gives up and panics.
+const MAX_RESTARTS: u32 = 8;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("initrd contains no {INIT_PATH} executable: {0}")]
+    NotFound(initrd::Error),
+
+    #[error("failed to load {INIT_PATH}: {0}")]
+    Load(#[from] LoadError),
+}
+
+struct Supervisor {
+    /// ID of the live init task.
+    id: Uuid,
+    restarts: u32,
+}
+
+/// ELF image of the init task, kept so it can be relaunched.
+static INIT_ELF: Once<&'static [u8]> = Once::new();
+static SUPERVISOR: SpinMutex<Option<Supervisor>> = SpinMutex::new(None);
+
+/// Parent of each task which has one.
+///
+/// # Remarks
+///
+/// Tasks without an entry are children of init.
+static PARENTS: SpinMutex<BTreeMap<Uuid, Uuid>> = SpinMutex::new(BTreeMap::new());
+
+fn launch(elf: &'static [u8]) -> Result<Uuid, LoadError> {
+    let startup = Startup {
+        args: &[INIT_PATH],
+        env: &[],
+    };
+    let mut task = Task::from_elf(Priority::Normal, elf, &startup)?;
+    task.set_traced(crate::interrupts::syscall::trace::is_traced_from_launch());
+    let id = task.id();
+    if let Err(error) = crate::task::hardware::grant_authority(id) {
+        warn!("Init task was launched without authority over hardware: {error}");
+    }
+    crate::task::enqueue_task(task);
+
+    Ok(id)
+}
+
+/// Launches [`INIT_PATH`] from the initramfs as the init task.
+pub fn spawn() -> Result<(), Error> {
+    let elf = *INIT_ELF.try_call_once(|| initrd::open(INIT_PATH).map_err(Error::NotFound))?;
+    let id = launch(elf)?;
+
+    info!("Launched init task: {id:?}");
+    crate::interrupts::uninterruptable(|| {
+        *SUPERVISOR.lock() = Some(Supervisor { id, restarts: 0 });
+    });
+
+    Ok(())
+}
+
+/// ID of the live init task, if one has been launched.
+pub fn id() -> Option<Uuid> {
+    crate::interrupts::uninterruptable(|| {
+        SUPERVISOR.lock().as_ref().map(|supervisor| supervisor.id)
+    })
+}
+
+/// Records `parent` as the parent of `child`.
+pub fn set_parent(child: Uuid, parent: Uuid) {
+    crate::interrupts::uninterruptable(|| {
+        PARENTS.lock().insert(child, parent);
+    });
+}
+
+/// Parent of the task `id`, or `None` if the task is init (or no init task exists).
+pub fn parent_of(id: Uuid) -> Option<Uuid> {
+    let parent = crate::interrupts::uninterruptable(|| PARENTS.lock().get(&id).copied());
+
+    parent.or_else(|| self::id().filter(|init_id| *init_id != id))
+}
+
+/// Whether the task `id` has any live children.
+///
+/// # Remarks
+///
+/// Init adopts every task without a parent, so it's always considered to have children.
+pub fn has_children(id: Uuid) -> bool {
+    self::id() == Some(id)
+        || crate::interrupts::uninterruptable(|| {
+            PARENTS.lock().values().any(|parent| *parent == id)
+        })
+}
+
+/// Handles the exit of the task `id`, reparenting its children to init, and applying the init
+/// exit policy if `id` is the init task itself.
+///
+/// # Remarks
+///
+/// This must be called from an interrupt context, before the exited task's scheduler picks its next task.
+pub fn on_exit(id: Uuid) {
+    let mut supervisor = SUPERVISOR.lock();
+
+    if let Some(init) = supervisor.as_mut().filter(|supervisor| supervisor.id == id) {
+        match InitExitPolicy::current() {
+            InitExitPolicy::Panic => panic!("init task exited"),
+
+            InitExitPolicy::Restart if init.restarts >= MAX_RESTARTS => {
+                panic!("init task exited, and has already been restarted {MAX_RESTARTS} times")
+            }
+
+            InitExitPolicy::Restart => {
+                // `INIT_ELF` is always set when a supervisor exists.
+                match launch(INIT_ELF.get().unwrap()) {
+                    Ok(new_id) => {
+                        init.restarts += 1;
+                        warn!(
+                            "Init task exited; restarted as {new_id:?} (restart {}).",
+                            init.restarts
+                        );
+
+                        init.id = new_id;
+                    }
+
+                    Err(error) => panic!("init task exited, and could not be restarted: {error}"),
+                }
+            }
+        }
+    }
+
+    drop(supervisor);
+
+    // Orphans are reparented to init by removing their parent entries.
+    let mut parents = PARENTS.lock();
+    parents.remove(&id);
+    parents.retain(|_, parent| *parent != id);
+}
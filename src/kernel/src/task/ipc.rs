@@ -0,0 +1,526 @@
+//! Synchronous message passing between tasks, over ports.
+//!
+//! A port is a kernel object holding a bounded queue of messages, which tasks refer to through their [handles]; sending
+//! requires [`Rights::WRITE`], and receiving [`Rights::READ`]. Sending to a full port blocks the sender until there's
+//! room, and receiving from an empty one blocks the receiver until a message arrives; either way, the system call is
+//! restarted once the task is woken. Messages carry a small payload, and may transfer handles (to any kind of object,
+//! if they grant [`Rights::TRANSFER`]): each is moved out of the sender's handles as the message is sent, and into
+//! the receiver's as it's received, granting the same rights.
+//!
+//! [`call`] sends a message along with a one-shot reply port, and then receives on it; the receiver replies by sending
+//! to the reply handle it was given. Each handle to a reply port is closed once it's been used: the replier's once it
+//! has sent the reply, and the caller's once it has received it. Neither can be duplicated.
+//!
+//! Ports live for as long as any handle to them does, including those in flight in queued messages. A port whose
+//! every other handle is held by tasks blocked on it has no one to wake them, so they're woken to find it
+//! disconnected.
+//!
+//! [handles]: crate::task::handles
+
+use crate::{
+    arch::x86_64::structures::idt::InterruptStackFrame,
+    sync::SpinMutex,
+    task::{
+        Registers, Task,
+        handles::{self, Entry, Handle, NO_HANDLE, Object, Rights, Table},
+    },
+};
+use alloc::{
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    vec::Vec,
+};
+use uuid::Uuid;
+
+/// Maximum number of bytes in a message's payload.
+pub const PAYLOAD_LEN: usize = 64;
+
+/// Maximum number of handles a single message may transfer.
+pub const MAX_TRANSFER: usize = 4;
+
+/// Maximum number of messages queued on a port before senders block.
+pub const QUEUE_LEN: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no active task")]
+    NoActiveTask,
+
+    #[error(transparent)]
+    Handles(#[from] handles::Error),
+
+    #[error("message is malformed (payload of {0} bytes, or too many handles)")]
+    InvalidMessage(usize),
+
+    #[error("port has no peer to send or receive a message")]
+    Disconnected,
+}
+
+/// Message exchanged with userspace by [`send`], [`recv`], and [`call`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable)]
+pub struct Message {
+    /// Number of bytes of `payload` in use.
+    pub len: usize,
+    pub payload: [u8; PAYLOAD_LEN],
+    /// Number of `handles` in use.
+    pub handle_count: usize,
+    /// Handles transferred with the message; received as the receiver's handles to the same objects.
+    pub handles: [Handle; MAX_TRANSFER],
+    /// Handle of the port to reply to, for messages sent by [`call`]; otherwise [`NO_HANDLE`].
+    ///
+    /// This is ignored when sending, as it's set by the kernel.
+    pub reply: Handle,
+}
+
+/// Rights granted by a handle to a newly created port.
+const PORT_RIGHTS: Rights = Rights::READ
+    .union(Rights::WRITE)
+    .union(Rights::TRANSFER)
+    .union(Rights::DUPLICATE);
+
+pub(super) type PortId = u64;
+
+/// Message queued on a port, holding objects (and their rights) rather than the sender's handles to them.
+struct Queued {
+    len: usize,
+    payload: [u8; PAYLOAD_LEN],
+    entries: Vec<Entry>,
+    reply: Option<PortId>,
+}
+
+struct Port {
+    queue: VecDeque<Queued>,
+    /// Number of handles to the port, including those in flight.
+    refs: usize,
+    /// Tasks blocked receiving from the port.
+    receivers: VecDeque<Uuid>,
+    /// Tasks blocked sending to the port.
+    senders: VecDeque<Uuid>,
+    /// Whether the port is a reply port, closed once its message has been received.
+    is_reply: bool,
+}
+
+impl Port {
+    fn new(refs: usize, is_reply: bool) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            refs,
+            receivers: VecDeque::new(),
+            senders: VecDeque::new(),
+            is_reply,
+        }
+    }
+
+    /// Whether any handle to the port (besides the calling task's own) isn't held by a task blocked on it.
+    fn has_peer(&self) -> bool {
+        self.refs > (self.receivers.len() + self.senders.len() + 1)
+    }
+}
+
+/// Port `handle` refers to in `table`, if the handle grants `rights`.
+pub(super) fn port(table: &Table, handle: Handle, rights: Rights) -> Result<PortId, Error> {
+    match table.get(handle, rights)? {
+        Object::Port(port_id) => Ok(*port_id),
+        _ => Err(handles::Error::WrongObject(handle).into()),
+    }
+}
+
+struct Ipc {
+    next_port: PortId,
+    ports: BTreeMap<PortId, Port>,
+}
+
+impl Ipc {
+    fn new_port(&mut self, port: Port) -> PortId {
+        let port_id = self.next_port;
+        self.next_port += 1;
+        self.ports.insert(port_id, port);
+
+        port_id
+    }
+
+    /// Drops a reference to `port_id`, freeing it (and the objects in flight in its queue) once none remain.
+    fn release(&mut self, port_id: PortId) {
+        let mut released = alloc::vec![port_id];
+
+        while let Some(port_id) = released.pop() {
+            // Ports in flight are always live, as their messages hold references to them.
+            let port = self.ports.get_mut(&port_id).unwrap();
+            port.refs -= 1;
+
+            if port.refs == 0 {
+                let port = self.ports.remove(&port_id).unwrap();
+                // Other objects in flight are freed with their last reference, as the queue is dropped.
+                released.extend(port.queue.into_iter().flat_map(|queued| {
+                    queued
+                        .entries
+                        .iter()
+                        .filter_map(|entry| match entry.object {
+                            Object::Port(port_id) => Some(port_id),
+                            _ => None,
+                        })
+                        .chain(queued.reply)
+                        .collect::<Vec<_>>()
+                }));
+            } else if port.refs <= (port.receivers.len() + port.senders.len()) {
+                // The remaining handles are all held by blocked tasks, which would otherwise never be woken.
+                port.receivers
+                    .drain(..)
+                    .chain(port.senders.drain(..))
+                    .for_each(crate::task::unblock_task);
+            }
+        }
+    }
+}
+
+static IPC: SpinMutex<Ipc> = SpinMutex::new(Ipc {
+    next_port: 0,
+    ports: BTreeMap::new(),
+});
+
+fn active_task_id() -> Result<Uuid, Error> {
+    crate::cpu::local_state::LocalState::with_scheduler(|scheduler| {
+        scheduler.process().map(Task::id)
+    })
+    .ok_or(Error::NoActiveTask)
+}
+
+/// Adds a reference to `port_id`, for a handle duplicated from one to it.
+pub(super) fn acquire(port_id: PortId) {
+    crate::interrupts::uninterruptable(|| {
+        // Ports are always live while a handle refers to them.
+        IPC.lock().ports.get_mut(&port_id).unwrap().refs += 1;
+    });
+}
+
+/// Drops the reference to `port_id` held by a closed handle.
+pub(super) fn release(port_id: PortId) {
+    crate::interrupts::uninterruptable(|| IPC.lock().release(port_id));
+}
+
+/// Creates a port, returning the active task's handle to it.
+pub fn create() -> Result<Handle, Error> {
+    handles::with_table(active_task_id()?, |table| {
+        if !table.has_room(1) {
+            return Err(handles::Error::TooManyHandles.into());
+        }
+
+        let port_id = IPC.lock().new_port(Port::new(1, false));
+
+        table
+            .insert(Entry {
+                object: Object::Port(port_id),
+                rights: PORT_RIGHTS,
+            })
+            .map_err(Error::from)
+    })
+}
+
+/// Queues a message of `payload` on `port_id` from the kernel, returning `false` (rather than blocking) if there's
+/// no room for it.
+///
+/// # Remarks
+///
+/// This may be called from an interrupt handler.
+pub(super) fn post(port_id: PortId, payload: &[u8]) -> bool {
+    let mut queued = Queued {
+        len: payload.len(),
+        payload: [0; PAYLOAD_LEN],
+        entries: Vec::new(),
+        reply: None,
+    };
+    queued.payload[..payload.len()].copy_from_slice(payload);
+
+    crate::interrupts::uninterruptable(|| {
+        let mut ipc = IPC.lock();
+        // The caller holds a reference to the port.
+        let port = ipc.ports.get_mut(&port_id).unwrap();
+        if port.queue.len() >= QUEUE_LEN {
+            return false;
+        }
+
+        port.queue.push_back(queued);
+        if let Some(receiver) = port.receivers.pop_front() {
+            crate::task::unblock_task(receiver);
+        }
+
+        true
+    })
+}
+
+/// Takes the handles `message` transfers out of `table`, as the objects (and rights) they refer to.
+fn take_transfers(table: &mut Table, message: &Message) -> Result<Queued, Error> {
+    let transfers = message
+        .handles
+        .get(..message.handle_count)
+        .filter(|_| message.len <= PAYLOAD_LEN)
+        .ok_or(Error::InvalidMessage(message.len))?;
+
+    // Every handle is checked before any is taken, so a failed send leaves the sender's handles as they were.
+    for handle in transfers {
+        table.get(*handle, Rights::TRANSFER)?;
+    }
+    if (1..transfers.len()).any(|index| transfers[..index].contains(&transfers[index])) {
+        return Err(Error::InvalidMessage(message.len));
+    }
+
+    Ok(Queued {
+        len: message.len,
+        payload: message.payload,
+        entries: transfers
+            .iter()
+            .filter_map(|handle| table.remove(*handle).ok())
+            .collect(),
+        reply: None,
+    })
+}
+
+/// Blocks the active task on `port`, to restart its system call once woken.
+fn block_on(
+    scheduler: &mut crate::task::Scheduler,
+    port: &mut Port,
+    task_id: Uuid,
+    is_sender: bool,
+    isf: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) {
+    if is_sender {
+        port.senders.push_back(task_id);
+    } else {
+        port.receivers.push_back(task_id);
+    }
+
+    // Safety: The task's registers are unchanged, so the system call is issued again when the task is woken.
+    unsafe {
+        crate::interrupts::syscall::restart(isf);
+    }
+
+    // The task is blocked with the ports locked, so a peer can't try to wake it before it's blocked.
+    scheduler.block_task(isf, regs);
+}
+
+/// Queues `message` on the port of the active task's `handle`, along with a reply port if `with_reply` is set,
+/// blocking the task until there's room.
+///
+/// Returns `None` if the task was blocked, otherwise the handle of the reply port (if any).
+fn enqueue(
+    handle: Handle,
+    message: &Message,
+    with_reply: bool,
+    isf: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result<Option<Option<Handle>>, Error> {
+    crate::cpu::local_state::LocalState::with_scheduler(|scheduler| {
+        let task_id = scheduler
+            .process()
+            .map(Task::id)
+            .ok_or(Error::NoActiveTask)?;
+
+        handles::with_table(task_id, |table| {
+            let mut ipc = IPC.lock();
+            let port_id = port(table, handle, Rights::WRITE)?;
+            let port = ipc.ports.get_mut(&port_id).unwrap();
+
+            if port.queue.len() >= QUEUE_LEN {
+                if !port.has_peer() {
+                    return Err(Error::Disconnected);
+                }
+
+                block_on(scheduler, port, task_id, true, isf, regs);
+
+                return Ok(None);
+            }
+
+            if with_reply && !table.has_room(1) {
+                return Err(handles::Error::TooManyHandles.into());
+            }
+
+            let mut queued = take_transfers(table, message)?;
+
+            let reply = with_reply
+                .then(|| {
+                    // One reference for the caller's handle, and one for the receiver's.
+                    let reply_id = ipc.new_port(Port::new(2, true));
+                    queued.reply = Some(reply_id);
+
+                    table.insert(Entry {
+                        object: Object::Port(reply_id),
+                        rights: Rights::READ,
+                    })
+                })
+                .transpose()?;
+
+            let port = ipc.ports.get_mut(&port_id).unwrap();
+            port.queue.push_back(queued);
+            if let Some(receiver) = port.receivers.pop_front() {
+                crate::task::unblock_task(receiver);
+            }
+
+            // A reply port takes a single reply, so the replier's handle to it is spent.
+            if port.is_reply {
+                table.remove(handle)?;
+                ipc.release(port_id);
+            }
+
+            Ok(Some(reply))
+        })
+    })
+}
+
+/// Sends `message` to the port of the active task's `handle`, blocking the task until there's room for it.
+///
+/// Returns `false` if the task was blocked, in which case the system call is restarted once it is woken.
+///
+/// # Remarks
+///
+/// This must be called from an interrupt context, as the active task may be switched out in place.
+pub fn send(
+    handle: Handle,
+    message: &Message,
+    isf: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result<bool, Error> {
+    enqueue(handle, message, false, isf, regs).map(|sent| sent.is_some())
+}
+
+/// Sends `message` to the port of the active task's `handle` (as [`send`] does), along with a reply port for the
+/// receiver to reply to, returning the active task's handle to the reply port.
+///
+/// The caller then receives the reply with [`recv`] on the returned handle, which closes it.
+///
+/// Returns `None` if the task was blocked, in which case the system call is restarted once it is woken.
+///
+/// # Remarks
+///
+/// This must be called from an interrupt context, as the active task may be switched out in place.
+pub fn call(
+    handle: Handle,
+    message: &Message,
+    isf: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result<Option<Handle>, Error> {
+    enqueue(handle, message, true, isf, regs).map(Option::flatten)
+}
+
+/// Receives the next message from the port of the active task's `handle`, blocking the task until one arrives.
+///
+/// Returns `None` if the task was blocked, in which case the system call is restarted once it is woken.
+///
+/// # Remarks
+///
+/// This must be called from an interrupt context, as the active task may be switched out in place.
+pub fn recv(
+    handle: Handle,
+    isf: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result<Option<Message>, Error> {
+    crate::cpu::local_state::LocalState::with_scheduler(|scheduler| {
+        let task_id = scheduler
+            .process()
+            .map(Task::id)
+            .ok_or(Error::NoActiveTask)?;
+
+        handles::with_table(task_id, |table| {
+            let mut ipc = IPC.lock();
+            let port_id = port(table, handle, Rights::READ)?;
+            let port = ipc.ports.get_mut(&port_id).unwrap();
+
+            let Some(queued) = port.queue.front() else {
+                if !port.has_peer() {
+                    return Err(Error::Disconnected);
+                }
+
+                block_on(scheduler, port, task_id, false, isf, regs);
+
+                return Ok(None);
+            };
+
+            if !table.has_room(queued.entries.len() + usize::from(queued.reply.is_some())) {
+                return Err(handles::Error::TooManyHandles.into());
+            }
+
+            let queued = port.queue.pop_front().unwrap();
+            if let Some(sender) = port.senders.pop_front() {
+                crate::task::unblock_task(sender);
+            }
+
+            // There's room for every handle, so none of these insertions fail.
+            let mut message = Message {
+                len: queued.len,
+                payload: queued.payload,
+                handle_count: queued.entries.len(),
+                handles: [NO_HANDLE; MAX_TRANSFER],
+                reply: queued.reply.map_or(Ok(NO_HANDLE), |reply_id| {
+                    table.insert(Entry {
+                        object: Object::Port(reply_id),
+                        rights: Rights::WRITE | Rights::TRANSFER,
+                    })
+                })?,
+            };
+            for (handle, entry) in message.handles.iter_mut().zip(queued.entries) {
+                *handle = table.insert(entry)?;
+            }
+
+            if port.is_reply {
+                table.remove(handle)?;
+                ipc.release(port_id);
+            }
+
+            Ok(Some(message))
+        })
+    })
+}
+
+crate::kernel_test! {
+    /// Transferring the same handle twice in one message is refused, without taking any of the sender's handles.
+    fn rejects_duplicate_transfers() -> crate::tests::Outcome {
+        let mut table = Table::new(Uuid::new_v4());
+        let handle = table
+            .insert(Entry {
+                object: Object::Port(0),
+                rights: PORT_RIGHTS,
+            })
+            .map_err(|_| "handle wasn't inserted")?;
+
+        let mut message = Message {
+            len: 0,
+            payload: [0; PAYLOAD_LEN],
+            handle_count: 2,
+            handles: [handle, handle, NO_HANDLE, NO_HANDLE],
+            reply: NO_HANDLE,
+        };
+        if take_transfers(&mut table, &message).is_ok() || table.get(handle, PORT_RIGHTS).is_err() {
+            return Err("duplicate transfer was accepted");
+        }
+
+        message.handle_count = 1;
+        let queued = take_transfers(&mut table, &message).map_err(|_| "valid transfer was refused")?;
+        if !matches!(queued.entries[..], [Entry { object: Object::Port(0), rights }] if rights == PORT_RIGHTS)
+            || table.get(handle, Rights::empty()).is_ok()
+        {
+            return Err("transferred handle wasn't moved out of the sender's handles");
+        }
+
+        Ok(())
+    }
+}
+
+crate::kernel_test! {
+    /// Messages posted by the kernel are queued until the port is full, and then refused rather than blocking.
+    fn posts_until_full() -> crate::tests::Outcome {
+        let port_id = crate::interrupts::uninterruptable(|| IPC.lock().new_port(Port::new(1, false)));
+
+        let queued = (0..QUEUE_LEN).all(|_| post(port_id, &[0; PAYLOAD_LEN]));
+        let refused = !post(port_id, &[0; PAYLOAD_LEN]);
+        release(port_id);
+
+        if !queued {
+            return Err("message was refused before the port was full");
+        }
+        if !refused {
+            return Err("message was queued on a full port");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+//! Kernel threads: tasks which run a kernel function in kernel mode, and are scheduled alongside userspace tasks.
+//!
+//! A kernel thread has its own kernel stack, but no address space of its own; it runs in the kernel's, so it never
+//! keeps a userspace task's address space alive. It exits once its function returns, and its stack is freed once it's
+//! reaped (see [`crate::task::lifecycle`]).
+
+use crate::{
+    arch::x86_64::structures::idt::InterruptStackFrame,
+    task::{Priority, Registers, Task},
+};
+use alloc::{boxed::Box, string::String};
+use core::alloc::AllocError;
+use libsys::Address;
+
+/// Size of each kernel thread's stack.
+pub const STACK_SIZE: usize = 0x10000;
+
+pub type Stack = crate::mem::stack::Stack<STACK_SIZE>;
+
+type Entry = Box<dyn FnOnce() + Send>;
+
+/// System call vector kernel threads exit with, as userspace tasks do.
+#[allow(clippy::as_conversions)]
+const TASK_EXIT: usize = libsys::syscall::Vector::TaskExit as usize;
+
+/// System call vector kernel threads yield with, as userspace tasks do.
+#[allow(clippy::as_conversions)]
+const TASK_YIELD: usize = libsys::syscall::Vector::TaskYield as usize;
+
+/// Creates a kernel thread named `name`, which runs `func`, without making it available to be scheduled.
+pub fn new(
+    name: impl Into<String>,
+    func: impl FnOnce() + Send + 'static,
+) -> Result<Task, AllocError> {
+    let stack = Stack::new_box()?;
+
+    // The entry is taken back (exactly once) by `run`.
+    let entry: *mut Entry = Box::into_raw(Box::new(Box::new(func)));
+
+    let mut regs = Registers::empty();
+    regs.rdi = entry.addr();
+
+    // `run` is entered as though it was called, so the stack pointer is offset by the return address `call` pushes.
+    let stack_pointer = stack.top().addr().get() - size_of::<u64>();
+
+    #[allow(clippy::as_conversions)]
+    let isf = InterruptStackFrame::new_kernel(
+        Address::new(run as usize).unwrap(),
+        Address::new(stack_pointer).unwrap(),
+    );
+
+    Ok(Task::new_kernel(
+        name.into(),
+        Priority::Normal,
+        (isf, regs),
+        stack,
+    ))
+}
+
+/// Spawns a kernel thread named `name`, which runs `func`, returning its task ID.
+pub fn spawn(
+    name: impl Into<String>,
+    func: impl FnOnce() + Send + 'static,
+) -> Result<uuid::Uuid, AllocError> {
+    let task = new(name, func)?;
+    let id = task.id();
+    debug!(
+        "Spawned kernel thread {:?}: {id:?}",
+        task.name().unwrap_or_default()
+    );
+    crate::task::enqueue_task(task);
+
+    Ok(id)
+}
+
+/// Yields the rest of the current kernel thread's time slice.
+pub fn yield_now() {
+    // Safety: The yield system call switches out the current task (this kernel thread), and resumes it after the
+    //         instruction once it's next scheduled, with only the result registers changed.
+    unsafe {
+        core::arch::asm!(
+            "int 0x80",
+            in("rax") TASK_YIELD,
+            lateout("rdi") _,
+            lateout("rsi") _,
+        );
+    }
+}
+
+extern "sysv64" fn run(entry: *mut Entry) -> ! {
+    // Safety: `spawn` leaked the entry for the thread to take back, and it's only taken back here.
+    let entry = unsafe { Box::from_raw(entry) };
+    entry();
+
+    // Safety: The exit system call switches out the current task (this kernel thread) in place, and never returns.
+    unsafe {
+        core::arch::asm!("int 0x80", in("rax") TASK_EXIT, options(noreturn));
+    }
+}
+
+crate::kernel_test! {
+    /// Kernel threads are queued with their name, and without an address space of their own.
+    fn spawn_queues_named_thread() -> crate::tests::Outcome {
+        let id = spawn("kthread-test", || {}).map_err(|_| "failed to allocate a kernel thread")?;
+
+        let mut found = None;
+        crate::task::try_for_each_queued(|task| {
+            if task.id() == id {
+                found = Some((task.name() == Some("kthread-test"), task.process().is_none()));
+            }
+        });
+
+        match found {
+            // The thread may already have been scheduled, and exited.
+            None | Some((true, true)) => Ok(()),
+            Some((false, _)) => Err("kernel thread is queued without its name"),
+            Some((_, false)) => Err("kernel thread is queued with an address space"),
+        }
+    }
+}
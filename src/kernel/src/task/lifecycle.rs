@@ -0,0 +1,187 @@
+//! Task exit statuses, and the reaping of exited tasks.
+//!
+//! An exited task's resources (its kernel stack, extended state save area, and, if it was its process's last thread,
+//! the process's address space) are freed as soon as its hardware thread has switched away from it, by [`reap`].
+//! Only its exit status is kept, until its parent collects it with [`wait`]; children of init (i.e. orphans) leave
+//! theirs for init to collect. Kernel threads have no parent to report to, so they leave no status at all.
+
+use crate::{
+    arch::x86_64::structures::idt::InterruptStackFrame,
+    sync::SpinMutex,
+    task::{Registers, State, Task, TaskInfo},
+};
+use alloc::{
+    collections::{btree_map::BTreeMap, btree_set::BTreeSet, vec_deque::VecDeque},
+    sync::Arc,
+};
+use uuid::Uuid;
+
+/// Exit code reported for tasks which exited without providing one.
+pub const DEFAULT_CODE: usize = 0;
+
+/// Exit code reported for tasks which were killed by the kernel (i.e. for an unrecoverable exception).
+pub const KILLED_CODE: usize = usize::MAX;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no active task")]
+    NoActiveTask,
+
+    #[error("task has no children to wait on")]
+    NoChildren,
+}
+
+/// Exit status of a child, collected by [`wait`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable)]
+pub struct ExitStatus {
+    /// ID of the child, as the bytes of its UUID.
+    pub id: [u8; 16],
+    pub code: usize,
+}
+
+/// Exited task whose exit status hasn't been collected yet.
+struct Zombie {
+    status: ExitStatus,
+    /// Snapshot of the task as it exited.
+    info: TaskInfo,
+}
+
+/// Zombies, by the ID of the parent they're left for.
+static ZOMBIES: SpinMutex<BTreeMap<Uuid, VecDeque<Zombie>>> = SpinMutex::new(BTreeMap::new());
+
+/// Tasks blocked in [`wait`].
+static WAITING: SpinMutex<BTreeSet<Uuid>> = SpinMutex::new(BTreeSet::new());
+
+/// Records the exit of `task` with `code`, waking its parent if it's waiting.
+///
+/// # Remarks
+///
+/// This must be called from an interrupt context, before the task's parent is reparented by
+/// [`crate::task::init::on_exit`].
+pub fn on_exit(task: &Task, code: usize) {
+    let id = task.id();
+    let mut zombies = ZOMBIES.lock();
+
+    // Zombies left for the exited task are now init's to collect, as its children are.
+    if let Some(orphaned) = zombies.remove(&id)
+        && let Some(init_id) = crate::task::init::id().filter(|init_id| *init_id != id)
+    {
+        zombies.entry(init_id).or_default().extend(orphaned);
+    }
+
+    if task.is_kernel() {
+        return;
+    }
+
+    let Some(parent) = crate::task::init::parent_of(id) else {
+        return;
+    };
+
+    zombies.entry(parent).or_default().push_back(Zombie {
+        status: ExitStatus {
+            id: id.into_bytes(),
+            code,
+        },
+        info: task.info(),
+    });
+
+    // The parent is woken with the zombies locked, so it can't miss this one between checking and blocking.
+    if WAITING.lock().remove(&parent) {
+        crate::task::unblock_task(parent);
+    }
+}
+
+/// Takes the exit status of one of the active task's exited children, blocking the task until one exits if none has.
+///
+/// Returns `None` if the task was blocked, in which case the system call is restarted once it is woken.
+///
+/// # Remarks
+///
+/// This must be called from an interrupt context, as the active task may be switched out in place.
+pub fn wait(
+    isf: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result<Option<ExitStatus>, Error> {
+    crate::cpu::local_state::LocalState::with_scheduler(|scheduler| {
+        let task_id = scheduler
+            .process()
+            .map(Task::id)
+            .ok_or(Error::NoActiveTask)?;
+
+        let mut zombies = ZOMBIES.lock();
+
+        if let Some(children) = zombies.get_mut(&task_id)
+            && let Some(zombie) = children.pop_front()
+        {
+            if children.is_empty() {
+                zombies.remove(&task_id);
+            }
+
+            return Ok(Some(zombie.status));
+        }
+
+        if !crate::task::init::has_children(task_id) {
+            return Err(Error::NoChildren);
+        }
+
+        WAITING.lock().insert(task_id);
+
+        // Safety: The task's registers are unchanged, so the wait is issued again when the task is woken.
+        unsafe {
+            crate::interrupts::syscall::restart(isf);
+        }
+
+        // The task is blocked with the zombies locked, so an exiting child can't try to wake it before it's blocked.
+        scheduler.block_task(isf, regs);
+
+        Ok(None)
+    })
+}
+
+/// Passes a snapshot of every zombie to `func`, returning `false` if the zombies are currently locked.
+pub fn try_for_each_zombie(mut func: impl FnMut(&TaskInfo)) -> bool {
+    crate::interrupts::uninterruptable(|| {
+        let Some(zombies) = ZOMBIES.try_lock() else {
+            return false;
+        };
+
+        zombies
+            .values()
+            .flatten()
+            .for_each(|zombie| func(&zombie.info));
+
+        true
+    })
+}
+
+/// Frees the resources of an exited task: its process (if it's the process's last thread), kernel stack (if it's a
+/// kernel thread), and extended state save area.
+///
+/// # Remarks
+///
+/// The task must no longer be in use anywhere, so this can't be called on its own stack, or with its address space
+/// current; the scheduler defers it until the hardware thread which ran the task has switched away (see
+/// [`crate::task::Scheduler::kill_task`]).
+pub fn reap(mut task: Task) {
+    trace!("Reaping: {:?}", task.id());
+
+    debug_assert!(
+        task.process()
+            .is_none_or(|process| Arc::strong_count(process) > 1 || !process.is_current()),
+        "reaped a task whose address space is current"
+    );
+
+    task.transition(State::Reaped);
+}
+
+crate::kernel_test! {
+    /// A task which was never made a parent has no children to wait on.
+    fn unknown_task_has_no_children() -> crate::tests::Outcome {
+        if crate::task::init::has_children(Uuid::new_v4()) {
+            return Err("task without children is reported to have some");
+        }
+
+        Ok(())
+    }
+}
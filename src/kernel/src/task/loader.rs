@@ -0,0 +1,359 @@
+//! Loading of userspace tasks from ELF images.
+//!
+//! Images are validated as they're loaded, so a malformed image fails to load, rather than faulting (or panicking)
+//! once its task runs. Position-independent executables are loaded at [`MIN_LOAD_OFFSET`], and fixed-address
+//! executables where they were linked; either way, segments are demand-mapped, and relocations are applied as the
+//! pages they fall in are mapped (see [`Task::demand_map`]). Images with thread-local storage give each thread a TLS
+//! block above their last segment (see [`tls`]).
+
+use crate::task::{
+    AddressSpace, DEFAULT_USERSPACE_SIZE, ElfData, ElfRela, Image, MIN_LOAD_OFFSET, Priority,
+    Startup, Task, tls,
+};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::ops::Range;
+use elf::{
+    ElfBytes,
+    endian::AnyEndian,
+    file::Class,
+    segment::ProgramHeader,
+    string_table::StringTable,
+    symbol::{Symbol, SymbolTable},
+};
+use libsys::{Address, page_size};
+
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("failed to parse ELF: {0:?}")]
+    Parse(elf::ParseError),
+
+    #[error("ELF is not a 64-bit x86 image (machine {0:#X})")]
+    UnsupportedMachine(u16),
+
+    #[error("ELF is not an executable (type {0:#X})")]
+    UnsupportedType(u16),
+
+    #[error("ELF has no program headers")]
+    NoSegments,
+
+    #[error("ELF segment at {0:#X} is invalid")]
+    InvalidSegment(u64),
+
+    #[error("ELF segment at {0:#X} is misaligned")]
+    MisalignedSegment(u64),
+
+    #[error("ELF segments at {0:#X} and {1:#X} overlap")]
+    OverlappingSegments(u64, u64),
+
+    #[error("ELF has an unsupported relocation type: {0:#X}")]
+    UnsupportedRelocation(u32),
+
+    #[error("ELF relocation has an invalid address: {0:#X}")]
+    InvalidRelocation(u64),
+
+    #[error("ELF relocation refers to an undefined symbol: {0}")]
+    UndefinedSymbol(String),
+
+    #[error("arguments and environment don't fit on the task's stack")]
+    StartupTooLarge,
+
+    #[error("failed to set up the task's address space: {0}")]
+    AddressSpace(crate::task::address_space::Error),
+}
+
+impl From<elf::ParseError> for LoadError {
+    fn from(error: elf::ParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+/// Pages (as an address range, before the load offset is applied) a loadable segment occupies.
+fn segment_pages(segment: &ProgramHeader) -> Range<u64> {
+    let page_size = u64::try_from(page_size()).unwrap();
+    let start = segment.p_vaddr - (segment.p_vaddr % page_size);
+    let end = (segment.p_vaddr + segment.p_memsz).next_multiple_of(page_size);
+
+    start..end
+}
+
+/// Checks each loadable segment of an image `image_len` bytes long, which is to be loaded at `load_offset`, returning
+/// them ordered by address.
+fn validate_segments(
+    segments: &[ProgramHeader],
+    image_len: usize,
+    load_offset: usize,
+) -> Result<Vec<ProgramHeader>, LoadError> {
+    let page_size = u64::try_from(page_size()).unwrap();
+    let image_len = u64::try_from(image_len).unwrap();
+    let load_offset = u64::try_from(load_offset).unwrap();
+    let userspace_size = u64::try_from(DEFAULT_USERSPACE_SIZE.get()).unwrap();
+
+    let mut loads = segments
+        .iter()
+        .filter(|segment| segment.p_type == elf::abi::PT_LOAD)
+        .copied()
+        .collect::<Vec<_>>();
+
+    for segment in &loads {
+        let invalid = LoadError::InvalidSegment(segment.p_vaddr);
+
+        // Segments are demand-mapped a page at a time, so each page must come from a single place in the file.
+        if !segment.p_align.is_power_of_two()
+            || !segment.p_align.is_multiple_of(page_size)
+            || (segment.p_vaddr % page_size) != (segment.p_offset % page_size)
+        {
+            return Err(LoadError::MisalignedSegment(segment.p_vaddr));
+        }
+
+        if segment.p_filesz > segment.p_memsz
+            || segment
+                .p_offset
+                .checked_add(segment.p_filesz)
+                .is_none_or(|file_end| file_end > image_len)
+        {
+            return Err(invalid);
+        }
+
+        // The segment must lie in userspace, above the stack.
+        let in_userspace = segment
+            .p_vaddr
+            .checked_add(segment.p_memsz)
+            .and_then(|end| end.checked_add(load_offset))
+            .is_some_and(|end| end <= userspace_size);
+        if !in_userspace
+            || (segment.p_vaddr + load_offset) < u64::try_from(MIN_LOAD_OFFSET).unwrap()
+        {
+            return Err(invalid);
+        }
+
+        let flags = segment.p_flags & (elf::abi::PF_W | elf::abi::PF_X);
+        if flags == (elf::abi::PF_W | elf::abi::PF_X) {
+            return Err(invalid);
+        }
+    }
+
+    loads.sort_unstable_by_key(|segment| segment.p_vaddr);
+
+    if let Some(pair) = loads
+        .windows(2)
+        .find(|pair| segment_pages(&pair[0]).end > segment_pages(&pair[1]).start)
+    {
+        return Err(LoadError::OverlappingSegments(
+            pair[0].p_vaddr,
+            pair[1].p_vaddr,
+        ));
+    }
+
+    Ok(loads)
+}
+
+/// Value of the symbol a relocation refers to, once loaded at `load_offset`.
+///
+/// # Remarks
+///
+/// Images aren't dynamically linked against anything, so every symbol must be defined by the image itself, save for
+/// weak symbols, which resolve to `0` if they're undefined.
+fn symbol_value(
+    symbols: Option<&(SymbolTable<'_, AnyEndian>, StringTable<'_>)>,
+    index: u32,
+    load_offset: usize,
+) -> Result<usize, LoadError> {
+    // The undefined symbol index means there's no symbol, which resolves to `0`.
+    if index == 0 {
+        return Ok(0);
+    }
+
+    let (symbols, strings) = symbols.ok_or(LoadError::UndefinedSymbol(String::from("?")))?;
+    let symbol: Symbol = symbols.get(usize::try_from(index).unwrap())?;
+
+    if symbol.is_undefined() {
+        return if symbol.st_bind() == elf::abi::STB_WEAK {
+            Ok(0)
+        } else {
+            Err(LoadError::UndefinedSymbol(String::from(
+                strings
+                    .get(usize::try_from(symbol.st_name).unwrap())
+                    .unwrap_or("?"),
+            )))
+        };
+    }
+
+    usize::try_from(symbol.st_value)
+        .map(|value| load_offset.wrapping_add(value))
+        .map_err(|_| LoadError::UndefinedSymbol(String::from("?")))
+}
+
+impl Task {
+    /// Constructs a new user task from the executable ELF image `elf_bytes`, started with `startup`.
+    ///
+    /// # Remarks
+    ///
+    /// The image is copied, and segments are demand-mapped from the copy as they are faulted in.
+    pub fn from_elf(
+        priority: Priority,
+        elf_bytes: &[u8],
+        startup: &Startup<'_>,
+    ) -> Result<Self, LoadError> {
+        let elf = ElfBytes::<AnyEndian>::minimal_parse(elf_bytes)?;
+
+        if elf.ehdr.class != Class::ELF64 || elf.ehdr.e_machine != elf::abi::EM_X86_64 {
+            return Err(LoadError::UnsupportedMachine(elf.ehdr.e_machine));
+        }
+
+        let load_offset = match elf.ehdr.e_type {
+            elf::abi::ET_DYN => MIN_LOAD_OFFSET,
+            elf::abi::ET_EXEC => 0,
+            e_type => return Err(LoadError::UnsupportedType(e_type)),
+        };
+
+        let segments = elf
+            .segments()
+            .ok_or(LoadError::NoSegments)?
+            .iter()
+            .collect::<Box<[_]>>();
+        let loads = validate_segments(&segments, elf_bytes.len(), load_offset)?;
+        let tls = tls::Layout::new(&segments, elf_bytes.len())?;
+
+        // Relocations must fall wholly within a page of a loadable segment, or they'd never be applied.
+        let rela_size = u64::try_from(size_of::<usize>()).unwrap();
+        let in_segment = |address: u64| {
+            address.is_multiple_of(rela_size)
+                && loads.iter().any(|segment| {
+                    address >= segment.p_vaddr
+                        && address
+                            .checked_add(rela_size)
+                            .is_some_and(|end| end <= segment.p_vaddr + segment.p_memsz)
+                })
+        };
+
+        let mut relas = Vec::new();
+        if let (Some(section_headers), _) = elf.section_headers_with_strtab()? {
+            for section_header in section_headers
+                .iter()
+                .filter(|section_header| section_header.sh_type == elf::abi::SHT_RELA)
+            {
+                // Relocations refer to symbols in the table the section is linked to.
+                let symbols = match section_headers
+                    .get(usize::try_from(section_header.sh_link).unwrap())
+                    .map(|linked| linked.sh_type)
+                {
+                    Ok(elf::abi::SHT_DYNSYM) => elf.dynamic_symbol_table()?,
+                    Ok(elf::abi::SHT_SYMTAB) => elf.symbol_table()?,
+                    _ => None,
+                };
+
+                for rela in elf.section_data_as_relas(&section_header)? {
+                    let addend = isize::try_from(rela.r_addend)
+                        .map_err(|_| LoadError::InvalidRelocation(rela.r_offset))?;
+
+                    let value = match rela.r_type {
+                        elf::abi::R_X86_64_NONE => continue,
+
+                        // B + A
+                        elf::abi::R_X86_64_RELATIVE => load_offset.wrapping_add_signed(addend),
+
+                        // S + A
+                        elf::abi::R_X86_64_64 => {
+                            symbol_value(symbols.as_ref(), rela.r_sym, load_offset)?
+                                .wrapping_add_signed(addend)
+                        }
+
+                        // S
+                        elf::abi::R_X86_64_GLOB_DAT | elf::abi::R_X86_64_JUMP_SLOT => {
+                            symbol_value(symbols.as_ref(), rela.r_sym, load_offset)?
+                        }
+
+                        // Offset of the thread-local symbol from the thread pointer.
+                        elf::abi::R_X86_64_TPOFF64 => {
+                            let tls = tls.ok_or(LoadError::InvalidRelocation(rela.r_offset))?;
+
+                            tls.tp_offset(symbol_value(symbols.as_ref(), rela.r_sym, 0)?)
+                                .wrapping_add_signed(addend)
+                        }
+
+                        r_type => return Err(LoadError::UnsupportedRelocation(r_type)),
+                    };
+
+                    if !in_segment(rela.r_offset) {
+                        return Err(LoadError::InvalidRelocation(rela.r_offset));
+                    }
+
+                    let address = usize::try_from(rela.r_offset)
+                        .ok()
+                        .and_then(Address::new)
+                        .ok_or(LoadError::InvalidRelocation(rela.r_offset))?;
+
+                    relas.push(ElfRela { address, value });
+                }
+            }
+        }
+
+        let image = Image::new(
+            AddressSpace::new_userspace(),
+            load_offset,
+            elf.ehdr,
+            segments,
+            relas,
+            ElfData::Memory(Box::from(elf_bytes)),
+            tls,
+        );
+
+        Self::new(priority, image, startup)
+    }
+}
+
+crate::kernel_test! {
+    /// Images which aren't valid ELF executables fail to load, rather than panicking.
+    fn rejects_malformed_images() -> crate::tests::Outcome {
+        if Task::from_elf(Priority::Normal, &[], &Startup::default()).is_ok() {
+            return Err("empty image loaded");
+        }
+
+        if Task::from_elf(Priority::Normal, b"\x7FELF not really an image", &Startup::default()).is_ok() {
+            return Err("truncated image loaded");
+        }
+
+        Ok(())
+    }
+
+    /// Segments must be page-aligned, and mustn't share pages.
+    fn rejects_invalid_segments() -> crate::tests::Outcome {
+        let page_size = u64::try_from(page_size()).unwrap();
+        let base = u64::try_from(MIN_LOAD_OFFSET).unwrap();
+        let validate = |segments: &[ProgramHeader]| validate_segments(segments, page_size(), 0);
+        let segment = |p_vaddr, p_memsz| ProgramHeader {
+            p_type: elf::abi::PT_LOAD,
+            p_offset: p_vaddr % page_size,
+            p_vaddr,
+            p_paddr: p_vaddr,
+            p_filesz: 0,
+            p_memsz,
+            p_flags: elf::abi::PF_R,
+            p_align: page_size,
+        };
+
+        if validate(&[segment(base, page_size), segment(base + page_size, 1)]).is_err() {
+            return Err("adjacent segments were rejected");
+        }
+
+        if !matches!(
+            validate(&[segment(base + page_size + 16, 1), segment(base, page_size + 8)]),
+            Err(LoadError::OverlappingSegments(..))
+        ) {
+            return Err("segments sharing a page were accepted");
+        }
+
+        if !matches!(
+            validate(&[ProgramHeader { p_align: 8, ..segment(base, 1) }]),
+            Err(LoadError::MisalignedSegment(_))
+        ) {
+            return Err("misaligned segment was accepted");
+        }
+
+        if validate(&[segment(0, 1)]).is_ok() {
+            return Err("segment overlapping the stack was accepted");
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use crate::arch::x86_64::structures::idt::InterruptStackFrame;
-use alloc::{boxed::Box, string::String, vec::Vec};
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
 use bit_field::BitField;
 use core::num::NonZeroUsize;
 use elf::{endian::AnyEndian, file::FileHeader, segment::ProgramHeader};
@@ -8,17 +8,61 @@ use libsys::{Address, Virtual, page_size};
 mod context;
 pub use context::*;
 
+mod affinity;
+pub use affinity::*;
+
+mod cpu_time;
+pub use cpu_time::*;
+
+mod info;
+pub use info::*;
+
+mod run_queue;
+pub use run_queue::*;
+
 mod scheduling;
 pub use scheduling::*;
 
 mod address_space;
 pub use address_space::*;
 
+pub mod deadline;
+pub mod handles;
+pub mod hardware;
+pub mod init;
+pub mod ipc;
+pub mod kthread;
+pub mod lifecycle;
+
+mod loader;
+pub use loader::*;
+
+mod process;
+pub use process::*;
+
+mod startup;
+pub use startup::*;
+
+pub mod shared_memory;
+pub mod signals;
+pub mod symbols;
+pub mod timers;
+pub mod tls;
+pub mod workqueue;
+
+mod state;
+pub use state::*;
+
+#[cfg(debug_assertions)]
+pub mod resources;
+
 #[allow(clippy::cast_possible_truncation)]
 pub const STACK_SIZE: NonZeroUsize = NonZeroUsize::new(1_000_000).unwrap();
 pub const STACK_PAGES: NonZeroUsize = NonZeroUsize::new(STACK_SIZE.get() / page_size()).unwrap();
 pub const STACK_START: NonZeroUsize = NonZeroUsize::new(page_size()).unwrap();
-pub const MIN_LOAD_OFFSET: usize = STACK_START.get() + STACK_SIZE.get();
+/// Address position-independent images are loaded at: the first page above the stack.
+pub const MIN_LOAD_OFFSET: usize =
+    (STACK_START.get() + STACK_SIZE.get()).next_multiple_of(page_size());
 
 pub const PT_FLAG_EXEC_BIT: usize = 0;
 pub const PT_FLAG_WRITE_BIT: usize = 1;
@@ -45,11 +89,37 @@ pub enum Error {
 
     #[error("address belongs to a non-load segment")]
     NonLoadAddress(Address<Virtual>),
+
+    #[error("kernel threads have no image to demand map from")]
+    KernelThread(Address<Virtual>),
+}
+
+/// Blocks the active task for at least `duration`, re-queueing it once the duration has elapsed.
+///
+/// # Remarks
+///
+/// This must be called from an interrupt context, as the active task is switched out in place.
+pub fn sleep(duration: core::time::Duration, isf: &mut InterruptStackFrame, regs: &mut Registers) {
+    crate::cpu::local_state::LocalState::with_scheduler(|scheduler| {
+        scheduler.sleep_task(duration, isf, regs);
+    });
+}
+
+/// Exits the active task with `code`, which is left for its parent to collect (see [`lifecycle::wait`]).
+///
+/// # Remarks
+///
+/// This must be called from an interrupt context, as the active task is switched out in place.
+pub fn exit(code: usize, isf: &mut InterruptStackFrame, regs: &mut Registers) {
+    crate::cpu::local_state::LocalState::with_scheduler(|scheduler| {
+        scheduler.kill_task(code, isf, regs);
+    });
 }
 
 pub static TASK_LOAD_BASE: usize = 0x20000;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, IntoPrimitive, TryFromPrimitive)]
 pub enum Priority {
     Idle = 0,
     Low = 1,
@@ -58,6 +128,22 @@ pub enum Priority {
     Critical = 4,
 }
 
+impl Priority {
+    /// Number of priority levels.
+    pub const COUNT: usize = 5;
+
+    /// Priority a task is scheduled at while it's boosted: a level higher, short of [`Self::Critical`] (which is
+    /// reserved for tasks created at it). Idle tasks are never boosted.
+    pub const fn boosted(self) -> Self {
+        match self {
+            Self::Idle => Self::Idle,
+            Self::Low => Self::Normal,
+            Self::Normal | Self::High => Self::High,
+            Self::Critical => Self::Critical,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct ElfRela {
     pub address: Address<Virtual>,
@@ -66,6 +152,51 @@ pub struct ElfRela {
 
 pub type Context = (InterruptStackFrame, Registers);
 
+/// A task's saved context, with a canary (see [`crate::mem::stack::canary`]) placed below it, so it's caught if a
+/// write runs off the end of whatever precedes it.
+#[repr(C)]
+struct SavedContext {
+    canary: u64,
+    context: Context,
+}
+
+impl SavedContext {
+    fn new(context: Context) -> Self {
+        Self {
+            canary: crate::mem::stack::canary(),
+            context,
+        }
+    }
+
+    fn is_intact(&self) -> bool {
+        // Safety: The canary is a field of `self`, so it's valid for reads. The read is volatile, as the canary is
+        //         only ever clobbered without the compiler's knowledge.
+        let canary = unsafe { core::ptr::from_ref(&self.canary).read_volatile() };
+
+        canary == crate::mem::stack::canary()
+    }
+}
+
+impl core::ops::Deref for SavedContext {
+    type Target = Context;
+
+    fn deref(&self) -> &Self::Target {
+        &self.context
+    }
+}
+
+impl core::ops::DerefMut for SavedContext {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.context
+    }
+}
+
+impl core::fmt::Debug for SavedContext {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.context.fmt(f)
+    }
+}
+
 #[derive(Debug)]
 pub enum ElfData {
     Memory(Box<[u8]>),
@@ -74,59 +205,152 @@ pub enum ElfData {
 
 pub struct Task {
     id: uuid::Uuid,
+    /// Name the task is listed with, if it was given one.
+    name: Option<String>,
     priority: Priority,
+    /// Whether the task is scheduled at its boosted priority, for having slept (see [`Self::set_boosted`]).
+    is_boosted: bool,
+    /// Hardware threads the task may be scheduled on.
+    affinity: Affinity,
+    state: State,
+    /// Time the task has spent running, up to when it was last accounted (see [`Scheduler`]).
+    cpu_time: CpuTime,
+    /// Hardware thread the task last ran on, if it has run.
+    hwthread_id: Option<u32>,
+    /// Whether the task's system calls are traced; see [`crate::interrupts::syscall::trace`].
+    is_traced: bool,
+
+    context: SavedContext,
+    extended_state: ExtendedState,
+    kind: Kind,
+}
+
+/// What a task runs.
+enum Kind {
+    /// A thread of a userspace process, in the process's address space.
+    User(Arc<Process>),
+    /// A kernel function, on the task's own kernel stack; see [`kthread`].
+    Kernel(Box<kthread::Stack>),
+}
 
+/// ELF image a userspace process was launched from, and the address space it's loaded into.
+pub struct Image {
     address_space: AddressSpace,
-    context: Context,
     load_offset: usize,
 
     elf_header: FileHeader<AnyEndian>,
     elf_segments: Box<[ProgramHeader]>,
     elf_relas: Vec<ElfRela>,
     elf_data: ElfData,
+    /// Identifies the binary the task was launched from.
+    image_id: u64,
+
+    /// Layout of each thread's thread-local storage, if the image has any.
+    tls: Option<tls::Layout>,
+    /// Address the next thread's TLS block is placed at.
+    next_tls: usize,
 }
 
 impl Task {
+    /// Creates the first thread of a new process, running `image`, which starts with `startup`.
     pub fn new(
         priority: Priority,
-        mut address_space: AddressSpace,
-        load_offset: usize,
-        elf_header: FileHeader<AnyEndian>,
-        elf_segments: Box<[ProgramHeader]>,
-        elf_relas: Vec<ElfRela>,
-        elf_data: ElfData,
-    ) -> Self {
+        mut image: Image,
+        startup: &Startup<'_>,
+    ) -> Result<Self, LoadError> {
         trace!("Generating a random ID for new task.");
         let id = uuid::Uuid::new_v4();
 
         trace!("Allocating userspace stack for task: {id:?}.");
-        let stack = address_space
+        let stack = image
+            .address_space
             .mmap(
                 Some(Address::new_truncate(STACK_START.get())),
                 STACK_PAGES,
                 MmapPermissions::ReadWrite,
             )
-            .unwrap();
-        Self {
+            .map_err(LoadError::AddressSpace)?;
+
+        let entry = image.load_offset + usize::try_from(image.elf_header.e_entry).unwrap();
+        let mut auxv = alloc::vec![
+            (AT_ENTRY, entry),
+            (AT_BASE, 0),
+            (AT_PHENT, usize::from(image.elf_header.e_phentsize)),
+            (AT_PHNUM, image.elf_segments.len()),
+        ];
+        if let Some(phdr) = startup::phdr_address(&image.elf_header, &image.elf_segments) {
+            auxv.push((AT_PHDR, image.load_offset + phdr));
+        }
+
+        trace!("Building initial stack for task: {id:?}.");
+        let (stack_pointer, registers) = startup::build_stack(
+            &mut image.address_space,
+            stack.cast::<u8>().as_ptr().addr() + stack.len(),
+            // As on other systems, a quarter of the stack may be taken up by arguments and environment.
+            stack.len() / 4,
+            startup,
+            &auxv,
+        )?;
+
+        if let Some(time_frame) = crate::time::vdso::frame() {
+            trace!("Mapping the time page for task: {id:?}.");
+            image
+                .address_space
+                .map_shared(
+                    crate::time::vdso::user_address(),
+                    time_frame,
+                    MmapPermissions::ReadOnly,
+                )
+                .map_err(LoadError::AddressSpace)?;
+        }
+
+        let mut extended_state = ExtendedState::new();
+        if let Some(thread_pointer) = image.allocate_tls()? {
+            extended_state.set_fs_base(u64::try_from(thread_pointer).unwrap());
+        }
+
+        Ok(Self {
             id,
+            name: None,
             priority,
-            address_space,
-            context: (
+            is_boosted: false,
+            affinity: Affinity::ALL,
+            state: State::Created,
+            cpu_time: CpuTime::ZERO,
+            hwthread_id: None,
+            is_traced: false,
+            context: SavedContext::new((
                 InterruptStackFrame::new_user(
-                    Address::new(load_offset + usize::try_from(elf_header.e_entry).unwrap())
-                        .unwrap(),
-                    Address::from_ptr({
-                        // Safety: Index is the end of the slice.
-                        unsafe { stack.get_unchecked_mut(stack.len()).as_ptr() }
-                    }),
+                    Address::new(entry).unwrap(),
+                    Address::new(stack_pointer).unwrap(),
                 ),
-                Registers::empty(),
-            ),
-            load_offset,
-            elf_header,
-            elf_segments,
-            elf_relas,
-            elf_data,
+                registers,
+            )),
+            extended_state,
+            kind: Kind::User(Arc::new(Process::new(id, image))),
+        })
+    }
+
+    /// Creates a kernel thread named `name`, which starts with `context` on `stack`.
+    pub(super) fn new_kernel(
+        name: String,
+        priority: Priority,
+        context: Context,
+        stack: Box<kthread::Stack>,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            name: Some(name),
+            priority,
+            is_boosted: false,
+            affinity: Affinity::ALL,
+            state: State::Created,
+            cpu_time: CpuTime::ZERO,
+            hwthread_id: None,
+            is_traced: false,
+            context: SavedContext::new(context),
+            extended_state: ExtendedState::new(),
+            kind: Kind::Kernel(stack),
         }
     }
 
@@ -135,11 +359,200 @@ impl Task {
         self.id
     }
 
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
     #[inline]
     pub const fn priority(&self) -> Priority {
         self.priority
     }
 
+    /// Priority the task is scheduled at, which is raised while it's boosted.
+    #[inline]
+    pub const fn effective_priority(&self) -> Priority {
+        if self.is_boosted {
+            self.priority.boosted()
+        } else {
+            self.priority
+        }
+    }
+
+    /// Sets whether the task is scheduled at its boosted priority.
+    ///
+    /// # Remarks
+    ///
+    /// Tasks are boosted when they wake from sleeping (or blocking), and lose the boost once they use up a whole time
+    /// slice, so I/O-bound tasks are scheduled ahead of CPU-bound tasks of the same priority.
+    #[inline]
+    pub fn set_boosted(&mut self, is_boosted: bool) {
+        self.is_boosted = is_boosted;
+    }
+
+    #[inline]
+    pub const fn affinity(&self) -> Affinity {
+        self.affinity
+    }
+
+    /// Restricts the hardware threads the task may be scheduled on to `affinity`.
+    ///
+    /// # Remarks
+    ///
+    /// The task isn't moved if it's already running elsewhere; it migrates the next time it's switched out.
+    #[inline]
+    pub fn set_affinity(&mut self, affinity: Affinity) {
+        self.affinity = affinity;
+    }
+
+    #[inline]
+    pub const fn state(&self) -> State {
+        self.state
+    }
+
+    /// Time the task has spent running, not counting the time since it was last accounted (if it's running).
+    #[inline]
+    pub const fn cpu_time(&self) -> CpuTime {
+        self.cpu_time
+    }
+
+    /// Hardware thread the task is running on, or last ran on.
+    #[inline]
+    pub const fn hwthread_id(&self) -> Option<u32> {
+        self.hwthread_id
+    }
+
+    /// Whether the task's system calls are traced.
+    #[inline]
+    pub const fn is_traced(&self) -> bool {
+        self.is_traced
+    }
+
+    /// Sets whether the task's system calls are traced (see [`crate::interrupts::syscall::trace`]).
+    pub fn set_traced(&mut self, is_traced: bool) {
+        if is_traced {
+            crate::interrupts::syscall::trace::enable();
+        }
+
+        self.is_traced = is_traced;
+    }
+
+    /// Moves the task into the `next` lifecycle state.
+    ///
+    /// # Remarks
+    ///
+    /// All task state changes should occur through this function, so illegal transitions are caught.
+    pub fn transition(&mut self, next: State) {
+        debug_assert!(
+            self.state.can_transition_to(next),
+            "illegal task state transition for {:?}: {:?} -> {:?}",
+            self.id,
+            self.state,
+            next
+        );
+
+        self.state = next;
+    }
+
+    /// Whether the task is a kernel thread, rather than a userspace task.
+    #[inline]
+    pub const fn is_kernel(&self) -> bool {
+        matches!(self.kind, Kind::Kernel(_))
+    }
+
+    /// Process the task is a thread of, or `None` for kernel threads, which run in whichever address space was
+    /// current (as the kernel's half is shared by every address space).
+    #[inline]
+    pub const fn process(&self) -> Option<&Arc<Process>> {
+        match &self.kind {
+            Kind::User(process) => Some(process),
+            Kind::Kernel(_) => None,
+        }
+    }
+
+    /// Panics, naming the task, if the canary below its saved context or at the base of its kernel stack has been
+    /// clobbered.
+    pub fn check_canaries(&self) {
+        let clobbered = if !self.context.is_intact() {
+            "saved context"
+        } else if let Kind::Kernel(stack) = &self.kind
+            && !stack.canary_intact()
+        {
+            "kernel stack"
+        } else {
+            return;
+        };
+
+        panic!(
+            "{clobbered} canary of task {:?} ({}) was clobbered",
+            self.id,
+            self.name().unwrap_or("unnamed")
+        );
+    }
+
+    /// Demand maps the page containing `address` from the task's image.
+    pub fn demand_map(&self, address: Address<Virtual>) -> Result<(), Error> {
+        match &self.kind {
+            Kind::User(process) => process.with_image(|image| image.demand_map(address)),
+            Kind::Kernel(_) => Err(Error::KernelThread(address)),
+        }
+    }
+}
+
+impl Image {
+    /// Image loaded (at `load_offset`) into `address_space`, whose threads' TLS blocks are laid out by `tls`.
+    pub fn new(
+        address_space: AddressSpace,
+        load_offset: usize,
+        elf_header: FileHeader<AnyEndian>,
+        elf_segments: Box<[ProgramHeader]>,
+        elf_relas: Vec<ElfRela>,
+        elf_data: ElfData,
+        tls: Option<tls::Layout>,
+    ) -> Self {
+        // TLS blocks are placed above the image, each past a guard page.
+        let image_end = elf_segments
+            .iter()
+            .filter(|segment| segment.p_type == elf::abi::PT_LOAD)
+            .map(|segment| usize::try_from(segment.p_vaddr + segment.p_memsz).unwrap())
+            .max()
+            .unwrap_or(0)
+            .next_multiple_of(page_size());
+
+        Self {
+            address_space,
+            load_offset,
+            elf_header,
+            elf_segments,
+            elf_relas,
+            image_id: symbols::image_id(&elf_data),
+            elf_data,
+            tls,
+            next_tls: load_offset + image_end + page_size(),
+        }
+    }
+
+    /// Maps and initializes a TLS block for a new thread, returning its thread pointer, or `None` if the image has no
+    /// thread-local storage.
+    pub fn allocate_tls(&mut self) -> Result<Option<usize>, LoadError> {
+        let Some(tls) = self.tls else {
+            return Ok(None);
+        };
+
+        let address = Some(self.next_tls)
+            .filter(|address| (address + tls.size()) <= DEFAULT_USERSPACE_SIZE.get())
+            .and_then(Address::new)
+            .ok_or(LoadError::AddressSpace(address_space::Error::OutOfMemory))?;
+
+        let thread_pointer = match &self.elf_data {
+            ElfData::Memory(data) => tls.allocate(&mut self.address_space, address, data)?,
+            ElfData::File(_) => unimplemented!(),
+        };
+        self.next_tls += tls.size().next_multiple_of(page_size()) + page_size();
+
+        Ok(Some(thread_pointer))
+    }
+
     #[inline]
     pub const fn address_space(&self) -> &AddressSpace {
         &self.address_space
@@ -170,13 +583,18 @@ impl Task {
         &self.elf_data
     }
 
+    #[inline]
+    pub const fn image_id(&self) -> u64 {
+        self.image_id
+    }
+
     #[inline]
     pub fn elf_relas(&mut self) -> &mut Vec<ElfRela> {
         &mut self.elf_relas
     }
 
     #[allow(clippy::too_many_lines)]
-    pub fn demand_map(&mut self, address: Address<Virtual>) -> Result<(), Error> {
+    fn demand_map(&mut self, address: Address<Virtual>) -> Result<(), Error> {
         use crate::mem::paging::TableEntryFlags;
         use core::mem::MaybeUninit;
         use libsys::Page;
@@ -203,12 +621,6 @@ impl Task {
             .copied()
             .ok_or(Error::NonLoadAddress(address))?;
 
-        // Small check to help ensure the segment alignments are page-fit.
-        debug_assert_eq!(
-            segment.p_align & u64::try_from(libsys::page_mask()).unwrap(),
-            0
-        );
-
         debug!(
             "Demand mapping {:X?} from segment: {:X?}",
             Address::<Page>::new_truncate(address.get()),
@@ -226,12 +638,17 @@ impl Task {
         let segment_size = usize::try_from(segment.p_filesz).unwrap();
         let segment_end_addr = segment_addr + segment_size;
 
-        let fault_offset = fault_unoffset_page_addr.saturating_sub(segment_addr);
-        let fault_end_pad = fault_unoffset_end_page_addr.saturating_sub(segment_end_addr);
-        let fault_front_pad = segment_addr.saturating_sub(fault_unoffset_page_addr);
-        let fault_size = ((fault_unoffset_end_page_addr - fault_unoffset_page_addr)
-            - fault_front_pad)
-            - fault_end_pad;
+        // Only the part of the page within the segment's file data is copied; the rest (before the segment, or in
+        // its zero-initialized tail, where `p_memsz` exceeds `p_filesz`) is zeroed.
+        let file_start_addr = fault_unoffset_page_addr.max(segment_addr);
+        let file_end_addr = fault_unoffset_end_page_addr
+            .min(segment_end_addr)
+            .max(file_start_addr);
+
+        let fault_offset = file_start_addr - segment_addr;
+        let fault_front_pad = file_start_addr - fault_unoffset_page_addr;
+        let fault_size = file_end_addr - file_start_addr;
+        let fault_end_pad = fault_unoffset_end_page_addr - file_end_addr;
 
         trace!("Mapping the demand page RW so data can be copied.");
         let mapped_memory = self
@@ -258,8 +675,8 @@ impl Task {
             file_memory.len(),
             end_pad.len()
         );
-        front_pad.fill(MaybeUninit::uninit());
-        end_pad.fill(MaybeUninit::uninit());
+        front_pad.fill(MaybeUninit::new(0));
+        end_pad.fill(MaybeUninit::new(0));
 
         if !file_memory.is_empty() {
             match self.elf_data() {
@@ -312,7 +729,7 @@ impl Task {
                     TableEntryFlags::PRESENT
                         | TableEntryFlags::USER
                         | TableEntryFlags::from(crate::task::segment_to_mmap_permissions(
-                            segment.p_type,
+                            segment.p_flags,
                         )),
                 )
                 .unwrap();
@@ -326,13 +743,25 @@ impl Task {
 
 impl core::fmt::Debug for Task {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("Task")
+        let mut debug_struct = f.debug_struct("Task");
+        debug_struct
             .field("ID", &self.id)
+            .field("Name", &self.name)
             .field("Priority", &self.priority)
-            .field("Address Space", &self.address_space)
-            .field("Context", &self.context)
-            .field("ELF Load Offset", &self.load_offset)
-            .field("ELF Header", &self.elf_header)
-            .finish_non_exhaustive()
+            .field("Boosted", &self.is_boosted)
+            .field("Affinity", &self.affinity)
+            .field("Context", &self.context);
+
+        match &self.kind {
+            Kind::User(process) => {
+                debug_struct.field("Process", process);
+            }
+
+            Kind::Kernel(stack) => {
+                debug_struct.field("Kernel Stack Top", &stack.top());
+            }
+        }
+
+        debug_struct.finish_non_exhaustive()
     }
 }
@@ -0,0 +1,133 @@
+//! Processes: the address space (and ELF image) shared by the threads of a userspace program.
+//!
+//! Each thread is a [`Task`] of its own, with its own registers, stack, thread-local storage, and scheduling state,
+//! referring to the process it belongs to. A process is created with its first thread (whose ID it shares), and is
+//! freed with its last, once that's reaped. Threads exit individually; each reports its exit status to the thread
+//! which created it, so it can be joined with [`crate::task::lifecycle::wait`].
+
+use crate::{
+    arch::x86_64::structures::{idt::InterruptStackFrame, tss::IoBitmap},
+    sync::SpinMutex,
+    task::{
+        DEFAULT_USERSPACE_SIZE, ExtendedState, Image, Kind, LoadError, Registers, SavedContext,
+        Task,
+    },
+};
+use alloc::{boxed::Box, sync::Arc};
+use libsys::{Address, Virtual};
+use uuid::Uuid;
+
+#[derive(Debug, Error)]
+pub enum ThreadError {
+    #[error("kernel threads have no process to create threads in")]
+    KernelThread,
+
+    #[error("thread entry or stack is not a userspace address: {0:#X}")]
+    InvalidAddress(usize),
+
+    #[error("failed to allocate the thread's thread-local storage: {0}")]
+    Tls(#[from] LoadError),
+}
+
+pub struct Process {
+    /// ID of the process's first thread.
+    id: Uuid,
+    image: SpinMutex<Image>,
+    /// Ports the process's threads are permitted to access, if any have been granted (see [`crate::task::hardware`]).
+    io_bitmap: SpinMutex<Option<Box<IoBitmap>>>,
+}
+
+impl Process {
+    pub(super) const fn new(id: Uuid, image: Image) -> Self {
+        Self {
+            id,
+            image: SpinMutex::new(image),
+            io_bitmap: SpinMutex::new(None),
+        }
+    }
+
+    /// Runs `func` with the ports the process's threads are permitted to access.
+    pub fn with_io_bitmap<T>(&self, func: impl FnOnce(&mut Option<Box<IoBitmap>>) -> T) -> T {
+        crate::interrupts::uninterruptable(|| func(&mut self.io_bitmap.lock()))
+    }
+
+    /// Runs `func` with the process's image, which its threads share.
+    pub fn with_image<T>(&self, func: impl FnOnce(&mut Image) -> T) -> T {
+        crate::interrupts::uninterruptable(|| func(&mut self.image.lock()))
+    }
+
+    /// Whether the process's address space is the current hardware thread's.
+    pub fn is_current(&self) -> bool {
+        self.with_image(|image| image.address_space().is_current())
+    }
+
+    /// # Safety
+    ///
+    /// Caller must ensure that switching the currently active address space will not cause undefined behaviour.
+    pub unsafe fn swap_into(&self) {
+        self.with_image(|image| {
+            // Safety: Caller is required to maintain safety invariants.
+            unsafe { image.address_space().swap_into() }
+        });
+    }
+}
+
+impl core::fmt::Debug for Process {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Process")
+            .field("ID", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+fn user_address(address: usize) -> Result<Address<Virtual>, ThreadError> {
+    Some(address)
+        .filter(|address| *address < DEFAULT_USERSPACE_SIZE.get())
+        .and_then(Address::new)
+        .ok_or(ThreadError::InvalidAddress(address))
+}
+
+impl Task {
+    /// Creates a thread in the process of `self`, which enters `entry` with `arg` on the stack ending at `stack`.
+    ///
+    /// The thread inherits the priority and affinity of `self`, and is given its own thread-local storage.
+    ///
+    /// # Remarks
+    ///
+    /// `entry` is entered as though it was called as `extern "C" fn(arg: usize) -> !`, so the stack pointer is
+    /// aligned, and offset by the return address `call` pushes; there's nothing to return to.
+    pub fn new_thread(&self, entry: usize, stack: usize, arg: usize) -> Result<Self, ThreadError> {
+        let Kind::User(process) = &self.kind else {
+            return Err(ThreadError::KernelThread);
+        };
+
+        let entry = user_address(entry)?;
+        let stack_pointer = (stack & !0xF)
+            .checked_sub(size_of::<u64>())
+            .ok_or(ThreadError::InvalidAddress(stack))
+            .and_then(user_address)?;
+
+        let mut extended_state = ExtendedState::new();
+        if let Some(thread_pointer) = process.with_image(Image::allocate_tls)? {
+            extended_state.set_fs_base(u64::try_from(thread_pointer).unwrap());
+        }
+
+        let mut regs = Registers::empty();
+        regs.rdi = arg;
+
+        Ok(Self {
+            id: Uuid::new_v4(),
+            name: None,
+            priority: self.priority,
+            is_boosted: false,
+            affinity: self.affinity,
+            state: crate::task::State::Created,
+            cpu_time: crate::task::CpuTime::ZERO,
+            hwthread_id: None,
+            is_traced: self.is_traced,
+            context: SavedContext::new((InterruptStackFrame::new_user(entry, stack_pointer), regs)),
+            extended_state,
+            kind: Kind::User(Arc::clone(process)),
+        })
+    }
+}
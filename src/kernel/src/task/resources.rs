@@ -0,0 +1,141 @@
+//! Debug-build tracking of the resources each task owns outside of its address space, so that any still held when the
+//! task exits can be reported (i.e. long test runs slowly exhausting handles).
+//!
+//! Each task's ledger is keyed by its ID, as resources may be acquired on behalf of a task which isn't running (i.e.
+//! a handle transferred to a task blocked receiving it).
+
+use crate::sync::SpinMutex;
+use alloc::collections::btree_map::BTreeMap;
+use libsys::{Address, Virtual};
+use uuid::Uuid;
+
+/// Number of stack frames captured when a resource is acquired.
+const BACKTRACE_DEPTH: usize = 8;
+
+/// Categories of resources a task may own outside of its address space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ResourceKind {
+    /// Handle to an IPC port or a hardware object.
+    Handle,
+    /// Handle to a shared memory object, which keeps its frames allocated.
+    Frame,
+    /// Handle to a per-task timer.
+    Timer,
+}
+
+struct Record {
+    backtrace: [Address<Virtual>; BACKTRACE_DEPTH],
+    backtrace_len: usize,
+}
+
+/// Tracks the resources owned by a task, so that any which are not released by exit can be reported.
+///
+/// # Remarks
+///
+/// Memory mapped into the task's address space is reclaimed along with it, and so is not tracked here.
+#[derive(Default)]
+struct ResourceLedger(BTreeMap<(ResourceKind, u64), Record>);
+
+impl ResourceLedger {
+    /// Records that the task has acquired the resource identified by `kind` and `id`.
+    #[inline(never)]
+    fn acquire(&mut self, kind: ResourceKind, id: u64) {
+        #[cfg_attr(not(feature = "panic_traces"), allow(unused_mut))]
+        let mut record = Record {
+            backtrace: [Address::new_truncate(0); BACKTRACE_DEPTH],
+            backtrace_len: 0,
+        };
+
+        #[cfg(feature = "panic_traces")]
+        {
+            record.backtrace_len = crate::panic::tracing::capture(&mut record.backtrace);
+        }
+
+        if self.0.insert((kind, id), record).is_some() {
+            warn!("Resource acquired twice without being released: {kind:?} #{id}");
+        }
+    }
+
+    /// Records that the task has released the resource identified by `kind` and `id`.
+    fn release(&mut self, kind: ResourceKind, id: u64) {
+        if self.0.remove(&(kind, id)).is_none() {
+            warn!("Resource released without being acquired: {kind:?} #{id}");
+        }
+    }
+
+    /// Logs a report of every resource which has not been released, returning how many were found.
+    fn report_leaks(&self, task_id: Uuid) -> usize {
+        use core::fmt::Write;
+
+        if self.0.is_empty() {
+            return 0;
+        }
+
+        error!(
+            "Task {task_id:?} exited with {} unreleased resources:",
+            self.0.len()
+        );
+
+        let mut kind_counts = BTreeMap::<ResourceKind, usize>::new();
+        for ((kind, id), record) in &self.0 {
+            *kind_counts.entry(*kind).or_default() += 1;
+
+            let mut report = alloc::string::String::new();
+            writeln!(&mut report, "Leaked {kind:?} #{id}, acquired at:").ok();
+
+            #[cfg(feature = "panic_traces")]
+            record.backtrace[..record.backtrace_len]
+                .iter()
+                .enumerate()
+                .try_for_each(|(depth, trace_address)| {
+                    crate::panic::tracing::write_frame(&mut report, depth, *trace_address)
+                })
+                .ok();
+
+            #[cfg(not(feature = "panic_traces"))]
+            let _ = record;
+
+            error!("{report}");
+        }
+
+        for (kind, count) in kind_counts {
+            error!("    {kind:?}: {count}");
+        }
+
+        self.0.len()
+    }
+}
+
+static LEDGERS: SpinMutex<BTreeMap<Uuid, ResourceLedger>> = SpinMutex::new(BTreeMap::new());
+
+/// Records that the task `task_id` has acquired the resource identified by `kind` and `id`.
+#[inline(never)]
+pub fn acquire(task_id: Uuid, kind: ResourceKind, id: u64) {
+    crate::interrupts::uninterruptable(|| {
+        LEDGERS.lock().entry(task_id).or_default().acquire(kind, id);
+    });
+}
+
+/// Records that the task `task_id` has released the resource identified by `kind` and `id`.
+///
+/// # Remarks
+///
+/// Resources released once their task has exited (i.e. by its handles being closed) were already reported, so they're
+/// ignored.
+pub fn release(task_id: Uuid, kind: ResourceKind, id: u64) {
+    crate::interrupts::uninterruptable(|| {
+        if let Some(ledger) = LEDGERS.lock().get_mut(&task_id) {
+            ledger.release(kind, id);
+        }
+    });
+}
+
+/// Logs a report of every resource the task `task_id` has not released, returning how many were found.
+///
+/// # Remarks
+///
+/// This must be called before the task's resources are reclaimed, as they're no longer tracked afterwards.
+pub fn on_exit(task_id: Uuid) -> usize {
+    crate::interrupts::uninterruptable(|| LEDGERS.lock().remove(&task_id))
+        .map_or(0, |ledger| ledger.report_leaks(task_id))
+}
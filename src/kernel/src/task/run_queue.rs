@@ -0,0 +1,365 @@
+//! Queue of tasks which are ready to run, ordered by the scheduling [`Policy`].
+//!
+//! Under the priority policy, each priority has its own queue, and the highest-priority task runs first, for a time
+//! slice scaled by its priority. A task left queued behind higher-priority tasks for [`STARVATION_LIMIT`] is run
+//! next regardless, so lower priorities are never starved outright.
+//!
+//! Each hardware thread has its own queue (see [`LocalRunQueue`]). Ready tasks are queued on the least-loaded
+//! hardware thread their affinity (see [`crate::task::Affinity`]) allows, preferring the one they last ran on, and
+//! load is balanced by stealing tasks from the busiest hardware thread: whenever a hardware thread runs out of tasks,
+//! and every [`BALANCE_INTERVAL`] otherwise. Isolated hardware threads only run tasks pinned to them (see
+//! [`crate::task::is_isolated`]).
+
+use crate::{
+    cpu::percpu::PerCpu,
+    sync::{SpinMutex, SpinMutexGuard},
+    task::{Priority, Task},
+};
+use alloc::collections::vec_deque::VecDeque;
+use core::{
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use spin::Once;
+
+crate::parameters! {
+    /// Scheduling policy: `priority` runs higher-priority tasks first, and `rr` runs every task in turn.
+    "sched": str = "priority",
+}
+
+/// Maximum time a task may run before being preempted, at [`Priority::Normal`] (or under round-robin scheduling).
+pub const TIME_SLICE: Duration = Duration::from_millis(15);
+
+/// Time a task may be queued behind higher-priority tasks before it's run regardless.
+pub const STARVATION_LIMIT: Duration = Duration::from_millis(250);
+
+/// Interval between a busy hardware thread's attempts to steal tasks from the busiest hardware thread.
+pub const BALANCE_INTERVAL: Duration = Duration::from_millis(60);
+
+/// How the scheduler picks the next task to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// Every task runs in turn, for the same time slice, regardless of its priority.
+    RoundRobin,
+    /// Higher-priority tasks run first, for longer time slices.
+    Priority,
+}
+
+static POLICY: Once<Policy> = Once::new();
+
+impl Policy {
+    /// Policy requested on the kernel command line.
+    pub fn current() -> Self {
+        *POLICY.call_once(|| match crate::params::get::<&str>("sched") {
+            "rr" => Self::RoundRobin,
+            "priority" => Self::Priority,
+
+            policy => {
+                warn!("Unrecognized scheduling policy {policy:?}; using priority scheduling.");
+                Self::Priority
+            }
+        })
+    }
+
+    /// Maximum time a task of `priority` may run before being preempted.
+    pub fn time_slice(self, priority: Priority) -> Duration {
+        match (self, priority) {
+            (Self::RoundRobin, _) | (Self::Priority, Priority::Normal) => TIME_SLICE,
+            (Self::Priority, Priority::Idle) => TIME_SLICE / 4,
+            (Self::Priority, Priority::Low) => TIME_SLICE / 2,
+            (Self::Priority, Priority::High) => TIME_SLICE * 3 / 2,
+            (Self::Priority, Priority::Critical) => TIME_SLICE * 2,
+        }
+    }
+}
+
+/// A ready task, and when it was queued (in nanoseconds since the system clock started).
+struct Queued {
+    task: Task,
+    queued_ns: u64,
+}
+
+/// Tasks which are ready to run.
+pub struct RunQueue {
+    /// Queue of each priority, indexed by priority; under round-robin scheduling, only [`Priority::Normal`]'s is used.
+    queues: [VecDeque<Queued>; Priority::COUNT],
+}
+
+impl RunQueue {
+    pub const fn new() -> Self {
+        Self {
+            queues: [const { VecDeque::new() }; Priority::COUNT],
+        }
+    }
+
+    fn queue_index(task: &Task) -> usize {
+        let priority = match Policy::current() {
+            Policy::RoundRobin => Priority::Normal,
+            Policy::Priority => task.effective_priority(),
+        };
+
+        usize::from(u8::from(priority))
+    }
+
+    /// Queues `task` behind every other task of its (effective) priority.
+    pub fn push_back(&mut self, task: Task) {
+        let index = Self::queue_index(&task);
+
+        self.queues[index].push_back(Queued {
+            task,
+            queued_ns: crate::time::now_ns(),
+        });
+    }
+
+    /// Takes the next task for the hardware thread `hwthread_id` to run: the longest-queued of any which are
+    /// starving, or otherwise the first of the highest priority. Tasks whose affinity excludes the hardware thread
+    /// are left for others.
+    pub fn pop_front(&mut self, hwthread_id: u32) -> Option<Task> {
+        let now_ns = crate::time::now_ns();
+        let starvation_limit_ns = u64::try_from(STARVATION_LIMIT.as_nanos()).unwrap();
+
+        // The first runnable task of each queue, which is the longest-queued of its priority.
+        let runnable = |queue: &VecDeque<Queued>| {
+            queue
+                .iter()
+                .position(|queued| queued.task.affinity().allows(hwthread_id))
+                .map(|position| (position, queue[position].queued_ns))
+        };
+
+        let starving = self
+            .queues
+            .iter()
+            .enumerate()
+            .filter_map(|(index, queue)| Some((index, runnable(queue)?)))
+            .filter(|(_, (_, queued_ns))| now_ns.saturating_sub(*queued_ns) >= starvation_limit_ns)
+            .min_by_key(|(_, (_, queued_ns))| *queued_ns);
+
+        let (index, (position, _)) = starving.or_else(|| {
+            self.queues
+                .iter()
+                .enumerate()
+                .rev()
+                .find_map(|(index, queue)| Some((index, runnable(queue)?)))
+        })?;
+
+        self.queues[index]
+            .remove(position)
+            .map(|queued| queued.task)
+    }
+
+    /// Number of queued tasks.
+    pub fn len(&self) -> usize {
+        self.queues.iter().map(VecDeque::len).sum()
+    }
+
+    /// Whether no tasks are queued.
+    pub fn is_empty(&self) -> bool {
+        self.queues.iter().all(VecDeque::is_empty)
+    }
+
+    /// Number of queued tasks the hardware thread `hwthread_id` may run.
+    pub fn runnable_len(&self, hwthread_id: u32) -> usize {
+        self.iter()
+            .filter(|task| task.affinity().allows(hwthread_id))
+            .count()
+    }
+
+    /// Iterates the queued tasks, from the highest priority to the lowest.
+    pub fn iter(&self) -> impl Iterator<Item = &Task> {
+        self.queues
+            .iter()
+            .rev()
+            .flat_map(|queue| queue.iter().map(|queued| &queued.task))
+    }
+}
+
+/// A hardware thread's [`RunQueue`], along with its length, which other hardware threads read to balance load without
+/// taking the lock.
+pub struct LocalRunQueue {
+    queue: SpinMutex<RunQueue>,
+    len: AtomicUsize,
+}
+
+impl LocalRunQueue {
+    pub const fn new() -> Self {
+        Self {
+            queue: SpinMutex::new(RunQueue::new()),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    #[track_caller]
+    pub fn lock(&self) -> RunQueueGuard<'_> {
+        RunQueueGuard {
+            queue: self.queue.lock(),
+            len: &self.len,
+        }
+    }
+
+    #[track_caller]
+    pub fn try_lock(&self) -> Option<RunQueueGuard<'_>> {
+        Some(RunQueueGuard {
+            queue: self.queue.try_lock()?,
+            len: &self.len,
+        })
+    }
+
+    /// Number of queued tasks, as of when the queue was last unlocked.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Whether no tasks were queued, as of when the queue was last unlocked.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Locked [`LocalRunQueue`], which updates the queue's length as it's unlocked.
+pub struct RunQueueGuard<'a> {
+    queue: SpinMutexGuard<'a, RunQueue>,
+    len: &'a AtomicUsize,
+}
+
+impl Deref for RunQueueGuard<'_> {
+    type Target = RunQueue;
+
+    fn deref(&self) -> &Self::Target {
+        &self.queue
+    }
+}
+
+impl DerefMut for RunQueueGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.queue
+    }
+}
+
+impl Drop for RunQueueGuard<'_> {
+    fn drop(&mut self) {
+        self.len.store(self.queue.len(), Ordering::Relaxed);
+    }
+}
+
+/// Load of a hardware thread: its queued tasks, and the one it's running (if any).
+fn load(block: &PerCpu) -> usize {
+    block.run_queue.len() + usize::from(block.is_busy.load(Ordering::Relaxed))
+}
+
+/// Hardware threads which are ready to schedule tasks; see [`crate::cpu::topology::register`].
+fn registered() -> impl Iterator<Item = &'static PerCpu> {
+    crate::cpu::percpu::blocks().filter(|block| block.location.get().is_some())
+}
+
+/// Picks the hardware thread `task` is queued on as it becomes ready: the least-loaded of those its affinity
+/// allows, preferring the one it last ran on, so its cache may still be warm.
+///
+/// # Remarks
+///
+/// Before any hardware thread has registered, tasks are queued on the current one, which runs them once it does.
+pub fn select_hwthread(task: &Task) -> &'static PerCpu {
+    let last_hwthread_id = task.hwthread_id();
+
+    registered()
+        .filter(|block| {
+            task.affinity()
+                .allows(block.hwthread_id.load(Ordering::Relaxed))
+        })
+        .min_by_key(|block| {
+            let is_last = Some(block.hwthread_id.load(Ordering::Relaxed)) == last_hwthread_id;
+            (load(block), !is_last)
+        })
+        .unwrap_or_else(PerCpu::current)
+}
+
+/// Takes a task which the current hardware thread (with `local_load` tasks) may run from the busiest other hardware
+/// thread with tasks queued.
+///
+/// An idle hardware thread takes any queued task. A busy one only takes a task if the busiest hardware thread has at
+/// least two more, so moving a task evens out the load, rather than just moving the imbalance.
+///
+/// # Remarks
+///
+/// The busiest hardware thread's queue is never waited on, as its own scheduler may be trying to steal from this
+/// one; if it's locked, nothing is stolen until the next attempt.
+pub fn steal(local_load: usize) -> Option<Task> {
+    let current = PerCpu::current();
+    let hwthread_id = current.hwthread_id.load(Ordering::Relaxed);
+
+    let (busiest, busiest_load) = registered()
+        .filter(|block| !core::ptr::eq(*block, current) && !block.run_queue.is_empty())
+        .map(|block| (block, load(block)))
+        .max_by_key(|(_, load)| *load)?;
+
+    if local_load > 0 && busiest_load < local_load + 2 {
+        return None;
+    }
+
+    let task = busiest.run_queue.try_lock()?.pop_front(hwthread_id)?;
+    trace!(
+        "Stole {:?} from hardware thread #{}.",
+        task.id(),
+        busiest.hwthread_id.load(Ordering::Relaxed)
+    );
+
+    Some(task)
+}
+
+crate::kernel_test! {
+    /// A local run queue's length is updated as it's unlocked.
+    fn local_run_queue_tracks_length() -> crate::tests::Outcome {
+        let stack = crate::task::kthread::Stack::new_box().map_err(|_| "failed to allocate a stack")?;
+        let top = libsys::Address::new(stack.top().addr().get()).unwrap();
+        // The task is never run, so its context is never used.
+        let task = Task::new_kernel(
+            alloc::string::String::from("run-queue-test"),
+            Priority::Normal,
+            (
+                crate::arch::x86_64::structures::idt::InterruptStackFrame::new_kernel(top, top),
+                crate::task::Registers::empty(),
+            ),
+            stack,
+        );
+
+        let run_queue = LocalRunQueue::new();
+
+        run_queue.lock().push_back(task);
+        if run_queue.len() != 1 {
+            return Err("queued task wasn't counted");
+        }
+
+        let task = run_queue.lock().pop_front(crate::cpu::get_id());
+        if task.is_none() || !run_queue.is_empty() {
+            return Err("taken task was still counted");
+        }
+
+        Ok(())
+    }
+}
+
+crate::kernel_test! {
+    /// Each priority's time slice is at least as long as those of the priorities below it.
+    fn time_slices_scale_with_priority() -> crate::tests::Outcome {
+        let priorities = [
+            Priority::Idle,
+            Priority::Low,
+            Priority::Normal,
+            Priority::High,
+            Priority::Critical,
+        ];
+
+        if priorities
+            .windows(2)
+            .any(|pair| Policy::Priority.time_slice(pair[0]) > Policy::Priority.time_slice(pair[1]))
+        {
+            return Err("a priority has a shorter time slice than a lower priority");
+        }
+
+        if Policy::RoundRobin.time_slice(Priority::Critical) != TIME_SLICE {
+            return Err("round-robin time slice depends on priority");
+        }
+
+        Ok(())
+    }
+}
@@ -1,28 +1,154 @@
 use crate::{
-    arch::x86_64::structures::idt::InterruptStackFrame,
-    cpu::local_state::LocalState,
+    arch::x86_64::structures::{idt::InterruptStackFrame, tss::TaskStateSegment},
+    cpu::{local_state::LocalState, percpu::PerCpu},
     mem::stack::Stack,
-    task::{Registers, Task},
+    sync::SpinMutex,
+    task::{
+        BALANCE_INTERVAL, CpuTime, Mode, Policy, Registers, RunQueue, State, TIME_SLICE, Task,
+        deadline::DeadlineClass,
+    },
+    util::CachePadded,
 };
-use alloc::{boxed::Box, collections::vec_deque::VecDeque};
+use alloc::{boxed::Box, collections::btree_map::BTreeMap};
 use core::{alloc::AllocError, time::Duration};
 use libsys::Address;
-use zerocopy::FromZeros;
 
-pub static PROCESSES: spin::Mutex<VecDeque<Task>> = spin::Mutex::new(VecDeque::new());
+/// Minimum time a task may run before being preempted, so timer interrupts can't starve it entirely.
+const MIN_PREEMPTION_WAIT: Duration = Duration::from_micros(100);
+
+/// Tasks which are blocked, and so must not be scheduled until they are woken.
+///
+/// # Remarks
+///
+/// This is taken by every hardware thread's scheduler, so it's padded to keep its lock word on its own cache line.
+static BLOCKED: CachePadded<SpinMutex<BTreeMap<uuid::Uuid, Task>>> =
+    CachePadded::new(SpinMutex::new(BTreeMap::new()));
+
+/// Moves a blocked task back into the scheduler queue.
+fn wake_task(id: uuid::Uuid) {
+    crate::interrupts::uninterruptable(|| {
+        let Some(mut task) = BLOCKED.lock().remove(&id) else {
+            warn!("Attempted to wake a task which is not blocked: {id:?}");
+            return;
+        };
+
+        trace!("Waking: {id:?}");
+        task.transition(State::Ready);
+        // Tasks which sleep are likely I/O-bound, so they're scheduled ahead of those which use their whole time slice.
+        task.set_boosted(true);
+
+        queue_ready(task);
+    });
+}
+
+/// Queues a ready task on the hardware thread it's best placed on; see [`crate::task::select_hwthread`].
+///
+/// # Remarks
+///
+/// This waits on the chosen hardware thread's queue, so it mustn't be called with another queue locked, or two hardware
+/// threads queueing tasks on each other could deadlock.
+fn queue_ready(task: Task) {
+    crate::task::select_hwthread(&task)
+        .run_queue
+        .lock()
+        .push_back(task);
+}
+
+/// Moves a task blocked by [`Scheduler::block_task`] back into the scheduler queue.
+pub fn unblock_task(id: uuid::Uuid) {
+    wake_task(id);
+}
+
+/// Passes every queued & blocked task to `func`, returning `false` if any of the queues are currently locked.
+///
+/// # Remarks
+///
+/// - This never waits on the queue locks, so it's safe to call from contexts (i.e. the debug shell) which may have
+///   interrupted their holder.
+/// - Each hardware thread's queue is visited in turn, so a task which migrates meanwhile may be missed, or passed
+///   twice.
+pub fn try_for_each_queued(mut func: impl FnMut(&Task)) -> bool {
+    crate::interrupts::uninterruptable(|| {
+        for block in crate::cpu::percpu::blocks() {
+            let Some(run_queue) = block.run_queue.try_lock() else {
+                return false;
+            };
+
+            run_queue.iter().for_each(&mut func);
+        }
+
+        let Some(blocked) = BLOCKED.try_lock() else {
+            return false;
+        };
+
+        blocked.values().for_each(&mut func);
+
+        true
+    })
+}
+
+/// Makes a newly-created task available to be scheduled.
+pub fn enqueue_task(mut task: Task) {
+    task.transition(State::Ready);
+
+    // The creating task may have observed the local generator's recent outputs, so they mustn't predict those the new
+    // task draws (as after a fork).
+    crate::rand::prng::mark_local_stale();
+
+    crate::interrupts::uninterruptable(|| queue_ready(task));
+}
+
+/// Queues `task` back on the local queue as it's switched out, or returns it if its affinity no longer allows this
+/// hardware thread, to be queued elsewhere once the local queue is unlocked (see [`queue_ready`]).
+fn requeue(processes: &mut RunQueue, task: Task) -> Option<Task> {
+    if task.affinity().allows(crate::cpu::get_id()) {
+        processes.push_back(task);
+
+        None
+    } else {
+        Some(task)
+    }
+}
+
+/// Mode `task` runs in as it's resumed (by being switched in, or returning from a system call); the idle task is
+/// accounted as the kernel.
+fn resumed_mode(task: Option<&Task>) -> Mode {
+    match task {
+        Some(task) if !task.is_kernel() => Mode::User,
+        _ => Mode::Kernel,
+    }
+}
 
 pub struct Scheduler {
     enabled: bool,
     idle_stack: Box<Stack<0x1000>>,
     task: Option<Task>,
+    /// When the active task was switched in (in nanoseconds since the system clock started).
+    switched_in_ns: u64,
+    /// When the active task's CPU time was last accounted, and the mode it has been running in since.
+    accounted_ns: u64,
+    mode: Mode,
+    /// When this hardware thread last tried to steal a task to balance load (in nanoseconds since the system clock
+    /// started).
+    balanced_ns: u64,
+    /// Task which most recently exited on this hardware thread, which can't be reaped until the hardware thread has
+    /// left its stack (i.e. returned from the interrupt it exited in).
+    exited: Option<Task>,
+    deadline_class: DeadlineClass,
 }
 
 impl Scheduler {
     pub fn new() -> Result<Self, AllocError> {
         Ok(Self {
             enabled: false,
-            idle_stack: Stack::new_box_zeroed().map_err(|_| AllocError)?,
+            idle_stack: Stack::new_box()?,
             task: None,
+            switched_in_ns: 0,
+            accounted_ns: 0,
+            mode: Mode::Kernel,
+            balanced_ns: 0,
+            exited: None,
+            deadline_class: DeadlineClass::new(),
         })
     }
 
@@ -49,10 +175,85 @@ impl Scheduler {
         self.task.as_mut()
     }
 
+    /// Time the active task has spent running, up to now.
+    pub fn cpu_time(&mut self) -> Option<CpuTime> {
+        self.charge(self.mode);
+
+        self.task.as_ref().map(Task::cpu_time)
+    }
+
+    /// Accounts the active task's entry into a system call, charging the time since it was last accounted as user
+    /// time.
+    pub fn enter_syscall(&mut self) {
+        self.charge(Mode::Kernel);
+    }
+
+    /// Accounts the active task's return from a system call, charging the time since it was last accounted as kernel
+    /// time.
+    ///
+    /// # Remarks
+    ///
+    /// The call may have switched in another task, in which case the time since it was switched in is charged to
+    /// the mode it was switched into.
+    pub fn exit_syscall(&mut self) {
+        self.charge(resumed_mode(self.task.as_ref()));
+    }
+
+    /// Charges the time since the active task was last accounted to the mode it was running in, and accounts it as
+    /// running in `mode` from now.
+    fn charge(&mut self, mode: Mode) {
+        let now_ns = crate::time::now_ns();
+        if let Some(task) = self.task.as_mut() {
+            task.cpu_time.charge(
+                self.mode,
+                Duration::from_nanos(now_ns.saturating_sub(self.accounted_ns)),
+            );
+        }
+
+        self.accounted_ns = now_ns;
+        self.mode = mode;
+    }
+
+    /// Panics if any canary on this hardware thread has been clobbered: the active task's (see
+    /// [`Task::check_canaries`]), or those at the bases of the idle stack and the privilege & interrupt stacks.
+    pub fn check_canaries(&self) {
+        if let Some(task) = self.task.as_ref() {
+            task.check_canaries();
+        }
+
+        if !self.idle_stack.canary_intact() {
+            panic!(
+                "idle stack canary of hardware thread {} was clobbered",
+                crate::cpu::get_id()
+            );
+        }
+
+        if !TaskStateSegment::local_stacks_intact() {
+            panic!(
+                "interrupt stack canary of hardware thread {} was clobbered, running task: {:?}",
+                crate::cpu::get_id(),
+                self.task
+                    .as_ref()
+                    .map(|task| (task.id(), task.name().unwrap_or("unnamed")))
+            );
+        }
+    }
+
+    /// Periodic kernel work, whose kernel threads are scheduled ahead of all queued tasks.
+    pub fn deadline_class_mut(&mut self) -> &mut DeadlineClass {
+        &mut self.deadline_class
+    }
+
     pub fn interrupt_task(&mut self, state: &mut InterruptStackFrame, regs: &mut Registers) {
         debug_assert!(!crate::interrupts::is_enabled());
 
-        let mut processes = PROCESSES.lock();
+        // The tick is an interrupt, so this hardware thread can't be within an RCU read-side critical section.
+        crate::sync::rcu::quiescent();
+
+        self.check_canaries();
+
+        let mut processes = PerCpu::current().run_queue.lock();
+        let mut migrating = None;
 
         // Move the current task, if any, back into the scheduler queue.
         if let Some(mut process) = self.task.take() {
@@ -60,57 +261,250 @@ impl Scheduler {
 
             process.context.0 = *state;
             process.context.1 = *regs;
+            process.extended_state.save();
+            process.transition(State::Ready);
+
+            // Periodic work which was preempted is returned to the deadline class, and throttled if it ran for its
+            // whole budget.
+            let ran_for = self.account(&mut process);
+            if let Some(mut process) = self.deadline_class.reclaim(process, ran_for, false) {
+                // Ticks may come early, for periodic work or timers, so only tasks which ran for their whole time
+                // slice are considered CPU-bound.
+                if ran_for >= Policy::current().time_slice(process.effective_priority()) {
+                    process.set_boosted(false);
+                }
+
+                migrating = requeue(&mut processes, process);
+            }
+        }
+
+        let now_ns = crate::time::now_ns();
+        if now_ns.saturating_sub(self.balanced_ns)
+            >= u64::try_from(BALANCE_INTERVAL.as_nanos()).unwrap()
+        {
+            self.balanced_ns = now_ns;
 
-            processes.push_back(process);
+            if let Some(stolen) = crate::task::steal(processes.len()) {
+                processes.push_back(stolen);
+            }
         }
 
         self.next_task(&mut processes, state, regs);
+
+        drop(processes);
+        if let Some(migrating) = migrating {
+            queue_ready(migrating);
+        }
     }
 
     /// Attempts to schedule the next task in the local task queue.
     pub fn yield_task(&mut self, isf: &mut InterruptStackFrame, regs: &mut Registers) {
         debug_assert!(!crate::interrupts::is_enabled());
 
-        let mut processes = PROCESSES.lock();
+        self.check_canaries();
+
+        let mut processes = PerCpu::current().run_queue.lock();
 
         let mut process = self.task.take().expect("no active task in scheduler");
         trace!("Yielding: {:?}", process.id());
+        let ran_for = self.account(&mut process);
+
+        process.context.0 = *isf;
+        process.context.1 = *regs;
+        process.extended_state.save();
+        process.transition(State::Ready);
+
+        // Periodic work yields once it completes an instance, which parks it until its next release.
+        let migrating = self
+            .deadline_class
+            .reclaim(process, ran_for, true)
+            .and_then(|process| requeue(&mut processes, process));
+
+        self.next_task(&mut processes, isf, regs);
+
+        drop(processes);
+        if let Some(migrating) = migrating {
+            queue_ready(migrating);
+        }
+    }
+
+    /// Blocks the active task for `duration`, and schedules the next task in its place.
+    pub fn sleep_task(
+        &mut self,
+        duration: Duration,
+        isf: &mut InterruptStackFrame,
+        regs: &mut Registers,
+    ) {
+        debug_assert!(!crate::interrupts::is_enabled());
+
+        self.check_canaries();
+
+        let mut process = self.task.take().expect("no active task in scheduler");
+        let id = process.id();
+        trace!("Sleeping: {id:?} for {duration:?}");
+        self.account(&mut process);
+
+        process.context.0 = *isf;
+        process.context.1 = *regs;
+        process.extended_state.save();
+        process.transition(State::Blocked);
+
+        // The task must be blocked before the timer is scheduled, or it could be woken before it sleeps.
+        BLOCKED.lock().insert(id, process);
+
+        #[cfg(feature = "time_warp")]
+        crate::time::timers::schedule_after_in(
+            crate::time::warp::group_of(id),
+            duration,
+            move || wake_task(id),
+        );
+        #[cfg(not(feature = "time_warp"))]
+        crate::time::timers::schedule_after(duration, move || wake_task(id));
+
+        let mut processes = PerCpu::current().run_queue.lock();
+        self.next_task(&mut processes, isf, regs);
+    }
+
+    /// Blocks the active task until it is passed to [`unblock_task`], and schedules the next task in its place.
+    pub fn block_task(&mut self, isf: &mut InterruptStackFrame, regs: &mut Registers) {
+        debug_assert!(!crate::interrupts::is_enabled());
+
+        self.check_canaries();
+
+        let mut process = self.task.take().expect("no active task in scheduler");
+        trace!("Blocking: {:?}", process.id());
+        self.account(&mut process);
 
         process.context.0 = *isf;
         process.context.1 = *regs;
+        process.extended_state.save();
+        process.transition(State::Blocked);
 
-        processes.push_back(process);
+        BLOCKED.lock().insert(process.id(), process);
 
+        let mut processes = PerCpu::current().run_queue.lock();
         self.next_task(&mut processes, isf, regs);
     }
 
-    pub fn kill_task(&mut self, isf: &mut InterruptStackFrame, regs: &mut Registers) {
+    /// Exits the active task with `code`, and schedules the next task in its place.
+    ///
+    /// # Remarks
+    ///
+    /// The task is reaped by the next scheduling decision on this hardware thread, as this may be running on its
+    /// stack (for a kernel thread), or in its address space.
+    pub fn kill_task(&mut self, code: usize, isf: &mut InterruptStackFrame, regs: &mut Registers) {
         debug_assert!(!crate::interrupts::is_enabled());
 
-        // TODO add process to reap queue to reclaim address space memory
-        let process = self.task.take().expect("no active task in scheduler");
-        trace!("Exiting: {:?}", process.id());
+        self.check_canaries();
 
-        let mut processes = PROCESSES.lock();
+        let mut process = self.task.take().expect("no active task in scheduler");
+        trace!("Exiting: {:?} (code {code:#X})", process.id());
+        self.account(&mut process);
+        process.transition(State::Exiting);
+
+        #[cfg(debug_assertions)]
+        crate::task::resources::on_exit(process.id());
+
+        // The exit status has to be recorded while the task still has a parent.
+        crate::task::lifecycle::on_exit(&process, code);
+        crate::task::init::on_exit(process.id());
+        crate::task::timers::on_exit(process.id());
+        crate::task::signals::on_exit(process.id());
+        crate::task::handles::on_exit(process.id());
+        crate::fs::fd::on_exit(process.id());
+        #[cfg(feature = "time_warp")]
+        crate::time::warp::on_exit(process.id());
+
+        let mut processes = PerCpu::current().run_queue.lock();
         self.next_task(&mut processes, isf, regs);
+
+        self.exited = Some(process);
+    }
+
+    /// Charges the time since the active task was last accounted to its CPU time, as it's switched out, returning the
+    /// time since it was switched in.
+    fn account(&self, process: &mut Task) -> Duration {
+        let now_ns = crate::time::now_ns();
+        process.cpu_time.charge(
+            self.mode,
+            Duration::from_nanos(now_ns.saturating_sub(self.accounted_ns)),
+        );
+
+        Duration::from_nanos(now_ns.saturating_sub(self.switched_in_ns))
     }
 
     fn next_task(
         &mut self,
-        processes: &mut VecDeque<Task>,
+        processes: &mut RunQueue,
         isf: &mut InterruptStackFrame,
         regs: &mut Registers,
     ) {
-        // Pop a new task from the task queue, or simply switch in the idle task.
-        if let Some(next_process) = processes.pop_front() {
+        // Any previously exited task exited in an earlier interrupt, which has since returned, so it's no longer in use.
+        if let Some(exited) = self.exited.take() {
+            crate::task::workqueue::defer(move || crate::task::lifecycle::reap(exited));
+        }
+
+        // Released periodic work runs ahead of every queued task, for no longer than its remaining budget. Otherwise,
+        // pop a new task from the local queue (unless it's better left for an idle core), or simply switch in the idle
+        // task.
+        let hwthread_id = crate::cpu::get_id();
+        let (next_process, budget) = match self.deadline_class.pick() {
+            Some((task, budget)) => (Some(task), Some(budget)),
+            None => {
+                let runnable = processes.runnable_len(hwthread_id);
+                let next_process = if runnable == 0 {
+                    // Rather than idle, take a task queued on the busiest hardware thread.
+                    crate::task::steal(processes.len())
+                } else if crate::cpu::topology::should_defer(runnable) {
+                    None
+                } else {
+                    processes.pop_front(hwthread_id)
+                };
+
+                (next_process, None)
+            }
+        };
+        crate::cpu::topology::set_busy(next_process.is_some());
+
+        let time_slice = budget.unwrap_or_else(|| {
+            next_process.as_ref().map_or(TIME_SLICE, |next_process| {
+                Policy::current().time_slice(next_process.effective_priority())
+            })
+        });
+        self.switched_in_ns = crate::time::now_ns();
+        self.accounted_ns = self.switched_in_ns;
+        self.mode = resumed_mode(next_process.as_ref());
+
+        if let Some(mut next_process) = next_process {
+            // The task's context is about to be restored, so it has to be checked before it's trusted.
+            next_process.check_canaries();
+            next_process.transition(State::Running);
+            next_process.hwthread_id = Some(hwthread_id);
+            crate::task::publish_running(Some(&next_process), self.switched_in_ns, self.mode);
+
             *isf = next_process.context.0;
             *regs = next_process.context.1;
-
-            if !next_process.address_space.is_current() {
-                // Safety: New task requires its own address space.
-                unsafe {
-                    next_process.address_space.swap_into();
+            next_process.extended_state.restore();
+
+            match next_process.process() {
+                Some(process) if !process.is_current() => {
+                    // Safety: New task requires its process's address space.
+                    unsafe {
+                        process.swap_into();
+                    }
                 }
+
+                Some(_) => {}
+
+                // Safety: Kernel threads only use the kernel's half of the address space.
+                None => unsafe { crate::mem::swap_into_kernel() },
+            }
+
+            match next_process.process() {
+                Some(process) => process.with_io_bitmap(|io_bitmap| {
+                    TaskStateSegment::load_io_bitmap(io_bitmap.as_deref());
+                }),
+                None => TaskStateSegment::load_io_bitmap(None),
             }
 
             trace!("Switched task: {:?}", next_process.id());
@@ -132,13 +526,29 @@ impl Scheduler {
 
             *regs = Registers::empty();
 
+            // Safety: The idle task only uses the kernel's half of the address space.
+            unsafe {
+                crate::mem::swap_into_kernel();
+            }
+
+            crate::task::publish_running(None, self.switched_in_ns, self.mode);
+
             trace!("Switched idle task.");
         }
 
+        // Preempt early if periodic work will be released, or a timer will expire, before the time slice is up.
+        let next_timer = LocalState::with_timers(|timers| timers.next_expiry_ns())
+            .map(|expiry_ns| Duration::from_nanos(expiry_ns.saturating_sub(crate::time::now_ns())));
+        let preemption_wait = [self.deadline_class.next_release(), next_timer]
+            .into_iter()
+            .flatten()
+            .fold(time_slice, Duration::min)
+            .max(MIN_PREEMPTION_WAIT);
+
         // TODO have some kind of queue of preemption waits, to ensure we select the shortest one.
         // Safety: Just having switched tasks, no preemption wait should supercede this one.
         unsafe {
-            LocalState::set_preemption_wait(Duration::from_millis(15));
+            LocalState::set_preemption_wait(preemption_wait);
         }
     }
 }
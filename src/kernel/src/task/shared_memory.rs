@@ -0,0 +1,195 @@
+//! Shared memory objects: sets of frames which tasks map into their own address spaces, to share memory without
+//! copying it through the kernel.
+//!
+//! A task creates an object, receiving a read-write handle to it. From that it can derive handles granting fewer
+//! rights (see [`crate::task::handles`]), and pass them to other tasks over IPC (see [`crate::task::ipc`]); each task
+//! maps the object with the permissions its handle grants: [`Rights::READ`] to map it at all, and [`Rights::WRITE`]
+//! or [`Rights::EXECUTE`] to map it writable or executable. Every handle and mapping holds a reference to the object,
+//! so its frames are freed only once each handle has been closed, and each mapping unmapped (or its address space
+//! freed).
+//!
+//! # Remarks
+//!
+//! Mappings are only unmapped from the translations of the hardware thread which unmaps them, so other hardware
+//! threads running the same process may keep using them until they next switch address space.
+
+use crate::{
+    mem::{HigherHalfDirectMap, pmm::PhysicalMemoryManager},
+    task::{
+        DEFAULT_USERSPACE_SIZE, MmapPermissions,
+        handles::{self, Handle, Object, Rights},
+    },
+};
+use alloc::{sync::Arc, vec::Vec};
+use libsys::{Address, Frame, Page, page_size};
+
+/// Maximum number of pages in a single object.
+pub const MAX_PAGES: usize = 0x1000;
+
+/// Rights granted by a handle to a newly created object.
+const OBJECT_RIGHTS: Rights = Rights::READ
+    .union(Rights::WRITE)
+    .union(Rights::TRANSFER)
+    .union(Rights::DUPLICATE);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no active task")]
+    NoActiveTask,
+
+    #[error("objects are between one and {MAX_PAGES} pages long (not {0})")]
+    InvalidSize(usize),
+
+    #[error("failed to allocate the object's frames: {0}")]
+    OutOfMemory(crate::mem::pmm::Error),
+
+    #[error("address is not a free, page-aligned userspace range: {0:#X}")]
+    InvalidAddress(usize),
+
+    #[error(transparent)]
+    Handles(#[from] handles::Error),
+
+    #[error("failed to map the object: {0}")]
+    AddressSpace(crate::task::address_space::Error),
+}
+
+/// Frames of a shared memory object, freed once the last reference to it is dropped.
+pub struct SharedMemory {
+    frames: Vec<Address<Frame>>,
+}
+
+impl SharedMemory {
+    /// Allocates an object of `page_count` zeroed pages.
+    fn new(page_count: usize) -> Result<Self, Error> {
+        if !(1..=MAX_PAGES).contains(&page_count) {
+            return Err(Error::InvalidSize(page_count));
+        }
+
+        // Frames are added as they're allocated, so they're freed (when the object is dropped) if any can't be.
+        let mut memory = Self {
+            frames: Vec::with_capacity(page_count),
+        };
+        for _ in 0..page_count {
+            let frame = PhysicalMemoryManager::next_frame().map_err(Error::OutOfMemory)?;
+            memory.frames.push(frame);
+
+            // Safety: The frame was just allocated, so nothing else uses it, and it's a whole page long.
+            unsafe {
+                HigherHalfDirectMap::frame_to_page(frame)
+                    .as_ptr()
+                    .write_bytes(0, page_size());
+            }
+        }
+
+        Ok(memory)
+    }
+
+    #[inline]
+    pub fn frames(&self) -> &[Address<Frame>] {
+        &self.frames
+    }
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        for frame in &self.frames {
+            if let Err(error) = PhysicalMemoryManager::free_frame(*frame) {
+                warn!("Failed to free a frame of a shared memory object: {error}");
+            }
+        }
+    }
+}
+
+impl core::fmt::Debug for SharedMemory {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SharedMemory")
+            .field("Pages", &self.frames.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Rights a handle must grant to map its object with `permissions`.
+fn required_rights(permissions: MmapPermissions) -> Rights {
+    match permissions {
+        MmapPermissions::ReadOnly => Rights::READ,
+        MmapPermissions::ReadWrite => Rights::READ | Rights::WRITE,
+        MmapPermissions::ReadExecute => Rights::READ | Rights::EXECUTE,
+    }
+}
+
+/// Creates an object of `page_count` zeroed pages, returning the active task's read-write handle to it.
+pub fn create(page_count: usize) -> Result<Handle, Error> {
+    let memory = SharedMemory::new(page_count)?;
+
+    handles::insert(Object::Memory(Arc::new(memory)), OBJECT_RIGHTS).map_err(Error::from)
+}
+
+/// Maps the object of `handle` into the active task's address space at `address`, with `permissions`.
+pub fn map(handle: Handle, address: usize, permissions: MmapPermissions) -> Result<(), Error> {
+    let Object::Memory(memory) = handles::get(handle, required_rights(permissions))? else {
+        return Err(handles::Error::WrongObject(handle).into());
+    };
+
+    let page = Some(address)
+        .filter(|address| {
+            address
+                .checked_add(memory.frames().len() * page_size())
+                .is_some_and(|end| end <= DEFAULT_USERSPACE_SIZE.get())
+        })
+        .and_then(Address::<Page>::new)
+        .ok_or(Error::InvalidAddress(address))?;
+
+    with_address_space(|address_space| {
+        address_space
+            .map_object(page, memory, permissions)
+            .map_err(|error| match error {
+                crate::task::address_space::Error::InvalidAddress => Error::InvalidAddress(address),
+                error => Error::AddressSpace(error),
+            })
+    })
+}
+
+/// Unmaps the object mapped at `address` in the active task's address space.
+pub fn unmap(address: usize) -> Result<(), Error> {
+    let page = Address::<Page>::new(address).ok_or(Error::InvalidAddress(address))?;
+
+    // The object's reference is dropped once the address space is unlocked, in case it's the last.
+    let _memory = with_address_space(|address_space| {
+        address_space
+            .unmap_object(page)
+            .map_err(|error| match error {
+                crate::task::address_space::Error::NotMapped(_) => Error::InvalidAddress(address),
+                error => Error::AddressSpace(error),
+            })
+    })?;
+
+    Ok(())
+}
+
+fn with_address_space<T>(
+    func: impl FnOnce(&mut crate::task::AddressSpace) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let process = crate::cpu::local_state::LocalState::with_scheduler(|scheduler| {
+        scheduler
+            .process()
+            .and_then(crate::task::Task::process)
+            .cloned()
+    })
+    .ok_or(Error::NoActiveTask)?;
+
+    process.with_image(|image| func(image.address_space_mut()))
+}
+
+crate::kernel_test! {
+    /// Mapping an object requires a right for each permission it's mapped with.
+    fn requires_rights_for_permissions() -> crate::tests::Outcome {
+        if required_rights(MmapPermissions::ReadOnly) != Rights::READ
+            || required_rights(MmapPermissions::ReadWrite).contains(Rights::EXECUTE)
+            || !required_rights(MmapPermissions::ReadExecute).contains(Rights::EXECUTE)
+        {
+            return Err("mapping permissions require the wrong rights");
+        }
+
+        Ok(())
+    }
+}
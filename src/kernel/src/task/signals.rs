@@ -0,0 +1,416 @@
+//! Asynchronous events delivered to userspace tasks, modelled on POSIX signals.
+//!
+//! The kernel posts events (a fault, a message from another task, or a timer expiration) to a task, which are held
+//! pending until the task next returns to user mode. If the task has registered a handler for the event, it's
+//! delivered then: a [`Frame`] saving the interrupted context is pushed onto the task's stack (below its red zone),
+//! and the task enters the handler as though it was called as
+//! `extern "C" fn(event: usize, info: usize, frame: *mut Frame)`, returning into the trampoline registered with it.
+//! The trampoline issues [`Syscall::SignalReturn`], which restores the saved context.
+//!
+//! Events are delivered one at a time; any posted while a handler runs are held until it returns. Events a task has
+//! no handler for are discarded, except faults, which kill the task as they would otherwise. Blocked tasks aren't
+//! woken by an event, which is delivered once they next run.
+//!
+//! # Remarks
+//!
+//! Only the general-purpose registers are saved in the frame, so handlers must preserve any floating-point or vector
+//! state they use.
+//!
+//! [`Syscall::SignalReturn`]: crate::interrupts::syscall::Syscall::SignalReturn
+
+use crate::{
+    arch::x86_64::{registers::RFlags, structures::idt::InterruptStackFrame},
+    cpu::local_state::LocalState,
+    mem::user::{read_user, write_user},
+    sync::SpinMutex,
+    task::{DEFAULT_USERSPACE_SIZE, Registers, Task},
+};
+use alloc::collections::btree_map::BTreeMap;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use libsys::{Address, Virtual};
+use uuid::Uuid;
+
+/// Bytes below the stack pointer which the System V ABI lets functions use without moving it, and so which a frame
+/// must be pushed below.
+const RED_ZONE: usize = 128;
+
+/// Flags a handler may change in its frame before returning; the rest are the kernel's to decide.
+const USER_FLAGS: RFlags = RFlags::CARRY_FLAG
+    .union(RFlags::PARITY_FLAG)
+    .union(RFlags::AUXILIARY_CARRY_FLAG)
+    .union(RFlags::ZERO_FLAG)
+    .union(RFlags::SIGN_FLAG)
+    .union(RFlags::DIRECTION_FLAG)
+    .union(RFlags::OVERFLOW_FLAG)
+    .union(RFlags::ALIGNMENT_CHECK);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no active task")]
+    NoActiveTask,
+
+    #[error("handler or trampoline is not a userspace address: {0:#X}")]
+    InvalidAddress(usize),
+
+    #[error("task is not running an event handler")]
+    NotInHandler,
+
+    #[error("failed to access the task's stack: {0}")]
+    User(#[from] crate::mem::user::Error),
+}
+
+/// Event posted to a task, passed to its handler as `event`.
+#[repr(usize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive)]
+pub enum Event {
+    /// The task raised an exception it would otherwise be killed for; `info` is the faulting address (for page
+    /// faults), or instruction.
+    Fault = 0,
+    /// Another task posted a message; `info` is the value it posted.
+    Message = 1,
+    /// One of the task's timers expired; `info` is its handle (see [`crate::task::timers`]).
+    Timer = 2,
+}
+
+bitflags! {
+    /// Set of events, i.e. those a handler is registered for.
+    #[repr(transparent)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct Events: usize {
+        const FAULT = 1 << 0;
+        const MESSAGE = 1 << 1;
+        const TIMER = 1 << 2;
+    }
+}
+
+impl Event {
+    const ALL: [Self; 3] = [Self::Fault, Self::Message, Self::Timer];
+
+    const fn as_events(self) -> Events {
+        match self {
+            Self::Fault => Events::FAULT,
+            Self::Message => Events::MESSAGE,
+            Self::Timer => Events::TIMER,
+        }
+    }
+}
+
+/// Context of a task before it entered an event handler, pushed onto its stack.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable)]
+pub struct Frame {
+    pub event: usize,
+    pub info: usize,
+    pub instruction_pointer: usize,
+    pub stack_pointer: usize,
+    pub cpu_flags: u64,
+    pub registers: Registers,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Handler {
+    entry: Address<Virtual>,
+    /// Address the handler returns into, which issues [`Syscall::SignalReturn`].
+    ///
+    /// [`Syscall::SignalReturn`]: crate::interrupts::syscall::Syscall::SignalReturn
+    trampoline: usize,
+    events: Events,
+}
+
+#[derive(Default)]
+struct TaskSignals {
+    handler: Option<Handler>,
+    pending: Events,
+    /// Info of the most recent post of each pending event.
+    info: [usize; Event::ALL.len()],
+    /// Whether the task is running its handler.
+    in_handler: bool,
+}
+
+impl TaskSignals {
+    /// Takes the lowest pending event, if the task can be given one.
+    fn take_pending(&mut self) -> Option<(Handler, Event, usize)> {
+        let handler = self.handler.filter(|_| !self.in_handler)?;
+        let event = Event::ALL
+            .into_iter()
+            .find(|event| self.pending.contains(event.as_events()))?;
+
+        self.pending.remove(event.as_events());
+        PENDING.fetch_sub(1, Ordering::Relaxed);
+        self.in_handler = true;
+
+        Some((handler, event, self.info[usize::from(event)]))
+    }
+}
+
+static TASK_SIGNALS: SpinMutex<BTreeMap<Uuid, TaskSignals>> = SpinMutex::new(BTreeMap::new());
+
+/// Number of events pending across every task, so returning to user mode needn't take the lock when there are none.
+static PENDING: AtomicUsize = AtomicUsize::new(0);
+
+fn active_task_id() -> Result<Uuid, Error> {
+    LocalState::with_scheduler(|scheduler| {
+        scheduler
+            .process()
+            .filter(|task| !task.is_kernel())
+            .map(Task::id)
+    })
+    .ok_or(Error::NoActiveTask)
+}
+
+fn user_address(address: usize) -> Result<Address<Virtual>, Error> {
+    Some(address)
+        .filter(|address| *address < DEFAULT_USERSPACE_SIZE.get())
+        .and_then(Address::new)
+        .ok_or(Error::InvalidAddress(address))
+}
+
+/// Registers `entry` as the active task's handler for `events`, returning into `trampoline`; a zero `entry` removes
+/// the handler, discarding any pending events.
+pub fn register(entry: usize, trampoline: usize, events: Events) -> Result<(), Error> {
+    let task_id = active_task_id()?;
+
+    let handler = if entry == 0 {
+        None
+    } else {
+        user_address(trampoline)?;
+
+        Some(Handler {
+            entry: user_address(entry)?,
+            trampoline,
+            events,
+        })
+    };
+
+    crate::interrupts::uninterruptable(|| {
+        let mut task_signals = TASK_SIGNALS.lock();
+        let task_signals = task_signals.entry(task_id).or_default();
+
+        // Events the task no longer handles won't be delivered.
+        let events = handler.map_or(Events::empty(), |handler| handler.events);
+        let discarded = task_signals.pending.difference(events);
+        task_signals.pending.remove(discarded);
+        PENDING.fetch_sub(discarded.iter().count(), Ordering::Relaxed);
+
+        task_signals.handler = handler;
+    });
+
+    Ok(())
+}
+
+/// Posts `event` (with `info`) to the task `task_id`, if it has a handler for it.
+///
+/// # Remarks
+///
+/// Posting an event which is already pending replaces its info, so a handler sees each event at most once per
+/// delivery.
+pub fn post(task_id: Uuid, event: Event, info: usize) {
+    crate::interrupts::uninterruptable(|| {
+        let mut task_signals = TASK_SIGNALS.lock();
+        let Some(task_signals) = task_signals.get_mut(&task_id).filter(|task_signals| {
+            task_signals
+                .handler
+                .is_some_and(|handler| handler.events.contains(event.as_events()))
+        }) else {
+            return;
+        };
+
+        if !task_signals.pending.contains(event.as_events()) {
+            task_signals.pending.insert(event.as_events());
+            PENDING.fetch_add(1, Ordering::Relaxed);
+        }
+        task_signals.info[usize::from(event)] = info;
+    });
+}
+
+/// Pushes a frame saving `isf` and `regs` onto the active task's stack, and redirects them into `handler`.
+fn enter_handler(
+    handler: Handler,
+    event: Event,
+    info: usize,
+    isf: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result<(), Error> {
+    let stack_pointer = isf.get_stack_pointer().get();
+    let frame_address = stack_pointer
+        .checked_sub(RED_ZONE + size_of::<Frame>())
+        .ok_or(Error::InvalidAddress(stack_pointer))?
+        & !0xF;
+    // The handler is entered as though it was called, so it finds the trampoline as its return address.
+    let return_address = frame_address
+        .checked_sub(size_of::<usize>())
+        .ok_or(Error::InvalidAddress(stack_pointer))?;
+
+    write_user(
+        frame_address,
+        &Frame {
+            event: usize::from(event),
+            info,
+            instruction_pointer: isf.get_instruction_pointer().get(),
+            stack_pointer,
+            cpu_flags: isf.get_cpu_flags().bits(),
+            registers: *regs,
+        },
+    )?;
+    write_user(return_address, &handler.trampoline)?;
+
+    *regs = Registers {
+        rdi: usize::from(event),
+        rsi: info,
+        rdx: frame_address,
+        ..*regs
+    };
+
+    // Safety: The handler was checked to be in user space when it was registered, and the stack was just written to,
+    //         so both are userspace addresses; the direction flag is cleared, as the ABI requires on function entry.
+    unsafe {
+        isf.set_instruction_pointer(handler.entry);
+        isf.set_stack_pointer(Address::new(return_address).unwrap());
+        isf.set_cpu_flags(isf.get_cpu_flags().difference(RFlags::DIRECTION_FLAG));
+    }
+
+    Ok(())
+}
+
+/// Delivers the active task's next pending event, if it has one and isn't already running its handler.
+///
+/// # Remarks
+///
+/// This must be called from an interrupt context, just before returning to user mode, as the active task may be
+/// killed in place (if its handler's frame can't be pushed).
+pub fn deliver_pending(isf: &mut InterruptStackFrame, regs: &mut Registers) {
+    if PENDING.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+
+    let Ok(task_id) = active_task_id() else {
+        return;
+    };
+
+    let Some((handler, event, info)) = crate::interrupts::uninterruptable(|| {
+        TASK_SIGNALS
+            .lock()
+            .get_mut(&task_id)
+            .and_then(TaskSignals::take_pending)
+    }) else {
+        return;
+    };
+
+    if let Err(error) = enter_handler(handler, event, info, isf, regs) {
+        warn!("Killing task {task_id:?}, as its {event:?} event couldn't be delivered: {error}");
+
+        LocalState::with_scheduler(|scheduler| {
+            scheduler.kill_task(crate::task::lifecycle::KILLED_CODE, isf, regs);
+        });
+    }
+}
+
+/// Delivers a fault at `address` to the active task straight away, returning `false` if it has no handler for
+/// faults, or faulted in its handler (in which case it should be killed).
+pub fn deliver_fault(address: usize, isf: &mut InterruptStackFrame, regs: &mut Registers) -> bool {
+    let Ok(task_id) = active_task_id() else {
+        return false;
+    };
+
+    let handler = crate::interrupts::uninterruptable(|| {
+        let mut task_signals = TASK_SIGNALS.lock();
+        let task_signals = task_signals.get_mut(&task_id)?;
+        let handler = task_signals
+            .handler
+            .filter(|handler| !task_signals.in_handler && handler.events.contains(Events::FAULT))?;
+
+        task_signals.in_handler = true;
+
+        Some(handler)
+    });
+
+    handler.is_some_and(|handler| {
+        enter_handler(handler, Event::Fault, address, isf, regs)
+            .inspect_err(|error| warn!("Fault couldn't be delivered to task {task_id:?}: {error}"))
+            .is_ok()
+    })
+}
+
+/// Returns the active task from its event handler, restoring the context saved in the frame at its stack pointer.
+///
+/// # Remarks
+///
+/// This must be called from the system call the task's trampoline issues, after the handler returned into it.
+pub fn restore(isf: &mut InterruptStackFrame, regs: &mut Registers) -> Result<(), Error> {
+    let task_id = active_task_id()?;
+
+    let frame = read_user::<Frame>(isf.get_stack_pointer().get())?;
+    let instruction_pointer = user_address(frame.instruction_pointer)?;
+    let stack_pointer = user_address(frame.stack_pointer)?;
+
+    crate::interrupts::uninterruptable(|| {
+        let mut task_signals = TASK_SIGNALS.lock();
+        let task_signals = task_signals
+            .get_mut(&task_id)
+            .filter(|task_signals| task_signals.in_handler)
+            .ok_or(Error::NotInHandler)?;
+
+        task_signals.in_handler = false;
+
+        Ok(())
+    })?;
+
+    *regs = frame.registers;
+
+    let cpu_flags = isf
+        .get_cpu_flags()
+        .difference(USER_FLAGS)
+        .union(RFlags::from_bits_truncate(frame.cpu_flags).intersection(USER_FLAGS));
+
+    // Safety: Both addresses were checked to be in user space, and the handler may only change the flags which
+    //         don't affect the kernel.
+    unsafe {
+        isf.set_instruction_pointer(instruction_pointer);
+        isf.set_stack_pointer(stack_pointer);
+        isf.set_cpu_flags(cpu_flags);
+    }
+
+    Ok(())
+}
+
+/// Removes the handler and pending events of the task `id`.
+pub fn on_exit(id: Uuid) {
+    let Some(task_signals) = TASK_SIGNALS.lock().remove(&id) else {
+        return;
+    };
+
+    PENDING.fetch_sub(task_signals.pending.iter().count(), Ordering::Relaxed);
+}
+
+crate::kernel_test! {
+    /// Events are delivered lowest first, one at a time, and not while the handler is running.
+    fn delivers_one_event_at_a_time() -> crate::tests::Outcome {
+        let mut task_signals = TaskSignals {
+            handler: Some(Handler {
+                entry: Address::new(0x1000).unwrap(),
+                trampoline: 0x2000,
+                events: Events::all(),
+            }),
+            pending: Events::TIMER | Events::MESSAGE,
+            info: [0, 1, 2],
+            in_handler: false,
+        };
+        // Balances the counter, as the events weren't posted.
+        PENDING.fetch_add(2, Ordering::Relaxed);
+
+        if !matches!(task_signals.take_pending(), Some((_, Event::Message, 1))) {
+            return Err("lowest pending event wasn't delivered first");
+        }
+
+        if task_signals.take_pending().is_some() {
+            return Err("event was delivered while the handler was running");
+        }
+
+        task_signals.in_handler = false;
+        if !matches!(task_signals.take_pending(), Some((_, Event::Timer, 2))) {
+            return Err("remaining event wasn't delivered once the handler returned");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+//! Initial stacks of userspace tasks, laid out as the System V ABI specifies for process entry, so images can use
+//! standard startup code.
+//!
+//! From the stack pointer up, the stack holds: the argument count; the argument pointers, then a null pointer; the
+//! environment pointers, then a null pointer; the auxiliary vector, ended by an [`AT_NULL`] entry; and lastly the
+//! strings (and random bytes) those point to. The stack pointer is 16-byte aligned.
+
+use crate::task::{AddressSpace, LoadError, Registers};
+use alloc::{vec, vec::Vec};
+use elf::{endian::AnyEndian, file::FileHeader, segment::ProgramHeader};
+use libsys::{Address, page_size};
+use zerocopy::IntoBytes;
+
+/// Ends the auxiliary vector.
+pub const AT_NULL: usize = 0;
+/// Address of the image's program headers.
+pub const AT_PHDR: usize = 3;
+/// Size of each of the image's program headers.
+pub const AT_PHENT: usize = 4;
+/// Number of the image's program headers.
+pub const AT_PHNUM: usize = 5;
+/// Page size, in bytes.
+pub const AT_PAGESZ: usize = 6;
+/// Base address of the interpreter; always `0`, as images aren't dynamically linked.
+pub const AT_BASE: usize = 7;
+/// Entry point of the image.
+pub const AT_ENTRY: usize = 9;
+/// Address of a string naming the platform.
+pub const AT_PLATFORM: usize = 15;
+/// Address of 16 random bytes, i.e. for stack protector canaries.
+pub const AT_RANDOM: usize = 25;
+
+const PLATFORM: &str = "x86_64";
+
+/// What a userspace task is started with, passed on its initial stack.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Startup<'a> {
+    /// Arguments; the first is conventionally the path the image was launched from.
+    pub args: &'a [&'a str],
+    /// Environment, as `KEY=value` strings.
+    pub env: &'a [&'a str],
+}
+
+/// Address (before the load offset is applied) the program headers of an image are loaded at, if they're loaded at
+/// all: either as their own segment, or as part of the first loadable segment.
+pub(super) fn phdr_address(
+    header: &FileHeader<AnyEndian>,
+    segments: &[ProgramHeader],
+) -> Option<usize> {
+    if let Some(phdr) = segments
+        .iter()
+        .find(|segment| segment.p_type == elf::abi::PT_PHDR)
+    {
+        return usize::try_from(phdr.p_vaddr).ok();
+    }
+
+    segments
+        .iter()
+        .filter(|segment| segment.p_type == elf::abi::PT_LOAD)
+        .find(|segment| {
+            (segment.p_offset..(segment.p_offset + segment.p_filesz)).contains(&header.e_phoff)
+        })
+        .and_then(|segment| {
+            usize::try_from(segment.p_vaddr + (header.e_phoff - segment.p_offset)).ok()
+        })
+}
+
+/// Appends `string`, NUL-terminated, to `strings`, returning its offset.
+fn push_str(strings: &mut Vec<u8>, string: &str) -> usize {
+    let offset = strings.len();
+    strings.extend_from_slice(string.as_bytes());
+    strings.push(0);
+
+    offset
+}
+
+/// Writes the initial stack for `startup` below `stack_top` in `address_space`, with `auxv` (the image's entries)
+/// leading the auxiliary vector.
+///
+/// Returns the initial stack pointer, and the registers the task enters with: besides the stack, `rdi` holds the
+/// argument count and `rsi` the argument pointers, for startup code written as a function of them; `rdx` is zeroed,
+/// as there's no termination function to register.
+pub(super) fn build_stack(
+    address_space: &mut AddressSpace,
+    stack_top: usize,
+    max_len: usize,
+    startup: &Startup<'_>,
+    auxv: &[(usize, usize)],
+) -> Result<(usize, Registers), LoadError> {
+    let mut strings = Vec::new();
+    let args = startup
+        .args
+        .iter()
+        .map(|arg| push_str(&mut strings, arg))
+        .collect::<Vec<_>>();
+    let env = startup
+        .env
+        .iter()
+        .map(|var| push_str(&mut strings, var))
+        .collect::<Vec<_>>();
+    let platform = push_str(&mut strings, PLATFORM);
+
+    let random = strings.len();
+    strings.resize(random + 16, 0);
+    crate::rand::fill(&mut strings[random..]);
+
+    let strings_addr = stack_top
+        .checked_sub(strings.len())
+        .ok_or(LoadError::StartupTooLarge)?
+        & !0xF;
+
+    let mut words = vec![args.len()];
+    words.extend(args.iter().map(|offset| strings_addr + offset));
+    words.push(0);
+    words.extend(env.iter().map(|offset| strings_addr + offset));
+    words.push(0);
+    for (key, value) in auxv.iter().copied().chain([
+        (AT_PAGESZ, page_size()),
+        (AT_RANDOM, strings_addr + random),
+        (AT_PLATFORM, strings_addr + platform),
+        (AT_NULL, 0),
+    ]) {
+        words.extend([key, value]);
+    }
+
+    let stack_pointer = strings_addr
+        .checked_sub(words.len() * size_of::<usize>())
+        .ok_or(LoadError::StartupTooLarge)?
+        & !0xF;
+    if (stack_top - stack_pointer) > max_len {
+        return Err(LoadError::StartupTooLarge);
+    }
+
+    let mut stack = vec![0u8; stack_top - stack_pointer];
+    let words = words.as_bytes();
+    stack[..words.len()].copy_from_slice(words);
+    stack[(strings_addr - stack_pointer)..][..strings.len()].copy_from_slice(&strings);
+
+    address_space
+        .write(
+            Address::new(stack_pointer).ok_or(LoadError::StartupTooLarge)?,
+            &stack,
+        )
+        .map_err(LoadError::AddressSpace)?;
+
+    Ok((
+        stack_pointer,
+        Registers {
+            rdi: args.len(),
+            rsi: stack_pointer + size_of::<usize>(),
+            rdx: 0,
+            ..Registers::empty()
+        },
+    ))
+}
+
+crate::kernel_test! {
+    /// Arguments are packed NUL-terminated, each at the offset they're referred to by.
+    fn packs_strings() -> crate::tests::Outcome {
+        let mut strings = Vec::new();
+        let offsets = ["init", "", "-v"].map(|arg| push_str(&mut strings, arg));
+
+        if offsets != [0, 5, 6] || strings != b"init\0\0-v\0" {
+            return Err("strings were packed incorrectly");
+        }
+
+        Ok(())
+    }
+}
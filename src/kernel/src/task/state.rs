@@ -0,0 +1,37 @@
+/// Lifecycle states of a [`super::Task`].
+///
+/// ```text
+/// Created ──► Ready ◄──► Running ──► Exiting ──► Reaped
+///               ▲           │           ▲
+///               │           ▼           │
+///               └──────  Blocked  ──────┘
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Task has been constructed, but has never been made available to a scheduler.
+    Created,
+    /// Task is queued, and waiting to be scheduled.
+    Ready,
+    /// Task is currently executing on a hardware thread.
+    Running,
+    /// Task is waiting on some event, and must not be scheduled.
+    Blocked,
+    /// Task has exited, but its resources have not yet been reclaimed.
+    Exiting,
+    /// Task's resources have been reclaimed.
+    Reaped,
+}
+
+impl State {
+    /// Whether the lifecycle permits moving from `self` to `next`.
+    pub const fn can_transition_to(self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (Self::Created | Self::Running | Self::Blocked, Self::Ready)
+                | (Self::Ready, Self::Running)
+                | (Self::Running, Self::Blocked)
+                | (Self::Running | Self::Blocked, Self::Exiting)
+                | (Self::Exiting, Self::Reaped)
+        )
+    }
+}
@@ -0,0 +1,122 @@
+//! Resolution of task code addresses to symbol names, with per-binary symbol tables cached across tasks.
+
+use crate::{
+    sync::SpinMutex,
+    task::{ElfData, Image},
+};
+use alloc::{boxed::Box, collections::btree_map::BTreeMap, sync::Arc, vec::Vec};
+use elf::{ElfBytes, endian::AnyEndian};
+
+/// Maximum number of binaries whose symbol tables are cached at once.
+const MAX_CACHED_TABLES: usize = 32;
+
+struct Symbol {
+    start: u64,
+    end: u64,
+    name: Box<str>,
+}
+
+/// Function symbols of a single ELF binary, sorted by address.
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    fn parse(elf_bytes: &[u8]) -> Self {
+        let symbols = ElfBytes::<AnyEndian>::minimal_parse(elf_bytes)
+            .ok()
+            // Prefer the full symbol table, but fall back to the dynamic symbols of stripped binaries.
+            .and_then(|elf| match elf.symbol_table() {
+                Ok(Some(tables)) => Some(tables),
+                _ => elf.dynamic_symbol_table().ok().flatten(),
+            })
+            .map(|(symbols, strings)| {
+                let mut symbols = symbols
+                    .iter()
+                    .filter(|symbol| {
+                        symbol.st_symtype() == elf::abi::STT_FUNC && symbol.st_size > 0
+                    })
+                    .filter_map(|symbol| {
+                        let name = strings.get(usize::try_from(symbol.st_name).ok()?).ok()?;
+
+                        Some(Symbol {
+                            start: symbol.st_value,
+                            end: symbol.st_value.saturating_add(symbol.st_size),
+                            name: Box::from(name),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                symbols.sort_unstable_by_key(|symbol| symbol.start);
+                symbols
+            })
+            .unwrap_or_default();
+
+        Self { symbols }
+    }
+
+    /// Resolves `address` (relative to the binary's load offset) to the name of its containing
+    /// function, and the offset of `address` within it.
+    pub fn resolve(&self, address: u64) -> Option<(&str, u64)> {
+        let index = self
+            .symbols
+            .partition_point(|symbol| symbol.start <= address)
+            .checked_sub(1)?;
+        let symbol = &self.symbols[index];
+
+        (address < symbol.end).then(|| (&*symbol.name, address - symbol.start))
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+static CACHE: SpinMutex<BTreeMap<u64, Arc<SymbolTable>>> = SpinMutex::new(BTreeMap::new());
+
+/// Identifies the binary `elf_data`, so tasks launched from identical binaries share a symbol table.
+pub(super) fn image_id(elf_data: &ElfData) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+    const FNV_PRIME: u64 = 0x0100_0000_01B3;
+
+    let bytes = match elf_data {
+        ElfData::Memory(data) => data.as_ref(),
+        ElfData::File(path) => path.as_bytes(),
+    };
+
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Symbol table of the binary `image` was loaded from, parsing and caching it if necessary.
+pub fn for_image(image: &Image) -> Arc<SymbolTable> {
+    let image_id = image.image_id();
+
+    if let Some(table) = crate::interrupts::uninterruptable(|| CACHE.lock().get(&image_id).cloned())
+    {
+        return table;
+    }
+
+    // The table is parsed without the cache locked, as parsing may take some time.
+    let table = Arc::new(match image.elf_data() {
+        ElfData::Memory(data) => SymbolTable::parse(data),
+        ElfData::File(_) => SymbolTable {
+            symbols: Vec::new(),
+        },
+    });
+    trace!("Cached {} symbols for binary {image_id:#X}", table.len());
+
+    crate::interrupts::uninterruptable(|| {
+        let mut cache = CACHE.lock();
+        if cache.len() >= MAX_CACHED_TABLES {
+            cache.pop_first();
+        }
+
+        cache.entry(image_id).or_insert(table).clone()
+    })
+}
@@ -0,0 +1,341 @@
+//! Per-task timer objects, modelled on POSIX `timer_create`.
+//!
+//! Timers are armed against the monotonic or realtime clock, and run on the timer wheel of the hardware thread
+//! that armed them. Each task has a queue of pending expirations, which it drains with [`wait`]; tasks which handle
+//! timer events are also notified of each expiration (see [`crate::task::signals`]).
+//!
+//! Timers are referred to by handles (see [`crate::task::handles`]), arming one requires [`Rights::WRITE`], and
+//! closing its handle destroys it.
+
+use crate::{
+    arch::x86_64::structures::idt::InterruptStackFrame,
+    interrupts::syscall::ClockId,
+    sync::SpinMutex,
+    task::{
+        Registers,
+        handles::{self, Entry, Handle, Object, Rights},
+    },
+    time::timers::TimerId,
+};
+use alloc::collections::{btree_map::BTreeMap, vec_deque::VecDeque};
+use core::time::Duration;
+use uuid::Uuid;
+
+/// Maximum number of timers a single task may own.
+pub const MAX_TIMERS: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no active task")]
+    NoActiveTask,
+
+    #[error("task already owns the maximum of {MAX_TIMERS} timers")]
+    TooManyTimers,
+
+    #[error("task owns no timer with handle {0}")]
+    NoSuchTimer(usize),
+
+    #[error("task has no armed timers to wait on")]
+    NothingToWait,
+
+    #[error(transparent)]
+    Handles(#[from] handles::Error),
+}
+
+/// Expiration delivered to a task by [`wait`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable)]
+pub struct Expiration {
+    pub handle: Handle,
+    /// Number of times the timer has expired since its last expiration was delivered.
+    pub count: u64,
+}
+
+bitflags! {
+    /// Flags accepted when arming a timer.
+    #[repr(transparent)]
+    #[derive(Debug, Clone, Copy)]
+    pub struct ArmFlags: usize {
+        /// The initial expiration is an absolute time on the timer's clock, rather than relative to now.
+        const ABSOLUTE = 1 << 0;
+    }
+}
+
+struct Timer {
+    clock: ClockId,
+    interval: Option<Duration>,
+
+    /// Wheel entry of the pending expiration, or `None` if the timer is disarmed.
+    ///
+    /// # Remarks
+    ///
+    /// Wheel entries can only be cancelled from the hardware thread they were scheduled on, so a callback whose
+    /// ID doesn't match this one is stale, and is ignored.
+    armed: Option<TimerId>,
+    /// Monotonic time (in nanoseconds) of the pending expiration.
+    expires_ns: u64,
+    /// Expirations not yet delivered to the task.
+    undelivered: u64,
+}
+
+#[derive(Default)]
+struct TaskTimers {
+    /// Timers keyed by the task's handle to each.
+    timers: BTreeMap<Handle, Timer>,
+    /// Timers with undelivered expirations, in order of their first undelivered expiration.
+    pending: VecDeque<Handle>,
+    /// Whether the task is blocked in [`wait`].
+    waiting: bool,
+}
+
+static TASK_TIMERS: SpinMutex<BTreeMap<Uuid, TaskTimers>> = SpinMutex::new(BTreeMap::new());
+
+fn active_task_id() -> Result<Uuid, Error> {
+    crate::cpu::local_state::LocalState::with_scheduler(|scheduler| {
+        scheduler.process().map(super::Task::id)
+    })
+    .ok_or(Error::NoActiveTask)
+}
+
+fn with_timer<T>(
+    task_id: Uuid,
+    handle: Handle,
+    func: impl FnOnce(&mut Timer) -> T,
+) -> Result<T, Error> {
+    crate::interrupts::uninterruptable(|| {
+        TASK_TIMERS
+            .lock()
+            .get_mut(&task_id)
+            .and_then(|task_timers| task_timers.timers.get_mut(&handle))
+            .map(func)
+            .ok_or(Error::NoSuchTimer(handle))
+    })
+}
+
+/// Creates a disarmed timer on `clock` for the active task, returning its handle.
+pub fn create(clock: ClockId) -> Result<Handle, Error> {
+    let task_id = active_task_id()?;
+
+    handles::with_table(task_id, |table| {
+        let mut task_timers = TASK_TIMERS.lock();
+        let task_timers = task_timers.entry(task_id).or_default();
+
+        if task_timers.timers.len() >= MAX_TIMERS {
+            return Err(Error::TooManyTimers);
+        }
+
+        // The timer is bound to the task, so its handle can be neither duplicated nor transferred.
+        let handle = table.insert(Entry {
+            object: Object::Timer,
+            rights: Rights::WRITE,
+        })?;
+        task_timers.timers.insert(
+            handle,
+            Timer {
+                clock,
+                interval: None,
+                armed: None,
+                expires_ns: 0,
+                undelivered: 0,
+            },
+        );
+
+        Ok(handle)
+    })
+}
+
+/// Schedules the wheel entry for the pending expiration of `timer`, owned by `task_id`.
+fn schedule(task_id: Uuid, handle: Handle, timer: &mut Timer) {
+    let timer_id = TimerId::new();
+    let delay = Duration::from_nanos(timer.expires_ns.saturating_sub(crate::time::now_ns()));
+
+    timer.armed = Some(timer_id);
+    crate::time::timers::schedule_after_as(timer_id, delay, move || {
+        expire(task_id, handle, timer_id);
+    });
+}
+
+/// Arms the timer `handle` of the active task to first expire at `initial`, then every `interval` (if nonzero).
+///
+/// A zero `initial` disarms the timer. Any undelivered expirations are kept.
+///
+/// # Remarks
+///
+/// Absolute realtime expirations are converted to monotonic time when armed, so later changes to the realtime
+/// clock do not move them.
+pub fn arm(
+    handle: Handle,
+    initial: Duration,
+    interval: Duration,
+    flags: ArmFlags,
+) -> Result<(), Error> {
+    let task_id = active_task_id()?;
+    if !matches!(handles::get(handle, Rights::WRITE)?, Object::Timer) {
+        return Err(handles::Error::WrongObject(handle).into());
+    }
+
+    with_timer(task_id, handle, |timer| {
+        if let Some(timer_id) = timer.armed.take() {
+            crate::time::timers::cancel(timer_id);
+        }
+
+        if initial.is_zero() {
+            return;
+        }
+
+        let initial_ns = u64::try_from(initial.as_nanos()).unwrap_or(u64::MAX);
+        let now_ns = crate::time::now_ns();
+        let delay_ns = if flags.contains(ArmFlags::ABSOLUTE) {
+            let clock_now_ns = match timer.clock {
+                ClockId::Realtime => crate::time::realtime_ns(),
+                ClockId::Monotonic => now_ns,
+            };
+
+            initial_ns.saturating_sub(clock_now_ns)
+        } else {
+            initial_ns
+        };
+
+        timer.interval = Some(interval).filter(|interval| !interval.is_zero());
+        timer.expires_ns = now_ns.saturating_add(delay_ns);
+        schedule(task_id, handle, timer);
+    })
+}
+
+/// Disarms and destroys the timer `handle` of the task `task_id`, discarding any undelivered expirations, as its
+/// handle was closed.
+pub(super) fn remove(task_id: Uuid, handle: Handle) {
+    crate::interrupts::uninterruptable(|| {
+        let mut task_timers = TASK_TIMERS.lock();
+        let Some(task_timers) = task_timers.get_mut(&task_id) else {
+            return;
+        };
+        let Some(timer) = task_timers.timers.remove(&handle) else {
+            return;
+        };
+
+        if let Some(timer_id) = timer.armed {
+            crate::time::timers::cancel(timer_id);
+        }
+
+        task_timers.pending.retain(|pending| *pending != handle);
+    });
+}
+
+/// Wheel callback for an expiration of the timer `handle`, owned by `task_id`.
+fn expire(task_id: Uuid, handle: Handle, timer_id: TimerId) {
+    let expired = crate::interrupts::uninterruptable(|| {
+        let mut task_timers = TASK_TIMERS.lock();
+        let task_timers = task_timers.get_mut(&task_id)?;
+        let timer = task_timers
+            .timers
+            .get_mut(&handle)
+            .filter(|timer| timer.armed == Some(timer_id))?;
+
+        timer.armed = None;
+
+        let mut expirations = 1;
+        if let Some(interval) = timer.interval {
+            let interval_ns = u64::try_from(interval.as_nanos()).unwrap_or(u64::MAX);
+            let late_ns = crate::time::now_ns().saturating_sub(timer.expires_ns);
+
+            // Expirations which were missed entirely (i.e. the wheel ran late) are counted, rather than run.
+            let missed = late_ns / interval_ns;
+            expirations += missed;
+            timer.expires_ns = timer
+                .expires_ns
+                .saturating_add(interval_ns.saturating_mul(missed + 1));
+
+            schedule(task_id, handle, timer);
+        }
+
+        if timer.undelivered == 0 {
+            task_timers.pending.push_back(handle);
+        }
+        timer.undelivered = timer.undelivered.saturating_add(expirations);
+
+        Some(core::mem::take(&mut task_timers.waiting))
+    });
+
+    let Some(wake) = expired else {
+        return;
+    };
+
+    crate::task::signals::post(task_id, crate::task::signals::Event::Timer, handle);
+
+    if wake {
+        super::unblock_task(task_id);
+    }
+}
+
+/// Delivers the active task's next pending expiration, blocking the task until one is available.
+///
+/// Returns `None` if the task was blocked, in which case the system call is restarted once it is woken.
+///
+/// # Remarks
+///
+/// This must be called from an interrupt context, as the active task may be switched out in place.
+pub fn wait(
+    isf: &mut InterruptStackFrame,
+    regs: &mut Registers,
+) -> Result<Option<Expiration>, Error> {
+    crate::cpu::local_state::LocalState::with_scheduler(|scheduler| {
+        let task_id = scheduler
+            .process()
+            .map(super::Task::id)
+            .ok_or(Error::NoActiveTask)?;
+
+        let mut task_timers = TASK_TIMERS.lock();
+        let task_timers = task_timers.entry(task_id).or_default();
+
+        if let Some(handle) = task_timers.pending.pop_front() {
+            // Handles are removed from `pending` when their timer is deleted.
+            let timer = task_timers.timers.get_mut(&handle).unwrap();
+
+            return Ok(Some(Expiration {
+                handle,
+                count: core::mem::take(&mut timer.undelivered),
+            }));
+        }
+
+        if task_timers
+            .timers
+            .values()
+            .all(|timer| timer.armed.is_none())
+        {
+            return Err(Error::NothingToWait);
+        }
+
+        task_timers.waiting = true;
+
+        // Safety: The task's registers are unchanged, so the wait is issued again when the task is woken.
+        unsafe {
+            crate::interrupts::syscall::restart(isf);
+        }
+
+        // The task is blocked with the timers locked, so an expiration can't try to wake it before it's blocked.
+        scheduler.block_task(isf, regs);
+
+        Ok(None)
+    })
+}
+
+/// Disarms and destroys every timer owned by the task `id`.
+///
+/// # Remarks
+///
+/// Wheel entries scheduled on other hardware threads can't be cancelled from here; they're ignored when they
+/// expire, as their task no longer has any timers.
+pub fn on_exit(id: Uuid) {
+    let Some(task_timers) = TASK_TIMERS.lock().remove(&id) else {
+        return;
+    };
+
+    task_timers
+        .timers
+        .values()
+        .filter_map(|timer| timer.armed)
+        .for_each(|timer_id| {
+            crate::time::timers::cancel(timer_id);
+        });
+}
@@ -0,0 +1,143 @@
+//! Thread-local storage of userspace tasks, laid out as the x86-64 System V ABI specifies (variant II).
+//!
+//! The thread pointer (the FS base) addresses the thread control block, whose first word points to itself, so it can
+//! be read without `rdfsbase`. The TLS block sits directly below it, initialized from the image's `PT_TLS` segment:
+//! its initialization image (`.tdata`) is copied, and the rest (`.tbss`) zeroed. Each thread of a process is given
+//! its own block, above the image (see [`crate::task::Image::allocate_tls`]).
+
+use crate::task::{AddressSpace, LoadError, MmapPermissions};
+use alloc::vec;
+use core::num::NonZeroUsize;
+use elf::segment::ProgramHeader;
+use libsys::{Address, Page, page_size};
+
+/// Size of the thread control block. Besides its self-pointer, C runtimes keep their stack protector canary in it (at
+/// `fs:0x28`), so it's given room for that and a little more; it's zeroed, for startup code to fill in.
+pub const TCB_SIZE: usize = 0x40;
+
+/// Layout of an image's thread-local storage.
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    segment: ProgramHeader,
+    /// Distance of the TLS block below the thread pointer.
+    block_offset: usize,
+    /// Alignment of the TLS block, and of the thread pointer.
+    align: usize,
+}
+
+impl Layout {
+    /// Layout of the thread-local storage of an image `image_len` bytes long, with the program headers `segments`, or
+    /// `None` if it has none.
+    pub fn new(segments: &[ProgramHeader], image_len: usize) -> Result<Option<Self>, LoadError> {
+        let Some(segment) = segments
+            .iter()
+            .find(|segment| segment.p_type == elf::abi::PT_TLS)
+            .copied()
+        else {
+            return Ok(None);
+        };
+
+        let invalid = LoadError::InvalidSegment(segment.p_vaddr);
+
+        let in_image = segment
+            .p_offset
+            .checked_add(segment.p_filesz)
+            .is_some_and(|file_end| file_end <= u64::try_from(image_len).unwrap());
+        if !in_image || segment.p_filesz > segment.p_memsz {
+            return Err(invalid);
+        }
+
+        // The thread pointer is aligned for the TLS block, and for the self-pointer it addresses.
+        let align = usize::try_from(segment.p_align.max(1))
+            .ok()
+            .filter(|align| align.is_power_of_two() && *align <= page_size())
+            .ok_or(LoadError::MisalignedSegment(segment.p_vaddr))?
+            .max(align_of::<usize>());
+
+        let block_offset = usize::try_from(segment.p_memsz)
+            .map_err(|_| invalid)?
+            .next_multiple_of(align);
+
+        Ok(Some(Self {
+            segment,
+            block_offset,
+            align,
+        }))
+    }
+
+    /// Offset from the thread pointer of the variable at `offset` in the TLS block, which `R_X86_64_TPOFF64`
+    /// relocations resolve to.
+    pub const fn tp_offset(&self, offset: usize) -> usize {
+        offset.wrapping_sub(self.block_offset)
+    }
+
+    /// Number of bytes mapped for a TLS block and thread control block.
+    pub const fn size(&self) -> usize {
+        self.block_offset + TCB_SIZE
+    }
+
+    /// Maps and initializes a TLS block (from `image`) and thread control block at `address` in `address_space`,
+    /// returning the thread pointer.
+    pub fn allocate(
+        &self,
+        address_space: &mut AddressSpace,
+        address: Address<Page>,
+        image: &[u8],
+    ) -> Result<usize, LoadError> {
+        // `address` is page-aligned, and the alignment is at most a page, so the thread pointer is aligned.
+        let base = address.get().get();
+        let thread_pointer = base + self.block_offset;
+        debug_assert!(thread_pointer.is_multiple_of(self.align));
+
+        address_space
+            .mmap(
+                Some(address),
+                NonZeroUsize::new(self.size().div_ceil(page_size())).unwrap(),
+                MmapPermissions::ReadWrite,
+            )
+            .map_err(LoadError::AddressSpace)?;
+
+        let file_offset = usize::try_from(self.segment.p_offset).unwrap();
+        let file_len = usize::try_from(self.segment.p_filesz).unwrap();
+
+        let mut contents = vec![0u8; self.size()];
+        contents[..file_len].copy_from_slice(&image[file_offset..(file_offset + file_len)]);
+        contents[self.block_offset..][..size_of::<usize>()]
+            .copy_from_slice(&thread_pointer.to_ne_bytes());
+
+        address_space
+            .write(address.get(), &contents)
+            .map_err(LoadError::AddressSpace)?;
+
+        Ok(thread_pointer)
+    }
+}
+
+crate::kernel_test! {
+    /// Variables are addressed below the thread pointer, by the aligned size of the TLS block.
+    fn tls_block_sits_below_thread_pointer() -> crate::tests::Outcome {
+        let segment = ProgramHeader {
+            p_type: elf::abi::PT_TLS,
+            p_offset: 0,
+            p_vaddr: 0,
+            p_paddr: 0,
+            p_filesz: 4,
+            p_memsz: 20,
+            p_flags: elf::abi::PF_R,
+            p_align: 16,
+        };
+
+        let layout = Layout::new(&[segment], 4)
+            .map_err(|_| "valid TLS segment was rejected")?
+            .ok_or("TLS segment wasn't found")?;
+        if layout.tp_offset(4) != 4usize.wrapping_sub(32) || layout.size() != 32 + TCB_SIZE {
+            return Err("TLS block is laid out incorrectly");
+        }
+
+        if Layout::new(&[ProgramHeader { p_filesz: 24, ..segment }], 24).is_ok() {
+            return Err("TLS segment with more file data than memory was accepted");
+        }
+
+        Ok(())
+    }
+}
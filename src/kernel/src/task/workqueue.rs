@@ -0,0 +1,161 @@
+//! Deferred work (bottom halves), run once an interrupt has been acknowledged, with interrupts enabled.
+//!
+//! Interrupt handlers run with interrupts disabled, so anything heavy they do (i.e. processing received packets, or
+//! fanning out timer callbacks) delays every other interrupt on the hardware thread. Instead, they [`defer`] it to
+//! the hardware thread's queue, which is drained as the outermost interrupt returns, after the local APIC has been
+//! acknowledged. The queue is drained on the hardware thread's worker stack, rather than the interrupted stack
+//! (which may be the scheduler's small idle stack).
+//!
+//! # Remarks
+//!
+//! Deferred work is still part of the interrupt (see [`crate::interrupts::in_interrupt_context`]), so it mustn't
+//! block. A timer tick taken while work runs can't switch tasks, as it interrupted the work rather than a task; the
+//! tasks are switched once the queue is empty instead.
+
+use crate::{
+    arch::x86_64::structures::idt::InterruptStackFrame, cpu::local_state::LocalState,
+    interrupts::InterruptCell, mem::stack::Stack, sync::SpinMutex, task::Registers,
+};
+use alloc::{boxed::Box, collections::vec_deque::VecDeque};
+use core::{
+    alloc::AllocError,
+    mem::MaybeUninit,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Size of each hardware thread's worker stack.
+const WORKER_STACK_SIZE: usize = 0x8000;
+
+type Work = Box<dyn FnOnce() + Send>;
+
+/// A hardware thread's deferred work, and the stack it's run on.
+pub struct WorkQueue {
+    queue: InterruptCell<SpinMutex<VecDeque<Work>>>,
+    stack: Box<Stack<WORKER_STACK_SIZE>>,
+    /// Whether the queue is being drained, so interrupts taken now interrupted deferred work rather than a task.
+    is_draining: AtomicBool,
+    /// Whether a timer tick was taken while draining, so tasks should be switched once the queue is empty.
+    is_preemption_pending: AtomicBool,
+}
+
+impl WorkQueue {
+    pub fn new() -> Result<Self, AllocError> {
+        Ok(Self {
+            queue: InterruptCell::new(SpinMutex::new(VecDeque::new())),
+            stack: Stack::new_box()?,
+            is_draining: AtomicBool::new(false),
+            is_preemption_pending: AtomicBool::new(false),
+        })
+    }
+
+    fn pop(&self) -> Option<Work> {
+        self.queue.with(|queue| queue.lock().pop_front())
+    }
+}
+
+/// Queues `work` to run on the current hardware thread once the current interrupt returns (or, outside of an
+/// interrupt, once the next one does).
+///
+/// # Panics
+///
+/// If the current hardware thread's local state hasn't been initialized.
+pub fn defer(work: impl FnOnce() + Send + 'static) {
+    LocalState::with_work_queue(|work_queue| {
+        work_queue
+            .queue
+            .with(|queue| queue.lock().push_back(Box::new(work)));
+    })
+    .expect("local state has not been initialized");
+}
+
+/// Whether the current hardware thread is running deferred work.
+pub fn is_draining() -> bool {
+    LocalState::with_work_queue(|work_queue| work_queue.is_draining.load(Ordering::Relaxed))
+        .unwrap_or(false)
+}
+
+/// Switches tasks once the current hardware thread's queue is empty, as a timer tick interrupted its deferred work.
+pub(crate) fn defer_preemption() {
+    LocalState::with_work_queue(|work_queue| {
+        work_queue
+            .is_preemption_pending
+            .store(true, Ordering::Relaxed);
+    });
+}
+
+/// Calls `func` on the stack whose top is `stack_top`, then returns to the current stack.
+///
+/// # Safety
+///
+/// `stack_top` must be the top of a 16-byte aligned stack which nothing else is using.
+unsafe fn call_on_stack(stack_top: NonNull<MaybeUninit<u8>>, func: extern "sysv64" fn()) {
+    // Safety: Caller is required to provide an unused stack. `r12` is preserved by `func`, so it holds the current
+    //         stack pointer across the call.
+    unsafe {
+        core::arch::asm!(
+            "mov r12, rsp",
+            "mov rsp, {stack_top}",
+            "call {func}",
+            "mov rsp, r12",
+            stack_top = in(reg) stack_top.as_ptr(),
+            func = in(reg) func,
+            out("r12") _,
+            clobber_abi("sysv64"),
+        );
+    }
+}
+
+extern "sysv64" fn drain() {
+    while let Some(work) = LocalState::with_work_queue(WorkQueue::pop).flatten() {
+        crate::interrupts::enable();
+        work();
+        crate::interrupts::disable();
+    }
+}
+
+/// Runs the current hardware thread's deferred work, if this is the outermost interrupt, then switches tasks if a
+/// timer tick was taken meanwhile; `isf` and `regs` are the outermost interrupt's.
+///
+/// # Remarks
+///
+/// This must be called with interrupts disabled, once the interrupt has been acknowledged.
+pub(crate) fn run_pending(isf: &mut InterruptStackFrame, regs: &mut Registers) {
+    debug_assert!(!crate::interrupts::is_enabled());
+
+    if crate::percpu!(interrupt_depth).load(Ordering::Relaxed) != 1 {
+        return;
+    }
+
+    let Some(work_queue) = LocalState::with_work_queue(|work_queue| work_queue) else {
+        return;
+    };
+
+    if work_queue.queue.with(|queue| queue.lock().is_empty()) {
+        return;
+    }
+
+    work_queue.is_draining.store(true, Ordering::Relaxed);
+
+    // Safety: The worker stack belongs to this hardware thread, and draining never nests (only the outermost interrupt
+    //         drains).
+    unsafe {
+        call_on_stack(work_queue.stack.top(), drain);
+    }
+
+    work_queue.is_draining.store(false, Ordering::Relaxed);
+
+    if !work_queue.stack.canary_intact() {
+        panic!(
+            "worker stack canary of hardware thread {} was clobbered",
+            crate::cpu::get_id()
+        );
+    }
+
+    if work_queue
+        .is_preemption_pending
+        .swap(false, Ordering::Relaxed)
+    {
+        LocalState::with_scheduler(|scheduler| scheduler.interrupt_task(isf, regs));
+    }
+}
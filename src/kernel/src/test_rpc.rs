@@ -0,0 +1,187 @@
+//! Command channel for host-driven integration tests, over the virtio-serial port [`PORT_NAME`].
+//!
+//! Requests are single lines of ASCII: a command, followed by space-separated arguments. Every request is answered
+//! with either `OK <len>\n` followed by `len` bytes of payload, or `ERR <message>\n`.
+//!
+//! Commands:
+//! - `ping`: responds `pong`.
+//! - `tests`: lists the names of the kernel tests (see [`crate::tests`]), one per line.
+//! - `run <test>`: runs a kernel test, responding with an empty payload if it passed.
+//! - `read <path>`: responds with the contents of the initramfs file at `path`.
+//! - `dmesg [from]`: responds with the log ring's records, from sequence number `from`.
+//! - `fault <panic|breakpoint|page-fault>`: raises the fault, to exercise the kernel's handling of it.
+
+use crate::{
+    cpu::local_state::LocalState, devices::virtio::console, interrupts::InterruptCell,
+    sync::SpinMutex,
+};
+use alloc::{string::String, vec::Vec};
+use core::{fmt::Write, time::Duration};
+
+/// Name the host gives the port (i.e. `-device virtserialport,name=...`).
+pub const PORT_NAME: &str = "org.linuiz.rpc";
+
+/// Longest accepted request line; longer lines are discarded.
+const MAX_LINE_LEN: usize = 256;
+
+const POLL_PERIOD: Duration = Duration::from_millis(10);
+const POLL_BUDGET: Duration = Duration::from_millis(1);
+
+/// Partially-received request line.
+static LINE: InterruptCell<SpinMutex<Vec<u8>>> = InterruptCell::new(SpinMutex::new(Vec::new()));
+
+/// Admits polling of the RPC port as periodic work on the current hardware thread, if the port exists.
+pub fn start() {
+    if !console::has_port(PORT_NAME) {
+        return;
+    }
+
+    let admitted = LocalState::with_scheduler(|scheduler| {
+        scheduler
+            .deadline_class_mut()
+            .admit("test-rpc", POLL_PERIOD, POLL_BUDGET, poll)
+    });
+
+    match admitted {
+        Ok(()) => debug!("Test RPC channel is listening."),
+        Err(error) => warn!("Failed to start the test RPC channel: {error}"),
+    }
+}
+
+fn poll() {
+    let mut buffer = [0; 64];
+
+    loop {
+        let len = console::read(PORT_NAME, &mut buffer);
+        if len == 0 {
+            break;
+        }
+
+        for &byte in &buffer[..len] {
+            let line = LINE.with(|line| {
+                let mut line = line.lock();
+
+                match byte {
+                    b'\n' => Some(core::mem::take(&mut *line)),
+                    b'\r' => None,
+                    byte => {
+                        if line.len() < MAX_LINE_LEN {
+                            line.push(byte);
+                        }
+
+                        None
+                    }
+                }
+            });
+
+            if let Some(line) = line {
+                respond(&line);
+            }
+        }
+    }
+}
+
+fn respond(line: &[u8]) {
+    let Ok(line) = core::str::from_utf8(line) else {
+        reply(Err("request is not valid UTF-8"));
+        return;
+    };
+
+    let mut args = line.split_ascii_whitespace();
+    let Some(command) = args.next() else {
+        return;
+    };
+
+    trace!("Test RPC request: {line}");
+
+    let response = match command {
+        "ping" => Ok(Vec::from(b"pong".as_slice())),
+        "tests" => Ok(tests()),
+        "run" => run(args.next()),
+        "read" => read(args.next()),
+        "dmesg" => dmesg(args.next()),
+        "fault" => fault(args.next()),
+        _ => Err("unknown command"),
+    };
+
+    reply(response);
+}
+
+fn reply(response: Result<Vec<u8>, &'static str>) {
+    let mut header = String::new();
+    match &response {
+        Ok(payload) => writeln!(header, "OK {}", payload.len()).ok(),
+        Err(message) => writeln!(header, "ERR {message}").ok(),
+    };
+
+    let written = match response {
+        Ok(payload) => {
+            console::write(PORT_NAME, header.as_bytes()) && console::write(PORT_NAME, &payload)
+        }
+        Err(_) => console::write(PORT_NAME, header.as_bytes()),
+    };
+
+    if !written {
+        warn!("Test RPC response was not fully written.");
+    }
+}
+
+fn tests() -> Vec<u8> {
+    crate::tests::registry()
+        .iter()
+        .fold(Vec::new(), |mut payload, test| {
+            payload.extend_from_slice(test.name().as_bytes());
+            payload.push(b'\n');
+
+            payload
+        })
+}
+
+fn run(name: Option<&str>) -> Result<Vec<u8>, &'static str> {
+    let name = name.ok_or("missing test name")?;
+    let test = crate::tests::find(name).ok_or("no such test")?;
+
+    (test.run)()?;
+
+    Ok(Vec::new())
+}
+
+fn read(path: Option<&str>) -> Result<Vec<u8>, &'static str> {
+    let path = path.ok_or("missing path")?;
+    let data = crate::fs::initrd::open(path).map_err(|_| "no such file")?;
+
+    Ok(Vec::from(data))
+}
+
+fn dmesg(from: Option<&str>) -> Result<Vec<u8>, &'static str> {
+    let from = from
+        .map(str::parse::<u64>)
+        .transpose()
+        .map_err(|_| "invalid sequence number")?
+        .unwrap_or(0);
+
+    let mut payload = String::new();
+    for record in crate::logging::ring::records_from(from) {
+        writeln!(payload, "{} {record}", record.sequence).ok();
+    }
+
+    Ok(payload.into_bytes())
+}
+
+fn fault(kind: Option<&str>) -> Result<Vec<u8>, &'static str> {
+    match kind.ok_or("missing fault kind")? {
+        "panic" => panic!("test RPC requested a panic"),
+        "breakpoint" => core::arch::breakpoint(),
+
+        "page-fault" => {
+            // Safety: Intentionally faults; the null page is never mapped.
+            unsafe {
+                core::arch::asm!("mov {}, qword ptr [0]", out(reg) _, options(nostack, readonly));
+            }
+        }
+
+        _ => return Err("unknown fault kind"),
+    }
+
+    Err("fault was handled")
+}
@@ -0,0 +1,108 @@
+//! In-kernel tests, run at the end of boot with `--run-tests` (or `--test-mode`), or individually over the test RPC
+//! channel.
+//!
+//! Subsystems declare their tests alongside their code with [`crate::kernel_test!`], which places each in the
+//! `.kernel_tests` linker section, so every test is known without a central list.
+
+crate::parameters! {
+    /// Whether to run the kernel tests at the end of boot, then exit QEMU with their result.
+    "run-tests": bool = false,
+}
+
+/// Result of a test: `Err` describes how it failed.
+pub type Outcome = Result<(), &'static str>;
+
+/// A registered test; see [`crate::kernel_test!`].
+pub struct Test {
+    /// Module path of the test, including the crate name.
+    pub path: &'static str,
+    pub run: fn() -> Outcome,
+}
+
+impl Test {
+    /// Name of the test, as its module path (without the crate name).
+    pub fn name(&self) -> &'static str {
+        self.path
+            .split_once("::")
+            .map_or(self.path, |(_, name)| name)
+    }
+}
+
+/// Declares kernel tests, which run on the bootstrap processor after every init stage.
+///
+/// ```ignore
+/// crate::kernel_test! {
+///     /// Allocated frames are locked until freed.
+///     fn allocation() -> crate::tests::Outcome {
+///         ...
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! kernel_test {
+    ($($(#[doc = $doc:literal])* fn $name:ident() -> $outcome:ty $body:block)*) => {
+        $(
+            $(#[doc = $doc])*
+            fn $name() -> $outcome $body
+
+            const _: () = {
+                #[used]
+                #[unsafe(link_section = ".kernel_tests")]
+                static TEST: $crate::tests::Test = $crate::tests::Test {
+                    path: concat!(module_path!(), "::", stringify!($name)),
+                    run: $name,
+                };
+            };
+        )*
+    };
+}
+
+unsafe extern "C" {
+    unsafe static __kernel_tests_start: u8;
+    unsafe static __kernel_tests_end: u8;
+}
+
+/// Every registered test.
+pub fn registry() -> &'static [Test] {
+    let start = (&raw const __kernel_tests_start).cast::<Test>();
+    let end = (&raw const __kernel_tests_end).cast::<Test>();
+
+    // Safety: The linker script places the `.kernel_tests` section, which only holds `Test`s, between the symbols.
+    unsafe { core::slice::from_raw_parts(start, usize::try_from(end.offset_from(start)).unwrap()) }
+}
+
+/// Finds the test named `name` (see [`Test::name`]).
+pub fn find(name: &str) -> Option<&'static Test> {
+    registry().iter().find(|test| test.name() == name)
+}
+
+/// Whether the kernel tests were requested to run at the end of boot.
+pub fn is_requested() -> bool {
+    crate::params::get::<bool>("run-tests") || crate::qemu_exit::is_test_mode()
+}
+
+/// Runs every registered test, reporting each result over the serial console, and returns whether all passed.
+pub fn run_all() -> bool {
+    let tests = registry();
+    let mut passed = 0;
+
+    info!("Running {} kernel tests...", tests.len());
+
+    for test in tests {
+        match (test.run)() {
+            Ok(()) => {
+                info!("test {} ... ok", test.name());
+                passed += 1;
+            }
+
+            Err(message) => error!("test {} ... FAILED: {message}", test.name()),
+        }
+    }
+
+    info!(
+        "Kernel tests: {passed} passed, {} failed.",
+        tests.len() - passed
+    );
+
+    passed == tests.len()
+}
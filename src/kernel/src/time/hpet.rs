@@ -0,0 +1,225 @@
+use core::{
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use libsys::{Address, Frame};
+use spin::Once;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to get the ACPI root table")]
+    Acpi(#[from] crate::acpi::Error),
+
+    #[error("platform does not provide an HPET table")]
+    NoHpetTable,
+
+    #[error("HPET base address is invalid: {0:#X}")]
+    InvalidBaseAddress(usize),
+
+    #[error("HPET reported an invalid tick period: {0}fs")]
+    InvalidPeriod(u64),
+
+    #[error("failed to map the HPET registers")]
+    Paging(#[from] crate::mem::paging::Error),
+}
+
+/// Size of the HPET register block.
+const REGISTER_BLOCK_SIZE: usize = 0x400;
+
+/// Femtoseconds per second.
+const FS_PER_SEC: u64 = 1_000_000_000_000_000;
+
+/// Maximum tick period allowed by the specification (100ns).
+const MAX_PERIOD_FS: u64 = 100_000_000;
+
+#[repr(usize)]
+#[derive(Debug, IntoPrimitive, Clone, Copy)]
+#[allow(non_camel_case_types)]
+#[rustfmt::skip]
+enum Register {
+    CAPABILITIES    = 0x000,
+    CONFIGURATION   = 0x010,
+    MAIN_COUNTER    = 0x0F0,
+}
+
+bitflags! {
+    #[repr(transparent)]
+    #[derive(Debug, Clone, Copy)]
+    struct Configuration: u64 {
+        /// Enables the main counter, and allows the timers to generate interrupts.
+        const ENABLE = 1 << 0;
+        /// Routes timers 0 and 1 to the legacy PIT & RTC interrupt lines.
+        const LEGACY_REPLACEMENT = 1 << 1;
+    }
+}
+
+static HPET: Once<Hpet> = Once::new();
+
+/// The high-precision event timer, used as a secondary monotonic clock source.
+pub struct Hpet {
+    registers: NonNull<u64>,
+    period_fs: u64,
+    frequency: u64,
+    is_64bit: bool,
+
+    /// Last observed (extended) counter value, used to extend 32-bit counters to 64 bits.
+    last_count: AtomicU64,
+}
+
+// Safety: Register block is mapped in the kernel address space, which is shared by all hardware threads.
+unsafe impl Send for Hpet {}
+// Safety: Register accesses are single volatile reads / writes, and the extended count is atomic.
+unsafe impl Sync for Hpet {}
+
+impl Hpet {
+    /// Attempts to locate, map, and enable the high-precision event timer.
+    pub fn init() -> Result<(), Error> {
+        HPET.try_call_once(|| {
+            trace!("Initializing `Hpet`...");
+
+            let hpet_info = acpi::HpetInfo::new(crate::acpi::Tables::root()?)
+                .map_err(|_| Error::NoHpetTable)?;
+
+            trace!("Found HPET: {hpet_info:#X?}");
+
+            let base_frame = Address::<Frame>::new(hpet_info.base_address)
+                .ok_or(Error::InvalidBaseAddress(hpet_info.base_address))?;
+            let registers = crate::mem::map_mmio(base_frame, REGISTER_BLOCK_SIZE)?.cast::<u64>();
+
+            let mut hpet = Self {
+                registers,
+                period_fs: 0,
+                frequency: 0,
+                is_64bit: false,
+                last_count: AtomicU64::new(0),
+            };
+
+            let capabilities = hpet.read(Register::CAPABILITIES);
+            let period_fs = capabilities >> 32;
+            if !(1..=MAX_PERIOD_FS).contains(&period_fs) {
+                return Err(Error::InvalidPeriod(period_fs));
+            }
+
+            hpet.period_fs = period_fs;
+            hpet.frequency = FS_PER_SEC / period_fs;
+            hpet.is_64bit = (capabilities & (1 << 13)) > 0;
+
+            // Halt the counter, reset it, then start it again without legacy routing.
+            let mut configuration =
+                Configuration::from_bits_retain(hpet.read(Register::CONFIGURATION));
+            configuration.remove(Configuration::ENABLE | Configuration::LEGACY_REPLACEMENT);
+            hpet.write(Register::CONFIGURATION, configuration.bits());
+            hpet.write(Register::MAIN_COUNTER, 0);
+            configuration.insert(Configuration::ENABLE);
+            hpet.write(Register::CONFIGURATION, configuration.bits());
+
+            debug!(
+                "HPET enabled: {{ frequency: {}Hz, 64 bit: {} }}",
+                hpet.frequency, hpet.is_64bit
+            );
+
+            Ok(hpet)
+        })
+        .map(|_| ())
+    }
+
+    /// Whether the HPET has been successfully initialized.
+    pub fn is_available() -> bool {
+        HPET.get().is_some()
+    }
+
+    fn get_static() -> &'static Self {
+        HPET.get()
+            .expect("static `Hpet` has not yet been initialized")
+    }
+
+    fn read(&self, register: Register) -> u64 {
+        // Safety: Register offset is within the mapped register block, and is 8-byte aligned.
+        unsafe {
+            self.registers
+                .byte_add(usize::from(register))
+                .read_volatile()
+        }
+    }
+
+    fn write(&self, register: Register, value: u64) {
+        // Safety: Register offset is within the mapped register block, and is 8-byte aligned.
+        unsafe {
+            self.registers
+                .byte_add(usize::from(register))
+                .write_volatile(value);
+        }
+    }
+
+    /// Frequency (in Hz) of the main counter.
+    pub fn frequency() -> u64 {
+        Self::get_static().frequency
+    }
+
+    /// Reads the monotonic main counter value.
+    ///
+    /// # Remarks
+    ///
+    /// If the hardware counter is only 32 bits wide, overflows are tracked in software so the
+    /// returned value is always monotonic, provided this is called at least once per half overflow
+    /// period (~2.5 minutes at the minimum legal frequency).
+    pub fn counter() -> u64 {
+        let hpet = Self::get_static();
+        let count = hpet.read(Register::MAIN_COUNTER);
+
+        if hpet.is_64bit {
+            return count;
+        }
+
+        let count = count & 0xFFFF_FFFF;
+        let mut last_count = hpet.last_count.load(Ordering::Acquire);
+        loop {
+            let delta = count.wrapping_sub(last_count & 0xFFFF_FFFF) & 0xFFFF_FFFF;
+            if delta > 0xFFFF_FFFF / 2 {
+                // ... the count was read before another hardware thread published a later one, so it's stale rather
+                // than having wrapped ...
+                return last_count;
+            }
+
+            let extended_count = last_count + delta;
+
+            match hpet.last_count.compare_exchange_weak(
+                last_count,
+                extended_count,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return extended_count,
+                Err(observed_count) => last_count = observed_count,
+            }
+        }
+    }
+
+    /// Time elapsed since the HPET was initialized.
+    pub fn elapsed() -> Duration {
+        let period_fs = Self::get_static().period_fs;
+        let elapsed_fs = u128::from(Self::counter()) * u128::from(period_fs);
+        let elapsed_ns = u64::try_from(elapsed_fs / 1_000_000).unwrap_or(u64::MAX);
+
+        Duration::from_nanos(elapsed_ns)
+    }
+
+    /// Spin waits for the provided [`Duration`].
+    ///
+    /// # Remarks
+    ///
+    /// - [`Duration`]s greater than [`u64::MAX`] nanoseconds will be truncated.
+    pub fn spin_wait(duration: Duration) {
+        let frequency = Self::frequency();
+        let duration_ns = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        let wait_ticks =
+            u64::try_from((u128::from(duration_ns) * u128::from(frequency)) / 1_000_000_000)
+                .unwrap_or(u64::MAX);
+
+        let start_count = Self::counter();
+        while Self::counter().wrapping_sub(start_count) < wait_ticks {
+            core::hint::spin_loop();
+        }
+    }
+}
@@ -1,23 +1,27 @@
 use crate::{
     arch::x86_64::{
-        cpuid::{
-            advanced_power_management_info, feature_info, hypervisor_info, processor_frequency_info,
-        },
-        devices::x2apic::{local_vector::TimerMode, x2Apic},
+        cpuid::{advanced_power_management_info, feature_info, hypervisor_info},
+        devices::local_apic::{LocalApic, TimerDivideConfiguration, local_vector::TimerMode},
         registers::model_specific::IA32_TSC_DEADLINE,
     },
-    time::Stopwatch,
+    time::{calibration_spin_wait, tsc_frequency},
 };
 use core::{arch::x86_64::_rdtsc, time::Duration};
-use raw_cpuid::{ApmInfo, FeatureInfo, HypervisorInfo};
+use raw_cpuid::{ApmInfo, FeatureInfo};
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("wait duration was too long")]
     InvalidWait,
+
+    #[error("timer frequency has not been calibrated")]
+    Uncalibrated,
+
+    #[error("local timer is not the local APIC timer")]
+    NotApicTimer,
 }
 
-/// Duration to measure other timer sources against [`Stopwatch`].
+/// Duration to measure other timer sources against the calibration clock source.
 const MEASUREMENT_DURATION: Duration = Duration::from_millis(50);
 
 /// Amount you need to multiply measured ticks by when using [`MEASUREMENT_DURATION`].
@@ -25,36 +29,17 @@ const MEASUREMENT_DURATION: Duration = Duration::from_millis(50);
 const MEASUREMENT_FREQUENCY_FACTOR: u32 =
     (Duration::SECOND.as_micros() / MEASUREMENT_DURATION.as_micros()) as u32;
 
-fn measure_tsc() -> u64 {
-    trace!("Measuring the timestamp counter frequency...");
-
-    // Safety: Processor has TSC capability.
-    let start_tsc = unsafe { _rdtsc() };
-    Stopwatch::spin_wait(MEASUREMENT_DURATION);
-    // Safety: Processor has TSC capability.
-    let end_tsc = unsafe { _rdtsc() };
-
-    let elapsed_ticks = end_tsc - start_tsc;
-    let frequency = elapsed_ticks * u64::from(MEASUREMENT_FREQUENCY_FACTOR);
-
-    trace!("Timestamp counter frequency: {frequency}Hz");
-
-    frequency
-}
-
 fn measure_lapic() -> u32 {
     trace!("Measuring the local APIC timer frequency...");
 
-    x2Apic::set_timer_divide_configuration(
-        crate::arch::x86_64::devices::x2apic::TimerDivideConfiguration::DivideBy1,
-    );
+    LocalApic::set_timer_divide_configuration(TimerDivideConfiguration::DivideBy1);
 
     const MEASURE_TIMER_COUNTDOWN_VALUE: u32 = u32::MAX;
 
     // Loading the initial count starts the timer.
-    x2Apic::set_timer_initial_count(MEASURE_TIMER_COUNTDOWN_VALUE);
-    Stopwatch::spin_wait(MEASUREMENT_DURATION);
-    let end_timer_count = x2Apic::get_timer_current_count();
+    LocalApic::set_timer_initial_count(MEASURE_TIMER_COUNTDOWN_VALUE);
+    calibration_spin_wait(MEASUREMENT_DURATION);
+    let end_timer_count = LocalApic::get_timer_current_count();
 
     let elapsed_ticks = MEASURE_TIMER_COUNTDOWN_VALUE - end_timer_count;
     let frequency = elapsed_ticks * MEASUREMENT_FREQUENCY_FACTOR;
@@ -64,6 +49,82 @@ fn measure_lapic() -> u32 {
     frequency
 }
 
+/// Divide configuration and initial count which program the local APIC timer to expire after some period.
+#[derive(Debug, Clone, Copy)]
+pub struct ApicTimerConfiguration {
+    divide: TimerDivideConfiguration,
+    initial_count: u32,
+    frequency: u32,
+}
+
+impl ApicTimerConfiguration {
+    /// Selects the configuration which expires after `period` with the finest resolution, for a timer with the
+    /// (undivided) base frequency `frequency`.
+    ///
+    /// # Remarks
+    ///
+    /// The smallest divisor whose initial count fits the count register is chosen, so that each count is as short
+    /// as possible. Periods shorter than a single count are rounded up to one count.
+    pub fn for_period(frequency: u32, period: Duration) -> Result<Self, Error> {
+        if frequency == 0 {
+            return Err(Error::Uncalibrated);
+        }
+
+        let base_ticks = (period.as_nanos() * u128::from(frequency)) / Duration::SECOND.as_nanos();
+
+        TimerDivideConfiguration::ALL
+            .into_iter()
+            .find_map(|divide| {
+                let initial_count =
+                    u32::try_from(base_ticks / u128::from(divide.divisor())).ok()?;
+
+                Some(Self {
+                    divide,
+                    // An initial count of `0` disarms the timer, so ensure the count is always nonzero.
+                    initial_count: initial_count.max(1),
+                    frequency,
+                })
+            })
+            .ok_or(Error::InvalidWait)
+    }
+
+    pub const fn divide(&self) -> TimerDivideConfiguration {
+        self.divide
+    }
+
+    pub const fn initial_count(&self) -> u32 {
+        self.initial_count
+    }
+
+    /// Duration of a single count.
+    pub fn resolution(&self) -> Duration {
+        Duration::from_nanos(
+            u64::try_from(
+                (Duration::SECOND.as_nanos() * u128::from(self.divide.divisor()))
+                    / u128::from(self.frequency),
+            )
+            .unwrap(),
+        )
+    }
+
+    /// Period the timer will actually expire after, once rounded to its resolution.
+    pub fn period(&self) -> Duration {
+        let nanos = (Duration::SECOND.as_nanos()
+            * u128::from(self.divide.divisor())
+            * u128::from(self.initial_count))
+            / u128::from(self.frequency);
+
+        Duration::from_nanos(u64::try_from(nanos).unwrap())
+    }
+
+    /// Programs the local APIC timer with this configuration, which starts it.
+    pub fn apply(&self) {
+        LocalApic::set_timer_divide_configuration(self.divide);
+        // Loading the initial count starts the timer, so it must come after the divide configuration.
+        LocalApic::set_timer_initial_count(self.initial_count);
+    }
+}
+
 pub enum LocalTimer {
     TimestampCounter { frequency: u64 },
     LocalApic { frequency: u32 },
@@ -77,27 +138,12 @@ impl LocalTimer {
         {
             trace!("Local Timer: Timestamp Counter");
 
-            x2Apic::lvt_timer().set_mode(TimerMode::TscDeadline);
+            LocalApic::lvt_timer().set_mode(TimerMode::TscDeadline);
 
-            // Notably, on AMD systems the first check simply won't work, becuase AMD is cursed and Lisa Su is
-            // continuing AMD's time-honored tradition of making their CPUs 10x more difficult to program for than Intel.
-            let frequency = processor_frequency_info()
-                .map(|processor_frequency_info| {
-                    // We read the processor frequency information directly from the CPU, to do the math to make it useful.
-                    u64::from(processor_frequency_info.bus_frequency())
-                        / (u64::from(processor_frequency_info.processor_base_frequency())
-                            * u64::from(processor_frequency_info.processor_max_frequency()))
-                })
-                .or_else(|| {
-                    // We're in a hypervisor environment and it provides the 0x40000000 and 0x40000010 hypervisor info leaves.
-                    feature_info()
-                        .is_some_and(FeatureInfo::has_hypervisor)
-                        .then(|| hypervisor_info())
-                        .flatten()
-                        .and_then(HypervisorInfo::tsc_frequency)
-                        .map(u64::from)
-                })
-                .unwrap_or_else(measure_tsc);
+            // Ensure the mode switch is observed before any writes to `IA32_TSC_DEADLINE`.
+            core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
+
+            let frequency = tsc_frequency();
 
             LocalTimer::TimestampCounter { frequency }
         } else {
@@ -105,39 +151,50 @@ impl LocalTimer {
 
             trace!("Local Timer: APIC (one-shot)");
 
-            x2Apic::lvt_timer().set_mode(TimerMode::OneShot);
+            LocalApic::lvt_timer().set_mode(TimerMode::OneShot);
 
+            // Hypervisors report the APIC bus frequency in kHz.
             let frequency = hypervisor_info()
                 .and_then(raw_cpuid::HypervisorInfo::apic_frequency)
+                .and_then(|frequency_khz| frequency_khz.checked_mul(1000))
                 .unwrap_or_else(measure_lapic);
 
             LocalTimer::LocalApic { frequency }
         }
     }
 
+    /// Arms the timer to fire once, after `duration` has elapsed.
     pub fn set_wait(&self, duration: Duration) -> Result<(), Error> {
         match self {
             Self::TimestampCounter { frequency } => {
-                let wait_us =
-                    u64::try_from(duration.as_micros()).map_err(|_| Error::InvalidWait)?;
-                let wait_ticks = (frequency / 1_000_000)
-                    .checked_mul(wait_us)
+                let wait_ticks = u64::try_from(
+                    (duration.as_nanos() * u128::from(*frequency)) / Duration::SECOND.as_nanos(),
+                )
+                .map_err(|_| Error::InvalidWait)?;
+
+                // Safety: Processor has TSC capability.
+                let deadline_tsc = unsafe { _rdtsc() }
+                    .checked_add(wait_ticks)
                     .ok_or(Error::InvalidWait)?;
 
-                IA32_TSC_DEADLINE::set(wait_ticks);
+                // A deadline of `0` disarms the timer, so ensure the deadline is always nonzero.
+                IA32_TSC_DEADLINE::set(deadline_tsc.max(1));
             }
 
             Self::LocalApic { frequency } => {
-                let wait_us =
-                    u32::try_from(duration.as_micros()).map_err(|_| Error::InvalidWait)?;
-                let wait_ticks = (frequency / 1_000_000)
-                    .checked_mul(wait_us)
-                    .ok_or(Error::InvalidWait)?;
-
-                x2Apic::set_timer_initial_count(wait_ticks);
+                ApicTimerConfiguration::for_period(*frequency, duration)?.apply();
             }
         }
 
         Ok(())
     }
+
+    /// Selects the local APIC timer configuration which expires after `period`, validated against the calibrated
+    /// timer frequency.
+    pub fn apic_configuration(&self, period: Duration) -> Result<ApicTimerConfiguration, Error> {
+        match self {
+            Self::TimestampCounter { frequency: _ } => Err(Error::NotApicTimer),
+            Self::LocalApic { frequency } => ApicTimerConfiguration::for_period(*frequency, period),
+        }
+    }
 }
@@ -1,8 +1,31 @@
-// mod system_clock;
-// pub use system_clock::*;
+mod system_clock;
+pub use system_clock::*;
 
 mod stopwatch;
 pub use stopwatch::*;
 
 mod local_timer;
 pub use local_timer::*;
+
+pub mod hpet;
+pub mod timers;
+pub mod vdso;
+
+pub mod rtc;
+pub use rtc::realtime_ns;
+
+#[cfg(feature = "time_warp")]
+pub mod warp;
+
+/// Spin waits for the provided [`Duration`], using the most precise clock source available.
+///
+/// # Remarks
+///
+/// This is intended for calibrating other timer sources against a known frequency.
+pub fn calibration_spin_wait(duration: core::time::Duration) {
+    if hpet::Hpet::is_available() {
+        hpet::Hpet::spin_wait(duration);
+    } else {
+        Stopwatch::spin_wait(duration);
+    }
+}
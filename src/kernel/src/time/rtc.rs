@@ -0,0 +1,216 @@
+use crate::sync::SpinMutex;
+use core::sync::atomic::{AtomicU64, Ordering};
+use ioports::{ReadOnlyPort, WriteOnlyPort};
+
+const INDEX_PORT: u16 = 0x70;
+const DATA_PORT: u16 = 0x71;
+
+#[repr(u8)]
+#[derive(Debug, IntoPrimitive, Clone, Copy)]
+#[allow(non_camel_case_types)]
+#[rustfmt::skip]
+enum Register {
+    SECONDS     = 0x00,
+    MINUTES     = 0x02,
+    HOURS       = 0x04,
+    DAY         = 0x07,
+    MONTH       = 0x08,
+    YEAR        = 0x09,
+    STATUS_A    = 0x0A,
+    STATUS_B    = 0x0B,
+}
+
+bitflags! {
+    #[repr(transparent)]
+    #[derive(Debug, Clone, Copy)]
+    struct StatusB: u8 {
+        /// Hours are reported in 24-hour format, rather than 12-hour format.
+        const HOUR_24 = 1 << 1;
+        /// Values are reported in binary, rather than binary-coded decimal.
+        const BINARY = 1 << 2;
+    }
+}
+
+/// Bit set in status register A while the RTC is updating its registers.
+const UPDATE_IN_PROGRESS: u8 = 1 << 7;
+
+/// Bit set in the hours register (in 12-hour format) for post-meridiem hours.
+const HOUR_PM: u8 = 1 << 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DateTime {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+    century: u8,
+}
+
+impl DateTime {
+    /// Seconds elapsed since the Unix epoch (1970-01-01T00:00:00Z).
+    fn unix_seconds(&self) -> u64 {
+        let year = i64::from(self.century) * 100 + i64::from(self.year);
+        let month = i64::from(self.month);
+        let day = i64::from(self.day);
+
+        // Days from the civil calendar, per Howard Hinnant's algorithm.
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+
+        let seconds = days * 86_400
+            + i64::from(self.hours) * 3600
+            + i64::from(self.minutes) * 60
+            + i64::from(self.seconds);
+
+        u64::try_from(seconds).unwrap_or(0)
+    }
+}
+
+fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0xF)
+}
+
+crate::singleton! {
+    /// The CMOS real-time clock, used as the source of wall-clock time.
+    pub Rtc {
+        ports: SpinMutex<(WriteOnlyPort<u8>, ReadOnlyPort<u8>)>,
+        /// CMOS register holding the century, as reported by the FADT (if any).
+        century_register: Option<u8>,
+    }
+
+    fn init() {
+        let century_register = crate::acpi::Tables::find_table::<acpi::fadt::Fadt>()
+            .ok()
+            .map(|fadt| fadt.century)
+            .filter(|century_register| *century_register != 0);
+
+        trace!("RTC century register: {century_register:#X?}");
+
+        Self {
+            // Safety: The CMOS index & data ports are fixed by the platform.
+            ports: SpinMutex::new(unsafe { (WriteOnlyPort::new(INDEX_PORT), ReadOnlyPort::new(DATA_PORT)) }),
+            century_register,
+        }
+    }
+}
+
+// Safety: Ports are only accessed behind a lock.
+unsafe impl Send for Rtc {}
+// Safety: Ports are only accessed behind a lock.
+unsafe impl Sync for Rtc {}
+
+/// Wall-clock time (in nanoseconds since the Unix epoch) at which the system clock started.
+static REALTIME_OFFSET_NS: AtomicU64 = AtomicU64::new(0);
+
+impl Rtc {
+    fn read_register(ports: &mut (WriteOnlyPort<u8>, ReadOnlyPort<u8>), register: u8) -> u8 {
+        // The NMI-disable bit (bit 7) is left clear, as NMIs are used to halt hardware threads on panic.
+        ports.0.write(register);
+        ports.1.read()
+    }
+
+    fn read_date_time(&self, ports: &mut (WriteOnlyPort<u8>, ReadOnlyPort<u8>)) -> DateTime {
+        while Self::read_register(ports, Register::STATUS_A.into()) & UPDATE_IN_PROGRESS > 0 {
+            core::hint::spin_loop();
+        }
+
+        DateTime {
+            seconds: Self::read_register(ports, Register::SECONDS.into()),
+            minutes: Self::read_register(ports, Register::MINUTES.into()),
+            hours: Self::read_register(ports, Register::HOURS.into()),
+            day: Self::read_register(ports, Register::DAY.into()),
+            month: Self::read_register(ports, Register::MONTH.into()),
+            year: Self::read_register(ports, Register::YEAR.into()),
+            century: self.century_register.map_or(0, |century_register| {
+                Self::read_register(ports, century_register)
+            }),
+        }
+    }
+
+    /// Reads the current wall-clock time, in seconds since the Unix epoch.
+    pub fn unix_seconds() -> u64 {
+        let rtc = Self::get_static();
+
+        let (mut date_time, status_b) = crate::interrupts::uninterruptable(|| {
+            let mut ports = rtc.ports.lock();
+
+            // Read until two consecutive reads agree, so an update can't tear the values.
+            let mut date_time = rtc.read_date_time(&mut ports);
+            loop {
+                let next_date_time = rtc.read_date_time(&mut ports);
+                if next_date_time == date_time {
+                    break;
+                }
+
+                date_time = next_date_time;
+            }
+
+            let status_b = StatusB::from_bits_retain(Self::read_register(
+                &mut ports,
+                Register::STATUS_B.into(),
+            ));
+
+            (date_time, status_b)
+        });
+
+        let is_pm = (date_time.hours & HOUR_PM) > 0;
+        date_time.hours &= !HOUR_PM;
+
+        if !status_b.contains(StatusB::BINARY) {
+            date_time.seconds = from_bcd(date_time.seconds);
+            date_time.minutes = from_bcd(date_time.minutes);
+            date_time.hours = from_bcd(date_time.hours);
+            date_time.day = from_bcd(date_time.day);
+            date_time.month = from_bcd(date_time.month);
+            date_time.year = from_bcd(date_time.year);
+            date_time.century = from_bcd(date_time.century);
+        }
+
+        if !status_b.contains(StatusB::HOUR_24) {
+            // 12-hour format reports midnight as 12AM, and noon as 12PM.
+            date_time.hours %= 12;
+
+            if is_pm {
+                date_time.hours += 12;
+            }
+        }
+
+        if date_time.century == 0 {
+            // ... the platform has no century register, so assume the 21st century ...
+            date_time.century = 20;
+        }
+
+        date_time.unix_seconds()
+    }
+
+    /// Anchors the system clock to the current wall-clock time.
+    pub fn synchronize() {
+        let unix_ns = Self::unix_seconds().saturating_mul(1_000_000_000);
+        let offset_ns = unix_ns.saturating_sub(super::now_ns());
+
+        REALTIME_OFFSET_NS.store(offset_ns, Ordering::Release);
+        super::vdso::publish();
+
+        debug!("Real-time clock synchronized: {unix_ns}ns since epoch");
+    }
+}
+
+/// Wall-clock time, in nanoseconds since the Unix epoch.
+///
+/// # Remarks
+///
+/// Until the RTC has been synchronized, this reports time since the system clock started.
+pub fn realtime_ns() -> u64 {
+    realtime_offset_ns().saturating_add(super::now_ns())
+}
+
+/// Offset (in nanoseconds) of wall-clock time from the system clock.
+pub fn realtime_offset_ns() -> u64 {
+    REALTIME_OFFSET_NS.load(Ordering::Acquire)
+}
@@ -1,6 +1,11 @@
 #![allow(clippy::similar_names)]
 
-use core::{num::NonZero, ptr::NonNull, time::Duration};
+use core::{
+    num::NonZero,
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 use ioports::ReadOnlyPort;
 use safe_mmio::{UniqueMmioPointer, fields::ReadPure};
 
@@ -49,10 +54,13 @@ crate::singleton! {
         ticks_per_sec: u64,
         ticks_per_ms: u64,
         ticks_per_us: u64,
+
+        /// Last observed (extended) counter value, used to extend the counter to 64 bits.
+        last_count: AtomicU64,
     }
 
-    fn init(rsdp_request: &limine::request::RsdpRequest) {
-        if let Ok(acpi_root_table) = crate::acpi::get_root_table(rsdp_request)
+    fn init() {
+        if let Ok(acpi_root_table) = crate::acpi::Tables::root()
             && let Ok(acpi_platform_info) = acpi_root_table.platform_info()
             && let Some(pm_timer) = acpi_platform_info.pm_timer
         {
@@ -82,6 +90,7 @@ crate::singleton! {
                         ticks_per_sec: 3579545,
                         ticks_per_ms: 3579545 / 1000,
                         ticks_per_us: 3579545 / 1000 / 1000,
+                        last_count: AtomicU64::new(0),
                     }
                 }
 
@@ -111,6 +120,7 @@ crate::singleton! {
                         ticks_per_sec: 3579545,
                         ticks_per_ms: 3579545 / 1000,
                         ticks_per_us: 3579545 / 1000 / 1000,
+                        last_count: AtomicU64::new(0),
                     }
                 }
 
@@ -128,6 +138,45 @@ unsafe impl Send for Stopwatch {}
 unsafe impl Sync for Stopwatch {}
 
 impl Stopwatch {
+    /// Frequency (in Hz) of the underlying counter.
+    pub fn frequency() -> u64 {
+        Self::get_static().ticks_per_sec
+    }
+
+    /// Reads the underlying counter, extended to a monotonic 64-bit value.
+    ///
+    /// # Remarks
+    ///
+    /// Overflows are tracked in software, so this must be called at least once per half overflow
+    /// period (~2.3 seconds for a 24-bit counter) for the returned value to remain monotonic.
+    pub fn counter() -> u64 {
+        let stopwatch = Self::get_static();
+        let max_value = stopwatch.source.max_value();
+        let count = stopwatch.source.read() & max_value;
+
+        let mut last_count = stopwatch.last_count.load(Ordering::Acquire);
+        loop {
+            let delta = count.wrapping_sub(last_count & max_value) & max_value;
+            if delta > max_value / 2 {
+                // ... the count was read before another hardware thread published a later one, so it's stale rather
+                // than having wrapped ...
+                return last_count;
+            }
+
+            let extended_count = last_count + delta;
+
+            match stopwatch.last_count.compare_exchange_weak(
+                last_count,
+                extended_count,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return extended_count,
+                Err(observed_count) => last_count = observed_count,
+            }
+        }
+    }
+
     /// Spin waits for the provided [`Duration`].
     ///
     /// # Remarks
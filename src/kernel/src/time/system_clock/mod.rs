@@ -1,21 +1,89 @@
-use crate::interrupts::uninterruptable;
-use spin::Once;
-
 #[cfg(target_arch = "x86_64")]
 mod x86_64;
 #[cfg(target_arch = "x86_64")]
-use x86_64::*;
+pub use x86_64::*;
+
+use spin::Once;
 
 static SYSTEM_CLOCK: Once<SystemClock> = Once::new();
 
-struct SystemClock(ArchClock);
+/// The monotonic system clock, backed by the most precise [`ClockSource`] available.
+pub struct SystemClock {
+    source: ClockSource,
+    frequency: u64,
+    base_ticks: u64,
+}
 
 impl SystemClock {
+    /// Selects the best available [`ClockSource`], and starts the system clock from it.
+    ///
+    /// # Remarks
+    ///
+    /// This should be called after all of the calibration clock sources have been initialized.
     pub fn init() {
-        SYSTEM_CLOCK.call_once(|| uninterruptable(|| Self(ArchClock::configure())));
+        SYSTEM_CLOCK.call_once(|| {
+            trace!("Initializing `SystemClock`...");
+
+            let source = ClockSource::select();
+            let frequency = source.frequency();
+
+            info!("System clock source: {source:?} @ {frequency}Hz");
+
+            Self {
+                source,
+                frequency,
+                base_ticks: source.ticks(),
+            }
+        });
+    }
+
+    /// Whether the system clock has been initialized.
+    pub fn is_initialized() -> bool {
+        SYSTEM_CLOCK.get().is_some()
     }
 
-    fn get_static() -> &'static Self {
-        SYSTEM_CLOCK.wait()
+    /// The [`ClockSource`] backing the system clock, if it has been initialized.
+    pub fn source() -> Option<ClockSource> {
+        SYSTEM_CLOCK.get().map(|system_clock| system_clock.source)
+    }
+
+    /// Nanoseconds elapsed since the system clock was initialized.
+    ///
+    /// # Remarks
+    ///
+    /// If the system clock is not yet initialized, this returns `0`.
+    pub fn now_ns() -> u64 {
+        SYSTEM_CLOCK.get().map_or(0, |system_clock| {
+            let elapsed_ticks = system_clock
+                .source
+                .ticks()
+                .saturating_sub(system_clock.base_ticks);
+
+            u64::try_from(
+                (u128::from(elapsed_ticks) * 1_000_000_000) / u128::from(system_clock.frequency),
+            )
+            .unwrap_or(u64::MAX)
+        })
+    }
+}
+
+/// Nanoseconds elapsed since the system clock was initialized.
+pub fn now_ns() -> u64 {
+    SystemClock::now_ns()
+}
+
+crate::kernel_test! {
+    /// The system clock advances, and never goes backwards.
+    fn monotonic() -> crate::tests::Outcome {
+        let start = now_ns();
+        while now_ns() == start {
+            core::hint::spin_loop();
+        }
+
+        if now_ns() < start {
+            return Err("clock went backwards");
+        }
+
+        Ok(())
     }
 }
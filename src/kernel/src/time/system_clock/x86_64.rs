@@ -1,15 +1,133 @@
-enum ClockSource {
+use crate::{
+    arch::x86_64::cpuid::{
+        advanced_power_management_info, feature_info, hypervisor_info, tsc_info,
+    },
+    time::{Stopwatch, calibration_spin_wait, hpet::Hpet},
+};
+use core::{arch::x86_64::_rdtsc, time::Duration};
+use raw_cpuid::{ApmInfo, FeatureInfo, HypervisorInfo, TscInfo};
+use spin::Once;
+
+/// Duration to measure the timestamp counter against the calibration clock source.
+const MEASUREMENT_DURATION: Duration = Duration::from_millis(50);
+
+/// Counter sources the system clock is able to be driven by, in order of preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockSource {
+    /// The invariant timestamp counter.
+    Tsc,
+    /// The high-precision event timer.
     Hpet,
-    Rtc,
+    /// The ACPI power management timer.
+    AcpiPm,
+}
+
+impl ClockSource {
+    /// Selects the most precise clock source available on this platform.
+    pub fn select() -> Self {
+        if has_invariant_tsc() {
+            Self::Tsc
+        } else if Hpet::is_available() {
+            Self::Hpet
+        } else {
+            Self::AcpiPm
+        }
+    }
+
+    /// Frequency (in Hz) of the clock source's counter.
+    pub fn frequency(self) -> u64 {
+        match self {
+            Self::Tsc => tsc_frequency(),
+            Self::Hpet => Hpet::frequency(),
+            Self::AcpiPm => Stopwatch::frequency(),
+        }
+    }
+
+    /// Reads the clock source's counter.
+    pub fn ticks(self) -> u64 {
+        match self {
+            // Safety: Processor has TSC capability.
+            Self::Tsc => unsafe { _rdtsc() },
+            Self::Hpet => Hpet::counter(),
+            Self::AcpiPm => Stopwatch::counter(),
+        }
+    }
 }
 
-pub struct ArchClock {
-    frequency: u64,
-    source: ClockSource,
+/// Whether the timestamp counter is present, and ticks at a constant rate regardless of power state.
+pub fn has_invariant_tsc() -> bool {
+    feature_info().is_some_and(FeatureInfo::has_tsc)
+        && advanced_power_management_info().is_some_and(ApmInfo::has_invariant_tsc)
+}
+
+/// Frequency (in Hz) of the timestamp counter.
+///
+/// # Remarks
+///
+/// The frequency is read from CPUID if it is advertised, and otherwise calibrated against the
+/// calibration clock source. In either case, it is only determined once.
+pub fn tsc_frequency() -> u64 {
+    static TSC_FREQUENCY: Once<u64> = Once::new();
+
+    *TSC_FREQUENCY.call_once(|| {
+        tsc_info()
+            .and_then(TscInfo::tsc_frequency)
+            .or_else(|| {
+                // We're in a hypervisor environment and it provides the 0x40000000 and 0x40000010 hypervisor info leaves.
+                feature_info()
+                    .is_some_and(FeatureInfo::has_hypervisor)
+                    .then(|| hypervisor_info())
+                    .flatten()
+                    .and_then(HypervisorInfo::tsc_frequency)
+                    .map(|frequency_khz| u64::from(frequency_khz) * 1000)
+            })
+            .unwrap_or_else(measure_tsc)
+    })
 }
 
-impl ArchClock {
-    pub fn configure() -> Self {
-        todo!()
+fn measure_tsc() -> u64 {
+    trace!("Measuring the timestamp counter frequency...");
+
+    // Safety: Processor has TSC capability.
+    let start_tsc = unsafe { _rdtsc() };
+    calibration_spin_wait(MEASUREMENT_DURATION);
+    // Safety: Processor has TSC capability.
+    let end_tsc = unsafe { _rdtsc() };
+
+    let elapsed_ticks = end_tsc - start_tsc;
+    let frequency = u64::try_from(
+        (u128::from(elapsed_ticks) * Duration::SECOND.as_micros())
+            / MEASUREMENT_DURATION.as_micros(),
+    )
+    .unwrap_or(u64::MAX);
+
+    trace!("Timestamp counter frequency: {frequency}Hz");
+
+    frequency
+}
+
+crate::kernel_test! {
+    /// The timestamp counter's frequency agrees (within 5%) with a fresh measurement against the calibration clock.
+    fn tsc_calibration() -> crate::tests::Outcome {
+        const DURATION: Duration = Duration::from_millis(10);
+
+        let expected = tsc_frequency();
+
+        // Safety: Processor has TSC capability.
+        let start_tsc = unsafe { _rdtsc() };
+        calibration_spin_wait(DURATION);
+        // Safety: Processor has TSC capability.
+        let end_tsc = unsafe { _rdtsc() };
+
+        let measured = u64::try_from(
+            (u128::from(end_tsc - start_tsc) * Duration::SECOND.as_micros()) / DURATION.as_micros(),
+        )
+        .unwrap_or(u64::MAX);
+
+        if measured.abs_diff(expected) > expected / 20 {
+            return Err("timestamp counter frequency disagrees with the calibration clock");
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,343 @@
+use crate::cpu::local_state::LocalState;
+#[cfg(feature = "time_warp")]
+use crate::time::warp::TimeGroup;
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Resolution of the timer wheel.
+const TICK_NS: u64 = 1_000_000;
+
+/// Number of bits of the expiration tick each level of the wheel is indexed by.
+const LEVEL_BITS: usize = 6;
+const SLOTS: usize = 1 << LEVEL_BITS;
+const SLOT_MASK: u64 = (1 << LEVEL_BITS) - 1;
+const LEVELS: usize = 4;
+
+/// Furthest into the future (in ticks) a timer can be placed without being cascaded from the top level.
+const MAX_DELTA_TICKS: u64 = (1 << (LEVEL_BITS * LEVELS)) - 1;
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+/// Identifies a pending timer, so that it can be cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerId(u64);
+
+impl TimerId {
+    /// Allocates a new, unique timer ID.
+    pub fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Default for TimerId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Timer {
+    id: TimerId,
+    expires_tick: u64,
+    callback: Callback,
+}
+
+/// Timer which expires once the clock observed by its group (see [`crate::time::warp`]) reaches its deadline.
+#[cfg(feature = "time_warp")]
+struct WarpedTimer {
+    id: TimerId,
+    group: TimeGroup,
+    deadline_ns: u64,
+    callback: Callback,
+}
+
+/// Hierarchical timer wheel, with [`SLOTS`] slots per level.
+///
+/// Each level's slots are [`SLOTS`] times wider than the level below it. Timers are placed on
+/// the lowest level that can represent their expiration, and cascaded down a level as the wheel
+/// turns, so insertion and expiration are both constant time.
+pub struct TimerWheel {
+    current_tick: u64,
+    levels: [[Vec<Timer>; SLOTS]; LEVELS],
+    len: usize,
+    /// Timers on warped clocks, which may be advanced at any time, so they're checked on every turn rather than being
+    /// placed in a slot.
+    #[cfg(feature = "time_warp")]
+    warped: Vec<WarpedTimer>,
+}
+
+fn now_tick() -> u64 {
+    crate::time::now_ns() / TICK_NS
+}
+
+impl TimerWheel {
+    pub fn new() -> Self {
+        Self {
+            current_tick: now_tick(),
+            levels: core::array::from_fn(|_| core::array::from_fn(|_| Vec::new())),
+            len: 0,
+            #[cfg(feature = "time_warp")]
+            warped: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, timer: Timer) {
+        let delta = timer
+            .expires_tick
+            .saturating_sub(self.current_tick)
+            .min(MAX_DELTA_TICKS);
+        let level = (0..LEVELS)
+            .find(|level| delta < (1 << (LEVEL_BITS * (level + 1))))
+            .unwrap_or(LEVELS - 1);
+        let placement_tick = self.current_tick + delta;
+        let slot = usize::try_from((placement_tick >> (LEVEL_BITS * level)) & SLOT_MASK).unwrap();
+
+        self.levels[level][slot].push(timer);
+    }
+
+    /// Adds a timer to the wheel, which will invoke `callback` on expiry.
+    pub fn schedule(&mut self, id: TimerId, expires_tick: u64, callback: Callback) {
+        // Timers can't expire on the tick currently being processed, so defer them to the next one.
+        let expires_tick = expires_tick.max(self.current_tick + 1);
+
+        self.insert(Timer {
+            id,
+            expires_tick,
+            callback,
+        });
+        self.len += 1;
+    }
+
+    /// Adds a timer to the wheel, which will invoke `callback` once the clock observed by `group` reaches
+    /// `deadline_ns`.
+    #[cfg(feature = "time_warp")]
+    pub fn schedule_in(
+        &mut self,
+        id: TimerId,
+        group: TimeGroup,
+        deadline_ns: u64,
+        callback: Callback,
+    ) {
+        self.warped.push(WarpedTimer {
+            id,
+            group,
+            deadline_ns,
+            callback,
+        });
+    }
+
+    /// Removes a pending timer from the wheel, returning whether it was found.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        #[cfg(feature = "time_warp")]
+        if let Some(index) = self.warped.iter().position(|timer| timer.id == id) {
+            self.warped.swap_remove(index);
+
+            return true;
+        }
+
+        for slot in self.levels.iter_mut().flatten() {
+            if let Some(index) = slot.iter().position(|timer| timer.id == id) {
+                slot.swap_remove(index);
+                self.len -= 1;
+
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Turns the wheel up to `now_tick`, collecting the callbacks of any expired timers.
+    fn advance(&mut self, now_tick: u64, expired: &mut Vec<Callback>) {
+        #[cfg(feature = "time_warp")]
+        expired.extend(
+            self.warped
+                .extract_if(.., |timer| {
+                    crate::time::warp::now_ns_for(timer.group) >= timer.deadline_ns
+                })
+                .map(|timer| timer.callback),
+        );
+
+        if self.len == 0 {
+            self.current_tick = self.current_tick.max(now_tick);
+            return;
+        }
+
+        while self.current_tick < now_tick {
+            self.current_tick += 1;
+
+            // Cascade from the highest level down, so timers are never cascaded into an already-processed slot.
+            for level in (1..LEVELS).rev() {
+                let level_shift = LEVEL_BITS * level;
+                if self.current_tick & ((1 << level_shift) - 1) == 0 {
+                    let slot =
+                        usize::try_from((self.current_tick >> level_shift) & SLOT_MASK).unwrap();
+                    let timers = core::mem::take(&mut self.levels[level][slot]);
+
+                    timers.into_iter().for_each(|timer| self.insert(timer));
+                }
+            }
+
+            let slot = usize::try_from(self.current_tick & SLOT_MASK).unwrap();
+            for timer in core::mem::take(&mut self.levels[0][slot]) {
+                if timer.expires_tick <= self.current_tick {
+                    expired.push(timer.callback);
+                    self.len -= 1;
+                } else {
+                    // ... the timer was clamped to the top level, and still has time remaining ...
+                    self.insert(timer);
+                }
+            }
+
+            if self.len == 0 {
+                self.current_tick = now_tick;
+            }
+        }
+    }
+
+    /// System clock time (in nanoseconds) of the earliest pending expiration, if any.
+    pub fn next_expiry_ns(&self) -> Option<u64> {
+        let next_expiry_ns = self
+            .levels
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|timer| timer.expires_tick)
+            .min()
+            .map(|expires_tick| expires_tick.saturating_mul(TICK_NS));
+
+        #[cfg(feature = "time_warp")]
+        let next_expiry_ns = {
+            let now_ns = crate::time::now_ns();
+
+            self.warped
+                .iter()
+                .map(|timer| {
+                    now_ns.saturating_add(crate::time::warp::real_ns_until(
+                        timer.group,
+                        timer.deadline_ns,
+                    ))
+                })
+                .chain(next_expiry_ns)
+                .min()
+        };
+
+        next_expiry_ns
+    }
+}
+
+impl Default for TimerWheel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Schedules `callback` to be run on the current hardware thread after `duration` has elapsed.
+///
+/// # Remarks
+///
+/// `callback` is run from the timer interrupt, so it must not block.
+pub fn schedule_after(duration: Duration, callback: impl FnOnce() + Send + 'static) -> TimerId {
+    let id = TimerId::new();
+    schedule_after_as(id, duration, callback);
+
+    id
+}
+
+/// Schedules `callback` as in [`schedule_after`], using a pre-allocated `id`.
+pub fn schedule_after_as(
+    id: TimerId,
+    duration: Duration,
+    callback: impl FnOnce() + Send + 'static,
+) {
+    let duration_ticks =
+        u64::try_from(duration.as_nanos().div_ceil(u128::from(TICK_NS))).unwrap_or(u64::MAX);
+    let expires_tick = now_tick().saturating_add(duration_ticks);
+
+    LocalState::with_timers(|timers| timers.schedule(id, expires_tick, Box::new(callback)));
+}
+
+/// Schedules `callback` as in [`schedule_after`], but after `duration` has elapsed on the clock observed by `group`.
+#[cfg(feature = "time_warp")]
+pub fn schedule_after_in(
+    group: TimeGroup,
+    duration: Duration,
+    callback: impl FnOnce() + Send + 'static,
+) -> TimerId {
+    if group == TimeGroup::REAL {
+        return schedule_after(duration, callback);
+    }
+
+    let id = TimerId::new();
+    let deadline_ns = crate::time::warp::now_ns_for(group)
+        .saturating_add(u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX));
+
+    LocalState::with_timers(|timers| {
+        timers.schedule_in(id, group, deadline_ns, Box::new(callback))
+    });
+
+    id
+}
+
+/// Cancels a pending timer on the current hardware thread, returning whether it was found.
+pub fn cancel(id: TimerId) -> bool {
+    LocalState::with_timers(|timers| timers.cancel(id))
+}
+
+/// Runs the callbacks of every expired timer on the current hardware thread.
+pub fn process_expired() {
+    let mut expired = Vec::new();
+    LocalState::with_timers(|timers| timers.advance(now_tick(), &mut expired));
+
+    // Callbacks are run without the wheel locked, so they're free to schedule further timers.
+    expired.into_iter().for_each(|callback| callback());
+}
+
+#[cfg(feature = "time_warp")]
+crate::kernel_test! {
+    /// Timers on a warped clock expire once their group's clock passes them, without waiting for it in real time.
+    fn expires_on_warped_clock() -> crate::tests::Outcome {
+        use alloc::sync::Arc;
+        use core::sync::atomic::AtomicBool;
+
+        let task_id = uuid::Uuid::new_v4();
+        crate::time::warp::set_group(task_id, TimeGroup(u32::MAX));
+        let group = crate::time::warp::group_of(task_id);
+
+        let fired = Arc::new(AtomicBool::new(false));
+        let mut wheel = TimerWheel::new();
+        wheel.schedule_in(
+            TimerId::new(),
+            group,
+            crate::time::warp::now_ns_for(group) + 60_000_000_000,
+            Box::new({
+                let fired = Arc::clone(&fired);
+                move || fired.store(true, Ordering::Relaxed)
+            }),
+        );
+
+        let mut expired = Vec::new();
+        wheel.advance(now_tick(), &mut expired);
+        let expired_early = !expired.is_empty();
+
+        crate::time::warp::advance(group, Duration::from_secs(60));
+        wheel.advance(now_tick(), &mut expired);
+
+        crate::time::warp::reset(group);
+        crate::time::warp::on_exit(task_id);
+        expired.into_iter().for_each(|callback| callback());
+
+        if expired_early {
+            return Err("timer expired before its group's clock was advanced");
+        }
+        if !fired.load(Ordering::Relaxed) {
+            return Err("timer didn't expire once its group's clock was advanced");
+        }
+
+        Ok(())
+    }
+}
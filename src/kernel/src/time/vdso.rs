@@ -0,0 +1,147 @@
+//! Time published by the timer tick, for readers which must never block.
+//!
+//! The published time lives in its own page, which is mapped read-only into every task at [`user_address`], so
+//! userspace can read the time without a system call. It's guarded by a sequence lock: the sequence number is odd
+//! while an update is in progress, so readers retry until they observe the same even sequence number before and
+//! after reading.
+
+use crate::mem::{HigherHalfDirectMap, pmm::PhysicalMemoryManager};
+use core::sync::atomic::{AtomicU64, Ordering, fence};
+use libsys::{Address, Frame, Page, page_size};
+use spin::Once;
+
+/// Userspace address the time page is mapped at: the last page below the top of userspace.
+pub fn user_address() -> Address<Page> {
+    Address::new_truncate(crate::task::DEFAULT_USERSPACE_SIZE.get() - page_size())
+}
+
+/// Layout of the time page, as read by userspace.
+#[repr(C)]
+pub struct TimePage {
+    /// Sequence lock; odd while an update is in progress.
+    pub sequence: AtomicU64,
+    /// Monotonic time (in nanoseconds) at publication.
+    pub monotonic_ns: AtomicU64,
+    /// Wall-clock time (in nanoseconds since the Unix epoch) at publication.
+    pub realtime_ns: AtomicU64,
+    /// System clock counter value at publication.
+    pub ticks: AtomicU64,
+    /// Frequency (in Hz) of the system clock counter.
+    pub frequency: AtomicU64,
+    /// System clock source; `0` for the timestamp counter, which userspace may read directly to extrapolate.
+    pub source: AtomicU64,
+}
+
+/// A consistent pair of monotonic & wall-clock times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot {
+    pub monotonic_ns: u64,
+    pub realtime_ns: u64,
+}
+
+static TIME_PAGE: Once<(Address<Frame>, &'static TimePage)> = Once::new();
+
+/// Allocates the time page, and publishes the current time into it.
+///
+/// # Remarks
+///
+/// This should be called after the system clock has been initialized.
+pub fn init() {
+    TIME_PAGE.call_once(|| {
+        let frame = PhysicalMemoryManager::next_frame().expect("no frame for the time page");
+        let page = HigherHalfDirectMap::frame_to_page(frame);
+
+        // Safety: The frame was just allocated, and is accessed only through the HHDM from here on.
+        let time_page = unsafe {
+            let time_page = page.as_ptr().cast::<TimePage>();
+            page.as_ptr().write_bytes(0, page_size());
+
+            &*time_page
+        };
+
+        (frame, time_page)
+    });
+
+    publish();
+}
+
+/// Frame backing the time page, if it has been initialized.
+pub fn frame() -> Option<Address<Frame>> {
+    TIME_PAGE.get().map(|(frame, _)| *frame)
+}
+
+/// Publishes the current time.
+///
+/// # Remarks
+///
+/// Every hardware thread's timer tick publishes, so if another is mid-update, this returns without waiting:
+/// the time it's publishing is just as recent.
+pub fn publish() {
+    let Some((_, time_page)) = TIME_PAGE.get() else {
+        return;
+    };
+
+    let sequence = time_page.sequence.load(Ordering::Relaxed);
+    if !sequence.is_multiple_of(2)
+        || time_page
+            .sequence
+            .compare_exchange(sequence, sequence + 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+    {
+        return;
+    }
+
+    fence(Ordering::Release);
+
+    let source = crate::time::SystemClock::source();
+    let monotonic_ns = crate::time::now_ns();
+    time_page
+        .monotonic_ns
+        .store(monotonic_ns, Ordering::Relaxed);
+    time_page.realtime_ns.store(
+        crate::time::rtc::realtime_offset_ns().saturating_add(monotonic_ns),
+        Ordering::Relaxed,
+    );
+    time_page.ticks.store(
+        source.map_or(0, crate::time::ClockSource::ticks),
+        Ordering::Relaxed,
+    );
+    time_page.frequency.store(
+        source.map_or(0, crate::time::ClockSource::frequency),
+        Ordering::Relaxed,
+    );
+    time_page.source.store(
+        match source {
+            Some(crate::time::ClockSource::Tsc) => 0,
+            Some(crate::time::ClockSource::Hpet) => 1,
+            Some(crate::time::ClockSource::AcpiPm) => 2,
+            None => u64::MAX,
+        },
+        Ordering::Relaxed,
+    );
+
+    time_page.sequence.store(sequence + 2, Ordering::Release);
+}
+
+/// Reads the most recently published time, or `None` if nothing has been published yet.
+pub fn read() -> Option<Snapshot> {
+    let (_, time_page) = TIME_PAGE.get()?;
+
+    loop {
+        let sequence = time_page.sequence.load(Ordering::Acquire);
+        if !sequence.is_multiple_of(2) {
+            core::hint::spin_loop();
+            continue;
+        }
+
+        let snapshot = Snapshot {
+            monotonic_ns: time_page.monotonic_ns.load(Ordering::Relaxed),
+            realtime_ns: time_page.realtime_ns.load(Ordering::Relaxed),
+        };
+
+        fence(Ordering::Acquire);
+        if time_page.sequence.load(Ordering::Relaxed) == sequence {
+            return Some(snapshot);
+        }
+    }
+}
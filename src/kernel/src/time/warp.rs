@@ -0,0 +1,155 @@
+//! Virtual clock warping, so that timeout logic can be exercised without real multi-second waits.
+//!
+//! Each [`TimeGroup`] observes its own view of the monotonic clock, which may be advanced by
+//! a fixed offset or sped up by an integer factor. Groups without a warp observe the real clock.
+//!
+//! Tasks are in [`TimeGroup::REAL`] unless placed in another with [`set_group`] (threads join their creator's), and
+//! their sleeps are timed against their group's clock (see [`crate::time::timers::schedule_after_in`]).
+
+use crate::{interrupts::InterruptCell, sync::SpinMutex, time::now_ns};
+use alloc::collections::BTreeMap;
+use core::time::Duration;
+use uuid::Uuid;
+
+/// Identifies a set of tasks which share a view of the monotonic clock.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimeGroup(pub u32);
+
+impl TimeGroup {
+    /// The group which always observes the real monotonic clock.
+    pub const REAL: Self = Self(0);
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Warp {
+    /// Real time at which this warp began.
+    anchor_real_ns: u64,
+    /// Warped time at which this warp began.
+    anchor_warped_ns: u64,
+    /// Factor by which real time is scaled after the anchor.
+    scale: u32,
+}
+
+impl Warp {
+    fn warped_ns(&self, real_ns: u64) -> u64 {
+        let elapsed_real_ns = real_ns.saturating_sub(self.anchor_real_ns);
+
+        self.anchor_warped_ns
+            .saturating_add(elapsed_real_ns.saturating_mul(u64::from(self.scale)))
+    }
+}
+
+static WARPS: InterruptCell<SpinMutex<BTreeMap<TimeGroup, Warp>>> =
+    InterruptCell::new(SpinMutex::new(BTreeMap::new()));
+
+/// Group of each task which isn't in [`TimeGroup::REAL`].
+static GROUPS: InterruptCell<SpinMutex<BTreeMap<Uuid, TimeGroup>>> =
+    InterruptCell::new(SpinMutex::new(BTreeMap::new()));
+
+/// Places the task `task_id` in `group`.
+pub fn set_group(task_id: Uuid, group: TimeGroup) {
+    GROUPS.with(|groups| {
+        let mut groups = groups.lock();
+
+        if group == TimeGroup::REAL {
+            groups.remove(&task_id);
+        } else {
+            groups.insert(task_id, group);
+        }
+    });
+}
+
+/// Group the task `task_id` is in.
+pub fn group_of(task_id: Uuid) -> TimeGroup {
+    GROUPS.with(|groups| {
+        groups
+            .lock()
+            .get(&task_id)
+            .copied()
+            .unwrap_or(TimeGroup::REAL)
+    })
+}
+
+/// Removes the task `id` from its group.
+pub fn on_exit(id: Uuid) {
+    set_group(id, TimeGroup::REAL);
+}
+
+fn with_warp(group: TimeGroup, func: impl FnOnce(&mut Warp)) {
+    assert_ne!(group, TimeGroup::REAL, "cannot warp the real clock");
+
+    WARPS.with(|warps| {
+        let mut warps = warps.lock();
+        let real_ns = now_ns();
+
+        let warp = warps.entry(group).or_insert(Warp {
+            anchor_real_ns: real_ns,
+            anchor_warped_ns: real_ns,
+            scale: 1,
+        });
+
+        // Re-anchor at the current time, so the group's clock remains monotonic across changes.
+        warp.anchor_warped_ns = warp.warped_ns(real_ns);
+        warp.anchor_real_ns = real_ns;
+
+        func(warp);
+    });
+}
+
+/// Nanoseconds elapsed on the monotonic clock, as observed by `group`.
+pub fn now_ns_for(group: TimeGroup) -> u64 {
+    let real_ns = now_ns();
+
+    WARPS.with(|warps| {
+        warps
+            .lock()
+            .get(&group)
+            .map_or(real_ns, |warp| warp.warped_ns(real_ns))
+    })
+}
+
+/// Real nanoseconds until the clock observed by `group` reaches `warped_ns`, unless its warp is changed meanwhile.
+pub fn real_ns_until(group: TimeGroup, warped_ns: u64) -> u64 {
+    let real_ns = now_ns();
+
+    WARPS.with(|warps| {
+        warps.lock().get(&group).map_or_else(
+            || warped_ns.saturating_sub(real_ns),
+            |warp| {
+                warped_ns
+                    .saturating_sub(warp.warped_ns(real_ns))
+                    .div_ceil(u64::from(warp.scale))
+            },
+        )
+    })
+}
+
+/// Instantly moves the clock observed by `group` forward by `duration`.
+pub fn advance(group: TimeGroup, duration: Duration) {
+    let duration_ns = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+
+    with_warp(group, |warp| {
+        warp.anchor_warped_ns = warp.anchor_warped_ns.saturating_add(duration_ns);
+    });
+}
+
+/// Makes the clock observed by `group` run `scale` times faster than the real clock.
+///
+/// # Panics
+///
+/// - If `scale` is `0`, as that would stop the clock entirely.
+pub fn set_scale(group: TimeGroup, scale: u32) {
+    assert_ne!(scale, 0, "clock scale must be non-zero");
+
+    with_warp(group, |warp| warp.scale = scale);
+}
+
+/// Removes any warp from `group`, so it once again observes the real clock.
+///
+/// # Remarks
+///
+/// If the group's clock had been advanced, it will appear to jump backwards.
+pub fn reset(group: TimeGroup) {
+    WARPS.with(|warps| warps.lock().remove(&group));
+}
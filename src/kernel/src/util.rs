@@ -30,3 +30,39 @@ impl InteriorRef for Mut {
         r
     }
 }
+
+/// Pads and aligns `T` to the length of a cache line, so it never shares a line with other data.
+///
+/// # Remarks
+///
+/// Shared data which is written frequently (i.e. counters or lock words) should be padded, so writers on different
+/// hardware threads don't contend over a cache line they're each only partly using (false sharing). On x86_64, the
+/// spatial prefetcher fetches lines in adjacent pairs, so padding is to 128 bytes rather than 64.
+#[cfg_attr(target_arch = "x86_64", repr(align(128)))]
+#[cfg_attr(not(target_arch = "x86_64"), repr(align(64)))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CachePadded<T>(T);
+
+impl<T> CachePadded<T> {
+    pub const fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> core::ops::Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> core::ops::DerefMut for CachePadded<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
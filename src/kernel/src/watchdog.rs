@@ -0,0 +1,212 @@
+//! Software watchdog, which reports hardware threads that stop responding.
+//!
+//! Every hardware thread pets its own slot from periodic work driven by its scheduler timer, and checks the slots of
+//! every other hardware thread. A hardware thread which goes quiet for half the threshold is probed with a
+//! [`Vector::Watchdog`] interrupt, which pets it if only its timer is late. One still quiet at the threshold is stuck
+//! (i.e. spinning with interrupts disabled, or deadlocked in an interrupt handler), so its backtrace is captured via
+//! NMI and reported. Each stall is reported once, and a recovery is reported when the hardware thread pets again.
+//!
+//! [`Vector::Watchdog`]: crate::interrupts::Vector::Watchdog
+
+use crate::{cpu::local_state::LocalState, util::CachePadded};
+use core::{
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// How often each hardware thread pets its slot, and checks every other.
+const PET_PERIOD: Duration = Duration::from_millis(100);
+const PET_BUDGET: Duration = Duration::from_millis(1);
+
+/// Most hardware threads watched; any beyond this are neither watched nor watch others.
+const MAX_HWTHREADS: usize = 64;
+
+/// Sentinel for [`Slot::hwthread_id`] before the slot is claimed.
+const UNCLAIMED: u32 = u32::MAX;
+
+crate::parameters! {
+    /// Milliseconds a hardware thread may go without petting the watchdog before it's reported as stuck (0 disables
+    /// the watchdog).
+    "watchdog": u64 = 5000,
+}
+
+struct Slot {
+    hwthread_id: AtomicU32,
+    /// Time (in nanoseconds since boot) the hardware thread last pet the watchdog.
+    last_pet_ns: AtomicU64,
+    /// Whether the hardware thread has been sent a [`Vector::Watchdog`] probe since it last pet the watchdog.
+    ///
+    /// [`Vector::Watchdog`]: crate::interrupts::Vector::Watchdog
+    probed: AtomicBool,
+    /// Whether the hardware thread has been reported as stuck since it last pet the watchdog.
+    reported: AtomicBool,
+}
+
+static SLOTS: [CachePadded<Slot>; MAX_HWTHREADS] = [const {
+    CachePadded::new(Slot {
+        hwthread_id: AtomicU32::new(UNCLAIMED),
+        last_pet_ns: AtomicU64::new(0),
+        probed: AtomicBool::new(false),
+        reported: AtomicBool::new(false),
+    })
+}; MAX_HWTHREADS];
+static NEXT_SLOT: AtomicUsize = AtomicUsize::new(0);
+
+/// Threshold (in nanoseconds), or 0 if the watchdog isn't running.
+static THRESHOLD_NS: AtomicU64 = AtomicU64::new(0);
+
+/// Every claimed slot.
+fn slots() -> impl Iterator<Item = &'static Slot> {
+    SLOTS[..NEXT_SLOT.load(Ordering::Acquire).min(MAX_HWTHREADS)]
+        .iter()
+        .map(|slot| &**slot)
+        .filter(|slot| slot.hwthread_id.load(Ordering::Acquire) != UNCLAIMED)
+}
+
+/// Starts watching the current hardware thread, if the watchdog is enabled with `--watchdog`.
+///
+/// # Remarks
+///
+/// Every hardware thread should call this once its local interrupt controller is enabled.
+pub fn start(is_bsp: bool) {
+    let threshold = Duration::from_millis(crate::params::get::<u64>("watchdog"));
+    if threshold.is_zero() {
+        return;
+    }
+
+    if threshold < PET_PERIOD * 2 {
+        if is_bsp {
+            warn!(
+                "Watchdog disabled: threshold must be at least {:?}.",
+                PET_PERIOD * 2
+            );
+        }
+
+        return;
+    }
+
+    let index = NEXT_SLOT.fetch_add(1, Ordering::AcqRel);
+    let Some(slot) = SLOTS.get(index) else {
+        warn!("Watchdog is not watching this hardware thread: only {MAX_HWTHREADS} are supported.");
+        return;
+    };
+
+    THRESHOLD_NS.store(
+        u64::try_from(threshold.as_nanos()).unwrap(),
+        Ordering::Relaxed,
+    );
+
+    // The slot is only visible to other hardware threads once claimed, so it must be pet first.
+    slot.last_pet_ns
+        .store(crate::time::now_ns(), Ordering::Relaxed);
+    slot.hwthread_id
+        .store(crate::cpu::get_id(), Ordering::Release);
+
+    let admitted = LocalState::with_scheduler(|scheduler| {
+        scheduler
+            .deadline_class_mut()
+            .admit("watchdog", PET_PERIOD, PET_BUDGET, tick)
+    });
+
+    match admitted {
+        Ok(()) if is_bsp => info!("Watchdog reporting hardware threads stuck for {threshold:?}."),
+        Ok(()) => {}
+        Err(error) => warn!("Watchdog is not watching this hardware thread: {error}"),
+    }
+}
+
+/// Pets the watchdog for the current hardware thread.
+pub fn pet() {
+    let hwthread_id = crate::cpu::get_id();
+    let Some(slot) = slots().find(|slot| slot.hwthread_id.load(Ordering::Relaxed) == hwthread_id)
+    else {
+        return;
+    };
+
+    let now_ns = crate::time::now_ns();
+    let last_pet_ns = slot.last_pet_ns.swap(now_ns, Ordering::AcqRel);
+    slot.probed.store(false, Ordering::Relaxed);
+
+    if slot.reported.swap(false, Ordering::AcqRel) {
+        warn!(
+            "Hardware thread #{hwthread_id} recovered after {}ms.",
+            now_ns.saturating_sub(last_pet_ns) / 1_000_000
+        );
+    }
+}
+
+/// Periodic work: pets the current hardware thread, then checks every other.
+fn tick() {
+    pet();
+
+    // The debug shell stops every other hardware thread while it's active, and a panic halts them, so neither is a
+    // stall.
+    if crate::debugger::is_active() || crate::panic::is_panicking() {
+        return;
+    }
+
+    let threshold_ns = THRESHOLD_NS.load(Ordering::Relaxed);
+    let local_id = crate::cpu::get_id();
+    let now_ns = crate::time::now_ns();
+
+    for slot in slots() {
+        let hwthread_id = slot.hwthread_id.load(Ordering::Relaxed);
+        if hwthread_id == local_id {
+            continue;
+        }
+
+        let silent_ns = now_ns.saturating_sub(slot.last_pet_ns.load(Ordering::Acquire));
+        if silent_ns < threshold_ns / 2 {
+            continue;
+        }
+
+        if silent_ns < threshold_ns {
+            if !slot.probed.swap(true, Ordering::AcqRel) {
+                probe(hwthread_id);
+            }
+        } else if !slot.reported.swap(true, Ordering::AcqRel) {
+            // Only the first hardware thread to notice the stall reports it.
+            report(hwthread_id, silent_ns);
+        }
+    }
+}
+
+/// Sends the [`Vector::Watchdog`] interrupt to `hwthread_id`, which pets the watchdog if it has interrupts enabled.
+///
+/// [`Vector::Watchdog`]: crate::interrupts::Vector::Watchdog
+fn probe(hwthread_id: u32) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        use crate::{
+            arch::x86_64::devices::local_apic::{LocalApic, interrupt_command::InterruptCommand},
+            interrupts::Vector,
+        };
+
+        let vector = core::num::NonZeroU8::new(u8::from(Vector::Watchdog)).unwrap();
+        LocalApic::send_interrupt_command(InterruptCommand::new_fixed(vector, hwthread_id));
+    }
+}
+
+fn report(hwthread_id: u32, silent_ns: u64) {
+    error!(
+        "Watchdog: hardware thread #{hwthread_id} has not responded for {}ms.",
+        silent_ns / 1_000_000
+    );
+
+    let Some(frames) = crate::debugger::capture_remote(hwthread_id) else {
+        error!("Watchdog: hardware thread #{hwthread_id} did not respond to NMI.");
+        return;
+    };
+
+    for (depth, address) in frames.into_iter().enumerate() {
+        #[cfg(feature = "panic_traces")]
+        {
+            let mut line = heapless::String::<256>::new();
+            crate::panic::tracing::write_frame(&mut line, depth, address).ok();
+            error!("{}", line.trim_end());
+        }
+
+        #[cfg(not(feature = "panic_traces"))]
+        error!("#{depth: <4}{:#X}", address.get());
+    }
+}
@@ -20,8 +20,48 @@ unsafe impl core::alloc::GlobalAlloc for FakeAllocator {
     }
 }
 
+/// Whether `rdgsbase`/`wrgsbase` are available, which the kernel enables whenever they're supported.
+fn has_fsgsbase() -> bool {
+    // Safety: `cpuid` is available on every x86_64 processor.
+    unsafe { core::arch::x86_64::__cpuid_count(0x7, 0x0).ebx & 0x1 != 0 }
+}
+
+/// Checks that a system call runs with the kernel's GS base, and returns with ours in place.
+///
+/// The kernel reads its per-CPU data through GS, so with a GS base of our own loaded, the system call only
+/// completes at all if the kernel swapped to its own on entry.
+fn test_syscall_gs() {
+    const GS_BASE: u64 = 0x7AB0_0000;
+
+    if !has_fsgsbase() {
+        libsys::syscall::klog::info("test syscall_gs ... skipped (no FSGSBASE)").unwrap();
+        return;
+    }
+
+    // Safety: Nothing in this task uses GS.
+    unsafe {
+        core::arch::asm!("wrgsbase {}", in(reg) GS_BASE, options(nostack, preserves_flags));
+    }
+
+    libsys::syscall::klog::info("test syscall_gs ... entering the kernel").unwrap();
+
+    let gs_base: u64;
+    // Safety: `FSGSBASE` is supported, and so enabled.
+    unsafe {
+        core::arch::asm!("rdgsbase {}", out(reg) gs_base, options(nostack, preserves_flags));
+    }
+
+    if gs_base == GS_BASE {
+        libsys::syscall::klog::info("test syscall_gs ... ok").unwrap();
+    } else {
+        libsys::syscall::klog::error("test syscall_gs ... FAILED: GS base was not restored").unwrap();
+    }
+}
+
 #[no_mangle]
 extern "C" fn _start() -> ! {
+    test_syscall_gs();
+
     loop {
         libsys::syscall::klog::info("klog syscall test 1").unwrap();
         libsys::syscall::klog::info("klog syscall test 2").unwrap();
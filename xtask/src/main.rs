@@ -1,4 +1,5 @@
 mod build;
+mod rpc;
 mod run;
 
 #[macro_use]
@@ -11,6 +12,7 @@ extern crate xshell;
 enum Arguments {
     Build(build::Options),
     Run(run::Options),
+    Rpc(rpc::Options),
 }
 
 fn main() -> anyhow::Result<()> {
@@ -26,6 +28,10 @@ fn main() -> anyhow::Result<()> {
         Arguments::Run(run_options) => {
             run::run(&sh, temp_dir.path(), run_options)?;
         }
+
+        Arguments::Rpc(rpc_options) => {
+            rpc::rpc(rpc_options)?;
+        }
     }
 
     Ok(())
@@ -0,0 +1,81 @@
+use anyhow::{Context, Result, bail};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    time::Duration,
+};
+
+#[derive(Parser)]
+#[group(skip)]
+pub struct Options {
+    /// Path of the Unix socket the kernel's test RPC port is exposed on (see `xtask run --rpc`).
+    #[arg(long, default_value = ".debug/rpc.sock")]
+    socket: PathBuf,
+
+    /// File of commands to run in order, one per line; blank lines and lines starting with `#` are skipped.
+    #[arg(long)]
+    script: Option<PathBuf>,
+
+    /// Seconds to wait for each response.
+    #[arg(long, default_value = "30")]
+    timeout: u64,
+
+    /// Commands to run in order, after any from the script (e.g. `"run mem::pmm::allocation"`).
+    commands: Vec<String>,
+}
+
+/// Runs each command against the kernel, stopping at the first which fails.
+pub fn rpc(options: Options) -> Result<()> {
+    let mut commands = Vec::new();
+    if let Some(script) = &options.script {
+        let script = std::fs::read_to_string(script)
+            .with_context(|| format!("failed to read script {}", script.display()))?;
+
+        commands.extend(
+            script
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from),
+        );
+    }
+    commands.extend(options.commands);
+
+    let stream = UnixStream::connect(&options.socket)
+        .with_context(|| format!("failed to connect to {}", options.socket.display()))?;
+    stream.set_read_timeout(Some(Duration::from_secs(options.timeout)))?;
+
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    for command in commands {
+        println!("> {command}");
+
+        writer.write_all(command.as_bytes())?;
+        writer.write_all(b"\n")?;
+
+        let mut header = String::new();
+        reader
+            .read_line(&mut header)
+            .with_context(|| format!("no response to `{command}`"))?;
+
+        let header = header.trim_end();
+        if let Some(len) = header.strip_prefix("OK ") {
+            let len = len.parse::<usize>().context("malformed response length")?;
+            let mut payload = vec![0; len];
+            reader.read_exact(&mut payload)?;
+
+            print!("{}", String::from_utf8_lossy(&payload));
+            if payload.last().is_some_and(|byte| *byte != b'\n') {
+                println!();
+            }
+        } else if let Some(message) = header.strip_prefix("ERR ") {
+            bail!("`{command}` failed: {message}");
+        } else {
+            bail!("malformed response to `{command}`: {header:?}");
+        }
+    }
+
+    Ok(())
+}
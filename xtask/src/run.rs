@@ -1,5 +1,5 @@
 use anyhow::Result;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Eq)]
 pub enum Cpu {
@@ -61,6 +61,49 @@ pub struct Options {
     /// Puts QEMU in GDB debug mode, awaiting signal from the debugger to begin execution.
     #[arg(short, long)]
     gdb: bool,
+
+    /// Exposes the kernel's test RPC port on a Unix socket at this path (see `xtask rpc`).
+    #[arg(long)]
+    rpc: Option<PathBuf>,
+
+    /// Boots the kernel in test mode, which runs its self-tests, and exits with their result.
+    #[arg(long)]
+    test: bool,
+}
+
+/// Status QEMU exits with when the kernel reports success via `isa-debug-exit` (`(0x10 << 1) | 1`).
+const TEST_SUCCESS_STATUS: i32 = 33;
+
+/// Copies the directory tree at `from` to `to`.
+fn copy_dir(sh: &xshell::Shell, from: &Path, to: &Path) -> Result<()> {
+    sh.create_dir(to)?;
+
+    for path in sh.read_dir(from)? {
+        let to = to.join(path.file_name().unwrap());
+
+        if path.is_dir() {
+            copy_dir(sh, &path, &to)?;
+        } else {
+            sh.copy_file(&path, &to)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates a copy of the boot partition in `temp_dir`, whose boot entry passes `--test-mode` to the kernel.
+fn test_system_dir(sh: &xshell::Shell, temp_dir: &Path) -> Result<PathBuf> {
+    let system_dir = temp_dir.join("system");
+    copy_dir(sh, Path::new("run/system"), &system_dir)?;
+
+    let config_path = system_dir.join("EFI/BOOT/limine.conf");
+    let config = sh.read_file(&config_path)?.replace(
+        "  PROTOCOL: limine\n",
+        "  PROTOCOL: limine\n  CMDLINE: --test-mode\n",
+    );
+    sh.write_file(&config_path, config)?;
+
+    Ok(system_dir)
 }
 
 pub fn run(sh: &xshell::Shell, temp_dir: impl AsRef<Path>, options: Options) -> Result<()> {
@@ -78,6 +121,12 @@ pub fn run(sh: &xshell::Shell, temp_dir: impl AsRef<Path>, options: Options) ->
         cmd!(sh, "qemu-img create -f raw run/disk0.img 256M").run()?;
     }
 
+    let system_dir = if options.test {
+        test_system_dir(sh, temp_dir.as_ref())?
+    } else {
+        PathBuf::from("run/system")
+    };
+
     let mut run_cmd = {
         match options.cpu {
             Cpu::Host | Cpu::Max | Cpu::Qemu64 => {
@@ -98,7 +147,10 @@ pub fn run(sh: &xshell::Shell, temp_dir: impl AsRef<Path>, options: Options) ->
                             ovmf_vars_fd_copy.to_string_lossy()
                         ),
                     ])
-                    .args(["-drive", "format=raw,file=fat:rw:run/system"])
+                    .args([
+                        "-drive",
+                        &format!("format=raw,file=fat:rw:{}", system_dir.to_string_lossy()),
+                    ])
             }
 
             Cpu::Rv64 => unimplemented!(),
@@ -149,8 +201,37 @@ pub fn run(sh: &xshell::Shell, temp_dir: impl AsRef<Path>, options: Options) ->
         run_cmd = run_cmd.args(["-S", "-s"]);
     }
 
+    if let Some(rpc) = &options.rpc {
+        run_cmd = run_cmd
+            .args(["-device", "virtio-serial-pci,id=virtio-serial0"])
+            .args([
+                "-chardev",
+                &format!(
+                    "socket,id=rpc,path={},server=on,wait=off",
+                    rpc.to_string_lossy()
+                ),
+            ])
+            .args([
+                "-device",
+                "virtserialport,bus=virtio-serial0.0,chardev=rpc,name=org.linuiz.rpc",
+            ]);
+    }
+
+    if options.test {
+        run_cmd = run_cmd
+            .args(["-device", "isa-debug-exit,iobase=0xF4,iosize=0x04"])
+            .args(["-display", "none"]);
+    }
+
     if options.norun {
         println!("cmd: {run_cmd}");
+    } else if options.test {
+        let status = std::process::Command::from(run_cmd).status()?;
+
+        match status.code() {
+            Some(TEST_SUCCESS_STATUS) => println!("Kernel tests passed."),
+            code => anyhow::bail!("kernel tests failed (QEMU exit status: {code:?})"),
+        }
     } else {
         run_cmd.run()?;
     }